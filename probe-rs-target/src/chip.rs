@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use super::memory::MemoryRegion;
 use crate::{
     serialize::{hex_option, hex_u_int},
-    CoreType,
+    CoreType, ImageFixup,
 };
 use serde::{Deserialize, Serialize};
 
@@ -99,6 +99,9 @@ pub struct Chip {
     // TODO: rename to default_platform
     #[serde(default)]
     pub default_binary_format: Option<String>,
+    /// Post-processing steps which are applied to images before they are flashed to this chip.
+    #[serde(default)]
+    pub image_fixups: Vec<ImageFixup>,
 }
 
 impl Chip {
@@ -121,6 +124,7 @@ impl Chip {
             rtt_scan_ranges: None,
             jtag: None,
             default_binary_format: None,
+            image_fixups: vec![],
         }
     }
 }
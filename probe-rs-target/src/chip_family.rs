@@ -114,6 +114,10 @@ pub enum InstructionSet {
     RV32,
     /// RISC-V 32-bit compressed instruction sets (RV32C) - covers all ISA variants that allow compressed 16-bit instructions.
     RV32C,
+    /// RISC-V 64-bit uncompressed instruction sets (RV64) - covers all ISA variants that use 32-bit instructions.
+    RV64,
+    /// RISC-V 64-bit compressed instruction sets (RV64C) - covers all ISA variants that allow compressed 16-bit instructions.
+    RV64C,
     /// Xtensa instruction set
     Xtensa,
 }
@@ -133,6 +137,12 @@ impl InstructionSet {
                     } else {
                         Some(InstructionSet::RV32)
                     }
+                } else if let Some(features) = other.strip_prefix("riscv64") {
+                    if features.contains('c') {
+                        Some(InstructionSet::RV64C)
+                    } else {
+                        Some(InstructionSet::RV64)
+                    }
                 } else {
                     None
                 }
@@ -151,6 +161,8 @@ impl InstructionSet {
             InstructionSet::A64 => 4,
             InstructionSet::RV32 => 4,
             InstructionSet::RV32C => 2,
+            InstructionSet::RV64 => 4,
+            InstructionSet::RV64C => 2,
             InstructionSet::Xtensa => 2,
         }
     }
@@ -169,6 +181,7 @@ impl InstructionSet {
         matches!(
             (self, instr_set),
             (InstructionSet::RV32C, InstructionSet::RV32)
+                | (InstructionSet::RV64C, InstructionSet::RV64)
         )
     }
 }
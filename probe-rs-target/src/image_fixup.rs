@@ -0,0 +1,48 @@
+use crate::serialize::hex_u_int;
+use serde::{Deserialize, Serialize};
+
+/// A post-processing step which is applied to an image before it is flashed.
+///
+/// Some chips only boot images which contain a checksum or a boot header that is not
+/// part of the output of a regular build. These fixups add the missing data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFixup {
+    /// Stores the checksum of the vector table at offset `0x1C`, as required by the boot ROM
+    /// of most NXP LPC chips.
+    ///
+    /// The checksum is the two's complement of the sum of the first seven vector table entries.
+    LpcVectorChecksum,
+    /// Adds a FlexSPI configuration block (FCB), an image vector table (IVT) and boot data
+    /// for booting from a serial NOR flash on NXP i.MX RT chips.
+    ///
+    /// The headers are only added if the image contains a vector table at offset `0x2000`
+    /// of the boot flash and no data in front of it.
+    ImxrtBootHeader(ImxrtBootHeader),
+    /// Writes the image trailer of an MCUboot slot, which makes MCUboot pick up the image.
+    McubootTrailer(McubootTrailer),
+}
+
+/// Options of the [`ImageFixup::ImxrtBootHeader`] fixup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImxrtBootHeader {
+    /// The offset of the FlexSPI configuration block from the start of the boot flash.
+    ///
+    /// This is `0x0` on most i.MX RT10xx chips and `0x400` on the i.MX RT1010 and RT11xx.
+    #[serde(default, serialize_with = "hex_u_int")]
+    pub fcb_offset: u64,
+}
+
+/// Options of the [`ImageFixup::McubootTrailer`] fixup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McubootTrailer {
+    /// The start address of the slot.
+    #[serde(serialize_with = "hex_u_int")]
+    pub slot_address: u64,
+    /// The size of the slot in bytes.
+    #[serde(serialize_with = "hex_u_int")]
+    pub slot_size: u64,
+    /// Marks the image as confirmed, so MCUboot does not revert it after the next reset.
+    #[serde(default)]
+    pub confirm: bool,
+}
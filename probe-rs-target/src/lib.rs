@@ -15,6 +15,7 @@ pub mod chip_detection;
 mod chip_family;
 mod flash_algorithm;
 mod flash_properties;
+mod image_fixup;
 mod memory;
 pub(crate) mod serialize;
 
@@ -27,6 +28,7 @@ pub use chip_family::{
 };
 pub use flash_algorithm::{RawFlashAlgorithm, TransferEncoding};
 pub use flash_properties::FlashProperties;
+pub use image_fixup::{ImageFixup, ImxrtBootHeader, McubootTrailer};
 pub use memory::{
     GenericRegion, MemoryAccess, MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion,
     RegionMergeIterator, SectorDescription, SectorInfo,
@@ -1,5 +1,6 @@
 pub mod attach;
 pub mod benchmark;
+pub mod bscan;
 pub mod cargo_embed;
 pub mod cargo_flash;
 pub mod chip;
@@ -11,12 +12,17 @@ pub mod erase;
 pub mod gdb;
 pub mod info;
 pub mod itm;
+pub mod jtag;
 pub mod list;
 pub mod mi;
+pub mod mpu;
+pub mod option_bytes;
 pub mod profile;
+pub mod protect;
 pub mod read;
 pub mod reset;
 pub mod run;
+pub mod serve;
 pub mod trace;
 pub mod verify;
 pub mod write;
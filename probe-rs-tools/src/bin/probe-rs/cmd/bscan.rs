@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use probe_rs::probe::{
+    boundary_scan::{BoundaryScan, Bsdl, PinDrive, PinLevels},
+    list::Lister,
+    WireProtocol,
+};
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The BSDL file which describes the device.
+    #[clap(long)]
+    bsdl: PathBuf,
+
+    /// The index of the device in the JTAG scan chain.
+    #[clap(long, default_value = "0")]
+    tap: usize,
+
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(clap::Subcommand)]
+enum Action {
+    /// Read the levels of the pins while the device operates normally.
+    Sample {
+        /// The pins to read. All pins are read if none are given.
+        pins: Vec<String>,
+    },
+    /// Drive pins, and read the levels of the pins while they are driven.
+    ///
+    /// The device does not operate normally while its pins are driven by boundary scan. It is
+    /// returned to normal operation afterwards.
+    Extest {
+        /// The states to drive the pins to, as `PIN=0`, `PIN=1` or `PIN=z`.
+        #[clap(value_parser = parse_drive)]
+        drives: Vec<(String, PinDrive)>,
+
+        /// The pins to read. All pins are read if none are given.
+        #[clap(long)]
+        read: Vec<String>,
+    },
+}
+
+fn parse_drive(src: &str) -> Result<(String, PinDrive), String> {
+    let (pin, state) = src
+        .split_once('=')
+        .ok_or_else(|| format!("'{src}' is not in the form PIN=STATE"))?;
+
+    let drive = match state.to_ascii_lowercase().as_str() {
+        "0" | "low" => PinDrive::Low,
+        "1" | "high" => PinDrive::High,
+        "z" => PinDrive::HighZ,
+        _ => return Err(format!("'{state}' is not a pin state, use 0, 1 or z")),
+    };
+
+    Ok((pin.to_string(), drive))
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let source = std::fs::read_to_string(&self.bsdl)
+            .with_context(|| format!("Failed to read {}", self.bsdl.display()))?;
+        let bsdl = Bsdl::parse(&source)
+            .with_context(|| format!("Failed to parse {}", self.bsdl.display()))?;
+
+        let probe_options = self.common.load()?;
+        let mut probe = probe_options.attach_probe(lister)?;
+        probe.select_protocol(WireProtocol::Jtag)?;
+        probe.attach_to_unspecified()?;
+        probe.select_jtag_tap(self.tap)?;
+
+        let jtag = probe
+            .try_as_jtag_probe()
+            .context("The probe does not support low-level JTAG access")?;
+        let mut scan = BoundaryScan::new(jtag, &bsdl)?;
+
+        match self.action {
+            Action::Sample { pins } => {
+                let levels = scan.sample()?;
+                print_levels(&levels, &pins)?;
+            }
+            Action::Extest { drives, read } => {
+                for (pin, drive) in drives {
+                    scan.set_pin(&pin, drive)?;
+                }
+
+                let levels = scan.extest();
+                scan.release()?;
+                print_levels(&levels?, &read)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn print_levels(levels: &PinLevels, pins: &[String]) -> anyhow::Result<()> {
+    if pins.is_empty() {
+        for (pin, level) in levels.iter() {
+            println!("{pin:<16} {}", level as u8);
+        }
+        return Ok(());
+    }
+
+    for pin in pins {
+        let level = levels
+            .get(pin)
+            .ok_or_else(|| anyhow!("The level of pin {pin} can not be read"))?;
+        println!("{pin:<16} {}", level as u8);
+    }
+
+    Ok(())
+}
@@ -51,7 +51,10 @@ pub struct Flashing {
     pub do_chip_erase: bool,
     pub disable_double_buffering: bool,
     pub preverify: bool,
+    pub skip_unchanged_sectors: bool,
     pub verify: bool,
+    pub preserve: Vec<String>,
+    pub skip_image_fixups: bool,
 }
 
 /// The reset config struct holding all the possible reset options.
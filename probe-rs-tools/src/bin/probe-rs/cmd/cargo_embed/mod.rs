@@ -6,6 +6,10 @@ use anyhow::{anyhow, Context, Result};
 use clap::Parser;
 use colored::Colorize;
 use parking_lot::FairMutex;
+use probe_rs::debug::{
+    debug_info::DebugInfo,
+    rtos::{detect_rtos, Rtos},
+};
 use probe_rs::flashing::FormatKind;
 use probe_rs::gdb_server::GdbInstanceConfiguration;
 use probe_rs::probe::list::Lister;
@@ -180,6 +184,7 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
                 vendor_id: u16::from_str_radix(vid, 16)?,
                 product_id: u16::from_str_radix(pid, 16)?,
                 serial_number: config.probe.serial.clone(),
+                remote: None,
             }),
             (vid, pid) => {
                 if vid.is_some() {
@@ -209,6 +214,9 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
         connect_under_reset: config.general.connect_under_reset,
         dry_run: false,
         allow_erase_all: config.flashing.enabled || config.gdb.enabled,
+        record: None,
+        replay: None,
+        simulate: false,
     };
 
     let lister = Lister::new();
@@ -244,7 +252,7 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
         Err(e) => return Err(e.into()),
     };
 
-    let format = FormatOptions::default().to_format_kind(session.target());
+    let format = FormatOptions::default().to_format_kind(session.target(), &path);
     let elf = if matches!(format, FormatKind::Elf | FormatKind::Idf) {
         Some(fs::read(&path)?)
     } else {
@@ -265,7 +273,12 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
             restore_unwritten: config.flashing.restore_unwritten_bytes,
             flash_layout_output_path: None,
             preverify: config.flashing.preverify,
+            skip_unchanged_sectors: config.flashing.skip_unchanged_sectors,
             verify: config.flashing.verify,
+            preserve: config.flashing.preserve.clone(),
+            skip_image_fixups: config.flashing.skip_image_fixups,
+            // Locking the target would prevent the RTT session which follows.
+            lock: false,
         };
         let format_options = FormatOptions::default();
         let loader = build_loader(&mut session, &path, format_options, image_instr_set)?;
@@ -305,6 +318,10 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
     if config.gdb.enabled {
         let gdb_connection_string = config.gdb.gdb_connection_string.clone();
         let session = session.clone();
+        let rtos = DebugInfo::from_file(&path)
+            .ok()
+            .and_then(|debug_info| detect_rtos(&debug_info))
+            .map(Arc::<dyn Rtos>::from);
 
         gdb_thread_handle = Some(thread::spawn(move || {
             let gdb_connection_string =
@@ -316,10 +333,13 @@ fn main_try(args: &[OsString], offset: UtcOffset) -> Result<()> {
                 gdb_connection_string,
             ));
 
-            let instances = {
+            let mut instances = {
                 let session = session.lock();
                 GdbInstanceConfiguration::from_session(&session, Some(gdb_connection_string))
             };
+            for instance in instances.iter_mut() {
+                instance.rtos = rtos.clone();
+            }
 
             if let Err(e) = probe_rs::gdb_server::run(&session, instances.iter()) {
                 logging::eprintln("During the execution of GDB an error was encountered:");
@@ -57,6 +57,18 @@ pub(crate) fn render_diagnostics(error: OperationError) {
                     vec![]
                 ),
             },
+            FileDownloadError::BundleOverlap { .. } | FileDownloadError::BundleCoreMismatch { .. } => (
+                e.to_string(),
+                vec![
+                    "Check the formats and base addresses of the images in the bundle manifest.".into()
+                ],
+            ),
+            FileDownloadError::InvalidBundle(_)
+            | FileDownloadError::BundleImage { .. }
+            | FileDownloadError::UnknownCore(_) => (
+                e.to_string(),
+                vec![]
+            ),
             _ => (
                 e.to_string(),
                 vec![
@@ -89,6 +101,10 @@ pub(crate) fn render_diagnostics(error: OperationError) {
             error.to_string(),
             vec![],
         ),
+        OperationError::FailedToCreateRecording { .. } => (
+            error.to_string(),
+            vec![],
+        ),
         OperationError::FailedToBuildExternalCargoProject { source, path } => match source {
             ArtifactError::NoArtifacts => (
                 source.to_string(),
@@ -208,6 +224,13 @@ pub(crate) fn render_diagnostics(error: OperationError) {
             error.to_string(),
             vec![],
         ),
+        OperationError::InvalidPreservedRange(_) => (
+            error.to_string(),
+            vec![
+                "Ranges are given as `START..END` or `START+SIZE`, e.g. `0x1000..0x2000` or `0x1000+0x1000`.".into(),
+                "To preserve a whole memory region, use its name from the target description.".into(),
+            ],
+        ),
     };
 
     use std::io::Write;
@@ -34,7 +34,8 @@ struct CliOptions {
     /// Default is `warn`. Possible choices are [error, warn, info, debug, trace].
     #[arg(value_name = "level", long)]
     pub log: Option<LevelFilter>,
-    /// The path to the file to be flashed. Setting this will ignore the cargo options.
+    /// The path to the file to be flashed, or to a manifest listing multiple images.
+    /// Setting this will ignore the cargo options.
     #[arg(value_name = "path", long)]
     pub path: Option<PathBuf>,
     /// The work directory from which cargo-flash should operate from.
@@ -130,7 +131,8 @@ fn main_try(args: &[OsString]) -> Result<(), OperationError> {
 
     // Flash the binary
     let loader =
-        flash::build_loader(&mut session, &path, opt.format_options, image_instr_set).unwrap();
+        flash::build_bundle_or_loader(&mut session, &path, opt.format_options, image_instr_set)
+            .map_err(OperationError::FailedToLoadElfData)?;
     flash::run_flash_download(
         &mut session,
         &path,
@@ -10,6 +10,7 @@ use super::{
 use crate::cmd::dap_server::{
     debug_adapter::protocol::{ProtocolAdapter, ProtocolHelper},
     server::{
+        breakpoint_conditions::BreakpointConditions,
         configuration::ConsoleLog,
         core_data::CoreHandle,
         session_data::{BreakpointType, SourceLocationScope},
@@ -568,12 +569,11 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         //TODO: Check for, and prevent SVD Peripheral/Register/Field values from being updated, until such time as we can do it safely.
 
-        match target_core
+        let register_frame = target_core
             .core_data
-            .stack_frames
-            .iter_mut()
-            .find(|stack_frame| stack_frame.id == parent_key)
-        {
+            .all_stack_frames_mut()
+            .find(|stack_frame| stack_frame.id == parent_key);
+        match register_frame {
             Some(stack_frame) => {
                 // The variable is a register value in this StackFrame
                 if let Some(_register_value) = stack_frame
@@ -596,7 +596,7 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 // The parent_key refers to a local or static variable in one of the in-scope StackFrames.
                 let mut cache_variable: Option<probe_rs::debug::Variable> = None;
                 let mut variable_cache: Option<&mut probe_rs::debug::VariableCache> = None;
-                for search_frame in target_core.core_data.stack_frames.iter_mut() {
+                for search_frame in target_core.core_data.all_stack_frames_mut() {
                     if let Some(search_cache) = &mut search_frame.local_variables {
                         if let Some(search_variable) =
                             search_cache.get_variable_by_name_and_parent(&variable_name, parent_key)
@@ -699,9 +699,11 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 let saved_breakpoints = std::mem::take(&mut target_core.core_data.breakpoints);
 
                 for breakpoint in saved_breakpoints {
-                    match target_core
-                        .set_breakpoint(breakpoint.address, breakpoint.breakpoint_type.clone())
-                    {
+                    match target_core.set_breakpoint(
+                        breakpoint.address,
+                        breakpoint.breakpoint_type.clone(),
+                        breakpoint.conditions.clone(),
+                    ) {
                         Ok(_) => {}
                         Err(error) => {
                             //This will cause the debugger to show the user an error, but not stop the debugger.
@@ -880,6 +882,11 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                         requested_breakpoint_line,
                         requested_breakpoint_column,
                         &args.source,
+                        BreakpointConditions::new(
+                            bp.condition.clone(),
+                            bp.hit_condition.clone(),
+                            bp.log_message.clone(),
+                        ),
                     ) {
                         Ok(VerifiedBreakpoint {
                             address,
@@ -972,16 +979,14 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         target_core: &mut CoreHandle,
         request: &Request,
     ) -> Result<()> {
-        // TODO: Implement actual thread resolution. For now, we just use the core id as the thread id.
         let current_core_status = target_core.core.status()?;
-        let mut threads: Vec<Thread> = vec![];
         if self.configuration_is_done() {
             // We can handle this request normally.
-            let single_thread = Thread {
-                id: target_core.core.id() as i64,
-                name: target_core.core_data.target_name.clone(),
-            };
-            threads.push(single_thread);
+            let threads = target_core
+                .threads()
+                .into_iter()
+                .map(|(id, name)| Thread { id, name })
+                .collect();
             return self.send_response(request, Ok(Some(ThreadsResponseBody { threads })));
         }
         self.send_response::<()>(
@@ -1016,8 +1021,13 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         let arguments: StackTraceArguments = get_arguments(self, request)?;
 
+        let stack_frames = match target_core.thread_stack_frames(arguments.thread_id) {
+            Ok(stack_frames) => stack_frames,
+            Err(error) => return self.send_response::<()>(request, Err(&error)),
+        };
+
         // If the core is halted, and we have no available strackframes, we can get out of here early.
-        if target_core.core_data.stack_frames.is_empty() {
+        if stack_frames.is_empty() {
             let body = StackTraceResponseBody {
                 stack_frames: Vec::new(),
                 total_frames: Some(0),
@@ -1032,11 +1042,11 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         // Update the `levels` to the number of available frames if it is 0.
         if levels == 0 {
-            levels = target_core.core_data.stack_frames.len() as i64;
+            levels = stack_frames.len() as i64;
         }
 
         // Determine the correct 'slice' of available [StackFrame]s to serve up ...
-        let total_frames = target_core.core_data.stack_frames.len() as i64;
+        let total_frames = stack_frames.len() as i64;
 
         // We need to copy some parts of StackFrame so that we can re-use it later without references to target_core.
         struct PartialStackFrameData {
@@ -1049,34 +1059,20 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         let frame_set = if levels == 1 && start_frame == 0 {
             // Just the first frame - use the LHS of the split at `levels`
-            target_core
-                .core_data
-                .stack_frames
-                .split_at(levels as usize)
-                .0
+            stack_frames.split_at(levels as usize).0
         } else if total_frames <= 20 && start_frame >= 0 && start_frame <= total_frames {
             // When we have less than 20 frames - use the RHS of of the split at `start_frame`
-            target_core
-                .core_data
-                .stack_frames
-                .split_at(start_frame as usize)
-                .1
+            stack_frames.split_at(start_frame as usize).1
         } else if total_frames > 20 && start_frame + levels <= total_frames {
             // When we have more than 20 frames - we can safely split twice
-            target_core
-                .core_data
-                .stack_frames
+            stack_frames
                 .split_at(start_frame as usize)
                 .1
                 .split_at(levels as usize)
                 .0
         } else if total_frames > 20 && start_frame + levels > total_frames {
             // The MS DAP spec may also ask for more frames than what we reported.
-            target_core
-                .core_data
-                .stack_frames
-                .split_at(start_frame as usize)
-                .1
+            stack_frames.split_at(start_frame as usize).1
         } else {
             return self.send_response::<()>(
                 request,
@@ -1426,7 +1422,18 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
         }
 
         if parent_variable.is_none() {
-            for stack_frame in target_core.core_data.stack_frames.iter_mut() {
+            // Borrow the fields separately, because the static variables and debug info are used while the frames are borrowed.
+            let rtos_stack_frames = target_core
+                .core_data
+                .rtos_stack_frames
+                .iter_mut()
+                .flat_map(|(_, stack_frames)| stack_frames.iter_mut());
+            for stack_frame in target_core
+                .core_data
+                .stack_frames
+                .iter_mut()
+                .chain(rtos_stack_frames)
+            {
                 if let Some(search_cache) = &mut stack_frame.local_variables {
                     if let Some(search_variable) = search_cache.get_variable_by_key(variable_ref) {
                         parent_variable = Some(search_variable);
@@ -30,7 +30,14 @@ impl DapStatus for CoreStatus {
                     "exception",
                     "Core halted due to an exception, e.g. interupt handler".to_string(),
                 ),
-                HaltReason::Watchpoint => (
+                HaltReason::Watchpoint(Some(watchpoint)) => (
+                    "data breakpoint",
+                    format!(
+                        "Core halted due to a {:?} watchpoint on {} bytes @{:#010x}",
+                        watchpoint.kind, watchpoint.length, watchpoint.address
+                    ),
+                ),
+                HaltReason::Watchpoint(None) => (
                     "data breakpoint",
                     "Core halted due to a watchpoint or data breakpoint".to_string(),
                 ),
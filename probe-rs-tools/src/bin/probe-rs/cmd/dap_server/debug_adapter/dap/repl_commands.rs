@@ -11,7 +11,7 @@ use crate::cmd::dap_server::{server::core_data::CoreHandle, DebuggerError};
 use itertools::Itertools;
 use probe_rs::{
     debug::{ObjectRef, VariableName},
-    CoreDump, CoreStatus, HaltReason,
+    CoreDump, CoreStatus, HaltReason, SecurityState,
 };
 use std::{fmt::Display, ops::Range, path::Path, str::FromStr, time::Duration};
 
@@ -133,9 +133,12 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
     ReplCommand {
         command: "break",
         // Stricly speaking, gdb refers to this as an expression, but we only support variables.
-        help_text: "Sets a breakpoint specified location, or next instruction if unspecified.",
+        help_text: "Sets a breakpoint specified location, or next instruction if unspecified. Use `if <condition>` to only halt when the condition is true.",
         sub_commands: None,
-        args: Some(&[ReplCommandArgs::Optional("*address")]),
+        args: Some(&[
+            ReplCommandArgs::Optional("*address"),
+            ReplCommandArgs::Optional("if <condition>"),
+        ]),
         handler: |target_core, command_arguments, _| {
             if command_arguments.is_empty() {
                 let core_info = target_core.core.halt(Duration::from_millis(500))?;
@@ -153,13 +156,17 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
                     body: None,
                 });
             } else {
-                let mut input_arguments = command_arguments.split_whitespace();
+                let (location, condition) = match command_arguments.split_once(" if ") {
+                    Some((location, condition)) => (location, Some(condition.trim().to_string())),
+                    None => (command_arguments, None),
+                };
+                let mut input_arguments = location.split_whitespace();
                 if let Some(input_argument) = input_arguments.next() {
                     if let Some(address_str) = &input_argument.strip_prefix('*') {
                         let result = set_instruction_breakpoint(
                             InstructionBreakpoint {
                                 instruction_reference: address_str.to_string(),
-                                condition: None,
+                                condition,
                                 hit_condition: None,
                                 offset: None,
                             },
@@ -278,12 +285,64 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
                 // TODO: This is easy to implement ... just requires deciding how to format the output.
                 handler: |_, _, _| Err(DebuggerError::Unimplemented),
             },
+            ReplCommand {
+                command: "security",
+                help_text: "Show the security state the core is executing in.",
+                sub_commands: None,
+                args: None,
+                handler: |target_core, _, _| {
+                    let message = match target_core.core.security_state()? {
+                        Some(security_state) => {
+                            format!("The core is executing in the {security_state} state.")
+                        }
+                        None => "The core does not implement a security extension.".to_string(),
+                    };
+                    Ok(Response {
+                        command: "security".to_string(),
+                        success: true,
+                        message: Some(message),
+                        type_: "response".to_string(),
+                        request_seq: 0,
+                        seq: 0,
+                        body: None,
+                    })
+                },
+            },
         ]),
         args: None,
         handler: |_, _, _| {
             Err(DebuggerError::UserMessage("Please provide one of the required subcommands. See the `help` command for more information.".to_string()))
         },
     },
+    ReplCommand {
+        command: "memory-security",
+        help_text: "Select whether memory is accessed with Secure or Non-secure transfers.",
+        sub_commands: None,
+        args: Some(&[ReplCommandArgs::Required("secure|non-secure")]),
+        handler: |target_core, command_arguments, _| {
+            let security_state = match command_arguments.trim() {
+                "secure" => SecurityState::Secure,
+                "non-secure" => SecurityState::NonSecure,
+                other => {
+                    return Err(DebuggerError::UserMessage(format!(
+                        "Invalid security state {other:?}. Use `secure` or `non-secure`."
+                    )))
+                }
+            };
+            target_core.core.set_memory_security_state(security_state)?;
+            Ok(Response {
+                command: "memory-security".to_string(),
+                success: true,
+                message: Some(format!(
+                    "Memory is now accessed with {security_state} transfers."
+                )),
+                type_: "response".to_string(),
+                request_seq: 0,
+                seq: 0,
+                body: None,
+            })
+        },
+    },
     ReplCommand {
         command: "p",
         // Stricly speaking, gdb refers to this as an expression, but we only support variables.
@@ -388,11 +447,7 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
 
                 input_address = if let Some(frame_pc) = frame_id
                     .and_then(|frame_id| {
-                        target_core
-                            .core_data
-                            .stack_frames
-                            .iter()
-                            .find(|stack_frame| stack_frame.id == frame_id)
+                        target_core.get_stackframe(frame_id)
                     })
                     .map(|stack_frame| stack_frame.pc)
                 {
@@ -409,7 +464,7 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
     },
     ReplCommand {
         command: "dump",
-        help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions.",
+        help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions. Paths ending in `.elf` are written as ELF core files, which can be opened with GDB.",
         sub_commands: None,
         args: Some(&[
             ReplCommandArgs::Optional("memory start address"),
@@ -467,7 +522,12 @@ pub(crate) static REPL_COMMANDS: &[ReplCommand<ReplHandler>] = &[
                 range_string = range_string.trim_end_matches(", ").to_string();
                 range_string = format!("(Includes memory ranges: {range_string})");
             }
-            CoreDump::dump_core(&mut target_core.core, ranges)?.store(location)?;
+            let core_dump = CoreDump::dump_core(&mut target_core.core, ranges)?;
+            if location.extension().is_some_and(|extension| extension == "elf") {
+                core_dump.store_elf(location)?;
+            } else {
+                core_dump.store(location)?;
+            }
 
             Ok(Response {
                 command: "dump".to_string(),
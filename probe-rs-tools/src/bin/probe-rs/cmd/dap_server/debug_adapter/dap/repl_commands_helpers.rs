@@ -28,8 +28,7 @@ pub(crate) fn get_local_variable(
     let stack_frame = match frame_ref {
         Some(frame_id) => target_core
             .core_data
-            .stack_frames
-            .iter_mut()
+            .all_stack_frames_mut()
             .find(|stack_frame| stack_frame.id == frame_id),
         None => {
             // Use the current frame_id
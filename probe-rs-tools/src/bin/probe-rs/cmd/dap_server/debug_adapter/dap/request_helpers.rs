@@ -1,7 +1,10 @@
 use crate::cmd::dap_server::{
     debug_adapter::dap::dap_types::{DisassembledInstruction, Source},
     peripherals::svd_cache::{SvdVariableCache, Variable},
-    server::{core_data::CoreHandle, session_data::BreakpointType},
+    server::{
+        breakpoint_conditions::BreakpointConditions, core_data::CoreHandle,
+        session_data::BreakpointType,
+    },
     DebuggerError,
 };
 use anyhow::{anyhow, Result};
@@ -98,7 +101,7 @@ pub(crate) fn disassemble_target_memory(
     let cs = get_capstone(target_core)?;
     let target_instruction_set = target_core.core.instruction_set()?;
     let instruction_offset_as_bytes = match target_instruction_set {
-        InstructionSet::Thumb2 | InstructionSet::RV32C => {
+        InstructionSet::Thumb2 | InstructionSet::RV32C | InstructionSet::RV64C => {
             // Since we cannot guarantee the size of individual instructions, let's assume we will read the 120% of the requested number of 16-bit instructions.
             (instruction_offset
                 * target_core
@@ -108,7 +111,7 @@ pub(crate) fn disassemble_target_memory(
                 / 4
                 * 5
         }
-        InstructionSet::A32 | InstructionSet::A64 | InstructionSet::RV32 => {
+        InstructionSet::A32 | InstructionSet::A64 | InstructionSet::RV32 | InstructionSet::RV64 => {
             instruction_offset
                 * target_core
                     .core
@@ -333,6 +336,19 @@ pub(crate) fn get_capstone(target_core: &mut CoreHandle) -> Result<Capstone, Deb
                 capstone::arch::riscv::ArchExtraMode::RiscVC,
             ))
             .build(),
+        InstructionSet::RV64 => Capstone::new()
+            .riscv()
+            .mode(riscvArchMode::RiscV64)
+            .endian(Endian::Little)
+            .build(),
+        InstructionSet::RV64C => Capstone::new()
+            .riscv()
+            .mode(riscvArchMode::RiscV64)
+            .endian(Endian::Little)
+            .extra_mode(std::iter::once(
+                capstone::arch::riscv::ArchExtraMode::RiscVC,
+            ))
+            .build(),
         InstructionSet::Xtensa => return Err(DebuggerError::Unimplemented),
     }
     .map_err(|err| anyhow!("Error creating capstone: {:?}", err))?;
@@ -506,7 +522,15 @@ pub(crate) fn set_instruction_breakpoint(
         .as_str()
         .try_into()
     {
-        match target_core.set_breakpoint(memory_reference, BreakpointType::InstructionBreakpoint) {
+        match target_core.set_breakpoint(
+            memory_reference,
+            BreakpointType::InstructionBreakpoint,
+            BreakpointConditions::new(
+                requested_breakpoint.condition.clone(),
+                requested_breakpoint.hit_condition.clone(),
+                None,
+            ),
+        ) {
             Ok(_) => {
                 breakpoint_response.verified = true;
                 breakpoint_response.instruction_reference =
@@ -0,0 +1,333 @@
+use crate::cmd::dap_server::DebuggerError;
+
+/// The optional `condition`, `hitCondition` and `logMessage` that the MS DAP client can attach to a breakpoint.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct BreakpointConditions {
+    /// An expression that has to evaluate to `true` before the debugger will halt at the breakpoint.
+    /// Supported syntax is a list of comparisons (`==`, `!=`, `<`, `<=`, `>`, `>=`) between variables and literals,
+    /// optionally combined with `&&` and `||` (without parentheses, and with `&&` taking precedence).
+    /// A single operand is considered `true` if it is a non-zero number, or the boolean `true`.
+    pub(crate) condition: Option<String>,
+    /// Controls how many hits of the breakpoint are ignored. Only hits where the `condition` (if any) is `true` are counted.
+    /// The supported formats are:
+    /// - `N` or `>=N`: Halt on the Nth hit, and every hit thereafter.
+    /// - `==N`, `>N`, `<N`, `<=N`: Halt whenever the hit count satisfies the comparison.
+    /// - `%N`: Halt on every Nth hit.
+    pub(crate) hit_condition: Option<String>,
+    /// If specified, the breakpoint is a 'logpoint'. Instead of halting, the message is written to the debug console,
+    /// after replacing every `{expression}` with the value of the variable it refers to. Use `{{` and `}}` for literal braces.
+    pub(crate) log_message: Option<String>,
+}
+
+impl BreakpointConditions {
+    /// Create a new set of conditions, treating empty strings (which some clients send instead of `null`) as not specified.
+    pub(crate) fn new(
+        condition: Option<String>,
+        hit_condition: Option<String>,
+        log_message: Option<String>,
+    ) -> Self {
+        let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+        Self {
+            condition: non_empty(condition),
+            hit_condition: non_empty(hit_condition),
+            log_message: non_empty(log_message),
+        }
+    }
+
+    /// `true` if processing these conditions requires access to the variables of the halted stack frame.
+    pub(crate) fn requires_variables(&self) -> bool {
+        self.condition.is_some() || self.log_message.is_some()
+    }
+}
+
+/// Check if `hit_count` (which includes the current hit) satisfies the `hit_condition`.
+pub(crate) fn hit_condition_is_met(
+    hit_condition: &str,
+    hit_count: u64,
+) -> Result<bool, DebuggerError> {
+    let hit_condition = hit_condition.trim();
+    let (operator, operand) = ["==", ">=", "<=", ">", "<", "%"]
+        .iter()
+        .find_map(|operator| {
+            hit_condition
+                .strip_prefix(operator)
+                .map(|operand| (*operator, operand))
+        })
+        .unwrap_or((">=", hit_condition));
+
+    let Ok(target) = operand.trim().parse::<u64>() else {
+        return Err(DebuggerError::UserMessage(format!(
+            "Invalid hit condition {hit_condition:?}. Expected a number, optionally prefixed with one of `==`, `>=`, `<=`, `>`, `<` or `%`."
+        )));
+    };
+
+    Ok(match operator {
+        "==" => hit_count == target,
+        ">=" => hit_count >= target,
+        "<=" => hit_count <= target,
+        ">" => hit_count > target,
+        "<" => hit_count < target,
+        "%" => target != 0 && hit_count % target == 0,
+        _ => unreachable!("All operators are handled above"),
+    })
+}
+
+/// Evaluate a breakpoint `condition`, using `resolve_variable` to look up the value of each variable it refers to.
+pub(crate) fn evaluate_condition(
+    condition: &str,
+    mut resolve_variable: impl FnMut(&str) -> Result<String, DebuggerError>,
+) -> Result<bool, DebuggerError> {
+    for alternative in condition.split("||") {
+        let mut all_true = true;
+        for clause in alternative.split("&&") {
+            if !evaluate_clause(clause, &mut resolve_variable)? {
+                all_true = false;
+                break;
+            }
+        }
+        if all_true {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Format a logpoint message, by replacing each `{expression}` with the value returned by `resolve_variable`.
+/// Failure to resolve an expression will not prevent the message from being logged, but will be reported inline.
+pub(crate) fn format_log_message(
+    template: &str,
+    mut resolve_variable: impl FnMut(&str) -> Result<String, DebuggerError>,
+) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut characters = template.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                message.push('{');
+            }
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                message.push('}');
+            }
+            '{' => {
+                let expression: String = characters.by_ref().take_while(|c| *c != '}').collect();
+                match resolve_variable(expression.trim()) {
+                    Ok(value) => message.push_str(&value),
+                    Err(error) => message.push_str(&format!("<{}: {error}>", expression.trim())),
+                }
+            }
+            other => message.push(other),
+        }
+    }
+    message
+}
+
+/// Evaluate a single comparison, or a single operand, from a breakpoint condition.
+fn evaluate_clause(
+    clause: &str,
+    resolve_variable: &mut impl FnMut(&str) -> Result<String, DebuggerError>,
+) -> Result<bool, DebuggerError> {
+    let clause = clause.trim();
+
+    // Find the left-most operator, preferring the two-character variants where they overlap.
+    let operator = ["==", "!=", "<=", ">=", "<", ">"]
+        .iter()
+        .filter_map(|operator| clause.find(operator).map(|index| (index, *operator)))
+        .min_by_key(|(index, operator)| (*index, std::cmp::Reverse(operator.len())));
+
+    let Some((index, operator)) = operator else {
+        // A single operand, so we consider its 'truthiness'.
+        let (negate, operand) = match clause.strip_prefix('!') {
+            Some(operand) => (true, operand),
+            None => (false, clause),
+        };
+        let is_true = match operand_value(operand, resolve_variable)? {
+            ConditionValue::Boolean(value) => value,
+            ConditionValue::Integer(value) => value != 0,
+            ConditionValue::Float(value) => value != 0.0,
+            ConditionValue::Text(value) => {
+                return Err(DebuggerError::UserMessage(format!(
+                    "The value {value:?} of {operand:?} cannot be used as a condition."
+                )))
+            }
+        };
+        return Ok(is_true != negate);
+    };
+
+    let left = operand_value(&clause[..index], resolve_variable)?;
+    let right = operand_value(&clause[index + operator.len()..], resolve_variable)?;
+
+    let ordering = match (&left, &right) {
+        (ConditionValue::Integer(left), ConditionValue::Integer(right)) => Some(left.cmp(right)),
+        (ConditionValue::Integer(_) | ConditionValue::Float(_), _)
+            if matches!(right, ConditionValue::Integer(_) | ConditionValue::Float(_)) =>
+        {
+            left.as_float().partial_cmp(&right.as_float())
+        }
+        (ConditionValue::Boolean(left), ConditionValue::Boolean(right)) => Some(left.cmp(right)),
+        (ConditionValue::Text(left), ConditionValue::Text(right)) => Some(left.cmp(right)),
+        _ => None,
+    };
+
+    let Some(ordering) = ordering else {
+        return Err(DebuggerError::UserMessage(format!(
+            "Cannot compare {left:?} with {right:?} in condition {clause:?}."
+        )));
+    };
+
+    Ok(match operator {
+        "==" => ordering.is_eq(),
+        "!=" => ordering.is_ne(),
+        "<=" => ordering.is_le(),
+        ">=" => ordering.is_ge(),
+        "<" => ordering.is_lt(),
+        ">" => ordering.is_gt(),
+        _ => unreachable!("All operators are handled above"),
+    })
+}
+
+/// The value of a single operand in a breakpoint condition.
+#[derive(Debug, PartialEq)]
+enum ConditionValue {
+    Boolean(bool),
+    Integer(i128),
+    Float(f64),
+    Text(String),
+}
+
+impl ConditionValue {
+    /// Interpret a literal, or the formatted value of a variable.
+    fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if let Ok(boolean) = value.parse::<bool>() {
+            return ConditionValue::Boolean(boolean);
+        }
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        let integer = if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            i128::from_str_radix(&hex.replace('_', ""), 16).ok()
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i128::from_str_radix(&binary.replace('_', ""), 2).ok()
+        } else {
+            digits.replace('_', "").parse::<i128>().ok()
+        };
+        if let Some(integer) = integer {
+            return ConditionValue::Integer(if negative { -integer } else { integer });
+        }
+        if let Ok(float) = value.parse::<f64>() {
+            return ConditionValue::Float(float);
+        }
+        let unquoted = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| {
+                value
+                    .strip_prefix('\'')
+                    .and_then(|value| value.strip_suffix('\''))
+            })
+            .unwrap_or(value);
+        ConditionValue::Text(unquoted.to_string())
+    }
+
+    fn as_float(&self) -> f64 {
+        match self {
+            ConditionValue::Integer(value) => *value as f64,
+            ConditionValue::Float(value) => *value,
+            _ => f64::NAN,
+        }
+    }
+}
+
+/// Operands that look like identifiers (or paths to struct members, e.g. `foo.bar`) are resolved as variables,
+/// everything else is treated as a literal.
+fn operand_value(
+    operand: &str,
+    resolve_variable: &mut impl FnMut(&str) -> Result<String, DebuggerError>,
+) -> Result<ConditionValue, DebuggerError> {
+    let operand = operand.trim();
+    if operand.is_empty() {
+        return Err(DebuggerError::UserMessage(
+            "Missing operand in breakpoint condition.".to_string(),
+        ));
+    }
+    let is_variable = operand
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && operand != "true"
+        && operand != "false";
+    if is_variable {
+        resolve_variable(operand).map(|value| ConditionValue::parse(&value))
+    } else {
+        Ok(ConditionValue::parse(operand))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn variables(name: &str) -> Result<String, DebuggerError> {
+        match name {
+            "counter" => Ok("42".to_string()),
+            "flag" => Ok("true".to_string()),
+            "status.code" => Ok("0x10".to_string()),
+            "ratio" => Ok("0.5".to_string()),
+            "name" => Ok("\"probe\"".to_string()),
+            other => Err(DebuggerError::UserMessage(format!(
+                "No variable named {other:?}"
+            ))),
+        }
+    }
+
+    #[test]
+    fn conditions_compare_variables_and_literals() {
+        assert!(evaluate_condition("counter == 42", variables).unwrap());
+        assert!(!evaluate_condition("counter != 42", variables).unwrap());
+        assert!(evaluate_condition("counter >= 0x2a", variables).unwrap());
+        assert!(evaluate_condition("status.code == 16", variables).unwrap());
+        assert!(evaluate_condition("ratio < 1", variables).unwrap());
+        assert!(evaluate_condition("name == \"probe\"", variables).unwrap());
+        assert!(evaluate_condition("flag", variables).unwrap());
+        assert!(!evaluate_condition("!flag", variables).unwrap());
+        assert!(evaluate_condition("counter < 10 || flag && ratio > 0.1", variables).unwrap());
+        assert!(!evaluate_condition("counter > 10 && ratio > 1", variables).unwrap());
+    }
+
+    #[test]
+    fn conditions_report_errors() {
+        assert!(evaluate_condition("missing == 1", variables).is_err());
+        assert!(evaluate_condition("name", variables).is_err());
+        assert!(evaluate_condition("flag > 1", variables).is_err());
+        assert!(evaluate_condition("counter ==", variables).is_err());
+    }
+
+    #[test]
+    fn hit_conditions() {
+        assert!(!hit_condition_is_met("3", 2).unwrap());
+        assert!(hit_condition_is_met("3", 3).unwrap());
+        assert!(hit_condition_is_met(">=3", 4).unwrap());
+        assert!(!hit_condition_is_met("==3", 4).unwrap());
+        assert!(hit_condition_is_met("%2", 4).unwrap());
+        assert!(!hit_condition_is_met("%2", 5).unwrap());
+        assert!(hit_condition_is_met("< 2", 1).unwrap());
+        assert!(hit_condition_is_met("often", 1).is_err());
+    }
+
+    #[test]
+    fn log_messages() {
+        assert_eq!(
+            format_log_message("counter={counter}, {{literal}} {missing}", variables),
+            "counter=42, {literal} <missing: No variable named \"missing\">"
+        );
+    }
+}
@@ -165,6 +165,9 @@ impl SessionConfig {
             connect_under_reset: self.connect_under_reset,
             dry_run: false,
             allow_erase_all: self.allow_erase_all,
+            record: None,
+            replay: None,
+            simulate: false,
         }
     }
 }
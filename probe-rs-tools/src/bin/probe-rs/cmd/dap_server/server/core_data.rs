@@ -1,6 +1,9 @@
 use std::{ops::Range, path::Path};
 
-use super::session_data::{self, ActiveBreakpoint, BreakpointType, SourceLocationScope};
+use super::{
+    breakpoint_conditions::{self, BreakpointConditions},
+    session_data::{self, ActiveBreakpoint, BreakpointType, SourceLocationScope},
+};
 use crate::cmd::dap_server::{
     debug_adapter::{
         dap::{
@@ -20,8 +23,12 @@ use anyhow::{anyhow, Result};
 use probe_rs::debug::VerifiedBreakpoint;
 use probe_rs::{
     debug::{
-        debug_info::DebugInfo, stack_frame::StackFrameInfo, ColumnType, ObjectRef, VariableCache,
+        debug_info::DebugInfo,
+        rtos::{Rtos, RtosThread},
+        stack_frame::{StackFrame, StackFrameInfo},
+        ColumnType, DebugRegisters, ObjectRef, VariableCache, VariableName,
     },
+    exception_handler_for_core,
     rtt::ScanRegion,
     Core, CoreStatus, HaltReason,
 };
@@ -45,6 +52,13 @@ pub struct CoreData {
     pub static_variables: Option<VariableCache>,
     pub core_peripherals: Option<SvdCache>,
     pub stack_frames: Vec<probe_rs::debug::stack_frame::StackFrame>,
+    /// The RTOS used by the program, if one was detected in the debug information.
+    pub rtos: Option<Box<dyn Rtos>>,
+    /// The threads of the RTOS, as read when the core last halted.
+    pub rtos_threads: Vec<RtosThread>,
+    /// The stack frames of the RTOS threads which are not running, keyed by the thread id.
+    /// These are unwound from the saved registers of the thread, when the client requests them.
+    pub rtos_stack_frames: Vec<(u64, Vec<StackFrame>)>,
     pub breakpoints: Vec<session_data::ActiveBreakpoint>,
     pub rtt_connection: Option<debug_rtt::RttConnection>,
     pub rtt_client: Option<RttClient>,
@@ -53,6 +67,24 @@ pub struct CoreData {
 /// [CoreHandle] provides handles to various data structures required to debug a single instance of a core. The actual state is stored in [session_data::SessionData].
 ///
 /// Usage: To get access to this structure please use the [session_data::SessionData::attach_core] method. Please keep access/locks to this to a minimum duration.
+impl CoreData {
+    /// Iterate over the stack frames of the running thread, followed by those of the RTOS threads which were unwound so far.
+    pub(crate) fn all_stack_frames_mut(&mut self) -> impl Iterator<Item = &mut StackFrame> {
+        self.stack_frames.iter_mut().chain(
+            self.rtos_stack_frames
+                .iter_mut()
+                .flat_map(|(_, stack_frames)| stack_frames.iter_mut()),
+        )
+    }
+
+    /// The RTOS thread with the given DAP thread id, if it is not running.
+    fn suspended_rtos_thread(&self, thread_id: i64) -> Option<&RtosThread> {
+        self.rtos_threads
+            .iter()
+            .find(|thread| !thread.is_running() && thread.id as i64 == thread_id)
+    }
+}
+
 pub struct CoreHandle<'p> {
     pub(crate) core: Core<'p>,
     pub(crate) core_data: &'p mut CoreData,
@@ -82,6 +114,17 @@ impl<'p> CoreHandle<'p> {
             match self.core.status() {
                 Ok(status) => {
                     let has_changed_state = status != self.core_data.last_known_status;
+                    if has_changed_state
+                        && matches!(status, CoreStatus::Halted(HaltReason::Breakpoint(_)))
+                        && self.core_data.last_known_status != CoreStatus::Halted(HaltReason::Step)
+                        && !self.process_breakpoint_hit(debug_adapter)?
+                    {
+                        // The breakpoint conditions tell us not to halt here, so we silently resume the core,
+                        // without notifying the client that it has stopped.
+                        self.core.run()?;
+                        self.core_data.last_known_status = CoreStatus::Running;
+                        return Ok(CoreStatus::Running);
+                    }
                     if has_changed_state {
                         match status {
                             CoreStatus::Running | CoreStatus::Sleeping => {
@@ -104,14 +147,17 @@ impl<'p> CoreHandle<'p> {
                                 {
                                     let program_counter =
                                         self.core.read_core_reg(self.core.program_counter()).ok();
+                                    let mut description =
+                                        status.short_long_status(program_counter).1;
+                                    if let Ok(Some(security_state)) = self.core.security_state() {
+                                        description.push_str(&format!(" ({security_state} state)"));
+                                    }
                                     let event_body = Some(StoppedEventBody {
                                         reason: status
                                             .short_long_status(program_counter)
                                             .0
                                             .to_owned(),
-                                        description: Some(
-                                            status.short_long_status(program_counter).1,
-                                        ),
+                                        description: Some(description),
                                         thread_id: Some(self.core.id() as i64),
                                         preserve_focus_hint: Some(false),
                                         text: None,
@@ -163,6 +209,230 @@ impl<'p> CoreHandle<'p> {
         }
     }
 
+    /// Process the [`BreakpointConditions`] of the breakpoint at the current program counter (if any),
+    /// and return `true` if the core should remain halted, or `false` if it should be resumed.
+    /// - Breakpoints with a `condition` that evaluates to `false` are not counted as hits.
+    /// - Breakpoints with a `hit_condition` will only halt once the hit count satisfies it.
+    /// - Logpoints will write their `log_message` to the debug console, and never halt.
+    ///
+    /// Failure to evaluate a condition is reported to the debug console, and will cause the core to remain halted.
+    fn process_breakpoint_hit<P: ProtocolAdapter>(
+        &mut self,
+        debug_adapter: &mut DebugAdapter<P>,
+    ) -> Result<bool, DebuggerError> {
+        let program_counter: u64 = self.core.read_core_reg(self.core.program_counter())?;
+        let Some(breakpoint_index) = self
+            .core_data
+            .breakpoints
+            .iter()
+            .position(|breakpoint| breakpoint.address == program_counter)
+        else {
+            return Ok(true);
+        };
+
+        let conditions = self.core_data.breakpoints[breakpoint_index]
+            .conditions
+            .clone();
+        if conditions.requires_variables() {
+            self.update_stack_frames()?;
+        }
+
+        if let Some(condition) = &conditions.condition {
+            match breakpoint_conditions::evaluate_condition(condition, |expression| {
+                self.evaluate_variable(expression)
+            }) {
+                Ok(true) => {}
+                Ok(false) => return Ok(false),
+                Err(error) => {
+                    debug_adapter.log_to_console(format!(
+                        "Failed to evaluate breakpoint condition {condition:?} at {program_counter:#010x}: {error}"
+                    ));
+                    return Ok(true);
+                }
+            }
+        }
+
+        let breakpoint = &mut self.core_data.breakpoints[breakpoint_index];
+        breakpoint.hit_count += 1;
+        let hit_count = breakpoint.hit_count;
+
+        if let Some(hit_condition) = &conditions.hit_condition {
+            match breakpoint_conditions::hit_condition_is_met(hit_condition, hit_count) {
+                Ok(true) => {}
+                Ok(false) => return Ok(false),
+                Err(error) => {
+                    debug_adapter.log_to_console(format!(
+                        "Failed to evaluate breakpoint hit condition at {program_counter:#010x}: {error}"
+                    ));
+                    return Ok(true);
+                }
+            }
+        }
+
+        if let Some(log_message) = &conditions.log_message {
+            let message = breakpoint_conditions::format_log_message(log_message, |expression| {
+                self.evaluate_variable(expression)
+            });
+            debug_adapter.log_to_console(message);
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Unwind the stack of the halted core, and refresh the cached static variables and [`CoreData::stack_frames`].
+    pub(crate) fn update_stack_frames(&mut self) -> Result<(), DebuggerError> {
+        let initial_registers = DebugRegisters::from_core(&mut self.core);
+        let exception_interface = exception_handler_for_core(self.core.core_type());
+        let instruction_set = self.core.instruction_set().ok();
+
+        self.core_data.static_variables =
+            Some(self.core_data.debug_info.create_static_scope_cache());
+
+        self.core_data.stack_frames = self.core_data.debug_info.unwind(
+            &mut self.core,
+            initial_registers,
+            exception_interface.as_ref(),
+            instruction_set,
+        )?;
+
+        self.core_data.rtos_stack_frames.clear();
+        if let Some(rtos) = &self.core_data.rtos {
+            self.core_data.rtos_threads = rtos.threads(&mut self.core).unwrap_or_else(|error| {
+                tracing::warn!("Failed to read the {} threads: {error}", rtos.name());
+                vec![]
+            });
+        }
+        Ok(())
+    }
+
+    /// The DAP threads of the core. If an RTOS is used, every RTOS thread is listed, and the running thread uses the core id as its thread id.
+    pub(crate) fn threads(&self) -> Vec<(i64, String)> {
+        if self.core_data.rtos_threads.is_empty() {
+            return vec![(self.core.id() as i64, self.core_data.target_name.clone())];
+        }
+
+        self.core_data
+            .rtos_threads
+            .iter()
+            .map(|thread| {
+                let id = if thread.is_running() {
+                    self.core.id() as i64
+                } else {
+                    thread.id as i64
+                };
+                (id, format!("{} ({})", thread.name, thread.state))
+            })
+            .collect()
+    }
+
+    /// The stack frames of the given DAP thread. The stack of an RTOS thread which is not running is unwound
+    /// from its saved registers when it is first requested.
+    pub(crate) fn thread_stack_frames(
+        &mut self,
+        thread_id: i64,
+    ) -> Result<&[StackFrame], DebuggerError> {
+        let Some(thread) = self.core_data.suspended_rtos_thread(thread_id) else {
+            return Ok(&self.core_data.stack_frames);
+        };
+        let rtos_thread_id = thread.id;
+
+        if !self
+            .core_data
+            .rtos_stack_frames
+            .iter()
+            .any(|(id, _)| *id == rtos_thread_id)
+        {
+            let stack_frames = match thread.registers.clone() {
+                Some(registers) => {
+                    let exception_interface = exception_handler_for_core(self.core.core_type());
+                    let instruction_set = self.core.instruction_set().ok();
+                    self.core_data.debug_info.unwind(
+                        &mut self.core,
+                        registers,
+                        exception_interface.as_ref(),
+                        instruction_set,
+                    )?
+                }
+                None => vec![],
+            };
+            self.core_data
+                .rtos_stack_frames
+                .push((rtos_thread_id, stack_frames));
+        }
+
+        Ok(self
+            .core_data
+            .rtos_stack_frames
+            .iter()
+            .find(|(id, _)| *id == rtos_thread_id)
+            .map(|(_, stack_frames)| stack_frames.as_slice())
+            .unwrap_or_default())
+    }
+
+    /// Resolve the formatted value of a variable in the top-most stack frame, using its name, or a `.` separated path to one of its members.
+    /// Local variables take precedence over static variables with the same name.
+    fn evaluate_variable(&mut self, expression: &str) -> Result<String, DebuggerError> {
+        let mut path = expression.split('.').map(str::trim);
+        let root_name = path.next().unwrap_or_default();
+
+        let Some(stack_frame) = self.core_data.stack_frames.first_mut() else {
+            return Err(DebuggerError::UserMessage(
+                "No frame available.".to_string(),
+            ));
+        };
+        let frame_info = StackFrameInfo {
+            registers: &stack_frame.registers,
+            frame_base: stack_frame.frame_base,
+            canonical_frame_address: stack_frame.canonical_frame_address,
+        };
+
+        let root_variable_name = VariableName::Named(root_name.to_string());
+        let (variable_cache, mut variable) =
+            match stack_frame.local_variables.as_mut().and_then(|cache| {
+                cache
+                    .get_variable_by_name(&root_variable_name)
+                    .map(|variable| (cache, variable))
+            }) {
+                Some(found) => found,
+                None => self
+                    .core_data
+                    .static_variables
+                    .as_mut()
+                    .and_then(|cache| {
+                        cache
+                            .get_variable_by_name(&root_variable_name)
+                            .map(|variable| (cache, variable))
+                    })
+                    .ok_or_else(|| {
+                        DebuggerError::UserMessage(format!(
+                            "No variable named {root_name:?} found."
+                        ))
+                    })?,
+            };
+
+        for member_name in path {
+            self.core_data.debug_info.cache_deferred_variables(
+                variable_cache,
+                &mut self.core,
+                &mut variable,
+                frame_info,
+            )?;
+            variable = variable_cache
+                .get_variable_by_name_and_parent(
+                    &VariableName::Named(member_name.to_string()),
+                    variable.variable_key(),
+                )
+                .ok_or_else(|| {
+                    DebuggerError::UserMessage(format!(
+                        "No member named {member_name:?} found in {expression:?}."
+                    ))
+                })?;
+        }
+
+        Ok(variable.to_string(variable_cache))
+    }
+
     /// Search available [`probe_rs::debug::StackFrame`]'s for the given `id`
     pub(crate) fn get_stackframe(
         &'p self,
@@ -171,6 +441,12 @@ impl<'p> CoreHandle<'p> {
         self.core_data
             .stack_frames
             .iter()
+            .chain(
+                self.core_data
+                    .rtos_stack_frames
+                    .iter()
+                    .flat_map(|(_, stack_frames)| stack_frames.iter()),
+            )
             .find(|stack_frame| stack_frame.id == id)
     }
 
@@ -249,6 +525,7 @@ impl<'p> CoreHandle<'p> {
         &mut self,
         address: u64,
         breakpoint_type: session_data::BreakpointType,
+        conditions: BreakpointConditions,
     ) -> Result<(), DebuggerError> {
         // NOTE: After receiving a DAP [`crate::debug_adapter::dap::dap_types::BreakpointEvent`], VSCode will mistakenly
         // identify a `InstructionBreakpoint` as a `SourceBreakpoint`. This results in breakpoints not being cleared correctly from [`CoreHandle::clear_breakpoints()`].
@@ -266,6 +543,8 @@ impl<'p> CoreHandle<'p> {
             .push(session_data::ActiveBreakpoint {
                 breakpoint_type,
                 address,
+                conditions,
+                hit_count: 0,
             });
         Ok(())
     }
@@ -319,6 +598,7 @@ impl<'p> CoreHandle<'p> {
         requested_breakpoint_line: u64,
         requested_breakpoint_column: Option<u64>,
         requested_source: &Source,
+        conditions: BreakpointConditions,
     ) -> Result<VerifiedBreakpoint, DebuggerError> {
         let VerifiedBreakpoint {
                  address,
@@ -338,6 +618,7 @@ impl<'p> CoreHandle<'p> {
                 source: requested_source.clone(),
                 location: SourceLocationScope::Specific(source_location.clone()),
             },
+            conditions,
         )?;
         Ok(VerifiedBreakpoint {
             address,
@@ -379,6 +660,7 @@ impl<'p> CoreHandle<'p> {
                                 ColumnType::Column(c) => c,
                             }),
                             &source,
+                            breakpoint.conditions.clone(),
                         )
                     });
 
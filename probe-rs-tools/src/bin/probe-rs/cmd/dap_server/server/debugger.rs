@@ -714,7 +714,8 @@ impl Debugger {
                             .update_progress(None, Some("Erasing Sectors"), id)
                             .ok();
                     }
-                    ProgressEvent::SectorErased { size, .. } => {
+                    ProgressEvent::SectorErased { size, .. }
+                    | ProgressEvent::SectorSkipped { size, .. } => {
                         flash_progress.sector_size_done += size as usize;
                         let progress = flash_progress.sector_size_done as f64
                             / flash_progress.total_sector_size as f64;
@@ -882,6 +883,9 @@ impl Debugger {
             supports_clipboard_context: Some(true),
             supports_disassemble_request: Some(true),
             supports_instruction_breakpoints: Some(true),
+            supports_conditional_breakpoints: Some(true),
+            supports_hit_conditional_breakpoints: Some(true),
+            supports_log_points: Some(true),
             supports_stepping_granularity: Some(true),
             supports_completions_request: Some(true),
             support_terminate_debuggee: Some(true),
@@ -1027,10 +1031,13 @@ mod test {
             support_suspend_debuggee: Some(true),
             supports_clipboard_context: Some(true),
             supports_completions_request: Some(true),
+            supports_conditional_breakpoints: Some(true),
             supports_configuration_done_request: Some(true),
             supports_delayed_stack_trace_loading: Some(true),
             supports_disassemble_request: Some(true),
+            supports_hit_conditional_breakpoints: Some(true),
             supports_instruction_breakpoints: Some(true),
+            supports_log_points: Some(true),
             supports_read_memory_request: Some(true),
             supports_write_memory_request: Some(true),
             supports_restart_request: Some(true),
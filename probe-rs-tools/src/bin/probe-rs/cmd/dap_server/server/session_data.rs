@@ -1,4 +1,5 @@
 use super::{
+    breakpoint_conditions::BreakpointConditions,
     configuration::{self, CoreConfig, SessionConfig},
     core_data::{CoreData, CoreHandle},
 };
@@ -15,8 +16,7 @@ use crate::{
 use anyhow::{anyhow, Result};
 use probe_rs::{
     config::TargetSelector,
-    debug::{debug_info::DebugInfo, DebugRegisters, SourceLocation},
-    exception_handler_for_core,
+    debug::{debug_info::DebugInfo, rtos::detect_rtos, SourceLocation},
     probe::list::Lister,
     CoreStatus, Session,
 };
@@ -49,6 +49,9 @@ pub(crate) enum SourceLocationScope {
 pub struct ActiveBreakpoint {
     pub(crate) breakpoint_type: BreakpointType,
     pub(crate) address: u64,
+    pub(crate) conditions: BreakpointConditions,
+    /// The number of times the breakpoint was hit, while its `condition` (if any) evaluated to `true`.
+    pub(crate) hit_count: u64,
 }
 
 /// SessionData is designed to be similar to [probe_rs::Session], in as much that it provides handles to the [CoreHandle] instances for each of the available [probe_rs::Core] involved in the debug session.
@@ -128,6 +131,8 @@ impl SessionData {
         let mut core_data_vec = vec![];
 
         for core_configuration in valid_core_configs {
+            let debug_info = debug_info_from_binary(core_configuration)?;
+            let rtos = detect_rtos(&debug_info);
             core_data_vec.push(CoreData {
                 core_index: core_configuration.core_index,
                 last_known_status: CoreStatus::Unknown,
@@ -136,10 +141,13 @@ impl SessionData {
                     core_configuration.core_index,
                     target_session.target().name
                 ),
-                debug_info: debug_info_from_binary(core_configuration)?,
+                debug_info,
                 static_variables: None,
                 core_peripherals: None,
                 stack_frames: vec![],
+                rtos,
+                rtos_threads: vec![],
+                rtos_stack_frames: vec![],
                 breakpoints: vec![],
                 rtt_connection: None,
                 rtt_client: None,
@@ -164,6 +172,9 @@ impl SessionData {
             .find(|core_data| core_data.core_index == core_configuration.core_index)
         {
             core_data.debug_info = debug_info_from_binary(core_configuration)?;
+            core_data.rtos = detect_rtos(&core_data.debug_info);
+            core_data.rtos_threads.clear();
+            core_data.rtos_stack_frames.clear();
             Ok(())
         } else {
             Err(DebuggerError::UnableToOpenProbe(Some(
@@ -279,19 +290,7 @@ impl SessionData {
                     target_core.core.id()
                 );
 
-                let initial_registers = DebugRegisters::from_core(&mut target_core.core);
-                let exception_interface = exception_handler_for_core(target_core.core.core_type());
-                let instruction_set = target_core.core.instruction_set().ok();
-
-                target_core.core_data.static_variables =
-                    Some(target_core.core_data.debug_info.create_static_scope_cache());
-
-                target_core.core_data.stack_frames = target_core.core_data.debug_info.unwind(
-                    &mut target_core.core,
-                    initial_registers,
-                    exception_interface.as_ref(),
-                    instruction_set,
-                )?;
+                target_core.update_stack_frames()?;
             }
             status_of_cores.push(current_core_status);
         }
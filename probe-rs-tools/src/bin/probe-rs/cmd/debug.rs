@@ -37,10 +37,20 @@ pub struct Cmd {
     #[clap(long, value_parser)]
     /// Binary to debug
     exe: Option<PathBuf>,
+
+    #[clap(long, value_parser)]
+    /// Show the registers and backtrace of a core dump instead of attaching to a target.
+    ///
+    /// The core dump can be written by the `dump` command, or be an ELF core file.
+    core_dump: Option<PathBuf>,
 }
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        if let Some(core_dump) = &self.core_dump {
+            return inspect_core_dump(core_dump, self.exe.as_deref());
+        }
+
         let (mut session, _probe_options) = self.common.simple_attach(lister)?;
 
         let di = self
@@ -83,6 +93,60 @@ impl Cmd {
     }
 }
 
+/// Print the registers of a core dump, and its backtrace if the binary is known.
+fn inspect_core_dump(path: &Path, exe: Option<&Path>) -> anyhow::Result<()> {
+    let mut core_dump = CoreDump::load(path)?;
+
+    println!("Core dump of a {:?} core", core_dump.core_type());
+    let registers = core_dump.debug_registers();
+    for register in &registers.0 {
+        if let Some(value) = &register.value {
+            println!("{:10}: {:#}", register.core_register.name(), value);
+        }
+    }
+
+    let Some(exe) = exe else {
+        println!("Specify the binary with --exe to show the backtrace.");
+        return Ok(());
+    };
+
+    let debug_info = DebugInfo::from_file(exe)
+        .map_err(|e| anyhow!("Failed to load debug info from {}: {e:?}", exe.display()))?;
+    let exception_handler = exception_handler_for_core(core_dump.core_type());
+    let instruction_set = core_dump.instruction_set();
+    let stack_frames = debug_info.unwind(
+        &mut core_dump,
+        registers,
+        exception_handler.as_ref(),
+        Some(instruction_set),
+    )?;
+
+    println!();
+    for (i, frame) in stack_frames.iter().enumerate() {
+        print!("Frame {}: {} @ {}", i, frame.function_name, frame.pc);
+        if frame.is_inlined {
+            print!(" inline");
+        }
+        println!();
+
+        if let Some(location) = &frame.source_location {
+            if let Some(file) = &location.file {
+                print!("       ");
+                if let Some(dir) = &location.directory {
+                    print!("{}/", dir.to_path().display());
+                }
+                print!("{file}");
+                if let Some(line) = location.line {
+                    print!(":{line}");
+                }
+                println!();
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, thiserror::Error)]
 enum CliError {
     #[error(transparent)]
@@ -193,6 +257,19 @@ impl DebugCli {
                             capstone::arch::riscv::ArchExtraMode::RiscVC,
                         ))
                         .build(),
+                    InstructionSet::RV64 => Capstone::new()
+                        .riscv()
+                        .mode(riscvArchMode::RiscV64)
+                        .endian(Endian::Little)
+                        .build(),
+                    InstructionSet::RV64C => Capstone::new()
+                        .riscv()
+                        .mode(riscvArchMode::RiscV64)
+                        .endian(Endian::Little)
+                        .extra_mode(std::iter::once(
+                            capstone::arch::riscv::ArchExtraMode::RiscVC,
+                        ))
+                        .build(),
                     InstructionSet::Xtensa => Err(capstone::Error::UnsupportedArch),
                 }
                 .map_err(|err| anyhow!("Error creating capstone: {:?}", err))?;
@@ -553,6 +630,24 @@ impl DebugCli {
             },
         });
 
+        cli.add_command(Command {
+            name: "mpu",
+            help_text:
+                "Show the MPU and SAU regions, and which of them cover an address (default: MMFAR)",
+
+            function: |cli_data, args| {
+                let address = if args.is_empty() {
+                    None
+                } else {
+                    Some(get_int_argument(args, 0)?)
+                };
+
+                crate::cmd::mpu::print_memory_protection(&mut cli_data.core, address)?;
+
+                Ok(CliState::Continue)
+            },
+        });
+
         cli.add_command(Command {
             name: "bt",
             help_text: "Show backtrace",
@@ -853,7 +948,7 @@ impl DebugCli {
 
         cli.add_command(Command {
             name: "dump",
-            help_text: "Dump the core memory & registers",
+            help_text: "Dump the core memory & registers. Paths ending in `.elf` are written as ELF core files",
 
             function: |cli_data, args| {
                 let mut args = args.to_vec();
@@ -903,7 +998,12 @@ impl DebugCli {
 
                 println!("Dumping core");
 
-                CoreDump::dump_core(&mut cli_data.core, ranges)?.store(location)?;
+                let core_dump = CoreDump::dump_core(&mut cli_data.core, ranges)?;
+                if location.extension().is_some_and(|extension| extension == "elf") {
+                    core_dump.store_elf(location)?;
+                } else {
+                    core_dump.store(location)?;
+                }
 
                 println!("Done.");
 
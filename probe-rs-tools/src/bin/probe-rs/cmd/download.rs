@@ -1,11 +1,21 @@
+use std::cell::RefCell;
+use std::fs::File;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use probe_rs::probe::list::Lister;
+use anyhow::{anyhow, Context};
+use colored::Colorize;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use probe_rs::config::TargetSelector;
+use probe_rs::flashing::{FlashBundle, FlashLayout, FlashPlan, FlashProgress, ProgressEvent};
+use probe_rs::probe::{list::Lister, DebugProbeInfo, DebugProbeSelector};
 
 use crate::util::common_options::BinaryDownloadOptions;
-use crate::util::common_options::ProbeOptions;
-use crate::util::flash::build_loader;
+use crate::util::common_options::{OperationError, ProbeOptions};
+use crate::util::flash::build_bundle_or_loader;
+use crate::util::flash::build_download_options;
 use crate::util::flash::run_flash_download;
+use crate::util::logging;
 use crate::FormatOptions;
 
 #[derive(clap::Parser)]
@@ -13,13 +23,32 @@ pub struct Cmd {
     #[clap(flatten)]
     pub probe_options: ProbeOptions,
 
-    /// The path to the file to be downloaded to the flash
+    /// The path to the file to be downloaded to the flash, or to a TOML or YAML
+    /// manifest listing multiple images to be downloaded together.
     pub path: PathBuf,
 
     /// Whether to erase the entire chip before downloading
     #[clap(long)]
     pub chip_erase: bool,
 
+    /// Print the plan of a dry run as JSON.
+    #[clap(long, requires = "dry_run")]
+    pub json: bool,
+
+    /// Flash every connected probe in parallel. All probes have to be connected to the same
+    /// kind of target.
+    #[clap(long, conflicts_with_all = ["probe", "dry_run"])]
+    pub all_probes: bool,
+
+    /// Flash the probes matching the given comma separated selectors in parallel.
+    /// All probes have to be connected to the same kind of target.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = ["probe", "all_probes", "dry_run"]
+    )]
+    pub probes: Vec<DebugProbeSelector>,
+
     #[clap(flatten)]
     pub download_options: BinaryDownloadOptions,
 
@@ -29,9 +58,16 @@ pub struct Cmd {
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        if self.probe_options.dry_run {
+            return self.plan();
+        }
+        if self.all_probes || !self.probes.is_empty() {
+            return self.run_gang(lister);
+        }
+
         let (mut session, probe_options) = self.probe_options.simple_attach(lister)?;
 
-        let loader = build_loader(&mut session, &self.path, self.format_options, None)?;
+        let loader = build_bundle_or_loader(&mut session, &self.path, self.format_options, None)?;
         run_flash_download(
             &mut session,
             &self.path,
@@ -43,4 +79,312 @@ impl Cmd {
 
         Ok(())
     }
+
+    /// Prints what the download would do, without connecting to a probe.
+    fn plan(self) -> anyhow::Result<()> {
+        let probe_options = self.probe_options.load()?;
+        let TargetSelector::Specified(target) = probe_options.get_target_selector()? else {
+            return Err(anyhow!(
+                "A dry run requires the target to be selected with --chip"
+            ));
+        };
+        if FlashBundle::is_manifest(&self.path) {
+            return Err(anyhow!("Dry runs of flash bundles are not supported"));
+        }
+
+        let format = self.format_options.into_format(&target, &self.path);
+        let mut file = File::open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        let mut loader = target.flash_loader();
+        loader.load_image_offline(&mut file, format)?;
+
+        let options = build_download_options(
+            &target,
+            &self.download_options,
+            &probe_options,
+            self.chip_erase,
+        )?;
+        let plan = loader.plan(&target, &options)?;
+
+        if let Some(path) = &self.download_options.flash_layout_output_path {
+            let mut layout = FlashLayout::default();
+            for region in &plan.regions {
+                layout.merge_from(region.layout.clone());
+            }
+            layout.visualize().write_svg(path)?;
+        }
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&plan)?);
+        } else {
+            print_plan(&plan);
+        }
+
+        Ok(())
+    }
+
+    /// Flashes the image with every selected probe, each on its own thread.
+    fn run_gang(self, lister: &Lister) -> anyhow::Result<()> {
+        let Cmd {
+            probe_options,
+            path,
+            chip_erase,
+            all_probes,
+            probes: selectors,
+            download_options,
+            format_options,
+            ..
+        } = self;
+        let probe_options = probe_options.load()?;
+        let target = probe_options.get_target_selector()?;
+
+        let probes = lister
+            .list_all()
+            .into_iter()
+            .filter(|info| {
+                all_probes
+                    || selectors
+                        .iter()
+                        .any(|selector| selector.matches_probe(info))
+            })
+            .collect::<Vec<_>>();
+        if probes.is_empty() {
+            return Err(OperationError::NoProbesFound.into());
+        }
+
+        // The lister can't be shared between threads, so the probes are opened up front.
+        let probes = probes
+            .into_iter()
+            .map(|info| {
+                let probe = probe_options.open_probe(lister, &info);
+                (info, probe)
+            })
+            .collect::<Vec<_>>();
+
+        let multi_progress = MultiProgress::new();
+        logging::set_progress_bar(multi_progress.clone());
+
+        let flash_timer = Instant::now();
+        let results = std::thread::scope(|scope| {
+            let workers = probes
+                .into_iter()
+                .map(|(info, probe)| {
+                    let bar = multi_progress.add(gang_progress_bar(&info));
+                    let target = target.clone();
+                    let (probe_options, path, download_options, format_options) =
+                        (&probe_options, &path, &download_options, &format_options);
+                    let worker = scope.spawn(move || {
+                        let result = probe.map_err(anyhow::Error::from).and_then(|probe| {
+                            let mut session = probe_options.attach_session(probe, target)?;
+                            let loader = build_bundle_or_loader(
+                                &mut session,
+                                path,
+                                format_options.clone(),
+                                None,
+                            )?;
+
+                            let mut options = build_download_options(
+                                session.target(),
+                                download_options,
+                                probe_options,
+                                chip_erase,
+                            )?;
+                            options.progress = Some(gang_progress(bar.clone()));
+
+                            Ok(loader.commit(&mut session, options)?)
+                        });
+
+                        match &result {
+                            Ok(()) => bar.finish_with_message("done"),
+                            Err(_) => bar.abandon_with_message("failed"),
+                        }
+                        result
+                    });
+                    (info, worker)
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .map(|(info, worker)| {
+                    let result = worker
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow!("The flashing thread panicked")));
+                    (info, result)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        logging::clear_progress_bar();
+
+        let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+        for (info, result) in &results {
+            match result {
+                Ok(()) => logging::eprintln(format!(
+                    "{} {}",
+                    "    PASS".green().bold(),
+                    probe_label(info)
+                )),
+                Err(error) => logging::eprintln(format!(
+                    "{} {}: {error:#}",
+                    "    FAIL".red().bold(),
+                    probe_label(info)
+                )),
+            }
+        }
+
+        logging::eprintln(format!(
+            "     {} {} of {} targets in {:.02}s",
+            "Finished".green().bold(),
+            results.len() - failed,
+            results.len(),
+            flash_timer.elapsed().as_secs_f32(),
+        ));
+
+        if failed > 0 {
+            return Err(anyhow!("{failed} of {} targets failed", results.len()));
+        }
+
+        Ok(())
+    }
+}
+
+/// The name of a probe in the output of gang programming.
+fn probe_label(info: &DebugProbeInfo) -> String {
+    match &info.serial_number {
+        Some(serial) => format!("{} ({serial})", info.identifier),
+        None => info.identifier.clone(),
+    }
+}
+
+fn gang_progress_bar(info: &DebugProbeInfo) -> ProgressBar {
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{prefix:.bold} {spinner} {percent:>3}% [{bar:20}] {bytes:>10} {msg:.green.bold}",
+        )
+        .expect("Error in progress bar creation. This is a bug, please report it.")
+        .progress_chars("##-"),
+    );
+    bar.set_prefix(probe_label(info));
+    bar.set_message("Connecting");
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
+
+/// Reports the progress of all flashing steps of one target to a single progress bar.
+fn gang_progress(bar: ProgressBar) -> FlashProgress {
+    // The pages of a skipped sector are not programmed, so they are counted when it is skipped.
+    let pages = RefCell::new(Vec::new());
+
+    FlashProgress::new(move |event| match event {
+        ProgressEvent::Initialized {
+            chip_erase,
+            phases,
+            restore_unwritten,
+        } => {
+            let mut length = 0;
+            for phase in &phases {
+                if restore_unwritten {
+                    length += phase.fills().iter().map(|fill| fill.size()).sum::<u64>();
+                }
+                if !chip_erase {
+                    length += phase
+                        .sectors()
+                        .iter()
+                        .map(|sector| sector.size())
+                        .sum::<u64>();
+                }
+                length += phase
+                    .pages()
+                    .iter()
+                    .map(|page| page.size() as u64)
+                    .sum::<u64>();
+            }
+            bar.set_length(length);
+
+            pages.replace(
+                phases
+                    .iter()
+                    .flat_map(|phase| phase.pages())
+                    .map(|page| page.address()..page.address() + page.size() as u64)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        ProgressEvent::StartedFilling => bar.set_message("Reading flash"),
+        ProgressEvent::StartedErasing => bar.set_message("Erasing"),
+        ProgressEvent::StartedProgramming { .. } => bar.set_message("Programming"),
+        ProgressEvent::PageFilled { size, .. } | ProgressEvent::SectorErased { size, .. } => {
+            bar.inc(size)
+        }
+        ProgressEvent::SectorSkipped { address, size } => {
+            let sector = address..address + size;
+            let skipped_pages = pages
+                .borrow()
+                .iter()
+                .filter(|page| sector.contains(&page.start))
+                .map(|page| page.end - page.start)
+                .sum::<u64>();
+            bar.inc(size + skipped_pages);
+        }
+        ProgressEvent::PageProgrammed { size, .. } => bar.inc(size as u64),
+        ProgressEvent::FailedErasing
+        | ProgressEvent::FailedProgramming
+        | ProgressEvent::FailedFilling
+        | ProgressEvent::FinishedErasing
+        | ProgressEvent::FinishedProgramming
+        | ProgressEvent::FinishedFilling
+        | ProgressEvent::DiagnosticMessage { .. } => {}
+    })
+}
+
+fn print_plan(plan: &FlashPlan) {
+    println!("Flashing plan for {}:", plan.target);
+    if plan.chip_erase {
+        println!("  The whole chip is erased.");
+    }
+
+    for region in &plan.regions {
+        println!(
+            "  {} {:#010x}..{:#010x} (algorithm {}, core {})",
+            region.name.as_deref().unwrap_or("NVM"),
+            region.range.start,
+            region.range.end,
+            region.algorithm,
+            region.core
+        );
+        if !plan.chip_erase {
+            print_ranges("Erase", &region.erase);
+        }
+        print_ranges("Program", &region.program);
+        if plan.restore_unwritten {
+            print_ranges("Restore", &region.fill);
+        } else {
+            print_ranges(
+                &format!("Fill with {:#04x}", region.erased_byte_value),
+                &region.fill,
+            );
+        }
+    }
+    print_ranges("RAM", &plan.ram);
+
+    println!(
+        "  Estimated time: at most {:.1}s",
+        plan.estimated_time.as_secs_f32()
+    );
+
+    for warning in &plan.warnings {
+        println!("Warning: {warning}");
+    }
+}
+
+fn print_ranges(label: &str, ranges: &[std::ops::Range<u64>]) {
+    for range in ranges {
+        println!(
+            "    {label:<16} {:#010x}..{:#010x} ({} bytes)",
+            range.start,
+            range.end,
+            range.end - range.start
+        );
+    }
 }
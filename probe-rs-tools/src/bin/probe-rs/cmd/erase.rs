@@ -2,11 +2,15 @@ use std::cell::RefCell;
 
 use indicatif::{MultiProgress, ProgressBar};
 use probe_rs::{
-    flashing::{erase_all, FlashProgress, ProgressEvent},
+    flashing::{erase_all, erase_all_preserving, FlashProgress, ProgressEvent},
     probe::list::Lister,
 };
 
-use crate::util::{common_options::ProbeOptions, flash::ProgressBarGroup, logging};
+use crate::util::{
+    common_options::ProbeOptions,
+    flash::{parse_preserved_range, ProgressBarGroup},
+    logging,
+};
 
 #[derive(clap::Parser)]
 pub struct Cmd {
@@ -15,11 +19,21 @@ pub struct Cmd {
 
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub disable_progressbars: bool,
+
+    /// Keep the contents of a range, e.g. factory calibration data. Takes `START..END`,
+    /// `START+SIZE` or the name of a memory region, and can be given multiple times.
+    #[arg(long, value_name = "RANGE", help_heading = "DOWNLOAD CONFIGURATION")]
+    pub preserve: Vec<String>,
 }
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
         let (mut session, _probe_options) = self.common.simple_attach(lister)?;
+        let preserve = self
+            .preserve
+            .iter()
+            .map(|spec| parse_preserved_range(spec, session.target()))
+            .collect::<Result<Vec<_>, _>>()?;
 
         let multi_progress = MultiProgress::new();
         logging::set_progress_bar(multi_progress.clone());
@@ -58,7 +72,11 @@ impl Cmd {
             FlashProgress::empty()
         };
 
-        erase_all(&mut session, progress)?;
+        if preserve.is_empty() {
+            erase_all(&mut session, progress)?;
+        } else {
+            erase_all_preserving(&mut session, progress, &preserve)?;
+        }
 
         Ok(())
     }
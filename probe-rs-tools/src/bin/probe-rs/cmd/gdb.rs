@@ -1,6 +1,9 @@
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use parking_lot::FairMutex;
+use probe_rs::debug::{debug_info::DebugInfo, rtos::detect_rtos};
 use probe_rs::probe::list::Lister;
 
 use crate::util::common_options::ProbeOptions;
@@ -20,6 +23,10 @@ pub struct Cmd {
     )]
     reset_halt: bool,
 
+    /// The binary running on the target. If it uses a supported RTOS, the RTOS threads are shown in GDB.
+    #[clap(long, value_parser)]
+    elf: Option<PathBuf>,
+
     #[clap(flatten)]
     common: ProbeOptions,
 }
@@ -38,11 +45,19 @@ impl Cmd {
             .gdb_connection_string
             .unwrap_or_else(|| "localhost:1337".to_string());
 
-        let instances = probe_rs::gdb_server::GdbInstanceConfiguration::from_session(
+        let mut instances = probe_rs::gdb_server::GdbInstanceConfiguration::from_session(
             &session,
             Some(gdb_connection_string),
         );
 
+        if let Some(elf) = &self.elf {
+            let debug_info = DebugInfo::from_file(elf)?;
+            let rtos = detect_rtos(&debug_info).map(Arc::from);
+            for instance in instances.iter_mut() {
+                instance.rtos = rtos.clone();
+            }
+        }
+
         for instance in instances.iter() {
             println!(
                 "Firing up GDB stub for {:?} cores at {:?}",
@@ -8,7 +8,10 @@ use probe_rs::{
             ap::ApClass,
             armv6m::Demcr,
             component::Scs,
-            dp::{DebugPortId, DebugPortVersion, MinDpSupport, DLPIDR, DPIDR, TARGETID},
+            dp::{
+                discover_multidrop_debug_ports, known_targetsels, targetsel_candidates,
+                DebugPortId, DebugPortVersion, MinDpSupport, DLPIDR, DPIDR, TARGETID,
+            },
             memory::{
                 romtable::{PeripheralID, RomTable},
                 Component, ComponentId, CoresightComponent, PeripheralType,
@@ -40,6 +43,18 @@ pub struct Cmd {
     /// when connecting. This is required for targets using SWD multidrop
     #[arg(long, value_parser = parse_hex)]
     target_sel: Option<u32>,
+
+    /// Discover the debug ports on an SWD multidrop bus
+    ///
+    /// The TARGETSEL values of the targets in the registry, and of the TARGETIDs given with
+    /// `--targetid`, are tried with all 16 instance IDs. The information of every debug port
+    /// which responds is shown.
+    #[arg(long, conflicts_with = "target_sel")]
+    discover_multidrop: bool,
+
+    /// An additional TARGETID to try during SWD multidrop discovery
+    #[arg(long, value_parser = parse_hex, requires = "discover_multidrop")]
+    targetid: Vec<u32>,
 }
 
 // Clippy doesn't like `from_str_radix` with radix 10, but I prefer the symmetry`
@@ -58,6 +73,10 @@ impl Cmd {
         let probe_options = self.common.load()?;
         let mut probe = probe_options.attach_probe(lister)?;
 
+        if self.discover_multidrop {
+            return discover_multidrop(probe, probe_options.connect_under_reset(), &self.targetid);
+        }
+
         let protocols = if let Some(protocol) = probe_options.protocol() {
             vec![protocol]
         } else {
@@ -90,6 +109,70 @@ impl Cmd {
     }
 }
 
+fn discover_multidrop(
+    mut probe: Probe,
+    connect_under_reset: bool,
+    targetids: &[u32],
+) -> Result<()> {
+    probe.select_protocol(WireProtocol::Swd)?;
+    if connect_under_reset {
+        probe.attach_to_unspecified_under_reset()?;
+    } else {
+        probe.attach_to_unspecified()?;
+    }
+
+    let candidates = targetsel_candidates(
+        known_targetsels()
+            .into_iter()
+            .chain(targetids.iter().copied()),
+    );
+    println!("Trying {} TARGETSEL values", candidates.len());
+    println!();
+
+    let dap_probe = probe
+        .try_as_dap_probe()
+        .ok_or_else(|| anyhow!("The probe does not support SWD multidrop"))?;
+    let found = discover_multidrop_debug_ports(dap_probe, &candidates)?;
+
+    if found.is_empty() {
+        println!("No debug port responded");
+        probe.detach()?;
+        return Ok(());
+    }
+
+    for dp in &found {
+        println!(
+            "TARGETSEL {:#010x}: DPIDR: {:#010x}, TARGETID: {:#010x}, Designer: {}, Part: {:#x}, Revision: {:#x}, Instance: {:#04x}",
+            dp.targetsel,
+            dp.dpidr,
+            dp.targetid,
+            dp.designer().get().unwrap_or("<unknown>"),
+            dp.part(),
+            dp.revision(),
+            dp.instance()
+        );
+    }
+    println!();
+
+    for dp in found {
+        let (probe_moved, result) = try_show_arm_dp_info(probe, dp.address());
+        probe = probe_moved;
+
+        if let Err(e) = result {
+            println!(
+                "Error showing ARM chip information for Debug Port {:?}: {:?}",
+                dp.address(),
+                e
+            );
+            println!();
+        }
+    }
+
+    probe.detach()?;
+
+    Ok(())
+}
+
 const ALTERNATE_DP_ADRESSES: [DpAddress; 2] = [
     DpAddress::Multidrop(0x01002927),
     DpAddress::Multidrop(0x11002927),
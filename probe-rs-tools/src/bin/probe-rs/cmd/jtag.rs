@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use probe_rs::probe::{list::Lister, svf::Program, WireProtocol};
+
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(clap::Subcommand)]
+enum Action {
+    /// Play an SVF or XSVF file on the JTAG scan chain.
+    ///
+    /// Files with the `.xsvf` extension are read as XSVF, all other files as SVF.
+    Play {
+        /// The SVF or XSVF file to play.
+        file: PathBuf,
+    },
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        match self.action {
+            Action::Play { file } => {
+                let xsvf = file
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("xsvf"));
+
+                let program = if xsvf {
+                    let data = std::fs::read(&file)
+                        .with_context(|| format!("Failed to read {}", file.display()))?;
+                    Program::parse_xsvf(&data)
+                } else {
+                    let source = std::fs::read_to_string(&file)
+                        .with_context(|| format!("Failed to read {}", file.display()))?;
+                    Program::parse_svf(&source)
+                }
+                .with_context(|| format!("Failed to parse {}", file.display()))?;
+
+                let probe_options = self.common.load()?;
+                let mut probe = probe_options.attach_probe(lister)?;
+                probe.select_protocol(WireProtocol::Jtag)?;
+                probe.attach_to_unspecified()?;
+
+                let jtag = probe
+                    .try_as_jtag_probe()
+                    .context("The probe does not support low-level JTAG access")?;
+                program
+                    .play(jtag)
+                    .with_context(|| format!("Failed to play {}", file.display()))?;
+
+                println!("Played {}", file.display());
+            }
+        }
+
+        Ok(())
+    }
+}
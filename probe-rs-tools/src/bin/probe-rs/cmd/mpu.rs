@@ -0,0 +1,218 @@
+use probe_rs::architecture::arm::mpu::{
+    memory_management_fault_address, Mpu, MpuArchitecture, MpuRegion, Sau,
+};
+use probe_rs::{probe::list::Lister, Core, CoreType};
+
+use crate::util::common_options::ProbeOptions;
+use crate::util::parse_u32;
+use crate::CoreOptions;
+
+/// Show the MPU and SAU regions of a Cortex-M core
+///
+/// e.g. probe-rs mpu --address 0x20000000
+///      Shows the configured regions, and which of them covers address 0x20000000.
+///
+/// Without an address, the region covering the address of the last MemManage fault is shown,
+/// if the MMFAR register holds a valid address.
+#[derive(clap::Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    probe_options: ProbeOptions,
+
+    /// Show which regions cover this address
+    #[clap(long, value_parser = parse_u32)]
+    address: Option<u32>,
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
+
+        let mut core = session.core(self.shared.core)?;
+
+        print_memory_protection(&mut core, self.address)?;
+
+        Ok(())
+    }
+}
+
+/// Print the MPU and SAU configuration of the core, and which regions cover `address`.
+///
+/// If no address is given, the address of the last MemManage fault is used, if it is valid.
+pub(crate) fn print_memory_protection(
+    core: &mut Core,
+    address: Option<u32>,
+) -> Result<(), probe_rs::Error> {
+    let core_type = core.core_type();
+
+    let mpu = Mpu::read(core, core_type)?;
+
+    // The SAU and the Non-secure MPU can only be read with Secure memory accesses, so they are
+    // reported as not accessible instead of failing with Non-secure accesses.
+    let (sau, non_secure_mpu) = if core_type == CoreType::Armv8m {
+        match Sau::read(core) {
+            Ok(Some(sau)) => {
+                let non_secure_mpu = Mpu::read_non_secure(core).map_err(|error| {
+                    tracing::debug!("Failed to read the Non-secure MPU: {error}");
+                });
+                (Ok(Some(sau)), non_secure_mpu)
+            }
+            Ok(None) => (Ok(None), Ok(None)),
+            Err(error) => {
+                tracing::debug!("Failed to read the SAU: {error}");
+                (Err(()), Err(()))
+            }
+        }
+    } else {
+        (Ok(None), Ok(None))
+    };
+
+    match (&mpu, &non_secure_mpu) {
+        (Some(mpu), Ok(Some(non_secure_mpu))) => {
+            print_mpu("Secure MPU", mpu);
+            print_mpu("Non-secure MPU", non_secure_mpu);
+        }
+        (Some(mpu), Err(())) => {
+            print_mpu("Secure MPU", mpu);
+            println!("Non-secure MPU: not accessible");
+        }
+        (Some(mpu), Ok(None)) => print_mpu("MPU", mpu),
+        (None, _) => println!("MPU: not implemented"),
+    }
+
+    match &sau {
+        Ok(Some(sau)) => print_sau(sau),
+        Ok(None) => {}
+        Err(()) => println!("SAU: not accessible"),
+    }
+
+    let (address, source) = match address {
+        Some(address) => (address, "address"),
+        None => match memory_management_fault_address(core, core_type)? {
+            Some(address) => (address, "MemManage fault address (MMFAR)"),
+            None => return Ok(()),
+        },
+    };
+
+    println!();
+    println!("{source} {address:#010x}:");
+
+    if let Some(mpu) = &mpu {
+        let name = if matches!(non_secure_mpu, Ok(None)) {
+            "MPU"
+        } else {
+            "Secure MPU"
+        };
+        print_covering_region(name, mpu, address);
+    }
+    match &non_secure_mpu {
+        Ok(Some(non_secure_mpu)) => {
+            print_covering_region("Non-secure MPU", non_secure_mpu, address)
+        }
+        Ok(None) => {}
+        Err(()) => println!("  Non-secure MPU: not accessible"),
+    }
+    if let Err(()) = &sau {
+        println!("  SAU: not accessible");
+    }
+    if let Ok(Some(sau)) = &sau {
+        let attribution = match sau.region_at(address) {
+            Some(region) if region.non_secure_callable => {
+                format!("Non-secure callable (region {})", region.number)
+            }
+            Some(region) => format!("Non-secure (region {})", region.number),
+            None if !sau.enabled && sau.all_non_secure => "Non-secure (SAU disabled)".to_string(),
+            None => "Secure".to_string(),
+        };
+        println!("  SAU: {attribution}");
+    }
+
+    Ok(())
+}
+
+fn print_mpu(name: &str, mpu: &Mpu) {
+    let architecture = match mpu.architecture {
+        MpuArchitecture::Pmsav7 => "PMSAv7",
+        MpuArchitecture::Pmsav8 => "PMSAv8",
+    };
+
+    println!(
+        "{name} ({architecture}): {}, {} regions",
+        if mpu.enabled { "enabled" } else { "disabled" },
+        mpu.regions.len()
+    );
+    if mpu.privileged_default_map {
+        println!("  Default memory map enabled for privileged accesses");
+    }
+    if mpu.enabled_in_fault_handlers {
+        println!("  Enabled in HardFault and NMI handlers");
+    }
+
+    for region in mpu.regions.iter().filter(|region| region.enabled) {
+        println!("  {}", format_region(region));
+    }
+}
+
+fn format_region(region: &MpuRegion) -> String {
+    let mut line = format!(
+        "{:>2}: {:#010x} - {:#010x}  {}  {}",
+        region.number, region.start, region.end, region.access, region.memory_type
+    );
+
+    if region.shareable {
+        line.push_str(", shareable");
+    }
+    if region.execute_never {
+        line.push_str(", XN");
+    } else if region.privileged_execute_never {
+        line.push_str(", PXN");
+    }
+    if region.disabled_subregions != 0 {
+        line.push_str(&format!(
+            ", disabled subregions {:#04x}",
+            region.disabled_subregions
+        ));
+    }
+
+    line
+}
+
+fn print_sau(sau: &Sau) {
+    println!(
+        "SAU: {}, {} regions",
+        if sau.enabled { "enabled" } else { "disabled" },
+        sau.regions.len()
+    );
+    if !sau.enabled && sau.all_non_secure {
+        println!("  All memory is Non-secure");
+    }
+
+    for region in sau.regions.iter().filter(|region| region.enabled) {
+        println!(
+            "  {:>2}: {:#010x} - {:#010x}  {}",
+            region.number,
+            region.start,
+            region.end,
+            if region.non_secure_callable {
+                "Non-secure callable"
+            } else {
+                "Non-secure"
+            }
+        );
+    }
+}
+
+fn print_covering_region(name: &str, mpu: &Mpu, address: u32) {
+    match mpu.region_at(address) {
+        Some(region) => println!("  {name}: {}", format_region(region)),
+        None if mpu.enabled && mpu.privileged_default_map => {
+            println!("  {name}: no region, default memory map for privileged accesses only")
+        }
+        None if mpu.enabled => println!("  {name}: no region, all accesses fault"),
+        None => println!("  {name}: no region, MPU disabled"),
+    }
+}
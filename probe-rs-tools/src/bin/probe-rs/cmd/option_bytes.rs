@@ -0,0 +1,96 @@
+use anyhow::{anyhow, Context};
+use probe_rs::{
+    architecture::arm::ArmError,
+    probe::list::Lister,
+    vendor::st::{self, sequences::option_bytes::OptionBytes},
+    Error, Session,
+};
+
+use crate::util::{common_options::ProbeOptions, parse_u32};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// Print all option byte fields of the target
+    #[clap(name = "show")]
+    Show {
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+    /// Change option byte fields of the target
+    #[clap(name = "set")]
+    Set {
+        #[clap(flatten)]
+        common: ProbeOptions,
+        /// The fields to change, e.g. `BOR_LEV=4 nBOOT0=0`.
+        #[clap(value_name = "FIELD=VALUE", value_parser = parse_assignment, required = true)]
+        fields: Vec<(String, u32)>,
+    },
+}
+
+fn parse_assignment(input: &str) -> anyhow::Result<(String, u32)> {
+    let (field, value) = input
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected FIELD=VALUE, got '{input}'"))?;
+    let value = parse_u32(value.trim()).with_context(|| format!("invalid value '{value}'"))?;
+
+    Ok((field.trim().to_string(), value))
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        match self.subcommand {
+            Subcommand::Show { common } => {
+                let (mut session, _) = common.simple_attach(lister)?;
+                let layout = option_bytes(&session)?;
+
+                let values = layout.read(&mut session.core(0)?)?;
+                println!("{} option bytes:", layout.family);
+                for (register, value) in layout.registers.iter().zip(values.registers()) {
+                    println!("{}: {value:#010x}", register.name);
+                    for field in values
+                        .fields()
+                        .filter(|field| field.register.name == register.name)
+                    {
+                        println!(
+                            "    {:<16} {:#x}\t{}",
+                            field.field.name, field.value, field.field.description
+                        );
+                    }
+                }
+
+                Ok(())
+            }
+            Subcommand::Set { common, fields } => {
+                let (mut session, _) = common.simple_attach(lister)?;
+                let layout = option_bytes(&session)?;
+
+                let mut core = session.core(0)?;
+                let mut values = layout.read(&mut core)?;
+                for (field, value) in fields {
+                    values.set(&field, value)?;
+                }
+
+                match layout.program(&mut core, &values) {
+                    Ok(()) => println!("Programmed the option bytes."),
+                    Err(Error::Arm(ArmError::ReAttachRequired)) => {
+                        println!("Programmed the option bytes, the target was reset to load them.")
+                    }
+                    Err(error) => return Err(error).context("Failed to program the option bytes"),
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn option_bytes(session: &Session) -> anyhow::Result<&'static OptionBytes> {
+    let name = &session.target().name;
+    st::option_bytes(name).ok_or_else(|| anyhow!("Option bytes of {name} are not supported"))
+}
@@ -0,0 +1,138 @@
+use anyhow::Context;
+use probe_rs::{
+    architecture::arm::ArmError, probe::list::Lister, Error, ProtectionLevel, ProtectionStatus,
+};
+
+use crate::util::common_options::{OperationError, ProbeOptions};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// Show the current readout protection level of the target
+    #[clap(name = "status")]
+    Status {
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+    /// Protect the target against reading out its memory
+    #[clap(name = "lock")]
+    Lock {
+        #[clap(flatten)]
+        common: ProbeOptions,
+        /// The protection level to apply.
+        #[clap(long, value_enum, default_value = "protected")]
+        level: LockLevel,
+        /// Allow the target to be protected permanently. This cannot be undone, and the target may
+        /// never be debugged or reprogrammed again.
+        #[clap(long)]
+        allow_permanent_protection: bool,
+    },
+    /// Remove the readout protection of the target. This erases the target, so it requires
+    /// `--allow-erase-all`.
+    #[clap(name = "unlock")]
+    Unlock {
+        #[clap(flatten)]
+        common: ProbeOptions,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum LockLevel {
+    /// Block debug access to the memory. It can be removed again by erasing the target.
+    Protected,
+    /// Block debug access permanently.
+    Permanent,
+}
+
+impl From<LockLevel> for ProtectionLevel {
+    fn from(level: LockLevel) -> Self {
+        match level {
+            LockLevel::Protected => ProtectionLevel::Protected,
+            LockLevel::Permanent => ProtectionLevel::PermanentlyProtected,
+        }
+    }
+}
+
+impl Cmd {
+    pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        match self.subcommand {
+            Subcommand::Status { common } => status(common, lister),
+            Subcommand::Lock {
+                common,
+                level,
+                allow_permanent_protection,
+            } => {
+                let options = common.load()?;
+                let mut permissions = options.permissions();
+                if allow_permanent_protection {
+                    permissions = permissions.allow_permanent_protection();
+                }
+
+                let target = options.get_target_selector()?;
+                let probe = options.attach_probe(lister)?;
+                let mut session =
+                    options.attach_session_with_permissions(probe, target, permissions)?;
+
+                session
+                    .set_protection(level.into())
+                    .context("Failed to protect the target")?;
+                print_status(&session.protection_status()?);
+                println!("Depending on the target, the protection takes effect after a reset.");
+
+                Ok(())
+            }
+            Subcommand::Unlock { common } => {
+                let (mut session, _) = common.simple_attach(lister)?;
+
+                session
+                    .set_protection(ProtectionLevel::Unprotected)
+                    .context("Failed to unlock the target")?;
+                print_status(&session.protection_status()?);
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn status(common: ProbeOptions, lister: &Lister) -> anyhow::Result<()> {
+    match common.simple_attach(lister) {
+        Ok((mut session, _)) => {
+            print_status(&session.protection_status()?);
+            Ok(())
+        }
+        // Some targets can only be attached to after erasing them, if they are protected.
+        Err(OperationError::AttachingFailed {
+            source:
+                Error::MissingPermissions(details) | Error::Arm(ArmError::MissingPermissions(details)),
+            ..
+        }) => {
+            print_status(&ProtectionStatus {
+                level: ProtectionLevel::Protected,
+                pending: None,
+                details,
+            });
+            Ok(())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn print_status(status: &ProtectionStatus) {
+    let name = |level| match level {
+        ProtectionLevel::Unprotected => "unprotected",
+        ProtectionLevel::Protected => "protected",
+        ProtectionLevel::PermanentlyProtected => "permanently protected",
+    };
+
+    println!("Protection level: {}", name(status.level));
+    if let Some(pending) = status.pending {
+        println!("After the next reset: {}", name(pending));
+    }
+    println!("Details: {}", status.details);
+}
@@ -36,6 +36,9 @@ impl Cmd {
         let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
 
         let mut core = session.core(self.shared.core)?;
+        if let Some(memory_security) = self.read_write_options.memory_security {
+            core.set_memory_security_state(memory_security.into())?;
+        }
         let words = self.words as usize;
 
         match self.read_write_options.width {
@@ -120,7 +120,7 @@ impl Cmd {
         let format = self
             .shared_options
             .format_options
-            .to_format_kind(session.target());
+            .to_format_kind(session.target(), &self.shared_options.path);
         let elf = if matches!(format, FormatKind::Elf | FormatKind::Idf) {
             Some(fs::read(&self.shared_options.path)?)
         } else {
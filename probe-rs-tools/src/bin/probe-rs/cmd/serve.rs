@@ -0,0 +1,24 @@
+use std::net::TcpListener;
+
+use anyhow::Context;
+use probe_rs::probe::{list::Lister, remote};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    /// The address to listen on.
+    ///
+    /// Clients are not authenticated, so only listen on addresses which are reachable from
+    /// trusted networks.
+    #[clap(long, default_value = "127.0.0.1:3500")]
+    address: String,
+}
+
+impl Cmd {
+    pub fn run(self) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(&self.address)
+            .with_context(|| format!("Failed to listen on {}", self.address))?;
+        println!("Serving probes on {}", listener.local_addr()?);
+
+        remote::serve(listener, Lister::new)
+    }
+}
@@ -1,8 +1,12 @@
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 use std::thread::sleep;
 use std::time::Duration;
 use std::time::Instant;
 
+use anyhow::Context;
+use probe_rs::architecture::arm::etm::{decode_instruction_trace, ProgramImage, TraceElement};
+use probe_rs::debug::DebugInfo;
 use probe_rs::probe::list::Lister;
 use probe_rs::MemoryInterface;
 use scroll::{Pwrite, LE};
@@ -19,12 +23,28 @@ pub struct Cmd {
     common: ProbeOptions,
 
     /// The address of the memory to dump from the target.
-    #[clap(value_parser = parse_u64)]
-    loc: u64,
+    #[clap(value_parser = parse_u64, required_unless_present = "instructions")]
+    loc: Option<u64>,
+
+    /// Capture ETM instruction trace into the on-chip trace buffer, and print the last N executed
+    /// instructions when the core halts.
+    #[clap(long, value_name = "N", conflicts_with = "loc", requires = "elf")]
+    instructions: Option<usize>,
+
+    /// The ELF file of the program running on the target, used to decode the instruction trace.
+    #[clap(long, value_parser)]
+    elf: Option<PathBuf>,
 }
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        if let (Some(count), Some(elf)) = (self.instructions, self.elf.clone()) {
+            return self.trace_instructions(lister, count, &elf);
+        }
+        let loc = self
+            .loc
+            .context("An address to trace or `--instructions` is required")?;
+
         let mut xs = vec![];
         let mut ys = vec![];
 
@@ -40,7 +60,7 @@ impl Cmd {
             let instant = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
 
             // Read data.
-            let value: u32 = core.read_word_32(self.loc)?;
+            let value: u32 = core.read_word_32(loc)?;
 
             xs.push(instant);
             ys.push(value);
@@ -62,4 +82,69 @@ impl Cmd {
             sleep(Duration::from_millis(time_to_wait));
         }
     }
+
+    /// Trace the executed instructions until the core halts, e.g. on a breakpoint or a fault,
+    /// and print the last `count` of them.
+    fn trace_instructions(self, lister: &Lister, count: usize, elf: &Path) -> anyhow::Result<()> {
+        let elf_data =
+            std::fs::read(elf).with_context(|| format!("Failed to read {}", elf.display()))?;
+        let image = ProgramImage::from_elf(&elf_data)?;
+        let debug_info = DebugInfo::from_raw(&elf_data).ok();
+
+        let (mut session, _probe_options) = self.common.simple_attach(lister)?;
+
+        let protocol = session.setup_instruction_trace(self.shared.core)?;
+        {
+            let mut core = session.core(self.shared.core)?;
+            if core.core_halted()? {
+                core.run()?;
+            }
+
+            eprintln!("Tracing with {protocol}, waiting for the core to halt...");
+            while !core.core_halted()? {
+                sleep(Duration::from_millis(100));
+            }
+        }
+
+        let data = session.read_instruction_trace()?;
+        let elements = decode_instruction_trace(protocol, &data, &image);
+
+        // Start at the element which leaves `count` instructions until the end of the trace.
+        let start = elements
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, element)| matches!(element, TraceElement::Instruction { .. }))
+            .nth(count.saturating_sub(1))
+            .map_or(0, |(index, _)| index);
+
+        for element in &elements[start..] {
+            match element {
+                TraceElement::Instruction {
+                    address, executed, ..
+                } => {
+                    let location = debug_info
+                        .as_ref()
+                        .and_then(|debug_info| debug_info.get_source_location(*address))
+                        .and_then(|location| {
+                            let path = location.combined_typed_path()?;
+                            Some(format!(
+                                "{}:{}",
+                                path.to_string_lossy(),
+                                location.line.unwrap_or_default()
+                            ))
+                        })
+                        .unwrap_or_default();
+                    let skipped = if *executed { " " } else { "x" };
+
+                    println!("{skipped} {address:#010x}  {location}");
+                }
+                TraceElement::Exception { number } => println!("-- exception {number} --"),
+                TraceElement::ExceptionReturn => println!("-- exception return --"),
+                TraceElement::Gap => println!("-- trace discontinuity --"),
+            }
+        }
+
+        Ok(())
+    }
 }
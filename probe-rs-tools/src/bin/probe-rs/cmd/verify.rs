@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use probe_rs::flashing::FlashError;
+use probe_rs::flashing::{DownloadOptions, FlashError};
 use probe_rs::probe::list::Lister;
 
 use crate::util::common_options::ProbeOptions;
@@ -17,6 +17,11 @@ pub struct Cmd {
 
     #[clap(flatten)]
     pub format_options: FormatOptions,
+
+    /// Compare with the image exactly as it is, for images which were flashed with
+    /// `--skip-image-fixups`.
+    #[arg(long)]
+    pub skip_image_fixups: bool,
 }
 
 impl Cmd {
@@ -25,7 +30,10 @@ impl Cmd {
 
         let loader = build_loader(&mut session, &self.path, self.format_options, None)?;
 
-        match loader.verify(&mut session) {
+        let mut options = DownloadOptions::new();
+        options.skip_image_fixups = self.skip_image_fixups;
+
+        match loader.verify(&mut session, &options) {
             Ok(()) => {
                 println!("Verification successful")
             }
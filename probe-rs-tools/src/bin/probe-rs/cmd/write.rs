@@ -32,6 +32,9 @@ impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
         let (mut session, _probe_options) = self.probe_options.simple_attach(lister)?;
         let mut core = session.core(self.shared.core)?;
+        if let Some(memory_security) = self.read_write_options.memory_security {
+            core.set_memory_security_state(memory_security.into())?;
+        }
 
         match self.read_write_options.width {
             ReadWriteBitWidth::B8 => {
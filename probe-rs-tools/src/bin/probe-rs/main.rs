@@ -76,13 +76,17 @@ enum Subcommand {
     Verify(cmd::verify::Cmd),
     /// Erase all nonvolatile memory of attached target
     Erase(cmd::erase::Cmd),
+    /// Inspect and change the readout protection of the attached target
+    Protect(cmd::protect::Cmd),
+    /// Inspect and change the option bytes of the attached STM32 target
+    OptionBytes(cmd::option_bytes::Cmd),
     /// Flash and run an ELF program
     #[clap(name = "run")]
     Run(cmd::run::Cmd),
     /// Attach to rtt logging
     #[clap(name = "attach")]
     Attach(cmd::attach::Cmd),
-    /// Trace a memory location on the target
+    /// Trace a memory location, or the executed instructions, on the target
     #[clap(name = "trace")]
     Trace(cmd::trace::Cmd),
     /// Configure and monitor ITM trace packets from the target.
@@ -93,6 +97,14 @@ enum Subcommand {
     Benchmark(cmd::benchmark::Cmd),
     /// Profile on-target runtime performance of target ELF program
     Profile(cmd::profile::ProfileCmd),
+    /// Serve the connected debug probes to remote clients over TCP
+    Serve(cmd::serve::Cmd),
+    /// Read and drive the pins of a JTAG device with boundary scan
+    Bscan(cmd::bscan::Cmd),
+    /// Low-level operations on the JTAG scan chain
+    Jtag(cmd::jtag::Cmd),
+    /// Show the MPU and SAU regions of a Cortex-M core
+    Mpu(cmd::mpu::Cmd),
     Read(cmd::read::Cmd),
     Write(cmd::write::Cmd),
     Complete(cmd::complete::Cmd),
@@ -132,8 +144,9 @@ pub struct IdfCliOptions {
 #[serde(default)]
 pub struct FormatOptions {
     /// If a format is provided, use it.
+    /// If the file extension identifies the format (e.g. `.hex` or `.s19`), we use that.
     /// If a target has a preferred format, we use that.
-    /// Finally, if neither of the above cases are true, we default to ELF.
+    /// Finally, if none of the above cases are true, we default to ELF.
     #[clap(
         value_enum,
         ignore_case = true,
@@ -151,20 +164,24 @@ pub struct FormatOptions {
 
 impl FormatOptions {
     /// If a format is provided, use it.
+    /// If the format can be determined from the file extension, we use that.
     /// If a target has a preferred format, we use that.
-    /// Finally, if neither of the above cases are true, we default to [`Format::default()`].
-    pub fn to_format_kind(&self, target: &Target) -> FormatKind {
-        self.binary_format.unwrap_or_else(|| {
-            FormatKind::from_optional(target.default_format.as_deref())
-                .expect("Failed to parse a default binary format. This shouldn't happen.")
-        })
+    /// Finally, if none of the above cases are true, we default to [`Format::default()`].
+    pub fn to_format_kind(&self, target: &Target, path: &Path) -> FormatKind {
+        self.binary_format
+            .or_else(|| FormatKind::from_extension(path))
+            .unwrap_or_else(|| {
+                FormatKind::from_optional(target.default_format.as_deref())
+                    .expect("Failed to parse a default binary format. This shouldn't happen.")
+            })
     }
 
     /// If a format is provided, use it.
+    /// If the format can be determined from the file extension, we use that.
     /// If a target has a preferred format, we use that.
-    /// Finally, if neither of the above cases are true, we default to [`Format::default()`].
-    pub fn into_format(self, target: &Target) -> Format {
-        match self.to_format_kind(target) {
+    /// Finally, if none of the above cases are true, we default to [`Format::default()`].
+    pub fn into_format(self, target: &Target, path: &Path) -> Format {
+        match self.to_format_kind(target, path) {
             FormatKind::Bin => Format::Bin(BinOptions {
                 base_address: self.bin_options.base_address,
                 skip: self.bin_options.skip,
@@ -176,6 +193,7 @@ impl FormatOptions {
                 bootloader: self.idf_options.idf_bootloader,
                 partition_table: self.idf_options.idf_partition_table,
             }),
+            FormatKind::Srec => Format::Srec,
         }
     }
 }
@@ -335,11 +353,17 @@ fn main() -> Result<()> {
             cmd.run(&lister)
         }
         Subcommand::Erase(cmd) => cmd.run(&lister),
+        Subcommand::Protect(cmd) => cmd.run(&lister),
+        Subcommand::OptionBytes(cmd) => cmd.run(&lister),
         Subcommand::Trace(cmd) => cmd.run(&lister),
         Subcommand::Itm(cmd) => cmd.run(&lister),
         Subcommand::Chip(cmd) => cmd.run(),
         Subcommand::Benchmark(cmd) => cmd.run(&lister),
         Subcommand::Profile(cmd) => cmd.run(&lister),
+        Subcommand::Serve(cmd) => cmd.run(),
+        Subcommand::Bscan(cmd) => cmd.run(&lister),
+        Subcommand::Jtag(cmd) => cmd.run(&lister),
+        Subcommand::Mpu(cmd) => cmd.run(&lister),
         Subcommand::Read(cmd) => cmd.run(&lister),
         Subcommand::Write(cmd) => cmd.run(&lister),
         Subcommand::Complete(cmd) => cmd.run(&lister),
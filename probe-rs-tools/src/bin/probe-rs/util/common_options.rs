@@ -11,9 +11,11 @@ use probe_rs::{
     flashing::{FileDownloadError, FlashError},
     integration::FakeProbe,
     probe::{
-        list::Lister, DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, WireProtocol,
+        list::Lister,
+        recording::{RecordingProbe, ReplayProbe},
+        DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, WireProtocol,
     },
-    Permissions, Session, Target,
+    Permissions, SecurityState, Session, Target,
 };
 use serde::{Deserialize, Serialize};
 
@@ -39,9 +41,25 @@ pub struct BinaryDownloadOptions {
     /// Before flashing, read back all the flashed data to skip flashing if the device is up to date.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub preverify: bool,
+    /// Before erasing, compare the CRC of each sector with the data to be flashed, and skip the
+    /// sectors which are already up to date.
+    #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub skip_unchanged_sectors: bool,
     /// After flashing, read back all the flashed data to verify it has been written correctly.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub verify: bool,
+    /// Keep the contents of a range while flashing, e.g. factory calibration data. Takes
+    /// `START..END`, `START+SIZE` or the name of a memory region, and can be given multiple times.
+    #[arg(long, value_name = "RANGE", help_heading = "DOWNLOAD CONFIGURATION")]
+    pub preserve: Vec<String>,
+    /// Flash the image exactly as it is, without adding the checksums or boot headers the
+    /// target requires to boot it.
+    #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub skip_image_fixups: bool,
+    /// After flashing, protect the target against reading out its memory. Depending on the
+    /// target, the protection takes effect after a reset.
+    #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub lock: bool,
 }
 
 /// Supported bit-widths for read/write commands (not every device may support each width).
@@ -65,6 +83,28 @@ pub struct ReadWriteOptions {
     /// Takes an integer as an argument, and can be specified in decimal (16), hexadecimal (0x10) or octal (0o20) format.
     #[clap(value_parser = parse_u64)]
     pub address: u64,
+    /// Access the memory with Secure or Non-secure transfers, on cores which implement the
+    /// ARMv8-M Security Extension.
+    #[clap(long, value_enum)]
+    pub memory_security: Option<MemorySecurity>,
+}
+
+/// The security of memory accesses.
+#[derive(Debug, Copy, Clone, clap::ValueEnum)]
+pub enum MemorySecurity {
+    /// Secure transfers
+    Secure,
+    /// Non-secure transfers
+    NonSecure,
+}
+
+impl From<MemorySecurity> for SecurityState {
+    fn from(security: MemorySecurity) -> Self {
+        match security {
+            MemorySecurity::Secure => SecurityState::Secure,
+            MemorySecurity::NonSecure => SecurityState::NonSecure,
+        }
+    }
 }
 
 /// Common options and logic when interfacing with a [Probe].
@@ -119,6 +159,34 @@ pub struct ProbeOptions {
         help_heading = "PROBE CONFIGURATION"
     )]
     pub allow_erase_all: bool,
+    /// Record all operations on the probe to the given file, so problems can be reproduced
+    /// without the hardware with --replay.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["replay", "dry_run"],
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub record: Option<PathBuf>,
+    /// Replay a recording made with --record instead of using a probe.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["probe", "dry_run"],
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub replay: Option<PathBuf>,
+    /// Simulate the chip selected with --chip instead of using a probe.
+    ///
+    /// Only Cortex-M chips can be simulated. Calls of the flash algorithms are emulated, and
+    /// all other code is run by a simple executor, which only supports ARMv6-M instructions.
+    #[arg(
+        long,
+        requires = "chip",
+        conflicts_with_all = ["probe", "dry_run", "replay"],
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub simulate: bool,
 }
 
 impl ProbeOptions {
@@ -189,7 +257,7 @@ impl LoadedProbeOptions {
                 }
             })?;
 
-            TargetSelector::Specified(target)
+            TargetSelector::Specified(Box::new(target))
         } else {
             TargetSelector::Auto
         };
@@ -239,8 +307,19 @@ impl LoadedProbeOptions {
 
     /// Attaches to specified probe and configures it.
     pub fn attach_probe(&self, lister: &Lister) -> Result<Probe, OperationError> {
-        let mut probe = if self.0.dry_run {
+        let probe = if self.0.dry_run {
             Probe::from_specific_probe(Box::new(FakeProbe::with_mocked_core()))
+        } else if let Some(path) = &self.0.replay {
+            let probe = ReplayProbe::open(path).map_err(DebugProbeError::from)?;
+            Probe::from_specific_probe(Box::new(probe))
+        } else if self.0.simulate {
+            let TargetSelector::Specified(target) = self.get_target_selector()? else {
+                return Err(DebugProbeError::Other(
+                    "A chip has to be selected to simulate it".to_string(),
+                )
+                .into());
+            };
+            Probe::from_specific_probe(Box::new(FakeProbe::with_simulated_target(&target)?))
         } else {
             // If we got a probe selector as an argument, open the probe
             // matching the selector if possible.
@@ -250,6 +329,35 @@ impl LoadedProbeOptions {
             }
         };
 
+        self.configure_probe(probe)
+    }
+
+    /// Opens the probe described by `info`, and configures it like [`Self::attach_probe`].
+    pub fn open_probe(
+        &self,
+        lister: &Lister,
+        info: &DebugProbeInfo,
+    ) -> Result<Probe, OperationError> {
+        let probe = lister.open(info)?;
+
+        self.configure_probe(probe)
+    }
+
+    /// Records the probe if requested, and selects the protocol and speed given in [ProbeOptions].
+    fn configure_probe(&self, probe: Probe) -> Result<Probe, OperationError> {
+        let mut probe = match &self.0.record {
+            Some(path) => {
+                let error = |source| OperationError::FailedToCreateRecording {
+                    source,
+                    path: path.clone(),
+                };
+                let file = File::create(path).map_err(error)?;
+                let probe = RecordingProbe::new(probe, file).map_err(error)?;
+                Probe::from_specific_probe(Box::new(probe))
+            }
+            None => probe,
+        };
+
         if let Some(protocol) = self.0.protocol {
             // Select protocol and speed
             probe.select_protocol(protocol).map_err(|error| {
@@ -294,11 +402,17 @@ impl LoadedProbeOptions {
         probe: Probe,
         target: TargetSelector,
     ) -> Result<Session, OperationError> {
-        let mut permissions = Permissions::new();
-        if self.0.allow_erase_all {
-            permissions = permissions.allow_erase_all();
-        }
+        self.attach_session_with_permissions(probe, target, self.permissions())
+    }
 
+    /// Attaches to target device session with the given permissions, instead of the
+    /// ones selected by [ProbeOptions].
+    pub fn attach_session_with_permissions(
+        &self,
+        probe: Probe,
+        target: TargetSelector,
+        permissions: Permissions,
+    ) -> Result<Session, OperationError> {
         let session = if self.0.connect_under_reset {
             probe.attach_under_reset(target, permissions)
         } else {
@@ -312,6 +426,15 @@ impl LoadedProbeOptions {
         Ok(session)
     }
 
+    /// The session permissions selected by [ProbeOptions].
+    pub(crate) fn permissions(&self) -> Permissions {
+        let mut permissions = Permissions::new();
+        if self.0.allow_erase_all {
+            permissions = permissions.allow_erase_all();
+        }
+        permissions
+    }
+
     pub(crate) fn protocol(&self) -> Option<WireProtocol> {
         self.0.protocol
     }
@@ -462,7 +585,6 @@ pub enum OperationError {
     },
 
     #[error("Failed to load the ELF data.")]
-    #[allow(dead_code)]
     FailedToLoadElfData(#[source] FileDownloadError),
 
     #[error("Failed to open the debug probe.")]
@@ -535,11 +657,18 @@ pub enum OperationError {
 
     #[error("Failed to write to file")]
     IOError(#[source] std::io::Error),
+    #[error("Failed to create the probe recording '{path}'.")]
+    FailedToCreateRecording {
+        source: std::io::Error,
+        path: PathBuf,
+    },
 
     #[error("Failed to parse CLI arguments.")]
     CliArgument(#[from] clap::Error),
     #[error("Failed to parse interactive probe index selection")]
     ParseProbeIndex(#[source] std::num::ParseIntError),
+    #[error("'{0}' is neither an address range nor the name of a memory region of the target.")]
+    InvalidPreservedRange(String),
 }
 
 /// Used in errors it can print a list of items.
@@ -2,19 +2,24 @@ use crate::FormatOptions;
 
 use super::common_options::{BinaryDownloadOptions, LoadedProbeOptions, OperationError};
 use super::logging;
+use super::parse_u64;
 
 use std::cell::RefCell;
+use std::ops::Range;
 use std::time::Duration;
 use std::{path::Path, time::Instant};
 
 use colored::Colorize;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use probe_rs::config::MemoryRegion;
 use probe_rs::flashing::FlashLayout;
-use probe_rs::InstructionSet;
 use probe_rs::{
-    flashing::{DownloadOptions, FileDownloadError, FlashLoader, FlashProgress, ProgressEvent},
+    flashing::{
+        DownloadOptions, FileDownloadError, FlashBundle, FlashLoader, FlashProgress, ProgressEvent,
+    },
     Session,
 };
+use probe_rs::{InstructionSet, Target};
 
 /// Performs the flash download with the given loader. Ensure that the loader has the data to load already stored.
 /// This function also manages the update and display of progress bars.
@@ -26,13 +31,12 @@ pub fn run_flash_download(
     loader: FlashLoader,
     do_chip_erase: bool,
 ) -> Result<(), OperationError> {
-    let mut options = DownloadOptions::default();
-    options.keep_unwritten_bytes = download_options.restore_unwritten;
-    options.dry_run = probe_options.dry_run();
-    options.do_chip_erase = do_chip_erase;
-    options.disable_double_buffering = download_options.disable_double_buffering;
-    options.verify = download_options.verify;
-    options.preverify = download_options.preverify;
+    let mut options = build_download_options(
+        session.target(),
+        download_options,
+        probe_options,
+        do_chip_erase,
+    )?;
 
     if !download_options.disable_progressbars {
         // Create progress bars.
@@ -111,6 +115,7 @@ pub fn run_flash_download(
                     progress_bars.program.inc(size as u64);
                 }
                 ProgressEvent::SectorErased { size, .. } => progress_bars.erase.inc(size),
+                ProgressEvent::SectorSkipped { size, .. } => progress_bars.erase.inc(size),
                 ProgressEvent::PageFilled { size, .. } => progress_bars.fill.inc(size),
                 ProgressEvent::FailedErasing => {
                     progress_bars.erase.abandon();
@@ -152,6 +157,70 @@ pub fn run_flash_download(
     Ok(())
 }
 
+/// Converts the command line options into the [DownloadOptions] of the library, without a
+/// progress reporter.
+pub fn build_download_options(
+    target: &Target,
+    download_options: &BinaryDownloadOptions,
+    probe_options: &LoadedProbeOptions,
+    do_chip_erase: bool,
+) -> Result<DownloadOptions, OperationError> {
+    let mut options = DownloadOptions::default();
+    options.keep_unwritten_bytes = download_options.restore_unwritten;
+    options.dry_run = probe_options.dry_run();
+    options.do_chip_erase = do_chip_erase;
+    options.disable_double_buffering = download_options.disable_double_buffering;
+    options.verify = download_options.verify;
+    options.preverify = download_options.preverify;
+    options.skip_unchanged_sectors = download_options.skip_unchanged_sectors;
+    options.preserve = download_options
+        .preserve
+        .iter()
+        .map(|spec| parse_preserved_range(spec, target))
+        .collect::<Result<_, _>>()?;
+    options.skip_image_fixups = download_options.skip_image_fixups;
+    options.lock = download_options.lock;
+
+    Ok(options)
+}
+
+/// Resolves a range to preserve while flashing, given as `START..END`, `START+SIZE` or the name
+/// of a memory region of `target`.
+pub fn parse_preserved_range(spec: &str, target: &Target) -> Result<Range<u64>, OperationError> {
+    let invalid = || OperationError::InvalidPreservedRange(spec.to_string());
+    let parse = |value: &str| parse_u64(value.trim()).map_err(|_| invalid());
+
+    if let Some((start, end)) = spec.split_once("..") {
+        let range = parse(start)?..parse(end)?;
+        return if range.is_empty() {
+            Err(invalid())
+        } else {
+            Ok(range)
+        };
+    }
+    if let Some((start, size)) = spec.split_once('+') {
+        let start = parse(start)?;
+        return match start.checked_add(parse(size)?) {
+            Some(end) if end > start => Ok(start..end),
+            _ => Err(invalid()),
+        };
+    }
+
+    target
+        .memory_map
+        .iter()
+        .find(|region| {
+            let name = match region {
+                MemoryRegion::Ram(region) => &region.name,
+                MemoryRegion::Nvm(region) => &region.name,
+                MemoryRegion::Generic(region) => &region.name,
+            };
+            name.as_deref() == Some(spec)
+        })
+        .map(MemoryRegion::address_range)
+        .ok_or_else(invalid)
+}
+
 /// Builds a new flash loader for the given target and path. This
 /// will check the path for validity and check what pages have to be
 /// flashed etc.
@@ -161,11 +230,29 @@ pub fn build_loader(
     format_options: FormatOptions,
     image_instruction_set: Option<InstructionSet>,
 ) -> Result<FlashLoader, FileDownloadError> {
-    let format = format_options.into_format(session.target());
+    let format = format_options.into_format(session.target(), path.as_ref());
 
     probe_rs::flashing::build_loader(session, path, format, image_instruction_set)
 }
 
+/// Builds a flash loader like [`build_loader`], but if the path points to a bundle manifest,
+/// all images of the bundle are loaded, and the format options are ignored.
+///
+/// Only commands which just flash the images accept bundles, the others need a single image,
+/// e.g. to load its debug information.
+pub fn build_bundle_or_loader(
+    session: &mut Session,
+    path: impl AsRef<Path>,
+    format_options: FormatOptions,
+    image_instruction_set: Option<InstructionSet>,
+) -> Result<FlashLoader, FileDownloadError> {
+    if FlashBundle::is_manifest(path.as_ref()) {
+        return FlashBundle::from_path(path)?.build_loader(session);
+    }
+
+    build_loader(session, path, format_options, image_instruction_set)
+}
+
 struct ProgressBars {
     erase: ProgressBarGroup,
     fill: ProgressBarGroup,
@@ -266,3 +353,21 @@ impl ProgressBarGroup {
         self.append_phase = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserved_ranges() {
+        let target = probe_rs::config::get_target_by_name("nrf52840_xxAA").unwrap();
+
+        let range = parse_preserved_range("0x1000..0x2000", &target).unwrap();
+        assert_eq!(range, 0x1000..0x2000);
+        let range = parse_preserved_range("0xFF000+0x1000", &target).unwrap();
+        assert_eq!(range, 0xFF000..0x10_0000);
+
+        assert!(parse_preserved_range("0x2000..0x1000", &target).is_err());
+        assert!(parse_preserved_range("CALIBRATION", &target).is_err());
+    }
+}
@@ -80,6 +80,27 @@ impl super::MemoryApType for AmbaAhb3 {
         Ok(())
     }
 
+    fn try_set_nonsecure<P: ApAccess + ?Sized>(
+        &mut self,
+        probe: &mut P,
+        nonsecure: bool,
+    ) -> Result<(), ArmError> {
+        if !nonsecure && !self.csw.SPIDEN {
+            return Err(ArmError::MissingPermissions(
+                "Secure accesses are not enabled (SPIDEN is low)".to_string(),
+            ));
+        }
+        if nonsecure != self.csw.HNONSEC {
+            let csw = CSW {
+                HNONSEC: nonsecure,
+                ..self.csw
+            };
+            probe.write_ap_register(self, csw)?;
+            self.csw = csw;
+        }
+        Ok(())
+    }
+
     fn has_large_address_extension(&self) -> bool {
         self.cfg.LA
     }
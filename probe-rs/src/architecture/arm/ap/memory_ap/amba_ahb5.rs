@@ -79,6 +79,27 @@ impl super::MemoryApType for AmbaAhb5 {
         Ok(())
     }
 
+    fn try_set_nonsecure<P: ApAccess + ?Sized>(
+        &mut self,
+        probe: &mut P,
+        nonsecure: bool,
+    ) -> Result<(), ArmError> {
+        if !nonsecure && !self.csw.SPIDEN {
+            return Err(ArmError::MissingPermissions(
+                "Secure accesses are not enabled (SPIDEN is low)".to_string(),
+            ));
+        }
+        if nonsecure != self.csw.HNONSEC {
+            let csw = CSW {
+                HNONSEC: nonsecure,
+                ..self.csw
+            };
+            probe.write_ap_register(self, csw)?;
+            self.csw = csw;
+        }
+        Ok(())
+    }
+
     fn has_large_address_extension(&self) -> bool {
         self.cfg.LA
     }
@@ -193,3 +214,54 @@ define_ap_register!(
     | (value.Size as u32)
     | value._reserved_bits
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::arm::ap::memory_ap::{mock::MockMemoryAp, MemoryApType};
+
+    const SPIDEN: u32 = 1 << 23;
+    const HNONSEC: u32 = 1 << 30;
+
+    fn access_port(csw: u32) -> (MockMemoryAp, AmbaAhb5) {
+        let address = FullyQualifiedApAddress::v1_with_default_dp(0);
+        let mut probe = MockMemoryAp::with_pattern_and_size(0);
+        probe
+            .write_raw_ap_register(&address, CSW::ADDRESS, csw)
+            .unwrap();
+        let ap = AmbaAhb5::new(&mut probe, address).unwrap();
+        (probe, ap)
+    }
+
+    fn csw(probe: &mut MockMemoryAp, ap: &AmbaAhb5) -> u32 {
+        probe
+            .read_raw_ap_register(ap.ap_address(), CSW::ADDRESS)
+            .unwrap()
+    }
+
+    #[test]
+    fn secure_transfers_are_selected_if_enabled() {
+        let (mut probe, mut ap) = access_port(SPIDEN);
+        assert_eq!(csw(&mut probe, &ap) & HNONSEC, 0);
+
+        ap.try_set_nonsecure(&mut probe, true).unwrap();
+        assert_eq!(csw(&mut probe, &ap) & HNONSEC, HNONSEC);
+
+        ap.try_set_nonsecure(&mut probe, false).unwrap();
+        assert_eq!(csw(&mut probe, &ap) & HNONSEC, 0);
+    }
+
+    #[test]
+    fn secure_transfers_are_rejected_without_spiden() {
+        let (mut probe, mut ap) = access_port(0);
+        assert_eq!(csw(&mut probe, &ap) & HNONSEC, HNONSEC);
+
+        assert!(matches!(
+            ap.try_set_nonsecure(&mut probe, false),
+            Err(ArmError::MissingPermissions(_))
+        ));
+        assert_eq!(csw(&mut probe, &ap) & HNONSEC, HNONSEC);
+
+        ap.try_set_nonsecure(&mut probe, true).unwrap();
+    }
+}
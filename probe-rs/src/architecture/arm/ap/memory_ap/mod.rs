@@ -72,6 +72,20 @@ pub trait MemoryApType:
         data_size: DataSize,
     ) -> Result<(), ArmError>;
 
+    /// Attempts to select whether the transfers are Secure or Non-secure transfers.
+    ///
+    /// The operation fails if the Memory Access Port does not support the selection, or if
+    /// Secure transfers are requested but not enabled.
+    fn try_set_nonsecure<I: ApAccess>(
+        &mut self,
+        _interface: &mut I,
+        _nonsecure: bool,
+    ) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented(
+            "selecting the security of transfers on this access port",
+        ))
+    }
+
     /// The current generic CSW (missing the memory AP specific fields).
     fn generic_status<I: ApAccess>(
         &mut self,
@@ -250,6 +264,14 @@ impl MemoryApType for MemoryAp {
         mem_ap_forward!(self, try_set_datasize(interface, data_size))
     }
 
+    fn try_set_nonsecure<I: ApAccess>(
+        &mut self,
+        interface: &mut I,
+        nonsecure: bool,
+    ) -> Result<(), ArmError> {
+        mem_ap_forward!(self, try_set_nonsecure(interface, nonsecure))
+    }
+
     fn status<I: ApAccess>(&mut self, interface: &mut I) -> Result<Self::CSW, ArmError> {
         mem_ap_forward!(self, generic_status(interface))
     }
@@ -24,7 +24,9 @@ use std::{
 
 /// An error in the communication with an access port or
 /// debug port.
-#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq, Copy)]
+#[derive(
+    Debug, thiserror::Error, Clone, PartialEq, Eq, Copy, serde::Serialize, serde::Deserialize,
+)]
 pub enum DapError {
     /// An error occurred during SWD communication.
     #[error("An error occurred in the SWD communication between probe and device.")]
@@ -247,7 +249,13 @@ impl<S: ArmDebugState> ArmCommunicationInterface<S> {
 ///
 /// This is used to combine the traits, because it cannot be done in the ArmCommunicationInterface
 /// struct itself.
-pub trait DapProbe: RawDapAccess + DebugProbe {}
+pub trait DapProbe: RawDapAccess + DebugProbe {
+    /// Whether the overrun detection of the debug port is used when the probe is accessed
+    /// through an [`ArmCommunicationInterface`].
+    fn use_overrun_detect(&self) -> bool {
+        true
+    }
+}
 
 impl ArmProbeInterface for ArmCommunicationInterface<Initialized> {
     fn memory_interface(
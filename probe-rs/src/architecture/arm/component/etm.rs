@@ -0,0 +1,208 @@
+//! Module for using the ETM and PTM.
+//!
+//! ETM = Embedded Trace Macrocell, PTM = Program Trace Macrocell
+
+use std::time::{Duration, Instant};
+
+use crate::architecture::arm::{
+    etm::TraceProtocol, memory::romtable::CoresightComponent, ArmError, ArmProbeInterface,
+};
+use crate::Error;
+
+const REGISTER_OFFSET_ACCESS: u32 = 0xFB0;
+
+/// ETMIDR for ETMv3 and PTM, TRCIDR1 for ETMv4. Both contain the major architecture version in bits [11:8].
+const REGISTER_OFFSET_ID: u32 = 0x1E4;
+
+// ETMv3 and PTM registers.
+const REGISTER_OFFSET_ETMCR: u32 = 0x000;
+const REGISTER_OFFSET_ETMSR: u32 = 0x010;
+const REGISTER_OFFSET_ETMTEEVR: u32 = 0x020;
+const REGISTER_OFFSET_ETMTECR1: u32 = 0x024;
+const REGISTER_OFFSET_ETMSYNCFR: u32 = 0x1E0;
+const REGISTER_OFFSET_ETMTRACEIDR: u32 = 0x200;
+
+// ETMv4 registers.
+const REGISTER_OFFSET_TRCPRGCTLR: u32 = 0x004;
+const REGISTER_OFFSET_TRCSTATR: u32 = 0x00C;
+const REGISTER_OFFSET_TRCCONFIGR: u32 = 0x010;
+const REGISTER_OFFSET_TRCEVENTCTL0R: u32 = 0x020;
+const REGISTER_OFFSET_TRCEVENTCTL1R: u32 = 0x024;
+const REGISTER_OFFSET_TRCSTALLCTLR: u32 = 0x02C;
+const REGISTER_OFFSET_TRCTSCTLR: u32 = 0x030;
+const REGISTER_OFFSET_TRCSYNCPR: u32 = 0x034;
+const REGISTER_OFFSET_TRCTRACEIDR: u32 = 0x040;
+const REGISTER_OFFSET_TRCVICTLR: u32 = 0x080;
+const REGISTER_OFFSET_TRCVIIECTLR: u32 = 0x084;
+const REGISTER_OFFSET_TRCVISSCTLR: u32 = 0x088;
+const REGISTER_OFFSET_TRCOSLAR: u32 = 0x300;
+
+/// The ETMCR programming bit, which has to be set while the ETM is configured.
+const ETMCR_PROGRAMMING: u32 = 1 << 10;
+/// The ETMCR power down bit.
+const ETMCR_POWER_DOWN: u32 = 1 << 0;
+/// The ETMCR port selection bit, which enables the trace output of Cortex-M ETMs.
+const ETMCR_PORT_SELECTION: u32 = 1 << 11;
+
+/// An interface to control the ETM (Embedded Trace Macrocell) or PTM (Program Trace Macrocell) of a core.
+///
+/// The ETM traces the program flow of its core, and emits it as a stream of packets, which can be
+/// decoded with [`crate::architecture::arm::etm`].
+pub struct Etm<'a> {
+    component: &'a CoresightComponent,
+    interface: &'a mut dyn ArmProbeInterface,
+}
+
+impl<'a> Etm<'a> {
+    /// Create a new ETM interface from a probe and a ROM table component.
+    pub fn new(
+        interface: &'a mut dyn ArmProbeInterface,
+        component: &'a CoresightComponent,
+    ) -> Self {
+        Etm {
+            component,
+            interface,
+        }
+    }
+
+    /// Unlock the ETM registers for writing.
+    pub fn unlock(&mut self) -> Result<(), Error> {
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_ACCESS, 0xC5AC_CE55)?;
+
+        Ok(())
+    }
+
+    /// Determine the trace protocol which the ETM implements.
+    pub fn protocol(&mut self) -> Result<TraceProtocol, Error> {
+        let id = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_ID)?;
+
+        match (id >> 8) & 0xF {
+            2 => Ok(TraceProtocol::EtmV3),
+            3 => Ok(TraceProtocol::Ptm),
+            4 => Ok(TraceProtocol::EtmV4),
+            version => Err(Error::Other(format!(
+                "Unsupported ETM architecture version {version} (ID register {id:#010x})"
+            ))),
+        }
+    }
+
+    /// Configure the ETM to trace all executed instructions, and enable it.
+    ///
+    /// The trace is emitted with the trace source ID `trace_id`, without cycle counts, timestamps
+    /// or context IDs.
+    pub fn enable_instruction_trace(&mut self, trace_id: u8) -> Result<(), Error> {
+        match self.protocol()? {
+            TraceProtocol::EtmV3 | TraceProtocol::Ptm => self.enable_etmv3(trace_id),
+            TraceProtocol::EtmV4 => self.enable_etmv4(trace_id),
+        }
+    }
+
+    /// Disable the ETM, which stops the trace output.
+    pub fn disable(&mut self) -> Result<(), Error> {
+        match self.protocol()? {
+            TraceProtocol::EtmV3 | TraceProtocol::Ptm => {
+                // The ETM does not trace while the programming bit is set.
+                let control = self.read(REGISTER_OFFSET_ETMCR)?;
+                self.write(
+                    REGISTER_OFFSET_ETMCR,
+                    (control | ETMCR_PROGRAMMING) & !ETMCR_PORT_SELECTION,
+                )?;
+            }
+            TraceProtocol::EtmV4 => {
+                self.write(REGISTER_OFFSET_TRCPRGCTLR, 0)?;
+                self.wait_for_etmv4_idle(true)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn enable_etmv3(&mut self, trace_id: u8) -> Result<(), Error> {
+        self.unlock()?;
+
+        // Power up the ETM and enter programming mode.
+        let control = self.read(REGISTER_OFFSET_ETMCR)?;
+        self.write(
+            REGISTER_OFFSET_ETMCR,
+            (control | ETMCR_PROGRAMMING) & !ETMCR_POWER_DOWN,
+        )?;
+        self.wait_for(|etm| Ok(etm.read(REGISTER_OFFSET_ETMSR)? & (1 << 1) != 0))?;
+
+        self.write(REGISTER_OFFSET_ETMTRACEIDR, u32::from(trace_id))?;
+        // Trace enable event: always true (resource 0x6F, function "A").
+        self.write(REGISTER_OFFSET_ETMTEEVR, 0x6F)?;
+        // Trace enable control: exclude nothing, i.e. trace everything.
+        self.write(REGISTER_OFFSET_ETMTECR1, 0x0100_0000)?;
+        // Synchronize every 256 bytes, so that the latest trace in a circular buffer can be decoded.
+        // This register is read-only on some implementations.
+        self.write(REGISTER_OFFSET_ETMSYNCFR, 0x100)?;
+
+        // Disable all optional trace features, enable the trace port and leave programming mode.
+        self.write(
+            REGISTER_OFFSET_ETMCR,
+            ETMCR_PROGRAMMING | ETMCR_PORT_SELECTION,
+        )?;
+        self.write(REGISTER_OFFSET_ETMCR, ETMCR_PORT_SELECTION)?;
+        self.wait_for(|etm| Ok(etm.read(REGISTER_OFFSET_ETMSR)? & (1 << 1) == 0))?;
+
+        Ok(())
+    }
+
+    fn enable_etmv4(&mut self, trace_id: u8) -> Result<(), Error> {
+        self.unlock()?;
+        self.write(REGISTER_OFFSET_TRCOSLAR, 0)?;
+
+        self.write(REGISTER_OFFSET_TRCPRGCTLR, 0)?;
+        self.wait_for_etmv4_idle(true)?;
+
+        // No branch broadcasting, cycle counting, timestamps or context IDs.
+        self.write(REGISTER_OFFSET_TRCCONFIGR, 0)?;
+        self.write(REGISTER_OFFSET_TRCEVENTCTL0R, 0)?;
+        self.write(REGISTER_OFFSET_TRCEVENTCTL1R, 0)?;
+        self.write(REGISTER_OFFSET_TRCSTALLCTLR, 0)?;
+        self.write(REGISTER_OFFSET_TRCTSCTLR, 0)?;
+        // Synchronize every 256 bytes, so that the latest trace in a circular buffer can be decoded.
+        self.write(REGISTER_OFFSET_TRCSYNCPR, 8)?;
+        self.write(REGISTER_OFFSET_TRCTRACEIDR, u32::from(trace_id))?;
+
+        // ViewInst: always enabled (resource selector 1 is always true), with the start/stop logic
+        // in the started state and no address range filtering.
+        self.write(REGISTER_OFFSET_TRCVICTLR, 0x201)?;
+        self.write(REGISTER_OFFSET_TRCVIIECTLR, 0)?;
+        self.write(REGISTER_OFFSET_TRCVISSCTLR, 0)?;
+
+        self.write(REGISTER_OFFSET_TRCPRGCTLR, 1)?;
+        self.wait_for_etmv4_idle(false)?;
+
+        Ok(())
+    }
+
+    fn wait_for_etmv4_idle(&mut self, idle: bool) -> Result<(), Error> {
+        self.wait_for(|etm| Ok((etm.read(REGISTER_OFFSET_TRCSTATR)? & 1 != 0) == idle))
+    }
+
+    fn wait_for(
+        &mut self,
+        mut condition: impl FnMut(&mut Self) -> Result<bool, Error>,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        while !condition(self)? {
+            if start.elapsed() > Duration::from_millis(100) {
+                return Err(ArmError::Timeout.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(&mut self, offset: u32) -> Result<u32, ArmError> {
+        self.component.read_reg(self.interface, offset)
+    }
+
+    fn write(&mut self, offset: u32, value: u32) -> Result<(), ArmError> {
+        self.component.write_reg(self.interface, offset, value)
+    }
+}
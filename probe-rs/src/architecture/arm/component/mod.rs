@@ -1,6 +1,7 @@
 //! Types and functions for interacting with CoreSight Components
 
 mod dwt;
+mod etm;
 mod itm;
 mod scs;
 mod swo;
@@ -11,14 +12,16 @@ mod trace_funnel;
 use crate::{
     architecture::arm::{
         core::armv6m::Demcr,
+        etm::TraceProtocol,
         memory::romtable::{CoresightComponent, PeripheralType, RomTableError},
-        ArmError, ArmProbeInterface, DpAddress, SwoConfig, SwoMode,
+        ArmError, ArmProbeInterface, DpAddress, FullyQualifiedApAddress, SwoConfig, SwoMode,
     },
     Core, Error, MemoryInterface, MemoryMappedRegister,
 };
 
 pub use self::itm::Itm;
 pub use dwt::Dwt;
+pub use etm::Etm;
 pub use scs::Scs;
 pub use swo::Swo;
 pub use tmc::TraceMemoryController;
@@ -336,6 +339,136 @@ pub(crate) fn read_trace_memory(
     Ok(itm_trace)
 }
 
+/// The trace source ID (ATID) used for instruction trace, see [`setup_instruction_trace`].
+const ETM_TRACE_ID: u8 = 1;
+
+/// Find the trace buffer which captures instruction trace, either a TMC or an ETB.
+///
+/// Returns the component and whether its RAM pointers address words (ETB) instead of bytes (TMC).
+fn find_trace_buffer(
+    components: &[CoresightComponent],
+) -> Result<(&CoresightComponent, bool), ArmError> {
+    match find_component(components, PeripheralType::Tmc) {
+        Ok(tmc) => Ok((tmc, false)),
+        Err(_) => Ok((find_component(components, PeripheralType::Etb)?, true)),
+    }
+}
+
+/// Find the ETM of a core, which is accessed through the access port `core_ap`.
+///
+/// The ETMs behind an access port are assigned to the cores sharing it in the order in which
+/// they appear in the ROM table, so `core_position` is the position of the core among the
+/// cores which are accessed through `core_ap`.
+fn find_etm<'a>(
+    components: &'a [CoresightComponent],
+    core_ap: &FullyQualifiedApAddress,
+    core_position: usize,
+) -> Result<&'a CoresightComponent, ArmError> {
+    let etm = components
+        .iter()
+        .flat_map(CoresightComponent::iter)
+        .filter(|component| {
+            &component.ap_address == core_ap
+                && component
+                    .component
+                    .id()
+                    .peripheral_id()
+                    .is_of_type(PeripheralType::Etm)
+        })
+        .nth(core_position)
+        .ok_or_else(|| RomTableError::ComponentNotFound(PeripheralType::Etm))?;
+
+    Ok(etm)
+}
+
+/// Wait until the trace buffer has stopped capturing and drained its pipelines.
+fn wait_for_trace_buffer(tmc: &mut TraceMemoryController) -> Result<(), Error> {
+    let start = std::time::Instant::now();
+    while !tmc.ready()? {
+        if start.elapsed() > std::time::Duration::from_millis(100) {
+            return Err(ArmError::Timeout.into());
+        }
+    }
+    Ok(())
+}
+
+/// Sets up the ETM for instruction trace into the on-chip trace buffer (ETB or ETF).
+///
+/// The trace buffer is used as a circular buffer, so that it always contains the most recent
+/// trace when the capture is stopped.
+///
+/// Expects to be given a list of all ROM table `components` as the second argument. The ETM
+/// of the traced core is selected like described in [`find_etm`].
+///
+/// # Returns
+/// The trace protocol of the ETM, which is needed to decode the trace.
+pub(crate) fn setup_instruction_trace(
+    interface: &mut dyn ArmProbeInterface,
+    components: &[CoresightComponent],
+    core_ap: &FullyQualifiedApAddress,
+    core_position: usize,
+) -> Result<TraceProtocol, Error> {
+    let (buffer, word_pointers) = find_trace_buffer(components)?;
+    let mut tmc = TraceMemoryController::new(interface, buffer);
+
+    tmc.disable_capture()?;
+    wait_for_trace_buffer(&mut tmc)?;
+
+    // An ETB always operates as a circular buffer, and has no mode register.
+    if !word_pointers {
+        tmc.set_mode(tmc::Mode::Circular)?;
+    }
+    tmc.enable_formatter()?;
+    tmc.reset_write_pointer()?;
+    tmc.enable_capture()?;
+
+    let mut etm = Etm::new(interface, find_etm(components, core_ap, core_position)?);
+    let protocol = etm.protocol()?;
+    etm.enable_instruction_trace(ETM_TRACE_ID)?;
+
+    Ok(protocol)
+}
+
+/// Stop the instruction trace capture, and read the trace of the ETM from the trace buffer.
+///
+/// The trace buffer is flushed before the capture stops, so that it contains all trace data
+/// that was generated. The capture has to be set up again with [`setup_instruction_trace`]
+/// to continue tracing.
+///
+/// # Returns
+/// The raw trace data of the ETM, oldest data first.
+pub(crate) fn read_instruction_trace(
+    interface: &mut dyn ArmProbeInterface,
+    components: &[CoresightComponent],
+) -> Result<Vec<u8>, Error> {
+    let (buffer, word_pointers) = find_trace_buffer(components)?;
+    let mut tmc = TraceMemoryController::new(interface, buffer);
+
+    // Flush the trace pipeline into the buffer and stop the capture afterwards.
+    tmc.stop_on_flush(true)?;
+    tmc.manual_flush()?;
+    wait_for_trace_buffer(&mut tmc)?;
+    tmc.disable_capture()?;
+
+    let formatted = tmc.read_circular_buffer(word_pointers)?;
+
+    // The buffer may have wrapped around in the middle of a frame sequence, so the source ID of
+    // the data before the first ID change is unknown, and the data is discarded.
+    let mut id = 0.into();
+    let mut trace = Vec::new();
+    for frame_buffer in formatted.chunks_exact(16) {
+        let mut frame = tmc::Frame::new(frame_buffer, id);
+        for (id, data) in &mut frame {
+            if u8::from(id) == ETM_TRACE_ID {
+                trace.push(data);
+            }
+        }
+        id = frame.id();
+    }
+
+    Ok(trace)
+}
+
 /// Configures DWT trace unit `unit` to begin tracing `address`.
 ///
 ///
@@ -12,6 +12,8 @@ use crate::{
 
 const REGISTER_OFFSET_RSZ: u32 = 0x04;
 const REGISTER_OFFSET_RRD: u32 = 0x10;
+const REGISTER_OFFSET_RRP: u32 = 0x14;
+const REGISTER_OFFSET_RWP: u32 = 0x18;
 const REGISTER_OFFSET_CTL: u32 = 0x20;
 const REGISTER_OFFSET_CBUFLVL: u32 = 0x30;
 
@@ -153,6 +155,65 @@ impl<'a> TraceMemoryController<'a> {
             .read_reg(self.interface, REGISTER_OFFSET_RSZ)?;
         Ok(size_words * core::mem::size_of::<u32>() as u32)
     }
+
+    /// Enable the formatter, which is required to separate trace data from multiple sources, and
+    /// insert a trigger marker into the trace stream on trigger events.
+    pub fn enable_formatter(&mut self) -> Result<(), Error> {
+        let mut ffcr = FormatFlushControl::load(self.component, self.interface)?;
+        ffcr.set_enft(true);
+        ffcr.set_enti(true);
+        ffcr.store(self.component, self.interface)?;
+        Ok(())
+    }
+
+    /// Reset the write pointer, so that the trace buffer is empty when the capture starts.
+    pub fn reset_write_pointer(&mut self) -> Result<(), Error> {
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_RWP, 0)?;
+        Ok(())
+    }
+
+    /// Read the contents of the trace buffer in circular buffer mode, oldest data first.
+    ///
+    /// The capture must be stopped before the buffer can be read.
+    ///
+    /// This also supports the Embedded Trace Buffer (ETB), which has the same register layout,
+    /// but uses word instead of byte addresses for its RAM pointers.
+    ///
+    /// # Args
+    /// * `word_pointers` - Specified true if the RAM pointers address words instead of bytes.
+    pub fn read_circular_buffer(&mut self, word_pointers: bool) -> Result<Vec<u8>, Error> {
+        let size = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_RSZ)?;
+        let pointer_unit = if word_pointers { 1 } else { 4 };
+
+        let write_pointer = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_RWP)?
+            / pointer_unit;
+        let status = Status::load(self.component, self.interface)?;
+
+        // If the buffer has wrapped around, the oldest data is at the write pointer.
+        let (start, words) = if status.full() {
+            (write_pointer, size)
+        } else {
+            (0, write_pointer)
+        };
+
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_RRP, start * pointer_unit)?;
+
+        let mut data = Vec::with_capacity(words as usize * 4);
+        for _ in 0..words {
+            let word = self
+                .component
+                .read_reg(self.interface, REGISTER_OFFSET_RRD)?;
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+
+        Ok(data)
+    }
 }
 
 memory_mapped_bitfield_register! {
@@ -1,13 +1,13 @@
 //! Register types and the core interface for armv6-M
 
-use super::{registers::cortex_m::*, CortexMState, Dfsr};
+use super::{cortex_m::DwtModel, registers::cortex_m::*, CortexMState, Dfsr};
 use crate::{
     architecture::arm::{memory::ArmMemoryInterface, sequences::ArmDebugSequence, ArmError},
     core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
     error::Error,
     memory::{valid_32bit_address, CoreMemoryInterface},
     Architecture, BreakpointCause, CoreInformation, CoreInterface, CoreRegister, CoreStatus,
-    CoreType, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister,
+    CoreType, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister, Watchpoint,
 };
 use bitfield::bitfield;
 use std::{
@@ -516,6 +516,13 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
             self.memory
                 .write_word_32(Dfsr::get_mmio_address(), Dfsr::clear_all().into())?;
 
+            // The DFSR only tells us that a watchpoint was hit, the DWT knows which one.
+            if reason == HaltReason::Watchpoint(None) {
+                let watchpoint =
+                    super::cortex_m::triggered_watchpoint(&mut *self.memory, DwtModel::Armv7m)?;
+                reason = HaltReason::Watchpoint(watchpoint);
+            }
+
             // If the core was halted before, we cannot read the halt reason from the chip,
             // because we clear it directly after reading.
             if self.state.current_state.is_halted() {
@@ -608,6 +615,10 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
 
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv6m, None)?;
+
+        // Debug sequences may use the DWT comparators while resetting.
+        self.state.watchpoints = None;
+
         Ok(())
     }
 
@@ -629,6 +640,9 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
 
         self.reset_catch_clear()?;
 
+        // Debug sequences may use the DWT comparators while resetting.
+        self.state.watchpoints = None;
+
         // try to read the program counter
         let pc_value = self.read_core_reg(self.program_counter().into())?;
 
@@ -792,6 +806,42 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(super::cortex_m::available_watchpoint_units(
+            &mut *self.memory,
+        )?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(super::cortex_m::hw_watchpoints(
+            &mut *self.memory,
+            DwtModel::Armv7m,
+            &mut self.state.watchpoints,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            DwtModel::Armv7m,
+            &mut self.state.watchpoints,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        Ok(super::cortex_m::clear_hw_watchpoint(
+            &mut *self.memory,
+            &mut self.state.watchpoints,
+            unit_index,
+        )?)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         &CORTEX_M_CORE_REGISTERS
     }
@@ -1,7 +1,7 @@
 //! Register types and the core interface for armv7-M
 
 use super::{
-    cortex_m::Mvfr0,
+    cortex_m::{DwtModel, Mvfr0},
     registers::cortex_m::{
         CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS, FP, PC, RA, SP,
     },
@@ -18,7 +18,7 @@ use crate::{
     },
     error::Error,
     memory::{valid_32bit_address, CoreMemoryInterface},
-    BreakpointCause, CoreRegister, CoreType, InstructionSet, MemoryInterface,
+    BreakpointCause, CoreRegister, CoreType, InstructionSet, MemoryInterface, Watchpoint,
 };
 use bitfield::bitfield;
 use std::{
@@ -695,6 +695,13 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
             self.memory
                 .write_word_32(Dfsr::get_mmio_address(), Dfsr::clear_all().into())?;
 
+            // The DFSR only tells us that a watchpoint was hit, the DWT knows which one.
+            if reason == HaltReason::Watchpoint(None) {
+                let watchpoint =
+                    super::cortex_m::triggered_watchpoint(&mut *self.memory, DwtModel::Armv7m)?;
+                reason = HaltReason::Watchpoint(watchpoint);
+            }
+
             // If the core was halted before, we cannot read the halt reason from the chip,
             // because we clear it directly after reading.
             if self.state.current_state.is_halted() {
@@ -798,6 +805,10 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
 
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv7m, None)?;
+
+        // Debug sequences may use the DWT comparators while resetting.
+        self.state.watchpoints = None;
+
         Ok(())
     }
 
@@ -821,6 +832,9 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
 
         self.reset_catch_clear()?;
 
+        // Debug sequences may use the DWT comparators while resetting.
+        self.state.watchpoints = None;
+
         // try to read the program counter
         let pc_value = self.read_core_reg(self.program_counter().into())?;
 
@@ -1018,6 +1032,42 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(super::cortex_m::available_watchpoint_units(
+            &mut *self.memory,
+        )?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(super::cortex_m::hw_watchpoints(
+            &mut *self.memory,
+            DwtModel::Armv7m,
+            &mut self.state.watchpoints,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            DwtModel::Armv7m,
+            &mut self.state.watchpoints,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        Ok(super::cortex_m::clear_hw_watchpoint(
+            &mut *self.memory,
+            &mut self.state.watchpoints,
+            unit_index,
+        )?)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         if self.state.fp_present {
             &CORTEX_M_WITH_FP_CORE_REGISTERS
@@ -132,7 +132,7 @@ impl Edscr {
             // Reset catch.
             0b100111 => HaltReason::Exception,
             // Watchpoint
-            0b101011 => HaltReason::Watchpoint,
+            0b101011 => HaltReason::Watchpoint(None),
             // HLT instruction - causes entry into Debug state.
             0b101111 => HaltReason::Breakpoint(BreakpointCause::Software),
             // Software access to debug register.
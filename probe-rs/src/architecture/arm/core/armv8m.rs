@@ -1,22 +1,24 @@
 //! Register types and the core interface for armv8-M
 
 use super::{
-    cortex_m::{IdPfr1, Mvfr0},
+    cortex_m::{DwtModel, IdPfr1, Mvfr0},
     registers::cortex_m::{
-        CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS, FP, PC, RA, SP,
+        CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS,
+        CORTEX_M_WITH_SECURITY_AND_FP_CORE_REGISTERS, CORTEX_M_WITH_SECURITY_CORE_REGISTERS, FP,
+        PC, RA, SP,
     },
     CortexMState, Dfsr,
 };
 use crate::{
     architecture::arm::{
-        core::registers::cortex_m::XPSR, memory::ArmMemoryInterface, sequences::ArmDebugSequence,
-        ArmError,
+        ap::memory_ap::MemoryApType, core::registers::cortex_m::XPSR, memory::ArmMemoryInterface,
+        sequences::ArmDebugSequence, ArmError,
     },
-    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
+    core::{CoreRegisters, RegisterId, RegisterValue, SecurityState, VectorCatchCondition},
     error::Error,
     memory::{valid_32bit_address, CoreMemoryInterface},
     Architecture, BreakpointCause, CoreInformation, CoreInterface, CoreRegister, CoreStatus,
-    CoreType, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister,
+    CoreType, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister, Watchpoint,
 };
 use bitfield::bitfield;
 use std::{
@@ -68,8 +70,14 @@ impl<'probe> Armv8m<'probe> {
 
             state.current_state = core_state;
             state.fp_present = Mvfr0(memory.read_word_32(Mvfr0::get_mmio_address())?).fp_present();
+            state.security_present =
+                IdPfr1(memory.read_word_32(IdPfr1::get_mmio_address())?).security_present();
 
             state.initialize();
+        } else if let Some(security_state) = state.memory_security_state {
+            // The memory AP is set up again for every new core handle.
+            let (interface, memory_ap) = memory.try_as_parts()?;
+            memory_ap.try_set_nonsecure(interface, security_state == SecurityState::NonSecure)?;
         }
 
         Ok(Self {
@@ -140,6 +148,13 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
             self.memory
                 .write_word_32(Dfsr::get_mmio_address(), Dfsr::clear_all().into())?;
 
+            // The DFSR only tells us that a watchpoint was hit, the DWT knows which one.
+            if reason == HaltReason::Watchpoint(None) {
+                let watchpoint =
+                    super::cortex_m::triggered_watchpoint(&mut *self.memory, DwtModel::Armv8m)?;
+                reason = HaltReason::Watchpoint(watchpoint);
+            }
+
             // If the core was halted before, we cannot read the halt reason from the chip,
             // because we clear it directly after reading.
             if self.state.current_state.is_halted() {
@@ -233,6 +248,10 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
 
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv8m, None)?;
+
+        // Debug sequences may use the DWT comparators while resetting.
+        self.state.watchpoints = None;
+
         Ok(())
     }
 
@@ -256,6 +275,9 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
 
         self.reset_catch_clear()?;
 
+        // Debug sequences may use the DWT comparators while resetting.
+        self.state.watchpoints = None;
+
         // try to read the program counter
         let pc_value = self.read_core_reg(self.program_counter().into())?;
 
@@ -409,11 +431,48 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(super::cortex_m::available_watchpoint_units(
+            &mut *self.memory,
+        )?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(super::cortex_m::hw_watchpoints(
+            &mut *self.memory,
+            DwtModel::Armv8m,
+            &mut self.state.watchpoints,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            DwtModel::Armv8m,
+            &mut self.state.watchpoints,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        Ok(super::cortex_m::clear_hw_watchpoint(
+            &mut *self.memory,
+            &mut self.state.watchpoints,
+            unit_index,
+        )?)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
-        if self.state.fp_present {
-            &CORTEX_M_WITH_FP_CORE_REGISTERS
-        } else {
-            &CORTEX_M_CORE_REGISTERS
+        match (self.state.fp_present, self.state.security_present) {
+            (true, true) => &CORTEX_M_WITH_SECURITY_AND_FP_CORE_REGISTERS,
+            (true, false) => &CORTEX_M_WITH_FP_CORE_REGISTERS,
+            (false, true) => &CORTEX_M_WITH_SECURITY_CORE_REGISTERS,
+            (false, false) => &CORTEX_M_CORE_REGISTERS,
         }
     }
 
@@ -538,6 +597,31 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
             .write_word_32(Demcr::get_mmio_address(), demcr.into())?;
         Ok(())
     }
+
+    fn security_state(&mut self) -> Result<Option<SecurityState>, Error> {
+        if !self.state.security_present {
+            return Ok(None);
+        }
+
+        let dscsr = Dscsr(self.memory.read_word_32(Dscsr::get_mmio_address())?);
+        Ok(Some(if dscsr.cds() {
+            SecurityState::Secure
+        } else {
+            SecurityState::NonSecure
+        }))
+    }
+
+    fn set_memory_security_state(&mut self, state: SecurityState) -> Result<(), Error> {
+        if !self.state.security_present {
+            return Err(Error::Arm(ArmError::ExtensionRequired(&["Security"])));
+        }
+
+        let (interface, memory_ap) = self.memory.try_as_parts()?;
+        memory_ap.try_set_nonsecure(interface, state == SecurityState::NonSecure)?;
+        self.state.memory_security_state = Some(state);
+
+        Ok(())
+    }
 }
 
 impl CoreMemoryInterface for Armv8m<'_> {
@@ -1073,3 +1157,210 @@ impl From<FpCompN> for u32 {
         value.0
     }
 }
+
+bitfield! {
+    /// Debug Security Control and Status Register, DSCSR (see armv8-M Architecture Reference Manual D1.2.40)
+    #[derive(Copy, Clone)]
+    pub struct Dscsr(u32);
+    impl Debug;
+    /// Current domain Secure. Indicates the current Security state of the PE.
+    ///
+    /// `0`: PE is in Non-secure state.\
+    /// `1`: PE is in Secure state.
+    pub cds, set_cds: 16;
+    /// Secure banked register select. If SBRSELEN is 1, selects whether the Secure or Non-secure
+    /// registers are accessed by the MSP, PSP, MSPLIM, PSPLIM, CONTROL, FAULTMASK, BASEPRI and
+    /// PRIMASK selectors of DCRSR.
+    pub sbrsel, set_sbrsel: 1;
+    /// Secure banked register select enable.
+    pub sbrselen, set_sbrselen: 0;
+}
+
+impl MemoryMappedRegister<u32> for Dscsr {
+    const ADDRESS_OFFSET: u64 = 0xE000_EE08;
+    const NAME: &'static str = "DSCSR";
+}
+
+impl From<u32> for Dscsr {
+    fn from(value: u32) -> Self {
+        Dscsr(value)
+    }
+}
+
+impl From<Dscsr> for u32 {
+    fn from(value: Dscsr) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        architecture::arm::{
+            ap::memory_ap::MemoryAp,
+            communication_interface::{Initialized, SwdSequence},
+            sequences::DefaultArmSequence,
+            ArmCommunicationInterface,
+        },
+        probe::DebugProbeError,
+    };
+
+    /// Memory mapped registers, which read as zero unless they have been written.
+    struct MockRegisters(HashMap<u64, u32>);
+
+    impl MemoryInterface<ArmError> for MockRegisters {
+        fn supports_native_64bit_access(&mut self) -> bool {
+            false
+        }
+
+        fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+            for (i, value) in data.iter_mut().enumerate() {
+                *value = self.0.get(&(address + 4 * i as u64)).copied().unwrap_or(0);
+            }
+            Ok(())
+        }
+
+        fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+            for (i, value) in data.iter().enumerate() {
+                self.0.insert(address + 4 * i as u64, *value);
+            }
+            Ok(())
+        }
+
+        fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+            todo!()
+        }
+
+        fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+            Ok(false)
+        }
+
+        fn flush(&mut self) -> Result<(), ArmError> {
+            Ok(())
+        }
+    }
+
+    impl ArmMemoryInterface for MockRegisters {
+        fn ap(&mut self) -> &mut MemoryAp {
+            todo!()
+        }
+
+        fn base_address(&mut self) -> Result<u64, ArmError> {
+            todo!()
+        }
+
+        fn get_arm_communication_interface(
+            &mut self,
+        ) -> Result<&mut ArmCommunicationInterface<Initialized>, DebugProbeError> {
+            Err(DebugProbeError::NotImplemented {
+                function_name: "get_arm_communication_interface",
+            })
+        }
+
+        fn try_as_parts(
+            &mut self,
+        ) -> Result<(&mut ArmCommunicationInterface<Initialized>, &mut MemoryAp), DebugProbeError>
+        {
+            Err(DebugProbeError::NotImplemented {
+                function_name: "try_as_parts",
+            })
+        }
+
+        fn update_core_status(&mut self, _state: CoreStatus) {}
+    }
+
+    impl SwdSequence for MockRegisters {
+        fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+            todo!()
+        }
+
+        fn swj_pins(
+            &mut self,
+            _pin_out: u32,
+            _pin_select: u32,
+            _pin_wait: u32,
+        ) -> Result<u32, DebugProbeError> {
+            todo!()
+        }
+    }
+
+    /// Reads the security state of a core, which has the Security Extension if `idpfr1` says so.
+    fn security_state(idpfr1: u32, dscsr: u32) -> Result<Option<SecurityState>, Error> {
+        let registers = MockRegisters(HashMap::from([
+            (IdPfr1::get_mmio_address(), idpfr1),
+            (Dscsr::get_mmio_address(), dscsr),
+        ]));
+        let mut state = CortexMState::new();
+        let mut core = Armv8m::new(
+            Box::new(registers),
+            &mut state,
+            DefaultArmSequence::create(),
+        )?;
+
+        core.security_state()
+    }
+
+    #[test]
+    fn security_state_is_read_from_dscsr() {
+        let mut dscsr = Dscsr(0);
+        dscsr.set_cds(true);
+        assert_eq!(
+            security_state(0x10, dscsr.into()).unwrap(),
+            Some(SecurityState::Secure)
+        );
+
+        dscsr.set_cds(false);
+        assert_eq!(
+            security_state(0x10, dscsr.into()).unwrap(),
+            Some(SecurityState::NonSecure)
+        );
+    }
+
+    #[test]
+    fn security_state_requires_the_security_extension() {
+        let mut dscsr = Dscsr(0);
+        dscsr.set_cds(true);
+        assert_eq!(security_state(0, dscsr.into()).unwrap(), None);
+    }
+
+    #[test]
+    fn memory_security_state_requires_the_security_extension() {
+        let mut state = CortexMState::new();
+        let mut core = Armv8m::new(
+            Box::new(MockRegisters(HashMap::new())),
+            &mut state,
+            DefaultArmSequence::create(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            core.set_memory_security_state(SecurityState::NonSecure),
+            Err(Error::Arm(ArmError::ExtensionRequired(_)))
+        ));
+        drop(core);
+        assert_eq!(state.memory_security_state, None);
+    }
+}
@@ -3,10 +3,11 @@
 use crate::{
     architecture::arm::{memory::ArmMemoryInterface, ArmError},
     core::RegisterId,
+    memory::valid_32bit_address,
     memory_mapped_bitfield_register,
     semihosting::decode_semihosting_syscall,
     semihosting::SemihostingCommand,
-    CoreInterface, Error, MemoryMappedRegister,
+    CoreInterface, Error, MemoryMappedRegister, Watchpoint, WatchpointKind,
 };
 use std::time::{Duration, Instant};
 
@@ -152,6 +153,304 @@ impl IdPfr1 {
     }
 }
 
+memory_mapped_bitfield_register! {
+    /// Debug Exception and Monitor Control Register, limited to the bits shared by all M-profile variants.
+    pub struct DemcrTrace(u32);
+    0xE000_EDFC, "DEMCR",
+    impl From;
+    /// Global enable for the DWT (and ITM, if present).
+    ///
+    /// This bit is called DWTENA on ARMv6-M.
+    pub trcena, set_trcena: 24;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Control Register
+    pub struct DwtCtrl(u32);
+    0xE000_1000, "DWT_CTRL",
+    impl From;
+    /// The number of implemented comparators.
+    pub u8, numcomp, _: 31, 28;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Register 0. The registers of comparator `n` are located at an offset of `16 * n`.
+    pub struct DwtComp(u32);
+    0xE000_1020, "DWT_COMP0",
+    impl From;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Mask Register 0 (ARMv6-M and ARMv7-M only).
+    pub struct DwtMask(u32);
+    0xE000_1024, "DWT_MASK0",
+    impl From;
+    /// The number of least significant address bits ignored by the comparator.
+    pub u8, mask, set_mask: 4, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Function Register 0.
+    ///
+    /// The `function` and `datavsize` fields have different meanings on ARMv8-M,
+    /// see [`DwtModel`].
+    pub struct DwtFunction(u32);
+    0xE000_1028, "DWT_FUNCTION0",
+    impl From;
+    /// Set when the comparator matched since the last read of the register. Cleared on read.
+    pub matched, _: 24;
+    /// The size of the watched access, as a power of two (ARMv8-M only).
+    pub u8, datavsize, set_datavsize: 11, 10;
+    /// The action taken on a match (ARMv8-M only). `0b01` generates a debug event.
+    pub u8, action, set_action: 5, 4;
+    /// The function of the comparator, called MATCH on ARMv8-M.
+    pub u8, function, set_function: 3, 0;
+}
+
+/// The programming model of the DWT comparators, which differs between
+/// ARMv6-M/ARMv7-M and ARMv8-M.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum DwtModel {
+    /// Comparators with an address mask, as implemented on ARMv6-M and ARMv7-M.
+    Armv7m,
+    /// Comparators with an access size and configurable action, as implemented on ARMv8-M.
+    Armv8m,
+}
+
+impl DwtModel {
+    fn function_for(self, kind: WatchpointKind) -> u8 {
+        match (self, kind) {
+            (DwtModel::Armv7m, WatchpointKind::Read) => 0b0101,
+            (DwtModel::Armv7m, WatchpointKind::Write) => 0b0110,
+            (DwtModel::Armv7m, WatchpointKind::Access) => 0b0111,
+            (DwtModel::Armv8m, WatchpointKind::Access) => 0b0100,
+            (DwtModel::Armv8m, WatchpointKind::Write) => 0b0101,
+            (DwtModel::Armv8m, WatchpointKind::Read) => 0b0110,
+        }
+    }
+
+    fn kind_for(self, function: &DwtFunction) -> Option<WatchpointKind> {
+        match self {
+            DwtModel::Armv7m => match function.function() {
+                0b0101 => Some(WatchpointKind::Read),
+                0b0110 => Some(WatchpointKind::Write),
+                0b0111 => Some(WatchpointKind::Access),
+                _ => None,
+            },
+            // Only comparators which halt the core are watchpoints, others are used for tracing.
+            DwtModel::Armv8m if function.action() != 0b01 => None,
+            DwtModel::Armv8m => match function.function() {
+                0b0100 => Some(WatchpointKind::Access),
+                0b0101 => Some(WatchpointKind::Write),
+                0b0110 => Some(WatchpointKind::Read),
+                _ => None,
+            },
+        }
+    }
+}
+
+fn dwt_unit_address<R: MemoryMappedRegister<u32>>(unit_index: usize) -> u64 {
+    R::get_mmio_address() + 16 * unit_index as u64
+}
+
+/// Returns the number of DWT comparators, which can be used as watchpoints.
+pub(crate) fn available_watchpoint_units(
+    memory: &mut dyn ArmMemoryInterface,
+) -> Result<u32, ArmError> {
+    let ctrl = DwtCtrl(memory.read_word_32(DwtCtrl::get_mmio_address())?);
+
+    Ok(ctrl.numcomp() as u32)
+}
+
+fn read_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    model: DwtModel,
+    unit_index: usize,
+    function: &DwtFunction,
+) -> Result<Option<Watchpoint>, ArmError> {
+    let Some(kind) = model.kind_for(function) else {
+        return Ok(None);
+    };
+
+    let address = memory.read_word_32(dwt_unit_address::<DwtComp>(unit_index))?;
+
+    let length = match model {
+        DwtModel::Armv7m => {
+            let mask = DwtMask(memory.read_word_32(dwt_unit_address::<DwtMask>(unit_index))?);
+            1 << mask.mask()
+        }
+        DwtModel::Armv8m => 1 << function.datavsize(),
+    };
+
+    Ok(Some(Watchpoint {
+        address: address as u64,
+        length,
+        kind,
+    }))
+}
+
+/// Returns the watchpoints configured in the DWT comparators.
+///
+/// The comparators are only read if `cached` is empty, and `cached` is then filled with their
+/// configuration. Reading `DWT_FUNCTION` clears its `MATCHED` bit, which
+/// [`triggered_watchpoint`] needs to find the watchpoint which halted the core.
+pub(crate) fn hw_watchpoints(
+    memory: &mut dyn ArmMemoryInterface,
+    model: DwtModel,
+    cached: &mut Option<Vec<Option<Watchpoint>>>,
+) -> Result<Vec<Option<Watchpoint>>, ArmError> {
+    if let Some(watchpoints) = cached {
+        return Ok(watchpoints.clone());
+    }
+
+    let num_units = available_watchpoint_units(memory)? as usize;
+
+    let mut watchpoints = Vec::with_capacity(num_units);
+    for unit_index in 0..num_units {
+        let function =
+            DwtFunction(memory.read_word_32(dwt_unit_address::<DwtFunction>(unit_index))?);
+        watchpoints.push(read_watchpoint(memory, model, unit_index, &function)?);
+    }
+
+    *cached = Some(watchpoints.clone());
+
+    Ok(watchpoints)
+}
+
+/// Updates the cached configuration of DWT comparator `unit_index`, if there is one.
+fn update_cached_watchpoint(
+    cached: &mut Option<Vec<Option<Watchpoint>>>,
+    unit_index: usize,
+    watchpoint: Option<Watchpoint>,
+) {
+    if let Some(unit) = cached
+        .as_mut()
+        .and_then(|watchpoints| watchpoints.get_mut(unit_index))
+    {
+        *unit = watchpoint;
+    }
+}
+
+/// Configures DWT comparator `unit_index` to halt the core on accesses described by `watchpoint`.
+pub(crate) fn set_hw_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    model: DwtModel,
+    cached: &mut Option<Vec<Option<Watchpoint>>>,
+    unit_index: usize,
+    watchpoint: Watchpoint,
+) -> Result<(), Error> {
+    let address = valid_32bit_address(watchpoint.address)?;
+
+    // Comparators can only match naturally aligned, power-of-two sized ranges.
+    let length = watchpoint.length.max(1).next_power_of_two();
+    let size_bits = length.trailing_zeros();
+
+    let max_size_bits = match model {
+        // The maximum mask size is IMPLEMENTATION DEFINED, but writing a larger value
+        // than supported will read back as the largest supported value.
+        DwtModel::Armv7m => 31,
+        // Only byte, halfword and word accesses can be matched without linking comparators.
+        DwtModel::Armv8m => 2,
+    };
+
+    if size_bits > max_size_bits || address as u64 % length != 0 {
+        return Err(Error::Other(format!(
+            "A watchpoint for {} bytes at address {:#010x} can not be represented by a DWT comparator",
+            watchpoint.length, address
+        )));
+    }
+
+    // The DWT is only active when it is enabled in the DEMCR.
+    let mut demcr = DemcrTrace(memory.read_word_32(DemcrTrace::get_mmio_address())?);
+    if !demcr.trcena() {
+        demcr.set_trcena(true);
+        memory.write_word_32(DemcrTrace::get_mmio_address(), demcr.into())?;
+    }
+
+    // Disable the comparator while it is reconfigured.
+    memory.write_word_32(dwt_unit_address::<DwtFunction>(unit_index), 0)?;
+    update_cached_watchpoint(cached, unit_index, None);
+    memory.write_word_32(dwt_unit_address::<DwtComp>(unit_index), address)?;
+
+    let mut function = DwtFunction(0);
+    function.set_function(model.function_for(watchpoint.kind));
+
+    match model {
+        DwtModel::Armv7m => {
+            let mut mask = DwtMask(0);
+            mask.set_mask(size_bits as u8);
+            memory.write_word_32(dwt_unit_address::<DwtMask>(unit_index), mask.into())?;
+
+            let readback = DwtMask(memory.read_word_32(dwt_unit_address::<DwtMask>(unit_index))?);
+            if readback.mask() != size_bits as u8 {
+                return Err(Error::Other(format!(
+                    "The DWT comparators support watching at most {} bytes",
+                    1u64 << readback.mask()
+                )));
+            }
+        }
+        DwtModel::Armv8m => {
+            function.set_action(0b01);
+            function.set_datavsize(size_bits as u8);
+        }
+    }
+
+    memory.write_word_32(dwt_unit_address::<DwtFunction>(unit_index), function.into())?;
+    memory.flush()?;
+
+    // The comparator matches the whole naturally aligned range.
+    update_cached_watchpoint(
+        cached,
+        unit_index,
+        Some(Watchpoint {
+            address: address as u64,
+            length,
+            kind: watchpoint.kind,
+        }),
+    );
+
+    Ok(())
+}
+
+/// Disables DWT comparator `unit_index`.
+pub(crate) fn clear_hw_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    cached: &mut Option<Vec<Option<Watchpoint>>>,
+    unit_index: usize,
+) -> Result<(), ArmError> {
+    memory.write_word_32(dwt_unit_address::<DwtFunction>(unit_index), 0)?;
+    memory.flush()?;
+
+    update_cached_watchpoint(cached, unit_index, None);
+
+    Ok(())
+}
+
+/// Determines which watchpoint caused the core to halt, by checking the `MATCHED` bit
+/// of all DWT comparators.
+///
+/// Reading the `DWT_FUNCTION` registers clears the `MATCHED` bits, so this only returns
+/// a result the first time it is called after a halt.
+pub(crate) fn triggered_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    model: DwtModel,
+) -> Result<Option<Watchpoint>, ArmError> {
+    let num_units = available_watchpoint_units(memory)? as usize;
+
+    for unit_index in 0..num_units {
+        let function =
+            DwtFunction(memory.read_word_32(dwt_unit_address::<DwtFunction>(unit_index))?);
+
+        if function.matched() {
+            if let Some(watchpoint) = read_watchpoint(memory, model, unit_index, &function)? {
+                return Ok(Some(watchpoint));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
 pub(crate) fn read_core_reg(
     memory: &mut dyn ArmMemoryInterface,
     addr: RegisterId,
@@ -247,3 +546,38 @@ fn wait_for_core_register_transfer(
     }
     Err(ArmError::Timeout)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{DwtFunction, DwtModel};
+    use crate::WatchpointKind;
+
+    #[test]
+    fn dwt_function_roundtrip() {
+        for model in [DwtModel::Armv7m, DwtModel::Armv8m] {
+            for kind in [
+                WatchpointKind::Read,
+                WatchpointKind::Write,
+                WatchpointKind::Access,
+            ] {
+                let mut function = DwtFunction(0);
+                function.set_function(model.function_for(kind));
+                if model == DwtModel::Armv8m {
+                    function.set_action(0b01);
+                }
+
+                assert_eq!(model.kind_for(&function), Some(kind));
+            }
+        }
+    }
+
+    #[test]
+    fn dwt_trace_comparator_is_not_a_watchpoint() {
+        // ARMv8-M comparator configured to generate trace packets instead of debug events.
+        let mut function = DwtFunction(0);
+        function.set_function(0b0100);
+        function.set_action(0b10);
+
+        assert_eq!(DwtModel::Armv8m.kind_for(&function), None);
+    }
+}
@@ -3,10 +3,10 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    core::{BreakpointCause, RegisterValue},
+    core::{BreakpointCause, RegisterValue, SecurityState},
     memory_mapped_bitfield_register,
     semihosting::SemihostingCommand,
-    CoreStatus, HaltReason,
+    CoreStatus, HaltReason, Watchpoint,
 };
 
 use super::memory::ArmMemoryInterface;
@@ -16,6 +16,7 @@ pub mod armv7a;
 pub mod armv7m;
 pub mod armv8a;
 pub mod armv8m;
+pub mod mpu;
 
 pub(crate) mod armv7a_debug_regs;
 pub(crate) mod armv8a_debug_regs;
@@ -101,7 +102,7 @@ impl Dfsr {
         } else if self.external() {
             HaltReason::External
         } else if self.dwttrap() {
-            HaltReason::Watchpoint
+            HaltReason::Watchpoint(None)
         } else if self.halted() {
             HaltReason::Request
         } else if self.vcatch() {
@@ -141,8 +142,17 @@ pub struct CortexMState {
 
     fp_present: bool,
 
+    /// Whether the ARMv8-M Security Extension is implemented
+    security_present: bool,
+
+    /// The security of the memory accesses selected by the user, if any
+    memory_security_state: Option<SecurityState>,
+
     /// The semihosting command that was decoded at the current program counter
     semihosting_command: Option<SemihostingCommand>,
+
+    /// The configuration of the DWT comparators, once it has been read
+    watchpoints: Option<Vec<Option<Watchpoint>>>,
 }
 
 impl CortexMState {
@@ -152,7 +162,10 @@ impl CortexMState {
             hw_breakpoints_enabled: false,
             current_state: CoreStatus::Unknown,
             fp_present: false,
+            security_present: false,
+            memory_security_state: None,
             semihosting_command: None,
+            watchpoints: None,
         }
     }
 
@@ -0,0 +1,913 @@
+//! Decoding of the memory protection configuration of Cortex-M cores.
+//!
+//! This reads the regions of the Memory Protection Unit (MPU), both in the PMSAv7 format used by
+//! ARMv6-M and ARMv7-M, and in the PMSAv8 format used by ARMv8-M, and the regions of the Security
+//! Attribution Unit (SAU) of ARMv8-M cores with the Security Extension.
+
+use std::fmt;
+
+use super::cortex_m::IdPfr1;
+use crate::{
+    architecture::arm::ArmError,
+    debug::exception_handling::armv7m::{Cfsr, Mmfar},
+    memory_mapped_bitfield_register, CoreType, Error, MemoryInterface, MemoryMappedRegister,
+};
+
+/// The offset of the Non-secure alias of the System Control Space.
+const NON_SECURE_ALIAS_OFFSET: u64 = 0x2_0000;
+
+memory_mapped_bitfield_register! {
+    /// MPU_TYPE - MPU Type Register
+    struct MpuType(u32);
+    0xE000_ED90, "MPU_TYPE",
+    impl From;
+    /// The number of MPU regions.
+    pub u8, dregion, _: 15, 8;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_CTRL - MPU Control Register
+    struct MpuCtrl(u32);
+    0xE000_ED94, "MPU_CTRL",
+    impl From;
+    /// Enables the default memory map as a background region for privileged accesses.
+    pub privdefena, _: 2;
+    /// Enables the MPU in HardFault and NMI handlers, and when FAULTMASK is set.
+    pub hfnmiena, _: 1;
+    /// Enables the MPU.
+    pub enable, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_RNR - MPU Region Number Register
+    struct MpuRnr(u32);
+    0xE000_ED98, "MPU_RNR",
+    impl From;
+    /// The region accessed by MPU_RBAR and MPU_RASR or MPU_RLAR.
+    pub u8, region, set_region: 7, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_RBAR - MPU Region Base Address Register, in the PMSAv7 format
+    struct Pmsav7Rbar(u32);
+    0xE000_ED9C, "MPU_RBAR",
+    impl From;
+    /// Bits [31:5] of the base address of the region.
+    pub addr, _: 31, 5;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_RASR - MPU Region Attribute and Size Register
+    struct Pmsav7Rasr(u32);
+    0xE000_EDA0, "MPU_RASR",
+    impl From;
+    /// Instruction fetches from the region are not permitted.
+    pub xn, _: 28;
+    /// The access permissions.
+    pub u8, ap, _: 26, 24;
+    /// The type extension of the memory attributes.
+    pub u8, tex, _: 21, 19;
+    /// Shareable.
+    pub s, _: 18;
+    /// Cacheable.
+    pub c, _: 17;
+    /// Bufferable.
+    pub b, _: 16;
+    /// Subregion disable bits, one for each eighth of the region.
+    pub u8, srd, _: 15, 8;
+    /// The size of the region is 2^(SIZE+1) bytes.
+    pub u8, size, _: 5, 1;
+    /// Enables the region.
+    pub enable, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_RBAR - MPU Region Base Address Register, in the PMSAv8 format
+    struct Pmsav8Rbar(u32);
+    0xE000_ED9C, "MPU_RBAR",
+    impl From;
+    /// Bits [31:5] of the base address of the region.
+    pub base, _: 31, 5;
+    /// The shareability of the region.
+    pub u8, sh, _: 4, 3;
+    /// The access permissions.
+    pub u8, ap, _: 2, 1;
+    /// Instruction fetches from the region are not permitted.
+    pub xn, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_RLAR - MPU Region Limit Address Register
+    struct Pmsav8Rlar(u32);
+    0xE000_EDA0, "MPU_RLAR",
+    impl From;
+    /// Bits [31:5] of the limit address of the region.
+    pub limit, _: 31, 5;
+    /// Privileged instruction fetches from the region are not permitted.
+    pub pxn, _: 4;
+    /// The index of the memory attributes in MPU_MAIR0 and MPU_MAIR1.
+    pub u8, attrindx, _: 3, 1;
+    /// Enables the region.
+    pub en, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_MAIR0 - MPU Memory Attribute Indirection Register 0
+    struct Mair0(u32);
+    0xE000_EDC0, "MPU_MAIR0",
+    impl From;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU_MAIR1 - MPU Memory Attribute Indirection Register 1
+    struct Mair1(u32);
+    0xE000_EDC4, "MPU_MAIR1",
+    impl From;
+}
+
+memory_mapped_bitfield_register! {
+    /// SAU_CTRL - SAU Control Register
+    struct SauCtrl(u32);
+    0xE000_EDD0, "SAU_CTRL",
+    impl From;
+    /// Memory is marked as Non-secure when the SAU is disabled.
+    pub allns, _: 1;
+    /// Enables the SAU.
+    pub enable, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// SAU_TYPE - SAU Type Register
+    struct SauType(u32);
+    0xE000_EDD4, "SAU_TYPE",
+    impl From;
+    /// The number of SAU regions.
+    pub u8, sregion, _: 7, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// SAU_RNR - SAU Region Number Register
+    struct SauRnr(u32);
+    0xE000_EDD8, "SAU_RNR",
+    impl From;
+    /// The region accessed by SAU_RBAR and SAU_RLAR.
+    pub u8, region, set_region: 7, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// SAU_RBAR - SAU Region Base Address Register
+    struct SauRbar(u32);
+    0xE000_EDDC, "SAU_RBAR",
+    impl From;
+    /// Bits [31:5] of the base address of the region.
+    pub baddr, _: 31, 5;
+}
+
+memory_mapped_bitfield_register! {
+    /// SAU_RLAR - SAU Region Limit Address Register
+    struct SauRlar(u32);
+    0xE000_EDE0, "SAU_RLAR",
+    impl From;
+    /// Bits [31:5] of the limit address of the region.
+    pub laddr, _: 31, 5;
+    /// The region is Non-secure callable.
+    pub nsc, _: 1;
+    /// Enables the region.
+    pub enable, _: 0;
+}
+
+/// The format of the MPU registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MpuArchitecture {
+    /// The Protected Memory System Architecture of ARMv6-M and ARMv7-M.
+    ///
+    /// Regions have a power of two size, and may overlap. The highest numbered region takes
+    /// priority.
+    Pmsav7,
+    /// The Protected Memory System Architecture of ARMv8-M.
+    ///
+    /// Regions have a base and a limit address. An access which matches several regions faults.
+    Pmsav8,
+}
+
+/// The access which is permitted to a region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// No access is permitted.
+    NoAccess,
+    /// Only reads are permitted.
+    ReadOnly,
+    /// Reads and writes are permitted.
+    ReadWrite,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::NoAccess => write!(f, "--"),
+            Access::ReadOnly => write!(f, "RO"),
+            Access::ReadWrite => write!(f, "RW"),
+        }
+    }
+}
+
+/// The access permissions of a region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessPermissions {
+    /// The access permitted to privileged software.
+    pub privileged: Access,
+    /// The access permitted to unprivileged software.
+    pub unprivileged: Access,
+}
+
+impl fmt::Display for AccessPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "P:{} U:{}", self.privileged, self.unprivileged)
+    }
+}
+
+/// The ordering requirements of Device memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceMemory {
+    /// Non-gathering, non-reordering, no early write acknowledgement.
+    ///
+    /// This is Strongly-ordered memory in PMSAv7.
+    NGnRnE,
+    /// Non-gathering, non-reordering, early write acknowledgement.
+    NGnRE,
+    /// Non-gathering, reordering, early write acknowledgement.
+    NGRE,
+    /// Gathering, reordering, early write acknowledgement.
+    GRE,
+}
+
+/// The cache policy of Normal memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// The memory is not cacheable.
+    NonCacheable,
+    /// Write-through cacheable.
+    WriteThrough {
+        /// Read allocation.
+        read_allocate: bool,
+        /// Write allocation.
+        write_allocate: bool,
+    },
+    /// Write-back cacheable.
+    WriteBack {
+        /// Read allocation.
+        read_allocate: bool,
+        /// Write allocation.
+        write_allocate: bool,
+    },
+}
+
+impl fmt::Display for CachePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, read_allocate, write_allocate) = match *self {
+            CachePolicy::NonCacheable => return write!(f, "NC"),
+            CachePolicy::WriteThrough {
+                read_allocate,
+                write_allocate,
+            } => ("WT", read_allocate, write_allocate),
+            CachePolicy::WriteBack {
+                read_allocate,
+                write_allocate,
+            } => ("WB", read_allocate, write_allocate),
+        };
+
+        write!(f, "{name}")?;
+        if read_allocate {
+            write!(f, " RA")?;
+        }
+        if write_allocate {
+            write!(f, " WA")?;
+        }
+        Ok(())
+    }
+}
+
+/// The memory type of a region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryType {
+    /// Device memory.
+    Device(DeviceMemory),
+    /// Normal memory.
+    Normal {
+        /// The inner cache policy.
+        inner: CachePolicy,
+        /// The outer cache policy.
+        outer: CachePolicy,
+    },
+    /// The attributes are reserved or implementation defined.
+    Reserved,
+}
+
+impl fmt::Display for MemoryType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryType::Device(device) => write!(f, "Device-{device:?}"),
+            MemoryType::Normal { inner, outer } if inner == outer => write!(f, "Normal {inner}"),
+            MemoryType::Normal { inner, outer } => {
+                write!(f, "Normal inner {inner}, outer {outer}")
+            }
+            MemoryType::Reserved => write!(f, "Reserved"),
+        }
+    }
+}
+
+/// A region of the MPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MpuRegion {
+    /// The number of the region.
+    pub number: u8,
+    /// Whether the region is enabled.
+    pub enabled: bool,
+    /// The first address of the region.
+    pub start: u32,
+    /// The last address of the region.
+    pub end: u32,
+    /// The subregions which are disabled, one bit for each eighth of the region.
+    ///
+    /// Subregions only exist in PMSAv7.
+    pub disabled_subregions: u8,
+    /// The access permissions.
+    pub access: AccessPermissions,
+    /// Instruction fetches are not permitted.
+    pub execute_never: bool,
+    /// Privileged instruction fetches are not permitted.
+    pub privileged_execute_never: bool,
+    /// Whether the region is shareable.
+    pub shareable: bool,
+    /// The memory type and cache policy.
+    pub memory_type: MemoryType,
+}
+
+impl MpuRegion {
+    /// Returns `true` if the region is enabled and covers `address`.
+    pub fn contains(&self, address: u32) -> bool {
+        if !self.enabled || address < self.start || address > self.end {
+            return false;
+        }
+
+        // Subregions are only supported for regions of 256 bytes or more.
+        let size = u64::from(self.end - self.start) + 1;
+        if self.disabled_subregions == 0 || size < 256 {
+            return true;
+        }
+
+        let subregion_size = size / 8;
+        let subregion = u64::from(address - self.start) / subregion_size;
+        self.disabled_subregions & (1 << subregion) == 0
+    }
+}
+
+/// The configuration of the MPU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mpu {
+    /// The format of the MPU registers.
+    pub architecture: MpuArchitecture,
+    /// Whether the MPU is enabled.
+    pub enabled: bool,
+    /// Whether the default memory map is used for privileged accesses which match no region.
+    pub privileged_default_map: bool,
+    /// Whether the MPU is enabled in HardFault and NMI handlers.
+    pub enabled_in_fault_handlers: bool,
+    /// The regions.
+    pub regions: Vec<MpuRegion>,
+}
+
+impl Mpu {
+    /// Reads the configuration of the MPU of a Cortex-M core.
+    ///
+    /// On ARMv8-M cores with the Security Extension, this reads the MPU of the security state the
+    /// memory accesses are made in. Returns `None` if the core has no MPU.
+    pub fn read(
+        memory: &mut dyn MemoryInterface,
+        core_type: CoreType,
+    ) -> Result<Option<Self>, Error> {
+        let architecture = match core_type {
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em => MpuArchitecture::Pmsav7,
+            CoreType::Armv8m => MpuArchitecture::Pmsav8,
+            _ => {
+                return Err(Error::Arm(ArmError::ArchitectureRequired(&[
+                    "ARMv6-M", "ARMv7-M", "ARMv8-M",
+                ])))
+            }
+        };
+
+        Self::read_at(memory, architecture, 0)
+    }
+
+    /// Reads the configuration of the Non-secure MPU of an ARMv8-M core with the Security
+    /// Extension, through the Non-secure alias of the System Control Space.
+    ///
+    /// This requires Secure memory accesses. Returns `None` if the core has no Non-secure MPU.
+    pub fn read_non_secure(memory: &mut dyn MemoryInterface) -> Result<Option<Self>, Error> {
+        Self::read_at(memory, MpuArchitecture::Pmsav8, NON_SECURE_ALIAS_OFFSET)
+    }
+
+    fn read_at(
+        memory: &mut dyn MemoryInterface,
+        architecture: MpuArchitecture,
+        offset: u64,
+    ) -> Result<Option<Self>, Error> {
+        let mpu_type = MpuType(memory.read_word_32(MpuType::get_mmio_address() + offset)?);
+        if mpu_type.dregion() == 0 {
+            return Ok(None);
+        }
+
+        let ctrl = MpuCtrl(memory.read_word_32(MpuCtrl::get_mmio_address() + offset)?);
+        let mair = match architecture {
+            MpuArchitecture::Pmsav7 => 0,
+            MpuArchitecture::Pmsav8 => {
+                let mair0 = memory.read_word_32(Mair0::get_mmio_address() + offset)?;
+                let mair1 = memory.read_word_32(Mair1::get_mmio_address() + offset)?;
+                u64::from(mair0) | u64::from(mair1) << 32
+            }
+        };
+
+        // The region number is restored afterwards, so the firmware is not disturbed if it is
+        // halted while it configures a region.
+        let rnr_address = MpuRnr::get_mmio_address() + offset;
+        let saved_rnr = memory.read_word_32(rnr_address)?;
+
+        let mut read_regions = || -> Result<_, Error> {
+            let mut regions = vec![];
+            for number in 0..mpu_type.dregion() {
+                let mut rnr = MpuRnr(0);
+                rnr.set_region(number);
+                memory.write_word_32(rnr_address, rnr.into())?;
+
+                let rbar = memory.read_word_32(Pmsav7Rbar::get_mmio_address() + offset)?;
+                let rasr_or_rlar = memory.read_word_32(Pmsav7Rasr::get_mmio_address() + offset)?;
+
+                regions.push(match architecture {
+                    MpuArchitecture::Pmsav7 => {
+                        decode_pmsav7_region(number, rbar.into(), rasr_or_rlar.into())
+                    }
+                    MpuArchitecture::Pmsav8 => {
+                        decode_pmsav8_region(number, rbar.into(), rasr_or_rlar.into(), mair)
+                    }
+                });
+            }
+            Ok(regions)
+        };
+        let regions = read_regions();
+        memory.write_word_32(rnr_address, saved_rnr)?;
+        let regions = regions?;
+
+        Ok(Some(Self {
+            architecture,
+            enabled: ctrl.enable(),
+            privileged_default_map: ctrl.privdefena(),
+            enabled_in_fault_handlers: ctrl.hfnmiena(),
+            regions,
+        }))
+    }
+
+    /// Returns the region which applies to an access to `address`, or `None` if no enabled region
+    /// covers it.
+    ///
+    /// With PMSAv7, the highest numbered region is returned. With PMSAv8, an access which matches
+    /// several regions faults, and the first one is returned.
+    pub fn region_at(&self, address: u32) -> Option<&MpuRegion> {
+        let mut matching = self
+            .regions
+            .iter()
+            .filter(|region| region.contains(address));
+
+        match self.architecture {
+            MpuArchitecture::Pmsav7 => matching.last(),
+            MpuArchitecture::Pmsav8 => matching.next(),
+        }
+    }
+}
+
+fn decode_pmsav7_region(number: u8, rbar: Pmsav7Rbar, rasr: Pmsav7Rasr) -> MpuRegion {
+    let size = 1u64 << (rasr.size() + 1);
+    let start = u64::from(rbar.addr() << 5) & !(size - 1);
+
+    let (privileged, unprivileged) = match rasr.ap() {
+        0b001 => (Access::ReadWrite, Access::NoAccess),
+        0b010 => (Access::ReadWrite, Access::ReadOnly),
+        0b011 => (Access::ReadWrite, Access::ReadWrite),
+        0b101 => (Access::ReadOnly, Access::NoAccess),
+        0b110 | 0b111 => (Access::ReadOnly, Access::ReadOnly),
+        _ => (Access::NoAccess, Access::NoAccess),
+    };
+
+    let (memory_type, always_shareable) = match (rasr.tex(), rasr.c(), rasr.b()) {
+        (0b000, false, false) => (MemoryType::Device(DeviceMemory::NGnRnE), true),
+        (0b000, false, true) => (MemoryType::Device(DeviceMemory::NGnRE), true),
+        (0b000, true, b) => {
+            let policy = pmsav7_cache_policy(0b10 | u8::from(b));
+            (
+                MemoryType::Normal {
+                    inner: policy,
+                    outer: policy,
+                },
+                false,
+            )
+        }
+        (0b001, false, false) => (
+            MemoryType::Normal {
+                inner: CachePolicy::NonCacheable,
+                outer: CachePolicy::NonCacheable,
+            },
+            false,
+        ),
+        (0b001, true, true) => {
+            let policy = pmsav7_cache_policy(0b01);
+            (
+                MemoryType::Normal {
+                    inner: policy,
+                    outer: policy,
+                },
+                false,
+            )
+        }
+        (0b010, false, false) => (MemoryType::Device(DeviceMemory::NGnRE), false),
+        (tex, c, b) if tex & 0b100 != 0 => (
+            MemoryType::Normal {
+                inner: pmsav7_cache_policy(u8::from(c) << 1 | u8::from(b)),
+                outer: pmsav7_cache_policy(tex & 0b11),
+            },
+            false,
+        ),
+        _ => (MemoryType::Reserved, false),
+    };
+
+    MpuRegion {
+        number,
+        enabled: rasr.enable(),
+        start: start as u32,
+        end: (start + size - 1) as u32,
+        // Regions smaller than 256 bytes have no subregions.
+        disabled_subregions: if size >= 256 { rasr.srd() } else { 0 },
+        access: AccessPermissions {
+            privileged,
+            unprivileged,
+        },
+        execute_never: rasr.xn(),
+        privileged_execute_never: false,
+        shareable: always_shareable || rasr.s(),
+        memory_type,
+    }
+}
+
+/// Decodes the cache policy of the `AA` or `BB` field of a PMSAv7 region.
+fn pmsav7_cache_policy(policy: u8) -> CachePolicy {
+    match policy {
+        0b00 => CachePolicy::NonCacheable,
+        0b01 => CachePolicy::WriteBack {
+            read_allocate: true,
+            write_allocate: true,
+        },
+        0b10 => CachePolicy::WriteThrough {
+            read_allocate: true,
+            write_allocate: false,
+        },
+        _ => CachePolicy::WriteBack {
+            read_allocate: true,
+            write_allocate: false,
+        },
+    }
+}
+
+fn decode_pmsav8_region(number: u8, rbar: Pmsav8Rbar, rlar: Pmsav8Rlar, mair: u64) -> MpuRegion {
+    let privileged = if rbar.ap() & 0b10 == 0 {
+        Access::ReadWrite
+    } else {
+        Access::ReadOnly
+    };
+    let unprivileged = if rbar.ap() & 0b01 == 0 {
+        Access::NoAccess
+    } else {
+        privileged
+    };
+
+    let attributes = (mair >> (8 * rlar.attrindx())) as u8;
+
+    MpuRegion {
+        number,
+        enabled: rlar.en(),
+        start: rbar.base() << 5,
+        end: rlar.limit() << 5 | 0x1F,
+        disabled_subregions: 0,
+        access: AccessPermissions {
+            privileged,
+            unprivileged,
+        },
+        execute_never: rbar.xn(),
+        privileged_execute_never: rlar.pxn(),
+        shareable: rbar.sh() != 0,
+        memory_type: decode_memory_attributes(attributes),
+    }
+}
+
+/// Decodes an attribute of the MAIR registers.
+fn decode_memory_attributes(attributes: u8) -> MemoryType {
+    if attributes >> 4 == 0 {
+        return match (attributes >> 2) & 0b11 {
+            0b00 => MemoryType::Device(DeviceMemory::NGnRnE),
+            0b01 => MemoryType::Device(DeviceMemory::NGnRE),
+            0b10 => MemoryType::Device(DeviceMemory::NGRE),
+            _ => MemoryType::Device(DeviceMemory::GRE),
+        };
+    }
+
+    MemoryType::Normal {
+        inner: mair_cache_policy(attributes & 0xF),
+        outer: mair_cache_policy(attributes >> 4),
+    }
+}
+
+/// Decodes the inner or outer cache policy of a Normal memory attribute.
+fn mair_cache_policy(policy: u8) -> CachePolicy {
+    let read_allocate = policy & 0b10 != 0;
+    let write_allocate = policy & 0b01 != 0;
+
+    match policy >> 2 {
+        _ if policy == 0b0100 => CachePolicy::NonCacheable,
+        0b00 | 0b10 => CachePolicy::WriteThrough {
+            read_allocate,
+            write_allocate,
+        },
+        _ => CachePolicy::WriteBack {
+            read_allocate,
+            write_allocate,
+        },
+    }
+}
+
+/// A region of the SAU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SauRegion {
+    /// The number of the region.
+    pub number: u8,
+    /// Whether the region is enabled.
+    pub enabled: bool,
+    /// The first address of the region.
+    pub start: u32,
+    /// The last address of the region.
+    pub end: u32,
+    /// Whether the region is Secure and Non-secure callable, instead of Non-secure.
+    pub non_secure_callable: bool,
+}
+
+impl SauRegion {
+    /// Returns `true` if the region is enabled and covers `address`.
+    pub fn contains(&self, address: u32) -> bool {
+        self.enabled && (self.start..=self.end).contains(&address)
+    }
+}
+
+/// The configuration of the Security Attribution Unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sau {
+    /// Whether the SAU is enabled.
+    pub enabled: bool,
+    /// Whether all memory is Non-secure while the SAU is disabled.
+    pub all_non_secure: bool,
+    /// The regions, which mark memory as Non-secure or Non-secure callable.
+    pub regions: Vec<SauRegion>,
+}
+
+impl Sau {
+    /// Reads the configuration of the SAU of an ARMv8-M core.
+    ///
+    /// Returns `None` if the core does not implement the Security Extension. The SAU registers
+    /// can only be read with Secure memory accesses.
+    pub fn read(memory: &mut dyn MemoryInterface) -> Result<Option<Self>, Error> {
+        let id_pfr1 = IdPfr1(memory.read_word_32(IdPfr1::get_mmio_address())?);
+        if !id_pfr1.security_present() {
+            return Ok(None);
+        }
+
+        let sau_type = SauType(memory.read_word_32(SauType::get_mmio_address())?);
+        let ctrl = SauCtrl(memory.read_word_32(SauCtrl::get_mmio_address())?);
+
+        // Like for the MPU, the region number is restored afterwards.
+        let saved_rnr = memory.read_word_32(SauRnr::get_mmio_address())?;
+
+        let mut read_regions = || -> Result<_, Error> {
+            let mut regions = vec![];
+            for number in 0..sau_type.sregion() {
+                let mut rnr = SauRnr(0);
+                rnr.set_region(number);
+                memory.write_word_32(SauRnr::get_mmio_address(), rnr.into())?;
+
+                let rbar = SauRbar(memory.read_word_32(SauRbar::get_mmio_address())?);
+                let rlar = SauRlar(memory.read_word_32(SauRlar::get_mmio_address())?);
+
+                regions.push(SauRegion {
+                    number,
+                    enabled: rlar.enable(),
+                    start: rbar.baddr() << 5,
+                    end: rlar.laddr() << 5 | 0x1F,
+                    non_secure_callable: rlar.nsc(),
+                });
+            }
+            Ok(regions)
+        };
+        let regions = read_regions();
+        memory.write_word_32(SauRnr::get_mmio_address(), saved_rnr)?;
+        let regions = regions?;
+
+        Ok(Some(Self {
+            enabled: ctrl.enable(),
+            all_non_secure: ctrl.allns(),
+            regions,
+        }))
+    }
+
+    /// Returns the enabled region which covers `address`, if any.
+    pub fn region_at(&self, address: u32) -> Option<&SauRegion> {
+        self.regions.iter().find(|region| region.contains(address))
+    }
+}
+
+/// Returns the address of the last MemManage fault, if the MemManage Fault Address Register
+/// holds a valid address.
+///
+/// ARMv6-M cores have no MemManage fault, and `None` is returned for them.
+pub fn memory_management_fault_address(
+    memory: &mut dyn MemoryInterface,
+    core_type: CoreType,
+) -> Result<Option<u32>, Error> {
+    if core_type == CoreType::Armv6m {
+        return Ok(None);
+    }
+
+    let cfsr = Cfsr(memory.read_word_32(Cfsr::get_mmio_address())?);
+    if !cfsr.mm_address_register_valid() {
+        return Ok(None);
+    }
+
+    Ok(Some(memory.read_word_32(Mmfar::get_mmio_address())?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::MockMemory;
+
+    #[test]
+    fn pmsav7_flash_region() {
+        // 512 KiB at 0x0800_0000, privileged and unprivileged read-only, Normal WT.
+        let region = decode_pmsav7_region(0, Pmsav7Rbar(0x0800_0010), Pmsav7Rasr(0x0602_0025));
+
+        assert_eq!(region.start, 0x0800_0000);
+        assert_eq!(region.end, 0x0807_FFFF);
+        assert!(region.enabled);
+        assert!(!region.execute_never);
+        assert_eq!(
+            region.access,
+            AccessPermissions {
+                privileged: Access::ReadOnly,
+                unprivileged: Access::ReadOnly,
+            }
+        );
+        assert_eq!(
+            region.memory_type,
+            MemoryType::Normal {
+                inner: pmsav7_cache_policy(0b10),
+                outer: pmsav7_cache_policy(0b10),
+            }
+        );
+    }
+
+    #[test]
+    fn pmsav7_subregions() {
+        // 1 KiB at 0x2000_0000, with the second eighth disabled.
+        let region = decode_pmsav7_region(1, Pmsav7Rbar(0x2000_0000), Pmsav7Rasr(0x1300_0213));
+
+        assert!(region.contains(0x2000_0000));
+        assert!(!region.contains(0x2000_0080));
+        assert!(region.contains(0x2000_0100));
+        assert!(!region.contains(0x2000_0400));
+        assert!(region.execute_never);
+        assert_eq!(region.memory_type, MemoryType::Device(DeviceMemory::NGnRnE));
+    }
+
+    #[test]
+    fn pmsav7_highest_region_takes_priority() {
+        let background = decode_pmsav7_region(0, Pmsav7Rbar(0), Pmsav7Rasr(0x0300_003F));
+        let stack_guard = decode_pmsav7_region(1, Pmsav7Rbar(0x2000_0000), Pmsav7Rasr(0x1000_0009));
+
+        let mpu = Mpu {
+            architecture: MpuArchitecture::Pmsav7,
+            enabled: true,
+            privileged_default_map: false,
+            enabled_in_fault_handlers: false,
+            regions: vec![background, stack_guard],
+        };
+
+        assert_eq!(mpu.region_at(0x2000_0010).map(|r| r.number), Some(1));
+        assert_eq!(mpu.region_at(0x2000_0020).map(|r| r.number), Some(0));
+    }
+
+    #[test]
+    fn pmsav8_region() {
+        // RAM at 0x2000_0000 - 0x2000_3FFF, read-write by any privilege level, execute never,
+        // inner shareable, using attribute index 1.
+        let mair = 0xFF44;
+        let region =
+            decode_pmsav8_region(2, Pmsav8Rbar(0x2000_001B), Pmsav8Rlar(0x2000_3FE3), mair);
+
+        assert_eq!(region.start, 0x2000_0000);
+        assert_eq!(region.end, 0x2000_3FFF);
+        assert!(region.enabled);
+        assert!(region.execute_never);
+        assert!(region.shareable);
+        assert_eq!(
+            region.access,
+            AccessPermissions {
+                privileged: Access::ReadWrite,
+                unprivileged: Access::ReadWrite,
+            }
+        );
+        assert_eq!(
+            region.memory_type,
+            MemoryType::Normal {
+                inner: CachePolicy::WriteBack {
+                    read_allocate: true,
+                    write_allocate: true,
+                },
+                outer: CachePolicy::WriteBack {
+                    read_allocate: true,
+                    write_allocate: true,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn mair_attributes() {
+        assert_eq!(
+            decode_memory_attributes(0x04),
+            MemoryType::Device(DeviceMemory::NGnRE)
+        );
+        assert_eq!(
+            decode_memory_attributes(0x44),
+            MemoryType::Normal {
+                inner: CachePolicy::NonCacheable,
+                outer: CachePolicy::NonCacheable,
+            }
+        );
+        assert_eq!(
+            decode_memory_attributes(0xAA).to_string(),
+            "Normal WT RA".to_string()
+        );
+    }
+
+    #[test]
+    fn mpu_is_read_without_changing_the_selected_region() {
+        let mut memory = MockMemory::new();
+        // One region, and the firmware has selected region 5. The region covers 4 bytes at
+        // 0x2000_0000, with all subregion disable bits set.
+        memory.add_word_range(
+            MpuType::get_mmio_address(),
+            &[0x0000_0100, 0x0000_0001, 5, 0x2000_0000, 0x0000_FF03],
+        );
+
+        let mpu = Mpu::read(&mut memory, CoreType::Armv7m).unwrap().unwrap();
+
+        assert_eq!(memory.read_word_32(MpuRnr::get_mmio_address()).unwrap(), 5);
+        assert!(mpu.enabled);
+        assert_eq!(mpu.regions.len(), 1);
+        assert_eq!(mpu.regions[0].end, 0x2000_0003);
+        // Regions smaller than 256 bytes have no subregions.
+        assert_eq!(mpu.region_at(0x2000_0002).map(|r| r.number), Some(0));
+        assert_eq!(mpu.region_at(0x2000_0004), None);
+    }
+
+    #[test]
+    fn sau_is_read_without_changing_the_selected_region() {
+        let mut memory = MockMemory::new();
+        // The Security Extension is implemented.
+        memory.add_word_range(IdPfr1::get_mmio_address(), &[0x0000_0010]);
+        // Enabled, with one region, and the firmware has selected region 3. The region marks
+        // 0x2000_0000 - 0x2000_0FFF as Non-secure callable.
+        memory.add_word_range(
+            SauCtrl::get_mmio_address(),
+            &[0x0000_0001, 0x0000_0001, 3, 0x2000_0000, 0x2000_0FE3],
+        );
+
+        let sau = Sau::read(&mut memory).unwrap().unwrap();
+
+        assert_eq!(memory.read_word_32(SauRnr::get_mmio_address()).unwrap(), 3);
+        assert!(sau.enabled);
+        assert_eq!(
+            sau.regions,
+            [SauRegion {
+                number: 0,
+                enabled: true,
+                start: 0x2000_0000,
+                end: 0x2000_0FFF,
+                non_secure_callable: true,
+            }]
+        );
+    }
+}
@@ -42,6 +42,30 @@ pub(crate) const XPSR: CoreRegister = CoreRegister {
     unwind_rule: UnwindRule::Preserve,
 };
 
+/// CONTROL_S bits [31:24], FAULTMASK_S bits [23:16], BASEPRI_S bits [15:8], and PRIMASK_S
+/// bits [7:0].
+pub(crate) const EXTRA_S: CoreRegister = CoreRegister {
+    roles: &[
+        RegisterRole::Core("EXTRA_S"),
+        RegisterRole::Other("EXTRA_S"),
+    ],
+    id: RegisterId(0b10_0010),
+    data_type: RegisterDataType::UnsignedInteger(32),
+    unwind_rule: UnwindRule::SpecialRule,
+};
+
+/// CONTROL_NS bits [31:24], FAULTMASK_NS bits [23:16], BASEPRI_NS bits [15:8], and PRIMASK_NS
+/// bits [7:0].
+pub(crate) const EXTRA_NS: CoreRegister = CoreRegister {
+    roles: &[
+        RegisterRole::Core("EXTRA_NS"),
+        RegisterRole::Other("EXTRA_NS"),
+    ],
+    id: RegisterId(0b10_0011),
+    data_type: RegisterDataType::UnsignedInteger(32),
+    unwind_rule: UnwindRule::SpecialRule,
+};
+
 /// All off the Cortex-M core registers.
 pub(crate) static CORTEX_M_CORE_REGISTERS: LazyLock<CoreRegisters> = LazyLock::new(|| {
     CoreRegisters::new(
@@ -62,6 +86,30 @@ pub(crate) static CORTEX_M_WITH_FP_CORE_REGISTERS: LazyLock<CoreRegisters> = Laz
     )
 });
 
+/// The Cortex-M core registers, with the banked registers of the ARMv8-M Security Extension.
+pub(crate) static CORTEX_M_WITH_SECURITY_CORE_REGISTERS: LazyLock<CoreRegisters> =
+    LazyLock::new(|| {
+        CoreRegisters::new(
+            ARM32_COMMON_REGS_SET
+                .iter()
+                .chain(CORTEX_M_COMMON_REGS_SET)
+                .chain(ARMV8M_SECURITY_REGS_SET)
+                .collect(),
+        )
+    });
+
+pub(crate) static CORTEX_M_WITH_SECURITY_AND_FP_CORE_REGISTERS: LazyLock<CoreRegisters> =
+    LazyLock::new(|| {
+        CoreRegisters::new(
+            ARM32_COMMON_REGS_SET
+                .iter()
+                .chain(CORTEX_M_COMMON_REGS_SET)
+                .chain(ARMV8M_SECURITY_REGS_SET)
+                .chain(CORTEX_M_WITH_FP_REGS_SET)
+                .collect(),
+        )
+    });
+
 pub(super) static ARM32_COMMON_REGS_SET: &[CoreRegister] = &[
     CoreRegister {
         roles: &[
@@ -173,6 +221,62 @@ static CORTEX_M_COMMON_REGS_SET: &[CoreRegister] = &[
     },
 ];
 
+/// The banked stack pointers and stack limits of the ARMv8-M Security Extension, and the
+/// banked `EXTRA` registers.
+static ARMV8M_SECURITY_REGS_SET: &[CoreRegister] = &[
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSP_NS")],
+        id: RegisterId(0b11000),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSP_NS")],
+        id: RegisterId(0b11001),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSP_S")],
+        id: RegisterId(0b11010),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSP_S")],
+        id: RegisterId(0b11011),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSPLIM_S")],
+        id: RegisterId(0b11100),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSPLIM_S")],
+        id: RegisterId(0b11101),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSPLIM_NS")],
+        id: RegisterId(0b11110),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSPLIM_NS")],
+        id: RegisterId(0b11111),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    // The banked CONTROL, FAULTMASK, BASEPRI and PRIMASK registers, in the same layout as EXTRA.
+    EXTRA_S,
+    EXTRA_NS,
+];
+
 static CORTEX_M_WITH_FP_REGS_SET: &[CoreRegister] = &[
     CoreRegister {
         roles: &[
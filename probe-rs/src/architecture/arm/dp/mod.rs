@@ -2,6 +2,11 @@
 
 #[macro_use]
 mod register_generation;
+mod multidrop;
+
+pub use multidrop::{
+    discover_multidrop_debug_ports, known_targetsels, targetsel_candidates, MultidropDebugPort,
+};
 
 use super::{
     communication_interface::RegisterParseError, ArmError, DapAccess, DapError, DpAddress, Register,
@@ -0,0 +1,365 @@
+//! Discovery of the debug ports on an SWD multidrop bus.
+//!
+//! With SWD multidrop, several debug ports share the same SWD lines. A debug port is selected by
+//! writing its `TARGETSEL` value, which consists of its `TARGETID` and its instance ID. As the
+//! `TARGETSEL` write is not acknowledged, the debug ports can only be found by trying candidate
+//! values.
+
+use jep106::JEP106Code;
+use probe_rs_target::CoreAccessOptions;
+
+use super::{DebugPortId, DebugPortVersion, Select, DLPIDR, DPIDR, TARGETID};
+use crate::{
+    architecture::arm::{
+        communication_interface::DapProbe,
+        sequences::{swd_activate_from_dormant, swd_line_reset, write_targetsel},
+        ArmError, DpAddress, PortType, Register,
+    },
+    config::registry,
+    probe::{DebugProbeError, WireProtocol},
+};
+
+/// The bits of a `TARGETSEL` value which hold the instance ID.
+const INSTANCE_MASK: u32 = 0xF000_0000;
+
+/// A debug port which responded on an SWD multidrop bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultidropDebugPort {
+    /// The `TARGETSEL` value which selects the debug port.
+    pub targetsel: u32,
+    /// The value of the `DPIDR` register.
+    pub dpidr: u32,
+    /// The value of the `TARGETID` register.
+    pub targetid: u32,
+    /// The value of the `DLPIDR` register.
+    pub dlpidr: u32,
+}
+
+impl MultidropDebugPort {
+    /// The address used to access the debug port.
+    pub fn address(&self) -> DpAddress {
+        DpAddress::Multidrop(self.targetsel)
+    }
+
+    /// The identification of the debug port.
+    pub fn debug_port_id(&self) -> DebugPortId {
+        DPIDR(self.dpidr).into()
+    }
+
+    /// The JEP106 code of the designer of the target.
+    pub fn designer(&self) -> JEP106Code {
+        let designer = TARGETID(self.targetid).tdesigner();
+        JEP106Code::new((designer >> 7) as u8, (designer & 0x7f) as u8)
+    }
+
+    /// The part number of the target.
+    pub fn part(&self) -> u16 {
+        TARGETID(self.targetid).tpartno()
+    }
+
+    /// The revision of the target.
+    pub fn revision(&self) -> u8 {
+        TARGETID(self.targetid).trevision()
+    }
+
+    /// The instance ID, which tells apart debug ports with the same `TARGETID`.
+    pub fn instance(&self) -> u8 {
+        DLPIDR(self.dlpidr).tinstance()
+    }
+}
+
+/// Returns the `TARGETSEL` values used by the targets in the registry.
+pub fn known_targetsels() -> Vec<u32> {
+    let mut targetsels = vec![];
+
+    for family in registry::families_ref().iter() {
+        for chip in &family.variants {
+            for core in &chip.cores {
+                if let CoreAccessOptions::Arm(options) = &core.core_access_options {
+                    if options.psel != 0 && !targetsels.contains(&options.psel) {
+                        targetsels.push(options.psel);
+                    }
+                }
+            }
+        }
+    }
+
+    targetsels
+}
+
+/// Returns the `TARGETSEL` values for all 16 instance IDs of each `TARGETID` in `targetids`.
+///
+/// The instance IDs of `targetids` are ignored.
+pub fn targetsel_candidates(targetids: impl IntoIterator<Item = u32>) -> Vec<u32> {
+    let mut candidates = vec![];
+
+    for targetid in targetids {
+        for instance in 0..16 {
+            let targetsel = (targetid & !INSTANCE_MASK) | (instance << 28);
+            if !candidates.contains(&targetsel) {
+                candidates.push(targetsel);
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Finds the debug ports on the SWD multidrop bus which respond to one of the `candidates`.
+///
+/// The bus is woken up from the dormant state first. Each candidate is selected after a line
+/// reset, and reported if it responds, and its `TARGETID` and `DLPIDR` registers match the
+/// `TARGETSEL` value. The debug ports are not powered up.
+pub fn discover_multidrop_debug_ports(
+    probe: &mut dyn DapProbe,
+    candidates: &[u32],
+) -> Result<Vec<MultidropDebugPort>, ArmError> {
+    if let Some(protocol @ WireProtocol::Jtag) = probe.active_protocol() {
+        return Err(DebugProbeError::UnsupportedProtocol(protocol).into());
+    }
+
+    swd_line_reset(probe, 0)?;
+    swd_activate_from_dormant(probe)?;
+
+    let mut found = vec![];
+    for &targetsel in candidates {
+        match try_select(probe, targetsel) {
+            Ok(Some(dp)) => {
+                tracing::info!("Found debug port with TARGETSEL {targetsel:#010x}");
+                found.push(dp);
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::debug!("No response for TARGETSEL {targetsel:#010x}: {error}");
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+fn try_select(
+    probe: &mut dyn DapProbe,
+    targetsel: u32,
+) -> Result<Option<MultidropDebugPort>, ArmError> {
+    swd_line_reset(probe, 3)?;
+    write_targetsel(probe, targetsel)?;
+
+    let dpidr = probe.raw_read_register(PortType::DebugPort, DPIDR::ADDRESS)?;
+
+    // A debug port without multidrop support ignores TARGETSEL, and responds to every
+    // candidate. It is filtered out by checking TARGETID and DLPIDR.
+    if DebugPortId::from(DPIDR(dpidr)).version < DebugPortVersion::DPv2 {
+        return Ok(None);
+    }
+
+    probe.raw_write_register(PortType::DebugPort, Select::ADDRESS, 2)?;
+    let targetid = probe.raw_read_register(PortType::DebugPort, TARGETID::ADDRESS & 0xf)?;
+    probe.raw_write_register(PortType::DebugPort, Select::ADDRESS, 3)?;
+    let dlpidr = probe.raw_read_register(PortType::DebugPort, DLPIDR::ADDRESS & 0xf)?;
+    probe.raw_write_register(PortType::DebugPort, Select::ADDRESS, 0)?;
+    probe.raw_flush()?;
+
+    let dp = MultidropDebugPort {
+        targetsel,
+        dpidr,
+        targetid,
+        dlpidr,
+    };
+
+    let matches = (targetid & !INSTANCE_MASK) == (targetsel & !INSTANCE_MASK)
+        && u32::from(dp.instance()) == targetsel >> 28;
+
+    Ok(matches.then_some(dp))
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs_target::ScanChainElement;
+
+    use super::*;
+    use crate::{
+        architecture::arm::{DapError, RawDapAccess},
+        probe::DebugProbe,
+    };
+
+    /// The TARGETID of the RP2040.
+    const RP2040: u32 = 0x0100_2927;
+
+    /// A DPv2 DPIDR, designed by ARM.
+    const DPIDR_V2: u32 = 0x0bc1_2477;
+
+    /// A multidrop bus with debug ports at the given `TARGETSEL` values.
+    #[derive(Debug)]
+    struct MockBus {
+        dps: Vec<u32>,
+        selected: Option<u32>,
+        bank: u32,
+    }
+
+    impl RawDapAccess for MockBus {
+        fn raw_read_register(&mut self, port: PortType, addr: u8) -> Result<u32, ArmError> {
+            assert_eq!(port, PortType::DebugPort);
+            let Some(targetsel) = self.selected else {
+                return Err(DapError::NoAcknowledge.into());
+            };
+
+            match (addr, self.bank) {
+                (0x0, _) => Ok(DPIDR_V2),
+                (0x4, 2) => Ok(targetsel & !INSTANCE_MASK),
+                (0x4, 3) => Ok((targetsel & INSTANCE_MASK) | 1),
+                _ => Ok(0),
+            }
+        }
+
+        fn raw_write_register(
+            &mut self,
+            _port: PortType,
+            addr: u8,
+            value: u32,
+        ) -> Result<(), ArmError> {
+            if self.selected.is_none() {
+                return Err(DapError::NoAcknowledge.into());
+            }
+            if addr == Select::ADDRESS {
+                self.bank = value & 0xf;
+            }
+            Ok(())
+        }
+
+        fn jtag_sequence(
+            &mut self,
+            _cycles: u8,
+            _tms: bool,
+            _tdi: u64,
+        ) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+            if bit_len == 48 && bits & 0x1fff == 0x1f99 {
+                let targetsel = (bits >> 13) as u32;
+                self.selected = self.dps.iter().copied().find(|dp| *dp == targetsel);
+            } else if bit_len >= 51 {
+                self.selected = None;
+            }
+            Ok(())
+        }
+
+        fn swj_pins(
+            &mut self,
+            _pin_out: u32,
+            _pin_select: u32,
+            _pin_wait: u32,
+        ) -> Result<u32, DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+            self
+        }
+
+        fn core_status_notification(
+            &mut self,
+            _state: crate::CoreStatus,
+        ) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+    }
+
+    impl DapProbe for MockBus {}
+
+    impl DebugProbe for MockBus {
+        fn get_name(&self) -> &str {
+            "Mock multidrop bus"
+        }
+
+        fn speed_khz(&self) -> u32 {
+            1000
+        }
+
+        fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+            Ok(speed_khz)
+        }
+
+        fn set_scan_chain(
+            &mut self,
+            _scan_chain: Vec<ScanChainElement>,
+        ) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn attach(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn detach(&mut self) -> Result<(), crate::Error> {
+            Ok(())
+        }
+
+        fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn select_protocol(&mut self, _protocol: WireProtocol) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn active_protocol(&self) -> Option<WireProtocol> {
+            Some(WireProtocol::Swd)
+        }
+
+        fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+            self
+        }
+    }
+
+    #[test]
+    fn candidates_cover_all_instances() {
+        let candidates = targetsel_candidates([0x1100_2927, RP2040]);
+
+        assert_eq!(candidates.len(), 16);
+        assert_eq!(candidates[0], RP2040);
+        assert_eq!(candidates[15], 0xF100_2927);
+    }
+
+    #[test]
+    fn registry_contains_rp2040() {
+        let known = known_targetsels();
+
+        assert!(known.contains(&0x0100_2927));
+        assert!(known.contains(&0x1100_2927));
+    }
+
+    #[test]
+    fn responding_debug_ports_are_found() {
+        let mut bus = MockBus {
+            dps: vec![RP2040, 0x1100_2927, 0xF100_2927],
+            selected: None,
+            bank: 0,
+        };
+
+        let found =
+            discover_multidrop_debug_ports(&mut bus, &targetsel_candidates([RP2040])).unwrap();
+
+        let targetsels: Vec<_> = found.iter().map(|dp| dp.targetsel).collect();
+        assert_eq!(targetsels, [RP2040, 0x1100_2927, 0xF100_2927]);
+
+        assert_eq!(found[1].instance(), 1);
+        assert_eq!(found[1].part(), 0x1002);
+        assert_eq!(found[1].designer().get(), Some("Raspberry Pi Trading Ltd"));
+        assert_eq!(found[1].address(), DpAddress::Multidrop(0x1100_2927));
+    }
+}
@@ -0,0 +1,217 @@
+//! Packet decoder for the ETMv3 protocol.
+//!
+//! The decoder assumes the configuration used by [`crate::architecture::arm::component::Etm`]:
+//! no cycle accurate tracing, no data tracing, and no context ID tracing.
+
+use super::{a_sync_length, decode_branch_address, read_continued, Atom, TraceIsa, TracePacket};
+
+pub(crate) fn decode(data: &[u8]) -> Vec<TracePacket> {
+    let mut decoder = Decoder {
+        packets: Vec::new(),
+        address: 0,
+        isa: TraceIsa::Thumb,
+    };
+
+    let mut synchronized = false;
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+
+        if let Some(length) = a_sync_length(rest) {
+            synchronized = true;
+            offset += length;
+            continue;
+        }
+
+        if !synchronized {
+            offset += 1;
+            continue;
+        }
+
+        match decoder.packet(rest) {
+            Some(length) => offset += length,
+            None => {
+                // Unknown or truncated packet, wait for the next synchronization.
+                tracing::debug!("Lost ETMv3 synchronization at offset {offset}");
+                synchronized = false;
+                offset += 1;
+            }
+        }
+    }
+
+    decoder.packets
+}
+
+struct Decoder {
+    packets: Vec<TracePacket>,
+    address: u64,
+    isa: TraceIsa,
+}
+
+impl Decoder {
+    /// Decode a single packet, returning its length.
+    fn packet(&mut self, data: &[u8]) -> Option<usize> {
+        let header = data[0];
+
+        if header & 0x01 == 0x01 {
+            // Branch address packet. Cortex-M ETMs use the alternative address encoding.
+            let branch = decode_branch_address(data, self.address, self.isa, true)?;
+            self.address = branch.address;
+            self.isa = branch.isa;
+            if let Some(number) = branch.exception {
+                self.packets.push(TracePacket::Exception {
+                    number,
+                    return_address: None,
+                });
+            }
+            self.packets.push(TracePacket::Address {
+                address: branch.address,
+                isa: branch.isa,
+            });
+            return Some(branch.length);
+        }
+
+        if header & 0x83 == 0x80 {
+            // P-header format 1: a number of E atoms, followed by zero or one N atom.
+            let executed = (header >> 2) & 0x0F;
+            for _ in 0..executed {
+                self.packets.push(TracePacket::Atom(Atom::Executed));
+            }
+            if header & 0x40 != 0 {
+                self.packets.push(TracePacket::Atom(Atom::NotExecuted));
+            }
+            return Some(1);
+        }
+
+        if header & 0xF3 == 0x82 {
+            // P-header format 2: two atoms.
+            for bit in [0x08, 0x04] {
+                self.packets.push(TracePacket::Atom(if header & bit == 0 {
+                    Atom::Executed
+                } else {
+                    Atom::NotExecuted
+                }));
+            }
+            return Some(1);
+        }
+
+        match header {
+            // I-sync, optionally with cycle count.
+            0x08 | 0x70 => {
+                let mut length = 1;
+                if header == 0x70 {
+                    length += read_continued(&data[length..], 5)?.1;
+                }
+                let info = *data.get(length)?;
+                let address =
+                    u32::from_le_bytes(data.get(length + 1..length + 5)?.try_into().ok()?);
+                length += 5;
+
+                match (info >> 5) & 0x03 {
+                    0b01 | 0b11 => self.packets.push(TracePacket::TraceOn),
+                    0b10 => self.packets.push(TracePacket::Overflow),
+                    _ => {}
+                }
+
+                self.isa = if address & 1 == 1 {
+                    TraceIsa::Thumb
+                } else {
+                    TraceIsa::Arm
+                };
+                self.address = u64::from(address & !1);
+                self.packets.push(TracePacket::Sync {
+                    address: self.address,
+                    isa: self.isa,
+                });
+                Some(length)
+            }
+            // Cycle count.
+            0x04 => Some(1 + read_continued(&data[1..], 5)?.1),
+            // Trigger, ignore, and exception entry (only traced with data trace).
+            0x0C | 0x66 | 0x7E => Some(1),
+            // Context ID, with a configured size of zero bytes.
+            0x6E => Some(1),
+            // VMID.
+            0x3C => Some(2),
+            // Timestamp.
+            0x42 | 0x46 => Some(1 + read_continued(&data[1..], 9)?.1),
+            // Exception exit.
+            0x76 => {
+                self.packets.push(TracePacket::ExceptionReturn);
+                Some(1)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const A_SYNC: [u8; 6] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x80];
+
+    #[test]
+    fn ignores_data_before_a_sync() {
+        let mut data = vec![0x84, 0x08];
+        data.extend_from_slice(&A_SYNC);
+        data.extend_from_slice(&[0x08, 0x00, 0x01, 0x02, 0x00, 0x08]);
+
+        assert_eq!(
+            decode(&data),
+            vec![TracePacket::Sync {
+                address: 0x0800_0200,
+                isa: TraceIsa::Thumb
+            }]
+        );
+    }
+
+    #[test]
+    fn p_headers() {
+        let mut data = A_SYNC.to_vec();
+        // Format 1 with two E atoms and one N atom, format 2 with E, N.
+        data.extend_from_slice(&[0xC8, 0x86]);
+
+        assert_eq!(
+            decode(&data),
+            vec![
+                TracePacket::Atom(Atom::Executed),
+                TracePacket::Atom(Atom::Executed),
+                TracePacket::Atom(Atom::NotExecuted),
+                TracePacket::Atom(Atom::Executed),
+                TracePacket::Atom(Atom::NotExecuted),
+            ]
+        );
+    }
+
+    #[test]
+    fn i_sync_after_overflow_and_exception() {
+        let mut data = A_SYNC.to_vec();
+        // I-sync with reason "overflow", at 0x0800_0100 in Thumb state.
+        data.extend_from_slice(&[0x08, 0x40, 0x01, 0x01, 0x00, 0x08]);
+        // Branch to 0x0800_0040 with exception 3 (HardFault).
+        data.extend_from_slice(&[0x81, 0x40, 0x06]);
+        // Exception exit.
+        data.push(0x76);
+
+        assert_eq!(
+            decode(&data),
+            vec![
+                TracePacket::Overflow,
+                TracePacket::Sync {
+                    address: 0x0800_0100,
+                    isa: TraceIsa::Thumb
+                },
+                TracePacket::Exception {
+                    number: 3,
+                    return_address: None
+                },
+                TracePacket::Address {
+                    address: 0x0800_0000,
+                    isa: TraceIsa::Thumb
+                },
+                TracePacket::ExceptionReturn,
+            ]
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! Packet decoder for the ETMv4 instruction trace protocol.
+//!
+//! Data trace and Q packets are not supported, since the instruction trace configured by
+//! [`crate::architecture::arm::component::Etm`] does not generate them.
+
+use super::{read_continued, Atom, TraceIsa, TracePacket};
+
+/// The length of the ETMv4 alignment synchronization packet: eleven `0x00` bytes followed by `0x80`.
+const A_SYNC_LENGTH: usize = 12;
+
+pub(crate) fn decode(data: &[u8]) -> Vec<TracePacket> {
+    let mut decoder = Decoder {
+        packets: Vec::new(),
+        history: [(0, TraceIsa::Thumb); 3],
+        pending_exception: None,
+        synchronize_on_address: true,
+    };
+
+    let mut synchronized = false;
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+
+        if is_a_sync(rest) {
+            synchronized = true;
+            offset += A_SYNC_LENGTH;
+            continue;
+        }
+
+        if !synchronized {
+            offset += 1;
+            continue;
+        }
+
+        match decoder.packet(rest) {
+            Some(length) => offset += length,
+            None => {
+                tracing::debug!("Lost ETMv4 synchronization at offset {offset}");
+                synchronized = false;
+                offset += 1;
+            }
+        }
+    }
+
+    decoder.packets
+}
+
+fn is_a_sync(data: &[u8]) -> bool {
+    data.len() >= A_SYNC_LENGTH
+        && data[..A_SYNC_LENGTH - 1].iter().all(|&byte| byte == 0)
+        && data[A_SYNC_LENGTH - 1] == 0x80
+}
+
+struct Decoder {
+    packets: Vec<TracePacket>,
+    /// The address history, most recent address first.
+    history: [(u64, TraceIsa); 3],
+    /// An exception packet, waiting for its return address.
+    pending_exception: Option<u16>,
+    /// The next address is the first after a trace info, trace on or overflow packet.
+    synchronize_on_address: bool,
+}
+
+impl Decoder {
+    /// Decode a single packet, returning its length.
+    fn packet(&mut self, data: &[u8]) -> Option<usize> {
+        let header = data[0];
+
+        match header {
+            // Extension packets.
+            0x00 => match *data.get(1)? {
+                // Discard.
+                0x03 => {
+                    self.trace_lost(TracePacket::TraceOn);
+                    Some(2)
+                }
+                // Overflow.
+                0x05 => {
+                    self.trace_lost(TracePacket::Overflow);
+                    Some(2)
+                }
+                _ => None,
+            },
+            // Trace info.
+            0x01 => {
+                let (control, mut length) = read_continued(&data[1..], 4)?;
+                length += 1;
+                for field in 0..4 {
+                    if control & (1 << field) != 0 {
+                        length += read_continued(&data[length..], 5)?.1;
+                    }
+                }
+                self.history = [(0, TraceIsa::Thumb); 3];
+                self.pending_exception = None;
+                self.synchronize_on_address = true;
+                Some(length)
+            }
+            // Timestamp, optionally with cycle count.
+            0x02 | 0x03 => {
+                let mut length = 1 + read_continued(&data[1..], 9)?.1;
+                if header == 0x03 {
+                    length += read_continued(&data[length..], 3)?.1;
+                }
+                Some(length)
+            }
+            // Trace on.
+            0x04 => {
+                self.trace_lost(TracePacket::TraceOn);
+                Some(1)
+            }
+            // Function return (only ARMv8-M), which has no payload.
+            0x05 => Some(1),
+            // Exception, followed by an address packet with the preferred return address.
+            0x06 => {
+                let first = *data.get(1)?;
+                let mut number = u16::from((first >> 1) & 0x1F);
+                let mut length = 2;
+                if first & 0x80 != 0 {
+                    number |= u16::from(*data.get(2)? & 0x1F) << 5;
+                    length += 1;
+                }
+                self.pending_exception = Some(number);
+                Some(length)
+            }
+            // Exception return.
+            0x07 => {
+                self.packets.push(TracePacket::ExceptionReturn);
+                Some(1)
+            }
+            // Cycle count formats 2, 1 and 3.
+            0x0C | 0x0D => Some(2),
+            0x0E | 0x0F => Some(1 + read_continued(&data[1..], 3)?.1),
+            0x10..=0x1F => Some(1),
+            // Commit and cancel format 1.
+            0x2D..=0x2F => Some(1 + read_continued(&data[1..], 5)?.1),
+            // Mispredict, cancel formats 2 and 3.
+            0x30..=0x3F => Some(1),
+            // Event.
+            0x70..=0x7F => Some(1),
+            // Context, either unchanged or with a context info byte.
+            0x80 => Some(1),
+            0x81 => Some(1 + context_length(&data[1..])?),
+            // Long addresses with context.
+            0x82 | 0x83 | 0x85 | 0x86 => {
+                let (address, isa, length) = long_address(header, &data[1..])?;
+                let length = 1 + length + context_length(data.get(1 + length..)?)?;
+                self.address(address, isa);
+                Some(length)
+            }
+            // Exact match address.
+            0x90..=0x92 => {
+                let (address, isa) = self.history[usize::from(header & 0x03)];
+                self.address(address, isa);
+                Some(1)
+            }
+            // Short address.
+            0x95 | 0x96 => {
+                let (previous, _) = self.history[0];
+                let (bits, shift, isa) = if header == 0x95 {
+                    (7, 2, TraceIsa::Arm)
+                } else {
+                    (7, 1, TraceIsa::Thumb)
+                };
+
+                let first = *data.get(1)?;
+                let mut value = u64::from(first & 0x7F);
+                let mut length = 2;
+                let mut width = bits;
+                if first & 0x80 != 0 {
+                    value |= u64::from(*data.get(2)?) << bits;
+                    width += 8;
+                    length += 1;
+                }
+
+                let mask = ((1u64 << width) - 1) << shift;
+                self.address((previous & !mask) | (value << shift), isa);
+                Some(length)
+            }
+            // Long address.
+            0x9A | 0x9B | 0x9D | 0x9E => {
+                let (address, isa, length) = long_address(header, &data[1..])?;
+                self.address(address, isa);
+                Some(1 + length)
+            }
+            // Atom format 6.
+            0xC0..=0xD4 | 0xE0..=0xF4 => {
+                let executed = (header & 0x1F) + 3;
+                for _ in 0..executed {
+                    self.atom(true);
+                }
+                self.atom(header & 0x20 == 0);
+                Some(1)
+            }
+            // Atom format 5.
+            0xD5..=0xD7 | 0xF5 => {
+                let pattern = match ((header & 0x20) >> 3) | (header & 0x03) {
+                    5 => 0x1E,
+                    1 => 0x00,
+                    2 => 0x0A,
+                    _ => 0x15,
+                };
+                self.atoms(pattern, 5);
+                Some(1)
+            }
+            // Atom format 2.
+            0xD8..=0xDB => {
+                self.atoms(header, 2);
+                Some(1)
+            }
+            // Atom format 4.
+            0xDC..=0xDF => {
+                let pattern = [0x0E, 0x00, 0x0A, 0x05][usize::from(header & 0x03)];
+                self.atoms(pattern, 4);
+                Some(1)
+            }
+            // Atom format 1.
+            0xF6 | 0xF7 => {
+                self.atoms(header, 1);
+                Some(1)
+            }
+            // Atom format 3.
+            0xF8..=0xFF => {
+                self.atoms(header, 3);
+                Some(1)
+            }
+            _ => None,
+        }
+    }
+
+    fn trace_lost(&mut self, packet: TracePacket) {
+        self.packets.push(packet);
+        self.pending_exception = None;
+        self.synchronize_on_address = true;
+    }
+
+    /// Add atoms from a bit pattern, where the lowest bit is the oldest atom and a set bit is an E atom.
+    fn atoms(&mut self, pattern: u8, count: u8) {
+        for index in 0..count {
+            self.atom(pattern & (1 << index) != 0);
+        }
+    }
+
+    fn atom(&mut self, executed: bool) {
+        self.packets.push(TracePacket::Atom(if executed {
+            Atom::Executed
+        } else {
+            Atom::NotExecuted
+        }));
+    }
+
+    fn address(&mut self, address: u64, isa: TraceIsa) {
+        self.history = [(address, isa), self.history[0], self.history[1]];
+
+        if let Some(number) = self.pending_exception.take() {
+            self.packets.push(TracePacket::Exception {
+                number,
+                return_address: Some(address),
+            });
+        } else if self.synchronize_on_address {
+            self.synchronize_on_address = false;
+            self.packets.push(TracePacket::Sync { address, isa });
+        } else {
+            self.packets.push(TracePacket::Address { address, isa });
+        }
+    }
+}
+
+/// Decode the payload of a long address packet, returning the address, instruction set and the
+/// length of the payload.
+fn long_address(header: u8, data: &[u8]) -> Option<(u64, TraceIsa, usize)> {
+    let (isa, length) = match header {
+        0x82 | 0x9A => (TraceIsa::Arm, 4),
+        0x83 | 0x9B => (TraceIsa::Thumb, 4),
+        0x85 | 0x9D => (TraceIsa::Arm, 8),
+        _ => (TraceIsa::Thumb, 8),
+    };
+    let bytes = data.get(..length)?;
+
+    let mut address = match isa {
+        TraceIsa::Arm => {
+            (u64::from(bytes[0] & 0x7F) << 2)
+                | (u64::from(bytes[1] & 0x7F) << 9)
+                | (u64::from(bytes[2]) << 16)
+        }
+        TraceIsa::Thumb => {
+            (u64::from(bytes[0] & 0x7F) << 1)
+                | (u64::from(bytes[1]) << 8)
+                | (u64::from(bytes[2]) << 16)
+        }
+    };
+    for (index, &byte) in bytes.iter().enumerate().skip(3) {
+        address |= u64::from(byte) << (8 * index);
+    }
+
+    Some((address, isa, length))
+}
+
+/// The length of a context info byte and the context values which follow it.
+fn context_length(data: &[u8]) -> Option<usize> {
+    let info = *data.first()?;
+    let mut length = 1;
+    if info & 0x40 != 0 {
+        // VMID.
+        length += 1;
+    }
+    if info & 0x80 != 0 {
+        // Context ID.
+        length += 4;
+    }
+    (data.len() >= length).then_some(length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const A_SYNC: [u8; 12] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80];
+
+    fn trace(packets: &[u8]) -> Vec<u8> {
+        let mut data = A_SYNC.to_vec();
+        // Trace info with no optional fields.
+        data.extend_from_slice(&[0x01, 0x00]);
+        data.extend_from_slice(packets);
+        data
+    }
+
+    #[test]
+    fn first_address_is_sync() {
+        // Long Thumb address 0x0800_0134, followed by short address 0x0800_0140.
+        let data = trace(&[0x9B, 0x1A, 0x01, 0x00, 0x08, 0x96, 0x20]);
+
+        assert_eq!(
+            decode(&data),
+            vec![
+                TracePacket::Sync {
+                    address: 0x0800_0134,
+                    isa: TraceIsa::Thumb
+                },
+                TracePacket::Address {
+                    address: 0x0800_0140,
+                    isa: TraceIsa::Thumb
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn atom_formats() {
+        // Format 1 (E), format 2 (E, N), format 3 (N, N, E), format 6 (3 x E, N).
+        let data = trace(&[0xF7, 0xD9, 0xFC, 0xE0]);
+
+        let executed = TracePacket::Atom(Atom::Executed);
+        let not_executed = TracePacket::Atom(Atom::NotExecuted);
+        assert_eq!(
+            decode(&data),
+            vec![
+                executed.clone(),
+                executed.clone(),
+                not_executed.clone(),
+                not_executed.clone(),
+                not_executed.clone(),
+                executed.clone(),
+                executed.clone(),
+                executed.clone(),
+                executed,
+                not_executed,
+            ]
+        );
+    }
+
+    #[test]
+    fn exception_with_return_address() {
+        // Sync, then exception 3 with the return address in the address history.
+        let data = trace(&[0x9B, 0x1A, 0x01, 0x00, 0x08, 0x06, 0x06, 0x90]);
+
+        assert_eq!(
+            decode(&data),
+            vec![
+                TracePacket::Sync {
+                    address: 0x0800_0134,
+                    isa: TraceIsa::Thumb
+                },
+                TracePacket::Exception {
+                    number: 3,
+                    return_address: Some(0x0800_0134)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        let data = trace(&[0x00, 0x05, 0x9B, 0x1A, 0x01, 0x00, 0x08]);
+
+        assert_eq!(
+            decode(&data),
+            vec![
+                TracePacket::Overflow,
+                TracePacket::Sync {
+                    address: 0x0800_0134,
+                    isa: TraceIsa::Thumb
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! Classification of A32 and T32 instructions, to follow the program flow between traced branches.
+
+use super::{ProgramImage, TraceIsa};
+
+/// The information about an instruction which is required to follow the program flow.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    /// The size of the instruction in bytes.
+    pub(crate) size: u64,
+    /// The branch target, if the instruction is a branch.
+    pub(crate) branch: Option<Branch>,
+}
+
+/// The target of a branch instruction.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Branch {
+    /// The target can be determined from the instruction encoding.
+    Direct { address: u64, isa: TraceIsa },
+    /// The target depends on register or memory contents, and is traced with an address packet.
+    Indirect,
+}
+
+/// Decode the instruction at `address`, or `None` if it is not part of the program image.
+pub(crate) fn decode(image: &ProgramImage, address: u64, isa: TraceIsa) -> Option<Instruction> {
+    match isa {
+        TraceIsa::Thumb => {
+            let first = image.read_u16(address)?;
+            if first >> 11 >= 0b11101 {
+                let second = image.read_u16(address + 2)?;
+                Some(Instruction {
+                    size: 4,
+                    branch: decode_thumb32(address, first, second),
+                })
+            } else {
+                Some(Instruction {
+                    size: 2,
+                    branch: decode_thumb16(address, first),
+                })
+            }
+        }
+        TraceIsa::Arm => Some(Instruction {
+            size: 4,
+            branch: decode_arm(address, image.read_u32(address)?),
+        }),
+    }
+}
+
+/// Sign extend the lowest `bits` bits of `value`.
+fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 32 - bits;
+    i64::from(((value << shift) as i32) >> shift)
+}
+
+fn relative(address: u64, offset: i64) -> u64 {
+    address.wrapping_add_signed(offset) & 0xFFFF_FFFF
+}
+
+fn decode_thumb16(address: u64, instruction: u16) -> Option<Branch> {
+    let pc = address + 4;
+    let value = u32::from(instruction);
+
+    if instruction & 0xF000 == 0xD000 && instruction & 0x0E00 != 0x0E00 {
+        // B<cond>, encoding T1. Condition codes 0b1110 and 0b1111 are UDF and SVC.
+        Some(Branch::Direct {
+            address: relative(pc, sign_extend((value & 0xFF) << 1, 9)),
+            isa: TraceIsa::Thumb,
+        })
+    } else if instruction & 0xF800 == 0xE000 {
+        // B, encoding T2.
+        Some(Branch::Direct {
+            address: relative(pc, sign_extend((value & 0x7FF) << 1, 12)),
+            isa: TraceIsa::Thumb,
+        })
+    } else if instruction & 0xF500 == 0xB100 {
+        // CBZ and CBNZ.
+        let offset = ((value & 0x0200) >> 3) | ((value & 0x00F8) >> 2);
+        Some(Branch::Direct {
+            address: pc + u64::from(offset),
+            isa: TraceIsa::Thumb,
+        })
+    } else if instruction & 0xFF00 == 0x4700
+        || instruction & 0xFF00 == 0xBD00
+        || instruction & 0xFF87 == 0x4687
+        || instruction & 0xFF87 == 0x4487
+    {
+        // BX, BLX (register), POP with PC, and MOV or ADD to PC.
+        Some(Branch::Indirect)
+    } else {
+        None
+    }
+}
+
+fn decode_thumb32(address: u64, first: u16, second: u16) -> Option<Branch> {
+    let pc = address + 4;
+    let first = u32::from(first);
+    let second = u32::from(second);
+
+    if first & 0xF800 == 0xF000 && second & 0x8000 == 0x8000 {
+        let s = (first >> 10) & 1;
+        let j1 = (second >> 13) & 1;
+        let j2 = (second >> 11) & 1;
+        let imm11 = second & 0x7FF;
+
+        match second & 0xD000 {
+            0x8000 => {
+                // B<cond>, encoding T3. Other encodings in this space are control instructions.
+                if (first >> 6) & 0x0E == 0x0E {
+                    return None;
+                }
+                let imm6 = first & 0x3F;
+                let offset = (s << 20) | (j2 << 19) | (j1 << 18) | (imm6 << 12) | (imm11 << 1);
+                return Some(Branch::Direct {
+                    address: relative(pc, sign_extend(offset, 21)),
+                    isa: TraceIsa::Thumb,
+                });
+            }
+            0x9000 | 0xD000 | 0xC000 => {
+                // B (encoding T4), BL and BLX (immediate).
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let imm10 = first & 0x3FF;
+                let offset = (s << 24) | (i1 << 23) | (i2 << 22) | (imm10 << 12) | (imm11 << 1);
+                let offset = sign_extend(offset, 25);
+
+                return Some(if second & 0xD000 == 0xC000 {
+                    Branch::Direct {
+                        address: relative(pc & !3, offset),
+                        isa: TraceIsa::Arm,
+                    }
+                } else {
+                    Branch::Direct {
+                        address: relative(pc, offset),
+                        isa: TraceIsa::Thumb,
+                    }
+                });
+            }
+            _ => return None,
+        }
+    }
+
+    let is_load_to_pc = first & 0xFF70 == 0xF850 && second >> 12 == 0xF;
+    let is_load_multiple_with_pc =
+        (first & 0xFFD0 == 0xE890 || first & 0xFFD0 == 0xE910) && second & 0x8000 != 0;
+    let is_table_branch = first & 0xFFF0 == 0xE8D0 && second & 0xFFE0 == 0xF000;
+
+    (is_load_to_pc || is_load_multiple_with_pc || is_table_branch).then_some(Branch::Indirect)
+}
+
+fn decode_arm(address: u64, instruction: u32) -> Option<Branch> {
+    let pc = address + 8;
+    let condition = instruction >> 28;
+
+    if instruction & 0x0E00_0000 == 0x0A00_0000 {
+        let offset = sign_extend((instruction & 0x00FF_FFFF) << 2, 26);
+        return Some(if condition == 0xF {
+            // BLX (immediate), which switches to Thumb.
+            let h = i64::from((instruction >> 23) & 0x2);
+            Branch::Direct {
+                address: relative(pc, offset + h),
+                isa: TraceIsa::Thumb,
+            }
+        } else {
+            // B and BL.
+            Branch::Direct {
+                address: relative(pc, offset),
+                isa: TraceIsa::Arm,
+            }
+        });
+    }
+
+    if condition == 0xF {
+        return None;
+    }
+
+    let is_branch_exchange = instruction & 0x0FFF_FFD0 == 0x012F_FF10;
+    let is_load_to_pc = instruction & 0x0C50_F000 == 0x0410_F000;
+    let is_load_multiple_with_pc = instruction & 0x0E10_8000 == 0x0810_8000;
+    let is_data_processing_to_pc =
+        instruction & 0x0C00_F000 == 0x0000_F000 && instruction & 0x0190_0000 != 0x0100_0000;
+
+    (is_branch_exchange || is_load_to_pc || is_load_multiple_with_pc || is_data_processing_to_pc)
+        .then_some(Branch::Indirect)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn thumb(address: u64, halfwords: &[u16]) -> Option<Instruction> {
+        let mut image = ProgramImage::new();
+        image.add_segment(
+            address,
+            halfwords.iter().flat_map(|h| h.to_le_bytes()).collect(),
+        );
+        decode(&image, address, TraceIsa::Thumb)
+    }
+
+    #[test]
+    fn thumb16_branches() {
+        // b.n 0x1000 from 0x1010.
+        assert_eq!(
+            thumb(0x1010, &[0xE7F6]).unwrap().branch,
+            Some(Branch::Direct {
+                address: 0x1000,
+                isa: TraceIsa::Thumb
+            })
+        );
+        // bne.n 0x1020 from 0x1010.
+        assert_eq!(
+            thumb(0x1010, &[0xD106]).unwrap().branch,
+            Some(Branch::Direct {
+                address: 0x1020,
+                isa: TraceIsa::Thumb
+            })
+        );
+        // cbz r0, 0x1020 from 0x1010.
+        assert_eq!(
+            thumb(0x1010, &[0xB130]).unwrap().branch,
+            Some(Branch::Direct {
+                address: 0x1020,
+                isa: TraceIsa::Thumb
+            })
+        );
+        // bx lr, pop {r4, pc}.
+        assert_eq!(
+            thumb(0x1010, &[0x4770]).unwrap().branch,
+            Some(Branch::Indirect)
+        );
+        assert_eq!(
+            thumb(0x1010, &[0xBD10]).unwrap().branch,
+            Some(Branch::Indirect)
+        );
+        // svc 0 and adds r0, #1 are not branches.
+        assert_eq!(thumb(0x1010, &[0xDF00]).unwrap().branch, None);
+        assert_eq!(
+            thumb(0x1010, &[0x3001]).unwrap(),
+            Instruction {
+                size: 2,
+                branch: None
+            }
+        );
+    }
+
+    #[test]
+    fn thumb32_branches() {
+        // bl 0x0800_0400 from 0x0800_0100.
+        assert_eq!(
+            thumb(0x0800_0100, &[0xF000, 0xF97E]).unwrap(),
+            Instruction {
+                size: 4,
+                branch: Some(Branch::Direct {
+                    address: 0x0800_0400,
+                    isa: TraceIsa::Thumb
+                })
+            }
+        );
+        // b.w 0x0800_0000 from 0x0800_0100.
+        assert_eq!(
+            thumb(0x0800_0100, &[0xF7FF, 0xBF7E]).unwrap().branch,
+            Some(Branch::Direct {
+                address: 0x0800_0000,
+                isa: TraceIsa::Thumb
+            })
+        );
+        // beq.w 0x0800_0200 from 0x0800_0100.
+        assert_eq!(
+            thumb(0x0800_0100, &[0xF000, 0x807E]).unwrap().branch,
+            Some(Branch::Direct {
+                address: 0x0800_0200,
+                isa: TraceIsa::Thumb
+            })
+        );
+        // pop.w {r4-r11, pc}, ldr.w pc, [sp], #4, tbb [pc, r0].
+        assert_eq!(
+            thumb(0, &[0xE8BD, 0x8FF0]).unwrap().branch,
+            Some(Branch::Indirect)
+        );
+        assert_eq!(
+            thumb(0, &[0xF85D, 0xFB04]).unwrap().branch,
+            Some(Branch::Indirect)
+        );
+        assert_eq!(
+            thumb(0, &[0xE8DF, 0xF000]).unwrap().branch,
+            Some(Branch::Indirect)
+        );
+        // ldr.w r0, [r1] is not a branch.
+        assert_eq!(thumb(0, &[0xF8D1, 0x0000]).unwrap().branch, None);
+    }
+
+    #[test]
+    fn arm_branches() {
+        let decode_at = |address, instruction| decode_arm(address, instruction);
+
+        // b 0x1000 from 0x1010.
+        assert_eq!(
+            decode_at(0x1010, 0xEAFF_FFFA),
+            Some(Branch::Direct {
+                address: 0x1000,
+                isa: TraceIsa::Arm
+            })
+        );
+        // bx lr, ldmia sp!, {r4, pc}.
+        assert_eq!(decode_at(0, 0xE12F_FF1E), Some(Branch::Indirect));
+        assert_eq!(decode_at(0, 0xE8BD_8010), Some(Branch::Indirect));
+        // mov r0, r1 and cmp r0, #1 are not branches.
+        assert_eq!(decode_at(0, 0xE1A0_0001), None);
+        assert_eq!(decode_at(0, 0xE350_0001), None);
+    }
+
+    #[test]
+    fn outside_of_image() {
+        assert_eq!(decode(&ProgramImage::new(), 0x1000, TraceIsa::Thumb), None);
+    }
+}
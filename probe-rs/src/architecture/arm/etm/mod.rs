@@ -0,0 +1,393 @@
+//! Decoding of ETM and PTM instruction trace.
+//!
+//! The Embedded Trace Macrocell (ETM) and its Cortex-A sibling, the Program Trace Macrocell (PTM),
+//! emit a compressed description of the program flow: the trace only contains the outcome of
+//! branches and the targets of indirect branches. Together with the program image, the executed
+//! instruction stream can be reconstructed from it.
+//!
+//! The trace is typically captured in an on-chip trace buffer (ETB/ETF), see
+//! [`crate::Session::setup_instruction_trace`] and [`crate::Session::read_instruction_trace`].
+//!
+//! The following trace protocols are supported:
+//! - ETMv3, as implemented by the Cortex-M3 and Cortex-M4 ETMs (ARM IHI 0014).
+//! - PFTv1, as implemented by the Cortex-A9 PTM (ARM IHI 0035).
+//! - ETMv4, as implemented by the Cortex-M7, Cortex-M33 and later ETMs (ARM IHI 0064),
+//!   without data trace.
+
+mod etmv3;
+mod etmv4;
+mod instructions;
+mod ptm;
+mod reconstruct;
+
+use std::ops::Range;
+
+use object::read::{Object, ObjectSection};
+
+use reconstruct::Reconstructor;
+
+/// The packet protocol used by a trace unit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceProtocol {
+    /// ETMv3, where each instruction is traced with an atom.
+    EtmV3,
+    /// The Program Flow Trace protocol of the PTM, where each branch is traced with an atom.
+    Ptm,
+    /// ETMv4, where each branch is traced with an atom.
+    EtmV4,
+}
+
+impl std::fmt::Display for TraceProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceProtocol::EtmV3 => write!(f, "ETMv3"),
+            TraceProtocol::Ptm => write!(f, "PFTv1 (PTM)"),
+            TraceProtocol::EtmV4 => write!(f, "ETMv4"),
+        }
+    }
+}
+
+/// The instruction set of traced code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceIsa {
+    /// The A32 (ARM) instruction set.
+    Arm,
+    /// The T32 (Thumb) instruction set.
+    Thumb,
+}
+
+/// An atom, i.e. the outcome of a traced instruction or branch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Atom {
+    /// The instruction passed its condition code check, or the branch was taken.
+    Executed,
+    /// The instruction failed its condition code check, or the branch was not taken.
+    NotExecuted,
+}
+
+/// A decoded trace packet, in a protocol independent representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TracePacket {
+    /// A synchronization point, with the address of the next instruction that is executed.
+    Sync {
+        /// The address of the next instruction.
+        address: u64,
+        /// The instruction set of the next instruction.
+        isa: TraceIsa,
+    },
+    /// The outcome of an instruction or branch.
+    Atom(Atom),
+    /// The target address of a branch that cannot be inferred from the program image.
+    Address {
+        /// The branch target.
+        address: u64,
+        /// The instruction set at the branch target.
+        isa: TraceIsa,
+    },
+    /// An exception was taken.
+    Exception {
+        /// The architecture specific exception number.
+        number: u16,
+        /// The preferred return address of the exception, if the protocol traces it.
+        return_address: Option<u64>,
+    },
+    /// Returned from an exception.
+    ExceptionReturn,
+    /// Tracing was (re)enabled, so the trace is not contiguous with the preceding trace.
+    TraceOn,
+    /// The trace unit overflowed, and trace data was lost.
+    Overflow,
+}
+
+/// An element of the reconstructed instruction stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceElement {
+    /// An instruction was traced.
+    Instruction {
+        /// The address of the instruction.
+        address: u64,
+        /// The instruction set of the instruction.
+        isa: TraceIsa,
+        /// False if the instruction failed its condition code check, or was a branch that was not taken.
+        executed: bool,
+    },
+    /// An exception was taken.
+    Exception {
+        /// The architecture specific exception number.
+        number: u16,
+    },
+    /// Returned from an exception.
+    ExceptionReturn,
+    /// The trace is not contiguous, e.g. because trace data was lost or could not be decoded.
+    Gap,
+}
+
+/// Decode the raw trace data of a single trace source into packets.
+///
+/// Bytes before the first synchronization sequence are ignored, since packet boundaries are
+/// unknown until then.
+pub fn decode_packets(protocol: TraceProtocol, data: &[u8]) -> Vec<TracePacket> {
+    match protocol {
+        TraceProtocol::EtmV3 => etmv3::decode(data),
+        TraceProtocol::Ptm => ptm::decode(data),
+        TraceProtocol::EtmV4 => etmv4::decode(data),
+    }
+}
+
+/// Decode the raw trace data of a single trace source, and reconstruct the executed instructions
+/// with the help of the program image.
+pub fn decode_instruction_trace(
+    protocol: TraceProtocol,
+    data: &[u8],
+    image: &ProgramImage,
+) -> Vec<TraceElement> {
+    let mut reconstructor = Reconstructor::new(protocol, image);
+    for packet in decode_packets(protocol, data) {
+        reconstructor.process(&packet);
+    }
+    reconstructor.finish()
+}
+
+/// The code of the traced program, used to follow the program flow between branches.
+#[derive(Debug, Default, Clone)]
+pub struct ProgramImage {
+    segments: Vec<(Range<u64>, Vec<u8>)>,
+}
+
+impl ProgramImage {
+    /// Create an empty program image.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a program image from the executable sections of an ELF file.
+    pub fn from_elf(elf: &[u8]) -> Result<Self, crate::Error> {
+        let object = object::File::parse(elf)
+            .map_err(|error| crate::Error::Other(format!("Failed to parse ELF file: {error}")))?;
+
+        let mut image = Self::new();
+        for section in object.sections() {
+            if section.kind() != object::SectionKind::Text {
+                continue;
+            }
+            let data = section.data().map_err(|error| {
+                crate::Error::Other(format!("Failed to read ELF section: {error}"))
+            })?;
+            image.add_segment(section.address(), data.to_vec());
+        }
+
+        Ok(image)
+    }
+
+    /// Add the code at the given address to the image.
+    pub fn add_segment(&mut self, address: u64, data: Vec<u8>) {
+        self.segments
+            .push((address..address + data.len() as u64, data));
+    }
+
+    fn read<const N: usize>(&self, address: u64) -> Option<[u8; N]> {
+        let (range, data) = self
+            .segments
+            .iter()
+            .find(|(range, _)| range.contains(&address))?;
+        let offset = (address - range.start) as usize;
+
+        data.get(offset..offset + N)?.try_into().ok()
+    }
+
+    pub(crate) fn read_u16(&self, address: u64) -> Option<u16> {
+        self.read(address).map(u16::from_le_bytes)
+    }
+
+    pub(crate) fn read_u32(&self, address: u64) -> Option<u32> {
+        self.read(address).map(u32::from_le_bytes)
+    }
+}
+
+/// Skip an alignment synchronization sequence, as used by ETMv3 and PFT: at least five `0x00`
+/// bytes, followed by `0x80`.
+///
+/// Returns the number of bytes of the sequence, or `None` if `data` does not start with one.
+pub(crate) fn a_sync_length(data: &[u8]) -> Option<usize> {
+    let zeros = data.iter().take_while(|&&byte| byte == 0).count();
+    (zeros >= 5 && data.get(zeros) == Some(&0x80)).then_some(zeros + 1)
+}
+
+/// Decode a compressed branch address, as used by ETMv3 and PFT.
+///
+/// The address is encoded in up to five bytes, each with a continuation bit. Bytes which are not
+/// sent are taken from the previous address. The fifth byte encodes the instruction set. If the
+/// address is followed by exception information, the exception number is returned as well.
+///
+/// With the alternative encoding (ETMv3.4+), a final byte before the fifth one carries 6 address
+/// bits and a flag which indicates that exception information follows.
+pub(crate) fn decode_branch_address(
+    data: &[u8],
+    previous_address: u64,
+    previous_isa: TraceIsa,
+    alternative_encoding: bool,
+) -> Option<BranchAddress> {
+    let mut value = u64::from((data.first()? >> 1) & 0x3F);
+    let mut bits = 6;
+    let mut index = 0;
+    let mut isa = previous_isa;
+    let mut exception_follows = false;
+    let mut upper_bits = None;
+
+    let mut continuation = data[0] & 0x80 != 0;
+    while continuation {
+        index += 1;
+        let byte = *data.get(index)?;
+
+        if index == 4 {
+            exception_follows = byte & 0x40 != 0;
+            (isa, upper_bits) = if byte & 0x30 == 0x10 {
+                (TraceIsa::Thumb, Some(u64::from(byte & 0x0F) << 28))
+            } else {
+                (TraceIsa::Arm, Some(u64::from(byte & 0x07) << 29))
+            };
+            break;
+        }
+
+        continuation = byte & 0x80 != 0;
+        if !continuation && alternative_encoding {
+            exception_follows = byte & 0x40 != 0;
+            value |= u64::from(byte & 0x3F) << bits;
+            bits += 6;
+        } else {
+            value |= u64::from(byte & 0x7F) << bits;
+            bits += 7;
+        }
+    }
+
+    let shift = match isa {
+        TraceIsa::Thumb => 1,
+        TraceIsa::Arm => 2,
+    };
+    let address = match upper_bits {
+        Some(upper_bits) => (value << shift) | upper_bits,
+        None => {
+            let mask = ((1u64 << bits) - 1) << shift;
+            (previous_address & !mask) | (value << shift)
+        }
+    };
+    let mut length = index + 1;
+
+    let mut exception = None;
+    if exception_follows {
+        let first = *data.get(length)?;
+        let mut number = u16::from((first >> 1) & 0x0F);
+        length += 1;
+        if first & 0x80 != 0 {
+            let second = *data.get(length)?;
+            number |= u16::from(second & 0x1F) << 4;
+            length += 1;
+            // A third byte may follow with resume information, which is not needed here.
+            if second & 0x80 != 0 {
+                data.get(length)?;
+                length += 1;
+            }
+        }
+        exception = Some(number);
+    }
+
+    Some(BranchAddress {
+        address,
+        isa,
+        exception,
+        length,
+    })
+}
+
+/// A decoded ETMv3/PFT branch address.
+#[derive(Debug, PartialEq)]
+pub(crate) struct BranchAddress {
+    pub(crate) address: u64,
+    pub(crate) isa: TraceIsa,
+    /// The exception number, if the branch was caused by an exception.
+    pub(crate) exception: Option<u16>,
+    /// The number of bytes of the encoded address, including exception information.
+    pub(crate) length: usize,
+}
+
+/// Skip a field which is encoded with a continuation bit in each byte, with up to `max_length` bytes.
+///
+/// Returns the decoded value and its length, or `None` if the data is truncated.
+pub(crate) fn read_continued(data: &[u8], max_length: usize) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (index, &byte) in data.iter().enumerate().take(max_length) {
+        if index == 8 {
+            // The 9th byte of a 64-bit value uses all 8 bits.
+            value |= u64::from(byte) << 56;
+            return Some((value, index + 1));
+        }
+        value |= u64::from(byte & 0x7F) << (7 * index);
+        if byte & 0x80 == 0 || index + 1 == max_length {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn a_sync() {
+        assert_eq!(a_sync_length(&[0, 0, 0, 0, 0, 0x80, 0x08]), Some(6));
+        assert_eq!(a_sync_length(&[0, 0, 0, 0, 0, 0, 0, 0x80]), Some(8));
+        assert_eq!(a_sync_length(&[0, 0, 0, 0, 0x80]), None);
+    }
+
+    #[test]
+    fn compressed_branch_address() {
+        // Only the low bits [6:1] are sent, the rest is taken from the previous address.
+        let branch = decode_branch_address(&[0x21], 0x0800_1000, TraceIsa::Thumb, true).unwrap();
+        assert_eq!(branch.address, 0x0800_1020);
+        assert_eq!(branch.isa, TraceIsa::Thumb);
+        assert_eq!(branch.length, 1);
+    }
+
+    #[test]
+    fn full_branch_address() {
+        // 0x0800_0134 in Thumb state: address bits [27:1] in four bytes, [31:28] in the fifth.
+        let value = 0x0800_0134u32 >> 1;
+        let data = [
+            0x80 | ((value & 0x3F) << 1) as u8 | 1,
+            0x80 | ((value >> 6) & 0x7F) as u8,
+            0x80 | ((value >> 13) & 0x7F) as u8,
+            0x80 | ((value >> 20) & 0x7F) as u8,
+            0x10 | (0x0800_0134u32 >> 28) as u8,
+        ];
+
+        let branch = decode_branch_address(&data, 0, TraceIsa::Arm, true).unwrap();
+        assert_eq!(branch.address, 0x0800_0134);
+        assert_eq!(branch.isa, TraceIsa::Thumb);
+        assert_eq!(branch.exception, None);
+        assert_eq!(branch.length, 5);
+    }
+
+    #[test]
+    fn branch_address_with_exception() {
+        // Alternative encoding: the final byte sets bit 6 to signal exception information,
+        // which encodes exception 15 (SysTick) in two bytes.
+        let branch = decode_branch_address(
+            &[0x81, 0x40, 0x9E, 0x00],
+            0x0800_0000,
+            TraceIsa::Thumb,
+            true,
+        )
+        .unwrap();
+        assert_eq!(branch.address, 0x0800_0000);
+        assert_eq!(branch.exception, Some(15));
+        assert_eq!(branch.length, 4);
+    }
+
+    #[test]
+    fn continued_value() {
+        assert_eq!(read_continued(&[0x05], 5), Some((5, 1)));
+        assert_eq!(read_continued(&[0x81, 0x01], 5), Some((129, 2)));
+        assert_eq!(read_continued(&[0x81], 5), None);
+    }
+}
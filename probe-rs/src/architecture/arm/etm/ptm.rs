@@ -0,0 +1,160 @@
+//! Packet decoder for the Program Flow Trace (PFT) protocol of the PTM.
+//!
+//! The decoder assumes no cycle accurate tracing and no context ID tracing.
+
+use super::{a_sync_length, decode_branch_address, read_continued, Atom, TraceIsa, TracePacket};
+
+pub(crate) fn decode(data: &[u8]) -> Vec<TracePacket> {
+    let mut decoder = Decoder {
+        packets: Vec::new(),
+        address: 0,
+        isa: TraceIsa::Arm,
+    };
+
+    let mut synchronized = false;
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+
+        if let Some(length) = a_sync_length(rest) {
+            synchronized = true;
+            offset += length;
+            continue;
+        }
+
+        if !synchronized {
+            offset += 1;
+            continue;
+        }
+
+        match decoder.packet(rest) {
+            Some(length) => offset += length,
+            None => {
+                tracing::debug!("Lost PFT synchronization at offset {offset}");
+                synchronized = false;
+                offset += 1;
+            }
+        }
+    }
+
+    decoder.packets
+}
+
+struct Decoder {
+    packets: Vec<TracePacket>,
+    address: u64,
+    isa: TraceIsa,
+}
+
+impl Decoder {
+    /// Decode a single packet, returning its length.
+    fn packet(&mut self, data: &[u8]) -> Option<usize> {
+        let header = data[0];
+
+        if header & 0x01 == 0x01 {
+            let branch = decode_branch_address(data, self.address, self.isa, false)?;
+            self.address = branch.address;
+            self.isa = branch.isa;
+            match branch.exception {
+                Some(number) => self.packets.push(TracePacket::Exception {
+                    number,
+                    return_address: None,
+                }),
+                // A branch address packet also signals that the branch at the next waypoint was taken.
+                None => self.packets.push(TracePacket::Atom(Atom::Executed)),
+            }
+            self.packets.push(TracePacket::Address {
+                address: branch.address,
+                isa: branch.isa,
+            });
+            return Some(branch.length);
+        }
+
+        if header & 0x81 == 0x80 {
+            self.packets.push(TracePacket::Atom(if header & 0x02 == 0 {
+                Atom::Executed
+            } else {
+                Atom::NotExecuted
+            }));
+            return Some(1);
+        }
+
+        match header {
+            // I-sync.
+            0x08 => {
+                let address = u32::from_le_bytes(data.get(1..5)?.try_into().ok()?);
+                let info = *data.get(5)?;
+
+                match (info >> 5) & 0x03 {
+                    0b01 | 0b11 => self.packets.push(TracePacket::TraceOn),
+                    0b10 => self.packets.push(TracePacket::Overflow),
+                    _ => {}
+                }
+
+                self.isa = if address & 1 == 1 {
+                    TraceIsa::Thumb
+                } else {
+                    TraceIsa::Arm
+                };
+                self.address = u64::from(address & !1);
+                self.packets.push(TracePacket::Sync {
+                    address: self.address,
+                    isa: self.isa,
+                });
+                Some(6)
+            }
+            // Waypoint update, which only matters when tracing stops after a non-branch waypoint.
+            0x72 => {
+                let update = decode_branch_address(&data[1..], self.address, self.isa, false)?;
+                Some(1 + update.length)
+            }
+            // Trigger, ignore, and context ID with a configured size of zero bytes.
+            0x0C | 0x66 | 0x6E => Some(1),
+            // VMID.
+            0x3C => Some(2),
+            // Timestamp.
+            0x42 | 0x46 => Some(1 + read_continued(&data[1..], 9)?.1),
+            // Exception return.
+            0x76 => {
+                self.packets.push(TracePacket::ExceptionReturn);
+                Some(1)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const A_SYNC: [u8; 6] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x80];
+
+    #[test]
+    fn atoms_and_branches() {
+        let mut data = A_SYNC.to_vec();
+        // I-sync at 0x0010_0000 in ARM state.
+        data.extend_from_slice(&[0x08, 0x00, 0x00, 0x10, 0x00, 0x00]);
+        // Taken, not taken.
+        data.extend_from_slice(&[0x80, 0x82]);
+        // Indirect branch to 0x0010_0040.
+        data.push(0x21);
+
+        assert_eq!(
+            decode(&data),
+            vec![
+                TracePacket::Sync {
+                    address: 0x0010_0000,
+                    isa: TraceIsa::Arm
+                },
+                TracePacket::Atom(Atom::Executed),
+                TracePacket::Atom(Atom::NotExecuted),
+                TracePacket::Atom(Atom::Executed),
+                TracePacket::Address {
+                    address: 0x0010_0040,
+                    isa: TraceIsa::Arm
+                },
+            ]
+        );
+    }
+}
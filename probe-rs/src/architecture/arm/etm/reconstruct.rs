@@ -0,0 +1,324 @@
+//! Reconstruction of the executed instructions from decoded trace packets.
+
+use super::{
+    instructions::{self, Branch},
+    Atom, ProgramImage, TraceElement, TraceIsa, TracePacket, TraceProtocol,
+};
+
+/// The maximum number of instructions to follow without finding a waypoint, so that corrupted
+/// trace or an incomplete image does not produce an endless instruction stream.
+const MAX_INSTRUCTIONS_PER_WAYPOINT: usize = 4096;
+
+/// Follows the program flow through the program image, driven by trace packets.
+pub(crate) struct Reconstructor<'a> {
+    protocol: TraceProtocol,
+    image: &'a ProgramImage,
+    /// The address and instruction set of the next instruction, if known.
+    pc: Option<(u64, TraceIsa)>,
+    /// An ETMv3 branch address, which was traced before the atom of its branch instruction.
+    pending_target: Option<(u64, TraceIsa)>,
+    elements: Vec<TraceElement>,
+}
+
+impl<'a> Reconstructor<'a> {
+    pub(crate) fn new(protocol: TraceProtocol, image: &'a ProgramImage) -> Self {
+        Self {
+            protocol,
+            image,
+            pc: None,
+            pending_target: None,
+            elements: Vec::new(),
+        }
+    }
+
+    pub(crate) fn process(&mut self, packet: &TracePacket) {
+        match *packet {
+            TracePacket::Sync { address, isa } => {
+                if self.pc.is_some_and(|(pc, _)| pc != address) {
+                    self.gap();
+                }
+                self.pc = Some((address, isa));
+                self.pending_target = None;
+            }
+            TracePacket::Atom(atom) => {
+                let executed = atom == Atom::Executed;
+                match self.protocol {
+                    TraceProtocol::EtmV3 => self.instruction(executed),
+                    TraceProtocol::Ptm | TraceProtocol::EtmV4 => self.waypoint(executed),
+                }
+            }
+            TracePacket::Address { address, isa } => {
+                if self.protocol == TraceProtocol::EtmV3 && self.pc.is_some() {
+                    self.pending_target = Some((address, isa));
+                } else {
+                    self.pc = Some((address, isa));
+                }
+            }
+            TracePacket::Exception {
+                number,
+                return_address,
+            } => {
+                if let Some(return_address) = return_address {
+                    self.run_to(return_address);
+                }
+                self.elements.push(TraceElement::Exception { number });
+                // The handler address follows in an address packet.
+                self.pc = None;
+                self.pending_target = None;
+            }
+            TracePacket::ExceptionReturn => self.elements.push(TraceElement::ExceptionReturn),
+            TracePacket::TraceOn | TracePacket::Overflow => {
+                self.gap();
+                self.pc = None;
+                self.pending_target = None;
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<TraceElement> {
+        self.elements
+    }
+
+    fn gap(&mut self) {
+        if !matches!(self.elements.last(), None | Some(TraceElement::Gap)) {
+            self.elements.push(TraceElement::Gap);
+        }
+    }
+
+    /// Trace a single instruction, for protocols with one atom per instruction.
+    fn instruction(&mut self, executed: bool) {
+        let Some((address, isa)) = self.pc else {
+            return;
+        };
+        let Some(instruction) = instructions::decode(self.image, address, isa) else {
+            self.lost(address);
+            return;
+        };
+
+        self.elements.push(TraceElement::Instruction {
+            address,
+            isa,
+            executed,
+        });
+
+        self.pc = match (executed, instruction.branch) {
+            (true, Some(branch)) => self.pending_target.take().or(match branch {
+                Branch::Direct { address, isa } => Some((address, isa)),
+                Branch::Indirect => None,
+            }),
+            _ => Some((address + instruction.size, isa)),
+        };
+    }
+
+    /// Trace the instructions up to and including the next branch, for protocols with one atom
+    /// per branch.
+    fn waypoint(&mut self, executed: bool) {
+        for _ in 0..MAX_INSTRUCTIONS_PER_WAYPOINT {
+            let Some((address, isa)) = self.pc else {
+                return;
+            };
+            let Some(instruction) = instructions::decode(self.image, address, isa) else {
+                self.lost(address);
+                return;
+            };
+
+            let Some(branch) = instruction.branch else {
+                self.elements.push(TraceElement::Instruction {
+                    address,
+                    isa,
+                    executed: true,
+                });
+                self.pc = Some((address + instruction.size, isa));
+                continue;
+            };
+
+            self.elements.push(TraceElement::Instruction {
+                address,
+                isa,
+                executed,
+            });
+            self.pc = match (executed, branch) {
+                (true, Branch::Direct { address, isa }) => Some((address, isa)),
+                (true, Branch::Indirect) => None,
+                (false, _) => Some((address + instruction.size, isa)),
+            };
+            return;
+        }
+
+        tracing::debug!("No waypoint found, giving up on the current trace");
+        self.gap();
+        self.pc = None;
+    }
+
+    /// Trace the instructions which were executed before an exception was taken at `end`.
+    fn run_to(&mut self, end: u64) {
+        for _ in 0..MAX_INSTRUCTIONS_PER_WAYPOINT {
+            let Some((address, isa)) = self.pc else {
+                return;
+            };
+            if address == end {
+                return;
+            }
+            let Some(instruction) = instructions::decode(self.image, address, isa) else {
+                self.lost(address);
+                return;
+            };
+            if instruction.branch.is_some() {
+                // The trace does not contain a waypoint before the exception, so the return
+                // address must be reached without branching.
+                self.gap();
+                return;
+            }
+
+            self.elements.push(TraceElement::Instruction {
+                address,
+                isa,
+                executed: true,
+            });
+            self.pc = Some((address + instruction.size, isa));
+        }
+    }
+
+    fn lost(&mut self, address: u64) {
+        tracing::debug!("Traced instruction at {address:#010x} is not in the program image");
+        self.gap();
+        self.pc = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::arm::etm::decode_instruction_trace;
+
+    /// A small Thumb program at 0x0800_0100:
+    ///
+    /// ```text
+    /// 0x0800_0100: movs r0, #0
+    /// 0x0800_0102: adds r0, #1
+    /// 0x0800_0104: cmp r0, #2
+    /// 0x0800_0106: bne.n 0x0800_0102
+    /// 0x0800_0108: bl 0x0800_0110
+    /// 0x0800_010c: b.n 0x0800_010c
+    /// 0x0800_010e: nop
+    /// 0x0800_0110: bx lr
+    /// ```
+    fn image() -> ProgramImage {
+        let code: [u16; 9] = [
+            0x2000, 0x3001, 0x2802, 0xD1FC, 0xF000, 0xF802, 0xE7FE, 0xBF00, 0x4770,
+        ];
+        let mut image = ProgramImage::new();
+        image.add_segment(
+            0x0800_0100,
+            code.iter().flat_map(|h| h.to_le_bytes()).collect(),
+        );
+        image
+    }
+
+    fn addresses(elements: &[TraceElement]) -> Vec<u64> {
+        elements
+            .iter()
+            .filter_map(|element| match element {
+                TraceElement::Instruction {
+                    address,
+                    executed: true,
+                    ..
+                } => Some(*address),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn etmv4_waypoints() {
+        let image = image();
+        let data = [
+            // A-sync and trace info.
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80, 0x01, 0x00,
+            // Long Thumb address 0x0800_0100.
+            0x9B, 0x00, 0x01, 0x00, 0x08,
+            // bne taken, bne not taken, bl taken, bx lr taken.
+            0xF7, 0xF6, 0xF7, 0xF7, // Short address 0x0800_010c.
+            0x96, 0x06,
+        ];
+
+        let elements = decode_instruction_trace(TraceProtocol::EtmV4, &data, &image);
+        assert_eq!(
+            addresses(&elements),
+            vec![
+                0x0800_0100,
+                0x0800_0102,
+                0x0800_0104,
+                0x0800_0106,
+                0x0800_0102,
+                0x0800_0104,
+                0x0800_0108,
+                0x0800_0110,
+            ]
+        );
+        assert!(elements.contains(&TraceElement::Instruction {
+            address: 0x0800_0106,
+            isa: TraceIsa::Thumb,
+            executed: false
+        }));
+    }
+
+    #[test]
+    fn etmv3_atoms_per_instruction() {
+        let image = image();
+        let data = [
+            // A-sync, I-sync at 0x0800_0100.
+            0, 0, 0, 0, 0, 0x80, 0x08, 0x00, 0x01, 0x01, 0x00, 0x08,
+            // Four E atoms, then N for the second bne.
+            0x90, 0xC8, // bl and bx lr executed.
+            0x88, // Branch address 0x0800_010c.
+            0x0D,
+        ];
+
+        let elements = decode_instruction_trace(TraceProtocol::EtmV3, &data, &image);
+        assert_eq!(
+            addresses(&elements),
+            vec![
+                0x0800_0100,
+                0x0800_0102,
+                0x0800_0104,
+                0x0800_0106,
+                0x0800_0102,
+                0x0800_0104,
+                0x0800_0108,
+                0x0800_0110,
+            ]
+        );
+    }
+
+    #[test]
+    fn exception_in_etmv4() {
+        let image = image();
+        let data = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80, 0x01, 0x00,
+            // Long Thumb address 0x0800_0100.
+            0x9B, 0x00, 0x01, 0x00, 0x08, // Exception 3 with return address 0x0800_0104.
+            0x06, 0x06, 0x96, 0x02,
+        ];
+
+        let elements = decode_instruction_trace(TraceProtocol::EtmV4, &data, &image);
+        assert_eq!(
+            elements.last(),
+            Some(&TraceElement::Exception { number: 3 })
+        );
+        assert_eq!(addresses(&elements), vec![0x0800_0100, 0x0800_0102]);
+    }
+
+    #[test]
+    fn gap_outside_of_image() {
+        let image = image();
+        let data = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80, 0x01, 0x00,
+            // Long Thumb address 0x0800_0100, a branch, and an address outside of the image.
+            0x9B, 0x00, 0x01, 0x00, 0x08, 0xF7, 0x9B, 0x00, 0x00, 0x00, 0x20, 0xF7,
+        ];
+
+        let elements = decode_instruction_trace(TraceProtocol::EtmV4, &data, &image);
+        assert_eq!(elements.last(), Some(&TraceElement::Gap));
+    }
+}
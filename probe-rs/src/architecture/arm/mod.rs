@@ -6,12 +6,13 @@ pub(crate) mod communication_interface;
 pub mod component;
 pub(crate) mod core;
 pub mod dp;
+pub mod etm;
 pub mod memory;
 pub mod sequences;
 pub mod swo;
 mod traits;
 
-pub use self::core::{armv6m, armv7a, armv7m, armv8a, armv8m, Dump};
+pub use self::core::{armv6m, armv7a, armv7m, armv8a, armv8m, mpu, Dump};
 use self::{
     ap::AccessPortError,
     communication_interface::RegisterParseError,
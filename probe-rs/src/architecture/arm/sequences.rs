@@ -16,7 +16,7 @@ use crate::{
         ArmProbeInterface,
     },
     probe::{DebugProbeError, WireProtocol},
-    MemoryMappedRegister,
+    MemoryMappedRegister, ProtectionLevel, ProtectionStatus,
 };
 
 use super::{
@@ -476,21 +476,6 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         // so this will have to be changed in the future.
         let has_dormant = matches!(dp, DpAddress::Multidrop(_));
 
-        fn alert_sequence(interface: &mut dyn DapProbe) -> Result<(), ArmError> {
-            tracing::trace!("Sending Selection Alert sequence");
-
-            // Ensure target is not in the middle of detecting a selection alert
-            interface.swj_sequence(8, 0xFF)?;
-
-            // Alert Sequence Bits  0.. 63
-            interface.swj_sequence(64, 0x86852D956209F392)?;
-
-            // Alert Sequence Bits 64..127
-            interface.swj_sequence(64, 0x19BC0EA2E3DDAFE9)?;
-
-            Ok(())
-        }
-
         // TODO: Use atomic block
 
         let mut result = Ok(());
@@ -528,15 +513,7 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
                 }
                 Some(WireProtocol::Swd) => {
                     if has_dormant {
-                        // Select Dormant State (from JTAG)
-                        tracing::debug!("Select Dormant State (from JTAG)");
-                        interface.swj_sequence(31, 0x33BBBBBA)?;
-
-                        // Leave dormant state
-                        alert_sequence(interface)?;
-
-                        // 4 cycles SWDIO/TMS LOW + 8-Bit SWD Activation Code (0x1A)
-                        interface.swj_sequence(12, 0x1A0)?;
+                        swd_activate_from_dormant(interface)?;
                     } else {
                         // Execute SWJ-DP Switch Sequence JTAG to SWD (0xE79E).
                         // Change if SWJ-DP uses deprecated switch code (0xEDB6).
@@ -778,6 +755,33 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         Ok(())
     }
 
+    /// Reads the readout protection state of the device.
+    ///
+    /// This is called on an attached device, so it only has to handle protection
+    /// levels which still allow attaching.
+    fn protection_status(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: &FullyQualifiedApAddress,
+    ) -> Result<ProtectionStatus, ArmError> {
+        Err(ArmError::NotImplemented("protection_status"))
+    }
+
+    /// Changes the readout protection of the device to `level`.
+    ///
+    /// The caller has already checked that `permissions` allow the transition from the current
+    /// level. If the debug connection is lost in the process, e.g. because the device was erased
+    /// and reset, `ArmError::ReAttachRequired` should be returned.
+    fn set_protection(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: &FullyQualifiedApAddress,
+        _level: ProtectionLevel,
+        _permissions: &crate::Permissions,
+    ) -> Result<(), ArmError> {
+        Err(ArmError::NotImplemented("set_protection"))
+    }
+
     /// Executed before step or run command to support recovery from a lost target connection, e.g. after a low power mode.
     /// This is based on the `RecoverSupportStart` function from the [ARM SVD Debug Description].
     ///
@@ -888,18 +892,7 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         if let DpAddress::Multidrop(targetsel) = dp {
             // Deselect other debug ports first?
 
-            tracing::debug!("Writing targetsel {:#x}", targetsel);
-            // TARGETSEL write.
-            // The TARGETSEL write is not ACKed by design. We can't use a normal register write
-            // because many probes don't even send the data phase when NAK.
-            let parity = targetsel.count_ones() % 2;
-            let data = (parity as u64) << 45 | (targetsel as u64) << 13 | 0x1f99;
-
-            // Should this be a swd_sequence?
-            // Technically we shouldn't drive SWDIO all the time when sending a request.
-            interface
-                .swj_sequence(6 * 8, data)
-                .map_err(DebugProbeError::from)?;
+            write_targetsel(interface, targetsel)?;
         }
 
         tracing::debug!("Reading DPIDR to enable SWD interface");
@@ -1001,7 +994,10 @@ pub trait DebugEraseSequence: Send + Sync {
 /// Perform a SWD line reset (SWDIO high for 50 clock cycles)
 ///
 /// After the line reset, SWDIO will be kept low for `swdio_low_cycles` cycles.
-fn swd_line_reset(interface: &mut dyn DapProbe, swdio_low_cycles: u8) -> Result<(), ArmError> {
+pub(crate) fn swd_line_reset(
+    interface: &mut dyn DapProbe,
+    swdio_low_cycles: u8,
+) -> Result<(), ArmError> {
     assert!(swdio_low_cycles + 51 <= 64);
 
     tracing::debug!("Performing SWD line reset");
@@ -1009,3 +1005,56 @@ fn swd_line_reset(interface: &mut dyn DapProbe, swdio_low_cycles: u8) -> Result<
 
     Ok(())
 }
+
+fn alert_sequence(interface: &mut dyn DapProbe) -> Result<(), ArmError> {
+    tracing::trace!("Sending Selection Alert sequence");
+
+    // Ensure target is not in the middle of detecting a selection alert
+    interface.swj_sequence(8, 0xFF)?;
+
+    // Alert Sequence Bits  0.. 63
+    interface.swj_sequence(64, 0x86852D956209F392)?;
+
+    // Alert Sequence Bits 64..127
+    interface.swj_sequence(64, 0x19BC0EA2E3DDAFE9)?;
+
+    Ok(())
+}
+
+/// Switch the SWJ-DP to SWD through the dormant state, as required for SWD multidrop.
+pub(crate) fn swd_activate_from_dormant(interface: &mut dyn DapProbe) -> Result<(), ArmError> {
+    // Select Dormant State (from JTAG)
+    tracing::debug!("Select Dormant State (from JTAG)");
+    interface.swj_sequence(31, 0x33BBBBBA)?;
+
+    // Leave dormant state
+    alert_sequence(interface)?;
+
+    // 4 cycles SWDIO/TMS LOW + 8-Bit SWD Activation Code (0x1A)
+    interface.swj_sequence(12, 0x1A0)?;
+
+    Ok(())
+}
+
+/// Write the TARGETSEL register, to select a debug port on an SWD multidrop bus.
+///
+/// This has to be done right after a line reset.
+pub(crate) fn write_targetsel(
+    interface: &mut dyn DapProbe,
+    targetsel: u32,
+) -> Result<(), ArmError> {
+    tracing::debug!("Writing targetsel {:#x}", targetsel);
+    // TARGETSEL write.
+    // The TARGETSEL write is not ACKed by design. We can't use a normal register write
+    // because many probes don't even send the data phase when NAK.
+    let parity = targetsel.count_ones() % 2;
+    let data = (parity as u64) << 45 | (targetsel as u64) << 13 | 0x1f99;
+
+    // Should this be a swd_sequence?
+    // Technically we shouldn't drive SWDIO all the time when sending a request.
+    interface
+        .swj_sequence(6 * 8, data)
+        .map_err(DebugProbeError::from)?;
+
+    Ok(())
+}
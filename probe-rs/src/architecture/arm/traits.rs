@@ -6,7 +6,7 @@ use crate::{
 use super::ArmError;
 
 /// The type of port we are using.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum PortType {
     /// Debug Port (e.g. SWD or JTAG)
     DebugPort,
@@ -132,4 +132,26 @@ mod test {
 
         assert_eq!(assembled, expected);
     }
+
+    #[test]
+    fn assemble_ld() {
+        // Assembly output of assembly 'ld      s1, 0(s0)'
+        //
+        let expected = 0x00043483;
+
+        let assembled = lw(0, 8, 3, 9);
+
+        assert_eq!(assembled, expected);
+    }
+
+    #[test]
+    fn assemble_sd() {
+        // Assembly output of assembly 'sd      s1, 0(s0)'
+        //
+        let expected = 0x00943023;
+
+        let assembled = sw(0, 8, 3, 9);
+
+        assert_eq!(assembled, expected);
+    }
 }
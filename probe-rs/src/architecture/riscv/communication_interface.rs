@@ -54,9 +54,15 @@ pub enum RiscvError {
     /// An error during system bus access occurred.
     #[error("Error using system bus")]
     SystemBusAccess,
+    /// The address is wider than the addresses supported by the hart or the system bus.
+    #[error("Address {0:#010x} out of range")]
+    AddressOutOfRange(u64),
     /// The given trigger type is not available for the address breakpoint.
     #[error("Unexpected trigger type {0} for address breakpoint.")]
     UnexpectedTriggerType(u32),
+    /// The trigger is already used for a different purpose.
+    #[error("Trigger {0} is already in use.")]
+    TriggerInUse(usize),
     /// The connected target is not a RISC-V device.
     #[error("Connected target is not a RISC-V device.")]
     NoRiscvTarget,
@@ -181,15 +187,15 @@ impl CoreRegisterAbstractCmdSupport {
 // TODO: we probably only need an Option, we don't seem to use scratch registers in nested situations.
 #[derive(Debug, Default)]
 struct ScratchState {
-    stack: Vec<u32>,
+    stack: Vec<u64>,
 }
 
 impl ScratchState {
-    fn push(&mut self, value: u32) {
+    fn push(&mut self, value: u64) {
         self.stack.push(value);
     }
 
-    fn pop(&mut self) -> Option<u32> {
+    fn pop(&mut self) -> Option<u64> {
         self.stack.pop()
     }
 }
@@ -228,6 +234,12 @@ pub struct RiscvCommunicationInterfaceState {
     /// Number of harts
     num_harts: u32,
 
+    /// Width of the general purpose registers (XLEN), determined on first use
+    xlen: Option<RiscvBusAccess>,
+
+    /// Width of the system bus addresses, in bits
+    sbasize: u8,
+
     /// Describes, which memory access method should be used for a given access width
     memory_access_info: HashMap<RiscvBusAccess, MemoryAccessMethod>,
 
@@ -296,6 +308,10 @@ impl RiscvCommunicationInterfaceState {
             // We assume only a singe hart exisits initially
             num_harts: 1,
 
+            xlen: None,
+
+            sbasize: 0,
+
             memory_access_info: HashMap::new(),
 
             abstract_cmd_register_info: HashMap::new(),
@@ -466,7 +482,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
         if saved {
             let s0 = self.state.s0.pop().unwrap();
 
-            self.abstract_cmd_register_write(&registers::S0, s0)?;
+            self.abstract_cmd_register_write_xlen(&registers::S0, s0)?;
         }
 
         Ok(())
@@ -484,7 +500,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
         if saved {
             let s1 = self.state.s1.pop().unwrap();
 
-            self.abstract_cmd_register_write(&registers::S1, s1)?;
+            self.abstract_cmd_register_write_xlen(&registers::S1, s1)?;
         }
 
         Ok(())
@@ -507,6 +523,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
 
         self.state.progbuf_cache.fill(0);
         self.state.memory_access_info.clear();
+        self.state.xlen = None;
         self.state.debug_version = DebugModuleVersion::from(status.version() as u8);
         self.state.is_halted = status.allhalted();
 
@@ -648,6 +665,9 @@ impl<'state> RiscvCommunicationInterface<'state> {
         // the system bus access conforms to the debug
         // specification 13.2.
         if sbcs.sbversion() == 1 {
+            self.state.sbasize = sbcs.sbasize() as u8;
+            tracing::debug!("System bus address width: {}", self.state.sbasize);
+
             // When possible, we use system bus access for memory access
 
             if sbcs.sbaccess8() {
@@ -762,13 +782,65 @@ impl<'state> RiscvCommunicationInterface<'state> {
     }
 
     pub(crate) fn core_info(&mut self) -> Result<CoreInformation, RiscvError> {
-        let pc: u64 = self
-            .read_csr(super::registers::PC.id().0)
-            .map(|v| v.into())?;
+        let pc = self.read_csr(super::registers::PC.id().0)?;
 
         Ok(CoreInformation { pc })
     }
 
+    /// Returns the width of the general purpose registers (XLEN) of the selected hart.
+    ///
+    /// The width is determined by reading `s0` with a 64-bit abstract command, which
+    /// fails if the register is narrower (see debug specification 0.13, section 3.6.1.1).
+    pub(crate) fn xlen(&mut self) -> Result<RiscvBusAccess, RiscvError> {
+        if let Some(xlen) = self.state.xlen {
+            return Ok(xlen);
+        }
+
+        let xlen = self.halted_access(|core| {
+            let mut command = AccessRegisterCommand(0);
+            command.set_cmd_type(0);
+            command.set_transfer(true);
+            command.set_aarsize(RiscvBusAccess::A64);
+            command.set_regno((registers::S0).id.0 as u32);
+
+            match core.execute_abstract_command(command.0) {
+                Ok(()) => Ok(RiscvBusAccess::A64),
+                Err(RiscvError::AbstractCommand(AbstractCommandErrorKind::NotSupported)) => {
+                    Ok(RiscvBusAccess::A32)
+                }
+                Err(other) => Err(other),
+            }
+        })?;
+
+        tracing::debug!("XLEN: {}", xlen.byte_width() * 8);
+        self.state.xlen = Some(xlen);
+
+        Ok(xlen)
+    }
+
+    /// Returns `true` if the selected hart has 64-bit registers.
+    pub(crate) fn is_64_bit(&mut self) -> Result<bool, RiscvError> {
+        Ok(self.xlen()? == RiscvBusAccess::A64)
+    }
+
+    /// Returns the width of the general purpose registers, if it has already been determined.
+    ///
+    /// Unlike [`Self::xlen`], this never accesses the hart.
+    pub(crate) fn known_xlen(&self) -> Option<RiscvBusAccess> {
+        self.state.xlen
+    }
+
+    /// Determines the width of the general purpose registers, if the hart is halted.
+    ///
+    /// A running hart is not halted for this, the width is determined once it halts.
+    pub(crate) fn detect_xlen_if_halted(&mut self) -> Result<(), RiscvError> {
+        if self.state.xlen.is_none() && self.core_halted()? {
+            self.xlen()?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn core_halted(&mut self) -> Result<bool, RiscvError> {
         if !self.state.is_halted {
             let dmstatus: Dmstatus = self.read_dm_register()?;
@@ -811,7 +883,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
         result
     }
 
-    pub(super) fn read_csr(&mut self, address: u16) -> Result<u32, RiscvError> {
+    pub(super) fn read_csr(&mut self, address: u16) -> Result<u64, RiscvError> {
         // We need to use the "Access Register Command",
         // which has cmdtype 0
 
@@ -831,6 +903,21 @@ impl<'state> RiscvCommunicationInterface<'state> {
         }
     }
 
+    /// Write a CSR or core register, using the full register width.
+    pub(super) fn write_csr(&mut self, address: u16, value: u64) -> Result<(), RiscvError> {
+        tracing::debug!("Writing CSR {:#x}", address);
+
+        // always try to write register with abstract command, fallback to program buffer,
+        // if not supported
+        match self.abstract_cmd_register_write_xlen(address, value) {
+            Err(RiscvError::AbstractCommand(AbstractCommandErrorKind::NotSupported)) => {
+                tracing::debug!("Could not write core register {:#x} with abstract command, falling back to program buffer", address);
+                self.write_csr_progbuf(address, value)
+            }
+            other => other,
+        }
+    }
+
     /// Schedules a DM register read, flushes the queue and returns the result.
     pub(crate) fn read_dm_register<R: MemoryMappedRegister<u32>>(
         &mut self,
@@ -952,10 +1039,57 @@ impl<'state> RiscvCommunicationInterface<'state> {
         Ok(())
     }
 
+    /// Write the address for the next system bus access.
+    ///
+    /// `sbaddress0` has to be written last, as writing it can trigger a read.
+    fn schedule_write_sbaddress(&mut self, address: u64) -> Result<(), RiscvError> {
+        if self.state.sbasize < 64 && address >> self.state.sbasize.max(32) != 0 {
+            return Err(RiscvError::AddressOutOfRange(address));
+        }
+
+        if self.state.sbasize > 32 {
+            self.schedule_write_dm_register(Sbaddress1((address >> 32) as u32))?;
+        }
+        self.schedule_write_dm_register(Sbaddress0(address as u32))
+    }
+
+    /// Write `value` into `arg0`, using the width of the general purpose registers.
+    ///
+    /// Returns the register width, which has to be used as `aarsize` for the
+    /// abstract command transferring the value into a register.
+    fn schedule_write_register_argument(
+        &mut self,
+        value: u64,
+    ) -> Result<RiscvBusAccess, RiscvError> {
+        let xlen = self.xlen()?;
+
+        if xlen == RiscvBusAccess::A64 {
+            self.schedule_write_large_dtm_register::<u64, Arg0>(value)?;
+        } else {
+            let value = u32::try_from(value).map_err(|_| RiscvError::AddressOutOfRange(value))?;
+            self.schedule_write_dm_register(Data0(value))?;
+        }
+
+        Ok(xlen)
+    }
+
+    /// Write a memory address into a general purpose register.
+    fn write_address_register(
+        &mut self,
+        regno: impl Into<RegisterId>,
+        address: u64,
+    ) -> Result<(), RiscvError> {
+        if !self.is_64_bit()? && address > u32::MAX as u64 {
+            return Err(RiscvError::AddressOutOfRange(address));
+        }
+
+        self.abstract_cmd_register_write_xlen(regno, address)
+    }
+
     /// Perform a single read from a memory location, using system bus access.
-    fn perform_memory_read_sysbus<V: RiscvValue32>(
+    fn perform_memory_read_sysbus<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
     ) -> Result<V, RiscvError> {
         let mut sbcs = Sbcs(0);
 
@@ -963,7 +1097,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
         sbcs.set_sbreadonaddr(true);
 
         self.schedule_write_dm_register(sbcs)?;
-        self.schedule_write_dm_register(Sbaddress0(address))?;
+        self.schedule_write_sbaddress(address)?;
 
         let mut results = vec![];
         self.schedule_read_large_dtm_register::<V, Sbdata>(&mut results)?;
@@ -980,10 +1114,10 @@ impl<'state> RiscvCommunicationInterface<'state> {
 
     /// Perform multiple reads from consecutive memory locations
     /// using system bus access.
-    /// Only reads up to a width of 32 bits are currently supported.
-    fn perform_memory_read_multiple_sysbus<V: RiscvValue32>(
+    /// Only reads up to a width of 64 bits are currently supported.
+    fn perform_memory_read_multiple_sysbus<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
         data: &mut [V],
     ) -> Result<(), RiscvError> {
         let mut sbcs = Sbcs(0);
@@ -997,7 +1131,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
 
         self.schedule_write_dm_register(sbcs)?;
 
-        self.schedule_write_dm_register(Sbaddress0(address))?;
+        self.schedule_write_sbaddress(address)?;
 
         let data_len = data.len();
 
@@ -1026,10 +1160,10 @@ impl<'state> RiscvCommunicationInterface<'state> {
     }
 
     /// Perform memory read from a single location using the program buffer.
-    /// Only reads up to a width of 32 bits are currently supported.
-    fn perform_memory_read_progbuf<V: RiscvValue32>(
+    /// Reads of 64 bits are only supported on harts with 64-bit registers.
+    fn perform_memory_read_progbuf<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
     ) -> Result<V, RiscvError> {
         self.halted_access(|core| {
             // assemble
@@ -1041,7 +1175,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
 
             core.schedule_setup_program_buffer(&[lw_command])?;
 
-            core.schedule_write_dm_register(Data0(address))?;
+            let xlen = core.schedule_write_register_argument(address)?;
 
             // Write s0, then execute program buffer
             let mut command = AccessRegisterCommand(0);
@@ -1049,8 +1183,8 @@ impl<'state> RiscvCommunicationInterface<'state> {
             command.set_transfer(true);
             command.set_write(true);
 
-            // registers are 32 bit, so we have size 2 here
-            command.set_aarsize(RiscvBusAccess::A32);
+            // the address is written with the full register width
+            command.set_aarsize(xlen);
             command.set_postexec(true);
 
             // register s0, ie. 0x1008
@@ -1073,9 +1207,9 @@ impl<'state> RiscvCommunicationInterface<'state> {
         })
     }
 
-    fn perform_memory_read_multiple_progbuf<V: RiscvValue32>(
+    fn perform_memory_read_multiple_progbuf<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
         data: &mut [V],
     ) -> Result<(), RiscvError> {
         self.halted_access(|core| {
@@ -1091,7 +1225,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
                 assembly::addi(8, 8, V::WIDTH.byte_width() as i16),
             ])?;
 
-            core.schedule_write_dm_register(Data0(address))?;
+            let xlen = core.schedule_write_register_argument(address)?;
 
             // Write s0, then execute program buffer
             let mut command = AccessRegisterCommand(0);
@@ -1099,8 +1233,8 @@ impl<'state> RiscvCommunicationInterface<'state> {
             command.set_transfer(true);
             command.set_write(true);
 
-            // registers are 32 bit, so we have size 2 here
-            command.set_aarsize(RiscvBusAccess::A32);
+            // the address is written with the full register width
+            command.set_aarsize(xlen);
             command.set_postexec(true);
 
             // register s0, ie. 0x1008
@@ -1110,15 +1244,15 @@ impl<'state> RiscvCommunicationInterface<'state> {
 
             let data_len = data.len();
 
-            let mut result_idxs = Vec::with_capacity(data_len - 1);
-            for out_idx in 0..data_len - 1 {
+            let mut read_results = Vec::with_capacity(data_len - 1);
+            for _ in 0..data_len - 1 {
                 let mut command = AccessRegisterCommand(0);
                 command.set_cmd_type(0);
                 command.set_transfer(true);
                 command.set_write(false);
 
-                // registers are 32 bit, so we have size 2 here
-                command.set_aarsize(RiscvBusAccess::A32);
+                // values up to 32 bits only need the lower half of a 64-bit register
+                command.set_aarsize(V::WIDTH.max(RiscvBusAccess::A32));
                 command.set_postexec(true);
 
                 command.set_regno((registers::S1).id.0 as u32);
@@ -1126,9 +1260,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
                 core.schedule_write_dm_register(command)?;
 
                 // Read back s1
-                let value_idx = core.schedule_read_dm_register::<Data0>()?;
-
-                result_idxs.push((out_idx, value_idx));
+                core.schedule_read_large_dtm_register::<V, Arg0>(&mut read_results)?;
             }
 
             // Specifically read the last value first. The result is that this last read is still
@@ -1136,10 +1268,8 @@ impl<'state> RiscvCommunicationInterface<'state> {
             let last_value = core.abstract_cmd_register_read(&registers::S1)?;
             data[data.len() - 1] = V::from_register_value(last_value);
 
-            for (out_idx, value_idx) in result_idxs {
-                let value = core.dtm.read_deferred_result(value_idx)?.into_u32();
-
-                data[out_idx] = V::from_register_value(value);
+            for out in data[..data_len - 1].iter_mut() {
+                *out = V::read_scheduled_result(core, &mut read_results)?;
             }
 
             let status: Abstractcs = core.read_dm_register()?;
@@ -1155,7 +1285,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
     /// Memory write using system bus
     fn perform_memory_write_sysbus<V: RiscvValue>(
         &mut self,
-        address: u32,
+        address: u64,
         data: &[V],
     ) -> Result<(), RiscvError> {
         let mut sbcs = Sbcs(0);
@@ -1166,7 +1296,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
 
         self.schedule_write_dm_register(sbcs)?;
 
-        self.schedule_write_dm_register(Sbaddress0(address))?;
+        self.schedule_write_sbaddress(address)?;
 
         for value in data {
             self.schedule_write_large_dtm_register::<V, Sbdata>(*value)?;
@@ -1183,10 +1313,10 @@ impl<'state> RiscvCommunicationInterface<'state> {
     }
 
     /// Perform memory write to a single location using the program buffer.
-    /// Only writes up to a width of 32 bits are currently supported.
-    fn perform_memory_write_progbuf<V: RiscvValue32>(
+    /// Writes of 64 bits are only supported on harts with 64-bit registers.
+    fn perform_memory_write_progbuf<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
         data: V,
     ) -> Result<(), RiscvError> {
         self.halted_access(|core| {
@@ -1205,10 +1335,10 @@ impl<'state> RiscvCommunicationInterface<'state> {
             core.schedule_setup_program_buffer(&[sw_command])?;
 
             // write address into s0
-            core.abstract_cmd_register_write(&registers::S0, address)?;
+            core.write_address_register(&registers::S0, address)?;
 
-            // write data into data 0
-            core.schedule_write_dm_register(Data0(data.into()))?;
+            // write data into arg0
+            core.schedule_write_large_dtm_register::<V, Arg0>(data)?;
 
             // Write s1, then execute program buffer
             let mut command = AccessRegisterCommand(0);
@@ -1216,8 +1346,8 @@ impl<'state> RiscvCommunicationInterface<'state> {
             command.set_transfer(true);
             command.set_write(true);
 
-            // registers are 32 bit, so we have size 2 here
-            command.set_aarsize(RiscvBusAccess::A32);
+            // the store instruction only uses the lower bits of the register
+            command.set_aarsize(V::WIDTH.max(RiscvBusAccess::A32));
             command.set_postexec(true);
 
             // register s1, ie. 0x1009
@@ -1247,10 +1377,10 @@ impl<'state> RiscvCommunicationInterface<'state> {
     }
 
     /// Perform multiple memory writes to consecutive locations using the program buffer.
-    /// Only writes up to a width of 32 bits are currently supported.
-    fn perform_memory_write_multiple_progbuf<V: RiscvValue32>(
+    /// Writes of 64 bits are only supported on harts with 64-bit registers.
+    fn perform_memory_write_multiple_progbuf<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
         data: &[V],
     ) -> Result<(), RiscvError> {
         self.halted_access(|core| {
@@ -1266,20 +1396,20 @@ impl<'state> RiscvCommunicationInterface<'state> {
             ])?;
 
             // write address into s0
-            core.abstract_cmd_register_write(&registers::S0, address)?;
+            core.write_address_register(&registers::S0, address)?;
 
             for value in data {
-                // write address into data 0
-                core.schedule_write_dm_register(Data0((*value).into()))?;
+                // write value into arg0
+                core.schedule_write_large_dtm_register::<V, Arg0>(*value)?;
 
-                // Write s0, then execute program buffer
+                // Write s1, then execute program buffer
                 let mut command = AccessRegisterCommand(0);
                 command.set_cmd_type(0);
                 command.set_transfer(true);
                 command.set_write(true);
 
-                // registers are 32 bit, so we have size 2 here
-                command.set_aarsize(RiscvBusAccess::A32);
+                // the store instruction only uses the lower bits of the register
+                command.set_aarsize(V::WIDTH.max(RiscvBusAccess::A32));
                 command.set_postexec(true);
 
                 // register s1
@@ -1405,11 +1535,11 @@ impl<'state> RiscvCommunicationInterface<'state> {
         entry.unset(rw);
     }
 
-    // Read a core register using an abstract command
+    // Read a core register using an abstract command, with the full register width
     pub(crate) fn abstract_cmd_register_read(
         &mut self,
         regno: impl Into<RegisterId>,
-    ) -> Result<u32, RiscvError> {
+    ) -> Result<u64, RiscvError> {
         let regno = regno.into();
 
         // Check if the register was already tried via abstract cmd
@@ -1419,11 +1549,13 @@ impl<'state> RiscvCommunicationInterface<'state> {
             ));
         }
 
-        // read from data0
+        let xlen = self.xlen()?;
+
+        // read from arg0
         let mut command = AccessRegisterCommand(0);
         command.set_cmd_type(0);
         command.set_transfer(true);
-        command.set_aarsize(RiscvBusAccess::A32);
+        command.set_aarsize(xlen);
 
         command.set_regno(regno.0 as u32);
 
@@ -1440,9 +1572,14 @@ impl<'state> RiscvCommunicationInterface<'state> {
             Err(e) => return Err(e),
         }
 
-        let register_value: Data0 = self.read_dm_register()?;
-
-        Ok(register_value.into())
+        if xlen == RiscvBusAccess::A64 {
+            let mut results = vec![];
+            self.schedule_read_large_dtm_register::<u64, Arg0>(&mut results)?;
+            u64::read_scheduled_result(self, &mut results)
+        } else {
+            let register_value: Data0 = self.read_dm_register()?;
+            Ok(u32::from(register_value).into())
+        }
     }
 
     pub(crate) fn abstract_cmd_register_write<V: RiscvValue>(
@@ -1484,8 +1621,23 @@ impl<'state> RiscvCommunicationInterface<'state> {
         }
     }
 
+    /// Write a core register using an abstract command, with the full register width.
+    ///
+    /// On harts with 32-bit registers, the upper 32 bits of `value` are ignored.
+    pub(crate) fn abstract_cmd_register_write_xlen(
+        &mut self,
+        regno: impl Into<RegisterId>,
+        value: u64,
+    ) -> Result<(), RiscvError> {
+        if self.is_64_bit()? {
+            self.abstract_cmd_register_write(regno, value)
+        } else {
+            self.abstract_cmd_register_write(regno, value as u32)
+        }
+    }
+
     /// Read the CSR `progbuf` register.
-    pub fn read_csr_progbuf(&mut self, address: u16) -> Result<u32, RiscvError> {
+    pub fn read_csr_progbuf(&mut self, address: u16) -> Result<u64, RiscvError> {
         self.halted_access(|core| {
             tracing::debug!("Reading CSR {:#04x}", address);
 
@@ -1518,7 +1670,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
     }
 
     /// Write the CSR `progbuf` register.
-    pub fn write_csr_progbuf(&mut self, address: u16, value: u32) -> Result<(), RiscvError> {
+    pub fn write_csr_progbuf(&mut self, address: u16, value: u64) -> Result<(), RiscvError> {
         self.halted_access(|core| {
             tracing::debug!("Writing CSR {:#04x}={}", address, value);
 
@@ -1531,7 +1683,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
             let s0 = core.save_s0()?;
 
             // Write value into s0
-            core.abstract_cmd_register_write(&registers::S0, value)?;
+            core.abstract_cmd_register_write_xlen(&registers::S0, value)?;
 
             // Built the CSRW command to write into the program buffer
             let csrw_cmd = assembly::csrw(address, 8);
@@ -1551,7 +1703,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
         })
     }
 
-    fn read_word<V: RiscvValue32>(&mut self, address: u32) -> Result<V, crate::Error> {
+    fn read_word<V: RiscvValue64>(&mut self, address: u64) -> Result<V, crate::Error> {
         let result = match self.state.memory_access_method(V::WIDTH) {
             MemoryAccessMethod::ProgramBuffer => self.perform_memory_read_progbuf(address)?,
             MemoryAccessMethod::SystemBus if self.state.sysbus_requires_halting => {
@@ -1566,14 +1718,14 @@ impl<'state> RiscvCommunicationInterface<'state> {
         Ok(result)
     }
 
-    fn read_multiple<V: RiscvValue32>(
+    fn read_multiple<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
         data: &mut [V],
     ) -> Result<(), crate::Error> {
-        tracing::debug!("read_32 from {:#08x}", address);
+        tracing::debug!("read_{} from {:#08x}", V::WIDTH.byte_width() * 8, address);
 
-        match self.state.memory_access_method(RiscvBusAccess::A32) {
+        match self.state.memory_access_method(V::WIDTH) {
             MemoryAccessMethod::ProgramBuffer => {
                 self.perform_memory_read_multiple_progbuf(address, data)?;
             }
@@ -1591,7 +1743,7 @@ impl<'state> RiscvCommunicationInterface<'state> {
         Ok(())
     }
 
-    fn write_word<V: RiscvValue32>(&mut self, address: u32, data: V) -> Result<(), crate::Error> {
+    fn write_word<V: RiscvValue64>(&mut self, address: u64, data: V) -> Result<(), crate::Error> {
         match self.state.memory_access_method(V::WIDTH) {
             MemoryAccessMethod::ProgramBuffer => {
                 self.perform_memory_write_progbuf(address, data)?
@@ -1608,9 +1760,9 @@ impl<'state> RiscvCommunicationInterface<'state> {
         Ok(())
     }
 
-    fn write_multiple<V: RiscvValue32>(
+    fn write_multiple<V: RiscvValue64>(
         &mut self,
-        address: u32,
+        address: u64,
         data: &[V],
     ) -> Result<(), crate::Error> {
         match self.state.memory_access_method(V::WIDTH) {
@@ -1813,19 +1965,13 @@ impl<'state> RiscvCommunicationInterface<'state> {
     }
 
     pub(crate) fn debug_on_sw_breakpoint(&mut self, enabled: bool) -> Result<(), RiscvError> {
-        let mut dcsr = Dcsr(self.read_csr(0x7b0)?);
+        let mut dcsr = Dcsr(self.read_csr(0x7b0)? as u32);
 
         dcsr.set_ebreakm(enabled);
         dcsr.set_ebreaks(enabled);
         dcsr.set_ebreaku(enabled);
 
-        match self.abstract_cmd_register_write(0x7b0, dcsr.0) {
-            Err(RiscvError::AbstractCommand(AbstractCommandErrorKind::NotSupported)) => {
-                tracing::debug!("Could not write core register {:#x} with abstract command, falling back to program buffer", 0x7b0);
-                self.write_csr_progbuf(0x7b0, dcsr.0)
-            }
-            other => other,
-        }
+        self.write_csr(0x7b0, dcsr.0.into())
     }
 
     pub(crate) fn sysbus_requires_halting(&mut self, en: bool) {
@@ -1858,23 +2004,28 @@ impl LargeRegister for Arg0 {
     const R3_ADDRESS: u8 = Data3::ADDRESS_OFFSET as u8;
 }
 
-/// Helper trait, limited to RiscvValue no larger than 32 bits
-pub(crate) trait RiscvValue32: RiscvValue + Into<u32> {
-    fn from_register_value(value: u32) -> Self;
+/// Helper trait, limited to RiscvValue no larger than 64 bits
+pub(crate) trait RiscvValue64: RiscvValue + Into<u64> {
+    fn from_register_value(value: u64) -> Self;
 }
 
-impl RiscvValue32 for u8 {
-    fn from_register_value(value: u32) -> Self {
+impl RiscvValue64 for u8 {
+    fn from_register_value(value: u64) -> Self {
         value as u8
     }
 }
-impl RiscvValue32 for u16 {
-    fn from_register_value(value: u32) -> Self {
+impl RiscvValue64 for u16 {
+    fn from_register_value(value: u64) -> Self {
         value as u16
     }
 }
-impl RiscvValue32 for u32 {
-    fn from_register_value(value: u32) -> Self {
+impl RiscvValue64 for u32 {
+    fn from_register_value(value: u64) -> Self {
+        value as u32
+    }
+}
+impl RiscvValue64 for u64 {
+    fn from_register_value(value: u64) -> Self {
         value
     }
 }
@@ -2105,11 +2256,19 @@ impl RiscvValue for u128 {
 
 impl MemoryInterface for RiscvCommunicationInterface<'_> {
     fn supports_native_64bit_access(&mut self) -> bool {
-        false
+        // 64-bit accesses are either done by the system bus, or by using `ld` and `sd` in the program buffer.
+        match self.state.memory_access_method(RiscvBusAccess::A64) {
+            MemoryAccessMethod::SystemBus => true,
+            _ => self.is_64_bit().unwrap_or(false),
+        }
     }
 
     fn read_word_64(&mut self, address: u64) -> Result<u64, crate::error::Error> {
-        let address = valid_32bit_address(address)?;
+        tracing::debug!("read_word_64 from {:#08x}", address);
+        if self.supports_native_64bit_access() {
+            return self.read_word(address);
+        }
+
         let mut ret = self.read_word::<u32>(address)? as u64;
         ret |= (self.read_word::<u32>(address + 4)? as u64) << 32;
 
@@ -2117,60 +2276,59 @@ impl MemoryInterface for RiscvCommunicationInterface<'_> {
     }
 
     fn read_word_32(&mut self, address: u64) -> Result<u32, crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("read_word_32 from {:#08x}", address);
         self.read_word(address)
     }
 
     fn read_word_16(&mut self, address: u64) -> Result<u16, crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("read_word_16 from {:#08x}", address);
         self.read_word(address)
     }
 
     fn read_word_8(&mut self, address: u64) -> Result<u8, crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("read_word_8 from {:#08x}", address);
         self.read_word(address)
     }
 
     fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::error::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("read_64 from {:#08x}", address);
 
+        if self.supports_native_64bit_access() {
+            return self.read_multiple(address, data);
+        }
+
         for (i, d) in data.iter_mut().enumerate() {
-            *d = self.read_word_64((address + (i as u32 * 8)).into())?;
+            *d = self.read_word_64(address + (i as u64 * 8))?;
         }
 
         Ok(())
     }
 
     fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("read_32 from {:#08x}", address);
         self.read_multiple(address, data)
     }
 
     fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("read_16 from {:#08x}", address);
         self.read_multiple(address, data)
     }
 
     fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("read_8 from {:#08x}", address);
 
         self.read_multiple(address, data)
     }
 
     fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         self.read_multiple(address, data)
     }
 
     fn write_word_64(&mut self, address: u64, data: u64) -> Result<(), crate::error::Error> {
-        let address = valid_32bit_address(address)?;
+        if self.supports_native_64bit_access() {
+            return self.write_word(address, data);
+        }
+
         let low_word = data as u32;
         let high_word = (data >> 32) as u32;
 
@@ -2179,54 +2337,50 @@ impl MemoryInterface for RiscvCommunicationInterface<'_> {
     }
 
     fn write_word_32(&mut self, address: u64, data: u32) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         self.write_word(address, data)
     }
 
     fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         self.write_word(address, data)
     }
 
     fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         self.write_word(address, data)
     }
 
     fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::error::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("write_64 to {:#08x}", address);
 
+        if self.supports_native_64bit_access() {
+            return self.write_multiple(address, data);
+        }
+
         for (i, d) in data.iter().enumerate() {
-            self.write_word_64((address + (i as u32 * 8)).into(), *d)?;
+            self.write_word_64(address + (i as u64 * 8), *d)?;
         }
 
         Ok(())
     }
 
     fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("write_32 to {:#08x}", address);
 
         self.write_multiple(address, data)
     }
 
     fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("write_16 to {:#08x}", address);
 
         self.write_multiple(address, data)
     }
 
     fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         tracing::debug!("write_8 to {:#08x}", address);
 
         self.write_multiple(address, data)
     }
 
     fn write(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
-        let address = valid_32bit_address(address)?;
         self.write_multiple(address, data)
     }
 
@@ -2242,7 +2396,7 @@ impl MemoryInterface for RiscvCommunicationInterface<'_> {
 /// Access width for bus access.
 /// This is used both for system bus access (`sbcs` register),
 /// as well for abstract commands.
-#[derive(Copy, Clone, PartialEq, PartialOrd, Hash, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, PartialOrd, Ord, Hash, Eq, Debug)]
 pub enum RiscvBusAccess {
     /// 1 byte
     A8 = 0,
@@ -2485,3 +2639,441 @@ memory_mapped_bitfield_register! { pub struct Confstrptr0(u32); 0x19, "confstrpt
 memory_mapped_bitfield_register! { pub struct Confstrptr1(u32); 0x1a, "confstrptr1", impl From; }
 memory_mapped_bitfield_register! { pub struct Confstrptr2(u32); 0x1b, "confstrptr2", impl From; }
 memory_mapped_bitfield_register! { pub struct Confstrptr3(u32); 0x1c, "confstrptr3", impl From; }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use super::{RiscvBusAccess, RiscvCommunicationInterface, RiscvCommunicationInterfaceState};
+    use crate::architecture::riscv::dtm::dtm_access::DtmAccess;
+    use crate::architecture::riscv::{assembly, communication_interface::RiscvError};
+    use crate::probe::{CommandResult, DebugProbeError, DeferredResultIndex, DeferredResultSet};
+    use crate::MemoryInterface;
+
+    const PROGBUF_SIZE: usize = 4;
+
+    /// A debug module (version 0.13) with a single hart, which only supports
+    /// accessing memory using the program buffer.
+    #[derive(Debug)]
+    pub(crate) struct MockDebugModule {
+        is_64_bit: bool,
+        halted: bool,
+        resume_ack: bool,
+        dmcontrol: u32,
+        cmderr: u32,
+        data: [u32; 2],
+        progbuf: [u32; PROGBUF_SIZE],
+        gprs: [u64; 32],
+        csrs: HashMap<u16, u64>,
+        memory: HashMap<u64, u8>,
+        results: DeferredResultSet,
+    }
+
+    impl MockDebugModule {
+        pub(crate) fn new(is_64_bit: bool) -> Self {
+            Self {
+                is_64_bit,
+                halted: false,
+                resume_ack: false,
+                dmcontrol: 0,
+                cmderr: 0,
+                data: [0; 2],
+                progbuf: [0; PROGBUF_SIZE],
+                gprs: [0; 32],
+                csrs: HashMap::new(),
+                memory: HashMap::new(),
+                results: DeferredResultSet::new(),
+            }
+        }
+
+        pub(crate) fn with_csr(mut self, address: u16, value: u64) -> Self {
+            self.csrs.insert(address, value);
+            self
+        }
+
+        pub(crate) fn with_memory(mut self, address: u64, data: &[u8]) -> Self {
+            for (offset, byte) in data.iter().enumerate() {
+                self.memory.insert(address + offset as u64, *byte);
+            }
+            self
+        }
+
+        fn register_mask(&self) -> u64 {
+            if self.is_64_bit {
+                u64::MAX
+            } else {
+                u32::MAX as u64
+            }
+        }
+
+        fn read_register(&self, regno: u32) -> Option<u64> {
+            match regno {
+                0x1000 => Some(0),
+                0x1001..=0x101f => Some(self.gprs[regno as usize - 0x1000]),
+                0..=0xfff => Some(self.csrs.get(&(regno as u16)).copied().unwrap_or(0)),
+                _ => None,
+            }
+        }
+
+        fn write_register(&mut self, regno: u32, value: u64) -> bool {
+            let value = value & self.register_mask();
+            match regno {
+                0x1000 => {}
+                0x1001..=0x101f => self.gprs[regno as usize - 0x1000] = value,
+                0..=0xfff => {
+                    self.csrs.insert(regno as u16, value);
+                }
+                _ => return false,
+            }
+            true
+        }
+
+        fn read_gpr(&self, register: u32) -> u64 {
+            self.read_register(0x1000 + register).unwrap()
+        }
+
+        fn write_gpr(&mut self, register: u32, value: u64) {
+            self.write_register(0x1000 + register, value);
+        }
+
+        /// Executes an access register command, see debug specification 0.13, section 3.6.1.1.
+        fn execute_command(&mut self, command: u32) {
+            if self.cmderr != 0 {
+                return;
+            }
+
+            if command >> 24 != 0 {
+                self.cmderr = 2;
+                return;
+            }
+
+            if !self.halted {
+                self.cmderr = 4;
+                return;
+            }
+
+            let aarsize = (command >> 20) & 0x7;
+            let postexec = command & (1 << 18) != 0;
+            let transfer = command & (1 << 17) != 0;
+            let write = command & (1 << 16) != 0;
+            let regno = command & 0xffff;
+
+            if transfer {
+                let mask = match aarsize {
+                    2 => u32::MAX as u64,
+                    3 if self.is_64_bit => u64::MAX,
+                    _ => {
+                        self.cmderr = 2;
+                        return;
+                    }
+                };
+
+                if write {
+                    let value = (self.data[0] as u64 | (self.data[1] as u64) << 32) & mask;
+                    if !self.write_register(regno, value) {
+                        self.cmderr = 2;
+                        return;
+                    }
+                } else {
+                    let Some(value) = self.read_register(regno) else {
+                        self.cmderr = 2;
+                        return;
+                    };
+                    let value = value & mask;
+                    self.data[0] = value as u32;
+                    if aarsize == 3 {
+                        self.data[1] = (value >> 32) as u32;
+                    }
+                }
+            }
+
+            if postexec {
+                self.execute_program_buffer();
+            }
+        }
+
+        fn execute_program_buffer(&mut self) {
+            for instruction in self.progbuf {
+                if instruction == assembly::EBREAK {
+                    return;
+                }
+
+                if !self.execute_instruction(instruction) {
+                    self.cmderr = 3;
+                    return;
+                }
+            }
+        }
+
+        /// Executes the subset of RV32I/RV64I used by the communication interface.
+        fn execute_instruction(&mut self, instruction: u32) -> bool {
+            let opcode = instruction & 0x7f;
+            let rd = (instruction >> 7) & 0x1f;
+            let funct3 = (instruction >> 12) & 0x7;
+            let rs1 = (instruction >> 15) & 0x1f;
+            let rs2 = (instruction >> 20) & 0x1f;
+            let imm_i = (instruction as i32 >> 20) as i64 as u64;
+            let imm_s = ((instruction as i32 >> 25) << 5) as i64 as u64
+                | ((instruction >> 7) & 0x1f) as u64;
+
+            let width = 1usize << (funct3 & 0x3);
+            if width == 8 && !self.is_64_bit && matches!(opcode, 0b000_0011 | 0b010_0011) {
+                return false;
+            }
+
+            match (opcode, funct3) {
+                // lb, lh, lw, ld
+                (0b000_0011, 0..=3) => {
+                    let address = self.read_gpr(rs1).wrapping_add(imm_i) & self.register_mask();
+                    let mut value = 0u64;
+                    for offset in 0..width {
+                        let byte = self
+                            .memory
+                            .get(&(address + offset as u64))
+                            .copied()
+                            .unwrap_or(0);
+                        value |= (byte as u64) << (offset * 8);
+                    }
+                    // Loads sign-extend the value to the register width.
+                    let shift = 64 - width * 8;
+                    self.write_gpr(rd, ((value << shift) as i64 >> shift) as u64);
+                }
+                // sb, sh, sw, sd
+                (0b010_0011, 0..=3) => {
+                    let address = self.read_gpr(rs1).wrapping_add(imm_s) & self.register_mask();
+                    let value = self.read_gpr(rs2);
+                    for offset in 0..width {
+                        self.memory
+                            .insert(address + offset as u64, (value >> (offset * 8)) as u8);
+                    }
+                }
+                // addi
+                (0b001_0011, 0) => {
+                    let value = self.read_gpr(rs1).wrapping_add(imm_i);
+                    self.write_gpr(rd, value);
+                }
+                _ => return false,
+            }
+
+            true
+        }
+
+        fn dmstatus(&self) -> u32 {
+            // version 0.13, authenticated
+            let mut dmstatus = 2 | 1 << 7;
+            if self.halted {
+                // allhalted, anyhalted
+                dmstatus |= 0b11 << 8;
+            } else {
+                // allrunning, anyrunning
+                dmstatus |= 0b11 << 10;
+            }
+            if self.resume_ack {
+                // allresumeack, anyresumeack
+                dmstatus |= 0b11 << 16;
+            }
+            dmstatus
+        }
+
+        fn read(&mut self, address: u64) -> u32 {
+            match address {
+                0x04 | 0x05 => self.data[address as usize - 0x04],
+                0x10 => self.dmcontrol,
+                0x11 => self.dmstatus(),
+                // progbufsize, cmderr, datacount
+                0x16 => (PROGBUF_SIZE as u32) << 24 | self.cmderr << 8 | 2,
+                0x20..=0x23 => self.progbuf[address as usize - 0x20],
+                _ => 0,
+            }
+        }
+
+        fn write(&mut self, address: u64, value: u32) {
+            match address {
+                0x04 | 0x05 => self.data[address as usize - 0x04] = value,
+                0x10 => {
+                    // haltreq
+                    if value & (1 << 31) != 0 {
+                        self.halted = true;
+                    }
+                    // resumereq
+                    if value & (1 << 30) != 0 {
+                        self.halted = false;
+                        self.resume_ack = true;
+                    }
+                    // Only a single hart can be selected.
+                    self.dmcontrol = value & 0x0000_0003;
+                }
+                // cmderr is write-1-to-clear
+                0x16 => self.cmderr &= !((value >> 8) & 0x7),
+                0x17 => self.execute_command(value),
+                0x20..=0x23 => self.progbuf[address as usize - 0x20] = value,
+                _ => {}
+            }
+        }
+    }
+
+    impl DtmAccess for MockDebugModule {
+        fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+            Ok(())
+        }
+
+        fn clear_error_state(&mut self) -> Result<(), RiscvError> {
+            Ok(())
+        }
+
+        fn read_deferred_result(
+            &mut self,
+            index: DeferredResultIndex,
+        ) -> Result<CommandResult, RiscvError> {
+            self.results
+                .take(index)
+                .map_err(|_| RiscvError::BatchedResultNotAvailable)
+        }
+
+        fn execute(&mut self) -> Result<(), RiscvError> {
+            Ok(())
+        }
+
+        fn schedule_write(
+            &mut self,
+            address: u64,
+            value: u32,
+        ) -> Result<Option<DeferredResultIndex>, RiscvError> {
+            self.write(address, value);
+            Ok(None)
+        }
+
+        fn schedule_read(&mut self, address: u64) -> Result<DeferredResultIndex, RiscvError> {
+            let index = DeferredResultIndex::new();
+            let value = self.read(address);
+            self.results.push(&index, CommandResult::U32(value));
+            Ok(index)
+        }
+
+        fn read_with_timeout(
+            &mut self,
+            address: u64,
+            _timeout: Duration,
+        ) -> Result<u32, RiscvError> {
+            Ok(self.read(address))
+        }
+
+        fn write_with_timeout(
+            &mut self,
+            address: u64,
+            value: u32,
+            _timeout: Duration,
+        ) -> Result<Option<u32>, RiscvError> {
+            self.write(address, value);
+            Ok(None)
+        }
+
+        fn read_idcode(&mut self) -> Result<Option<u32>, DebugProbeError> {
+            Ok(None)
+        }
+    }
+
+    pub(crate) fn interface(
+        debug_module: MockDebugModule,
+        state: &mut RiscvCommunicationInterfaceState,
+    ) -> RiscvCommunicationInterface<'_> {
+        let mut interface = RiscvCommunicationInterface::new(Box::new(debug_module), state);
+        interface.enter_debug_mode().unwrap();
+        interface
+    }
+
+    #[test]
+    fn xlen_is_detected_without_leaving_the_hart_halted() {
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let mut interface = interface(MockDebugModule::new(true), &mut state);
+
+        assert_eq!(interface.known_xlen(), None);
+
+        // A running hart is not halted to determine the width.
+        interface.detect_xlen_if_halted().unwrap();
+        assert_eq!(interface.known_xlen(), None);
+
+        assert_eq!(interface.xlen().unwrap(), RiscvBusAccess::A64);
+        assert!(!interface.core_halted().unwrap());
+    }
+
+    #[test]
+    fn rv64_registers_are_accessed_with_64_bit_abstract_commands() {
+        const DPC: u16 = 0x7b1;
+
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let debug_module = MockDebugModule::new(true).with_csr(DPC, 0x1234_5678_9abc_def0);
+        let mut interface = interface(debug_module, &mut state);
+
+        interface.halt(Duration::from_millis(100)).unwrap();
+
+        assert_eq!(interface.read_csr(DPC).unwrap(), 0x1234_5678_9abc_def0);
+
+        interface.write_csr(DPC, 0xffff_ffff_0000_0004).unwrap();
+        assert_eq!(interface.read_csr(DPC).unwrap(), 0xffff_ffff_0000_0004);
+    }
+
+    #[test]
+    fn rv64_memory_is_accessed_with_the_program_buffer() {
+        const ADDRESS: u64 = 0x1_0000_0000;
+
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let debug_module = MockDebugModule::new(true)
+            .with_memory(ADDRESS, &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x88]);
+        let mut interface = interface(debug_module, &mut state);
+
+        interface.halt(Duration::from_millis(100)).unwrap();
+        interface
+            .abstract_cmd_register_write(0x1008u16, 0xaaaa_aaaa_aaaa_aaaau64)
+            .unwrap();
+
+        // The address does not fit in 32 bits, and the values are sign-extended by the loads.
+        assert!(interface.supports_native_64bit_access());
+        assert_eq!(
+            interface.read_word_64(ADDRESS).unwrap(),
+            0x8807_0605_0403_0201
+        );
+        assert_eq!(interface.read_word_32(ADDRESS + 4).unwrap(), 0x8807_0605);
+
+        let mut words = [0u32; 2];
+        interface.read_32(ADDRESS, &mut words).unwrap();
+        assert_eq!(words, [0x0403_0201, 0x8807_0605]);
+
+        interface
+            .write_word_64(ADDRESS + 8, 0x1122_3344_5566_7788)
+            .unwrap();
+        interface
+            .write_8(ADDRESS + 16, &[0xa0, 0xa1, 0xa2])
+            .unwrap();
+
+        let mut bytes = [0u8; 11];
+        interface.read_8(ADDRESS + 8, &mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            [0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0xa0, 0xa1, 0xa2]
+        );
+
+        // The scratch registers are restored after each access.
+        assert_eq!(
+            interface.abstract_cmd_register_read(0x1008u16).unwrap(),
+            0xaaaa_aaaa_aaaa_aaaa
+        );
+    }
+
+    #[test]
+    fn rv32_rejects_64_bit_addresses() {
+        let mut state = RiscvCommunicationInterfaceState::new();
+        let mut interface = interface(MockDebugModule::new(false), &mut state);
+
+        interface.halt(Duration::from_millis(100)).unwrap();
+
+        assert_eq!(interface.xlen().unwrap(), RiscvBusAccess::A32);
+        assert!(!interface.supports_native_64bit_access());
+        assert!(interface.read_word_32(0x1_0000_0000).is_err());
+    }
+}
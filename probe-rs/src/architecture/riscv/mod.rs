@@ -11,11 +11,13 @@ use crate::{
     semihosting::decode_semihosting_syscall,
     semihosting::SemihostingCommand,
     CoreInterface, CoreRegister, CoreStatus, CoreType, Error, HaltReason, InstructionSet,
-    MemoryInterface, MemoryMappedRegister,
+    MemoryInterface, MemoryMappedRegister, Watchpoint, WatchpointKind,
 };
 use bitfield::bitfield;
-use communication_interface::{AbstractCommandErrorKind, RiscvCommunicationInterface, RiscvError};
-use registers::{FP, RA, RISCV_CORE_REGSISTERS, SP};
+use communication_interface::{
+    AbstractCommandErrorKind, RiscvBusAccess, RiscvCommunicationInterface, RiscvError,
+};
+use registers::{FP, FP_64, RA, RA_64, RISCV64_CORE_REGISTERS, RISCV_CORE_REGSISTERS, SP, SP_64};
 use std::{
     sync::Arc,
     time::{Duration, Instant},
@@ -23,13 +25,23 @@ use std::{
 
 #[macro_use]
 pub(crate) mod registers;
-pub use registers::PC;
+pub use registers::{PC, PC_64};
 pub(crate) mod assembly;
 pub mod communication_interface;
 pub(crate) mod dtm;
 pub mod sequences;
 
+/// Trigger select register, see debug specification 0.13, section 5.2.1.
+const TSELECT: u16 = 0x7a0;
+/// First trigger data register, see debug specification 0.13, section 5.2.2.
+const TDATA1: u16 = 0x7a1;
+/// Second trigger data register, see debug specification 0.13, section 5.2.3.
+const TDATA2: u16 = 0x7a2;
+
 /// An interface to operate a RISC-V core.
+///
+/// Both harts with 32-bit (RV32) and 64-bit (RV64) registers are supported.
+/// The register width is determined the first time the hart is halted.
 pub struct Riscv32<'state> {
     interface: RiscvCommunicationInterface<'state>,
     state: &'state mut RiscvCoreState,
@@ -39,10 +51,16 @@ pub struct Riscv32<'state> {
 impl<'state> Riscv32<'state> {
     /// Create a new RISC-V interface for a particular hart.
     pub fn new(
-        interface: RiscvCommunicationInterface<'state>,
+        mut interface: RiscvCommunicationInterface<'state>,
         state: &'state mut RiscvCoreState,
         sequence: Arc<dyn RiscvDebugSequence>,
     ) -> Result<Self, RiscvError> {
+        // Determining the register width requires a halted hart, so a running
+        // hart is left alone until it is halted anyway.
+        if let Err(error) = interface.detect_xlen_if_halted() {
+            tracing::warn!("Failed to determine the register width of the hart: {error}");
+        }
+
         Ok(Self {
             interface,
             state,
@@ -50,25 +68,51 @@ impl<'state> Riscv32<'state> {
         })
     }
 
-    fn read_csr(&mut self, address: u16) -> Result<u32, RiscvError> {
+    fn read_csr(&mut self, address: u16) -> Result<u64, RiscvError> {
         self.interface.read_csr(address)
     }
 
-    fn write_csr(&mut self, address: u16, value: u32) -> Result<(), RiscvError> {
-        tracing::debug!("Writing CSR {:#x}", address);
+    fn write_csr(&mut self, address: u16, value: u64) -> Result<(), RiscvError> {
+        self.interface.write_csr(address, value)
+    }
 
-        match self.interface.abstract_cmd_register_write(address, value) {
-            Err(RiscvError::AbstractCommand(AbstractCommandErrorKind::NotSupported)) => {
-                tracing::debug!("Could not write core register {:#x} with abstract command, falling back to program buffer", address);
-                self.interface.write_csr_progbuf(address, value)
-            }
-            other => other,
+    /// Read the `tdata1` register of the selected trigger, as an `mcontrol` trigger.
+    fn read_mcontrol(&mut self) -> Result<Mcontrol, RiscvError> {
+        let value = self.read_csr(TDATA1)?;
+
+        if self.interface.is_64_bit()? {
+            Ok(Mcontrol::from_rv64(value))
+        } else {
+            Ok(Mcontrol(value as u32))
+        }
+    }
+
+    /// Write the `tdata1` register of the selected trigger.
+    fn write_mcontrol(&mut self, value: &Mcontrol) -> Result<(), RiscvError> {
+        let value = if self.interface.is_64_bit()? {
+            value.to_rv64()
+        } else {
+            value.0.into()
+        };
+
+        self.write_csr(TDATA1, value)
+    }
+
+    /// Checks that `address` can be used by the hart.
+    ///
+    /// As long as the register width is unknown, the address is passed on unchecked.
+    fn valid_address(&self, address: u64) -> Result<u64, Error> {
+        if self.interface.known_xlen() != Some(RiscvBusAccess::A32) {
+            Ok(address)
+        } else {
+            valid_32bit_address(address).map(u64::from)
         }
     }
 
     /// Resume the core.
     fn resume_core(&mut self) -> Result<(), crate::Error> {
         self.state.semihosting_command = None;
+        self.state.trigger_halt_reason = None;
         self.interface.resume_core()?;
 
         Ok(())
@@ -91,11 +135,11 @@ impl<'state> Riscv32<'state> {
             return Ok(Some(command));
         }
 
-        let pc: u32 = self.read_core_reg(self.program_counter().id)?.try_into()?;
+        let pc: u64 = self.read_core_reg(self.program_counter().id)?.try_into()?;
 
         // Read the actual instructions, starting at the instruction before the ebreak (PC-4)
         let mut actual_instructions = [0u32; 3];
-        self.read_32((pc - 4) as u64, &mut actual_instructions)?;
+        self.read_32(pc - 4, &mut actual_instructions)?;
         let actual_instructions = actual_instructions.as_slice();
 
         tracing::debug!(
@@ -118,8 +162,6 @@ impl<'state> Riscv32<'state> {
     fn determine_number_of_hardware_breakpoints(&mut self) -> Result<u32, RiscvError> {
         tracing::debug!("Determining number of HW breakpoints supported");
 
-        let tselect = 0x7a0;
-        let tdata1 = 0x7a1;
         let tinfo = 0x7a4;
 
         let mut tselect_index = 0;
@@ -127,16 +169,16 @@ impl<'state> Riscv32<'state> {
         // These steps follow the debug specification 0.13, section 5.1 Enumeration
         loop {
             tracing::debug!("Trying tselect={}", tselect_index);
-            if let Err(e) = self.write_csr(tselect, tselect_index) {
+            if let Err(e) = self.write_csr(TSELECT, tselect_index.into()) {
                 match e {
                     RiscvError::AbstractCommand(AbstractCommandErrorKind::Exception) => break,
                     other_error => return Err(other_error),
                 }
             }
 
-            let readback = self.read_csr(tselect)?;
+            let readback = self.read_csr(TSELECT)?;
 
-            if readback != tselect_index {
+            if readback != u64::from(tselect_index) {
                 break;
             }
 
@@ -155,13 +197,7 @@ impl<'state> Riscv32<'state> {
                 }
                 Err(RiscvError::AbstractCommand(AbstractCommandErrorKind::Exception)) => {
                     // An exception means we have to read tdata1 to discover the type
-                    let tdata_val = self.read_csr(tdata1)?;
-
-                    // Read the mxl field from the misa register (see RISC-V Privileged Spec, 3.1.1)
-                    let misa_value = Misa(self.read_csr(0x301)?);
-                    let xlen = u32::pow(2, misa_value.mxl() + 4);
-
-                    let trigger_type = tdata_val >> (xlen - 4);
+                    let trigger_type = self.read_mcontrol()?.type_();
 
                     if trigger_type == 0 {
                         break;
@@ -183,6 +219,147 @@ impl<'state> Riscv32<'state> {
 
         Ok(tselect_index)
     }
+
+    /// Decodes the watchpoint configured in the trigger described by `tdata_value`.
+    ///
+    /// Returns `None` if the trigger is not a data watchpoint set up by probe-rs.
+    fn decode_watchpoint(
+        &mut self,
+        tdata_value: &Mcontrol,
+    ) -> Result<Option<Watchpoint>, RiscvError> {
+        let trigger_any_mode_active = tdata_value.m() || tdata_value.s() || tdata_value.u();
+
+        if tdata_value.type_() != 0b10
+            || tdata_value.action() != 1
+            || tdata_value.execute()
+            || tdata_value.select()
+            || !trigger_any_mode_active
+        {
+            return Ok(None);
+        }
+
+        let kind = match (tdata_value.load(), tdata_value.store()) {
+            (true, true) => WatchpointKind::Access,
+            (true, false) => WatchpointKind::Read,
+            (false, true) => WatchpointKind::Write,
+            (false, false) => return Ok(None),
+        };
+
+        let value = self.read_csr(TDATA2)?;
+
+        let (address, length) = match tdata_value.match_() {
+            // Exact match of a single address.
+            0 => (value, 1),
+            // NAPOT match: the number of trailing ones encodes the size of the range.
+            1 => {
+                let length = 1u64 << (value.trailing_ones() + 1).min(63);
+                (value & !(length - 1), length)
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Watchpoint {
+            address,
+            length,
+            kind,
+        }))
+    }
+
+    /// Enables or disables the watchpoint triggers set up by probe-rs.
+    fn enable_watchpoints(&mut self, state: bool) -> Result<(), Error> {
+        for unit_index in 0..self.available_breakpoint_units()? {
+            self.write_csr(TSELECT, unit_index.into())?;
+
+            let mut tdata_value = self.read_mcontrol()?;
+
+            // Only modify the trigger if it is a data watchpoint in all modes (probe-rs enabled it) or no modes (we previously disabled it).
+            if tdata_value.type_() == 2
+                && tdata_value.action() == 1
+                && !tdata_value.execute()
+                && (tdata_value.load() || tdata_value.store())
+                && ((tdata_value.m() && tdata_value.u()) || (!tdata_value.m() && !tdata_value.u()))
+            {
+                tdata_value.set_m(state);
+                tdata_value.set_u(state);
+                self.write_mcontrol(&tdata_value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Determines whether the trigger module halted the core because of a breakpoint or a watchpoint.
+    fn trigger_halt_reason(&mut self) -> Result<HaltReason, Error> {
+        if let Some(reason) = self.state.trigger_halt_reason {
+            return Ok(reason);
+        }
+
+        let mut reason = None;
+        let mut any_watchpoint = false;
+
+        for unit_index in 0..self.available_breakpoint_units()? {
+            self.write_csr(TSELECT, unit_index.into())?;
+
+            let mut tdata_value = self.read_mcontrol()?;
+            if tdata_value.type_() != 2 {
+                continue;
+            }
+
+            let watchpoint = self.decode_watchpoint(&tdata_value)?;
+            any_watchpoint |= watchpoint.is_some();
+
+            // The hit bit is optional, and has to be cleared by the debugger.
+            if tdata_value.hit() {
+                tdata_value.set_hit(false);
+                self.write_mcontrol(&tdata_value)?;
+
+                if reason.is_none() {
+                    reason = Some(match watchpoint {
+                        Some(watchpoint) => HaltReason::Watchpoint(Some(watchpoint)),
+                        None => HaltReason::Breakpoint(BreakpointCause::Hardware),
+                    });
+                }
+            }
+        }
+
+        let reason = match reason {
+            Some(reason) => reason,
+            // Without a hit bit, we can only guess based on the program counter.
+            None if any_watchpoint => {
+                let pc = self.read_core_reg(self.program_counter().id)?.try_into()?;
+                if self.hw_breakpoints()?.contains(&Some(pc)) {
+                    HaltReason::Breakpoint(BreakpointCause::Hardware)
+                } else {
+                    HaltReason::Watchpoint(None)
+                }
+            }
+            None => HaltReason::Breakpoint(BreakpointCause::Hardware),
+        };
+
+        self.state.trigger_halt_reason = Some(reason);
+
+        Ok(reason)
+    }
+
+    /// Returns an error if trigger `unit_index` is in use by a watchpoint or breakpoint,
+    /// depending on `for_watchpoint`.
+    fn check_trigger_available(
+        &mut self,
+        unit_index: usize,
+        for_watchpoint: bool,
+    ) -> Result<(), RiscvError> {
+        self.write_csr(TSELECT, unit_index as u64)?;
+        let tdata_value = self.read_mcontrol()?;
+
+        let is_breakpoint = tdata_value.type_() == 2 && tdata_value.execute();
+        let is_watchpoint = self.decode_watchpoint(&tdata_value)?.is_some();
+
+        if (for_watchpoint && is_breakpoint) || (!for_watchpoint && is_watchpoint) {
+            return Err(RiscvError::TriggerInUse(unit_index));
+        }
+
+        Ok(())
+    }
 }
 
 impl<'state> CoreInterface for Riscv32<'state> {
@@ -219,7 +396,7 @@ impl<'state> CoreInterface for Riscv32<'state> {
                     // TODO: Add testcase to probe-rs-debugger-test to validate semihosting exit/abort work and unknown semihosting operations are skipped
                 }
                 // Trigger module caused halt
-                2 => HaltReason::Breakpoint(BreakpointCause::Hardware),
+                2 => self.trigger_halt_reason()?,
                 // Debugger requested a halt
                 3 => HaltReason::Request,
                 // Core halted after single step
@@ -242,6 +419,7 @@ impl<'state> CoreInterface for Riscv32<'state> {
 
     fn halt(&mut self, timeout: Duration) -> Result<CoreInformation, Error> {
         self.interface.halt(timeout)?;
+        self.interface.detect_xlen_if_halted()?;
         Ok(self.interface.core_info()?)
     }
 
@@ -284,10 +462,13 @@ impl<'state> CoreInterface for Riscv32<'state> {
             // If we are halted on a software breakpoint, we can skip the single step and manually advance the dpc.
             let mut debug_pc = self.read_core_reg(RegisterId(0x7b1))?;
             // Advance the dpc by the size of the EBREAK (ebreak or c.ebreak) instruction.
-            if matches!(self.instruction_set()?, InstructionSet::RV32C) {
+            if matches!(
+                self.instruction_set()?,
+                InstructionSet::RV32C | InstructionSet::RV64C
+            ) {
                 // We may have been halted by either an EBREAK or a C.EBREAK instruction.
                 // We need to read back the instruction to determine how many bytes we need to skip.
-                let instruction = self.read_word_32(debug_pc.try_into().unwrap())?;
+                let instruction = self.read_word_32(debug_pc.try_into()?)?;
                 if instruction & 0x3 != 0x3 {
                     // Compressed instruction.
                     debug_pc.increment_address(2)?;
@@ -308,6 +489,10 @@ impl<'state> CoreInterface for Riscv32<'state> {
         ) {
             // If we are halted on a hardware breakpoint.
             self.enable_breakpoints(false)?;
+        } else if matches!(halt_reason, CoreStatus::Halted(HaltReason::Watchpoint(_))) {
+            // Watchpoints trigger before the access is performed, so we need to disable
+            // them to be able to step over the instruction.
+            self.enable_watchpoints(false)?;
         }
 
         let mut dcsr = Dcsr(self.read_core_reg(RegisterId(0x7b0))?.try_into()?);
@@ -316,7 +501,7 @@ impl<'state> CoreInterface for Riscv32<'state> {
         // Disable any interrupts during single step.
         dcsr.set_stepie(false);
         dcsr.set_stopcount(true);
-        self.write_csr(0x7b0, dcsr.0)?;
+        self.write_csr(0x7b0, dcsr.0.into())?;
 
         // Now we can resume the core for the single step.
         self.resume_core()?;
@@ -330,7 +515,7 @@ impl<'state> CoreInterface for Riscv32<'state> {
         //Re-enable interrupts for single step.
         dcsr.set_stepie(true);
         dcsr.set_stopcount(false);
-        self.write_csr(0x7b0, dcsr.0)?;
+        self.write_csr(0x7b0, dcsr.0.into())?;
 
         // Re-enable breakpoints before we continue.
         if matches!(
@@ -339,6 +524,8 @@ impl<'state> CoreInterface for Riscv32<'state> {
         ) {
             // If we are halted on a hardware breakpoint.
             self.enable_breakpoints(true)?;
+        } else if matches!(halt_reason, CoreStatus::Halted(HaltReason::Watchpoint(_))) {
+            self.enable_watchpoints(true)?;
         }
 
         self.state.pc_written = false;
@@ -346,9 +533,13 @@ impl<'state> CoreInterface for Riscv32<'state> {
     }
 
     fn read_core_reg(&mut self, address: RegisterId) -> Result<RegisterValue, crate::Error> {
-        self.read_csr(address.0)
-            .map(|v| v.into())
-            .map_err(|e| e.into())
+        let value = self.read_csr(address.0)?;
+
+        if self.interface.is_64_bit()? {
+            Ok(RegisterValue::U64(value))
+        } else {
+            Ok(RegisterValue::U32(value as u32))
+        }
     }
 
     fn write_core_reg(
@@ -356,7 +547,11 @@ impl<'state> CoreInterface for Riscv32<'state> {
         address: RegisterId,
         value: RegisterValue,
     ) -> Result<(), crate::Error> {
-        let value: u32 = value.try_into()?;
+        let value: u64 = if self.interface.is_64_bit()? {
+            value.try_into()?
+        } else {
+            TryInto::<u32>::try_into(value)?.into()
+        };
 
         if address == self.program_counter().id {
             self.state.pc_written = true;
@@ -386,18 +581,14 @@ impl<'state> CoreInterface for Riscv32<'state> {
             self.halt(Duration::from_millis(100))?;
         }
 
-        let tselect = 0x7a0;
-        let tdata1 = 0x7a1;
-        let tdata2 = 0x7a2;
-
         let mut breakpoints = vec![];
         let num_hw_breakpoints = self.available_breakpoint_units()? as usize;
         for bp_unit_index in 0..num_hw_breakpoints {
             // Select the trigger.
-            self.write_csr(tselect, bp_unit_index as u32)?;
+            self.write_csr(TSELECT, bp_unit_index as u64)?;
 
             // Read the trigger "configuration" data.
-            let tdata_value = Mcontrol(self.read_csr(tdata1)?);
+            let tdata_value = self.read_mcontrol()?;
 
             tracing::debug!("Breakpoint {}: {:?}", bp_unit_index, tdata_value);
 
@@ -414,8 +605,8 @@ impl<'state> CoreInterface for Riscv32<'state> {
                 && trigger_any_mode_active
                 && trigger_any_action_enabled
             {
-                let breakpoint = self.read_csr(tdata2)?;
-                breakpoints.push(Some(breakpoint as u64));
+                let breakpoint = self.read_csr(TDATA2)?;
+                breakpoints.push(Some(breakpoint));
             } else {
                 breakpoints.push(None);
             }
@@ -430,15 +621,12 @@ impl<'state> CoreInterface for Riscv32<'state> {
 
     fn enable_breakpoints(&mut self, state: bool) -> Result<(), crate::Error> {
         // Loop through all triggers, and enable/disable them.
-        let tselect = 0x7a0;
-        let tdata1 = 0x7a1;
-
         for bp_unit_index in 0..self.available_breakpoint_units()? as usize {
             // Select the trigger.
-            self.write_csr(tselect, bp_unit_index as u32)?;
+            self.write_csr(TSELECT, bp_unit_index as u64)?;
 
             // Read the trigger "configuration" data.
-            let mut tdata_value = Mcontrol(self.read_csr(tdata1)?);
+            let mut tdata_value = self.read_mcontrol()?;
 
             // Only modify the trigger if it is for an execution debug action in all modes(probe-rs enabled it) or no modes (we previously disabled it).
             if tdata_value.type_() == 2
@@ -455,7 +643,7 @@ impl<'state> CoreInterface for Riscv32<'state> {
                 );
                 tdata_value.set_m(state);
                 tdata_value.set_u(state);
-                self.write_csr(tdata1, tdata_value.0)?;
+                self.write_mcontrol(&tdata_value)?;
             }
         }
 
@@ -464,20 +652,18 @@ impl<'state> CoreInterface for Riscv32<'state> {
     }
 
     fn set_hw_breakpoint(&mut self, bp_unit_index: usize, addr: u64) -> Result<(), crate::Error> {
-        let addr = valid_32bit_address(addr)?;
-
-        // select requested trigger
-        let tselect = 0x7a0;
-        let tdata1 = 0x7a1;
-        let tdata2 = 0x7a2;
+        let addr = self.valid_address(addr)?;
 
         tracing::info!("Setting breakpoint {}", bp_unit_index);
 
-        self.write_csr(tselect, bp_unit_index as u32)?;
+        self.check_trigger_available(bp_unit_index, false)?;
+
+        // select requested trigger
+        self.write_csr(TSELECT, bp_unit_index as u64)?;
 
         // verify the trigger has the correct type
 
-        let tdata_value = Mcontrol(self.read_csr(tdata1)?);
+        let tdata_value = self.read_mcontrol()?;
 
         // This should not happen
         let trigger_type = tdata_value.type_();
@@ -508,9 +694,9 @@ impl<'state> CoreInterface for Riscv32<'state> {
         // Match address
         instruction_breakpoint.set_select(false);
 
-        self.write_csr(tdata1, 0)?;
-        self.write_csr(tdata2, addr)?;
-        self.write_csr(tdata1, instruction_breakpoint.0)?;
+        self.write_csr(TDATA1, 0)?;
+        self.write_csr(TDATA2, addr)?;
+        self.write_mcontrol(&instruction_breakpoint)?;
 
         Ok(())
     }
@@ -524,39 +710,168 @@ impl<'state> CoreInterface for Riscv32<'state> {
             self.halt(Duration::from_millis(100))?;
         }
 
-        let tselect = 0x7a0;
-        let tdata1 = 0x7a1;
-        let tdata2 = 0x7a2;
+        self.write_csr(TSELECT, unit_index as u64)?;
+        self.write_csr(TDATA1, 0)?;
+        self.write_csr(TDATA2, 0)?;
+
+        if was_running {
+            self.resume_core()?;
+        }
 
-        self.write_csr(tselect, unit_index as u32)?;
-        self.write_csr(tdata1, 0)?;
-        self.write_csr(tdata2, 0)?;
+        Ok(())
+    }
+
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        // Watchpoints use the same triggers as breakpoints.
+        self.available_breakpoint_units()
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        // this can be called w/o halting the core via Session::new - temporarily halt if not halted
+
+        let was_running = !self.core_halted()?;
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
+
+        let mut watchpoints = vec![];
+        for unit_index in 0..self.available_watchpoint_units()? {
+            self.write_csr(TSELECT, unit_index.into())?;
+
+            let tdata_value = self.read_mcontrol()?;
+            watchpoints.push(self.decode_watchpoint(&tdata_value)?);
+        }
 
         if was_running {
             self.resume_core()?;
         }
 
+        Ok(watchpoints)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        let address = self.valid_address(watchpoint.address)?;
+
+        tracing::info!("Setting watchpoint {}", unit_index);
+
+        self.check_trigger_available(unit_index, true)?;
+
+        self.write_csr(TSELECT, unit_index as u64)?;
+
+        let tdata_value = self.read_mcontrol()?;
+
+        let trigger_type = tdata_value.type_();
+        if trigger_type != 0b10 {
+            return Err(RiscvError::UnexpectedTriggerType(trigger_type).into());
+        }
+
+        let length = watchpoint.length.max(1).next_power_of_two();
+        if address % length != 0 {
+            return Err(Error::Other(format!(
+                "The watchpoint address {:#010x} is not aligned to its length of {} bytes",
+                address, length
+            )));
+        }
+
+        let mut data_watchpoint = Mcontrol(0);
+
+        // Enter debug mode
+        data_watchpoint.set_action(1);
+        data_watchpoint.set_type(2);
+        data_watchpoint.set_dmode(true);
+
+        // A single byte is matched exactly, larger ranges are encoded as a naturally aligned power of two.
+        let tdata2_value = if length == 1 {
+            data_watchpoint.set_match(0);
+            address
+        } else {
+            data_watchpoint.set_match(1);
+            address | (length / 2 - 1)
+        };
+
+        data_watchpoint.set_m(true);
+        data_watchpoint.set_u(true);
+
+        data_watchpoint.set_load(matches!(
+            watchpoint.kind,
+            WatchpointKind::Read | WatchpointKind::Access
+        ));
+        data_watchpoint.set_store(matches!(
+            watchpoint.kind,
+            WatchpointKind::Write | WatchpointKind::Access
+        ));
+
+        // Match address
+        data_watchpoint.set_select(false);
+
+        self.write_csr(TDATA1, 0)?;
+        self.write_csr(TDATA2, tdata2_value)?;
+        self.write_mcontrol(&data_watchpoint)?;
+
+        // Not all trigger modules support every match type, verify that our configuration was accepted.
+        let readback = self.read_mcontrol()?;
+        if readback.match_() != data_watchpoint.match_() {
+            self.write_csr(TDATA1, 0)?;
+            return Err(Error::Other(format!(
+                "The trigger module does not support watchpoints of {} bytes",
+                length
+            )));
+        }
+
         Ok(())
     }
 
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        // Watchpoints use the same triggers as breakpoints, so clearing works the same way.
+        self.clear_hw_breakpoint(unit_index)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
-        &RISCV_CORE_REGSISTERS
+        if self.is_64_bit() {
+            &RISCV64_CORE_REGISTERS
+        } else {
+            &RISCV_CORE_REGSISTERS
+        }
     }
 
     fn program_counter(&self) -> &'static CoreRegister {
-        &PC
+        if self.is_64_bit() {
+            &PC_64
+        } else {
+            &PC
+        }
     }
 
     fn frame_pointer(&self) -> &'static CoreRegister {
-        &FP
+        if self.is_64_bit() {
+            &FP_64
+        } else {
+            &FP
+        }
     }
 
     fn stack_pointer(&self) -> &'static CoreRegister {
-        &SP
+        if self.is_64_bit() {
+            &SP_64
+        } else {
+            &SP
+        }
     }
 
     fn return_address(&self) -> &'static CoreRegister {
-        &RA
+        if self.is_64_bit() {
+            &RA_64
+        } else {
+            &RA
+        }
+    }
+
+    fn is_64_bit(&self) -> bool {
+        self.interface.known_xlen() == Some(RiscvBusAccess::A64)
     }
 
     fn hw_breakpoints_enabled(&self) -> bool {
@@ -577,14 +892,18 @@ impl<'state> CoreInterface for Riscv32<'state> {
     }
 
     fn instruction_set(&mut self) -> Result<InstructionSet, Error> {
-        let misa_value = Misa(self.read_csr(0x301)?);
+        // The extensions are in the lower bits of `misa`, independent of XLEN.
+        let misa_value = Misa(self.read_csr(0x301)? as u32);
 
         // Check if the Bit at position 2 (signifies letter C, for compressed) is set.
-        if misa_value.extensions() & (1 << 2) != 0 {
-            Ok(InstructionSet::RV32C)
-        } else {
-            Ok(InstructionSet::RV32)
-        }
+        let compressed = misa_value.extensions() & (1 << 2) != 0;
+
+        Ok(match (self.interface.is_64_bit()?, compressed) {
+            (false, false) => InstructionSet::RV32,
+            (false, true) => InstructionSet::RV32C,
+            (true, false) => InstructionSet::RV64,
+            (true, true) => InstructionSet::RV64C,
+        })
     }
 
     /// Returns the number of fpu registers defined in this register file, or `None` if there are none.
@@ -599,7 +918,7 @@ impl<'state> CoreInterface for Riscv32<'state> {
     fn fpu_support(&mut self) -> Result<bool, crate::error::Error> {
         // Read the extensions from the Machine ISA regiseter.
         let isa_extensions =
-            Misa::from(self.read_csr(Misa::get_mmio_address() as u16)?).extensions();
+            Misa::from(self.read_csr(Misa::get_mmio_address() as u16)? as u32).extensions();
         // Mask for the D(double float), F(single float) and Q(quad float) extension bits.
         let mask = (1 << 3) | (1 << 5) | (1 << 16);
         Ok(isa_extensions & mask != 0)
@@ -646,6 +965,10 @@ pub struct RiscvCoreState {
 
     /// The semihosting command that was decoded at the current program counter
     semihosting_command: Option<SemihostingCommand>,
+
+    /// The halt reason determined from the trigger module. Cached, because determining
+    /// it clears the trigger's `hit` bits.
+    trigger_halt_reason: Option<HaltReason>,
 }
 
 impl RiscvCoreState {
@@ -655,6 +978,7 @@ impl RiscvCoreState {
             hw_breakpoints: None,
             pc_written: false,
             semihosting_command: None,
+            trigger_halt_reason: None,
         }
     }
 }
@@ -888,6 +1212,28 @@ bitfield! {
     load, set_load: 0;
 }
 
+impl Mcontrol {
+    /// Convert the value of `tdata1` on an RV64 hart. The `type`, `dmode` and `maskmax` fields
+    /// are located at the top of the register, the remaining fields are at the same position as on RV32.
+    ///
+    /// The `sizehi` field is not used by probe-rs and is discarded.
+    fn from_rv64(value: u64) -> Self {
+        Mcontrol(
+            ((value >> 32) as u32 & MCONTROL_UPPER_FIELDS)
+                | (value as u32 & !MCONTROL_UPPER_FIELDS),
+        )
+    }
+
+    /// Convert to the value of `tdata1` on an RV64 hart, see [`Mcontrol::from_rv64`].
+    fn to_rv64(&self) -> u64 {
+        (u64::from(self.0 & MCONTROL_UPPER_FIELDS) << 32)
+            | u64::from(self.0 & !MCONTROL_UPPER_FIELDS)
+    }
+}
+
+/// The `type`, `dmode` and `maskmax` fields of `mcontrol`, which are located at the top of the register.
+const MCONTROL_UPPER_FIELDS: u32 = 0xFFE0_0000;
+
 memory_mapped_bitfield_register! {
     /// Isa and Extensions (see RISC-V Privileged Spec, 3.1.1)
     pub struct Misa(u32);
@@ -899,3 +1245,54 @@ memory_mapped_bitfield_register! {
     /// Standard RISC-V extensions
     extensions, _: 25, 0;
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::communication_interface::{test::MockDebugModule, RiscvCommunicationInterfaceState};
+    use super::{communication_interface, sequences::DefaultRiscvSequence};
+    use super::{Mcontrol, Riscv32, RiscvCoreState, RISCV64_CORE_REGISTERS};
+    use crate::CoreInterface;
+
+    #[test]
+    fn register_width_is_determined_once_halted() {
+        let mut interface_state = RiscvCommunicationInterfaceState::new();
+        let interface = communication_interface::test::interface(
+            MockDebugModule::new(true),
+            &mut interface_state,
+        );
+        let mut core_state = RiscvCoreState::new();
+
+        let mut core =
+            Riscv32::new(interface, &mut core_state, DefaultRiscvSequence::create()).unwrap();
+
+        // Attaching does not halt the running hart.
+        assert!(!core.core_halted().unwrap());
+        assert!(!core.is_64_bit());
+
+        core.halt(Duration::from_millis(100)).unwrap();
+
+        assert!(core.is_64_bit());
+        assert!(std::ptr::eq(core.registers(), &*RISCV64_CORE_REGISTERS));
+    }
+
+    #[test]
+    fn mcontrol_rv64_conversion() {
+        // type = 2, dmode, maskmax = 0x3f, hit, action = 1, m, u, execute
+        let rv64 = 0x2FE0_0000_0010_104C;
+
+        let mcontrol = Mcontrol::from_rv64(rv64);
+
+        assert_eq!(mcontrol.type_(), 2);
+        assert!(mcontrol.dmode());
+        assert_eq!(mcontrol.maskmax(), 0x3f);
+        assert!(mcontrol.hit());
+        assert_eq!(mcontrol.action(), 1);
+        assert!(mcontrol.m());
+        assert!(mcontrol.u());
+        assert!(mcontrol.execute());
+
+        assert_eq!(mcontrol.to_rv64(), rv64);
+    }
+}
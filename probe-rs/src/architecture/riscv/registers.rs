@@ -38,6 +38,32 @@ pub(crate) const RA: CoreRegister = CoreRegister {
     unwind_rule: UnwindRule::Clear,
 };
 
+/// The machine exception program counter, which holds the address of the
+/// instruction that was interrupted by the most recent trap.
+pub const MEPC: CoreRegister = CoreRegister {
+    roles: &[RegisterRole::Core("mepc")],
+    id: RegisterId(0x341),
+    data_type: RegisterDataType::UnsignedInteger(32),
+    unwind_rule: UnwindRule::Preserve,
+};
+
+/// The machine trap cause register.
+pub const MCAUSE: CoreRegister = CoreRegister {
+    roles: &[RegisterRole::Core("mcause")],
+    id: RegisterId(0x342),
+    data_type: RegisterDataType::UnsignedInteger(32),
+    unwind_rule: UnwindRule::Preserve,
+};
+
+/// The machine trap value register, which holds exception specific information,
+/// e.g. the faulting address.
+pub const MTVAL: CoreRegister = CoreRegister {
+    roles: &[RegisterRole::Core("mtval")],
+    id: RegisterId(0x343),
+    data_type: RegisterDataType::UnsignedInteger(32),
+    unwind_rule: UnwindRule::Preserve,
+};
+
 // S0 and S1 need to be referenceable as constants in other parts of the architecture specific code.
 pub const S0: CoreRegister = FP;
 pub const S1: CoreRegister = CoreRegister {
@@ -50,6 +76,30 @@ pub const S1: CoreRegister = CoreRegister {
 pub(crate) static RISCV_CORE_REGSISTERS: LazyLock<CoreRegisters> =
     LazyLock::new(|| CoreRegisters::new(RISCV_REGISTERS_SET.iter().collect()));
 
+/// The program counter register of a hart with 64-bit registers (RV64).
+pub const PC_64: CoreRegister = xlen_64(PC);
+pub(crate) const FP_64: CoreRegister = xlen_64(FP);
+pub(crate) const SP_64: CoreRegister = xlen_64(SP);
+pub(crate) const RA_64: CoreRegister = xlen_64(RA);
+
+pub(crate) static RISCV64_CORE_REGISTERS: LazyLock<CoreRegisters> =
+    LazyLock::new(|| CoreRegisters::new(RISCV64_REGISTERS_SET.iter().collect()));
+
+/// The registers of RV64 harts are the same as for RV32, but 64 bits wide.
+static RISCV64_REGISTERS_SET: LazyLock<Vec<CoreRegister>> = LazyLock::new(|| {
+    RISCV_REGISTERS_SET
+        .iter()
+        .map(|register| xlen_64(register.clone()))
+        .collect()
+});
+
+const fn xlen_64(register: CoreRegister) -> CoreRegister {
+    CoreRegister {
+        data_type: RegisterDataType::UnsignedInteger(64),
+        ..register
+    }
+}
+
 static RISCV_REGISTERS_SET: &[CoreRegister] = &[
     CoreRegister {
         roles: &[RegisterRole::Core("x0"), RegisterRole::Other("zero")],
@@ -232,5 +282,8 @@ static RISCV_REGISTERS_SET: &[CoreRegister] = &[
         unwind_rule: UnwindRule::Clear,
     },
     PC,
+    MEPC,
+    MCAUSE,
+    MTVAL,
     // TODO: Add FPU registers
 ];
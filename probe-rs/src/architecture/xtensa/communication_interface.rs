@@ -102,6 +102,9 @@ pub(super) struct XtensaInterfaceState {
     /// The number of hardware breakpoints the target supports. CPU-specific configuration value.
     hw_breakpoint_num: u32,
 
+    /// The number of hardware watchpoints the target supports. CPU-specific configuration value.
+    hw_watchpoint_num: u32,
+
     /// The interrupt level at which debug exceptions are generated. CPU-specific configuration value.
     debug_level: DebugLevel,
 
@@ -117,6 +120,7 @@ impl Default for XtensaInterfaceState {
 
             // FIXME: these are per-chip configuration parameters
             hw_breakpoint_num: 2,
+            hw_watchpoint_num: 2,
             debug_level: DebugLevel::L6,
             slow_memory_access_ranges: vec![],
         }
@@ -195,6 +199,13 @@ impl<'probe> XtensaCommunicationInterface<'probe> {
         self.state.hw_breakpoint_num
     }
 
+    /// Returns the number of hardware watchpoints the target supports.
+    ///
+    /// On the Xtensa architecture this is the `NDBREAK` configuration parameter.
+    pub fn available_watchpoint_units(&self) -> u32 {
+        self.state.hw_watchpoint_num
+    }
+
     /// Returns whether the core is halted.
     pub fn core_halted(&mut self) -> Result<bool, XtensaError> {
         if !self.state.is_halted {
@@ -1033,7 +1044,9 @@ impl DebugCause {
         } else if is_breakpoint {
             HaltReason::Breakpoint(BreakpointCause::Software)
         } else if is_dbreak_exception {
-            HaltReason::Watchpoint
+            // The watchpoint that was hit is identified by `dbreak_num`, which requires
+            // reading the watchpoint configuration.
+            HaltReason::Watchpoint(None)
         } else if is_debug_interrupt {
             HaltReason::Request
         } else {
@@ -1042,6 +1055,29 @@ impl DebugCause {
     }
 }
 
+bitfield::bitfield! {
+    /// The `DBREAKC` (data breakpoint control) registers.
+    #[derive(Copy, Clone)]
+    pub struct DBreakC(u32);
+    impl Debug;
+
+    /// Break on store instructions
+    pub store, set_store: 31;
+
+    /// Break on load instructions
+    pub load,  set_load : 30;
+
+    /// Address mask. The number of trailing zeros is the base-2 logarithm of the watched range's size.
+    pub mask,  set_mask : 5, 0;
+}
+
+impl DBreakC {
+    /// Returns the number of bytes covered by the data breakpoint.
+    pub fn length(&self) -> u64 {
+        1 << self.mask().trailing_zeros().min(6)
+    }
+}
+
 bitfield::bitfield! {
     /// The `PS` (Program Status) register.
     ///
@@ -10,7 +10,7 @@ use crate::{
             instruction::{Instruction, InstructionEncoding},
             Register, SpecialRegister,
         },
-        communication_interface::{DebugCause, IBreakEn, XtensaCommunicationInterface},
+        communication_interface::{DBreakC, DebugCause, IBreakEn, XtensaCommunicationInterface},
         registers::{FP, PC, RA, SP, XTENSA_CORE_REGSISTERS},
         sequences::XtensaDebugSequence,
     },
@@ -22,6 +22,7 @@ use crate::{
     semihosting::decode_semihosting_syscall,
     semihosting::SemihostingCommand,
     CoreInformation, CoreInterface, CoreRegister, CoreStatus, Error, HaltReason, MemoryInterface,
+    Watchpoint, WatchpointKind,
 };
 
 pub(crate) mod arch;
@@ -84,6 +85,10 @@ pub struct Xtensa<'probe> {
 impl<'probe> Xtensa<'probe> {
     const IBREAKA_REGS: [SpecialRegister; 2] =
         [SpecialRegister::IBreakA0, SpecialRegister::IBreakA1];
+    const DBREAKA_REGS: [SpecialRegister; 2] =
+        [SpecialRegister::DBreakA0, SpecialRegister::DBreakA1];
+    const DBREAKC_REGS: [SpecialRegister; 2] =
+        [SpecialRegister::DBreakC0, SpecialRegister::DBreakC1];
 
     /// Create a new Xtensa interface for a particular core.
     pub fn new(
@@ -175,6 +180,56 @@ impl<'probe> Xtensa<'probe> {
         Ok(())
     }
 
+    /// Data breakpoints trigger before the load or store instruction is executed, so to make
+    /// progress we have to step over the instruction with the data breakpoints disabled.
+    ///
+    /// Returns `true` if the core was stepped.
+    fn step_over_watchpoint(&mut self) -> Result<bool, Error> {
+        let debug_cause = self.interface.read_register::<DebugCause>()?;
+        if !debug_cause.dbreak_exception() {
+            return Ok(false);
+        }
+
+        let num_units = self.interface.available_watchpoint_units() as usize;
+        let mut saved_config = Vec::with_capacity(num_units);
+        for register in Self::DBREAKC_REGS.into_iter().take(num_units) {
+            saved_config.push(self.interface.read_register_untyped(register)?);
+            self.interface.write_register_untyped(register, 0)?;
+        }
+
+        self.interface.step()?;
+
+        for (register, value) in Self::DBREAKC_REGS.into_iter().zip(saved_config) {
+            self.interface.write_register_untyped(register, value)?;
+        }
+
+        Ok(true)
+    }
+
+    fn read_watchpoint(&mut self, unit_index: usize) -> Result<Option<Watchpoint>, Error> {
+        let control = DBreakC(
+            self.interface
+                .read_register_untyped(Self::DBREAKC_REGS[unit_index])?,
+        );
+
+        let kind = match (control.load(), control.store()) {
+            (true, true) => WatchpointKind::Access,
+            (true, false) => WatchpointKind::Read,
+            (false, true) => WatchpointKind::Write,
+            (false, false) => return Ok(None),
+        };
+
+        let address = self
+            .interface
+            .read_register_untyped(Self::DBREAKA_REGS[unit_index])?;
+
+        Ok(Some(Watchpoint {
+            address: address as u64,
+            length: control.length(),
+            kind,
+        }))
+    }
+
     /// Check if the current breakpoint is a semihosting call
     // OpenOCD implementation: https://github.com/espressif/openocd-esp32/blob/93dd01511fd13d4a9fb322cd9b600c337becef9e/src/target/espressif/esp_xtensa_semihosting.c#L42-L103
     fn check_for_semihosting(&mut self) -> Result<Option<SemihostingCommand>, Error> {
@@ -260,6 +315,11 @@ impl<'probe> CoreInterface for Xtensa<'probe> {
                 if let Some(cmd) = self.check_for_semihosting()? {
                     reason = HaltReason::Breakpoint(BreakpointCause::Semihosting(cmd));
                 }
+            } else if reason == HaltReason::Watchpoint(None) {
+                let unit_index = debug_cause.dbreak_num() as usize;
+                if unit_index < self.interface.available_watchpoint_units() as usize {
+                    reason = HaltReason::Watchpoint(self.read_watchpoint(unit_index)?);
+                }
             }
 
             CoreStatus::Halted(reason)
@@ -279,6 +339,7 @@ impl<'probe> CoreInterface for Xtensa<'probe> {
 
     fn run(&mut self) -> Result<(), Error> {
         self.skip_breakpoint_instruction()?;
+        self.step_over_watchpoint()?;
         if self.state.pc_written {
             self.interface.clear_register_cache();
         }
@@ -305,7 +366,9 @@ impl<'probe> CoreInterface for Xtensa<'probe> {
 
     fn step(&mut self) -> Result<CoreInformation, Error> {
         self.skip_breakpoint_instruction()?;
-        self.interface.step()?;
+        if !self.step_over_watchpoint()? {
+            self.interface.step()?;
+        }
         self.on_halted()?;
 
         self.core_info()
@@ -395,6 +458,56 @@ impl<'probe> CoreInterface for Xtensa<'probe> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(self.interface.available_watchpoint_units())
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        (0..self.interface.available_watchpoint_units() as usize)
+            .map(|unit_index| self.read_watchpoint(unit_index))
+            .collect()
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        // Data breakpoints can only match naturally aligned, power-of-two sized ranges of up to 64 bytes.
+        let length = watchpoint.length.max(1).next_power_of_two();
+        if length > 64 || watchpoint.address % length != 0 {
+            return Err(Error::Other(format!(
+                "A watchpoint for {} bytes at address {:#010x} can not be represented by a data breakpoint",
+                watchpoint.length, watchpoint.address
+            )));
+        }
+
+        let mut control = DBreakC(0);
+        control.set_mask(0x3F & !(length as u32 - 1));
+        control.set_load(matches!(
+            watchpoint.kind,
+            WatchpointKind::Read | WatchpointKind::Access
+        ));
+        control.set_store(matches!(
+            watchpoint.kind,
+            WatchpointKind::Write | WatchpointKind::Access
+        ));
+
+        self.interface
+            .write_register_untyped(Self::DBREAKA_REGS[unit_index], watchpoint.address as u32)?;
+        self.interface
+            .write_register_untyped(Self::DBREAKC_REGS[unit_index], control.0)?;
+
+        Ok(())
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        self.interface
+            .write_register_untyped(Self::DBREAKC_REGS[unit_index], 0)?;
+
+        Ok(())
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         &XTENSA_CORE_REGSISTERS
     }
@@ -111,6 +111,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: None,
+                image_fixups: vec![],
             }],
             flash_algorithms: vec![],
             source: TargetDescriptionSource::Generic,
@@ -12,7 +12,7 @@ use crate::{
     rtt::ScanRegion,
 };
 use probe_rs_target::{
-    Architecture, Chip, ChipFamily, Jtag, MemoryAccess, MemoryRange as _, NvmRegion,
+    Architecture, Chip, ChipFamily, ImageFixup, Jtag, MemoryAccess, MemoryRange as _, NvmRegion,
 };
 use std::sync::Arc;
 
@@ -44,6 +44,8 @@ pub struct Target {
     pub jtag: Option<Jtag>,
     /// The default executable format for the target.
     pub default_format: Option<String>,
+    /// Post-processing steps which are applied to images before they are flashed.
+    pub image_fixups: Vec<ImageFixup>,
 }
 
 impl std::fmt::Debug for Target {
@@ -131,6 +133,7 @@ impl Target {
             rtt_scan_regions,
             jtag: chip.jtag.clone(),
             default_format: chip.default_binary_format.clone(),
+            image_fixups: chip.image_fixups.clone(),
         }
     }
 
@@ -170,6 +173,15 @@ impl Target {
         FlashLoader::new(self.memory_map.clone(), self.source.clone())
     }
 
+    /// Returns whether the debug sequence of this target can erase all of its memory.
+    pub(crate) fn has_sequence_erase_all(&self) -> bool {
+        match &self.debug_sequence {
+            DebugSequence::Arm(seq) => seq.debug_erase_sequence().is_some(),
+            // Currently, debug_erase_sequence is ARM (and ATSAM) specific
+            _ => false,
+        }
+    }
+
     /// Returns a [RawFlashAlgorithm] by name.
     pub(crate) fn flash_algorithm_by_name(&self, name: &str) -> Option<&RawFlashAlgorithm> {
         self.flash_algorithms.iter().find(|a| a.name == name)
@@ -203,7 +215,7 @@ pub enum TargetSelector {
     /// targets.
     Unspecified(String),
     /// Directly specify a target.
-    Specified(Target),
+    Specified(Box<Target>),
     /// Try to automatically identify the target,
     /// by reading identifying information from
     /// the probe and / or target.
@@ -245,7 +257,7 @@ impl From<()> for TargetSelector {
 
 impl From<Target> for TargetSelector {
     fn from(target: Target) -> Self {
-        TargetSelector::Specified(target)
+        TargetSelector::Specified(Box::new(target))
     }
 }
 
@@ -98,6 +98,31 @@ pub trait CoreInterface: MemoryInterface + CoreMemoryInterfaceShim {
     /// Clears the breakpoint configured in unit `unit_index`.
     fn clear_hw_breakpoint(&mut self, unit_index: usize) -> Result<(), Error>;
 
+    /// Returns the number of available hardware watchpoint units of the core.
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(0)
+    }
+
+    /// Read the hardware watchpoints configured on the core, and adds them to the Result Vector.
+    /// A value of None in any position of the Vector indicates that the position is unset/available.
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(vec![])
+    }
+
+    /// Sets a watchpoint described by `watchpoint`. It does so by using unit `unit_index`.
+    fn set_hw_watchpoint(
+        &mut self,
+        _unit_index: usize,
+        _watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        Err(Error::NotImplemented("hardware watchpoints"))
+    }
+
+    /// Clears the watchpoint configured in unit `unit_index`.
+    fn clear_hw_watchpoint(&mut self, _unit_index: usize) -> Result<(), Error> {
+        Err(Error::NotImplemented("hardware watchpoints"))
+    }
+
     /// Returns a list of all the registers of this core.
     fn registers(&self) -> &'static registers::CoreRegisters;
 
@@ -177,6 +202,19 @@ pub trait CoreInterface: MemoryInterface + CoreMemoryInterfaceShim {
     fn is_64_bit(&self) -> bool {
         false
     }
+
+    /// Determine the security state the core is executing in.
+    ///
+    /// Returns `None` if the core does not implement a security extension.
+    fn security_state(&mut self) -> Result<Option<SecurityState>, Error> {
+        Ok(None)
+    }
+
+    /// Select whether the memory accesses of the debugger are made as Secure or Non-secure
+    /// accesses.
+    fn set_memory_security_state(&mut self, _state: SecurityState) -> Result<(), Error> {
+        Err(Error::NotImplemented("memory security state selection"))
+    }
 }
 
 /// Implementation detail to allow trait upcasting-like behaviour.
@@ -524,6 +562,100 @@ impl<'probe> Core<'probe> {
         Ok(())
     }
 
+    /// Returns the number of available hardware watchpoint units of the core.
+    pub fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.inner.available_watchpoint_units()
+    }
+
+    /// Returns the hardware watchpoints configured on the core.
+    ///
+    /// A value of `None` in any position indicates that the watchpoint unit is unset/available.
+    pub fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        self.inner.hw_watchpoints()
+    }
+
+    /// Set a hardware watchpoint
+    ///
+    /// This function will try to set a hardware watchpoint, which halts the core when
+    /// `length` bytes starting at `address` are accessed in the way described by `kind`.
+    ///
+    /// The amount of hardware watchpoints which are supported is chip specific,
+    /// and can be queried using the `available_watchpoint_units` function.
+    #[tracing::instrument(skip(self))]
+    pub fn set_hw_watchpoint(
+        &mut self,
+        address: u64,
+        length: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        let watchpoints = self.inner.hw_watchpoints()?;
+
+        // If there is a watchpoint set already, reuse its unit, else find the next free unit.
+        // Free units are allocated from the top, because some architectures share their trigger
+        // units between breakpoints and watchpoints, and breakpoints are allocated from the bottom.
+        let unit_index = match watchpoints
+            .iter()
+            .position(|wp| matches!(wp, Some(wp) if wp.address == address && wp.kind == kind))
+        {
+            Some(unit_index) => unit_index,
+            None => watchpoints
+                .iter()
+                .rposition(|wp| wp.is_none())
+                .ok_or_else(|| Error::Other("No available hardware watchpoints".to_string()))?,
+        };
+
+        tracing::debug!(
+            "Trying to set HW watchpoint #{} for {:?} access to {:#08x} ({} bytes)",
+            unit_index,
+            kind,
+            address,
+            length
+        );
+
+        self.inner.set_hw_watchpoint(
+            unit_index,
+            Watchpoint {
+                address,
+                length,
+                kind,
+            },
+        )
+    }
+
+    /// Clear a hardware watchpoint
+    ///
+    /// This function will try to clear a hardware watchpoint of the given `kind` at `address`, if there exists one.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_hw_watchpoint(&mut self, address: u64, kind: WatchpointKind) -> Result<(), Error> {
+        let wp_position = self
+            .inner
+            .hw_watchpoints()?
+            .iter()
+            .position(|wp| matches!(wp, Some(wp) if wp.address == address && wp.kind == kind));
+
+        match wp_position {
+            Some(wp_position) => self.inner.clear_hw_watchpoint(wp_position),
+            None => Err(Error::Other(format!(
+                "No {:?} watchpoint found at address {:#010x}",
+                kind, address
+            ))),
+        }
+    }
+
+    /// Clear all hardware watchpoints
+    ///
+    /// This function will clear all HW watchpoints which are configured on the target,
+    /// regardless if they are set by probe-rs.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_all_hw_watchpoints(&mut self) -> Result<(), Error> {
+        for (unit_index, watchpoint) in self.inner.hw_watchpoints()?.into_iter().enumerate() {
+            if watchpoint.is_some() {
+                self.inner.clear_hw_watchpoint(unit_index)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the architecture of the core.
     pub fn architecture(&self) -> Architecture {
         self.inner.architecture()
@@ -580,6 +712,23 @@ impl<'probe> Core<'probe> {
     pub fn is_64_bit(&self) -> bool {
         self.inner.is_64_bit()
     }
+
+    /// Determine the security state the core is executing in.
+    ///
+    /// Returns `None` if the core does not implement a security extension.
+    /// This must be queried while halted.
+    pub fn security_state(&mut self) -> Result<Option<SecurityState>, Error> {
+        self.inner.security_state()
+    }
+
+    /// Select whether the memory accesses of the debugger are made as Secure or Non-secure
+    /// accesses.
+    ///
+    /// The selection is kept until it is changed again, and applies to all following memory
+    /// accesses through this core.
+    pub fn set_memory_security_state(&mut self, state: SecurityState) -> Result<(), Error> {
+        self.inner.set_memory_security_state(state)
+    }
 }
 
 impl<'probe> CoreInterface for Core<'probe> {
@@ -650,6 +799,26 @@ impl<'probe> CoreInterface for Core<'probe> {
         self.clear_all_hw_breakpoints()
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.available_watchpoint_units()
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        self.hw_watchpoints()
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        self.inner.set_hw_watchpoint(unit_index, watchpoint)
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        self.inner.clear_hw_watchpoint(unit_index)
+    }
+
     fn registers(&self) -> &'static registers::CoreRegisters {
         self.registers()
     }
@@ -709,6 +878,14 @@ impl<'probe> CoreInterface for Core<'probe> {
     fn is_64_bit(&self) -> bool {
         self.is_64_bit()
     }
+
+    fn security_state(&mut self) -> Result<Option<SecurityState>, Error> {
+        self.security_state()
+    }
+
+    fn set_memory_security_state(&mut self, state: SecurityState) -> Result<(), Error> {
+        self.set_memory_security_state(state)
+    }
 }
 
 pub enum ResolvedCoreOptions {
@@ -44,6 +44,32 @@ pub enum BreakpointCause {
     Semihosting(SemihostingCommand),
 }
 
+/// The kind of memory access that triggers a hardware watchpoint.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WatchpointKind {
+    /// Trigger when the watched memory is read.
+    Read,
+    /// Trigger when the watched memory is written.
+    Write,
+    /// Trigger when the watched memory is either read or written.
+    Access,
+}
+
+/// A hardware data watchpoint, which halts the core when the watched memory range is accessed.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Watchpoint {
+    /// The start address of the watched memory range.
+    pub address: u64,
+    /// The length of the watched memory range, in bytes.
+    ///
+    /// Depending on the architecture, this may be larger than the length that was
+    /// requested when setting the watchpoint, because the comparators can only match
+    /// naturally aligned, power-of-two sized ranges.
+    pub length: u64,
+    /// The kind of access which triggers the watchpoint.
+    pub kind: WatchpointKind,
+}
+
 /// The reason why a core was halted.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum HaltReason {
@@ -58,8 +84,9 @@ pub enum HaltReason {
     /// Core halted due to an exception, e.g. an
     /// an interrupt.
     Exception,
-    /// Core halted due to a data watchpoint
-    Watchpoint,
+    /// Core halted due to a data watchpoint. Contains the watchpoint that triggered the halt, if the
+    /// architecture allows us to determine it.
+    Watchpoint(Option<Watchpoint>),
     /// Core halted after single step
     Step,
     /// Core halted because of a debugger request
@@ -86,3 +113,22 @@ pub enum VectorCatchCondition {
     /// We encountered any exception.
     All,
 }
+
+/// The security state of a core which implements a security extension, like the ARMv8-M
+/// Security Extension (TrustZone).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SecurityState {
+    /// The core executes in the Secure state, or accesses Secure memory.
+    Secure,
+    /// The core executes in the Non-secure state, or accesses Non-secure memory.
+    NonSecure,
+}
+
+impl std::fmt::Display for SecurityState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecurityState::Secure => write!(f, "Secure"),
+            SecurityState::NonSecure => write!(f, "Non-secure"),
+        }
+    }
+}
@@ -8,11 +8,11 @@ use crate::{
             aarch64::AARCH64_CORE_REGSISTERS,
             cortex_m::{CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS},
         },
-        riscv::registers::RISCV_CORE_REGSISTERS,
+        riscv::registers::{RISCV64_CORE_REGISTERS, RISCV_CORE_REGSISTERS},
         xtensa::registers::XTENSA_CORE_REGSISTERS,
     },
     debug::{DebugRegister, DebugRegisters},
-    Core, CoreType, Error, InstructionSet, MemoryInterface,
+    Core, CoreRegisters, CoreType, Error, InstructionSet, MemoryInterface,
 };
 use crate::{RegisterId, RegisterValue};
 use probe_rs_target::MemoryRange;
@@ -27,6 +27,8 @@ use std::{
 
 use super::RegisterDataType;
 
+mod elf;
+
 /// A snapshot representation of a core state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreDump {
@@ -97,22 +99,81 @@ impl CoreDump {
         Ok(())
     }
 
+    /// Store the dumped core to a file, as an ELF core file.
+    ///
+    /// The dumped memory ranges are stored as `PT_LOAD` segments, and the registers as
+    /// `NT_PRSTATUS` and floating point notes, so that the file can be opened with GDB.
+    pub fn store_elf(&self, path: &Path) -> Result<(), CoreDumpError> {
+        let elf = self.to_elf()?;
+        std::fs::write(path, elf).map_err(|e| {
+            CoreDumpError::CoreDumpFileWrite(e, dunce::canonicalize(path).unwrap_or_default())
+        })
+    }
+
+    /// Encode the dumped core as an ELF core file.
+    pub fn to_elf(&self) -> Result<Vec<u8>, CoreDumpError> {
+        elf::to_elf(self)
+    }
+
     /// Load the dumped core from a file.
+    ///
+    /// The file can either be a MessagePack core dump written by [`CoreDump::store`],
+    /// or an ELF core file.
     pub fn load(path: &Path) -> Result<Self, CoreDumpError> {
-        let file = OpenOptions::new().read(true).open(path).map_err(|e| {
+        let data = std::fs::read(path).map_err(|e| {
             CoreDumpError::CoreDumpFileRead(e, dunce::canonicalize(path).unwrap_or_default())
         })?;
-        rmp_serde::from_read(&file).map_err(CoreDumpError::DecodingCoreDump)
+        Self::load_raw(&data)
     }
 
     /// Load the dumped core from a file.
+    ///
+    /// The data can either be a MessagePack core dump or an ELF core file.
     pub fn load_raw(data: &[u8]) -> Result<Self, CoreDumpError> {
+        if elf::is_elf(data) {
+            return elf::from_elf(data);
+        }
         rmp_serde::from_slice(data).map_err(CoreDumpError::DecodingCoreDump)
     }
 
     /// Read all registers defined in [`crate::core::CoreRegisters`] from the given core.
     pub fn debug_registers(&self) -> DebugRegisters {
-        let reg_list = match self.core_type {
+        let reg_list = self.register_file();
+
+        let mut debug_registers = Vec::<DebugRegister>::new();
+        for (dwarf_id, core_register) in reg_list.core_registers().enumerate() {
+            // Check to ensure the register type is compatible with u64.
+            if matches!(core_register.data_type(), RegisterDataType::UnsignedInteger(size_in_bits) if size_in_bits <= 64)
+            {
+                debug_registers.push(DebugRegister {
+                    core_register,
+                    // The DWARF register ID is only valid for the first 32 registers.
+                    dwarf_id: if dwarf_id < 32 {
+                        Some(dwarf_id as u16)
+                    } else {
+                        None
+                    },
+                    value: match self.registers.get(&core_register.id()) {
+                        Some(register_value) => Some(*register_value),
+                        None => {
+                            tracing::warn!("Failed to read value for register {:?}", core_register);
+                            None
+                        }
+                    },
+                });
+            } else {
+                tracing::trace!(
+                    "Unwind will use the default rule for this register : {:?}",
+                    core_register
+                );
+            }
+        }
+        DebugRegisters(debug_registers)
+    }
+
+    /// The register file of the dumped core.
+    fn register_file(&self) -> &'static CoreRegisters {
+        match self.core_type {
             CoreType::Armv6m => &CORTEX_M_CORE_REGISTERS,
             CoreType::Armv7a => match self.floating_point_register_count {
                 Some(16) => &AARCH32_WITH_FP_16_CORE_REGSISTERS,
@@ -143,39 +204,12 @@ impl CoreDump {
                     &CORTEX_M_CORE_REGISTERS
                 }
             }
-            CoreType::Riscv => &RISCV_CORE_REGSISTERS,
+            CoreType::Riscv => match self.instruction_set {
+                InstructionSet::RV64 | InstructionSet::RV64C => &RISCV64_CORE_REGISTERS,
+                _ => &RISCV_CORE_REGSISTERS,
+            },
             CoreType::Xtensa => &XTENSA_CORE_REGSISTERS,
-        };
-
-        let mut debug_registers = Vec::<DebugRegister>::new();
-        for (dwarf_id, core_register) in reg_list.core_registers().enumerate() {
-            // Check to ensure the register type is compatible with u64.
-            if matches!(core_register.data_type(), RegisterDataType::UnsignedInteger(size_in_bits) if size_in_bits <= 64)
-            {
-                debug_registers.push(DebugRegister {
-                    core_register,
-                    // The DWARF register ID is only valid for the first 32 registers.
-                    dwarf_id: if dwarf_id < 32 {
-                        Some(dwarf_id as u16)
-                    } else {
-                        None
-                    },
-                    value: match self.registers.get(&core_register.id()) {
-                        Some(register_value) => Some(*register_value),
-                        None => {
-                            tracing::warn!("Failed to read value for register {:?}", core_register);
-                            None
-                        }
-                    },
-                });
-            } else {
-                tracing::trace!(
-                    "Unwind will use the default rule for this register : {:?}",
-                    core_register
-                );
-            }
         }
-        DebugRegisters(debug_registers)
     }
 
     /// Returns the type of the core.
@@ -328,4 +362,7 @@ pub enum CoreDumpError {
     /// Decoding the coredump MessagePack failed.
     #[error("Decoding the coredump MessagePack failed.")]
     DecodingCoreDump(rmp_serde::decode::Error),
+    /// The ELF core file is invalid or not supported.
+    #[error("The ELF core file is invalid: {0}")]
+    InvalidElfCoreDump(String),
 }
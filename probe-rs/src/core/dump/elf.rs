@@ -0,0 +1,829 @@
+//! Conversion of core dumps from and to ELF core files.
+//!
+//! The register layout of the `NT_PRSTATUS` and floating point notes follows the Linux
+//! `elf_gregset_t` and `elf_fpregset_t` definitions, which is what GDB expects in core files.
+//! Additionally, the complete [`CoreDump`] metadata is stored in a `probe-rs` note, so that a
+//! dump can be loaded again without losing any registers which don't fit into the standard notes.
+
+use std::collections::HashMap;
+
+use object::{
+    elf,
+    read::elf::{FileHeader, ProgramHeader},
+    Endianness,
+};
+
+use super::{CoreDump, CoreDumpError};
+use crate::{CoreRegister, CoreType, InstructionSet, RegisterValue};
+
+/// The owner name of the note containing the serialized [`CoreDump`] metadata.
+const PROBE_RS_NOTE_NAME: &[u8] = b"probe-rs";
+/// The type of the note containing the serialized [`CoreDump`] metadata.
+const NT_PROBE_RS_CORE_DUMP: u32 = 1;
+
+/// The index of the file class in `e_ident`.
+const EI_CLASS: usize = 4;
+/// The size of `e_ident`.
+const EI_NIDENT: usize = 16;
+
+/// `SIGTRAP`, reported as the signal which stopped the "process".
+const SIGTRAP: u16 = 5;
+
+/// ARM EABI version 5, which GDB requires to pick the correct ABI.
+const EF_ARM_EABI_VER5: u32 = 0x0500_0000;
+/// The RISC-V ELF flag for the compressed instruction set extension.
+const EF_RISCV_RVC: u32 = 0x0001;
+
+/// The T bit in the Cortex-M xPSR. It is always set, and selects a Cortex-M on import.
+const XPSR_THUMB: u32 = 1 << 24;
+/// The T bit in the A-profile CPSR.
+const CPSR_THUMB: u32 = 1 << 5;
+
+/// The registers in `pr_reg` of an ARM `NT_PRSTATUS` note, for M-profile cores.
+/// Empty names are written as zero.
+const ARM_M_GREGS: &[&str] = &[
+    "R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7", "R8", "R9", "R10", "R11", "R12", "R13", "R14",
+    "R15", "XPSR", "",
+];
+
+/// The registers in `pr_reg` of an ARM `NT_PRSTATUS` note, for A-profile cores.
+const ARM_A_GREGS: &[&str] = &[
+    "R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7", "R8", "R9", "R10", "R11", "R12", "R13", "R14",
+    "R15", "CPSR", "",
+];
+
+/// The registers in `pr_reg` of an AArch64 `NT_PRSTATUS` note.
+const AARCH64_GREGS: &[&str] = &[
+    "X0", "X1", "X2", "X3", "X4", "X5", "X6", "X7", "X8", "X9", "X10", "X11", "X12", "X13", "X14",
+    "X15", "X16", "X17", "X18", "X19", "X20", "X21", "X22", "X23", "X24", "X25", "X26", "X27",
+    "X28", "X29", "X30", "SP", "PC", "PSTATE",
+];
+
+/// The registers in `pr_reg` of a RISC-V `NT_PRSTATUS` note. `x0` is replaced by the PC.
+const RISCV_GREGS: &[&str] = &[
+    "pc", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14",
+    "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
+    "x28", "x29", "x30", "x31",
+];
+
+/// The size of the ARM `NT_ARM_VFP` note: 32 double precision registers and FPSCR.
+const ARM_VFP_SIZE: usize = 32 * 8 + 4;
+/// The size of the AArch64 `NT_FPREGSET` note: 32 vector registers, FPSR, FPCR and padding.
+const AARCH64_FPREGSET_SIZE: usize = 32 * 16 + 16;
+
+/// The ELF representation of a core architecture.
+struct ElfArchitecture {
+    machine: u16,
+    flags: u32,
+    is_64_bit: bool,
+    /// The general purpose registers, if the architecture has a known `NT_PRSTATUS` layout.
+    gregs: Option<&'static [&'static str]>,
+}
+
+impl ElfArchitecture {
+    fn for_dump(dump: &CoreDump) -> Self {
+        let is_64_bit = matches!(dump.core_type, CoreType::Armv8a)
+            || matches!(
+                dump.instruction_set,
+                InstructionSet::RV64 | InstructionSet::RV64C
+            );
+
+        let (machine, flags, gregs) = match dump.core_type {
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                (elf::EM_ARM, EF_ARM_EABI_VER5, Some(ARM_M_GREGS))
+            }
+            CoreType::Armv7a => (elf::EM_ARM, EF_ARM_EABI_VER5, Some(ARM_A_GREGS)),
+            CoreType::Armv8a => (elf::EM_AARCH64, 0, Some(AARCH64_GREGS)),
+            CoreType::Riscv => {
+                let flags = match dump.instruction_set {
+                    InstructionSet::RV32C | InstructionSet::RV64C => EF_RISCV_RVC,
+                    _ => 0,
+                };
+                (elf::EM_RISCV, flags, Some(RISCV_GREGS))
+            }
+            // There is no common register layout for Xtensa core files, so only the memory
+            // and the probe-rs note are written.
+            CoreType::Xtensa => (elf::EM_XTENSA, 0, None),
+        };
+
+        ElfArchitecture {
+            machine,
+            flags,
+            is_64_bit,
+            gregs,
+        }
+    }
+
+    fn word_size(&self) -> usize {
+        if self.is_64_bit {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// The offset of `pr_reg` in the `NT_PRSTATUS` note.
+    fn prstatus_registers_offset(&self) -> usize {
+        if self.is_64_bit {
+            112
+        } else {
+            72
+        }
+    }
+
+    /// The offset of `pr_pid` in the `NT_PRSTATUS` note.
+    fn prstatus_pid_offset(&self) -> usize {
+        if self.is_64_bit {
+            32
+        } else {
+            24
+        }
+    }
+}
+
+/// Encode the core dump as an ELF core file.
+pub(super) fn to_elf(dump: &CoreDump) -> Result<Vec<u8>, CoreDumpError> {
+    let architecture = ElfArchitecture::for_dump(dump);
+
+    let mut notes = Vec::new();
+    if let Some(gregs) = architecture.gregs {
+        let prstatus = prstatus(dump, &architecture, gregs);
+        write_note(&mut notes, b"CORE", elf::NT_PRSTATUS, &prstatus);
+    }
+    if let Some(fpregs) = arm_vfp(dump) {
+        write_note(&mut notes, b"LINUX", elf::NT_ARM_VFP, &fpregs);
+    }
+    if let Some(fpregs) = aarch64_fpregset(dump) {
+        write_note(&mut notes, b"CORE", elf::NT_FPREGSET, &fpregs);
+    }
+
+    let metadata = CoreDump {
+        data: Vec::new(),
+        ..dump.clone()
+    };
+    let metadata = rmp_serde::to_vec_named(&metadata).map_err(CoreDumpError::EncodingCoreDump)?;
+    write_note(
+        &mut notes,
+        PROBE_RS_NOTE_NAME,
+        NT_PROBE_RS_CORE_DUMP,
+        &metadata,
+    );
+
+    Ok(ElfWriter::new(&architecture).write(&architecture, &notes, &dump.data))
+}
+
+/// Decode an ELF core file.
+pub(super) fn from_elf(data: &[u8]) -> Result<CoreDump, CoreDumpError> {
+    match data.get(EI_CLASS) {
+        Some(&elf::ELFCLASS32) => parse::<elf::FileHeader32<Endianness>>(data),
+        Some(&elf::ELFCLASS64) => parse::<elf::FileHeader64<Endianness>>(data),
+        _ => Err(invalid("unknown ELF class")),
+    }
+}
+
+/// Returns `true` if the data starts with the ELF magic number.
+pub(super) fn is_elf(data: &[u8]) -> bool {
+    data.starts_with(&elf::ELFMAG)
+}
+
+fn invalid(message: impl Into<String>) -> CoreDumpError {
+    CoreDumpError::InvalidElfCoreDump(message.into())
+}
+
+fn register_map(dump: &CoreDump) -> HashMap<&'static str, &'static CoreRegister> {
+    dump.register_file()
+        .all_registers()
+        .map(|register| (register.name(), register))
+        .collect()
+}
+
+fn register_value(dump: &CoreDump, name: &str) -> Option<u128> {
+    let register = dump
+        .register_file()
+        .all_registers()
+        .find(|r| r.name() == name)?;
+    Some(match dump.registers.get(&register.id())? {
+        RegisterValue::U32(value) => u128::from(*value),
+        RegisterValue::U64(value) => u128::from(*value),
+        RegisterValue::U128(value) => *value,
+    })
+}
+
+fn prstatus(dump: &CoreDump, architecture: &ElfArchitecture, gregs: &[&str]) -> Vec<u8> {
+    let word_size = architecture.word_size();
+    let registers_offset = architecture.prstatus_registers_offset();
+    // `pr_reg` is followed by `pr_fpvalid`, padded to the word size.
+    let mut prstatus = vec![0; registers_offset + gregs.len() * word_size + word_size];
+
+    // pr_cursig
+    prstatus[12..14].copy_from_slice(&SIGTRAP.to_le_bytes());
+    // pr_pid, which GDB uses as the thread ID.
+    let pid_offset = architecture.prstatus_pid_offset();
+    prstatus[pid_offset..pid_offset + 4].copy_from_slice(&1u32.to_le_bytes());
+
+    for (index, name) in gregs.iter().enumerate() {
+        let value = register_value(dump, name).unwrap_or(0);
+        let offset = registers_offset + index * word_size;
+        prstatus[offset..offset + word_size].copy_from_slice(&value.to_le_bytes()[..word_size]);
+    }
+
+    prstatus
+}
+
+fn arm_vfp(dump: &CoreDump) -> Option<Vec<u8>> {
+    if !matches!(
+        dump.core_type,
+        CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m | CoreType::Armv7a
+    ) || !dump.fpu_support
+    {
+        return None;
+    }
+
+    let mut vfp = Vec::with_capacity(ARM_VFP_SIZE);
+    for index in 0..32 {
+        // A-profile cores have double precision registers, while Cortex-M cores only have single
+        // precision registers, two of which make up one double precision register.
+        let value = register_value(dump, &format!("D{index}")).unwrap_or_else(|| {
+            let low = register_value(dump, &format!("S{}", 2 * index)).unwrap_or(0);
+            let high = register_value(dump, &format!("S{}", 2 * index + 1)).unwrap_or(0);
+            low | (high << 32)
+        });
+        vfp.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+    let fpscr = register_value(dump, "FPSCR").unwrap_or(0);
+    vfp.extend_from_slice(&(fpscr as u32).to_le_bytes());
+
+    Some(vfp)
+}
+
+fn aarch64_fpregset(dump: &CoreDump) -> Option<Vec<u8>> {
+    if dump.core_type != CoreType::Armv8a || !dump.fpu_support {
+        return None;
+    }
+
+    let mut fpregset = Vec::with_capacity(AARCH64_FPREGSET_SIZE);
+    for index in 0..32 {
+        let value = register_value(dump, &format!("v{index}")).unwrap_or(0);
+        fpregset.extend_from_slice(&value.to_le_bytes());
+    }
+    for name in ["FPSR", "FPCR"] {
+        let value = register_value(dump, name).unwrap_or(0);
+        fpregset.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+    fpregset.resize(AARCH64_FPREGSET_SIZE, 0);
+
+    Some(fpregset)
+}
+
+fn write_note(notes: &mut Vec<u8>, name: &[u8], note_type: u32, desc: &[u8]) {
+    // The name size includes the terminating NUL byte.
+    notes.extend_from_slice(&(name.len() as u32 + 1).to_le_bytes());
+    notes.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    notes.extend_from_slice(&note_type.to_le_bytes());
+    notes.extend_from_slice(name);
+    notes.push(0);
+    align(notes, 4);
+    notes.extend_from_slice(desc);
+    align(notes, 4);
+}
+
+fn align(buffer: &mut Vec<u8>, alignment: usize) {
+    buffer.resize(buffer.len().next_multiple_of(alignment), 0);
+}
+
+/// A minimal little endian ELF writer, which only supports program headers.
+struct ElfWriter {
+    buffer: Vec<u8>,
+    is_64_bit: bool,
+}
+
+impl ElfWriter {
+    fn new(architecture: &ElfArchitecture) -> Self {
+        ElfWriter {
+            buffer: Vec::new(),
+            is_64_bit: architecture.is_64_bit,
+        }
+    }
+
+    fn header_size(&self) -> usize {
+        if self.is_64_bit {
+            64
+        } else {
+            52
+        }
+    }
+
+    fn program_header_size(&self) -> usize {
+        if self.is_64_bit {
+            56
+        } else {
+            32
+        }
+    }
+
+    fn write(
+        mut self,
+        architecture: &ElfArchitecture,
+        notes: &[u8],
+        data: &[(std::ops::Range<u64>, Vec<u8>)],
+    ) -> Vec<u8> {
+        let program_header_count = 1 + data.len();
+        let notes_offset = self.header_size() + program_header_count * self.program_header_size();
+
+        // e_ident
+        self.buffer.extend_from_slice(&elf::ELFMAG);
+        self.buffer.push(if self.is_64_bit {
+            elf::ELFCLASS64
+        } else {
+            elf::ELFCLASS32
+        });
+        self.buffer.push(elf::ELFDATA2LSB);
+        self.buffer.push(elf::EV_CURRENT);
+        self.buffer.push(elf::ELFOSABI_NONE);
+        self.buffer.resize(EI_NIDENT, 0);
+
+        self.u16(elf::ET_CORE);
+        self.u16(architecture.machine);
+        self.u32(u32::from(elf::EV_CURRENT));
+        // e_entry, e_phoff, e_shoff
+        self.word(0);
+        self.word(self.header_size() as u64);
+        self.word(0);
+        self.u32(architecture.flags);
+        self.u16(self.header_size() as u16);
+        self.u16(self.program_header_size() as u16);
+        self.u16(program_header_count as u16);
+        // e_shentsize, e_shnum, e_shstrndx
+        self.u16(0);
+        self.u16(0);
+        self.u16(0);
+
+        self.program_header(elf::PT_NOTE, 0, notes_offset, 0, notes.len(), 4);
+        let mut offset = notes_offset + notes.len();
+        for (range, memory) in data {
+            self.program_header(
+                elf::PT_LOAD,
+                elf::PF_R | elf::PF_W | elf::PF_X,
+                offset,
+                range.start,
+                memory.len(),
+                1,
+            );
+            offset += memory.len();
+        }
+
+        self.buffer.extend_from_slice(notes);
+        for (_, memory) in data {
+            self.buffer.extend_from_slice(memory);
+        }
+
+        self.buffer
+    }
+
+    fn program_header(
+        &mut self,
+        segment_type: u32,
+        flags: u32,
+        offset: usize,
+        address: u64,
+        size: usize,
+        alignment: u64,
+    ) {
+        self.u32(segment_type);
+        if self.is_64_bit {
+            self.u32(flags);
+        }
+        self.word(offset as u64);
+        // p_vaddr, p_paddr
+        self.word(address);
+        self.word(address);
+        // p_filesz, p_memsz
+        self.word(size as u64);
+        self.word(size as u64);
+        if !self.is_64_bit {
+            self.u32(flags);
+        }
+        self.word(alignment);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn word(&mut self, value: u64) {
+        if self.is_64_bit {
+            self.buffer.extend_from_slice(&value.to_le_bytes());
+        } else {
+            self.u32(value as u32);
+        }
+    }
+}
+
+/// The notes of a core file which are relevant for the register state.
+#[derive(Default)]
+struct Notes<'data> {
+    prstatus: Option<&'data [u8]>,
+    arm_vfp: Option<&'data [u8]>,
+    fpregset: Option<&'data [u8]>,
+    probe_rs: Option<&'data [u8]>,
+}
+
+fn parse<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Result<CoreDump, CoreDumpError> {
+    let header = Elf::parse(data).map_err(|e| invalid(e.to_string()))?;
+    let endian = header.endian().map_err(|e| invalid(e.to_string()))?;
+    if header.e_type(endian) != elf::ET_CORE {
+        return Err(invalid("the file is not a core file"));
+    }
+
+    let mut memory = Vec::new();
+    let mut notes = Notes::default();
+    for segment in header
+        .program_headers(endian, data)
+        .map_err(|e| invalid(e.to_string()))?
+    {
+        match segment.p_type(endian) {
+            elf::PT_LOAD => {
+                let bytes = segment
+                    .data(endian, data)
+                    .map_err(|_| invalid("a PT_LOAD segment is out of bounds"))?;
+                if !bytes.is_empty() {
+                    let start: u64 = segment.p_vaddr(endian).into();
+                    memory.push((start..start + bytes.len() as u64, bytes.to_vec()));
+                }
+            }
+            elf::PT_NOTE => {
+                let Some(mut iterator) = segment
+                    .notes(endian, data)
+                    .map_err(|e| invalid(e.to_string()))?
+                else {
+                    continue;
+                };
+                while let Some(note) = iterator.next().map_err(|e| invalid(e.to_string()))? {
+                    let desc = Some(note.desc());
+                    match (note.name(), note.n_type(endian)) {
+                        // Only the first thread is used, which is the one that caused the dump.
+                        (b"CORE", elf::NT_PRSTATUS) if notes.prstatus.is_none() => {
+                            notes.prstatus = desc
+                        }
+                        (b"CORE", elf::NT_FPREGSET) if notes.fpregset.is_none() => {
+                            notes.fpregset = desc
+                        }
+                        (b"LINUX", elf::NT_ARM_VFP) if notes.arm_vfp.is_none() => {
+                            notes.arm_vfp = desc
+                        }
+                        (PROBE_RS_NOTE_NAME, NT_PROBE_RS_CORE_DUMP) => notes.probe_rs = desc,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(metadata) = notes.probe_rs {
+        let metadata: CoreDump =
+            rmp_serde::from_slice(metadata).map_err(CoreDumpError::DecodingCoreDump)?;
+        return Ok(CoreDump {
+            data: memory,
+            ..metadata
+        });
+    }
+
+    let is_64_bit = header.is_class_64();
+    let mut dump = foreign_core_dump(
+        header.e_machine(endian),
+        header.e_flags(endian),
+        is_64_bit,
+        &notes,
+    )?;
+    dump.data = memory;
+
+    let architecture = ElfArchitecture::for_dump(&dump);
+    if let (Some(gregs), Some(prstatus)) = (architecture.gregs, notes.prstatus) {
+        read_prstatus(&mut dump, &architecture, gregs, prstatus)?;
+    }
+    if let Some(vfp) = notes.arm_vfp {
+        read_arm_vfp(&mut dump, vfp)?;
+    }
+    if let Some(fpregset) = notes
+        .fpregset
+        .filter(|_| dump.core_type == CoreType::Armv8a)
+    {
+        read_aarch64_fpregset(&mut dump, fpregset)?;
+    }
+
+    Ok(dump)
+}
+
+/// Determine the core of a core file which was not written by probe-rs.
+fn foreign_core_dump(
+    machine: u16,
+    flags: u32,
+    is_64_bit: bool,
+    notes: &Notes,
+) -> Result<CoreDump, CoreDumpError> {
+    let (core_type, instruction_set, fpu_support) = match machine {
+        elf::EM_ARM if !is_64_bit => {
+            let prstatus = notes
+                .prstatus
+                .ok_or_else(|| invalid("the core file has no NT_PRSTATUS note"))?;
+            // The processor status register follows R0 to R15.
+            let psr = read_u32(prstatus, 72 + 16 * 4)?;
+            let fpu_support = notes.arm_vfp.is_some();
+            if psr & XPSR_THUMB != 0 {
+                // Only the Armv7-EM and Armv8-M profiles have an optional FPU.
+                let core_type = if fpu_support {
+                    CoreType::Armv7em
+                } else {
+                    CoreType::Armv7m
+                };
+                (core_type, InstructionSet::Thumb2, fpu_support)
+            } else if psr & CPSR_THUMB != 0 {
+                (CoreType::Armv7a, InstructionSet::Thumb2, fpu_support)
+            } else {
+                (CoreType::Armv7a, InstructionSet::A32, fpu_support)
+            }
+        }
+        elf::EM_AARCH64 if is_64_bit => (
+            CoreType::Armv8a,
+            InstructionSet::A64,
+            notes.fpregset.is_some(),
+        ),
+        elf::EM_RISCV => {
+            let compressed = flags & EF_RISCV_RVC != 0;
+            let instruction_set = match (is_64_bit, compressed) {
+                (false, false) => InstructionSet::RV32,
+                (false, true) => InstructionSet::RV32C,
+                (true, false) => InstructionSet::RV64,
+                (true, true) => InstructionSet::RV64C,
+            };
+            (CoreType::Riscv, instruction_set, false)
+        }
+        machine => {
+            return Err(invalid(format!(
+                "core files for ELF machine {machine} are only supported if written by probe-rs"
+            )))
+        }
+    };
+
+    let floating_point_register_count = match core_type {
+        CoreType::Armv7a | CoreType::Armv8a | CoreType::Armv7em if fpu_support => Some(32),
+        _ => None,
+    };
+
+    Ok(CoreDump {
+        registers: HashMap::new(),
+        data: Vec::new(),
+        instruction_set,
+        supports_native_64bit_access: is_64_bit,
+        core_type,
+        fpu_support,
+        floating_point_register_count,
+    })
+}
+
+fn read_prstatus(
+    dump: &mut CoreDump,
+    architecture: &ElfArchitecture,
+    gregs: &[&str],
+    prstatus: &[u8],
+) -> Result<(), CoreDumpError> {
+    let registers = register_map(dump);
+    let word_size = architecture.word_size();
+    let registers_offset = architecture.prstatus_registers_offset();
+
+    for (index, name) in gregs.iter().enumerate() {
+        let Some(register) = registers.get(name) else {
+            continue;
+        };
+        let offset = registers_offset + index * word_size;
+        let bytes = prstatus
+            .get(offset..offset + word_size)
+            .ok_or_else(|| invalid("the NT_PRSTATUS note is truncated"))?;
+        let mut value = [0; 16];
+        value[..word_size].copy_from_slice(bytes);
+        insert_register(dump, register, u128::from_le_bytes(value));
+    }
+
+    Ok(())
+}
+
+fn read_arm_vfp(dump: &mut CoreDump, vfp: &[u8]) -> Result<(), CoreDumpError> {
+    if vfp.len() < ARM_VFP_SIZE {
+        return Err(invalid("the NT_ARM_VFP note is truncated"));
+    }
+
+    let registers = register_map(dump);
+    for index in 0..32 {
+        let value = read_u64(vfp, index * 8)?;
+        if let Some(register) = registers.get(format!("D{index}").as_str()) {
+            insert_register(dump, register, u128::from(value));
+        }
+        for (half, value) in [value as u32, (value >> 32) as u32].into_iter().enumerate() {
+            if let Some(register) = registers.get(format!("S{}", 2 * index + half).as_str()) {
+                insert_register(dump, register, u128::from(value));
+            }
+        }
+    }
+    if let Some(register) = registers.get("FPSCR") {
+        insert_register(dump, register, u128::from(read_u32(vfp, 32 * 8)?));
+    }
+
+    Ok(())
+}
+
+fn read_aarch64_fpregset(dump: &mut CoreDump, fpregset: &[u8]) -> Result<(), CoreDumpError> {
+    let registers = register_map(dump);
+    for index in 0..32 {
+        let bytes = fpregset
+            .get(index * 16..index * 16 + 16)
+            .ok_or_else(|| invalid("the NT_FPREGSET note is truncated"))?;
+        if let Some(register) = registers.get(format!("v{index}").as_str()) {
+            let mut value = [0; 16];
+            value.copy_from_slice(bytes);
+            insert_register(dump, register, u128::from_le_bytes(value));
+        }
+    }
+    for (index, name) in ["FPSR", "FPCR"].into_iter().enumerate() {
+        if let Some(register) = registers.get(name) {
+            let value = read_u32(fpregset, 32 * 16 + index * 4)?;
+            insert_register(dump, register, u128::from(value));
+        }
+    }
+
+    Ok(())
+}
+
+fn insert_register(dump: &mut CoreDump, register: &CoreRegister, value: u128) {
+    let value = match register.size_in_bits() {
+        0..=32 => RegisterValue::U32(value as u32),
+        33..=64 => RegisterValue::U64(value as u64),
+        _ => RegisterValue::U128(value),
+    };
+    dump.registers.insert(register.id(), value);
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, CoreDumpError> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| invalid("a note is truncated"))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, CoreDumpError> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| invalid("a note is truncated"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::RegisterId;
+
+    fn cortex_m_dump() -> CoreDump {
+        let mut registers = HashMap::new();
+        for id in 0..16 {
+            registers.insert(RegisterId(id), RegisterValue::U32(0x1000 + u32::from(id)));
+        }
+        // xPSR with the T bit set.
+        registers.insert(RegisterId(0x10), RegisterValue::U32(0x0100_0003));
+
+        CoreDump {
+            registers,
+            data: vec![
+                (0x2000_0000..0x2000_0008, vec![1, 2, 3, 4, 5, 6, 7, 8]),
+                (0x0800_0000..0x0800_0004, vec![0xAA, 0xBB, 0xCC, 0xDD]),
+            ],
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type: CoreType::Armv7m,
+            fpu_support: false,
+            floating_point_register_count: Some(32),
+        }
+    }
+
+    /// Remove the probe-rs note, by renaming it, to simulate a core file written by other tools.
+    fn strip_probe_rs_note(elf: &mut [u8]) {
+        let position = elf
+            .windows(PROBE_RS_NOTE_NAME.len())
+            .position(|window| window == PROBE_RS_NOTE_NAME)
+            .unwrap();
+        elf[position] = b'x';
+    }
+
+    #[test]
+    fn round_trip() {
+        let dump = cortex_m_dump();
+        let elf = to_elf(&dump).unwrap();
+        assert!(is_elf(&elf));
+
+        let loaded = from_elf(&elf).unwrap();
+        assert_eq!(loaded.registers, dump.registers);
+        assert_eq!(loaded.data, dump.data);
+        assert_eq!(loaded.core_type, CoreType::Armv7m);
+    }
+
+    #[test]
+    fn segments_and_prstatus() {
+        let elf = to_elf(&cortex_m_dump()).unwrap();
+
+        let file = object::read::elf::ElfFile32::<Endianness>::parse(elf.as_slice()).unwrap();
+        let header = file.elf_header();
+        assert_eq!(header.e_machine(Endianness::Little), elf::EM_ARM);
+        assert_eq!(header.e_type(Endianness::Little), elf::ET_CORE);
+
+        let segments = file.elf_program_headers();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].p_type(Endianness::Little), elf::PT_NOTE);
+        assert_eq!(segments[1].p_type(Endianness::Little), elf::PT_LOAD);
+        assert_eq!(segments[1].p_vaddr(Endianness::Little), 0x2000_0000);
+        assert_eq!(
+            segments[2]
+                .data(Endianness::Little, elf.as_slice())
+                .unwrap(),
+            &[0xAA, 0xBB, 0xCC, 0xDD]
+        );
+
+        let mut notes = segments[0]
+            .notes(Endianness::Little, elf.as_slice())
+            .unwrap()
+            .unwrap();
+        let prstatus = notes.next().unwrap().unwrap();
+        assert_eq!(prstatus.name(), b"CORE");
+        assert_eq!(prstatus.n_type(Endianness::Little), elf::NT_PRSTATUS);
+        assert_eq!(prstatus.desc().len(), 148);
+        // R0 and the PC.
+        assert_eq!(read_u32(prstatus.desc(), 72).unwrap(), 0x1000);
+        assert_eq!(read_u32(prstatus.desc(), 72 + 15 * 4).unwrap(), 0x100F);
+    }
+
+    #[test]
+    fn import_foreign_cortex_m() {
+        let mut dump = cortex_m_dump();
+        dump.fpu_support = true;
+        dump.core_type = CoreType::Armv7em;
+        // S2 and S3 make up D1.
+        dump.registers
+            .insert(RegisterId(66), RegisterValue::U32(0x1111));
+        dump.registers
+            .insert(RegisterId(67), RegisterValue::U32(0x2222));
+
+        let mut elf = to_elf(&dump).unwrap();
+        strip_probe_rs_note(&mut elf);
+        let loaded = from_elf(&elf).unwrap();
+
+        assert_eq!(loaded.core_type, CoreType::Armv7em);
+        assert_eq!(loaded.instruction_set, InstructionSet::Thumb2);
+        assert!(loaded.fpu_support);
+        assert_eq!(
+            loaded.registers.get(&RegisterId(15)),
+            Some(&RegisterValue::U32(0x100F))
+        );
+        assert_eq!(
+            loaded.registers.get(&RegisterId(0x10)),
+            Some(&RegisterValue::U32(0x0100_0003))
+        );
+        assert_eq!(
+            loaded.registers.get(&RegisterId(67)),
+            Some(&RegisterValue::U32(0x2222))
+        );
+        assert_eq!(loaded.data, dump.data);
+    }
+
+    #[test]
+    fn import_foreign_riscv64() {
+        let dump = CoreDump {
+            registers: HashMap::from([
+                (RegisterId(0x7b1), RegisterValue::U64(0x8000_0000_1234)),
+                (RegisterId(0x1002), RegisterValue::U64(0x8000_0001_0000)),
+            ]),
+            data: vec![(0x8000_0000..0x8000_0002, vec![0x01, 0x00])],
+            instruction_set: InstructionSet::RV64C,
+            supports_native_64bit_access: true,
+            core_type: CoreType::Riscv,
+            fpu_support: false,
+            floating_point_register_count: None,
+        };
+
+        let mut elf = to_elf(&dump).unwrap();
+        assert_eq!(elf[EI_CLASS], elf::ELFCLASS64);
+        strip_probe_rs_note(&mut elf);
+        let loaded = from_elf(&elf).unwrap();
+
+        assert_eq!(loaded.instruction_set, InstructionSet::RV64C);
+        assert_eq!(
+            loaded.registers.get(&RegisterId(0x7b1)),
+            Some(&RegisterValue::U64(0x8000_0000_1234))
+        );
+        assert_eq!(
+            loaded.registers.get(&RegisterId(0x1002)),
+            Some(&RegisterValue::U64(0x8000_0001_0000))
+        );
+    }
+}
@@ -17,7 +17,10 @@ use crate::{
 use gimli::{
     BaseAddresses, DebugFrame, DebugInfoOffset, UnwindContext, UnwindSection, UnwindTableRow,
 };
-use object::read::{Object, ObjectSection};
+use object::{
+    read::{Object, ObjectSection, ObjectSymbol},
+    SectionKind,
+};
 use probe_rs_target::InstructionSet;
 use std::{
     borrow, cmp::Ordering, num::NonZeroU64, ops::ControlFlow, path::Path, rc::Rc, str::from_utf8,
@@ -41,6 +44,21 @@ pub struct DebugInfo {
     pub(crate) debug_line_section: gimli::DebugLine<DwarfReader>,
 
     pub(crate) unit_infos: Vec<UnitInfo>,
+
+    /// Symbols in executable sections of the ELF file, sorted by address.
+    pub(crate) code_symbols: Vec<CodeSymbol>,
+}
+
+/// A symbol from the ELF symbol table that marks a location in code.
+///
+/// Unlike the DWARF function information, this also covers hand-written assembly,
+/// such as the trap entry routines of embedded runtimes.
+#[derive(Debug, Clone)]
+pub(crate) struct CodeSymbol {
+    pub(crate) name: String,
+    pub(crate) address: u64,
+    /// The size of the symbol, which is zero for plain assembly labels.
+    pub(crate) size: u64,
 }
 
 impl DebugInfo {
@@ -97,6 +115,29 @@ impl DebugInfo {
             };
         }
 
+        let mut code_symbols = object
+            .symbols()
+            .filter(|symbol| {
+                symbol
+                    .section_index()
+                    .and_then(|index| object.section_by_index(index).ok())
+                    .is_some_and(|section| section.kind() == SectionKind::Text)
+            })
+            .filter_map(|symbol| {
+                let name = symbol.name().ok()?;
+                // Skip mapping symbols (`$x`, `$d`, ...) and local labels.
+                if name.is_empty() || name.starts_with('$') || name.starts_with(".L") {
+                    return None;
+                }
+                Some(CodeSymbol {
+                    name: name.to_string(),
+                    address: symbol.address(),
+                    size: symbol.size(),
+                })
+            })
+            .collect::<Vec<_>>();
+        code_symbols.sort_by_key(|symbol| symbol.address);
+
         Ok(DebugInfo {
             dwarf: dwarf_cow,
             frame_section,
@@ -104,9 +145,32 @@ impl DebugInfo {
             address_section,
             debug_line_section,
             unit_infos,
+            code_symbols,
         })
     }
 
+    /// Find the name of the code symbol that contains the given address.
+    ///
+    /// Symbols without a size (e.g. labels in assembly code) are assumed to extend
+    /// up to the next symbol.
+    pub(crate) fn get_code_symbol_name(&self, address: u64) -> Option<&str> {
+        let index = self
+            .code_symbols
+            .partition_point(|symbol| symbol.address <= address);
+        let symbol = self.code_symbols[..index].last()?;
+
+        if symbol.size == 0 || address < symbol.address + symbol.size {
+            Some(&symbol.name)
+        } else {
+            None
+        }
+    }
+
+    /// Check if the ELF file has a code symbol with the given name.
+    pub(crate) fn has_code_symbol(&self, name: &str) -> bool {
+        self.code_symbols.iter().any(|symbol| symbol.name == name)
+    }
+
     /// Try get the [`SourceLocation`] for a given address.
     pub fn get_source_location(&self, address: u64) -> Option<SourceLocation> {
         for unit_info in &self.unit_infos {
@@ -953,6 +1017,266 @@ impl DebugInfo {
                 },
             )
     }
+
+    /// The size of an address on the target, in bytes, as recorded in the debug information.
+    pub(crate) fn address_size(&self) -> u8 {
+        self.unit_infos
+            .first()
+            .map(|unit_info| unit_info.unit.encoding().address_size)
+            .unwrap_or(4)
+    }
+
+    /// Find all statically allocated variables with the given name, across all compilation units.
+    /// Declarations without a fixed address (e.g. `extern` declarations in C) are ignored.
+    pub(crate) fn find_static_variables(&self, name: &str) -> Vec<StaticVariableInfo> {
+        let mut variables = Vec::new();
+
+        for unit_info in &self.unit_infos {
+            let unit = &unit_info.unit;
+            let mut entries = unit.entries();
+            let mut depth = 0;
+            // The names of the namespaces containing the current entry, and the depth at which they were entered.
+            let mut namespaces: Vec<(isize, String)> = Vec::new();
+
+            while let Ok(Some((delta_depth, entry))) = entries.next_dfs() {
+                depth += delta_depth;
+                while namespaces
+                    .last()
+                    .is_some_and(|(namespace_depth, _)| *namespace_depth >= depth)
+                {
+                    namespaces.pop();
+                }
+
+                match entry.tag() {
+                    gimli::DW_TAG_namespace | gimli::DW_TAG_subprogram => {
+                        if let Some(namespace) = self.entry_name(unit, entry) {
+                            namespaces.push((depth, namespace));
+                        }
+                    }
+                    gimli::DW_TAG_variable
+                        if self.entry_name(unit, entry).as_deref() == Some(name) =>
+                    {
+                        let Some(address) = static_address(entry, unit.encoding().address_size)
+                        else {
+                            continue;
+                        };
+                        let type_entry = self.resolve_type(unit, entry);
+                        variables.push(StaticVariableInfo {
+                            address,
+                            byte_size: type_entry
+                                .as_ref()
+                                .and_then(|type_entry| self.type_byte_size(unit, type_entry)),
+                            type_name: type_entry
+                                .as_ref()
+                                .and_then(|type_entry| self.entry_name(unit, type_entry)),
+                            namespace: namespaces.iter().map(|(_, name)| name.clone()).collect(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        variables
+    }
+
+    /// Find the first statically allocated variable with the given name. See [`Self::find_static_variables`].
+    pub(crate) fn find_static_variable(&self, name: &str) -> Option<StaticVariableInfo> {
+        self.find_static_variables(name).into_iter().next()
+    }
+
+    /// Find the size, in bytes, of the structure with the given name.
+    pub(crate) fn struct_byte_size(&self, struct_name: &str) -> Option<u64> {
+        self.unit_infos.iter().find_map(|unit_info| {
+            let entry = self.find_struct(&unit_info.unit, struct_name)?;
+            super::extract_byte_size(&entry)
+        })
+    }
+
+    /// Find the offset of a member of the structure with the given name.
+    ///
+    /// The `member_path` can refer to members of nested structures, by separating the member names with a `.`,
+    /// e.g. `base.thread_state`. Members that are arrays resolve to the offset of their first element.
+    pub(crate) fn struct_member_offset(&self, struct_name: &str, member_path: &str) -> Option<u64> {
+        self.unit_infos.iter().find_map(|unit_info| {
+            let unit = &unit_info.unit;
+            let mut struct_entry = self.find_struct(unit, struct_name)?;
+            let mut offset = 0;
+
+            let mut members = member_path.split('.').peekable();
+            while let Some(member_name) = members.next() {
+                let mut tree = unit.entries_tree(Some(struct_entry.offset())).ok()?;
+                let root = tree.root().ok()?;
+                let mut children = root.children();
+
+                let mut member_entry = None;
+                while let Ok(Some(child)) = children.next() {
+                    let entry = child.entry();
+                    if entry.tag() == gimli::DW_TAG_member
+                        && self.entry_name(unit, entry).as_deref() == Some(member_name)
+                    {
+                        member_entry = Some(entry.clone());
+                        break;
+                    }
+                }
+                let member_entry = member_entry?;
+
+                offset += match member_entry
+                    .attr_value(gimli::DW_AT_data_member_location)
+                    .ok()?
+                {
+                    Some(gimli::AttributeValue::Udata(offset)) => offset,
+                    Some(gimli::AttributeValue::Sdata(offset)) => u64::try_from(offset).ok()?,
+                    Some(gimli::AttributeValue::Data1(offset)) => offset as u64,
+                    Some(gimli::AttributeValue::Data2(offset)) => offset as u64,
+                    Some(gimli::AttributeValue::Data4(offset)) => offset as u64,
+                    Some(gimli::AttributeValue::Data8(offset)) => offset,
+                    // Members of unions have no location.
+                    None => 0,
+                    Some(other) => {
+                        tracing::debug!("Unsupported member location {other:?} for {member_path}");
+                        return None;
+                    }
+                };
+
+                if members.peek().is_some() {
+                    let mut type_entry = self.resolve_type(unit, &member_entry)?;
+                    // For arrays, we continue with the element type.
+                    if type_entry.tag() == gimli::DW_TAG_array_type {
+                        type_entry = self.resolve_type(unit, &type_entry)?;
+                    }
+                    struct_entry = type_entry;
+                }
+            }
+
+            Some(offset)
+        })
+    }
+
+    /// Find the definition (not a declaration) of a structure or union type with the given name.
+    fn find_struct<'unit>(
+        &self,
+        unit: &'unit gimli::Unit<GimliReader, usize>,
+        struct_name: &str,
+    ) -> Option<Die<'unit, 'unit>> {
+        let mut entries = unit.entries();
+        while let Ok(Some((_, entry))) = entries.next_dfs() {
+            if matches!(
+                entry.tag(),
+                gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type
+            ) && !matches!(
+                entry.attr_value(gimli::DW_AT_declaration),
+                Ok(Some(gimli::AttributeValue::Flag(true)))
+            ) && self.entry_name(unit, entry).as_deref() == Some(struct_name)
+            {
+                return Some(entry.clone());
+            }
+        }
+        None
+    }
+
+    /// Resolve the `DW_AT_type` of an entry, skipping over any typedefs and type qualifiers.
+    fn resolve_type<'unit>(
+        &self,
+        unit: &'unit gimli::Unit<GimliReader, usize>,
+        entry: &Die<'unit, 'unit>,
+    ) -> Option<Die<'unit, 'unit>> {
+        let mut entry = entry.clone();
+        loop {
+            let Ok(Some(gimli::AttributeValue::UnitRef(type_offset))) =
+                entry.attr_value(gimli::DW_AT_type)
+            else {
+                return None;
+            };
+            entry = unit.entry(type_offset).ok()?;
+            if !matches!(
+                entry.tag(),
+                gimli::DW_TAG_typedef
+                    | gimli::DW_TAG_const_type
+                    | gimli::DW_TAG_volatile_type
+                    | gimli::DW_TAG_atomic_type
+            ) {
+                return Some(entry);
+            }
+        }
+    }
+
+    /// The size of a type in bytes. For arrays without an explicit size, this is calculated from the element size and count.
+    fn type_byte_size<'unit>(
+        &self,
+        unit: &'unit gimli::Unit<GimliReader, usize>,
+        entry: &Die<'unit, 'unit>,
+    ) -> Option<u64> {
+        if let Some(byte_size) = super::extract_byte_size(entry) {
+            return Some(byte_size);
+        }
+        if entry.tag() != gimli::DW_TAG_array_type {
+            return None;
+        }
+
+        let element_size = self
+            .resolve_type(unit, entry)
+            .and_then(|element_type| self.type_byte_size(unit, &element_type))?;
+
+        let mut tree = unit.entries_tree(Some(entry.offset())).ok()?;
+        let root = tree.root().ok()?;
+        let mut children = root.children();
+        let mut element_count = 1;
+        while let Ok(Some(child)) = children.next() {
+            let subrange = child.entry();
+            if subrange.tag() != gimli::DW_TAG_subrange_type {
+                continue;
+            }
+            let count = if let Ok(Some(count)) = subrange.attr_value(gimli::DW_AT_count) {
+                count.udata_value()?
+            } else if let Ok(Some(upper_bound)) = subrange.attr_value(gimli::DW_AT_upper_bound) {
+                upper_bound.udata_value()? + 1
+            } else {
+                return None;
+            };
+            element_count *= count;
+        }
+
+        Some(element_size * element_count)
+    }
+
+    /// The `DW_AT_name` of an entry, if it has one.
+    fn entry_name(
+        &self,
+        unit: &gimli::Unit<GimliReader, usize>,
+        entry: &Die<'_, '_>,
+    ) -> Option<String> {
+        let name = entry.attr_value(gimli::DW_AT_name).ok()??;
+        let name = self.dwarf.attr_string(unit, name).ok()?;
+        Some(gimli::Reader::to_string_lossy(&name).ok()?.into_owned())
+    }
+}
+
+/// The location and type information of a statically allocated variable.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StaticVariableInfo {
+    /// The address of the variable in target memory.
+    pub(crate) address: u64,
+    /// The size of the variable, in bytes, if it could be determined.
+    pub(crate) byte_size: Option<u64>,
+    /// The name of the type of the variable, if it could be determined.
+    pub(crate) type_name: Option<String>,
+    /// The names of the namespaces (and functions) that contain the variable, outermost first.
+    pub(crate) namespace: Vec<String>,
+}
+
+/// Extract the address of a variable whose `DW_AT_location` is a simple `DW_OP_addr` expression.
+fn static_address(entry: &Die<'_, '_>, address_size: u8) -> Option<u64> {
+    let Ok(Some(gimli::AttributeValue::Exprloc(expression))) =
+        entry.attr_value(gimli::DW_AT_location)
+    else {
+        return None;
+    };
+    let mut reader = expression.0;
+    if gimli::Reader::read_u8(&mut reader).ok()? != gimli::DW_OP_addr.0 {
+        return None;
+    }
+    gimli::Reader::read_address(&mut reader, address_size).ok()
 }
 
 /// Uses the [`TypedPathBuf::normalize`] function to normalize both paths before comparing them
@@ -1360,7 +1684,17 @@ fn unwind_program_counter_register(
                 _ => Some(RegisterValue::U32(return_address)),
             }
         }
-        RegisterValue::U64(return_address) => Some(RegisterValue::U64(return_address)),
+        RegisterValue::U64(return_address) => match instruction_set {
+            Some(InstructionSet::RV64C) => {
+                *register_rule_string = "PC=(unwound x1 - 2) (dwarf Undefined)".to_string();
+                Some(RegisterValue::U64(return_address - 2))
+            }
+            Some(InstructionSet::RV64) => {
+                *register_rule_string = "PC=(unwound x1 - 4) (dwarf Undefined)".to_string();
+                Some(RegisterValue::U64(return_address - 4))
+            }
+            _ => Some(RegisterValue::U64(return_address)),
+        },
         RegisterValue::U128(_) => {
             tracing::warn!("128 bit address space not supported");
             None
@@ -1939,6 +2273,36 @@ mod test {
         insta::assert_snapshot!(printed_backtrace);
     }
 
+    #[test]
+    fn test_print_stacktrace_from_elf_core_dump() {
+        let elf = Path::new("./tests/gpio-hal-blinky/elf");
+        let coredump = include_bytes!("../../tests/gpio-hal-blinky/coredump");
+        let debug_info = DebugInfo::from_file(elf).unwrap();
+
+        let elf_coredump = CoreDump::load_raw(coredump).unwrap().to_elf().unwrap();
+
+        let backtraces = [coredump.as_slice(), elf_coredump.as_slice()].map(|coredump| {
+            let mut adapter = CoreDump::load_raw(coredump).unwrap();
+            let initial_registers = adapter.debug_registers();
+            let exception_handler = exception_handler_for_core(adapter.core_type());
+            let instruction_set = adapter.instruction_set();
+
+            debug_info
+                .unwind(
+                    &mut adapter,
+                    initial_registers,
+                    exception_handler.as_ref(),
+                    Some(instruction_set),
+                )
+                .unwrap()
+                .iter()
+                .map(|f| TestFormatter(f).to_string())
+                .collect::<String>()
+        });
+
+        assert_eq!(backtraces[0], backtraces[1]);
+    }
+
     #[test_case("RP2040_full_unwind"; "full_unwind Armv6-m using RP2040")]
     #[test_case("RP2040_svcall"; "svcall Armv6-m using RP2040")]
     #[test_case("RP2040_systick"; "systick Armv6-m using RP2040")]
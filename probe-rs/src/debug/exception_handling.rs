@@ -1,5 +1,5 @@
 //! This module (and its children) contains the implementation of the [`ExceptionInterface`] for the various ARM core
-//! variants.
+//! variants, RISC-V and Xtensa.
 
 use std::ops::ControlFlow;
 
@@ -18,19 +18,20 @@ pub(crate) mod armv7m;
 
 pub(crate) mod armv8m;
 
+pub(crate) mod riscv;
+
 pub(crate) mod xtensa;
 
 /// Creates a new exception interface for the [`CoreType`] at hand.
 pub fn exception_handler_for_core(core_type: CoreType) -> Box<dyn ExceptionInterface> {
-    use self::{armv6m, armv7m, armv8m};
+    use self::{armv6m, armv7m, armv8m, riscv};
     match core_type {
         CoreType::Armv6m => Box::new(armv6m::ArmV6MExceptionHandler),
         CoreType::Armv7m | CoreType::Armv7em => Box::new(armv7m::ArmV7MExceptionHandler),
         CoreType::Armv8m => Box::new(armv8m::ArmV8MExceptionHandler),
+        CoreType::Riscv => Box::new(riscv::RiscvExceptionHandler),
         CoreType::Xtensa => Box::new(xtensa::XtensaExceptionHandler),
-        CoreType::Armv7a | CoreType::Armv8a | CoreType::Riscv => {
-            Box::new(UnimplementedExceptionHandler)
-        }
+        CoreType::Armv7a | CoreType::Armv8a => Box::new(UnimplementedExceptionHandler),
     }
 }
 
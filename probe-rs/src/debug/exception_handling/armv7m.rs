@@ -52,7 +52,7 @@ memory_mapped_bitfield_register! {
     /// Aggregate view of the MemManage Fault bits.
     mem_manage_fault, _: 7,0;
     ///  MMAR has valid contents.
-    mm_address_register_valid, _: 7;
+    pub mm_address_register_valid, _: 7;
     /// A MemManage fault occurred during FP lazy state preservation.
     mm_fp_lazy_state_preservation, _: 5;
     /// A derived MemManage fault occurred on exception entry.
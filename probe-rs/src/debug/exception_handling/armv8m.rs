@@ -1,4 +1,5 @@
 use crate::{
+    architecture::arm::core::registers::cortex_m::EXTRA_S,
     debug::{get_object_reference, DebugError, DebugInfo, DebugRegisters, StackFrame},
     memory::MemoryInterface,
     memory_mapped_bitfield_register, Error, MemoryMappedRegister, RegisterRole, RegisterValue,
@@ -387,6 +388,80 @@ impl ExceptionReason {
 }
 pub struct ArmV8MExceptionHandler;
 
+/// The registers which are stored in the additional state context, after the integrity signature
+/// and a reserved word.
+static ADDITIONAL_STATE_REGISTERS: &[RegisterRole] = &[
+    RegisterRole::Core("R4"),
+    RegisterRole::Core("R5"),
+    RegisterRole::Core("R6"),
+    RegisterRole::Core("R7"),
+    RegisterRole::Core("R8"),
+    RegisterRole::Core("R9"),
+    RegisterRole::Core("R10"),
+    RegisterRole::Core("R11"),
+];
+
+/// The size of the additional state context, in bytes.
+const ADDITIONAL_STATE_CONTEXT_SIZE: u32 = 0x28;
+
+const MSP: u16 = 0b10001;
+const PSP: u16 = 0b10010;
+const MSP_NS: u16 = 0b11000;
+const PSP_NS: u16 = 0b11001;
+const MSP_S: u16 = 0b11010;
+const PSP_S: u16 = 0b11011;
+
+/// Returns `true` if `return_address` is an FNC_RETURN value.
+///
+/// The link register holds FNC_RETURN when Secure code calls a Non-secure function. The real
+/// return address is then stored on the Secure stack.
+fn is_fnc_return(return_address: u32) -> bool {
+    return_address & !1 == 0xFEFF_FFFE
+}
+
+impl ArmV8MExceptionHandler {
+    /// The registers of the Secure function which called the current Non-secure function.
+    ///
+    /// The return address, and the partial xPSR are stored on the Secure stack which was in use
+    /// during the call.
+    fn secure_caller_registers(
+        &self,
+        memory_interface: &mut dyn MemoryInterface,
+        stackframe_registers: &DebugRegisters,
+    ) -> Result<DebugRegisters, DebugError> {
+        let handler_mode = self.raw_exception(stackframe_registers)? != 0;
+        let control_s =
+            register_value(stackframe_registers, EXTRA_S.id.0)?.unwrap_or_default() >> 24;
+        let sp_reg_id = if !handler_mode && control_s & 0b10 != 0 {
+            PSP_S
+        } else {
+            MSP_S
+        };
+        let sp_value = register_value(stackframe_registers, sp_reg_id)?
+            .ok_or_else(|| Error::Register("No value for the Secure stack pointer.".to_string()))?;
+
+        let mut frame = [0u32; 2];
+        memory_interface.read_32(sp_value.into(), &mut frame)?;
+        let [return_address, partial_xpsr] = frame;
+
+        let mut calling_frame_registers = stackframe_registers.clone();
+        calling_frame_registers
+            .get_register_mut_by_role(&RegisterRole::ProgramCounter)?
+            .value = Some(RegisterValue::U32(return_address & !1));
+        calling_frame_registers
+            .get_register_mut_by_role(&RegisterRole::ProcessorStatus)?
+            .value = Some(RegisterValue::U32(partial_xpsr));
+        calling_frame_registers
+            .get_register_mut_by_role(&RegisterRole::StackPointer)?
+            .value = Some(RegisterValue::U32(sp_value + 8));
+        if let Some(register) = calling_frame_registers.get_register_mut(sp_reg_id.into()) {
+            register.value = Some(RegisterValue::U32(sp_value + 8));
+        }
+
+        Ok(calling_frame_registers)
+    }
+}
+
 impl ExceptionInterface for ArmV8MExceptionHandler {
     fn calling_frame_registers(
         &self,
@@ -398,40 +473,59 @@ impl ExceptionInterface for ArmV8MExceptionHandler {
         let stack_frame_return_address: u32 = get_stack_frame_return_address(stackframe_registers)?;
         let exc_return = ExcReturn(stack_frame_return_address);
 
-        let sp_value = if exc_return.is_exception_flag() == 0xFF {
-            let stack_info = (
+        let handler_stack = exc_return.use_secure_stack() == exc_return.exception_secure()
+            && !exc_return.stack_pointer_selection();
+
+        let mut sp_value = if handler_stack {
+            // The exception frame is on the stack the handler is using.
+            stackframe_registers.get_register_value_by_role(&RegisterRole::StackPointer)? as u32
+        } else {
+            let sp_reg_id = match (
                 exc_return.use_secure_stack(),
                 exc_return.stack_pointer_selection(),
-            );
-
-            let sp_reg_id = match stack_info {
-                (false, false) => 0b00011000, // non-secure, main stack pointer
-                (false, true) => 0b00011001,  // non-secure, process stack pointer
-                (true, false) => 0b00011010,  // secure, main stack pointer
-                (true, true) => 0b00011011,   // secure, process stack pointer
+            ) {
+                (false, false) => MSP_NS,
+                (false, true) => PSP_NS,
+                (true, false) => MSP_S,
+                (true, true) => PSP_S,
             };
-            stackframe_registers
-                .get_register(sp_reg_id.into())
-                .ok_or_else(|| {
-                    Error::Register(
-                        "No Stack Pointer register. Please report this as a bug.".to_string(),
-                    )
-                })?
-                .value
-                .ok_or_else(|| {
+            // Without the Security Extension, the banked registers do not exist, and EXC_RETURN
+            // always selects the Secure stack.
+            let fallback_id = if exc_return.stack_pointer_selection() {
+                PSP
+            } else {
+                MSP
+            };
+
+            match register_value(stackframe_registers, sp_reg_id)? {
+                Some(value) => value,
+                None => register_value(stackframe_registers, fallback_id)?.ok_or_else(|| {
                     Error::Register(
                         "No value for Stack Pointer register. Please report this as a bug."
                             .to_string(),
                     )
-                })?
-                .try_into()?
-        } else {
-            stackframe_registers
-                .get_register_value_by_role(&crate::core::RegisterRole::StackPointer)?
+                })?,
+            }
         };
 
-        memory_interface.read_32(sp_value, &mut calling_stack_registers)?;
         let mut calling_frame_registers = stackframe_registers.clone();
+
+        // An exception from Secure state, which is taken to Non-secure state, stacks the callee
+        // saved registers as well.
+        if exc_return.use_secure_stack()
+            && (!exc_return.exception_secure() || !exc_return.use_default_register_stacking())
+        {
+            let mut additional_state = vec![0u32; ADDITIONAL_STATE_REGISTERS.len()];
+            memory_interface.read_32((sp_value + 8).into(), &mut additional_state)?;
+            for (register_role, value) in ADDITIONAL_STATE_REGISTERS.iter().zip(additional_state) {
+                calling_frame_registers
+                    .get_register_mut_by_role(register_role)?
+                    .value = Some(RegisterValue::U32(value));
+            }
+            sp_value += ADDITIONAL_STATE_CONTEXT_SIZE;
+        }
+
+        memory_interface.read_32(sp_value.into(), &mut calling_stack_registers)?;
         for (i, register_role) in EXCEPTION_STACK_REGISTERS.iter().enumerate() {
             calling_frame_registers
                 .get_register_mut_by_role(register_role)?
@@ -467,9 +561,19 @@ impl ExceptionInterface for ArmV8MExceptionHandler {
         _debug_info: &DebugInfo,
     ) -> Result<Option<ExceptionInfo>, DebugError> {
         let stack_frame_return_address: u32 = get_stack_frame_return_address(stackframe_registers)?;
-        if ExcReturn(stack_frame_return_address).is_exception_flag() == 0xFF {
-            // This is an exception frame.
 
+        let (raw_exception, description, registers) = if is_fnc_return(stack_frame_return_address) {
+            // This is a call from Secure state to a Non-secure function.
+            let raw_exception = self.raw_exception(stackframe_registers)?;
+            let registers = self.secure_caller_registers(memory_interface, stackframe_registers)?;
+
+            (
+                raw_exception,
+                "<Non-secure function call from Secure state>".to_string(),
+                registers,
+            )
+        } else if ExcReturn(stack_frame_return_address).is_exception_flag() == 0xFF {
+            // This is an exception frame.
             let raw_exception = self.raw_exception(stackframe_registers)?;
             let description = self.exception_description(raw_exception, memory_interface)?;
             let registers = self.calling_frame_registers(
@@ -478,33 +582,45 @@ impl ExceptionInterface for ArmV8MExceptionHandler {
                 raw_exception,
             )?;
 
-            let exception_frame_pc =
-                registers.get_register_value_by_role(&RegisterRole::ProgramCounter)?;
-
-            let handler_frame = StackFrame {
-                id: get_object_reference(),
-                function_name: description.clone(),
-                source_location: None,
-                registers,
-                pc: RegisterValue::U32(exception_frame_pc as u32),
-                frame_base: None,
-                is_inlined: false,
-                local_variables: None,
-                canonical_frame_address: None,
-            };
-
-            Ok(Some(ExceptionInfo {
-                raw_exception,
-                description,
-                handler_frame,
-            }))
+            (raw_exception, description, registers)
         } else {
             // This is a normal function return.
-            Ok(None)
-        }
+            return Ok(None);
+        };
+
+        let exception_frame_pc =
+            registers.get_register_value_by_role(&RegisterRole::ProgramCounter)?;
+
+        let handler_frame = StackFrame {
+            id: get_object_reference(),
+            function_name: description.clone(),
+            source_location: None,
+            registers,
+            pc: RegisterValue::U32(exception_frame_pc as u32),
+            frame_base: None,
+            is_inlined: false,
+            local_variables: None,
+            canonical_frame_address: None,
+        };
+
+        Ok(Some(ExceptionInfo {
+            raw_exception,
+            description,
+            handler_frame,
+        }))
     }
 }
 
+/// The value of the register with `id`, or `None` if the register does not exist, or has no
+/// value.
+fn register_value(registers: &DebugRegisters, id: u16) -> Result<Option<u32>, Error> {
+    registers
+        .get_register(id.into())
+        .and_then(|register| register.value)
+        .map(|value| value.try_into())
+        .transpose()
+}
+
 fn get_stack_frame_return_address(stackframe_registers: &DebugRegisters) -> Result<u32, Error> {
     let return_address: u32 = stackframe_registers
         .get_return_address()
@@ -521,3 +637,145 @@ fn get_stack_frame_return_address(stackframe_registers: &DebugRegisters) -> Resu
 
     Ok(return_address)
 }
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        architecture::arm::core::registers::cortex_m::{
+            CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_SECURITY_CORE_REGISTERS,
+        },
+        debug::DebugRegister,
+        test::MockMemory,
+        CoreRegisters,
+    };
+
+    fn registers(register_set: &'static CoreRegisters, values: &[(&str, u32)]) -> DebugRegisters {
+        DebugRegisters(
+            register_set
+                .core_registers()
+                .map(|core_register| DebugRegister {
+                    core_register,
+                    dwarf_id: None,
+                    value: values
+                        .iter()
+                        .find(|(name, _)| *name == core_register.name())
+                        .map(|(_, value)| RegisterValue::U32(*value)),
+                })
+                .collect(),
+        )
+    }
+
+    fn words(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn value(registers: &DebugRegisters, name: &str) -> Option<RegisterValue> {
+        registers
+            .0
+            .iter()
+            .find(|register| register.core_register.name() == name)
+            .and_then(|register| register.value)
+    }
+
+    #[test]
+    fn fnc_return_unwinds_to_secure_caller() {
+        let handler = ArmV8MExceptionHandler;
+
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0100, words(&[0x1000_0201, 0x0100_0000]));
+
+        let registers = registers(
+            &CORTEX_M_WITH_SECURITY_CORE_REGISTERS,
+            &[
+                ("XPSR", 0),
+                ("EXTRA_S", 0),
+                ("MSP_S", 0x2000_0100),
+                ("R13", 0x3000_0000),
+                ("R14", 0xFEFF_FFFF),
+                ("R15", 0x0020_0000),
+            ],
+        );
+
+        let caller = handler
+            .secure_caller_registers(&mut memory, &registers)
+            .unwrap();
+
+        assert_eq!(value(&caller, "R15"), Some(RegisterValue::U32(0x1000_0200)));
+        assert_eq!(
+            value(&caller, "XPSR"),
+            Some(RegisterValue::U32(0x0100_0000))
+        );
+        assert_eq!(value(&caller, "R13"), Some(RegisterValue::U32(0x2000_0108)));
+        assert_eq!(
+            value(&caller, "MSP_S"),
+            Some(RegisterValue::U32(0x2000_0108))
+        );
+    }
+
+    #[test]
+    fn additional_state_context_is_skipped() {
+        let handler = ArmV8MExceptionHandler;
+
+        // Integrity signature, reserved word, R4-R11, then the basic frame.
+        let mut frame = vec![0xFEFA_125B, 0];
+        frame.extend(4..=11);
+        frame.extend([0, 1, 2, 3, 12, 0x1000_0101, 0x1000_0100, 0x0100_0000]);
+
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0200, words(&frame));
+
+        // Exception from Secure thread mode on PSP_S, taken to Non-secure state.
+        let registers = registers(
+            &CORTEX_M_WITH_SECURITY_CORE_REGISTERS,
+            &[
+                ("XPSR", 3),
+                ("PSP_S", 0x2000_0200),
+                ("R13", 0x3000_0000),
+                ("R14", 0xFFFF_FFFC),
+            ],
+        );
+
+        let caller = handler
+            .calling_frame_registers(&mut memory, &registers, 3)
+            .unwrap();
+
+        assert_eq!(value(&caller, "R4"), Some(RegisterValue::U32(4)));
+        assert_eq!(value(&caller, "R11"), Some(RegisterValue::U32(11)));
+        assert_eq!(value(&caller, "R12"), Some(RegisterValue::U32(12)));
+        assert_eq!(value(&caller, "R15"), Some(RegisterValue::U32(0x1000_0100)));
+    }
+
+    #[test]
+    fn stack_pointer_without_security_extension() {
+        let handler = ArmV8MExceptionHandler;
+
+        let mut memory = MockMemory::new();
+        memory.add_range(
+            0x2000_0300,
+            words(&[0, 1, 2, 3, 12, 0x1000_0101, 0x1000_0100, 0x0100_0000]),
+        );
+
+        // Exception from thread mode on the PSP.
+        let registers = registers(
+            &CORTEX_M_CORE_REGISTERS,
+            &[
+                ("XPSR", 3),
+                ("PSP", 0x2000_0300),
+                ("R13", 0x3000_0000),
+                ("R14", 0xFFFF_FFFD),
+            ],
+        );
+
+        let caller = handler
+            .calling_frame_registers(&mut memory, &registers, 3)
+            .unwrap();
+
+        assert_eq!(value(&caller, "R15"), Some(RegisterValue::U32(0x1000_0100)));
+    }
+}
@@ -0,0 +1,596 @@
+use crate::{
+    architecture::riscv::registers::{MCAUSE, MEPC, MTVAL},
+    core::RegisterId,
+    debug::{
+        get_object_reference, DebugError, DebugInfo, DebugRegister, DebugRegisters, StackFrame,
+    },
+    Error, MemoryInterface, RegisterRole, RegisterValue,
+};
+
+use super::{ExceptionInfo, ExceptionInterface};
+
+/// The bit in the raw exception number that marks the trap as an interrupt.
+///
+/// This matches the layout of `mcause` on RV32. On RV64, the interrupt bit is moved down to this
+/// position, so the raw exception number always fits in 32 bits.
+const INTERRUPT_FLAG: u32 = 1 << 31;
+
+/// Decode the `mcause` value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ExceptionReason {
+    InstructionAddressMisaligned,
+    InstructionAccessFault,
+    IllegalInstruction,
+    Breakpoint,
+    LoadAddressMisaligned,
+    LoadAccessFault,
+    StoreAddressMisaligned,
+    StoreAccessFault,
+    EnvironmentCallFromUMode,
+    EnvironmentCallFromSMode,
+    EnvironmentCallFromMMode,
+    InstructionPageFault,
+    LoadPageFault,
+    StorePageFault,
+    /// An exception code that is reserved, or used for custom exceptions.
+    UnknownException(u32),
+    SupervisorSoftwareInterrupt,
+    MachineSoftwareInterrupt,
+    SupervisorTimerInterrupt,
+    MachineTimerInterrupt,
+    SupervisorExternalInterrupt,
+    MachineExternalInterrupt,
+    /// Interrupt codes from 16 upwards are available for platform use.
+    PlatformInterrupt(u32),
+    /// An interrupt code that is reserved by the ISA.
+    UnknownInterrupt(u32),
+}
+
+impl From<u32> for ExceptionReason {
+    fn from(raw_exception: u32) -> Self {
+        let code = raw_exception & !INTERRUPT_FLAG;
+
+        if raw_exception & INTERRUPT_FLAG == 0 {
+            match code {
+                0 => ExceptionReason::InstructionAddressMisaligned,
+                1 => ExceptionReason::InstructionAccessFault,
+                2 => ExceptionReason::IllegalInstruction,
+                3 => ExceptionReason::Breakpoint,
+                4 => ExceptionReason::LoadAddressMisaligned,
+                5 => ExceptionReason::LoadAccessFault,
+                6 => ExceptionReason::StoreAddressMisaligned,
+                7 => ExceptionReason::StoreAccessFault,
+                8 => ExceptionReason::EnvironmentCallFromUMode,
+                9 => ExceptionReason::EnvironmentCallFromSMode,
+                11 => ExceptionReason::EnvironmentCallFromMMode,
+                12 => ExceptionReason::InstructionPageFault,
+                13 => ExceptionReason::LoadPageFault,
+                15 => ExceptionReason::StorePageFault,
+                other => ExceptionReason::UnknownException(other),
+            }
+        } else {
+            match code {
+                1 => ExceptionReason::SupervisorSoftwareInterrupt,
+                3 => ExceptionReason::MachineSoftwareInterrupt,
+                5 => ExceptionReason::SupervisorTimerInterrupt,
+                7 => ExceptionReason::MachineTimerInterrupt,
+                9 => ExceptionReason::SupervisorExternalInterrupt,
+                11 => ExceptionReason::MachineExternalInterrupt,
+                16.. => ExceptionReason::PlatformInterrupt(code - 16),
+                other => ExceptionReason::UnknownInterrupt(other),
+            }
+        }
+    }
+}
+
+impl ExceptionReason {
+    /// A human readable description of the trap cause.
+    pub(crate) fn description(&self) -> String {
+        match self {
+            ExceptionReason::InstructionAddressMisaligned => {
+                "Instruction address misaligned".to_string()
+            }
+            ExceptionReason::InstructionAccessFault => "Instruction access fault".to_string(),
+            ExceptionReason::IllegalInstruction => "Illegal instruction".to_string(),
+            ExceptionReason::Breakpoint => "Breakpoint".to_string(),
+            ExceptionReason::LoadAddressMisaligned => "Load address misaligned".to_string(),
+            ExceptionReason::LoadAccessFault => "Load access fault".to_string(),
+            ExceptionReason::StoreAddressMisaligned => "Store/AMO address misaligned".to_string(),
+            ExceptionReason::StoreAccessFault => "Store/AMO access fault".to_string(),
+            ExceptionReason::EnvironmentCallFromUMode => "Environment call from U-mode".to_string(),
+            ExceptionReason::EnvironmentCallFromSMode => "Environment call from S-mode".to_string(),
+            ExceptionReason::EnvironmentCallFromMMode => "Environment call from M-mode".to_string(),
+            ExceptionReason::InstructionPageFault => "Instruction page fault".to_string(),
+            ExceptionReason::LoadPageFault => "Load page fault".to_string(),
+            ExceptionReason::StorePageFault => "Store/AMO page fault".to_string(),
+            ExceptionReason::UnknownException(code) => format!("Exception #{code}"),
+            ExceptionReason::SupervisorSoftwareInterrupt => {
+                "Supervisor software interrupt".to_string()
+            }
+            ExceptionReason::MachineSoftwareInterrupt => "Machine software interrupt".to_string(),
+            ExceptionReason::SupervisorTimerInterrupt => "Supervisor timer interrupt".to_string(),
+            ExceptionReason::MachineTimerInterrupt => "Machine timer interrupt".to_string(),
+            ExceptionReason::SupervisorExternalInterrupt => {
+                "Supervisor external interrupt".to_string()
+            }
+            ExceptionReason::MachineExternalInterrupt => "Machine external interrupt".to_string(),
+            ExceptionReason::PlatformInterrupt(interrupt) => {
+                format!("Platform interrupt #{interrupt}")
+            }
+            ExceptionReason::UnknownInterrupt(code) => format!("Interrupt #{code}"),
+        }
+    }
+
+    /// Describes the meaning of `mtval` for this trap cause, if the ISA defines one.
+    pub(crate) fn describe_mtval(&self, mtval: u64) -> Option<String> {
+        match self {
+            ExceptionReason::InstructionAddressMisaligned
+            | ExceptionReason::InstructionAccessFault
+            | ExceptionReason::InstructionPageFault
+            | ExceptionReason::LoadAddressMisaligned
+            | ExceptionReason::LoadAccessFault
+            | ExceptionReason::LoadPageFault
+            | ExceptionReason::StoreAddressMisaligned
+            | ExceptionReason::StoreAccessFault
+            | ExceptionReason::StorePageFault => Some(format!("address {mtval:#010x}")),
+            ExceptionReason::Breakpoint => Some(format!("at {mtval:#010x}")),
+            // Implementations are allowed to not report the faulting instruction.
+            ExceptionReason::IllegalInstruction if mtval != 0 => {
+                Some(format!("instruction {mtval:#010x}"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A value saved by a trap entry routine in its trap frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TrapFrameSlot {
+    /// A general purpose register, identified by its number, e.g. `1` for `x1`/`ra`.
+    Register(u16),
+    /// The `mepc` value, i.e. the program counter of the interrupted code.
+    Mepc,
+    /// The `mcause` value.
+    Mcause,
+    /// The `mtval` value.
+    Mtval,
+    /// A value that is not needed for unwinding, e.g. `mstatus`.
+    Unused,
+}
+
+/// The layout of the trap frame that a runtime saves on the stack when entering a trap handler.
+struct TrapFrameLayout {
+    /// The name of the runtime, used for diagnostics.
+    runtime: &'static str,
+    /// Matches the names of the trap entry routines of the runtime.
+    is_entry_point: fn(&str) -> bool,
+    /// A symbol that identifies the runtime, for runtimes that use the same entry point names.
+    marker_symbol: Option<&'static str>,
+    /// The values saved in the trap frame, one per XLEN sized word, starting at the stack pointer.
+    ///
+    /// If the interrupted stack pointer (`x2`) is not saved, it is assumed to point directly above
+    /// the trap frame.
+    slots: &'static [TrapFrameSlot],
+}
+
+use TrapFrameSlot::{Mcause, Mepc, Mtval, Register, Unused};
+
+/// The trap frame layouts of the supported runtimes. More specific layouts have to be listed first.
+static TRAP_FRAME_LAYOUTS: &[TrapFrameLayout] = &[
+    // `esp-riscv-rt`, used by `esp-hal`. See `TrapFrame` in `esp-riscv-rt/src/lib.rs`.
+    TrapFrameLayout {
+        runtime: "esp-riscv-rt",
+        is_entry_point: |name| name.starts_with("_start_trap"),
+        marker_symbol: Some("_start_trap_rust_hal"),
+        slots: &[
+            Register(1),
+            Register(5),
+            Register(6),
+            Register(7),
+            Register(28),
+            Register(29),
+            Register(30),
+            Register(31),
+            Register(10),
+            Register(11),
+            Register(12),
+            Register(13),
+            Register(14),
+            Register(15),
+            Register(16),
+            Register(17),
+            Register(8),
+            Register(9),
+            Register(18),
+            Register(19),
+            Register(20),
+            Register(21),
+            Register(22),
+            Register(23),
+            Register(24),
+            Register(25),
+            Register(26),
+            Register(27),
+            Register(3),
+            Register(4),
+            Register(2),
+            Mepc,
+            Unused,
+            Mcause,
+            Mtval,
+        ],
+    },
+    // `riscv-rt`. See `TrapFrame` in `riscv-rt/src/lib.rs`.
+    TrapFrameLayout {
+        runtime: "riscv-rt",
+        is_entry_point: |name| name == "_start_trap",
+        marker_symbol: None,
+        slots: &[
+            Register(1),
+            Register(5),
+            Register(6),
+            Register(7),
+            Register(28),
+            Register(29),
+            Register(30),
+            Register(31),
+            Register(10),
+            Register(11),
+            Register(12),
+            Register(13),
+            Register(14),
+            Register(15),
+            Register(16),
+            Register(17),
+        ],
+    },
+    // ESP-IDF. See `RvExcFrame` in `components/riscv/include/riscv/rvruntime-frames.h`.
+    TrapFrameLayout {
+        runtime: "ESP-IDF",
+        is_entry_point: |name| name == "_panic_handler",
+        marker_symbol: None,
+        slots: &[
+            Mepc,
+            Register(1),
+            Register(2),
+            Register(3),
+            Register(4),
+            Register(5),
+            Register(6),
+            Register(7),
+            Register(8),
+            Register(9),
+            Register(10),
+            Register(11),
+            Register(12),
+            Register(13),
+            Register(14),
+            Register(15),
+            Register(16),
+            Register(17),
+            Register(18),
+            Register(19),
+            Register(20),
+            Register(21),
+            Register(22),
+            Register(23),
+            Register(24),
+            Register(25),
+            Register(26),
+            Register(27),
+            Register(28),
+            Register(29),
+            Register(30),
+            Register(31),
+            Unused,
+            Unused,
+            Mcause,
+            Mtval,
+            Unused,
+        ],
+    },
+];
+
+impl TrapFrameLayout {
+    /// Find the trap frame layout for the trap entry routine at the given address.
+    fn for_address(debug_info: &DebugInfo, address: u64) -> Option<&'static TrapFrameLayout> {
+        let function_name = debug_info.get_code_symbol_name(address)?;
+
+        TRAP_FRAME_LAYOUTS.iter().find(|layout| {
+            (layout.is_entry_point)(function_name)
+                && layout
+                    .marker_symbol
+                    .map_or(true, |marker| debug_info.has_code_symbol(marker))
+        })
+    }
+
+    /// Read the trap frame at the given address, and return the saved values for each slot.
+    fn read(
+        &'static self,
+        memory: &mut dyn MemoryInterface,
+        address: u64,
+        is_64_bit: bool,
+    ) -> Result<Vec<(TrapFrameSlot, u64)>, Error> {
+        let values = if is_64_bit {
+            let mut values = vec![0u64; self.slots.len()];
+            memory.read_64(address, &mut values)?;
+            values
+        } else {
+            let mut values = vec![0u32; self.slots.len()];
+            memory.read_32(address, &mut values)?;
+            values.into_iter().map(u64::from).collect()
+        };
+
+        Ok(self.slots.iter().copied().zip(values).collect())
+    }
+
+    /// The size of the trap frame, in bytes.
+    fn size(&self, is_64_bit: bool) -> u64 {
+        let word_size = if is_64_bit { 8 } else { 4 };
+        self.slots.len() as u64 * word_size
+    }
+}
+
+/// Exception handling for RISC-V cores.
+///
+/// Traps are recognised by the trap entry routine of the runtime, which saves the interrupted
+/// context in a trap frame on the stack. The layouts of [`riscv-rt`](https://crates.io/crates/riscv-rt),
+/// `esp-riscv-rt` and ESP-IDF are supported.
+pub struct RiscvExceptionHandler;
+
+impl ExceptionInterface for RiscvExceptionHandler {
+    fn exception_details(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        stackframe_registers: &DebugRegisters,
+        debug_info: &DebugInfo,
+    ) -> Result<Option<ExceptionInfo>, DebugError> {
+        let frame_pc =
+            stackframe_registers.get_register_value_by_role(&RegisterRole::ProgramCounter)?;
+
+        let Some(layout) = TrapFrameLayout::for_address(debug_info, frame_pc) else {
+            // This is a normal function return / not a trap handler.
+            return Ok(None);
+        };
+
+        tracing::debug!(
+            "UNWIND: Found {} trap entry at {frame_pc:#010x}",
+            layout.runtime
+        );
+
+        let is_64_bit = stackframe_registers.get_address_size_bytes() == 8;
+        let trap_frame_address =
+            stackframe_registers.get_register_value_by_role(&RegisterRole::StackPointer)?;
+        let trap_frame = layout.read(memory, trap_frame_address, is_64_bit)?;
+
+        // Prefer the trap CSR values saved by the runtime, since the live CSRs can be
+        // overwritten by nested traps.
+        let mut trap_registers = stackframe_registers.clone();
+        for (slot, value) in trap_frame.iter().copied() {
+            let id = match slot {
+                Mepc => MEPC.id(),
+                Mcause => MCAUSE.id(),
+                Mtval => MTVAL.id(),
+                Register(_) | Unused => continue,
+            };
+            set_register_value(trap_registers.get_register_mut(id), value);
+        }
+
+        let raw_exception = self.raw_exception(&trap_registers)?;
+        let mut registers = self.calling_frame_registers(memory, &trap_registers, raw_exception)?;
+
+        // Restore the registers that were saved in the trap frame.
+        let mut stack_pointer_saved = false;
+        for (slot, value) in trap_frame {
+            if let Register(number) = slot {
+                stack_pointer_saved |= number == 2;
+                set_register_value(
+                    registers.get_register_mut(RegisterId(0x1000 + number)),
+                    value,
+                );
+            }
+        }
+        if !stack_pointer_saved {
+            let stack_pointer = registers.get_register_mut_by_role(&RegisterRole::StackPointer)?;
+            if let Some(value) = stack_pointer.value.as_mut() {
+                value.increment_address(layout.size(is_64_bit) as usize)?;
+            }
+        }
+
+        let mut description = self.exception_description(raw_exception, memory)?;
+        if let Some(mtval_description) = trap_registers
+            .get_register(MTVAL.id())
+            .and_then(|mtval| mtval.value)
+            .and_then(|mtval| mtval.try_into().ok())
+            .and_then(|mtval| ExceptionReason::from(raw_exception).describe_mtval(mtval))
+        {
+            description = format!("{description} ({mtval_description})");
+        }
+
+        let exception_pc = registers.get_register_value_by_role(&RegisterRole::ProgramCounter)?;
+
+        let handler_frame = StackFrame {
+            id: get_object_reference(),
+            function_name: description.clone(),
+            source_location: None,
+            pc: if is_64_bit {
+                RegisterValue::U64(exception_pc)
+            } else {
+                RegisterValue::U32(exception_pc as u32)
+            },
+            registers,
+            frame_base: None,
+            is_inlined: false,
+            local_variables: None,
+            canonical_frame_address: None,
+        };
+
+        Ok(Some(ExceptionInfo {
+            raw_exception,
+            description,
+            handler_frame,
+        }))
+    }
+
+    /// Without knowledge of the trap frame layout, only the program counter of the interrupted code can
+    /// be recovered, from `mepc`. The trap CSRs are cleared, because they do not describe the interrupted code.
+    fn calling_frame_registers(
+        &self,
+        _memory: &mut dyn MemoryInterface,
+        stackframe_registers: &DebugRegisters,
+        _raw_exception: u32,
+    ) -> Result<DebugRegisters, DebugError> {
+        let mepc = stackframe_registers
+            .get_register(MEPC.id())
+            .and_then(|mepc| mepc.value)
+            .ok_or_else(|| Error::Register("No value for the mepc register.".to_string()))?;
+
+        let mut calling_frame_registers = stackframe_registers.clone();
+        calling_frame_registers
+            .get_register_mut_by_role(&RegisterRole::ProgramCounter)?
+            .value = Some(mepc);
+
+        for id in [MEPC.id(), MCAUSE.id(), MTVAL.id()] {
+            if let Some(register) = calling_frame_registers.get_register_mut(id) {
+                register.value = None;
+            }
+        }
+
+        Ok(calling_frame_registers)
+    }
+
+    fn raw_exception(&self, stackframe_registers: &DebugRegisters) -> Result<u32, DebugError> {
+        let mcause = stackframe_registers
+            .get_register(MCAUSE.id())
+            .and_then(|mcause| mcause.value)
+            .ok_or_else(|| Error::Register("No value for the mcause register.".to_string()))?;
+
+        Ok(match mcause {
+            RegisterValue::U32(mcause) => mcause,
+            other => {
+                let mcause: u64 = other.try_into()?;
+                let interrupt = mcause & (1 << 63) != 0;
+                // Exception codes are far smaller than 31 bits in practice.
+                (mcause as u32 & !INTERRUPT_FLAG) | if interrupt { INTERRUPT_FLAG } else { 0 }
+            }
+        })
+    }
+
+    fn exception_description(
+        &self,
+        raw_exception: u32,
+        _memory: &mut dyn MemoryInterface,
+    ) -> Result<String, DebugError> {
+        Ok(ExceptionReason::from(raw_exception).description())
+    }
+}
+
+/// Update the value of a register, keeping the width of the register.
+fn set_register_value(register: Option<&mut DebugRegister>, value: u64) {
+    if let Some(register) = register {
+        register.value = Some(if register.is_u32() {
+            RegisterValue::U32(value as u32)
+        } else {
+            RegisterValue::U64(value)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::architecture::riscv::registers::RISCV_CORE_REGSISTERS;
+
+    fn registers_with_mcause(mcause: RegisterValue) -> DebugRegisters {
+        DebugRegisters(
+            RISCV_CORE_REGSISTERS
+                .core_registers()
+                .map(|core_register| DebugRegister {
+                    core_register,
+                    dwarf_id: None,
+                    value: (core_register.id() == MCAUSE.id()).then_some(mcause),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn decode_exceptions() {
+        assert_eq!(
+            ExceptionReason::from(2),
+            ExceptionReason::IllegalInstruction
+        );
+        assert_eq!(ExceptionReason::from(7), ExceptionReason::StoreAccessFault);
+        assert_eq!(
+            ExceptionReason::from(11),
+            ExceptionReason::EnvironmentCallFromMMode
+        );
+        assert_eq!(
+            ExceptionReason::from(10),
+            ExceptionReason::UnknownException(10)
+        );
+    }
+
+    #[test]
+    fn decode_interrupts() {
+        assert_eq!(
+            ExceptionReason::from(0x8000_0007),
+            ExceptionReason::MachineTimerInterrupt
+        );
+        assert_eq!(
+            ExceptionReason::from(0x8000_0013),
+            ExceptionReason::PlatformInterrupt(3)
+        );
+        assert_eq!(
+            ExceptionReason::from(0x8000_0002),
+            ExceptionReason::UnknownInterrupt(2)
+        );
+    }
+
+    #[test]
+    fn describe_mtval() {
+        assert_eq!(
+            ExceptionReason::LoadAccessFault.describe_mtval(0x2000_0004),
+            Some("address 0x20000004".to_string())
+        );
+        assert_eq!(ExceptionReason::IllegalInstruction.describe_mtval(0), None);
+        assert_eq!(
+            ExceptionReason::MachineTimerInterrupt.describe_mtval(0),
+            None
+        );
+    }
+
+    #[test]
+    fn raw_exception_from_rv64_mcause() {
+        let registers = registers_with_mcause(RegisterValue::U64(0x8000_0000_0000_000B));
+
+        assert_eq!(
+            RiscvExceptionHandler.raw_exception(&registers).unwrap(),
+            0x8000_000B
+        );
+    }
+
+    #[test]
+    fn trap_frame_layouts_save_the_return_address() {
+        for layout in TRAP_FRAME_LAYOUTS {
+            let registers = layout
+                .slots
+                .iter()
+                .filter_map(|slot| match slot {
+                    Register(number) => Some(*number),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert!(
+                registers.contains(&1),
+                "{} does not save ra",
+                layout.runtime
+            );
+            assert!(
+                registers.iter().all(|number| (1..32).contains(number)),
+                "{} has an invalid register",
+                layout.runtime
+            );
+        }
+    }
+}
@@ -13,6 +13,8 @@ pub mod function_die;
 pub(crate) mod language;
 /// Target Register definitions, expanded from [`crate::core::registers::CoreRegister`] to include unwind specific information.
 pub mod registers;
+/// Awareness of the threads managed by an RTOS.
+pub mod rtos;
 /// The source statement information used while identifying haltpoints for debug stepping and breakpoints.
 pub(crate) mod source_instructions;
 /// The stack frame information used while unwinding the stack from a specific program counter.
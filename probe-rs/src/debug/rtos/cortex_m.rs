@@ -0,0 +1,231 @@
+//! Reconstruction of the registers of a Cortex-M thread that was switched out by an RTOS.
+//!
+//! On Cortex-M, context switches happen in the PendSV exception handler. The hardware pushes the caller saved
+//! registers to the stack of the thread (the exception frame), and the RTOS saves the callee saved registers `R4` to `R11`,
+//! either on the same stack (FreeRTOS), or in the thread control block (Zephyr).
+
+use crate::{
+    core::{RegisterId, RegisterValue},
+    debug::DebugRegisters,
+    CoreType, Error, MemoryInterface,
+};
+
+/// The `EXC_RETURN` value for a return to thread mode, using the process stack, without floating point context.
+pub(super) const EXC_RETURN_THREAD_PSP: u32 = 0xFFFF_FFFD;
+
+/// The register ids of `R4` to `R11`.
+const CALLEE_SAVED_REGISTERS: [RegisterId; 8] = [
+    RegisterId(4),
+    RegisterId(5),
+    RegisterId(6),
+    RegisterId(7),
+    RegisterId(8),
+    RegisterId(9),
+    RegisterId(10),
+    RegisterId(11),
+];
+
+/// The register ids of the basic exception frame, in the order in which they are stacked:
+/// `R0`-`R3`, `R12`, `LR`, `PC` and `xPSR`.
+const EXCEPTION_FRAME_REGISTERS: [RegisterId; 8] = [
+    RegisterId(0),
+    RegisterId(1),
+    RegisterId(2),
+    RegisterId(3),
+    RegisterId(12),
+    RegisterId(14),
+    RegisterId(15),
+    RegisterId(0b1_0000),
+];
+
+const SP: RegisterId = RegisterId(13);
+const PSP: RegisterId = RegisterId(0b1_0010);
+
+/// The context of a thread, as it was saved by a context switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct SwitchFrame {
+    /// The values of `R4` to `R11`.
+    pub(super) callee_saved: [u32; 8],
+    /// The `EXC_RETURN` value, which tells us if the exception frame includes floating point registers.
+    pub(super) exc_return: u32,
+    /// The address of the exception frame pushed by the hardware.
+    pub(super) exception_frame: u64,
+}
+
+impl SwitchFrame {
+    /// Read the context that the FreeRTOS Cortex-M ports push onto the thread stack, starting at `top_of_stack`.
+    ///
+    /// The ports differ in what they save in addition to `R4`-`R11`:
+    /// - ARMv6-M and ARMv7-M (`ARM_CM0`, `ARM_CM3`): `R4`-`R11`.
+    /// - ARMv7E-M with FPU (`ARM_CM4F`, `ARM_CM7`): `R4`-`R11`, `EXC_RETURN`, and if the thread used the FPU, `S16`-`S31`.
+    /// - ARMv8-M (`ARM_CM33_NTZ`, etc.): `PSPLIM`, `EXC_RETURN`, `R4`-`R11`, and if the thread used the FPU, `S16`-`S31`.
+    ///
+    /// Cores which may, or may not, have an FPU could use either of the layouts, so we identify them by looking for the `EXC_RETURN` value.
+    pub(super) fn from_stack(
+        memory: &mut impl MemoryInterface,
+        core_type: CoreType,
+        top_of_stack: u64,
+    ) -> Result<Self, Error> {
+        let mut words = [0u32; 10];
+        memory.read_32(top_of_stack, &mut words)?;
+
+        let (callee_saved, exc_return, context_words) = match core_type {
+            CoreType::Armv8m if is_exc_return(words[1]) => (&words[2..10], words[1], 10),
+            CoreType::Armv7em | CoreType::Armv8m if is_exc_return(words[8]) => {
+                (&words[0..8], words[8], 9)
+            }
+            _ => (&words[0..8], EXC_RETURN_THREAD_PSP, 8),
+        };
+
+        let mut exception_frame = top_of_stack + context_words * 4;
+        if has_fp_context(exc_return) {
+            // S16-S31 are saved by software, before the exception frame.
+            exception_frame += 16 * 4;
+        }
+
+        let mut saved_registers = [0; 8];
+        saved_registers.copy_from_slice(callee_saved);
+
+        Ok(Self {
+            callee_saved: saved_registers,
+            exc_return,
+            exception_frame,
+        })
+    }
+
+    /// Read the exception frame from the thread stack, and combine it with the callee saved registers.
+    ///
+    /// The registers which are not part of the saved context (e.g. the floating point registers) have no value.
+    pub(super) fn registers(
+        &self,
+        memory: &mut impl MemoryInterface,
+        live_registers: &DebugRegisters,
+    ) -> Result<DebugRegisters, Error> {
+        let mut exception_frame = [0u32; 8];
+        memory.read_32(self.exception_frame, &mut exception_frame)?;
+
+        let xpsr = exception_frame[7];
+        let frame_size = if has_fp_context(self.exc_return) {
+            // The extended frame includes S0-S15, FPSCR and a reserved word.
+            0x68
+        } else {
+            0x20
+        };
+        // Bit 9 of the stacked xPSR indicates that the hardware inserted a padding word, to align the stack to 8 bytes.
+        let padding = if xpsr & (1 << 9) != 0 { 4 } else { 0 };
+        let stack_pointer = (self.exception_frame + frame_size + padding) as u32;
+
+        let mut registers = live_registers.clone();
+        for register in registers.0.iter_mut() {
+            let id = register.core_register.id;
+            let value = if let Some(index) = CALLEE_SAVED_REGISTERS.iter().position(|r| *r == id) {
+                Some(self.callee_saved[index])
+            } else if let Some(index) = EXCEPTION_FRAME_REGISTERS.iter().position(|r| *r == id) {
+                Some(exception_frame[index])
+            } else if id == SP || id == PSP {
+                Some(stack_pointer)
+            } else {
+                None
+            };
+            register.value = value.map(RegisterValue::U32);
+        }
+
+        Ok(registers)
+    }
+}
+
+/// Check if a value looks like an `EXC_RETURN` value, which always has the top 24 bits set (or 25 bits, on ARMv8-M).
+fn is_exc_return(value: u32) -> bool {
+    value & 0xFFFF_FF00 == 0xFFFF_FF00
+}
+
+/// Bit 4 of `EXC_RETURN` is cleared if the exception frame includes the floating point registers.
+fn has_fp_context(exc_return: u32) -> bool {
+    exc_return & (1 << 4) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::MockMemory;
+
+    #[test]
+    fn freertos_cm3_stack_layout() {
+        let mut memory = MockMemory::new();
+        let top_of_stack = 0x2000_1000;
+        // R4-R11, followed by the exception frame.
+        let stack = [
+            4,
+            5,
+            6,
+            7,
+            8,
+            9,
+            10,
+            11,
+            0,
+            1,
+            2,
+            3,
+            12,
+            0x0800_0101,
+            0x0800_0200,
+            0x0100_0000,
+        ];
+        memory.add_range(
+            top_of_stack,
+            stack
+                .iter()
+                .flat_map(|word: &u32| word.to_le_bytes())
+                .collect(),
+        );
+
+        let frame = SwitchFrame::from_stack(&mut memory, CoreType::Armv7m, top_of_stack).unwrap();
+
+        assert_eq!(frame.callee_saved, [4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(frame.exc_return, EXC_RETURN_THREAD_PSP);
+        assert_eq!(frame.exception_frame, top_of_stack + 8 * 4);
+    }
+
+    #[test]
+    fn freertos_cm4f_stack_layout_with_fp_context() {
+        let mut memory = MockMemory::new();
+        let top_of_stack = 0x2000_1000;
+        // R4-R11, EXC_RETURN, then S16-S31 and the extended exception frame.
+        let stack = [4, 5, 6, 7, 8, 9, 10, 11, 0xFFFF_FFED, 0];
+        memory.add_range(
+            top_of_stack,
+            stack
+                .iter()
+                .flat_map(|word: &u32| word.to_le_bytes())
+                .collect(),
+        );
+
+        let frame = SwitchFrame::from_stack(&mut memory, CoreType::Armv7em, top_of_stack).unwrap();
+
+        assert_eq!(frame.callee_saved, [4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(frame.exc_return, 0xFFFF_FFED);
+        assert_eq!(frame.exception_frame, top_of_stack + (9 + 16) * 4);
+    }
+
+    #[test]
+    fn freertos_armv8m_stack_layout() {
+        let mut memory = MockMemory::new();
+        let top_of_stack = 0x2000_1000;
+        // PSPLIM, EXC_RETURN, R4-R11.
+        let stack = [0x2000_0000, 0xFFFF_FFBC, 4, 5, 6, 7, 8, 9, 10, 11];
+        memory.add_range(
+            top_of_stack,
+            stack
+                .iter()
+                .flat_map(|word: &u32| word.to_le_bytes())
+                .collect(),
+        );
+
+        let frame = SwitchFrame::from_stack(&mut memory, CoreType::Armv8m, top_of_stack).unwrap();
+
+        assert_eq!(frame.callee_saved, [4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(frame.exc_return, 0xFFFF_FFBC);
+        assert_eq!(frame.exception_frame, top_of_stack + 10 * 4);
+    }
+}
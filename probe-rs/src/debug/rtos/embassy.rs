@@ -0,0 +1,155 @@
+//! Task awareness for the [embassy](https://embassy.dev) async executor.
+//!
+//! The `#[embassy_executor::task]` macro places the storage of each task in a `static POOL: TaskPool<F, N>`,
+//! inside the function that spawns the task. Each of the `N` slots starts with a `TaskHeader`, whose `state`
+//! tells us if the slot holds a spawned task, and if that task is queued to be polled.
+//!
+//! Unlike the threads of a preemptive RTOS, async tasks do not have their own stack, or saved registers. A task that
+//! is not being polled has returned from its `poll` function, so there is no context that could be unwound.
+//! The tasks are listed with their state, alongside the executor context that is running on the core.
+
+use super::{RtosThread, RtosThreadState};
+use crate::{debug::DebugInfo, Core, Error, MemoryInterface};
+
+/// The bit in the task state which is set while the task is spawned.
+const STATE_SPAWNED: u32 = 1 << 0;
+
+/// A `TaskPool` static, holding the storage for up to `size` instances of a task.
+#[derive(Debug, Clone, PartialEq)]
+struct TaskPool {
+    /// The name of the task function.
+    name: String,
+    /// The address of the first slot in the pool.
+    address: u64,
+    /// The number of slots in the pool.
+    size: u64,
+    /// The size of a slot in the pool, in bytes.
+    stride: u64,
+}
+
+/// The location of the embassy task pools, and the layout of the task state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embassy {
+    pools: Vec<TaskPool>,
+    /// The offset of `state` in `TaskHeader`.
+    state: u64,
+    /// The bit in the task state which is set while the task is queued to be polled.
+    run_queued: u32,
+}
+
+impl Embassy {
+    /// Locate the embassy task pools in the debug information.
+    ///
+    /// Returns `None` if the program does not use the embassy executor.
+    pub fn new(debug_info: &DebugInfo) -> Option<Self> {
+        let pools: Vec<TaskPool> = debug_info
+            .find_static_variables("POOL")
+            .into_iter()
+            .filter_map(|pool| {
+                let type_name = pool.type_name?;
+                if !type_name.starts_with("TaskPool<") {
+                    return None;
+                }
+                let size = pool_size(&type_name)?;
+                let byte_size = pool.byte_size?;
+                Some(TaskPool {
+                    name: pool
+                        .namespace
+                        .last()
+                        .cloned()
+                        .unwrap_or_else(|| "task".to_string()),
+                    address: pool.address,
+                    size,
+                    stride: byte_size / size,
+                })
+            })
+            .collect();
+
+        if pools.is_empty() {
+            return None;
+        }
+
+        let state = debug_info
+            .struct_member_offset("TaskHeader", "state")
+            .unwrap_or(0);
+        // Depending on the target, the state is either a single `AtomicU32` with flag bits, or separate `AtomicBool`s.
+        let run_queued = debug_info
+            .struct_member_offset("TaskHeader", "state.run_queued")
+            .and_then(|run_queued| run_queued.checked_sub(state))
+            .filter(|byte_offset| *byte_offset < 4)
+            .map_or(1 << 1, |byte_offset| 1 << (byte_offset * 8));
+
+        Some(Self {
+            pools,
+            state,
+            run_queued,
+        })
+    }
+}
+
+impl super::Rtos for Embassy {
+    fn name(&self) -> &'static str {
+        "embassy"
+    }
+
+    fn threads(&self, core: &mut Core<'_>) -> Result<Vec<RtosThread>, Error> {
+        // We can't tell which task is being polled, so the context of the core is represented by the executor.
+        let mut threads = vec![RtosThread {
+            id: 0,
+            name: "executor".to_string(),
+            state: RtosThreadState::Running,
+            registers: None,
+        }];
+
+        for pool in &self.pools {
+            for index in 0..pool.size {
+                let task = pool.address + index * pool.stride;
+                let state = core.read_word_32(task + self.state)?;
+                if state & STATE_SPAWNED == 0 {
+                    continue;
+                }
+
+                threads.push(RtosThread {
+                    id: task,
+                    name: if pool.size > 1 {
+                        format!("{}[{index}]", pool.name)
+                    } else {
+                        pool.name.clone()
+                    },
+                    state: if state & self.run_queued != 0 {
+                        RtosThreadState::Ready
+                    } else {
+                        RtosThreadState::Blocked
+                    },
+                    registers: None,
+                });
+            }
+        }
+
+        Ok(threads)
+    }
+}
+
+/// Extract the number of slots `N` from the type name of a `TaskPool<F, N>`.
+fn pool_size(type_name: &str) -> Option<u64> {
+    let (_, size) = type_name.strip_suffix('>')?.rsplit_once(',')?;
+    size.trim().parse().ok().filter(|size| *size > 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::pool_size;
+
+    #[test]
+    fn task_pool_size_from_type_name() {
+        assert_eq!(
+            pool_size("TaskPool<app::__blink_task::{opaque#0}, 1>"),
+            Some(1)
+        );
+        assert_eq!(
+            pool_size("TaskPool<core::future::from_generator::GenFuture<u8, 3>, 4>"),
+            Some(4)
+        );
+        assert_eq!(pool_size("TaskPool<F>"), None);
+    }
+}
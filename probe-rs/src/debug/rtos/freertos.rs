@@ -0,0 +1,206 @@
+//! Thread awareness for [FreeRTOS](https://www.freertos.org).
+//!
+//! FreeRTOS keeps every task in exactly one of its state lists (the ready list for each priority, the delayed lists, etc.).
+//! Each list is a circular linked list of `ListItem_t`, whose `pvOwner` points to the task control block (TCB) of the task.
+
+use super::{
+    read_pointer, read_string, saved_registers, RtosThread, RtosThreadState, SavedContext,
+};
+use crate::{
+    debug::{DebugInfo, DebugRegisters},
+    Core, Error, MemoryInterface,
+};
+
+/// The maximum number of characters read for a task name. FreeRTOS always terminates the names with a NUL character.
+const MAX_TASK_NAME_LENGTH: usize = 32;
+
+/// An upper limit for the number of items we will follow in a single list, in case the list is corrupted.
+const MAX_LIST_ITEMS: u64 = 256;
+
+/// The location of the FreeRTOS kernel data structures, and the layout of the structures we need to read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreeRtos {
+    pointer_size: u8,
+    /// The address of `pxCurrentTCB`.
+    current_tcb: u64,
+    /// The address of `xSchedulerRunning`, if available.
+    scheduler_running: Option<u64>,
+    /// The addresses of the task lists (`List_t`), and the state of the tasks in each list.
+    task_lists: Vec<(u64, RtosThreadState)>,
+    /// The offset of `uxNumberOfItems` in `List_t`.
+    list_number_of_items: u64,
+    /// The offset of `xListEnd` in `List_t`.
+    list_end: u64,
+    /// The offset of `pxNext` in `MiniListItem_t`.
+    mini_list_item_next: u64,
+    /// The offset of `pxNext` in `ListItem_t`.
+    list_item_next: u64,
+    /// The offset of `pvOwner` in `ListItem_t`.
+    list_item_owner: u64,
+    /// The offset of `pxTopOfStack` in `TCB_t`.
+    tcb_top_of_stack: u64,
+    /// The offset of `pcTaskName` in `TCB_t`.
+    tcb_task_name: Option<u64>,
+}
+
+impl FreeRtos {
+    /// Locate the FreeRTOS kernel data structures in the debug information.
+    ///
+    /// Returns `None` if the program does not use FreeRTOS.
+    pub fn new(debug_info: &DebugInfo) -> Option<Self> {
+        let current_tcb = debug_info.find_static_variable("pxCurrentTCB")?;
+        let ready_lists = debug_info.find_static_variable("pxReadyTasksLists")?;
+
+        let pointer_size = debug_info.address_size();
+        let pointer_bytes = u64::from(pointer_size);
+
+        // The default layouts are for 32-bit targets, without `configUSE_LIST_DATA_INTEGRITY_CHECK_BYTES`.
+        let list_size = debug_info
+            .struct_byte_size("xLIST")
+            .unwrap_or(5 * pointer_bytes);
+        let list_item_next = debug_info
+            .struct_member_offset("xLIST_ITEM", "pxNext")
+            .unwrap_or(pointer_bytes);
+
+        let mut task_lists = Vec::new();
+        let ready_list_count = match ready_lists.byte_size {
+            Some(byte_size) => byte_size / list_size,
+            None => {
+                tracing::warn!("Unable to determine the number of FreeRTOS priorities. Only tasks with the lowest priority will be shown as ready.");
+                1
+            }
+        };
+        for priority in 0..ready_list_count {
+            task_lists.push((
+                ready_lists.address + priority * list_size,
+                RtosThreadState::Ready,
+            ));
+        }
+        for (list_name, state) in [
+            ("xPendingReadyList", RtosThreadState::Ready),
+            ("xDelayedTaskList1", RtosThreadState::Blocked),
+            ("xDelayedTaskList2", RtosThreadState::Blocked),
+            ("xSuspendedTaskList", RtosThreadState::Suspended),
+            ("xTasksWaitingTermination", RtosThreadState::Deleted),
+        ] {
+            if let Some(list) = debug_info.find_static_variable(list_name) {
+                task_lists.push((list.address, state));
+            }
+        }
+
+        Some(Self {
+            pointer_size,
+            current_tcb: current_tcb.address,
+            scheduler_running: debug_info
+                .find_static_variable("xSchedulerRunning")
+                .map(|variable| variable.address),
+            task_lists,
+            list_number_of_items: debug_info
+                .struct_member_offset("xLIST", "uxNumberOfItems")
+                .unwrap_or(0),
+            list_end: debug_info
+                .struct_member_offset("xLIST", "xListEnd")
+                .unwrap_or(2 * pointer_bytes),
+            // Without `configUSE_MINI_LIST_ITEM`, the list end is a regular list item.
+            mini_list_item_next: debug_info
+                .struct_member_offset("xMINI_LIST_ITEM", "pxNext")
+                .unwrap_or(list_item_next),
+            list_item_next,
+            list_item_owner: debug_info
+                .struct_member_offset("xLIST_ITEM", "pvOwner")
+                .unwrap_or(3 * pointer_bytes),
+            tcb_top_of_stack: debug_info
+                .struct_member_offset("tskTaskControlBlock", "pxTopOfStack")
+                .unwrap_or(0),
+            tcb_task_name: debug_info.struct_member_offset("tskTaskControlBlock", "pcTaskName"),
+        })
+    }
+
+    /// Collect the addresses of the TCBs in a task list.
+    fn list_tasks(&self, memory: &mut impl MemoryInterface, list: u64) -> Result<Vec<u64>, Error> {
+        let number_of_items =
+            read_pointer(memory, list + self.list_number_of_items, self.pointer_size)?;
+        let list_end = list + self.list_end;
+
+        let mut tasks = Vec::new();
+        let mut item = read_pointer(
+            memory,
+            list_end + self.mini_list_item_next,
+            self.pointer_size,
+        )?;
+        for _ in 0..number_of_items.min(MAX_LIST_ITEMS) {
+            if item == list_end || item == 0 {
+                break;
+            }
+            tasks.push(read_pointer(
+                memory,
+                item + self.list_item_owner,
+                self.pointer_size,
+            )?);
+            item = read_pointer(memory, item + self.list_item_next, self.pointer_size)?;
+        }
+        Ok(tasks)
+    }
+}
+
+impl super::Rtos for FreeRtos {
+    fn name(&self) -> &'static str {
+        "FreeRTOS"
+    }
+
+    fn threads(&self, core: &mut Core<'_>) -> Result<Vec<RtosThread>, Error> {
+        if let Some(scheduler_running) = self.scheduler_running {
+            if read_pointer(core, scheduler_running, self.pointer_size)? == 0 {
+                return Ok(vec![]);
+            }
+        }
+        let current_tcb = read_pointer(core, self.current_tcb, self.pointer_size)?;
+        if current_tcb == 0 {
+            return Ok(vec![]);
+        }
+
+        let live_registers = DebugRegisters::from_core(core);
+
+        let mut threads: Vec<RtosThread> = Vec::new();
+        for (list, list_state) in &self.task_lists {
+            for tcb in self.list_tasks(core, *list)? {
+                if threads.iter().any(|thread| thread.id == tcb) {
+                    continue;
+                }
+
+                let name = match self.tcb_task_name {
+                    Some(task_name) => read_string(core, tcb + task_name, MAX_TASK_NAME_LENGTH)?,
+                    None => format!("Task @ {tcb:#010x}"),
+                };
+
+                let (state, registers) = if tcb == current_tcb {
+                    (RtosThreadState::Running, None)
+                } else {
+                    let top_of_stack =
+                        read_pointer(core, tcb + self.tcb_top_of_stack, self.pointer_size)?;
+                    let registers = saved_registers(
+                        core,
+                        &live_registers,
+                        SavedContext::CortexMStack { top_of_stack },
+                    )
+                    .unwrap_or_else(|error| {
+                        tracing::warn!(
+                            "Failed to read the saved context of task {name:?}: {error}"
+                        );
+                        None
+                    });
+                    (*list_state, registers)
+                };
+
+                threads.push(RtosThread {
+                    id: tcb,
+                    name,
+                    state,
+                    registers,
+                });
+            }
+        }
+
+        Ok(threads)
+    }
+}
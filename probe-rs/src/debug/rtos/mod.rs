@@ -0,0 +1,181 @@
+//! RTOS awareness for debugging multi-threaded firmware.
+//!
+//! The RTOS kernel objects (task control blocks, ready lists, etc.) are located using the debug information of the
+//! program binary, which allows debuggers to show every task as a separate thread. For tasks that are not currently
+//! running, the register context saved by the RTOS during the last context switch is reconstructed, so that they can
+//! be unwound like the running task.
+
+mod cortex_m;
+mod embassy;
+mod freertos;
+mod zephyr;
+
+pub use embassy::Embassy;
+pub use freertos::FreeRtos;
+pub use zephyr::Zephyr;
+
+use super::{DebugInfo, DebugRegisters};
+use crate::{Core, Error, MemoryInterface};
+
+/// The scheduling state of an RTOS thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtosThreadState {
+    /// The thread is currently executing on the core.
+    Running,
+    /// The thread is ready to run, and waiting to be scheduled.
+    Ready,
+    /// The thread is waiting for an event, or for a timeout to expire.
+    Blocked,
+    /// The thread was suspended, or has not been started yet.
+    Suspended,
+    /// The thread was deleted, but its resources have not been released yet.
+    Deleted,
+    /// The state could not be determined.
+    Unknown,
+}
+
+impl std::fmt::Display for RtosThreadState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            RtosThreadState::Running => "Running",
+            RtosThreadState::Ready => "Ready",
+            RtosThreadState::Blocked => "Blocked",
+            RtosThreadState::Suspended => "Suspended",
+            RtosThreadState::Deleted => "Deleted",
+            RtosThreadState::Unknown => "Unknown",
+        };
+        f.write_str(state)
+    }
+}
+
+/// A thread (or task) managed by the RTOS on the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtosThread {
+    /// An identifier for the thread which remains stable while the thread exists, usually the address of its control block.
+    pub id: u64,
+    /// The name of the thread.
+    pub name: String,
+    /// The scheduling state of the thread.
+    pub state: RtosThreadState,
+    /// The registers of the thread, as saved by the RTOS when it was last switched out.
+    ///
+    /// This is `None` for the [`RtosThreadState::Running`] thread, whose registers are those of the core,
+    /// and for threads whose context could not be reconstructed.
+    pub registers: Option<DebugRegisters>,
+}
+
+impl RtosThread {
+    /// Returns `true` if this thread is currently executing on the core.
+    pub fn is_running(&self) -> bool {
+        self.state == RtosThreadState::Running
+    }
+}
+
+/// The interface to the kernel data structures of a specific RTOS.
+///
+/// The implementations resolve the location and layout of the kernel data structures from the debug information once,
+/// so that the threads can be listed every time the core halts without access to the [`DebugInfo`].
+pub trait Rtos: std::fmt::Debug + Send + Sync {
+    /// The name of the RTOS.
+    fn name(&self) -> &'static str;
+
+    /// Read the list of threads from the target. The core must be halted.
+    ///
+    /// Returns an empty list if the RTOS has not started scheduling threads yet.
+    fn threads(&self, core: &mut Core<'_>) -> Result<Vec<RtosThread>, Error>;
+}
+
+/// Detect which RTOS (if any) is used by the program, based on the symbols in its debug information.
+pub fn detect_rtos(debug_info: &DebugInfo) -> Option<Box<dyn Rtos>> {
+    let detected: Option<Box<dyn Rtos>> = if let Some(freertos) = FreeRtos::new(debug_info) {
+        Some(Box::new(freertos))
+    } else if let Some(zephyr) = Zephyr::new(debug_info) {
+        Some(Box::new(zephyr))
+    } else if let Some(embassy) = Embassy::new(debug_info) {
+        Some(Box::new(embassy))
+    } else {
+        None
+    };
+
+    if let Some(rtos) = &detected {
+        tracing::info!("Detected {} in the program binary.", rtos.name());
+    }
+    detected
+}
+
+/// Read a pointer sized value from the target.
+fn read_pointer(
+    memory: &mut impl MemoryInterface,
+    address: u64,
+    pointer_size: u8,
+) -> Result<u64, Error> {
+    if pointer_size == 8 {
+        memory.read_word_64(address)
+    } else {
+        memory.read_word_32(address).map(u64::from)
+    }
+}
+
+/// Read a NUL terminated string of at most `max_length` bytes from the target.
+fn read_string(
+    memory: &mut impl MemoryInterface,
+    address: u64,
+    max_length: usize,
+) -> Result<String, Error> {
+    let mut buffer = vec![0; max_length];
+    memory.read_8(address, &mut buffer)?;
+    let length = buffer.iter().position(|&c| c == 0).unwrap_or(max_length);
+    Ok(String::from_utf8_lossy(&buffer[..length]).into_owned())
+}
+
+/// Reconstruct the registers of a thread that is not running, or return `None` if the core architecture is not supported.
+fn saved_registers(
+    core: &mut Core<'_>,
+    live_registers: &DebugRegisters,
+    context: SavedContext,
+) -> Result<Option<DebugRegisters>, Error> {
+    if !core.core_type().is_cortex_m() {
+        tracing::debug!(
+            "Reconstructing the context of RTOS threads is not supported for {:?} cores.",
+            core.core_type()
+        );
+        return Ok(None);
+    }
+
+    let frame = match context {
+        SavedContext::CortexMStack { top_of_stack } => {
+            let core_type = core.core_type();
+            cortex_m::SwitchFrame::from_stack(core, core_type, top_of_stack)?
+        }
+        SavedContext::CortexMCalleeSaved {
+            callee_saved,
+            process_stack_pointer,
+            exc_return,
+        } => cortex_m::SwitchFrame {
+            callee_saved,
+            exc_return,
+            exception_frame: process_stack_pointer,
+        },
+    };
+
+    frame.registers(core, live_registers).map(Some)
+}
+
+/// Where an RTOS saved the context of a thread during the last context switch.
+enum SavedContext {
+    /// All registers were pushed to the stack of the thread, in the layout used by the FreeRTOS Cortex-M ports.
+    CortexMStack {
+        /// The last value of the stack pointer, after the registers were saved.
+        top_of_stack: u64,
+    },
+    /// The callee saved registers are stored in the thread control block,
+    /// and the remaining registers in the exception frame on the thread stack.
+    CortexMCalleeSaved {
+        /// The values of `R4` to `R11`.
+        callee_saved: [u32; 8],
+        /// The stack pointer, pointing at the exception frame.
+        process_stack_pointer: u64,
+        /// The `EXC_RETURN` value used to return to the thread.
+        exc_return: u32,
+    },
+}
@@ -0,0 +1,179 @@
+//! Thread awareness for the [Zephyr](https://zephyrproject.org) RTOS.
+//!
+//! Zephyr keeps a linked list of all threads in `_kernel.threads`, if the kernel is built with `CONFIG_THREAD_MONITOR`.
+//! On Cortex-M, the callee saved registers of a thread that is switched out are stored in `k_thread.callee_saved`,
+//! and the remaining registers in the exception frame at `callee_saved.psp`.
+
+use super::{
+    cortex_m::EXC_RETURN_THREAD_PSP, read_pointer, read_string, saved_registers, RtosThread,
+    RtosThreadState, SavedContext,
+};
+use crate::{
+    debug::{DebugInfo, DebugRegisters},
+    Core, Error, MemoryInterface,
+};
+
+/// The maximum number of characters read for a thread name (`CONFIG_THREAD_MAX_NAME_LEN`).
+const MAX_THREAD_NAME_LENGTH: usize = 32;
+
+/// An upper limit for the number of threads we will follow, in case the list is corrupted.
+const MAX_THREADS: usize = 256;
+
+// The bits of `k_thread.base.thread_state`.
+const THREAD_PENDING: u8 = 1 << 1;
+const THREAD_PRESTART: u8 = 1 << 2;
+const THREAD_DEAD: u8 = 1 << 3;
+const THREAD_SUSPENDED: u8 = 1 << 4;
+const THREAD_QUEUED: u8 = 1 << 7;
+
+/// The location of the Zephyr kernel data structures, and the layout of the structures we need to read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zephyr {
+    pointer_size: u8,
+    /// The address of `_kernel.threads`.
+    threads: u64,
+    /// The address of `_kernel.cpus[0].current`.
+    current: u64,
+    /// The offset of `next_thread` in `struct k_thread`.
+    next_thread: u64,
+    /// The offset of `name` in `struct k_thread`.
+    name: Option<u64>,
+    /// The offset of `base.thread_state` in `struct k_thread`.
+    thread_state: u64,
+    /// The offset of `callee_saved.v1` in `struct k_thread`.
+    callee_saved: Option<u64>,
+    /// The offset of `callee_saved.psp` in `struct k_thread`.
+    process_stack_pointer: Option<u64>,
+    /// The offset of `arch.mode_exc_return` in `struct k_thread`.
+    mode_exc_return: Option<u64>,
+}
+
+impl Zephyr {
+    /// Locate the Zephyr kernel data structures in the debug information.
+    ///
+    /// Returns `None` if the program does not use Zephyr, or if it was built without `CONFIG_THREAD_MONITOR`.
+    pub fn new(debug_info: &DebugInfo) -> Option<Self> {
+        let kernel = debug_info.find_static_variable("_kernel")?;
+
+        let Some(threads) = debug_info.struct_member_offset("z_kernel", "threads") else {
+            tracing::warn!(
+                "Zephyr was detected, but thread awareness requires `CONFIG_THREAD_MONITOR=y`."
+            );
+            return None;
+        };
+
+        Some(Self {
+            pointer_size: debug_info.address_size(),
+            threads: kernel.address + threads,
+            current: kernel.address
+                + debug_info.struct_member_offset("z_kernel", "cpus.current")?,
+            next_thread: debug_info.struct_member_offset("k_thread", "next_thread")?,
+            name: debug_info.struct_member_offset("k_thread", "name"),
+            thread_state: debug_info.struct_member_offset("k_thread", "base.thread_state")?,
+            callee_saved: debug_info.struct_member_offset("k_thread", "callee_saved.v1"),
+            process_stack_pointer: debug_info.struct_member_offset("k_thread", "callee_saved.psp"),
+            mode_exc_return: debug_info.struct_member_offset("k_thread", "arch.mode_exc_return"),
+        })
+    }
+
+    /// Read the context of a thread that is switched out.
+    fn saved_context(
+        &self,
+        memory: &mut impl MemoryInterface,
+        thread: u64,
+    ) -> Result<Option<SavedContext>, Error> {
+        let (Some(callee_saved_offset), Some(process_stack_pointer)) =
+            (self.callee_saved, self.process_stack_pointer)
+        else {
+            return Ok(None);
+        };
+
+        let mut callee_saved = [0; 8];
+        memory.read_32(thread + callee_saved_offset, &mut callee_saved)?;
+        let process_stack_pointer = u64::from(memory.read_word_32(thread + process_stack_pointer)?);
+        let exc_return = match self.mode_exc_return {
+            Some(mode_exc_return) => {
+                0xFFFF_FF00 | u32::from(memory.read_word_8(thread + mode_exc_return)?)
+            }
+            None => EXC_RETURN_THREAD_PSP,
+        };
+
+        Ok(Some(SavedContext::CortexMCalleeSaved {
+            callee_saved,
+            process_stack_pointer,
+            exc_return,
+        }))
+    }
+}
+
+impl super::Rtos for Zephyr {
+    fn name(&self) -> &'static str {
+        "Zephyr"
+    }
+
+    fn threads(&self, core: &mut Core<'_>) -> Result<Vec<RtosThread>, Error> {
+        let current = read_pointer(core, self.current, self.pointer_size)?;
+        if current == 0 {
+            return Ok(vec![]);
+        }
+
+        let live_registers = DebugRegisters::from_core(core);
+
+        let mut threads = Vec::new();
+        let mut thread = read_pointer(core, self.threads, self.pointer_size)?;
+        while thread != 0 && threads.len() < MAX_THREADS {
+            let name = match self.name {
+                Some(name) => read_string(core, thread + name, MAX_THREAD_NAME_LENGTH)?,
+                None => String::new(),
+            };
+            let name = if name.is_empty() {
+                format!("Thread @ {thread:#010x}")
+            } else {
+                name
+            };
+
+            let (state, registers) = if thread == current {
+                (RtosThreadState::Running, None)
+            } else {
+                let state = thread_state(core.read_word_8(thread + self.thread_state)?);
+                let registers = match self.saved_context(core, thread) {
+                    Ok(Some(context)) => saved_registers(core, &live_registers, context),
+                    Ok(None) => Ok(None),
+                    Err(error) => Err(error),
+                }
+                .unwrap_or_else(|error| {
+                    tracing::warn!("Failed to read the saved context of thread {name:?}: {error}");
+                    None
+                });
+                (state, registers)
+            };
+
+            threads.push(RtosThread {
+                id: thread,
+                name,
+                state,
+                registers,
+            });
+
+            thread = read_pointer(core, thread + self.next_thread, self.pointer_size)?;
+        }
+
+        Ok(threads)
+    }
+}
+
+/// Convert the `thread_state` bits of a thread that is not running.
+fn thread_state(thread_state: u8) -> RtosThreadState {
+    if thread_state & THREAD_DEAD != 0 {
+        RtosThreadState::Deleted
+    } else if thread_state & (THREAD_SUSPENDED | THREAD_PRESTART) != 0 {
+        RtosThreadState::Suspended
+    } else if thread_state & THREAD_PENDING != 0 {
+        RtosThreadState::Blocked
+    } else if thread_state & THREAD_QUEUED != 0 {
+        RtosThreadState::Ready
+    } else {
+        // Threads which are sleeping, or waiting for a timeout, have no specific state bits set.
+        RtosThreadState::Blocked
+    }
+}
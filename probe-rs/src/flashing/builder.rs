@@ -69,6 +69,10 @@ impl FlashSector {
     pub fn size(&self) -> u64 {
         self.size
     }
+
+    fn contains(&self, page: &FlashPage) -> bool {
+        (self.address..self.address + self.size).contains(&page.address)
+    }
 }
 
 /// A struct to hold all the information about one region
@@ -144,6 +148,45 @@ impl FlashLayout {
     pub fn visualize(&self) -> FlashVisualizer {
         FlashVisualizer::new(self)
     }
+
+    /// Returns the expected contents of `sector` after flashing.
+    ///
+    /// Bytes which are not covered by any page are expected to be erased.
+    pub(super) fn sector_contents(&self, sector: &FlashSector, erased_byte_value: u8) -> Vec<u8> {
+        let mut contents = vec![erased_byte_value; sector.size as usize];
+        for page in self.pages.iter().filter(|page| sector.contains(page)) {
+            let offset = (page.address - sector.address) as usize;
+            contents[offset..][..page.data.len()].copy_from_slice(&page.data);
+        }
+        contents
+    }
+
+    /// Removes all sectors for which `keep` returns `false`, together with their pages and fills.
+    pub(super) fn retain_sectors(&mut self, mut keep: impl FnMut(&FlashSector) -> bool) {
+        let (kept, removed): (Vec<_>, Vec<_>) = self.sectors.drain(..).partition(&mut keep);
+        self.sectors = kept;
+
+        let mut page_indices = Vec::with_capacity(self.pages.len());
+        let mut pages = Vec::with_capacity(self.pages.len());
+        for page in self.pages.drain(..) {
+            if removed.iter().any(|sector| sector.contains(&page)) {
+                page_indices.push(None);
+            } else {
+                page_indices.push(Some(pages.len()));
+                pages.push(page);
+            }
+        }
+        self.pages = pages;
+
+        self.fills
+            .retain_mut(|fill| match page_indices[fill.page_index] {
+                Some(index) => {
+                    fill.page_index = index;
+                    true
+                }
+                None => false,
+            });
+    }
 }
 
 /// A block of data that is to be written to flash.
@@ -166,7 +209,7 @@ impl FlashDataBlockSpan {
 }
 
 /// A helper structure to build a flash layout from a set of data blocks.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub(super) struct FlashBuilder {
     pub(super) data: BTreeMap<u64, Vec<u8>>,
 }
@@ -222,6 +265,62 @@ impl FlashBuilder {
         Ok(())
     }
 
+    /// Overwrites staged data with `data`.
+    ///
+    /// Unlike [`FlashBuilder::add_data`], the new data may overlap already staged data. The parts
+    /// of `data` which are not covered by staged data yet are added as new chunks.
+    pub(crate) fn write_data(&mut self, address: u64, data: &[u8]) -> Result<(), FlashError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let end = address + data.len() as u64;
+        let start = match self.data.range(..address).next_back() {
+            Some((&prev_addr, prev_data)) if prev_addr + prev_data.len() as u64 > address => {
+                prev_addr
+            }
+            _ => address,
+        };
+
+        let mut gaps = vec![];
+        let mut cursor = address;
+        for (&chunk_addr, chunk) in self.data.range_mut(start..end) {
+            let from = chunk_addr.max(address);
+            let to = (chunk_addr + chunk.len() as u64).min(end);
+            if from > cursor {
+                gaps.push(cursor..from);
+            }
+
+            chunk[(from - chunk_addr) as usize..(to - chunk_addr) as usize]
+                .copy_from_slice(&data[(from - address) as usize..(to - address) as usize]);
+            cursor = to;
+        }
+        if cursor < end {
+            gaps.push(cursor..end);
+        }
+
+        for gap in gaps {
+            let data = &data[(gap.start - address) as usize..(gap.end - address) as usize];
+            self.add_data(gap.start, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a copy of the staged data in the given address range, if the whole range is
+    /// covered by staged data.
+    pub(crate) fn read_data(&self, range: &Range<u64>) -> Option<Vec<u8>> {
+        let mut result = Vec::with_capacity((range.end - range.start) as usize);
+        for (address, data) in self.data_in_range(range) {
+            if address != range.start + result.len() as u64 {
+                return None;
+            }
+            result.extend_from_slice(data);
+        }
+
+        (result.len() as u64 == range.end - range.start).then_some(result)
+    }
+
     /// Check whether there is staged data for a given address range.
     pub(crate) fn has_data_in_range(&self, range: &Range<u64>) -> bool {
         self.data_in_range(range).next().is_some()
@@ -1147,4 +1246,80 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn retain_sectors_removes_pages_and_fills() {
+        let (region, flash_algorithm) = assemble_demo_flash1();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0, &[42; 1024]).unwrap();
+        flash_builder.add_data(4096 + 16, &[43; 16]).unwrap();
+        let mut flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, true)
+            .unwrap();
+
+        let first_sector = flash_layout.sectors()[0].clone();
+        let contents = flash_layout.sector_contents(&first_sector, 255);
+        assert_eq!(&contents[..1024], &[42; 1024]);
+        assert_eq!(&contents[1024..], &[255; 3072]);
+
+        flash_layout.retain_sectors(|sector| sector.address() != 0);
+
+        assert_eq!(
+            flash_layout.sectors(),
+            &[FlashSector {
+                address: 4096,
+                size: 4096
+            }]
+        );
+        assert_eq!(flash_layout.pages().len(), 4);
+        assert_eq!(flash_layout.pages()[0].address(), 4096);
+        assert_eq!(
+            flash_layout.fills(),
+            &[
+                FlashFill {
+                    address: 4096,
+                    size: 16,
+                    page_index: 0,
+                },
+                FlashFill {
+                    address: 4096 + 32,
+                    size: 1024 - 32,
+                    page_index: 0,
+                },
+                FlashFill {
+                    address: 5120,
+                    size: 1024,
+                    page_index: 1,
+                },
+                FlashFill {
+                    address: 6144,
+                    size: 1024,
+                    page_index: 2,
+                },
+                FlashFill {
+                    address: 7168,
+                    size: 1024,
+                    page_index: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn write_data_overwrites_and_fills_gaps() {
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0, &[1, 2, 3, 4]).unwrap();
+        flash_builder.add_data(8, &[5, 6, 7, 8]).unwrap();
+
+        flash_builder.write_data(2, &[0xAA; 8]).unwrap();
+
+        assert_eq!(
+            flash_builder.read_data(&(0..12)),
+            Some(vec![
+                1, 2, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 7, 8
+            ])
+        );
+        assert_eq!(flash_builder.read_data(&(0..13)), None);
+        assert_eq!(FlashBuilder::new().read_data(&(0..1)), None);
+    }
 }
@@ -0,0 +1,285 @@
+//! Flash bundles, which describe multiple images to be flashed in a single session.
+//!
+//! A bundle is described by a manifest in TOML or YAML format:
+//!
+//! ```toml
+//! [[images]]
+//! path = "bootloader.elf"
+//!
+//! [[images]]
+//! path = "application.bin"
+//! format = "bin"
+//! base_address = 0x0801_0000
+//!
+//! [[images]]
+//! path = "calibration.hex"
+//! core = "main"
+//! ```
+//!
+//! Relative paths are resolved relative to the directory containing the manifest.
+
+use std::{
+    fs::File,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use probe_rs_target::MemoryRange;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{BinOptions, FileDownloadError, Format, FormatKind};
+use crate::{flashing::FlashLoader, session::Session};
+
+/// A list of images which are flashed together.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FlashBundle {
+    /// The images contained in the bundle.
+    pub images: Vec<BundleImage>,
+}
+
+/// A single image of a [`FlashBundle`].
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BundleImage {
+    /// The path of the image file.
+    pub path: PathBuf,
+    /// The format of the image.
+    ///
+    /// If not set, the format is determined from the file extension, falling back to ELF.
+    #[serde(default, deserialize_with = "deserialize_format")]
+    pub format: Option<FormatKind>,
+    /// The address the image is placed at, for images in binary format.
+    #[serde(default)]
+    pub base_address: Option<u64>,
+    /// The number of bytes to skip at the start of the image, for images in binary format.
+    #[serde(default)]
+    pub skip: u32,
+    /// The name of the core the image belongs to.
+    ///
+    /// If set, all data of the image must be placed in memory accessible by this core.
+    #[serde(default)]
+    pub core: Option<String>,
+}
+
+fn deserialize_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<FormatKind>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|format| FormatKind::from_str(&format).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+impl BundleImage {
+    /// Returns the format the image is loaded with.
+    pub fn format(&self) -> Format {
+        let kind = self
+            .format
+            .or_else(|| FormatKind::from_extension(&self.path))
+            .unwrap_or_default();
+
+        match kind {
+            FormatKind::Bin => Format::Bin(BinOptions {
+                base_address: self.base_address,
+                skip: self.skip,
+            }),
+            other => other.into(),
+        }
+    }
+}
+
+impl FlashBundle {
+    /// Returns whether the file at `path` is a bundle manifest, based on its extension.
+    pub fn is_manifest(path: &Path) -> bool {
+        matches!(
+            path.extension().and_then(|extension| extension.to_str()),
+            Some("toml" | "yaml" | "yml")
+        )
+    }
+
+    /// Reads a bundle manifest from `path`.
+    ///
+    /// The manifest is parsed as YAML if the extension is `.yaml` or `.yml`, and as TOML otherwise.
+    /// Relative image paths are resolved relative to the directory containing the manifest.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, FileDownloadError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+
+        let mut bundle = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Self::from_yaml(&contents)?,
+            _ => Self::from_toml(&contents)?,
+        };
+
+        if let Some(directory) = path.parent() {
+            for image in bundle.images.iter_mut() {
+                image.path = directory.join(&image.path);
+            }
+        }
+
+        Ok(bundle)
+    }
+
+    /// Parses a bundle manifest in TOML format.
+    pub fn from_toml(manifest: &str) -> Result<Self, FileDownloadError> {
+        toml::from_str(manifest)
+            .map_err(|error| FileDownloadError::InvalidBundle(error.to_string()))
+    }
+
+    /// Parses a bundle manifest in YAML format.
+    pub fn from_yaml(manifest: &str) -> Result<Self, FileDownloadError> {
+        serde_yaml::from_str(manifest)
+            .map_err(|error| FileDownloadError::InvalidBundle(error.to_string()))
+    }
+
+    /// Loads all images of the bundle into a single [`FlashLoader`].
+    ///
+    /// This fails if the data of two images overlaps, or if an image is placed in
+    /// memory which is not accessible by the core it belongs to.
+    pub fn build_loader(&self, session: &mut Session) -> Result<FlashLoader, FileDownloadError> {
+        let mut loader = session.target().flash_loader();
+        let mut loaded: Vec<(Range<u64>, &Path)> = Vec::new();
+
+        for image in self.images.iter() {
+            let image_loader = self.load_image(session, image)?;
+
+            for (address, data) in image_loader.data() {
+                let range = address..address + data.len() as u64;
+
+                if let Some((existing, other)) = loaded
+                    .iter()
+                    .find(|(existing, _)| existing.intersects_range(&range))
+                {
+                    return Err(FileDownloadError::BundleOverlap {
+                        first: other.to_path_buf(),
+                        second: image.path.clone(),
+                        address: existing.start.max(range.start),
+                    });
+                }
+
+                loader.add_data(address, data)?;
+                loaded.push((range, &image.path));
+            }
+        }
+
+        Ok(loader)
+    }
+
+    fn load_image(
+        &self,
+        session: &mut Session,
+        image: &BundleImage,
+    ) -> Result<FlashLoader, FileDownloadError> {
+        let mut loader = session.target().flash_loader();
+
+        let result = File::open(&image.path)
+            .map_err(FileDownloadError::IO)
+            .and_then(|mut file| loader.load_image(session, &mut file, image.format(), None));
+
+        if let Err(error) = result {
+            return Err(FileDownloadError::BundleImage {
+                path: image.path.clone(),
+                source: Box::new(error),
+            });
+        }
+
+        let Some(core) = &image.core else {
+            return Ok(loader);
+        };
+
+        let target = session.target();
+        if target.core_index_by_name(core).is_none() {
+            return Err(FileDownloadError::UnknownCore(core.clone()));
+        }
+
+        for (address, data) in loader.data() {
+            let range = address..address + data.len() as u64;
+            let inaccessible = target.memory_map.iter().find(|region| {
+                region.address_range().intersects_range(&range) && !region.cores().contains(core)
+            });
+
+            if let Some(region) = inaccessible {
+                return Err(FileDownloadError::BundleCoreMismatch {
+                    path: image.path.clone(),
+                    core: core.clone(),
+                    address: region.address_range().start.max(address),
+                });
+            }
+        }
+
+        Ok(loader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_toml_manifest() {
+        let bundle = FlashBundle::from_toml(
+            r#"
+            [[images]]
+            path = "bootloader.elf"
+
+            [[images]]
+            path = "application.bin"
+            format = "bin"
+            base_address = 0x08010000
+            skip = 16
+
+            [[images]]
+            path = "calibration.hex"
+            core = "main"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(bundle.images.len(), 3);
+        assert_eq!(bundle.images[0].format(), Format::Elf);
+        assert_eq!(
+            bundle.images[1].format(),
+            Format::Bin(BinOptions {
+                base_address: Some(0x0801_0000),
+                skip: 16,
+            })
+        );
+        assert_eq!(bundle.images[2].format(), Format::Hex);
+        assert_eq!(bundle.images[2].core.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn parse_yaml_manifest() {
+        let bundle = FlashBundle::from_yaml(
+            "
+            images:
+              - path: application.s19
+              - path: data.img
+                format: bin
+                base_address: 0x10000000
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(bundle.images[0].format(), Format::Srec);
+        assert_eq!(
+            bundle.images[1].format(),
+            Format::Bin(BinOptions {
+                base_address: Some(0x1000_0000),
+                skip: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn reject_invalid_manifests() {
+        assert!(matches!(
+            FlashBundle::from_toml("[[images]]\npath = \"a.bin\"\nformat = \"zip\""),
+            Err(FileDownloadError::InvalidBundle(_))
+        ));
+        assert!(matches!(
+            FlashBundle::from_toml("[[images]]\nfile = \"a.bin\""),
+            Err(FileDownloadError::InvalidBundle(_))
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+//! CRC-32 calculation, used to find flash sectors which already contain the data to be flashed.
+//!
+//! The checksum is the common CRC-32 (as used by zlib and Ethernet), which can be calculated
+//! both on the host and on the target, using [`THUMB_ROUTINE`].
+
+/// The reversed CRC-32 polynomial.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Continues the CRC-32 calculation of `crc` with `data`.
+///
+/// Use a `crc` of `0` to start a new calculation.
+pub(super) fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// A position independent Thumb routine which calculates the same checksum as [`crc32`].
+///
+/// It only uses instructions available on ARMv6-M, so it runs on every Cortex-M core.
+/// The routine has to be loaded to a word aligned address and is called with
+/// `r0` = address, `r1` = length and `r2` = previous CRC. The CRC is returned in `r0`.
+///
+/// ```text
+///     mvns  r2, r2
+///     ldr   r3, =0xEDB88320
+/// 1:  cmp   r1, #0
+///     beq   4f
+///     ldrb  r4, [r0]
+///     adds  r0, r0, #1
+///     eors  r2, r4
+///     movs  r4, #8
+/// 2:  lsrs  r2, r2, #1
+///     bcc   3f
+///     eors  r2, r3
+/// 3:  subs  r4, r4, #1
+///     bne   2b
+///     subs  r1, r1, #1
+///     b     1b
+/// 4:  mvns  r0, r2
+///     bx    lr
+/// ```
+pub(super) const THUMB_ROUTINE: [u32; 10] = [
+    0x4b08_43d2,
+    0xd00a_2900,
+    0x1c40_7804,
+    0x2408_4062,
+    0xd300_0852,
+    0x1e64_405a,
+    0x1e49_d1fa,
+    0x43d0_e7f2,
+    0x46c0_4770,
+    POLYNOMIAL,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn chained_calculation() {
+        let data = b"The quick brown fox jumps over the lazy dog";
+        let (start, end) = data.split_at(10);
+
+        assert_eq!(crc32(crc32(0, start), end), crc32(0, data));
+        assert_eq!(crc32(0, data), 0x414F_A339);
+    }
+}
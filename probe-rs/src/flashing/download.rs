@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 
 use std::{
     fs::File,
+    ops::Range,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -49,6 +50,8 @@ pub enum FormatKind {
     Idf,
     /// Marks a file in the [UF2](https://github.com/microsoft/uf2) format.
     Uf2,
+    /// Marks a file in the [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format.
+    Srec,
 }
 
 impl FormatKind {
@@ -61,6 +64,20 @@ impl FormatKind {
             None => Ok(Self::default()),
         }
     }
+
+    /// Determines the format from the extension of the file at `path`.
+    ///
+    /// Only extensions which unambiguously identify a format are recognized. This means that
+    /// `None` is returned for ELF and binary files, which often have no extension at all.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match &extension[..] {
+            "hex" | "ihex" => Some(Self::Hex),
+            "srec" | "s19" | "s28" | "s37" | "mot" | "mhx" => Some(Self::Srec),
+            "uf2" => Some(Self::Uf2),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for FormatKind {
@@ -73,6 +90,7 @@ impl FromStr for FormatKind {
             "elf" => Ok(Self::Elf),
             "uf2" => Ok(Self::Uf2),
             "idf" | "esp-idf" | "espidf" => Ok(Self::Idf),
+            "srec" | "s19" | "s28" | "s37" | "mot" | "mhx" | "motorola" => Ok(Self::Srec),
             _ => Err(format!("Format '{s}' is unknown.")),
         }
     }
@@ -95,6 +113,8 @@ pub enum Format {
     Idf(IdfOptions),
     /// Marks a file in the [UF2](https://github.com/microsoft/uf2) format.
     Uf2,
+    /// Marks a file in the [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format.
+    Srec,
 }
 
 impl From<FormatKind> for Format {
@@ -105,6 +125,7 @@ impl From<FormatKind> for Format {
             FormatKind::Elf => Format::Elf,
             FormatKind::Uf2 => Format::Uf2,
             FormatKind::Idf => Format::Idf(IdfOptions::default()),
+            FormatKind::Srec => Format::Srec,
         }
     }
 }
@@ -124,6 +145,15 @@ pub enum FileDownloadError {
     /// Failed to read or decode the IHEX file.
     IhexRead(#[from] ihex::ReaderError),
 
+    /// Failed to decode line {line} of the S-record file.
+    SrecRead {
+        /// The line number of the invalid record, starting at 1.
+        line: usize,
+        /// The reason why the record is invalid.
+        #[source]
+        source: SrecError,
+    },
+
     /// An IO error has occurred while reading the firmware file.
     IO(#[from] std::io::Error),
 
@@ -139,6 +169,44 @@ pub enum FileDownloadError {
     /// Target {0} does not support the esp-idf format
     IdfUnsupported(String),
 
+    /// Loading an esp-idf image requires a connection to the target.
+    IdfOffline,
+
+    /// The flash bundle manifest is invalid: {0}
+    InvalidBundle(String),
+
+    /// Failed to load {path:?} of the flash bundle.
+    BundleImage {
+        /// The path of the image.
+        path: PathBuf,
+        /// The reason why the image could not be loaded.
+        #[source]
+        source: Box<FileDownloadError>,
+    },
+
+    /// The data of {second:?} overlaps with the data of {first:?} at address {address:#010x}.
+    BundleOverlap {
+        /// The image which was loaded first.
+        first: PathBuf,
+        /// The image which overlaps with the first one.
+        second: PathBuf,
+        /// The first address contained in both images.
+        address: u64,
+    },
+
+    /// The data of {path:?} at address {address:#010x} is not accessible by core '{core}'.
+    BundleCoreMismatch {
+        /// The path of the image.
+        path: PathBuf,
+        /// The core the image belongs to.
+        core: String,
+        /// The first address which is not accessible by the core.
+        address: u64,
+    },
+
+    /// The target has no core named '{0}'.
+    UnknownCore(String),
+
     /// No loadable segments were found in the ELF file.
     #[ignore_extra_doc_attributes]
     ///
@@ -200,10 +268,29 @@ pub struct DownloadOptions {
     pub skip_erase: bool,
     /// Before flashing, read back the flash contents to skip up-to-date regions.
     pub preverify: bool,
+    /// Before erasing, compare the CRC of every affected sector with the data to be flashed,
+    /// and skip erasing and programming the sectors which are already up to date.
+    ///
+    /// On ARM targets the CRC is calculated on the target, so only the checksums
+    /// have to be transferred.
+    pub skip_unchanged_sectors: bool,
     /// After flashing, read back all the flashed data to verify it has been written correctly.
     pub verify: bool,
     /// Disable double buffering when loading flash.
     pub disable_double_buffering: bool,
+    /// Address ranges whose contents are kept, even if the sectors containing them are erased.
+    ///
+    /// These ranges are read back before erasing, and programmed again afterwards. Flashing fails
+    /// if the image contains data for any of these ranges.
+    pub preserve: Vec<Range<u64>>,
+    /// Do not apply the [image fixups](crate::config::ImageFixup) of the target, and flash the
+    /// image exactly as it is.
+    pub skip_image_fixups: bool,
+    /// After flashing, protect the device against reading out its memory, like
+    /// [`Session::set_protection`] does with [`ProtectionLevel::Protected`](crate::ProtectionLevel::Protected).
+    ///
+    /// Depending on the device, the protection only takes effect after a reset.
+    pub lock: bool,
 }
 
 impl DownloadOptions {
@@ -264,6 +351,21 @@ pub fn download_file_with_options(
         .map_err(FileDownloadError::Flash)
 }
 
+/// Downloads all images of `bundle` to the flash of the target given in `session`.
+///
+/// The images are merged before flashing, so every sector is only erased and programmed once.
+pub fn download_bundle_with_options(
+    session: &mut Session,
+    bundle: &FlashBundle,
+    options: DownloadOptions,
+) -> Result<(), FileDownloadError> {
+    let loader = bundle.build_loader(session)?;
+
+    loader
+        .commit(session, options)
+        .map_err(FileDownloadError::Flash)
+}
+
 /// Flash data which was extracted from an ELF file.
 pub(super) struct ExtractedFlashData<'data> {
     pub(super) section_names: Vec<String>,
@@ -389,7 +491,7 @@ pub(super) fn extract_from_elf(
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{path::Path, str::FromStr};
 
     use super::FormatKind;
 
@@ -414,6 +516,10 @@ mod tests {
         assert_eq!(FormatKind::from_str("espidf"), Ok(FormatKind::Idf));
         assert_eq!(FormatKind::from_str("esp-idf"), Ok(FormatKind::Idf));
         assert_eq!(FormatKind::from_str("ESP-IDF"), Ok(FormatKind::Idf));
+        assert_eq!(FormatKind::from_str("srec"), Ok(FormatKind::Srec));
+        assert_eq!(FormatKind::from_str("S19"), Ok(FormatKind::Srec));
+        assert_eq!(FormatKind::from_str("mot"), Ok(FormatKind::Srec));
+        assert_eq!(FormatKind::from_str("mhx"), Ok(FormatKind::Srec));
         assert_eq!(
             FormatKind::from_str("elfbin"),
             Err("Format 'elfbin' is unknown.".to_string())
@@ -427,4 +533,30 @@ mod tests {
             Err("Format 'asdasdf' is unknown.".to_string())
         );
     }
+
+    #[test]
+    fn format_from_extension() {
+        let from_extension = |path| FormatKind::from_extension(Path::new(path));
+
+        assert_eq!(from_extension("firmware.hex"), Some(FormatKind::Hex));
+        assert_eq!(from_extension("firmware.S19"), Some(FormatKind::Srec));
+        assert_eq!(from_extension("out/firmware.srec"), Some(FormatKind::Srec));
+        assert_eq!(from_extension("firmware.mot"), Some(FormatKind::Srec));
+        assert_eq!(from_extension("firmware.uf2"), Some(FormatKind::Uf2));
+        assert_eq!(from_extension("firmware.bin"), None);
+        assert_eq!(
+            from_extension("target/thumbv7em-none-eabihf/release/firmware"),
+            None
+        );
+
+        // Every recognized extension can also be given as the format.
+        for extension in [
+            "hex", "ihex", "srec", "s19", "s28", "s37", "mot", "mhx", "uf2",
+        ] {
+            assert_eq!(
+                FormatKind::from_extension(Path::new(&format!("firmware.{extension}"))),
+                FormatKind::from_str(extension).ok(),
+            );
+        }
+    }
 }
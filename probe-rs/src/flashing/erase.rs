@@ -1,8 +1,9 @@
 use std::collections::HashMap;
+use std::ops::Range;
 
 use probe_rs_target::{MemoryRange, MemoryRegion, NvmRegion};
 
-use crate::flashing::{flasher::Flasher, FlashError, FlashLoader};
+use crate::flashing::{flasher::Flasher, DownloadOptions, FlashError, FlashLoader};
 use crate::flashing::{FlashAlgorithm, FlashLayout, FlashSector};
 use crate::Session;
 
@@ -142,6 +143,36 @@ pub fn erase_all(session: &mut Session, progress: FlashProgress) -> Result<(), F
     Ok(())
 }
 
+/// Mass-erase all nonvolatile memory, but keep the contents of the `preserve` ranges, e.g.
+/// factory calibration data.
+///
+/// The preserved ranges are read back before erasing, and programmed again afterwards.
+pub fn erase_all_preserving(
+    session: &mut Session,
+    progress: FlashProgress,
+    preserve: &[Range<u64>],
+) -> Result<(), FlashError> {
+    let mut loader = session.target().flash_loader();
+    let nvm = session
+        .target()
+        .memory_map
+        .iter()
+        .filter_map(MemoryRegion::as_nvm_region)
+        .filter(|region| !region.is_alias)
+        .map(|region| region.range.clone())
+        .collect::<Vec<_>>();
+    loader.add_preserved_data(session, preserve, &nvm)?;
+
+    erase_all(session, progress)?;
+
+    // The preserved data is restored exactly as it was read.
+    let options = DownloadOptions {
+        skip_image_fixups: true,
+        ..Default::default()
+    };
+    loader.commit(session, options)
+}
+
 /// Erases `sectors` sectors starting from `start_sector` from flash.
 // TODO: currently no progress is reported by anything in this function.
 pub fn erase_sectors(
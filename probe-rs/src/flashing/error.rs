@@ -177,6 +177,16 @@ pub enum FlashError {
         /// The address range that was already present.
         existing_addresses: Range<u64>,
     },
+    /// The image overwrites a range which should be preserved.
+    #[error(
+        "The image writes to {address:#010x}, which is in the preserved range {preserved:#010x?}."
+    )]
+    PreservedRangeOverwritten {
+        /// The preserved range.
+        preserved: Range<u64>,
+        /// The first address of the image in the preserved range.
+        address: u64,
+    },
     /// No core can access this NVM region.
     #[error("No core can access the NVM region {0:?}.")]
     NoNvmCoreAccess(NvmRegion),
@@ -192,4 +202,7 @@ pub enum FlashError {
         /// The operation that caused the stack overflow.
         operation: &'static str,
     },
+    /// The device could not be protected after flashing it.
+    #[error("Failed to protect the device after flashing it.")]
+    Protection(#[source] error::Error),
 }
@@ -0,0 +1,425 @@
+//! Post-processing of images before they are flashed.
+//!
+//! Some boot ROMs refuse to start an image unless it contains a checksum or a boot header,
+//! which regular builds usually do not produce. The fixups requested by the target description
+//! add the missing data to the image when it is flashed, after all images were merged, unless
+//! [`DownloadOptions::skip_image_fixups`](super::DownloadOptions::skip_image_fixups) is set.
+
+use std::ops::Range;
+
+use probe_rs_target::{ImxrtBootHeader, McubootTrailer, NvmRegion};
+
+use super::{FlashError, FlashLoader};
+use crate::config::{ImageFixup, MemoryRegion};
+use crate::{MemoryInterface, Session};
+
+/// The result of applying a single fixup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FixupOutcome {
+    /// The image already contains the data added by the fixup.
+    AlreadyValid,
+    /// The image was patched.
+    Applied,
+    /// The fixup does not apply to the image, e.g. because it does not contain a vector table.
+    NotApplicable,
+}
+
+/// Tag of the FlexSPI configuration block, "FCFB".
+const FCB_TAG: u32 = 0x4246_4346;
+/// Tag, length and version of the image vector table.
+const IVT_HEADER: u32 = 0x4120_00D1;
+const FCB_SIZE: usize = 512;
+const IVT_OFFSET: u64 = 0x1000;
+const BOOT_DATA_OFFSET: u64 = 0x1020;
+const IMXRT_VECTOR_TABLE_OFFSET: u64 = 0x2000;
+
+const MCUBOOT_IMAGE_MAGIC: u32 = 0x96f3_b83d;
+const MCUBOOT_TRAILER_MAGIC: [u8; 16] = [
+    0x77, 0xc2, 0x95, 0xf3, 0x60, 0xd2, 0xef, 0x7f, 0x35, 0x52, 0x50, 0x0f, 0x2c, 0xb6, 0x79, 0x80,
+];
+/// Alignment of the flags in the MCUboot trailer.
+const MCUBOOT_MAX_ALIGN: u64 = 8;
+
+impl FlashLoader {
+    /// Applies the given fixups to the staged image.
+    ///
+    /// This is done automatically by [`FlashLoader::commit`], [`FlashLoader::verify`] and
+    /// [`FlashLoader::plan`] with the fixups of the target, so it is only needed to inspect the
+    /// patched image. A warning is logged for every fixup which had to patch the image, as the
+    /// image would not boot if it was flashed by other tools.
+    pub fn apply_fixups(&mut self, fixups: &[ImageFixup]) -> Result<(), FlashError> {
+        self.apply_fixups_for_device(fixups, None)
+    }
+
+    /// Returns a copy of the loader with the given fixups applied to the staged image.
+    ///
+    /// If a `session` is given, data which is already present on the device is taken into
+    /// account, e.g. a boot header which was flashed separately is not replaced.
+    pub(super) fn with_fixups(
+        &self,
+        fixups: &[ImageFixup],
+        session: Option<&mut Session>,
+    ) -> Result<FlashLoader, FlashError> {
+        let mut loader = FlashLoader {
+            memory_map: self.memory_map.clone(),
+            builder: self.builder.clone(),
+            source: self.source.clone(),
+        };
+        loader.apply_fixups_for_device(fixups, session)?;
+
+        Ok(loader)
+    }
+
+    fn apply_fixups_for_device(
+        &mut self,
+        fixups: &[ImageFixup],
+        mut session: Option<&mut Session>,
+    ) -> Result<(), FlashError> {
+        for fixup in fixups {
+            let outcome = match fixup {
+                ImageFixup::LpcVectorChecksum => self.lpc_vector_checksum()?,
+                ImageFixup::ImxrtBootHeader(options) => {
+                    self.imxrt_boot_header(options, session.as_deref_mut())?
+                }
+                ImageFixup::McubootTrailer(options) => self.mcuboot_trailer(options)?,
+            };
+
+            tracing::debug!("Image fixup {fixup:?}: {outcome:?}");
+            if outcome == FixupOutcome::Applied {
+                tracing::warn!(
+                    "The image does not contain the {} required to boot this chip. It was added before flashing.",
+                    description(fixup)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the NVM region the chip boots from.
+    fn boot_region(&self) -> Option<&NvmRegion> {
+        let mut nvm_regions = self.memory_map.iter().filter_map(|region| match region {
+            MemoryRegion::Nvm(region) if !region.is_alias => Some(region),
+            _ => None,
+        });
+
+        nvm_regions
+            .clone()
+            .find(|region| region.is_boot_memory())
+            .or_else(|| nvm_regions.next())
+    }
+
+    fn read_u32(&self, address: u64) -> Option<u32> {
+        let bytes = self.builder.read_data(&(address..address + 4))?;
+        bytes.try_into().ok().map(u32::from_le_bytes)
+    }
+
+    fn write_data(&mut self, address: u64, data: &[u8]) -> Result<(), FlashError> {
+        self.check_data_in_memory_map(address..address + data.len() as u64)?;
+        self.builder.write_data(address, data)
+    }
+
+    fn lpc_vector_checksum(&mut self) -> Result<FixupOutcome, FlashError> {
+        let Some(base) = self.boot_region().map(|region| region.range.start) else {
+            return Ok(FixupOutcome::NotApplicable);
+        };
+        let Some(table) = self.builder.read_data(&(base..base + 32)) else {
+            return Ok(FixupOutcome::NotApplicable);
+        };
+
+        let (stored, checksum) = lpc_checksum(&table);
+        if stored == checksum {
+            return Ok(FixupOutcome::AlreadyValid);
+        }
+
+        self.write_data(base + 0x1C, &checksum.to_le_bytes())?;
+        Ok(FixupOutcome::Applied)
+    }
+
+    fn imxrt_boot_header(
+        &mut self,
+        options: &ImxrtBootHeader,
+        session: Option<&mut Session>,
+    ) -> Result<FixupOutcome, FlashError> {
+        let Some(region) = self.boot_region() else {
+            return Ok(FixupOutcome::NotApplicable);
+        };
+        let region = region.range.clone();
+        let base = region.start;
+        let fcb_address = base + options.fcb_offset;
+
+        if self.read_u32(fcb_address) == Some(FCB_TAG)
+            && self.read_u32(base + IVT_OFFSET) == Some(IVT_HEADER)
+        {
+            return Ok(FixupOutcome::AlreadyValid);
+        }
+
+        let vector_table = base + IMXRT_VECTOR_TABLE_OFFSET;
+        let Some(entry) = self.read_u32(vector_table + 4) else {
+            return Ok(FixupOutcome::NotApplicable);
+        };
+
+        if self.builder.has_data_in_range(&(base..vector_table)) {
+            tracing::warn!(
+                "The image contains data in front of the vector table at {vector_table:#010x}, but no valid boot header. It is flashed unchanged."
+            );
+            return Ok(FixupOutcome::NotApplicable);
+        }
+
+        // The boot header is often flashed once, e.g. with a configuration block matching the
+        // flash of a custom board, and only the application is updated afterwards.
+        if let Some(session) = session {
+            if device_has_boot_header(session, fcb_address, base + IVT_OFFSET) {
+                tracing::info!(
+                    "The image does not contain a boot header, the one in flash at {fcb_address:#010x} is kept."
+                );
+                return Ok(FixupOutcome::NotApplicable);
+            }
+        }
+
+        let image_end = self
+            .builder
+            .data_in_range(&region)
+            .last()
+            .map(|(address, data)| address + data.len() as u64)
+            .unwrap_or(vector_table);
+
+        let fcb = flexspi_nor_config(region.end - region.start);
+        let ivt = [
+            IVT_HEADER,
+            entry,
+            0,
+            0,
+            (base + BOOT_DATA_OFFSET) as u32,
+            (base + IVT_OFFSET) as u32,
+            0,
+            0,
+        ];
+        let boot_data = [base as u32, (image_end - base) as u32, 0, 0xFFFF_FFFF];
+
+        self.write_data(fcb_address, &fcb)?;
+        self.write_data(base + IVT_OFFSET, &words_to_bytes(&ivt))?;
+        self.write_data(base + BOOT_DATA_OFFSET, &words_to_bytes(&boot_data))?;
+
+        Ok(FixupOutcome::Applied)
+    }
+
+    fn mcuboot_trailer(&mut self, options: &McubootTrailer) -> Result<FixupOutcome, FlashError> {
+        let slot = options.slot_address..options.slot_address + options.slot_size;
+        let magic_address = slot.end - MCUBOOT_TRAILER_MAGIC.len() as u64;
+        let image_ok_address = magic_address - MCUBOOT_MAX_ALIGN;
+
+        if self.read_u32(slot.start) != Some(MCUBOOT_IMAGE_MAGIC) {
+            if self.builder.has_data_in_range(&slot) {
+                tracing::warn!(
+                    "The image in the MCUboot slot at {:#010x} has no MCUboot image header and will not be booted. Sign it with imgtool first.",
+                    slot.start
+                );
+            }
+            return Ok(FixupOutcome::NotApplicable);
+        }
+
+        let trailer: Range<u64> = image_ok_address..slot.end;
+        if self
+            .builder
+            .read_data(&(magic_address..slot.end))
+            .as_deref()
+            == Some(&MCUBOOT_TRAILER_MAGIC[..])
+        {
+            return Ok(FixupOutcome::AlreadyValid);
+        }
+        if self.builder.has_data_in_range(&trailer) {
+            tracing::warn!(
+                "The MCUboot trailer at {:#010x} contains data, but no valid magic. It is flashed unchanged.",
+                trailer.start
+            );
+            return Ok(FixupOutcome::NotApplicable);
+        }
+
+        if options.confirm {
+            let mut image_ok = [0xFF; MCUBOOT_MAX_ALIGN as usize];
+            image_ok[0] = 0x01;
+            self.write_data(image_ok_address, &image_ok)?;
+        }
+        self.write_data(magic_address, &MCUBOOT_TRAILER_MAGIC)?;
+
+        Ok(FixupOutcome::Applied)
+    }
+}
+
+/// Checks if the flash of the device already contains a FlexSPI configuration block and an
+/// image vector table.
+fn device_has_boot_header(session: &mut Session, fcb_address: u64, ivt_address: u64) -> bool {
+    let mut read =
+        |address| -> Result<u32, crate::Error> { session.core(0)?.read_word_32(address) };
+
+    match (read(fcb_address), read(ivt_address)) {
+        (Ok(fcb), Ok(ivt)) => fcb == FCB_TAG && ivt == IVT_HEADER,
+        (Err(error), _) | (_, Err(error)) => {
+            tracing::debug!("Could not read the boot header from flash: {error}");
+            false
+        }
+    }
+}
+
+/// Returns a human-readable description of the data added by `fixup`.
+fn description(fixup: &ImageFixup) -> &'static str {
+    match fixup {
+        ImageFixup::LpcVectorChecksum => "vector table checksum",
+        ImageFixup::ImxrtBootHeader(_) => "FlexSPI configuration block and image vector table",
+        ImageFixup::McubootTrailer(_) => "MCUboot image trailer",
+    }
+}
+
+/// Returns the stored and the expected checksum of an LPC vector table.
+fn lpc_checksum(table: &[u8]) -> (u32, u32) {
+    let mut words = table
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+
+    let checksum = words
+        .by_ref()
+        .take(7)
+        .fold(0u32, |sum, word| sum.wrapping_add(word))
+        .wrapping_neg();
+
+    (words.next().unwrap_or_default(), checksum)
+}
+
+fn words_to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Builds a FlexSPI NOR configuration block, which works with the QSPI flashes used on
+/// the evaluation boards of the i.MX RT family.
+fn flexspi_nor_config(flash_size: u64) -> [u8; FCB_SIZE] {
+    let mut fcb = [0; FCB_SIZE];
+    let mut put = |offset: usize, bytes: &[u8]| fcb[offset..][..bytes.len()].copy_from_slice(bytes);
+
+    put(0x000, &FCB_TAG.to_le_bytes());
+    put(0x004, &0x5601_0400u32.to_le_bytes());
+    // readSampleClkSrc: loopback from the DQS pad
+    put(0x00C, &[1]);
+    // csHoldTime, csSetupTime
+    put(0x00D, &[3, 3]);
+    // deviceType: serial NOR, sflashPadType: quad, serialClkFreq: 30 MHz
+    put(0x044, &[1, 4, 1]);
+    put(0x050, &(flash_size as u32).to_le_bytes());
+    // LUT sequence 0: quad I/O fast read (0xEB) with 24 bit addresses
+    put(0x080, &words_to_bytes(&[0x0A18_04EB, 0x2604_3206]));
+    // pageSize, sectorSize
+    put(0x1C0, &words_to_bytes(&[256, 4096]));
+    // blockSize
+    put(0x1D0, &0x1_0000u32.to_le_bytes());
+
+    fcb
+}
+
+#[cfg(test)]
+mod tests {
+    use probe_rs_target::MemoryAccess;
+
+    use super::*;
+    use crate::config::TargetDescriptionSource;
+
+    fn loader(flash: Range<u64>) -> FlashLoader {
+        FlashLoader::new(
+            vec![MemoryRegion::Nvm(NvmRegion {
+                name: Some("FLASH".into()),
+                range: flash,
+                cores: vec!["main".into()],
+                is_alias: false,
+                access: Some(MemoryAccess {
+                    boot: true,
+                    ..Default::default()
+                }),
+            })],
+            TargetDescriptionSource::BuiltIn,
+        )
+    }
+
+    #[test]
+    fn lpc_vector_checksum() {
+        let mut loader = loader(0..0x1_0000);
+        let table = words_to_bytes(&[0x1000_2000, 0x101, 0x103, 0x105, 0, 0, 0, 0]);
+        loader.add_data(0, &table).unwrap();
+
+        loader
+            .apply_fixups(&[ImageFixup::LpcVectorChecksum])
+            .unwrap();
+
+        let checksum = loader.read_u32(0x1C).unwrap();
+        let sum = [0x1000_2000u32, 0x101, 0x103, 0x105]
+            .iter()
+            .fold(checksum, |sum, word| sum.wrapping_add(*word));
+        assert_eq!(sum, 0);
+        assert_eq!(
+            loader.lpc_vector_checksum().unwrap(),
+            FixupOutcome::AlreadyValid
+        );
+    }
+
+    #[test]
+    fn fixups_are_applied_to_a_copy() {
+        let mut loader = loader(0..0x1_0000);
+        let table = words_to_bytes(&[0x1000_2000, 0x101, 0x103, 0x105, 0, 0, 0, 0]);
+        loader.add_data(0, &table).unwrap();
+
+        let fixed = loader
+            .with_fixups(&[ImageFixup::LpcVectorChecksum], None)
+            .unwrap();
+
+        assert_eq!(loader.read_u32(0x1C), Some(0));
+        assert_ne!(fixed.read_u32(0x1C), Some(0));
+    }
+
+    #[test]
+    fn imxrt_boot_header() {
+        let mut loader = loader(0x6000_0000..0x6080_0000);
+        let vector_table = words_to_bytes(&[0x2000_8000, 0x6000_2401]);
+        loader.add_data(0x6000_2000, &vector_table).unwrap();
+
+        let fixup = ImageFixup::ImxrtBootHeader(ImxrtBootHeader { fcb_offset: 0 });
+        loader.apply_fixups(&[fixup]).unwrap();
+
+        assert_eq!(loader.read_u32(0x6000_0000), Some(FCB_TAG));
+        assert_eq!(loader.read_u32(0x6000_0050), Some(0x80_0000));
+        assert_eq!(loader.read_u32(0x6000_1000), Some(IVT_HEADER));
+        assert_eq!(loader.read_u32(0x6000_1004), Some(0x6000_2401));
+        assert_eq!(loader.read_u32(0x6000_1010), Some(0x6000_1020));
+        assert_eq!(loader.read_u32(0x6000_1024), Some(0x2008));
+        assert_eq!(
+            loader
+                .imxrt_boot_header(&ImxrtBootHeader { fcb_offset: 0 }, None)
+                .unwrap(),
+            FixupOutcome::AlreadyValid
+        );
+    }
+
+    #[test]
+    fn mcuboot_trailer() {
+        let mut loader = loader(0..0x2_0000);
+        loader
+            .add_data(0x1_0000, &MCUBOOT_IMAGE_MAGIC.to_le_bytes())
+            .unwrap();
+
+        let options = McubootTrailer {
+            slot_address: 0x1_0000,
+            slot_size: 0x1_0000,
+            confirm: true,
+        };
+        loader
+            .apply_fixups(&[ImageFixup::McubootTrailer(options.clone())])
+            .unwrap();
+
+        assert_eq!(
+            loader.builder.read_data(&(0x1_FFF0..0x2_0000)).as_deref(),
+            Some(&MCUBOOT_TRAILER_MAGIC[..])
+        );
+        assert_eq!(loader.read_u32(0x1_FFE8), Some(0xFFFF_FF01));
+        assert_eq!(
+            loader.mcuboot_trailer(&options).unwrap(),
+            FixupOutcome::AlreadyValid
+        );
+    }
+}
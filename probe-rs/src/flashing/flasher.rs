@@ -1,7 +1,7 @@
 use probe_rs_target::RawFlashAlgorithm;
 use tracing::Level;
 
-use super::{FlashAlgorithm, FlashBuilder, FlashError, FlashPage, FlashProgress};
+use super::{crc, FlashAlgorithm, FlashBuilder, FlashError, FlashPage, FlashProgress};
 use crate::config::NvmRegion;
 use crate::error::Error;
 use crate::flashing::encoder::FlashEncoder;
@@ -45,6 +45,22 @@ impl Operation for Verify {
     const NAME: &'static str = "Verify";
 }
 
+/// Settings for programming a region with [`Flasher::program`].
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct ProgramSettings {
+    /// Read all bytes of a sector that are not to be written during flashing from the flash
+    /// first, and write them again once the sector is erased.
+    pub restore_unwritten_bytes: bool,
+    /// Use double buffering, if the flash algorithm supports it.
+    pub enable_double_buffering: bool,
+    /// Do not erase any sectors, e.g. because the chip was erased before.
+    pub skip_erasing: bool,
+    /// Neither erase nor program sectors which already contain the data to be flashed.
+    pub skip_unchanged_sectors: bool,
+    /// Verify the contents of the flash after programming it.
+    pub verify: bool,
+}
+
 /// A structure to control the flash of an attached microchip.
 ///
 /// Once constructed it can be used to program date to the flash.
@@ -254,18 +270,21 @@ impl<'session> Flasher<'session> {
 
     /// Program the contents of given `FlashBuilder` to the flash.
     ///
-    /// If `restore_unwritten_bytes` is `true`, all bytes of a sector,
-    /// that are not to be written during flashing will be read from the flash first
-    /// and written again once the sector is erased.
+    /// How the region is programmed is described by `settings`, see [`ProgramSettings`].
     pub(super) fn program(
         &mut self,
         region: &NvmRegion,
         flash_builder: &FlashBuilder,
-        restore_unwritten_bytes: bool,
-        enable_double_buffering: bool,
-        skip_erasing: bool,
-        verify: bool,
+        settings: ProgramSettings,
     ) -> Result<(), FlashError> {
+        let ProgramSettings {
+            restore_unwritten_bytes,
+            enable_double_buffering,
+            skip_erasing,
+            skip_unchanged_sectors,
+            verify,
+        } = settings;
+
         tracing::debug!("Starting program procedure.");
         // Convert the list of flash operations into flash sectors and pages.
         let mut flash_layout = self.flash_layout(region, flash_builder, restore_unwritten_bytes)?;
@@ -280,6 +299,12 @@ impl<'session> Flasher<'session> {
             self.fill_unwritten(&mut flash_layout)?;
         }
 
+        // Sectors are only compared if they would be erased, as a
+        // pre-erased sector will be programmed anyway.
+        if skip_unchanged_sectors && !skip_erasing {
+            self.skip_unchanged_sectors(&mut flash_layout)?;
+        }
+
         let flash_encoder = FlashEncoder::new(self.flash_algorithm.transfer_encoding, flash_layout);
 
         // Skip erase if necessary (i.e. chip erase was done before)
@@ -334,6 +359,44 @@ impl<'session> Flasher<'session> {
         result
     }
 
+    /// Removes all sectors from `layout` which already contain the data to be flashed.
+    ///
+    /// The flash contents are compared by their CRC, which is calculated on the target if possible.
+    fn skip_unchanged_sectors(&mut self, layout: &mut FlashLayout) -> Result<(), FlashError> {
+        let erased_byte_value = self.flash_algorithm.flash_properties.erased_byte_value;
+
+        let actual_crcs = self.run_verify(|active| {
+            layout
+                .sectors()
+                .iter()
+                .map(|sector| active.sector_crc(sector))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let unchanged = layout
+            .sectors()
+            .iter()
+            .zip(actual_crcs)
+            .filter(|(sector, actual_crc)| {
+                crc::crc32(0, &layout.sector_contents(sector, erased_byte_value)) == *actual_crc
+            })
+            .map(|(sector, _)| sector.clone())
+            .collect::<Vec<_>>();
+
+        for sector in unchanged.iter() {
+            tracing::debug!(
+                "Sector at address {:#010x} is unchanged, skipping it",
+                sector.address()
+            );
+            self.progress
+                .sector_skipped(sector.address(), sector.size());
+        }
+
+        layout.retain_sectors(|sector| !unchanged.contains(sector));
+
+        Ok(())
+    }
+
     /// Verifies all the to-be-written bytes of `layout`.
     pub(super) fn verify(
         &mut self,
@@ -934,6 +997,126 @@ impl<O: Operation> ActiveFlasher<'_, O> {
     }
 }
 
+impl ActiveFlasher<'_, Verify> {
+    /// Calculates the CRC-32 of the current contents of `sector`.
+    fn sector_crc(&mut self, sector: &FlashSector) -> Result<u32, FlashError> {
+        let crc = match self.crc_routine_address() {
+            Some(routine) => self.sector_crc_on_target(sector, routine)?,
+            None => {
+                let mut contents = vec![0; sector.size() as usize];
+                self.read_flash(sector.address(), &mut contents)?;
+                crc::crc32(0, &contents)
+            }
+        };
+
+        tracing::debug!(
+            "CRC of sector at address {:#010x}: {:#010x}",
+            sector.address(),
+            crc
+        );
+
+        Ok(crc)
+    }
+
+    /// Returns the address the CRC routine can be loaded to, or `None` if it cannot be used.
+    ///
+    /// If the flash has to be read using the flash algorithm, the first page buffer is
+    /// needed for the data, so the routine is placed in the second one.
+    fn crc_routine_address(&self) -> Option<u64> {
+        if self.instruction_set != InstructionSet::Thumb2 {
+            return None;
+        }
+
+        let buffer = if self.flash_algorithm.pc_read.is_some() {
+            1
+        } else {
+            0
+        };
+        let address = *self.flash_algorithm.page_buffers.get(buffer)?;
+
+        let routine_size = std::mem::size_of_val(&crc::THUMB_ROUTINE) as u32;
+        let fits = self.flash_algorithm.flash_properties.page_size >= routine_size;
+
+        (address % 4 == 0 && fits).then_some(address)
+    }
+
+    fn sector_crc_on_target(
+        &mut self,
+        sector: &FlashSector,
+        routine: u64,
+    ) -> Result<u32, FlashError> {
+        self.core
+            .write_32(routine, &crc::THUMB_ROUTINE)
+            .map_err(FlashError::Core)?;
+
+        let Some(read_flash) = self.flash_algorithm.pc_read else {
+            // The flash is memory mapped, so the routine can read it directly.
+            return self.call_crc_routine(routine, sector.address(), sector.size(), 0);
+        };
+
+        let page_size = u64::from(self.flash_algorithm.flash_properties.page_size);
+        let buffer_address = self.flash_algorithm.page_buffers[0];
+
+        let mut crc = 0;
+        let mut address = sector.address();
+        let end = sector.address() + sector.size();
+        while address < end {
+            let length = page_size.min(end - address);
+
+            let result = self
+                .call_function_and_wait(
+                    &Registers {
+                        pc: into_reg(read_flash)?,
+                        r0: Some(into_reg(address)?),
+                        r1: Some(into_reg(length)?),
+                        r2: Some(into_reg(buffer_address)?),
+                        r3: None,
+                    },
+                    false,
+                    Duration::from_secs(30),
+                )
+                .map_err(|error| FlashError::FlashReadFailed {
+                    source: Box::new(error),
+                })?;
+
+            if result != 0 {
+                return Err(FlashError::FlashReadFailed {
+                    source: Box::new(FlashError::RoutineCallFailed {
+                        name: "read_flash",
+                        error_code: result,
+                    }),
+                });
+            }
+
+            crc = self.call_crc_routine(routine, buffer_address, length, crc)?;
+            address += length;
+        }
+
+        Ok(crc)
+    }
+
+    fn call_crc_routine(
+        &mut self,
+        routine: u64,
+        address: u64,
+        length: u64,
+        crc: u32,
+    ) -> Result<u32, FlashError> {
+        self.call_function_and_wait(
+            &Registers {
+                // Set the Thumb bit, the routine does not contain any ARM instructions.
+                pc: into_reg(routine | 1)?,
+                r0: Some(into_reg(address)?),
+                r1: Some(into_reg(length)?),
+                r2: Some(crc),
+                r3: None,
+            },
+            false,
+            Duration::from_secs(30),
+        )
+    }
+}
+
 impl<'probe> ActiveFlasher<'probe, Erase> {
     pub(super) fn erase_all(&mut self) -> Result<(), FlashError> {
         tracing::debug!("Erasing entire chip.");
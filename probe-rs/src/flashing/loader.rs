@@ -12,15 +12,16 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use super::builder::FlashBuilder;
+use super::srec;
 use super::{
     extract_from_elf, BinOptions, DownloadOptions, FileDownloadError, FlashError, Flasher,
-    IdfOptions,
+    IdfOptions, ProgramSettings,
 };
 use crate::config::DebugSequence;
 use crate::flashing::{FlashLayout, FlashProgress, Format};
 use crate::memory::MemoryInterface;
 use crate::session::Session;
-use crate::Target;
+use crate::{ProtectionLevel, Target};
 
 /// Helper trait for object safety.
 pub trait ImageReader: Read + Seek {}
@@ -47,11 +48,28 @@ impl ImageLoader for Format {
         file: &mut dyn ImageReader,
     ) -> Result<(), FileDownloadError> {
         match self {
-            Format::Bin(options) => BinLoader(options.clone()).load(flash_loader, session, file),
-            Format::Elf => ElfLoader.load(flash_loader, session, file),
-            Format::Hex => HexLoader.load(flash_loader, session, file),
             Format::Idf(options) => IdfLoader(options.clone()).load(flash_loader, session, file),
-            Format::Uf2 => Uf2Loader.load(flash_loader, session, file),
+            _ => self.load_offline(flash_loader, file),
+        }
+    }
+}
+
+impl Format {
+    /// Loads the image without a connection to the target.
+    ///
+    /// This fails for formats which need to inspect the target, like esp-idf images.
+    fn load_offline(
+        &self,
+        flash_loader: &mut FlashLoader,
+        file: &mut dyn ImageReader,
+    ) -> Result<(), FileDownloadError> {
+        match self {
+            Format::Bin(options) => BinLoader(options.clone()).load(flash_loader, file),
+            Format::Elf => ElfLoader.load(flash_loader, file),
+            Format::Hex => HexLoader.load(flash_loader, file),
+            Format::Idf(_) => Err(FileDownloadError::IdfOffline),
+            Format::Uf2 => Uf2Loader.load(flash_loader, file),
+            Format::Srec => SrecLoader.load(flash_loader, file),
         }
     }
 }
@@ -59,11 +77,10 @@ impl ImageLoader for Format {
 /// Reads the data from the binary file and adds it to the loader without splitting it into flash instructions yet.
 struct BinLoader(BinOptions);
 
-impl ImageLoader for BinLoader {
+impl BinLoader {
     fn load(
         &self,
         flash_loader: &mut FlashLoader,
-        _session: &mut Session,
         file: &mut dyn ImageReader,
     ) -> Result<(), FileDownloadError> {
         // Skip the specified bytes.
@@ -87,11 +104,10 @@ impl ImageLoader for BinLoader {
 /// This will validate the ELF file and transform all its data into sections but no flash loader commands yet.
 struct ElfLoader;
 
-impl ImageLoader for ElfLoader {
+impl ElfLoader {
     fn load(
         &self,
         flash_loader: &mut FlashLoader,
-        _session: &mut Session,
         file: &mut dyn ImageReader,
     ) -> Result<(), FileDownloadError> {
         let mut elf_buffer = Vec::new();
@@ -134,11 +150,10 @@ impl ImageLoader for ElfLoader {
 /// This does not create any flash loader instructions yet.
 struct HexLoader;
 
-impl ImageLoader for HexLoader {
+impl HexLoader {
     fn load(
         &self,
         flash_loader: &mut FlashLoader,
-        _session: &mut Session,
         file: &mut dyn ImageReader,
     ) -> Result<(), FileDownloadError> {
         let mut base_address = 0;
@@ -168,15 +183,53 @@ impl ImageLoader for HexLoader {
     }
 }
 
+/// Reads the data records of a Motorola S-record file and adds them as loadable data blocks to the loader.
+/// This does not create any flash loader instructions yet.
+struct SrecLoader;
+
+impl SrecLoader {
+    fn load(
+        &self,
+        flash_loader: &mut FlashLoader,
+        file: &mut dyn ImageReader,
+    ) -> Result<(), FileDownloadError> {
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        for (index, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record =
+                srec::parse_record(line).map_err(|source| FileDownloadError::SrecRead {
+                    line: index + 1,
+                    source,
+                })?;
+
+            match record {
+                Some(srec::Record::Data { address, data }) => {
+                    flash_loader.add_data(address, &data)?;
+                }
+                Some(srec::Record::StartAddress(address)) => {
+                    tracing::debug!("S-record start address: {:#010X}", address);
+                }
+                None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Prepares the data sections that have to be loaded into flash from an UF2 file.
 /// This will validate the UF2 file and transform all its data into sections but no flash loader commands yet.
 struct Uf2Loader;
 
-impl ImageLoader for Uf2Loader {
+impl Uf2Loader {
     fn load(
         &self,
         flash_loader: &mut FlashLoader,
-        _session: &mut Session,
         file: &mut dyn ImageReader,
     ) -> Result<(), FileDownloadError> {
         let mut uf2_buffer = Vec::new();
@@ -294,12 +347,12 @@ impl ImageLoader for IdfLoader {
 /// The flash loader will make sure to select the appropriate flash region for the right data chunks.
 /// Region crossing data chunks are allowed as long as the regions are contiguous.
 pub struct FlashLoader {
-    memory_map: Vec<MemoryRegion>,
-    builder: FlashBuilder,
+    pub(super) memory_map: Vec<MemoryRegion>,
+    pub(super) builder: FlashBuilder,
 
     /// Source of the flash description,
     /// used for diagnostics.
-    source: TargetDescriptionSource,
+    pub(super) source: TargetDescriptionSource,
 }
 
 impl FlashLoader {
@@ -314,7 +367,7 @@ impl FlashLoader {
 
     /// Check the given address range is completely covered by the memory map,
     /// possibly by multiple memory regions.
-    fn check_data_in_memory_map(&mut self, range: Range<u64>) -> Result<(), FlashError> {
+    pub(super) fn check_data_in_memory_map(&mut self, range: Range<u64>) -> Result<(), FlashError> {
         let mut address = range.start;
         while address < range.end {
             match Self::get_region_for_address(&self.memory_map, address) {
@@ -389,12 +442,48 @@ impl FlashLoader {
             }
         }
 
-        format.load(self, session, file)
+        format.load(self, session, file)?;
+
+        Ok(())
+    }
+
+    /// Reads the image according to the file format and adds it to the loader, without a
+    /// connection to the target.
+    ///
+    /// This allows building a [`FlashPlan`](super::FlashPlan) offline. Images in the esp-idf
+    /// format can not be loaded this way.
+    pub fn load_image_offline<T: Read + Seek>(
+        &mut self,
+        file: &mut T,
+        format: Format,
+    ) -> Result<(), FileDownloadError> {
+        format.load_offline(self, file)?;
+
+        Ok(())
     }
 
     /// Verifies data on the device.
-    pub fn verify(&self, session: &mut Session) -> Result<(), FlashError> {
-        let algos = self.prepare_plan(session)?;
+    ///
+    /// The [image fixups](crate::config::ImageFixup) of the target are applied to the data
+    /// before, like it is done by [`FlashLoader::commit`] with the same `options`, so
+    /// [`DownloadOptions::skip_image_fixups`] has to match the one used for flashing.
+    pub fn verify(
+        &self,
+        session: &mut Session,
+        options: &DownloadOptions,
+    ) -> Result<(), FlashError> {
+        let fixups = session.target().image_fixups.clone();
+        if !options.skip_image_fixups && !fixups.is_empty() {
+            return self
+                .with_fixups(&fixups, Some(session))?
+                .verify_image(session);
+        }
+
+        self.verify_image(session)
+    }
+
+    fn verify_image(&self, session: &mut Session) -> Result<(), FlashError> {
+        let algos = self.prepare_plan(session.target())?;
 
         let progress = FlashProgress::new(|_| {});
 
@@ -425,6 +514,8 @@ impl FlashLoader {
     /// Writes all the stored data chunks to flash.
     ///
     /// Requires a session with an attached target that has a known flash algorithm.
+    /// The [image fixups](crate::config::ImageFixup) of the target are applied to the data
+    /// before, unless [`DownloadOptions::skip_image_fixups`] is set.
     pub fn commit(
         &self,
         session: &mut Session,
@@ -432,7 +523,15 @@ impl FlashLoader {
     ) -> Result<(), FlashError> {
         tracing::debug!("Committing FlashLoader!");
 
-        let algos = self.prepare_plan(session)?;
+        let fixups = session.target().image_fixups.clone();
+        if !options.skip_image_fixups && !fixups.is_empty() {
+            let loader = self.with_fixups(&fixups, Some(session))?;
+            options.skip_image_fixups = true;
+            return loader.commit(session, options);
+        }
+
+        let algos = self.prepare_plan(session.target())?;
+        self.check_preserved_ranges(&options.preserve)?;
 
         if options.dry_run {
             tracing::info!("Skipping programming, dry run!");
@@ -446,6 +545,12 @@ impl FlashLoader {
             return Ok(());
         }
 
+        if !options.preserve.is_empty() {
+            let loader = self.with_preserved_data(session, &options)?;
+            options.preserve.clear();
+            return loader.commit(session, options);
+        }
+
         let progress = options
             .progress
             .clone()
@@ -518,10 +623,13 @@ impl FlashLoader {
                 flasher.program(
                     &region,
                     &self.builder,
-                    options.keep_unwritten_bytes,
-                    do_use_double_buffering,
-                    options.skip_erase || did_chip_erase,
-                    options.verify,
+                    ProgramSettings {
+                        restore_unwritten_bytes: options.keep_unwritten_bytes,
+                        enable_double_buffering: do_use_double_buffering,
+                        skip_erasing: options.skip_erase || did_chip_erase,
+                        skip_unchanged_sectors: options.skip_unchanged_sectors,
+                        verify: options.verify,
+                    },
                 )?;
             }
         }
@@ -583,12 +691,111 @@ impl FlashLoader {
             self.verify_ram(session)?;
         }
 
+        if options.lock {
+            tracing::info!("Protecting the device");
+            session
+                .set_protection(ProtectionLevel::Protected)
+                .map_err(FlashError::Protection)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the staged data does not overwrite any of the `preserved` ranges.
+    pub(super) fn check_preserved_ranges(
+        &self,
+        preserved: &[Range<u64>],
+    ) -> Result<(), FlashError> {
+        for range in preserved {
+            if let Some((address, _)) = self.builder.data_in_range(range).next() {
+                return Err(FlashError::PreservedRangeOverwritten {
+                    preserved: range.clone(),
+                    address,
+                });
+            }
+        }
+
         Ok(())
     }
 
-    fn prepare_plan(
+    /// Returns a copy of this loader, which additionally contains the current contents of the
+    /// preserved ranges that are erased while flashing.
+    fn with_preserved_data(
         &self,
         session: &mut Session,
+        options: &DownloadOptions,
+    ) -> Result<FlashLoader, FlashError> {
+        let mut loader = FlashLoader {
+            memory_map: self.memory_map.clone(),
+            builder: self.builder.clone(),
+            source: self.source.clone(),
+        };
+        if options.skip_erase {
+            return Ok(loader);
+        }
+
+        let plan = self.plan(session.target(), options)?;
+        let erased = if plan.chip_erase {
+            self.memory_map
+                .iter()
+                .filter_map(MemoryRegion::as_nvm_region)
+                .filter(|region| !region.is_alias)
+                .map(|region| region.range.clone())
+                .collect::<Vec<_>>()
+        } else {
+            plan.regions
+                .iter()
+                .flat_map(|region| region.erase.iter().cloned())
+                .collect()
+        };
+
+        loader.add_preserved_data(session, &options.preserve, &erased)?;
+
+        Ok(loader)
+    }
+
+    /// Reads the parts of the `preserved` ranges which are `erased` from the device, and stages
+    /// them to be programmed again.
+    ///
+    /// The erased ranges have to be part of NVM regions.
+    pub(super) fn add_preserved_data(
+        &mut self,
+        session: &mut Session,
+        preserved: &[Range<u64>],
+        erased: &[Range<u64>],
+    ) -> Result<(), FlashError> {
+        for preserved in preserved {
+            for erased in erased {
+                let range = preserved.start.max(erased.start)..preserved.end.min(erased.end);
+                if range.is_empty() {
+                    continue;
+                }
+
+                // This can't fail, the erased ranges are part of NVM regions.
+                let region = Self::get_region_for_address(&self.memory_map, range.start)
+                    .and_then(MemoryRegion::as_nvm_region)
+                    .unwrap();
+                let core_name = region
+                    .cores
+                    .first()
+                    .ok_or_else(|| FlashError::NoNvmCoreAccess(region.clone()))?;
+                let core_index = session.target().core_index_by_name(core_name).unwrap();
+
+                tracing::info!("Preserving the contents of {:#010X?}", range);
+                let mut data = vec![0; (range.end - range.start) as usize];
+                let mut core = session.core(core_index).map_err(FlashError::Core)?;
+                core.read(range.start, &mut data)
+                    .map_err(FlashError::Core)?;
+                self.builder.add_data(range.start, &data)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn prepare_plan(
+        &self,
+        target: &Target,
     ) -> Result<HashMap<(String, usize), Vec<NvmRegion>>, FlashError> {
         tracing::debug!("Contents of builder:");
         for (&address, data) in &self.builder.data {
@@ -601,7 +808,7 @@ impl FlashLoader {
         }
 
         tracing::debug!("Flash algorithms:");
-        for algorithm in &session.target().flash_algorithms {
+        for algorithm in &target.flash_algorithms {
             let Range { start, end } = algorithm.flash_properties.address_range;
 
             tracing::debug!(
@@ -615,7 +822,7 @@ impl FlashLoader {
 
         // Iterate over all memory regions, and program their data.
 
-        if self.memory_map != session.target().memory_map {
+        if self.memory_map != target.memory_map {
             tracing::warn!("Memory map of flash loader does not match memory map of target!");
         }
 
@@ -651,7 +858,6 @@ impl FlashLoader {
                 continue;
             }
 
-            let target = session.target();
             let algo = Self::get_flash_algorithm_for_region(region, target)?;
             let core_name = region
                 .cores
@@ -23,6 +23,21 @@
 //! # Ok::<(), anyhow::Error>(())
 //! ```
 //!
+//! ### Flashing multiple images
+//!
+//! Multiple images can be described by a [`FlashBundle`] manifest, and are flashed in one go:
+//!
+//! ```no_run
+//! use probe_rs::{Session, flashing::{self, DownloadOptions, FlashBundle}, Permissions};
+//!
+//! let mut session = Session::auto_attach("nrf51822", Permissions::default())?;
+//!
+//! let bundle = FlashBundle::from_path("bundle.toml")?;
+//! flashing::download_bundle_with_options(&mut session, &bundle, DownloadOptions::default())?;
+//!
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
 //! ### Adding data manually
 //!
 //! ```no_run
@@ -44,24 +59,32 @@
 //!
 
 mod builder;
+mod bundle;
+mod crc;
 mod download;
 mod encoder;
 mod erase;
 mod error;
+mod fixup;
 mod flash_algorithm;
 mod flasher;
 mod loader;
+mod plan;
 mod progress;
+mod srec;
 mod visualizer;
 
 use builder::*;
 use flasher::*;
 
 pub use builder::{FlashDataBlockSpan, FlashFill, FlashLayout, FlashPage, FlashSector};
+pub use bundle::{BundleImage, FlashBundle};
 pub use download::*;
 pub use erase::*;
 pub use error::*;
 pub use flash_algorithm::*;
 pub use loader::*;
+pub use plan::{FlashPlan, PlanWarning, RegionPlan};
 pub use progress::*;
+pub use srec::SrecError;
 pub use visualizer::*;
@@ -0,0 +1,297 @@
+//! Flashing plans, which describe what a download does without connecting to the target.
+
+use std::{ops::Range, time::Duration};
+
+use serde::{Serialize, Serializer};
+
+use super::{DownloadOptions, FlashAlgorithm, FlashError, FlashLayout, FlashLoader};
+use crate::{config::MemoryRegion, Target};
+
+/// A description of everything a download does, built from the staged data and the target
+/// description alone.
+///
+/// Use [`FlashLoader::plan`] to create a plan.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashPlan {
+    /// The name of the target.
+    pub target: String,
+    /// Whether the whole chip is erased before programming.
+    pub chip_erase: bool,
+    /// Whether bytes which are erased but not covered by the image are restored.
+    pub restore_unwritten: bool,
+    /// The NVM regions which are programmed, in ascending address order.
+    pub regions: Vec<RegionPlan>,
+    /// The address ranges which are written to RAM.
+    pub ram: Vec<Range<u64>>,
+    /// An upper bound for the time spent erasing and programming, based on the timeouts of
+    /// the flash algorithms.
+    #[serde(serialize_with = "serialize_secs")]
+    pub estimated_time: Duration,
+    /// Things which are likely not intended.
+    pub warnings: Vec<PlanWarning>,
+}
+
+/// The part of a [`FlashPlan`] which concerns a single NVM region.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionPlan {
+    /// The name of the region.
+    pub name: Option<String>,
+    /// The address range of the region.
+    pub range: Range<u64>,
+    /// The name of the flash algorithm used to program the region.
+    pub algorithm: String,
+    /// The name of the core which runs the flash algorithm.
+    pub core: String,
+    /// The address ranges which are erased.
+    pub erase: Vec<Range<u64>>,
+    /// The address ranges which are programmed.
+    pub program: Vec<Range<u64>>,
+    /// The address ranges in programmed pages which are not covered by the image.
+    ///
+    /// These bytes are set to `erased_byte_value`, or restored to their previous contents if
+    /// [`FlashPlan::restore_unwritten`] is set.
+    pub fill: Vec<Range<u64>>,
+    /// The value of erased bytes.
+    pub erased_byte_value: u8,
+    /// The number of bytes of the image in this region.
+    pub data_size: u64,
+    /// The layout of the region, which can be used to visualize the plan.
+    #[serde(skip)]
+    pub layout: FlashLayout,
+}
+
+/// A problem found while building a [`FlashPlan`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, docsplay::Display)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PlanWarning {
+    /// Data at {range:#010x?} is written to RAM and is lost after a reset.
+    DataInRam {
+        /// The address range of the data.
+        range: Range<u64>,
+    },
+    /// Data at {range:#010x?} is written to the boot region {region}.
+    BootRegion {
+        /// The name of the boot region.
+        region: String,
+        /// The address range of the data.
+        range: Range<u64>,
+    },
+    /// The flash algorithm {algorithm} does not support erasing the whole chip, its sectors are erased individually.
+    ChipEraseUnsupported {
+        /// The name of the flash algorithm.
+        algorithm: String,
+    },
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+impl FlashLoader {
+    /// Builds a plan of the erase and program operations a download with `options` performs,
+    /// without connecting to the target.
+    ///
+    /// The options which only affect the communication with the target, like `verify`, are
+    /// ignored. The [image fixups](crate::config::ImageFixup) of the target are applied like
+    /// they are when flashing, but without checking the data which is already on the device.
+    pub fn plan(
+        &self,
+        target: &Target,
+        options: &DownloadOptions,
+    ) -> Result<FlashPlan, FlashError> {
+        if !options.skip_image_fixups && !target.image_fixups.is_empty() {
+            return self
+                .with_fixups(&target.image_fixups, None)?
+                .plan_image(target, options);
+        }
+
+        self.plan_image(target, options)
+    }
+
+    fn plan_image(
+        &self,
+        target: &Target,
+        options: &DownloadOptions,
+    ) -> Result<FlashPlan, FlashError> {
+        let mut plan = FlashPlan {
+            target: target.name.clone(),
+            chip_erase: options.do_chip_erase,
+            restore_unwritten: options.keep_unwritten_bytes,
+            regions: vec![],
+            ram: vec![],
+            estimated_time: Duration::ZERO,
+            warnings: vec![],
+        };
+        self.check_preserved_ranges(&options.preserve)?;
+
+        let mut algos = self.prepare_plan(target)?.into_iter().collect::<Vec<_>>();
+        algos.sort_by_key(|(_, regions)| regions.first().map(|region| region.range.start));
+
+        for ((algo_name, core), regions) in algos {
+            // This can't fail, algo_name comes from the target.
+            let raw = target.flash_algorithm_by_name(&algo_name).unwrap();
+            let core_name = &target.cores[core].name;
+            let algo = FlashAlgorithm::assemble_from_raw_with_core(raw, core_name, target)?;
+
+            // Chip erase is done with the first flash algorithm, see `FlashLoader::commit`.
+            if plan.chip_erase
+                && plan.regions.is_empty()
+                && !target.has_sequence_erase_all()
+                && algo.pc_erase_all.is_none()
+            {
+                plan.chip_erase = false;
+                plan.warnings.push(PlanWarning::ChipEraseUnsupported {
+                    algorithm: algo_name.clone(),
+                });
+            }
+
+            for region in regions {
+                let layout = self.builder.build_sectors_and_pages(
+                    &region,
+                    &algo,
+                    options.keep_unwritten_bytes,
+                )?;
+
+                if !plan.chip_erase {
+                    plan.estimated_time += Duration::from_millis(
+                        layout.sectors().len() as u64
+                            * algo.flash_properties.erase_sector_timeout as u64,
+                    );
+                }
+                plan.estimated_time += Duration::from_millis(
+                    layout.pages().len() as u64 * algo.flash_properties.program_page_timeout as u64,
+                );
+
+                let data = self.data_ranges(&region.range);
+                if let (true, Some(first), Some(last)) =
+                    (region.is_boot_memory(), data.first(), data.last())
+                {
+                    plan.warnings.push(PlanWarning::BootRegion {
+                        region: region.name.clone().unwrap_or_else(|| "NVM".to_string()),
+                        range: first.start..last.end,
+                    });
+                }
+
+                plan.regions.push(RegionPlan {
+                    name: region.name.clone(),
+                    range: region.range.clone(),
+                    algorithm: algo_name.clone(),
+                    core: core_name.clone(),
+                    erase: merge_ranges(
+                        layout
+                            .sectors()
+                            .iter()
+                            .map(|sector| sector.address()..sector.address() + sector.size()),
+                    ),
+                    program: merge_ranges(
+                        layout
+                            .pages()
+                            .iter()
+                            .map(|page| page.address()..page.address() + page.size() as u64),
+                    ),
+                    fill: merge_ranges(
+                        layout
+                            .fills()
+                            .iter()
+                            .map(|fill| fill.address()..fill.address() + fill.size()),
+                    ),
+                    erased_byte_value: algo.flash_properties.erased_byte_value,
+                    data_size: data.iter().map(|range| range.end - range.start).sum(),
+                    layout,
+                });
+            }
+        }
+
+        plan.regions.sort_by_key(|region| region.range.start);
+
+        for region in self
+            .memory_map
+            .iter()
+            .filter_map(MemoryRegion::as_ram_region)
+        {
+            let data = self.data_ranges(&region.range);
+            plan.warnings
+                .extend(data.iter().map(|range| PlanWarning::DataInRam {
+                    range: range.clone(),
+                }));
+            plan.ram.extend(data);
+        }
+
+        Ok(plan)
+    }
+
+    /// Returns the ranges of the staged data in `range`, with adjacent chunks merged.
+    fn data_ranges(&self, range: &Range<u64>) -> Vec<Range<u64>> {
+        merge_ranges(
+            self.builder
+                .data_in_range(range)
+                .map(|(address, data)| address..address + data.len() as u64),
+        )
+    }
+}
+
+/// Merges adjacent and overlapping ranges. The ranges must be sorted by their start address.
+fn merge_ranges(ranges: impl Iterator<Item = Range<u64>>) -> Vec<Range<u64>> {
+    let mut merged: Vec<Range<u64>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_adjacent_ranges() {
+        let ranges = [0..4, 4..8, 16..32, 20..24, 32..40];
+        assert_eq!(merge_ranges(ranges.into_iter()), vec![0..8, 16..40]);
+    }
+
+    #[test]
+    fn plan_without_session() {
+        let target = crate::config::get_target_by_name("nrf52840_xxAA").unwrap();
+        let mut loader = target.flash_loader();
+        loader.add_data(0x1000, &[0xAA; 0x1800]).unwrap();
+        loader.add_data(0x2000_0000, &[0x55; 16]).unwrap();
+
+        let plan = loader.plan(&target, &DownloadOptions::default()).unwrap();
+
+        assert_eq!(plan.regions.len(), 1);
+        let region = &plan.regions[0];
+        assert_eq!(region.erase, vec![0x1000..0x3000]);
+        assert_eq!(region.data_size, 0x1800);
+        assert!(region.program[0].start <= 0x1000 && region.program[0].end >= 0x2800);
+        assert_eq!(plan.ram, vec![0x2000_0000..0x2000_0010]);
+        assert!(plan.warnings.contains(&PlanWarning::DataInRam {
+            range: 0x2000_0000..0x2000_0010
+        }));
+        assert!(plan.estimated_time > Duration::ZERO);
+    }
+
+    #[test]
+    fn plan_rejects_overwriting_preserved_ranges() {
+        let target = crate::config::get_target_by_name("nrf52840_xxAA").unwrap();
+        let mut loader = target.flash_loader();
+        loader.add_data(0x1000, &[0xAA; 0x100]).unwrap();
+
+        let mut options = DownloadOptions {
+            preserve: vec![0x0..0x100, 0x1100..0x1200],
+            ..Default::default()
+        };
+        assert!(loader.plan(&target, &options).is_ok());
+
+        options.preserve = vec![0x1100..0x1200, 0x10F0..0x1100];
+        assert!(matches!(
+            loader.plan(&target, &options),
+            Err(FlashError::PreservedRangeOverwritten {
+                address: 0x10F0,
+                ..
+            })
+        ));
+    }
+}
@@ -78,6 +78,11 @@ impl FlashProgress {
         self.emit(ProgressEvent::SectorErased { size, time });
     }
 
+    /// Signalize that a sector already contains the data to be flashed and will not be erased.
+    pub(super) fn sector_skipped(&self, address: u64, size: u64) {
+        self.emit(ProgressEvent::SectorSkipped { address, size });
+    }
+
     /// Signalize that the page filling procedure has made progress.
     pub(super) fn page_filled(&self, size: u64, time: Duration) {
         self.emit(ProgressEvent::PageFilled { size, time });
@@ -127,6 +132,7 @@ impl FlashProgress {
 /// * `StartedFilling`
 /// * `PageFilled` for every page
 /// * `FinishedFilling`
+/// * `SectorSkipped` for every unchanged sector, if enabled
 /// * `StartedErasing`
 /// * `SectorErased` for every sector
 /// * `FinishedErasing`
@@ -177,6 +183,16 @@ pub enum ProgressEvent {
         /// The time it took to erase this sector.
         time: Duration,
     },
+    /// A sector already contains the data to be flashed, so it is neither erased nor programmed.
+    ///
+    /// This is only reported if [`DownloadOptions::skip_unchanged_sectors`](super::DownloadOptions::skip_unchanged_sectors)
+    /// is enabled.
+    SectorSkipped {
+        /// The start address of the sector.
+        address: u64,
+        /// The size of the sector in bytes.
+        size: u64,
+    },
     /// Erasing of the flash has failed.
     FailedErasing,
     /// Erasing of the flash has finished successfully.
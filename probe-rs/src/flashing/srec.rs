@@ -0,0 +1,182 @@
+//! Parser for the [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format.
+
+/// A record of an S-record file which is relevant for flashing.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Record {
+    /// Data which is placed at the given address (S1, S2 and S3 records).
+    Data { address: u64, data: Vec<u8> },
+    /// The start address of the program (S7, S8 and S9 records).
+    StartAddress(u64),
+}
+
+/// An error which occurred while decoding a record of an S-record file.
+#[derive(Debug, thiserror::Error, docsplay::Display, PartialEq, Eq)]
+pub enum SrecError {
+    /// The record does not start with 'S'.
+    MissingStartCode,
+
+    /// The record type 'S{0}' is not supported.
+    UnsupportedRecordType(char),
+
+    /// The record contains characters which are not hexadecimal digits.
+    InvalidHexCharacter,
+
+    /// The record contains {actual} bytes after the byte count, but the byte count is {expected}.
+    ByteCountMismatch {
+        /// The byte count of the record.
+        expected: usize,
+        /// The number of bytes following the byte count.
+        actual: usize,
+    },
+
+    /// The record is too short to contain an address and a checksum.
+    RecordTooShort,
+
+    /// The checksum of the record is {actual:#04x}, but the record data adds up to {expected:#04x}.
+    ChecksumMismatch {
+        /// The checksum calculated from the record.
+        expected: u8,
+        /// The checksum stored in the record.
+        actual: u8,
+    },
+}
+
+/// Decodes a single line of an S-record file.
+///
+/// Returns `None` for records which don't contain anything relevant for flashing,
+/// i.e. the header (S0) and record count (S5 and S6) records.
+pub(crate) fn parse_record(line: &str) -> Result<Option<Record>, SrecError> {
+    let line = line.trim();
+    let record = line.strip_prefix('S').ok_or(SrecError::MissingStartCode)?;
+
+    let mut characters = record.chars();
+    let record_type = characters.next().ok_or(SrecError::RecordTooShort)?;
+    let address_size = match record_type {
+        '0' | '1' | '5' | '9' => 2,
+        '2' | '6' | '8' => 3,
+        '3' | '7' => 4,
+        other => return Err(SrecError::UnsupportedRecordType(other)),
+    };
+
+    let bytes = decode_hex(characters.as_str())?;
+    let (&byte_count, rest) = bytes.split_first().ok_or(SrecError::RecordTooShort)?;
+    if rest.len() != usize::from(byte_count) {
+        return Err(SrecError::ByteCountMismatch {
+            expected: usize::from(byte_count),
+            actual: rest.len(),
+        });
+    }
+    if rest.len() < address_size + 1 {
+        return Err(SrecError::RecordTooShort);
+    }
+
+    // The checksum is the one's complement of the sum of all bytes from the byte count
+    // to the end of the data.
+    let (&actual, checked) = bytes.split_last().ok_or(SrecError::RecordTooShort)?;
+    let expected = !checked
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if actual != expected {
+        return Err(SrecError::ChecksumMismatch { expected, actual });
+    }
+
+    let (address, data) = rest[..rest.len() - 1].split_at(address_size);
+    let address = address
+        .iter()
+        .fold(0u64, |address, byte| (address << 8) | u64::from(*byte));
+
+    Ok(match record_type {
+        '1' | '2' | '3' => Some(Record::Data {
+            address,
+            data: data.to_vec(),
+        }),
+        '7' | '8' | '9' => Some(Record::StartAddress(address)),
+        _ => None,
+    })
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, SrecError> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(SrecError::InvalidHexCharacter);
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| SrecError::InvalidHexCharacter))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_records() {
+        assert_eq!(
+            parse_record("S1137AF00A0A0D0000000000000000000000000061"),
+            Ok(Some(Record::Data {
+                address: 0x7AF0,
+                data: vec![0x0A, 0x0A, 0x0D, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            }))
+        );
+        assert_eq!(
+            parse_record("S20808000001020304E5"),
+            Ok(Some(Record::Data {
+                address: 0x08_0000,
+                data: vec![1, 2, 3, 4],
+            }))
+        );
+        assert_eq!(
+            parse_record("S30908000000DEADBEEFB6\r\n"),
+            Ok(Some(Record::Data {
+                address: 0x0800_0000,
+                data: vec![0xDE, 0xAD, 0xBE, 0xEF],
+            }))
+        );
+    }
+
+    #[test]
+    fn other_records() {
+        assert_eq!(parse_record("S00F000068656C6C6F202020202000003C"), Ok(None));
+        assert_eq!(parse_record("S5030003F9"), Ok(None));
+        assert_eq!(
+            parse_record("S70508000101F0"),
+            Ok(Some(Record::StartAddress(0x0800_0101)))
+        );
+        assert_eq!(
+            parse_record("S9030000FC"),
+            Ok(Some(Record::StartAddress(0)))
+        );
+    }
+
+    #[test]
+    fn invalid_records() {
+        assert_eq!(
+            parse_record(":1000000000"),
+            Err(SrecError::MissingStartCode)
+        );
+        assert_eq!(
+            parse_record("S4030000FC"),
+            Err(SrecError::UnsupportedRecordType('4'))
+        );
+        assert_eq!(
+            parse_record("S1030000FG"),
+            Err(SrecError::InvalidHexCharacter)
+        );
+        assert_eq!(
+            parse_record("S1040000FC"),
+            Err(SrecError::ByteCountMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(parse_record("S10200FD"), Err(SrecError::RecordTooShort));
+        assert_eq!(
+            parse_record("S2080800000102030400"),
+            Err(SrecError::ChecksumMismatch {
+                expected: 0xE5,
+                actual: 0x00
+            })
+        );
+    }
+}
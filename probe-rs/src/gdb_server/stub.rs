@@ -1,7 +1,9 @@
+use crate::debug::rtos::Rtos;
 use crate::{CoreType, Session};
 use parking_lot::FairMutex;
 
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
 use std::time::Duration;
 
 use itertools::Itertools;
@@ -18,6 +20,10 @@ pub struct GdbInstanceConfiguration {
     pub cores: Vec<usize>,
     /// The list of [SocketAddr] addresses to bind to
     pub socket_addrs: Vec<SocketAddr>,
+    /// The RTOS used by the program, to expose its threads to GDB.
+    ///
+    /// RTOS threads are only shown if the instance has a single core. See [crate::debug::rtos::detect_rtos()].
+    pub rtos: Option<Arc<dyn Rtos>>,
 }
 
 impl GdbInstanceConfiguration {
@@ -65,6 +71,7 @@ impl GdbInstanceConfiguration {
                 core_type,
                 cores,
                 socket_addrs: adjust_addrs(&addrs, i),
+                rtos: None,
             })
             .collect()
     }
@@ -87,7 +94,12 @@ pub fn run<'a>(
     // Turn our group list into GDB targets
     let mut targets = instances
         .map(|instance| {
-            target::RuntimeTarget::new(session, instance.cores.to_vec(), &instance.socket_addrs[..])
+            target::RuntimeTarget::new(
+                session,
+                instance.cores.to_vec(),
+                &instance.socket_addrs[..],
+                instance.rtos.clone(),
+            )
         })
         .collect::<Result<Vec<_>, _>>()?;
 
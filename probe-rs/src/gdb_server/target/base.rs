@@ -0,0 +1,307 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use gdbstub::common::Tid;
+use gdbstub::target::ext::base::multithread::{MultiThreadBase, MultiThreadResumeOps};
+use gdbstub::target::ext::base::single_register_access::{
+    SingleRegisterAccess, SingleRegisterAccessOps,
+};
+use gdbstub::target::ext::memory_map::MemoryMap;
+use gdbstub::target::ext::target_description_xml_override::TargetDescriptionXmlOverride;
+use gdbstub::target::ext::thread_extra_info::{ThreadExtraInfo, ThreadExtraInfoOps};
+use gdbstub::target::{TargetError, TargetResult};
+use probe_rs_target::MemoryRegion;
+
+use super::{core_tid, thread_tid, RuntimeTarget};
+use crate::gdb_server::arch::{RuntimeRegId, RuntimeRegisters};
+use crate::{Error, MemoryInterface, RegisterId, RegisterValue};
+
+impl RuntimeTarget<'_> {
+    /// Reads the GDB register number `number` of thread `tid`, and appends its value to `buf`.
+    ///
+    /// The registers of RTOS threads which are not running are taken from the context saved by
+    /// the RTOS. Registers which it did not save are reported as zero.
+    fn read_gdb_register(&self, tid: Tid, number: usize, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let register = self
+            .target_desc
+            .register(number)
+            .ok_or_else(|| Error::Other(format!("Unknown GDB register {number}")))?;
+
+        if let Some(saved) = self
+            .rtos_thread(tid)
+            .and_then(|thread| thread.registers.as_ref())
+        {
+            return register.read(
+                |id| {
+                    Ok(saved
+                        .get_register(id)
+                        .and_then(|r| r.value)
+                        .unwrap_or_default())
+                },
+                buf,
+            );
+        }
+
+        let mut session = self.session.lock();
+        let mut core = session.core(self.core_id(tid))?;
+
+        register.read(
+            |id: RegisterId| core.read_core_reg::<RegisterValue>(id),
+            buf,
+        )
+    }
+
+    /// Writes the GDB register number `number` of thread `tid`.
+    fn write_gdb_register(&self, tid: Tid, number: usize, value: &[u8]) -> Result<(), Error> {
+        let register = self
+            .target_desc
+            .register(number)
+            .ok_or_else(|| Error::Other(format!("Unknown GDB register {number}")))?;
+
+        if self
+            .rtos_thread(tid)
+            .is_some_and(|thread| thread.registers.is_some())
+        {
+            return Err(Error::Other(
+                "The registers of RTOS threads which are not running cannot be modified".into(),
+            ));
+        }
+
+        let mut session = self.session.lock();
+        let mut core = session.core(self.core_id(tid))?;
+
+        register.write(&mut core, value)
+    }
+}
+
+impl MultiThreadBase for RuntimeTarget<'_> {
+    fn read_registers(&mut self, regs: &mut RuntimeRegisters, tid: Tid) -> TargetResult<(), Self> {
+        regs.regs.clear();
+
+        for number in 0..self.target_desc.registers().count() {
+            self.read_gdb_register(tid, number, &mut regs.regs)
+                .map_err(TargetError::Fatal)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_registers(&mut self, regs: &RuntimeRegisters, tid: Tid) -> TargetResult<(), Self> {
+        let mut offset = 0;
+
+        for (number, register) in self.target_desc.registers().enumerate() {
+            let size = register.size_in_bytes();
+            let Some(value) = regs.regs.get(offset..offset + size) else {
+                break;
+            };
+
+            self.write_gdb_register(tid, number, value)
+                .map_err(TargetError::Fatal)?;
+
+            offset += size;
+        }
+
+        Ok(())
+    }
+
+    fn support_single_register_access(&mut self) -> Option<SingleRegisterAccessOps<'_, Tid, Self>> {
+        Some(self)
+    }
+
+    fn read_addrs(
+        &mut self,
+        start_addr: u64,
+        data: &mut [u8],
+        tid: Tid,
+    ) -> TargetResult<usize, Self> {
+        let mut session = self.session.lock();
+        let mut core = session
+            .core(self.core_id(tid))
+            .map_err(TargetError::Fatal)?;
+
+        core.read(start_addr, data).map_err(|error| {
+            tracing::debug!("Could not read memory at {:#010x}: {}", start_addr, error);
+            TargetError::NonFatal
+        })?;
+
+        Ok(data.len())
+    }
+
+    fn write_addrs(&mut self, start_addr: u64, data: &[u8], tid: Tid) -> TargetResult<(), Self> {
+        let mut session = self.session.lock();
+        let mut core = session
+            .core(self.core_id(tid))
+            .map_err(TargetError::Fatal)?;
+
+        core.write(start_addr, data).map_err(|error| {
+            tracing::debug!("Could not write memory at {:#010x}: {}", start_addr, error);
+            TargetError::NonFatal
+        })
+    }
+
+    fn list_active_threads(
+        &mut self,
+        thread_is_active: &mut dyn FnMut(Tid),
+    ) -> Result<(), Self::Error> {
+        let threads = self
+            .threads
+            .iter()
+            .filter_map(thread_tid)
+            .collect::<Vec<_>>();
+
+        if threads.is_empty() {
+            for &core_id in &self.cores {
+                thread_is_active(core_tid(core_id));
+            }
+        } else {
+            for tid in threads {
+                thread_is_active(tid);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn support_resume(&mut self) -> Option<MultiThreadResumeOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_thread_extra_info(&mut self) -> Option<ThreadExtraInfoOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleRegisterAccess<Tid> for RuntimeTarget<'_> {
+    fn read_register(
+        &mut self,
+        tid: Tid,
+        reg_id: RuntimeRegId,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let mut value = Vec::new();
+        self.read_gdb_register(tid, reg_id.into(), &mut value)
+            .map_err(|error| {
+                tracing::debug!("Could not read register: {}", error);
+                TargetError::NonFatal
+            })?;
+
+        let len = value.len().min(buf.len());
+        buf[..len].copy_from_slice(&value[..len]);
+
+        Ok(len)
+    }
+
+    fn write_register(
+        &mut self,
+        tid: Tid,
+        reg_id: RuntimeRegId,
+        val: &[u8],
+    ) -> TargetResult<(), Self> {
+        self.write_gdb_register(tid, reg_id.into(), val)
+            .map_err(|error| {
+                tracing::debug!("Could not write register: {}", error);
+                TargetError::NonFatal
+            })
+    }
+}
+
+impl ThreadExtraInfo for RuntimeTarget<'_> {
+    fn thread_extra_info(&self, tid: Tid, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let thread = self.rtos_thread(tid);
+        let mut info = match thread {
+            Some(thread) => format!("{} ({})", thread.name, thread.state),
+            None => format!("Core {}", self.core_id(tid)),
+        };
+
+        // The security state is only known for the code which currently runs on the core.
+        if thread.map_or(true, |thread| thread.is_running()) {
+            let mut session = self.session.lock();
+            let state = session.core(self.core_id(tid))?.security_state()?;
+            if let Some(state) = state {
+                let _ = write!(info, ", {state}");
+            }
+        }
+
+        Ok(super::copy_range_to_buf(
+            info.as_bytes(),
+            0,
+            info.len(),
+            buf,
+        ))
+    }
+}
+
+impl TargetDescriptionXmlOverride for RuntimeTarget<'_> {
+    fn target_description_xml(
+        &self,
+        annex: &[u8],
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        if annex != b"target.xml" {
+            return Err(TargetError::NonFatal);
+        }
+
+        let xml = self.target_desc.to_xml();
+
+        Ok(super::copy_range_to_buf(
+            xml.as_bytes(),
+            offset,
+            length,
+            buf,
+        ))
+    }
+}
+
+impl MemoryMap for RuntimeTarget<'_> {
+    fn memory_map_xml(
+        &self,
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let mut xml = String::from(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE memory-map PUBLIC "+//IDN gnu.org//DTD GDB Memory Map V1.0//EN" "http://sourceware.org/gdb/gdb-memory-map.dtd">
+<memory-map>
+"#,
+        );
+
+        let session = self.session.lock();
+        let mut reported: Vec<&Range<u64>> = Vec::new();
+        for region in &session.target().memory_map {
+            // Flash is reported as read-only memory, because the GDB server cannot program it.
+            let (kind, range) = match region {
+                MemoryRegion::Ram(region) => ("ram", &region.range),
+                MemoryRegion::Generic(region) => ("ram", &region.range),
+                MemoryRegion::Nvm(region) => ("rom", &region.range),
+            };
+
+            // GDB rejects overlapping regions, which exist on targets with aliased memory.
+            if reported
+                .iter()
+                .any(|r| r.start < range.end && range.start < r.end)
+            {
+                continue;
+            }
+            reported.push(range);
+
+            let _ = writeln!(
+                xml,
+                r#"<memory type="{}" start="{:#x}" length="{:#x}"/>"#,
+                kind,
+                range.start,
+                range.end - range.start
+            );
+        }
+        xml.push_str("</memory-map>");
+
+        Ok(super::copy_range_to_buf(
+            xml.as_bytes(),
+            offset,
+            length,
+            buf,
+        ))
+    }
+}
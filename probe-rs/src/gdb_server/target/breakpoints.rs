@@ -0,0 +1,116 @@
+use gdbstub::target::ext::breakpoints::{
+    Breakpoints, HwBreakpoint, HwBreakpointOps, HwWatchpoint, HwWatchpointOps, WatchKind,
+};
+use gdbstub::target::{TargetError, TargetResult};
+
+use super::RuntimeTarget;
+use crate::WatchpointKind;
+
+impl Breakpoints for RuntimeTarget<'_> {
+    fn support_hw_breakpoint(&mut self) -> Option<HwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_hw_watchpoint(&mut self) -> Option<HwWatchpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl HwBreakpoint for RuntimeTarget<'_> {
+    fn add_hw_breakpoint(&mut self, addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock();
+
+        for &core_id in &self.cores {
+            let mut core = session.core(core_id).map_err(TargetError::Fatal)?;
+
+            if let Err(error) = core.set_hw_breakpoint(addr) {
+                tracing::warn!("Could not set a breakpoint at {:#010x}: {}", addr, error);
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_breakpoint(&mut self, addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock();
+
+        for &core_id in &self.cores {
+            let mut core = session.core(core_id).map_err(TargetError::Fatal)?;
+
+            if let Err(error) = core.clear_hw_breakpoint(addr) {
+                tracing::warn!(
+                    "Could not clear the breakpoint at {:#010x}: {}",
+                    addr,
+                    error
+                );
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl HwWatchpoint for RuntimeTarget<'_> {
+    fn add_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        len: u64,
+        kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock();
+
+        for &core_id in &self.cores {
+            let mut core = session.core(core_id).map_err(TargetError::Fatal)?;
+
+            if let Err(error) = core.set_hw_watchpoint(addr, len, watchpoint_kind(kind)) {
+                tracing::warn!("Could not set a watchpoint at {:#010x}: {}", addr, error);
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        _len: u64,
+        kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock();
+
+        for &core_id in &self.cores {
+            let mut core = session.core(core_id).map_err(TargetError::Fatal)?;
+
+            if let Err(error) = core.clear_hw_watchpoint(addr, watchpoint_kind(kind)) {
+                tracing::warn!(
+                    "Could not clear the watchpoint at {:#010x}: {}",
+                    addr,
+                    error
+                );
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+fn watchpoint_kind(kind: WatchKind) -> WatchpointKind {
+    match kind {
+        WatchKind::Write => WatchpointKind::Write,
+        WatchKind::Read => WatchpointKind::Read,
+        WatchKind::ReadWrite => WatchpointKind::Access,
+    }
+}
+
+/// Converts the kind of a watchpoint which halted the core into the kind reported to GDB.
+pub(super) fn watch_kind(kind: WatchpointKind) -> WatchKind {
+    match kind {
+        WatchpointKind::Write => WatchKind::Write,
+        WatchpointKind::Read => WatchKind::Read,
+        WatchpointKind::Access => WatchKind::ReadWrite,
+    }
+}
@@ -0,0 +1,376 @@
+use std::fmt::Write;
+
+use crate::architecture::arm::core::registers::cortex_m::{EXTRA_NS, EXTRA_S};
+use crate::core::RegisterDataType;
+use crate::{Core, CoreRegister, CoreType, Error, RegisterId, RegisterRole, RegisterValue};
+
+/// The location of the value of a register sent to GDB.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum GdbRegisterSource {
+    /// The value is the value of a core register.
+    Core(RegisterId),
+    /// The value is a single byte of a core register.
+    ///
+    /// On Cortex-M, CONTROL, FAULTMASK, BASEPRI and PRIMASK are all read through one register.
+    Byte(RegisterId, u8),
+    /// The value is made up of two 32-bit core registers, the first one being the low word.
+    ///
+    /// Used for the double precision registers of cores which only expose single precision registers.
+    Pair(RegisterId, RegisterId),
+}
+
+/// A register, as described to GDB.
+#[derive(Debug, Clone)]
+pub(crate) struct GdbRegister {
+    name: String,
+    size_in_bits: usize,
+    ty: &'static str,
+    source: GdbRegisterSource,
+}
+
+impl GdbRegister {
+    fn new(
+        name: impl Into<String>,
+        size_in_bits: usize,
+        ty: &'static str,
+        source: GdbRegisterSource,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            size_in_bits,
+            ty,
+            source,
+        }
+    }
+
+    /// The size of the register in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.size_in_bits.div_ceil(8)
+    }
+
+    /// Reads the register, using `read_core_reg` to read the core registers it is made of, and
+    /// appends its value to `buf` in little endian byte order.
+    pub fn read(
+        &self,
+        mut read_core_reg: impl FnMut(RegisterId) -> Result<RegisterValue, Error>,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let value = match self.source {
+            GdbRegisterSource::Core(id) => to_u128(read_core_reg(id)?),
+            GdbRegisterSource::Byte(id, byte) => (to_u128(read_core_reg(id)?) >> (8 * byte)) & 0xff,
+            GdbRegisterSource::Pair(low, high) => {
+                to_u128(read_core_reg(low)?) & 0xffff_ffff
+                    | (to_u128(read_core_reg(high)?) & 0xffff_ffff) << 32
+            }
+        };
+
+        buf.extend_from_slice(&value.to_le_bytes()[..self.size_in_bytes().min(16)]);
+
+        Ok(())
+    }
+
+    /// Writes the little endian value in `bytes` to the register of `core`.
+    pub fn write(&self, core: &mut Core, bytes: &[u8]) -> Result<(), Error> {
+        let mut value = [0; 16];
+        let len = bytes.len().min(self.size_in_bytes()).min(16);
+        value[..len].copy_from_slice(&bytes[..len]);
+        let value = u128::from_le_bytes(value);
+
+        match self.source {
+            GdbRegisterSource::Core(id) => {
+                match core.registers().all_registers().find(|r| r.id() == id) {
+                    Some(r) if r.size_in_bits() > 64 => core.write_core_reg(id, value),
+                    Some(r) if r.size_in_bits() > 32 => core.write_core_reg(id, value as u64),
+                    _ => core.write_core_reg(id, value as u32),
+                }
+            }
+            GdbRegisterSource::Byte(id, byte) => {
+                let shift = 8 * u32::from(byte);
+                let current: u32 = core.read_core_reg(id)?;
+                let updated = current & !(0xff << shift) | (value as u32 & 0xff) << shift;
+                core.write_core_reg(id, updated)
+            }
+            GdbRegisterSource::Pair(low, high) => {
+                core.write_core_reg(low, value as u32)?;
+                core.write_core_reg(high, (value >> 32) as u32)
+            }
+        }
+    }
+}
+
+fn to_u128(value: RegisterValue) -> u128 {
+    match value {
+        RegisterValue::U32(v) => u128::from(v),
+        RegisterValue::U64(v) => u128::from(v),
+        RegisterValue::U128(v) => v,
+    }
+}
+
+/// A group of registers, which GDB uses to recognize the capabilities of the target.
+#[derive(Debug, Clone)]
+struct Feature {
+    name: &'static str,
+    registers: Vec<GdbRegister>,
+}
+
+/// The target description sent to GDB.
+///
+/// The registers are numbered in the order of their features, and this numbering is used by all
+/// register accesses.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TargetDescription {
+    architecture: &'static str,
+    features: Vec<Feature>,
+}
+
+impl TargetDescription {
+    /// Creates the description of the registers of `core`.
+    pub fn new(core: &mut Core) -> Result<Self, Error> {
+        let mut desc = match core.core_type() {
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                Self::cortex_m(core)?
+            }
+            CoreType::Armv7a => Self::with_core_registers(
+                core,
+                "arm",
+                "org.gnu.gdb.arm.core",
+                Some("org.gnu.gdb.arm.vfp"),
+            ),
+            CoreType::Armv8a if core.is_64_bit() => Self::with_core_registers(
+                core,
+                "aarch64",
+                "org.gnu.gdb.aarch64.core",
+                Some("org.gnu.gdb.aarch64.fpu"),
+            ),
+            CoreType::Armv8a => Self::with_core_registers(
+                core,
+                "arm",
+                "org.gnu.gdb.arm.core",
+                Some("org.gnu.gdb.arm.vfp"),
+            ),
+            CoreType::Riscv => Self::with_core_registers(
+                core,
+                if core.is_64_bit() {
+                    "riscv:rv64"
+                } else {
+                    "riscv:rv32"
+                },
+                "org.gnu.gdb.riscv.cpu",
+                Some("org.gnu.gdb.riscv.fpu"),
+            ),
+            CoreType::Xtensa => {
+                Self::with_core_registers(core, "xtensa", "org.gnu.gdb.xtensa.core", None)
+            }
+        };
+
+        desc.features
+            .retain(|feature| !feature.registers.is_empty());
+
+        Ok(desc)
+    }
+
+    /// Describes a Cortex-M core, using the register names and features expected by GDB.
+    fn cortex_m(core: &mut Core) -> Result<Self, Error> {
+        use GdbRegisterSource::{Byte, Core as CoreReg, Pair};
+
+        let mut profile = (0..13)
+            .map(|i| GdbRegister::new(format!("r{i}"), 32, "uint32", CoreReg(RegisterId(i))))
+            .collect::<Vec<_>>();
+        profile.extend([
+            GdbRegister::new("sp", 32, "data_ptr", CoreReg(RegisterId(13))),
+            GdbRegister::new("lr", 32, "uint32", CoreReg(RegisterId(14))),
+            GdbRegister::new("pc", 32, "code_ptr", CoreReg(RegisterId(15))),
+            GdbRegister::new("xpsr", 32, "uint32", CoreReg(RegisterId(0b1_0000))),
+        ]);
+
+        let mut features = vec![Feature {
+            name: "org.gnu.gdb.arm.m-profile",
+            registers: profile,
+        }];
+
+        if core.fpu_support()? {
+            let count = core.floating_point_register_count()?;
+
+            // GDB expects the double precision registers, and shows the single
+            // precision registers as pseudo registers on top of them.
+            let mut vfp = (0..count as u16 / 2)
+                .map(|i| {
+                    GdbRegister::new(
+                        format!("d{i}"),
+                        64,
+                        "ieee_double",
+                        Pair(
+                            RegisterId(0b100_0000 + 2 * i),
+                            RegisterId(0b100_0001 + 2 * i),
+                        ),
+                    )
+                })
+                .collect::<Vec<_>>();
+            vfp.push(GdbRegister::new(
+                "fpscr",
+                32,
+                "uint32",
+                CoreReg(RegisterId(0b10_0001)),
+            ));
+
+            features.push(Feature {
+                name: "org.gnu.gdb.arm.vfp",
+                registers: vfp,
+            });
+        }
+
+        let extra = RegisterId(0b1_0100);
+        features.push(Feature {
+            name: "org.gnu.gdb.arm.m-system",
+            registers: vec![
+                GdbRegister::new("msp", 32, "data_ptr", CoreReg(RegisterId(0b1_0001))),
+                GdbRegister::new("psp", 32, "data_ptr", CoreReg(RegisterId(0b1_0010))),
+                GdbRegister::new("primask", 8, "uint8", Byte(extra, 0)),
+                GdbRegister::new("basepri", 8, "uint8", Byte(extra, 1)),
+                GdbRegister::new("faultmask", 8, "uint8", Byte(extra, 2)),
+                GdbRegister::new("control", 8, "uint8", Byte(extra, 3)),
+            ],
+        });
+
+        // The banked registers are only present on cores with the ARMv8-M Security Extension.
+        if core
+            .registers()
+            .all_registers()
+            .any(|r| r.id() == EXTRA_S.id())
+        {
+            let stack_pointers = [
+                "msp_ns",
+                "psp_ns",
+                "msp_s",
+                "psp_s",
+                "msplim_s",
+                "psplim_s",
+                "msplim_ns",
+                "psplim_ns",
+            ];
+
+            let mut secext = (0b1_1000..)
+                .zip(stack_pointers)
+                .map(|(id, name)| GdbRegister::new(name, 32, "data_ptr", CoreReg(RegisterId(id))))
+                .collect::<Vec<_>>();
+
+            for (suffix, extra) in [("s", EXTRA_S.id()), ("ns", EXTRA_NS.id())] {
+                for (byte, name) in ["primask", "basepri", "faultmask", "control"]
+                    .into_iter()
+                    .enumerate()
+                {
+                    secext.push(GdbRegister::new(
+                        format!("{name}_{suffix}"),
+                        8,
+                        "uint8",
+                        Byte(extra, byte as u8),
+                    ));
+                }
+            }
+
+            features.push(Feature {
+                name: "org.gnu.gdb.arm.secext",
+                registers: secext,
+            });
+        }
+
+        Ok(Self {
+            architecture: "arm",
+            features,
+        })
+    }
+
+    /// Describes a core by listing its registers as they are defined by probe-rs.
+    fn with_core_registers(
+        core: &Core,
+        architecture: &'static str,
+        core_feature: &'static str,
+        fpu_feature: Option<&'static str>,
+    ) -> Self {
+        let describe = |register: &CoreRegister| {
+            let ty = if register.register_has_role(RegisterRole::ProgramCounter) {
+                "code_ptr"
+            } else if register.register_has_role(RegisterRole::StackPointer) {
+                "data_ptr"
+            } else {
+                match register.data_type() {
+                    RegisterDataType::FloatingPoint(32) => "ieee_single",
+                    RegisterDataType::FloatingPoint(64) => "ieee_double",
+                    _ => "int",
+                }
+            };
+
+            GdbRegister::new(
+                register.name().to_lowercase(),
+                register.size_in_bits(),
+                ty,
+                GdbRegisterSource::Core(register.id()),
+            )
+        };
+
+        let registers = core.registers();
+        let mut features = vec![Feature {
+            name: core_feature,
+            registers: registers.core_registers().map(describe).collect(),
+        }];
+
+        if let Some(fpu_feature) = fpu_feature {
+            features.push(Feature {
+                name: fpu_feature,
+                registers: registers
+                    .all_registers()
+                    .filter(|r| {
+                        r.register_has_role(RegisterRole::FloatingPoint)
+                            || r.register_has_role(RegisterRole::FloatingPointStatus)
+                    })
+                    .map(describe)
+                    .collect(),
+            });
+        }
+
+        Self {
+            architecture,
+            features,
+        }
+    }
+
+    /// Returns an iterator over all registers, in the order of their GDB register numbers.
+    pub fn registers(&self) -> impl Iterator<Item = &GdbRegister> {
+        self.features.iter().flat_map(|f| f.registers.iter())
+    }
+
+    /// Returns the register with the GDB register number `number`.
+    pub fn register(&self, number: usize) -> Option<&GdbRegister> {
+        self.registers().nth(number)
+    }
+
+    /// Renders the description as the `target.xml` document sent to GDB.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+"#,
+        );
+
+        let _ = writeln!(xml, "<architecture>{}</architecture>", self.architecture);
+
+        let mut number = 0;
+        for feature in &self.features {
+            let _ = writeln!(xml, "<feature name=\"{}\">", feature.name);
+            for register in &feature.registers {
+                let _ = writeln!(
+                    xml,
+                    "<reg name=\"{}\" bitsize=\"{}\" type=\"{}\" regnum=\"{}\"/>",
+                    register.name, register.size_in_bits, register.ty, number
+                );
+                number += 1;
+            }
+            xml.push_str("</feature>\n");
+        }
+
+        xml.push_str("</target>");
+
+        xml
+    }
+}
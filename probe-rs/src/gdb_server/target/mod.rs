@@ -0,0 +1,318 @@
+mod base;
+mod breakpoints;
+mod desc;
+mod monitor;
+mod resume;
+
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use desc::TargetDescription;
+use gdbstub::common::{Signal, Tid};
+use gdbstub::conn::ConnectionExt;
+use gdbstub::stub::state_machine::GdbStubStateMachine;
+use gdbstub::stub::{GdbStub, MultiThreadStopReason};
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::ext::breakpoints::BreakpointsOps;
+use gdbstub::target::ext::memory_map::MemoryMapOps;
+use gdbstub::target::ext::monitor_cmd::MonitorCmdOps;
+use gdbstub::target::ext::target_description_xml_override::TargetDescriptionXmlOverrideOps;
+use gdbstub::target::Target;
+use parking_lot::FairMutex;
+
+use super::arch::RuntimeArch;
+use crate::debug::rtos::{Rtos, RtosThread};
+use crate::{CoreStatus, Error, HaltReason, Session};
+
+/// The resume action GDB requested for a core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResumeAction {
+    Unchanged,
+    Resume,
+    Step,
+}
+
+/// A GDB target for a group of cores of a [Session], serving one GDB connection at a time.
+pub(crate) struct RuntimeTarget<'a> {
+    session: &'a FairMutex<Session>,
+    cores: Vec<usize>,
+    listener: TcpListener,
+    gdb: Option<GdbStubStateMachine<'a, RuntimeTarget<'a>, TcpStream>>,
+    resume_action: (usize, ResumeAction),
+    target_desc: TargetDescription,
+    rtos: Option<Arc<dyn Rtos>>,
+    /// The RTOS threads, as read when the core last halted.
+    threads: Vec<RtosThread>,
+}
+
+impl<'a> RuntimeTarget<'a> {
+    /// Creates a new target for the `cores` of `session`, listening for GDB on `addrs`.
+    ///
+    /// If `rtos` is set and only a single core is exposed, the threads of the RTOS are shown
+    /// to GDB instead of the core.
+    pub fn new(
+        session: &'a FairMutex<Session>,
+        cores: Vec<usize>,
+        addrs: &[SocketAddr],
+        rtos: Option<Arc<dyn Rtos>>,
+    ) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(addrs)?;
+        listener.set_nonblocking(true)?;
+
+        let rtos = rtos.filter(|_| cores.len() == 1);
+
+        Ok(Self {
+            session,
+            cores,
+            listener,
+            gdb: None,
+            resume_action: (0, ResumeAction::Unchanged),
+            target_desc: TargetDescription::default(),
+            rtos,
+            threads: Vec::new(),
+        })
+    }
+
+    /// Processes any pending work for this target.
+    ///
+    /// Returns the duration after which this function should be called again.
+    pub fn process(&mut self) -> anyhow::Result<Duration> {
+        let gdb = match self.gdb.take() {
+            Some(gdb) => gdb,
+            None => match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    tracing::info!("New GDB connection from {}", addr);
+                    stream.set_nonblocking(false)?;
+
+                    self.on_connect()?;
+
+                    GdbStub::new(stream).run_state_machine(self)?
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    return Ok(Duration::from_millis(10));
+                }
+                Err(e) => return Err(e.into()),
+            },
+        };
+
+        let mut wait_time = Duration::ZERO;
+
+        let gdb = match gdb {
+            GdbStubStateMachine::Idle(mut state) => match read_if_available(state.borrow_conn())? {
+                Some(byte) => state.incoming_data(self, byte)?,
+                None => {
+                    wait_time = Duration::from_millis(10);
+                    state.into()
+                }
+            },
+            GdbStubStateMachine::Running(mut state) => {
+                if let Some(byte) = read_if_available(state.borrow_conn())? {
+                    state.incoming_data(self, byte)?
+                } else if let Some(reason) = self.poll_halted()? {
+                    state.report_stop(self, reason)?
+                } else {
+                    wait_time = Duration::from_millis(10);
+                    state.into()
+                }
+            }
+            GdbStubStateMachine::CtrlCInterrupt(state) => {
+                self.halt_all()?;
+                let tid = self.stop_tid(self.cores[0]);
+                let reason = MultiThreadStopReason::SignalWithThread {
+                    tid,
+                    signal: Signal::SIGINT,
+                };
+                state.interrupt_handled(self, Some(reason))?
+            }
+            GdbStubStateMachine::Disconnected(state) => {
+                tracing::info!("GDB client disconnected: {:?}", state.get_reason());
+
+                return Ok(Duration::ZERO);
+            }
+        };
+
+        self.gdb = Some(gdb);
+
+        Ok(wait_time)
+    }
+
+    /// Halts the cores and reads the target description when a client connects.
+    fn on_connect(&mut self) -> Result<(), Error> {
+        self.halt_all()?;
+
+        let mut session = self.session.lock();
+        let mut core = session.core(self.cores[0])?;
+        self.target_desc = TargetDescription::new(&mut core)?;
+
+        Ok(())
+    }
+
+    /// Halts all cores, and updates the RTOS threads.
+    fn halt_all(&mut self) -> Result<(), Error> {
+        let mut session = self.session.lock();
+        for &core_id in &self.cores {
+            let mut core = session.core(core_id)?;
+            if !core.core_halted()? {
+                core.halt(Duration::from_millis(100))?;
+            }
+        }
+        drop(session);
+
+        self.update_threads();
+
+        Ok(())
+    }
+
+    /// Checks if any of the cores halted. If one did, all other cores are halted as well, and
+    /// the stop reason is returned.
+    fn poll_halted(&mut self) -> Result<Option<MultiThreadStopReason<u64>>, Error> {
+        let mut session = self.session.lock();
+
+        let mut halted = None;
+        for &core_id in &self.cores {
+            if let CoreStatus::Halted(reason) = session.core(core_id)?.status()? {
+                halted = Some((core_id, reason));
+                break;
+            }
+        }
+        drop(session);
+
+        let Some((core_id, reason)) = halted else {
+            return Ok(None);
+        };
+
+        self.halt_all()?;
+
+        let tid = self.stop_tid(core_id);
+        let reason = match reason {
+            HaltReason::Step => MultiThreadStopReason::DoneStep,
+            HaltReason::Breakpoint(_) => MultiThreadStopReason::SwBreak(tid),
+            HaltReason::Watchpoint(Some(watchpoint)) => MultiThreadStopReason::Watch {
+                tid,
+                kind: breakpoints::watch_kind(watchpoint.kind),
+                addr: watchpoint.address,
+            },
+            _ => MultiThreadStopReason::SignalWithThread {
+                tid,
+                signal: Signal::SIGTRAP,
+            },
+        };
+
+        Ok(Some(reason))
+    }
+
+    /// Reads the RTOS threads after the core halted.
+    fn update_threads(&mut self) {
+        let Some(rtos) = self.rtos.clone() else {
+            return;
+        };
+
+        let mut session = self.session.lock();
+        self.threads = match session.core(self.cores[0]) {
+            Ok(mut core) => rtos.threads(&mut core).unwrap_or_else(|error| {
+                tracing::warn!("Could not read the threads of {}: {}", rtos.name(), error);
+                Vec::new()
+            }),
+            Err(error) => {
+                tracing::warn!("Could not read the threads of {}: {}", rtos.name(), error);
+                Vec::new()
+            }
+        };
+    }
+
+    /// Returns the RTOS thread with the thread id `tid`.
+    fn rtos_thread(&self, tid: Tid) -> Option<&RtosThread> {
+        self.threads
+            .iter()
+            .find(|thread| thread_tid(thread) == Some(tid))
+    }
+
+    /// Returns the thread id which is reported when core `core_id` stops.
+    ///
+    /// This is the running RTOS thread if there is one, and the core otherwise.
+    fn stop_tid(&self, core_id: usize) -> Tid {
+        self.threads
+            .iter()
+            .find(|thread| thread.is_running())
+            .and_then(thread_tid)
+            .unwrap_or_else(|| core_tid(core_id))
+    }
+
+    /// Returns the id of the core which runs the thread `tid`.
+    fn core_id(&self, tid: Tid) -> usize {
+        if self.rtos.is_some() {
+            self.cores[0]
+        } else {
+            tid.get() - 1
+        }
+    }
+}
+
+impl Target for RuntimeTarget<'_> {
+    type Arch = RuntimeArch;
+    type Error = Error;
+
+    fn base_ops(&mut self) -> BaseOps<'_, Self::Arch, Self::Error> {
+        BaseOps::MultiThread(self)
+    }
+
+    fn support_target_description_xml_override(
+        &mut self,
+    ) -> Option<TargetDescriptionXmlOverrideOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_breakpoints(&mut self) -> Option<BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_memory_map(&mut self) -> Option<MemoryMapOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_monitor_cmd(&mut self) -> Option<MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn guard_rail_implicit_sw_breakpoints(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the thread id of the core `core_id`.
+fn core_tid(core_id: usize) -> Tid {
+    NonZeroUsize::new(core_id + 1).unwrap()
+}
+
+/// Returns the thread id of an RTOS thread, which is derived from its stable id.
+fn thread_tid(thread: &RtosThread) -> Option<Tid> {
+    usize::try_from(thread.id).ok().and_then(NonZeroUsize::new)
+}
+
+/// Reads a byte from the connection, if one is available.
+fn read_if_available(conn: &mut TcpStream) -> std::io::Result<Option<u8>> {
+    match conn.peek() {
+        Ok(Some(_)) => ConnectionExt::read(conn).map(Some),
+        Ok(None) => Ok(None),
+        Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Copies the part of `data` requested by GDB into `buf`, and returns the number of bytes copied.
+fn copy_range_to_buf(data: &[u8], offset: u64, length: usize, buf: &mut [u8]) -> usize {
+    let Ok(offset) = usize::try_from(offset) else {
+        return 0;
+    };
+    if offset >= data.len() {
+        return 0;
+    }
+
+    let len = length.min(buf.len()).min(data.len() - offset);
+    buf[..len].copy_from_slice(&data[offset..offset + len]);
+
+    len
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use gdbstub::target::ext::monitor_cmd::{outputln, ConsoleOutput, MonitorCmd};
+
+use super::RuntimeTarget;
+use crate::SecurityState;
+
+impl MonitorCmd for RuntimeTarget<'_> {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        match cmd {
+            b"help" => {
+                outputln!(out, "Supported commands:");
+                outputln!(out, "    help     - this help text");
+                outputln!(out, "    reset    - reset and halt the target");
+                outputln!(out, "    security - show the security state of the cores");
+                outputln!(
+                    out,
+                    "    security secure|non-secure - select the security of memory accesses"
+                );
+            }
+            b"reset" => {
+                outputln!(out, "Resetting target");
+
+                let mut session = self.session.lock();
+                for &core_id in &self.cores {
+                    session
+                        .core(core_id)?
+                        .reset_and_halt(Duration::from_millis(400))?;
+                }
+                drop(session);
+
+                self.update_threads();
+
+                outputln!(out, "Done");
+            }
+            b"security" => {
+                let mut session = self.session.lock();
+                for &core_id in &self.cores {
+                    match session.core(core_id)?.security_state()? {
+                        Some(state) => outputln!(out, "Core {}: {}", core_id, state),
+                        None => outputln!(out, "Core {}: no Security Extension", core_id),
+                    }
+                }
+            }
+            b"security secure" | b"security non-secure" => {
+                let state = if cmd == b"security secure" {
+                    SecurityState::Secure
+                } else {
+                    SecurityState::NonSecure
+                };
+
+                let mut session = self.session.lock();
+                for &core_id in &self.cores {
+                    session.core(core_id)?.set_memory_security_state(state)?;
+                }
+                outputln!(out, "Memory is accessed with {} transfers", state);
+            }
+            _ => {
+                outputln!(out, "Unknown command: {}", String::from_utf8_lossy(cmd));
+                outputln!(out, "Use 'monitor help' for a list of commands");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use gdbstub::common::{Signal, Tid};
+use gdbstub::target::ext::base::multithread::{
+    MultiThreadResume, MultiThreadSingleStep, MultiThreadSingleStepOps,
+};
+
+use super::{ResumeAction, RuntimeTarget};
+use crate::Error;
+
+impl MultiThreadResume for RuntimeTarget<'_> {
+    fn resume(&mut self) -> Result<(), Self::Error> {
+        let (core_id, action) = self.resume_action;
+
+        let mut session = self.session.lock();
+        match action {
+            ResumeAction::Unchanged => {}
+            ResumeAction::Step => {
+                // Only the stepped core runs, all others stay halted.
+                session.core(core_id)?.step()?;
+            }
+            ResumeAction::Resume => {
+                for &core_id in &self.cores {
+                    session.core(core_id)?.run()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear_resume_actions(&mut self) -> Result<(), Self::Error> {
+        self.resume_action = (0, ResumeAction::Unchanged);
+
+        Ok(())
+    }
+
+    fn set_resume_action_continue(
+        &mut self,
+        tid: Tid,
+        signal: Option<Signal>,
+    ) -> Result<(), Self::Error> {
+        if signal.is_some() {
+            return Err(Error::NotImplemented("Resuming with a signal"));
+        }
+
+        // A step requested for another thread takes precedence.
+        if self.resume_action.1 != ResumeAction::Step {
+            self.resume_action = (self.core_id(tid), ResumeAction::Resume);
+        }
+
+        Ok(())
+    }
+
+    fn support_single_step(&mut self) -> Option<MultiThreadSingleStepOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl MultiThreadSingleStep for RuntimeTarget<'_> {
+    fn set_resume_action_step(
+        &mut self,
+        tid: Tid,
+        signal: Option<Signal>,
+    ) -> Result<(), Self::Error> {
+        if signal.is_some() {
+            return Err(Error::NotImplemented("Stepping with a signal"));
+        }
+
+        self.resume_action = (self.core_id(tid), ResumeAction::Step);
+
+        Ok(())
+    }
+}
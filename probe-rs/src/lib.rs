@@ -84,6 +84,7 @@ pub mod gdb_server;
 pub mod integration;
 mod memory;
 pub mod probe;
+mod protection;
 pub mod rtt;
 pub mod semihosting;
 mod session;
@@ -94,10 +95,12 @@ pub use crate::config::{CoreType, InstructionSet, Target};
 pub use crate::core::{
     Architecture, BreakpointCause, Core, CoreInformation, CoreInterface, CoreRegister,
     CoreRegisters, CoreState, CoreStatus, HaltReason, MemoryMappedRegister, RegisterId,
-    RegisterRole, RegisterValue, SpecificCoreState, VectorCatchCondition,
+    RegisterRole, RegisterValue, SecurityState, SpecificCoreState, VectorCatchCondition,
+    Watchpoint, WatchpointKind,
 };
 pub use crate::error::Error;
 pub use crate::memory::MemoryInterface;
+pub use crate::protection::{ProtectionLevel, ProtectionStatus};
 pub use crate::session::{Permissions, Session};
 
 #[cfg(feature = "debug")]
@@ -4,13 +4,19 @@ pub(crate) mod common;
 pub(crate) mod usb_util;
 
 pub mod blackmagic;
+pub mod boundary_scan;
 pub mod cmsisdap;
 pub mod espusbjtag;
 pub mod fake_probe;
 pub mod ftdi;
 pub mod jlink;
+pub mod jtag_vpi;
 pub mod list;
+pub mod recording;
+pub mod remote;
+pub mod remote_bitbang;
 pub mod stlink;
+pub mod svf;
 pub mod wlink;
 
 use crate::architecture::arm::sequences::{ArmDebugSequence, DefaultArmSequence};
@@ -573,6 +579,13 @@ impl Probe {
         self.inner.try_as_dap_probe()
     }
 
+    /// Gets a JTAG interface from the debug probe.
+    ///
+    /// This does not work on all probes.
+    pub fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        self.inner.try_as_jtag_probe()
+    }
+
     /// Try reading the target voltage of via the connected voltage pin.
     ///
     /// This does not work on all probes.
@@ -766,6 +779,13 @@ pub trait DebugProbe: Send + fmt::Debug {
         None
     }
 
+    /// Try getting low-level access to the JTAG interface of the probe.
+    ///
+    /// This is not available on all probes.
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        None
+    }
+
     /// Reads the target voltage in Volts, if possible. Returns `Ok(None)`
     /// if the probe doesn’t support reading the target voltage.
     fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
@@ -874,7 +894,7 @@ pub enum DebugProbeSelectorParseError {
     /// Could not parse VID or PID: {0}
     ParseInt(#[from] std::num::ParseIntError),
 
-    /// The format of the selector is invalid. Please use a string in the form `VID:PID:<Serial>`, where Serial is optional.
+    /// The format of the selector is invalid. Please use a string in the form `VID:PID:<Serial>`, where Serial is optional, or `tcp://HOST:PORT/VID:PID:<Serial>` for remote probes.
     Format,
 }
 
@@ -885,6 +905,9 @@ pub enum DebugProbeSelectorParseError {
 /// where the serial number is optional, and VID and PID are
 /// parsed as hexadecimal numbers.
 ///
+/// Probes attached to a probe server (see [`remote`]) are selected with
+/// "tcp://HOST:PORT/VID:PID:SERIALNUMBER".
+///
 /// ## Example:
 ///
 /// ```
@@ -904,16 +927,37 @@ pub struct DebugProbeSelector {
     pub product_id: u16,
     /// The the serial number of the debug probe to be used.
     pub serial_number: Option<String>,
+    /// The address of the probe server the debug probe is attached to, if it is not
+    /// attached to this machine.
+    pub remote: Option<String>,
 }
 
 impl DebugProbeSelector {
     pub(crate) fn matches(&self, info: &DeviceInfo) -> bool {
-        info.vendor_id() == self.vendor_id
-            && info.product_id() == self.product_id
+        self.matches_ids(info.vendor_id(), info.product_id(), info.serial_number())
+    }
+
+    /// Returns whether the probe described by `info` is selected by this selector.
+    pub fn matches_probe(&self, info: &DebugProbeInfo) -> bool {
+        self.matches_ids(
+            info.vendor_id,
+            info.product_id,
+            info.serial_number.as_deref(),
+        )
+    }
+
+    /// Returns whether a USB device attached to this machine, with the given ids and serial
+    /// number, is selected.
+    ///
+    /// Selectors for probes attached to a probe server never select a local device.
+    fn matches_ids(&self, vendor_id: u16, product_id: u16, serial_number: Option<&str>) -> bool {
+        self.remote.is_none()
+            && vendor_id == self.vendor_id
+            && product_id == self.product_id
             && self
                 .serial_number
-                .as_ref()
-                .map(|s| info.serial_number() == Some(s))
+                .as_deref()
+                .map(|s| serial_number == Some(s))
                 .unwrap_or(true)
     }
 }
@@ -921,6 +965,17 @@ impl DebugProbeSelector {
 impl TryFrom<&str> for DebugProbeSelector {
     type Error = DebugProbeSelectorParseError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if let Some(remote) = value.strip_prefix("tcp://") {
+            let (address, selector) = remote
+                .split_once('/')
+                .ok_or(DebugProbeSelectorParseError::Format)?;
+
+            return Ok(DebugProbeSelector {
+                remote: Some(address.to_string()),
+                ..selector.try_into()?
+            });
+        }
+
         // Split into at most 3 parts: VID, PID, Serial.
         // We limit the number of splits to allow for colons in the
         // serial number (EspJtag uses MAC address)
@@ -934,6 +989,7 @@ impl TryFrom<&str> for DebugProbeSelector {
             vendor_id: u16::from_str_radix(vendor_id, 16)?,
             product_id: u16::from_str_radix(product_id, 16)?,
             serial_number,
+            remote: None,
         })
     }
 }
@@ -958,6 +1014,7 @@ impl From<DebugProbeInfo> for DebugProbeSelector {
             vendor_id: selector.vendor_id,
             product_id: selector.product_id,
             serial_number: selector.serial_number,
+            remote: None,
         }
     }
 }
@@ -968,6 +1025,7 @@ impl From<&DebugProbeInfo> for DebugProbeSelector {
             vendor_id: selector.vendor_id,
             product_id: selector.product_id,
             serial_number: selector.serial_number.clone(),
+            remote: None,
         }
     }
 }
@@ -980,6 +1038,9 @@ impl From<&DebugProbeSelector> for DebugProbeSelector {
 
 impl fmt::Display for DebugProbeSelector {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref remote) = self.remote {
+            write!(f, "tcp://{remote}/")?;
+        }
         write!(f, "{:04x}:{:04x}", self.vendor_id, self.product_id)?;
         if let Some(ref sn) = self.serial_number {
             write!(f, ":{sn}")?;
@@ -988,6 +1049,28 @@ impl fmt::Display for DebugProbeSelector {
     }
 }
 
+/// A register of the TAPs on a JTAG scan chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JtagRegister {
+    /// The instruction register.
+    Ir,
+    /// The data register selected by the current instruction.
+    Dr,
+}
+
+/// A state of the JTAG TAP controller in which it stays while TCK is clocked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JtagStableState {
+    /// The Test-Logic-Reset state.
+    Reset,
+    /// The Run-Test/Idle state.
+    Idle,
+    /// The Pause-DR state.
+    DrPause,
+    /// The Pause-IR state.
+    IrPause,
+}
+
 /// Low-Level Access to the JTAG protocol
 ///
 /// This trait should be implemented by all probes which offer low-level access to
@@ -1039,6 +1122,31 @@ pub trait JTAGAccess: DebugProbe {
     /// The data shifted out of the DR register will be returned.
     fn write_dr(&mut self, data: &[u8], len: u32) -> Result<Vec<u8>, DebugProbeError>;
 
+    /// Moves the TAP controllers to `state`, and clocks TCK `cycles` times while staying in it.
+    fn run_test(&mut self, _state: JtagStableState, _cycles: u32) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "run_test",
+        })
+    }
+
+    /// Shifts `len` bits of `data` through a register of the whole scan chain, and then moves the
+    /// TAP controllers to `end_state`.
+    ///
+    /// Unlike [`JTAGAccess::write_register`], this does not take the selected TAP into account,
+    /// so the data has to include the bits of all TAPs. The data shifted out of the register is
+    /// returned.
+    fn shift_raw(
+        &mut self,
+        _register: JtagRegister,
+        _data: &[u8],
+        _len: u32,
+        _end_state: JtagStableState,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "shift_raw",
+        })
+    }
+
     /// Executes a sequence of JTAG commands.
     fn write_register_batch(
         &mut self,
@@ -1350,8 +1458,8 @@ impl fmt::Debug for DeferredResultIndex {
 }
 
 impl DeferredResultIndex {
-    // Intentionally private. User code must not be able to create these.
-    fn new() -> Self {
+    // Intentionally crate-private. User code must not be able to create these.
+    pub(crate) fn new() -> Self {
         Self(Arc::new(()))
     }
 
@@ -1419,4 +1527,40 @@ mod test {
             Some("DC:DA:0C:D3:FE:D8".to_string())
         );
     }
+
+    #[test]
+    fn test_parsing_remote_selector() {
+        let selector: DebugProbeSelector = "tcp://lab-pc:3500/1366:1015:000123".parse().unwrap();
+
+        assert_eq!(selector.remote.as_deref(), Some("lab-pc:3500"));
+        assert_eq!(selector.vendor_id, 0x1366);
+        assert_eq!(selector.product_id, 0x1015);
+        assert_eq!(selector.serial_number.as_deref(), Some("000123"));
+        assert_eq!(selector.to_string(), "tcp://lab-pc:3500/1366:1015:000123");
+
+        assert!("tcp://lab-pc:3500".parse::<DebugProbeSelector>().is_err());
+    }
+
+    #[test]
+    fn test_selector_matches_probe() {
+        let probe_info = DebugProbeInfo::new(
+            "Mock probe",
+            0x12,
+            0x23,
+            Some("mock_serial".to_owned()),
+            &ftdi::FtdiProbeFactory,
+            None,
+        );
+
+        let selector: DebugProbeSelector = "12:23".try_into().unwrap();
+        assert!(selector.matches_probe(&probe_info));
+        let selector: DebugProbeSelector = "12:23:mock_serial".try_into().unwrap();
+        assert!(selector.matches_probe(&probe_info));
+        let selector: DebugProbeSelector = "12:23:other_serial".try_into().unwrap();
+        assert!(!selector.matches_probe(&probe_info));
+        let selector: DebugProbeSelector = "12:24".try_into().unwrap();
+        assert!(!selector.matches_probe(&probe_info));
+        let selector: DebugProbeSelector = "tcp://lab-pc:3500/12:23".try_into().unwrap();
+        assert!(!selector.matches_probe(&probe_info));
+    }
 }
@@ -1150,6 +1150,10 @@ impl DebugProbe for BlackMagicProbe {
         self
     }
 
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
     /// Turn this probe into an ARM probe
     fn try_get_arm_interface<'probe>(
         mut self: Box<Self>,
@@ -0,0 +1,467 @@
+//! Boundary scan of JTAG devices.
+//!
+//! The boundary register of a device, described by its [BSDL file](Bsdl), connects to the pins of
+//! the device. With the `SAMPLE` instruction, the levels of the pins can be read while the device
+//! operates normally. With the `EXTEST` instruction, the pins are driven from the boundary
+//! register instead, which can be used to test the connections on a board.
+//!
+//! ```no_run
+//! use probe_rs::probe::{
+//!     boundary_scan::{BoundaryScan, Bsdl, PinDrive},
+//!     list::Lister,
+//!     WireProtocol,
+//! };
+//!
+//! let bsdl = Bsdl::parse(&std::fs::read_to_string("device.bsd")?)?;
+//!
+//! let mut probe = Lister::new().list_all()[0].open()?;
+//! probe.select_protocol(WireProtocol::Jtag)?;
+//! probe.attach_to_unspecified()?;
+//! probe.select_jtag_tap(0)?;
+//!
+//! let jtag = probe.try_as_jtag_probe().expect("JTAG is not supported");
+//! let mut scan = BoundaryScan::new(jtag, &bsdl)?;
+//!
+//! scan.set_pin("PA0", PinDrive::High)?;
+//! let levels = scan.extest()?;
+//! println!("PB0 is {:?}", levels.get("PB0"));
+//!
+//! scan.release()?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod bsdl;
+
+pub use bsdl::{BoundaryCell, Bsdl, BsdlError, CellFunction, IdCodePattern, Pin};
+
+use bitvec::prelude::*;
+
+use crate::probe::{DebugProbeError, JTAGAccess};
+
+/// An error which occurred during boundary scan.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum BoundaryScanError {
+    /// An error occurred while accessing the JTAG interface.
+    Probe(#[from] DebugProbeError),
+
+    /// The device does not have a pin named {0}.
+    UnknownPin(String),
+
+    /// The pin {0} can not be driven to the requested state.
+    PinNotDrivable(String),
+
+    /// The BSDL file does not define the {0} instruction.
+    MissingInstruction(&'static str),
+
+    /// The IDCODE {found:#010x} does not match the IDCODE of the BSDL file.
+    IdCodeMismatch {
+        /// The IDCODE read from the device.
+        found: u32,
+    },
+}
+
+/// The state a pin is driven to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinDrive {
+    /// The pin is driven low.
+    Low,
+    /// The pin is driven high.
+    High,
+    /// The output of the pin is disabled.
+    HighZ,
+}
+
+/// The levels of the input pins of a device, captured by the boundary register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinLevels {
+    levels: Vec<(String, bool)>,
+}
+
+impl PinLevels {
+    /// Returns the level of the pin with the given name, if it can be read.
+    pub fn get(&self, pin: &str) -> Option<bool> {
+        self.levels
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(pin))
+            .map(|(_, level)| *level)
+    }
+
+    /// Returns an iterator over the names and levels of all pins which can be read.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.levels
+            .iter()
+            .map(|(name, level)| (name.as_str(), *level))
+    }
+}
+
+/// Boundary scan access to a device on the JTAG scan chain.
+///
+/// The TAP of the device has to be selected with
+/// [`select_jtag_tap`](crate::probe::Probe::select_jtag_tap) before.
+pub struct BoundaryScan<'probe> {
+    jtag: &'probe mut dyn JTAGAccess,
+    bsdl: &'probe Bsdl,
+
+    /// The values which are shifted into the boundary register.
+    register: BitVec<u8, Lsb0>,
+    extest: bool,
+}
+
+impl<'probe> BoundaryScan<'probe> {
+    /// Creates a boundary scan of the device described by `bsdl`.
+    ///
+    /// If the BSDL file describes the `IDCODE` register, the `IDCODE` of the device is checked
+    /// against it. The boundary register starts out with the safe values of all cells, so
+    /// all pins are undriven.
+    pub fn new(
+        jtag: &'probe mut dyn JTAGAccess,
+        bsdl: &'probe Bsdl,
+    ) -> Result<Self, BoundaryScanError> {
+        if let (Some(pattern), Some(instruction)) = (bsdl.idcode, bsdl.instruction("IDCODE")) {
+            let idcode = jtag.read_register(instruction, 32)?;
+            let idcode = idcode.view_bits::<Lsb0>()[..32].load_le::<u32>();
+
+            if !pattern.matches(idcode) {
+                return Err(BoundaryScanError::IdCodeMismatch { found: idcode });
+            }
+        }
+
+        let register = bsdl
+            .cells
+            .iter()
+            .map(|cell| cell.safe.unwrap_or(false))
+            .collect();
+
+        Ok(Self {
+            jtag,
+            bsdl,
+            register,
+            extest: false,
+        })
+    }
+
+    /// Sets the state the pin is driven to by the next [`preload`](Self::preload) or
+    /// [`extest`](Self::extest).
+    pub fn set_pin(&mut self, pin: &str, drive: PinDrive) -> Result<(), BoundaryScanError> {
+        let pin = self
+            .bsdl
+            .pin(pin)
+            .ok_or_else(|| BoundaryScanError::UnknownPin(pin.to_string()))?;
+        let not_drivable = || BoundaryScanError::PinNotDrivable(pin.name.clone());
+
+        let output = pin.output.ok_or_else(not_drivable)?;
+
+        match (drive, pin.control) {
+            (PinDrive::HighZ, Some((control, disable))) => self.register.set(control, disable),
+            (PinDrive::HighZ, None) => return Err(not_drivable()),
+            (level, control) => {
+                self.register.set(output, level == PinDrive::High);
+                if let Some((control, disable)) = control {
+                    self.register.set(control, !disable);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Captures the levels of the pins while the device operates normally.
+    ///
+    /// This also loads the boundary register, like [`preload`](Self::preload).
+    pub fn sample(&mut self) -> Result<PinLevels, BoundaryScanError> {
+        let instruction = self.instruction("SAMPLE")?;
+        let captured = self.shift(instruction)?;
+        self.extest = false;
+
+        Ok(self.levels(&captured))
+    }
+
+    /// Loads the boundary register with the pin states, without driving the pins.
+    ///
+    /// The pins are driven to these states as soon as `EXTEST` is selected.
+    pub fn preload(&mut self) -> Result<(), BoundaryScanError> {
+        let instruction = self
+            .instruction("PRELOAD")
+            .or_else(|_| self.instruction("SAMPLE"))?;
+        self.shift(instruction)?;
+        self.extest = false;
+
+        Ok(())
+    }
+
+    /// Drives the pins to the states set with [`set_pin`](Self::set_pin), and captures the levels
+    /// of the pins while they are driven.
+    ///
+    /// The pins stay driven until [`release`](Self::release) is called.
+    pub fn extest(&mut self) -> Result<PinLevels, BoundaryScanError> {
+        // Selecting EXTEST drives the pins right away, so they have to be preloaded to avoid
+        // driving stale values.
+        if !self.extest {
+            self.preload()?;
+        }
+
+        let instruction = self.instruction("EXTEST")?;
+        self.extest = true;
+
+        // The levels are captured before the new states are applied, so a second scan is needed
+        // to capture the levels with the new states.
+        self.shift(instruction)?;
+        let captured = self.shift(instruction)?;
+
+        Ok(self.levels(&captured))
+    }
+
+    /// Returns the device to normal operation, by resetting the TAP.
+    pub fn release(&mut self) -> Result<(), BoundaryScanError> {
+        self.jtag.tap_reset()?;
+        self.extest = false;
+
+        Ok(())
+    }
+
+    fn instruction(&self, name: &'static str) -> Result<u32, BoundaryScanError> {
+        self.bsdl
+            .instruction(name)
+            .ok_or(BoundaryScanError::MissingInstruction(name))
+    }
+
+    /// Shifts the boundary register with the given instruction, and returns the captured values.
+    fn shift(&mut self, instruction: u32) -> Result<BitVec<u8, Lsb0>, BoundaryScanError> {
+        let len = self.register.len();
+        let captured =
+            self.jtag
+                .write_register(instruction, self.register.as_raw_slice(), len as u32)?;
+
+        let mut captured = BitVec::from_vec(captured);
+        captured.truncate(len);
+        Ok(captured)
+    }
+
+    fn levels(&self, captured: &BitSlice<u8, Lsb0>) -> PinLevels {
+        let levels = self
+            .bsdl
+            .pins
+            .iter()
+            .filter_map(|pin| Some((pin.name.clone(), captured[pin.input?])))
+            .collect();
+
+        PinLevels { levels }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitvec::prelude::*;
+
+    use super::{bsdl::test::BSDL, *};
+    use crate::probe::{DebugProbe, ScanChainElement, WireProtocol};
+
+    const IDCODE: u32 = 0x1411_1047;
+
+    /// A mock of the device described by [`BSDL`], whose `OUT0` pin is connected to `IN0`, and
+    /// whose `D(0)` pin is connected to `IN1`. Undriven pins are pulled high.
+    #[derive(Debug)]
+    struct MockDevice {
+        instruction: u32,
+        /// The update latches of the boundary register.
+        update: BitVec<u8, Lsb0>,
+        resets: usize,
+    }
+
+    impl MockDevice {
+        fn new() -> Self {
+            Self {
+                instruction: 0b1110,
+                update: bitvec![u8, Lsb0; 0; 8],
+                resets: 0,
+            }
+        }
+
+        fn driven(&self, output: usize, control: usize) -> Option<bool> {
+            let extest = self.instruction == 0b0000;
+            (extest && self.update[control]).then_some(self.update[output])
+        }
+
+        fn capture(&self) -> BitVec<u8, Lsb0> {
+            let out0 = self.driven(1, 0).unwrap_or(true);
+            let d0 = self.driven(4, 6).unwrap_or(true);
+            let d1 = self.driven(5, 6).unwrap_or(true);
+
+            let mut captured = self.update.clone();
+            captured.set(2, out0);
+            captured.set(3, d0);
+            captured.set(4, d0);
+            captured.set(5, d1);
+            captured
+        }
+    }
+
+    impl JTAGAccess for MockDevice {
+        fn scan_chain(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
+            self.instruction = 0b1110;
+            self.resets += 1;
+            Ok(())
+        }
+
+        fn set_idle_cycles(&mut self, _idle_cycles: u8) {}
+
+        fn idle_cycles(&self) -> u8 {
+            0
+        }
+
+        fn write_register(
+            &mut self,
+            address: u32,
+            data: &[u8],
+            len: u32,
+        ) -> Result<Vec<u8>, DebugProbeError> {
+            self.instruction = address;
+
+            match address {
+                0b1110 => {
+                    assert_eq!(len, 32);
+                    Ok(IDCODE.to_le_bytes().to_vec())
+                }
+                0b0000 | 0b0010 => {
+                    assert_eq!(len, 8);
+                    let captured = self.capture();
+                    self.update = data.view_bits::<Lsb0>()[..8].to_bitvec();
+                    Ok(captured.into_vec())
+                }
+                _ => panic!("Unexpected instruction {address:#06b}"),
+            }
+        }
+
+        fn write_dr(&mut self, _data: &[u8], _len: u32) -> Result<Vec<u8>, DebugProbeError> {
+            unimplemented!()
+        }
+    }
+
+    impl DebugProbe for MockDevice {
+        fn get_name(&self) -> &str {
+            "Mock boundary scan device"
+        }
+
+        fn speed_khz(&self) -> u32 {
+            unimplemented!()
+        }
+
+        fn set_speed(&mut self, _speed_khz: u32) -> Result<u32, DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn set_scan_chain(
+            &mut self,
+            _scan_chain: Vec<ScanChainElement>,
+        ) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn attach(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn detach(&mut self) -> Result<(), crate::Error> {
+            unimplemented!()
+        }
+
+        fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn select_protocol(&mut self, _protocol: WireProtocol) -> Result<(), DebugProbeError> {
+            unimplemented!()
+        }
+
+        fn active_protocol(&self) -> Option<WireProtocol> {
+            Some(WireProtocol::Jtag)
+        }
+
+        fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+            self
+        }
+    }
+
+    #[test]
+    fn sample_reads_pins() {
+        let bsdl = Bsdl::parse(BSDL).unwrap();
+        let mut device = MockDevice::new();
+        let mut scan = BoundaryScan::new(&mut device, &bsdl).unwrap();
+
+        let levels = scan.sample().unwrap();
+
+        let levels: Vec<_> = levels.iter().collect();
+        assert_eq!(
+            levels,
+            [("IN0", true), ("IN1", true), ("D(0)", true), ("D(1)", true)]
+        );
+    }
+
+    #[test]
+    fn extest_drives_pins() {
+        let bsdl = Bsdl::parse(BSDL).unwrap();
+        let mut device = MockDevice::new();
+        let mut scan = BoundaryScan::new(&mut device, &bsdl).unwrap();
+
+        scan.set_pin("OUT0", PinDrive::Low).unwrap();
+        scan.set_pin("D(0)", PinDrive::Low).unwrap();
+        scan.set_pin("D(1)", PinDrive::High).unwrap();
+        let levels = scan.extest().unwrap();
+
+        assert_eq!(levels.get("IN0"), Some(false));
+        assert_eq!(levels.get("IN1"), Some(false));
+        assert_eq!(levels.get("D(1)"), Some(true));
+
+        scan.set_pin("OUT0", PinDrive::HighZ).unwrap();
+        let levels = scan.extest().unwrap();
+        assert_eq!(levels.get("IN0"), Some(true));
+        assert_eq!(levels.get("IN1"), Some(false));
+
+        scan.release().unwrap();
+        assert_eq!(device.resets, 1);
+    }
+
+    #[test]
+    fn invalid_pins_are_rejected() {
+        let bsdl = Bsdl::parse(BSDL).unwrap();
+        let mut device = MockDevice::new();
+        let mut scan = BoundaryScan::new(&mut device, &bsdl).unwrap();
+
+        assert!(matches!(
+            scan.set_pin("PA0", PinDrive::High),
+            Err(BoundaryScanError::UnknownPin(_))
+        ));
+        assert!(matches!(
+            scan.set_pin("IN0", PinDrive::High),
+            Err(BoundaryScanError::PinNotDrivable(_))
+        ));
+    }
+
+    #[test]
+    fn idcode_is_checked() {
+        let bsdl =
+            Bsdl::parse(&BSDL.replace("\"0100000100010001\"", "\"0100000100010010\"")).unwrap();
+        let mut device = MockDevice::new();
+
+        assert!(matches!(
+            BoundaryScan::new(&mut device, &bsdl),
+            Err(BoundaryScanError::IdCodeMismatch { found: IDCODE })
+        ));
+    }
+}
@@ -0,0 +1,747 @@
+//! Parser for Boundary Scan Description Language (BSDL) files.
+//!
+//! Only the parts of a BSDL file which are needed to perform boundary scan are parsed: the
+//! ports of the entity, the instruction register, the `IDCODE` register and the boundary register.
+
+use std::collections::HashMap;
+
+/// An error which occurred while parsing a BSDL file.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum BsdlError {
+    /// The BSDL file does not contain an entity declaration.
+    MissingEntity,
+
+    /// The port declaration is invalid.
+    InvalidPort,
+
+    /// The attribute {0} is missing.
+    MissingAttribute(&'static str),
+
+    /// The attribute {0} is invalid: {1}
+    InvalidAttribute(&'static str, String),
+}
+
+/// The function of a cell in the boundary register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellFunction {
+    /// Captures the level of an input pin.
+    Input,
+    /// Drives an output pin which can't be disabled, or only with a control cell.
+    Output2,
+    /// Drives a tristate output pin.
+    Output3,
+    /// Enables or disables one or more output cells.
+    Control,
+    /// A control cell which is forced to the disable value in the Test-Logic-Reset state.
+    ControlR,
+    /// A cell which is not connected to a pin.
+    Internal,
+    /// Captures the level of a clock input pin.
+    Clock,
+    /// Captures and drives a bidirectional pin.
+    Bidir,
+    /// Captures the level of a pin, but can't drive it.
+    ObserveOnly,
+}
+
+impl CellFunction {
+    fn parse(function: &str) -> Option<Self> {
+        let function = match function.to_ascii_uppercase().as_str() {
+            "INPUT" => Self::Input,
+            "OUTPUT2" => Self::Output2,
+            "OUTPUT3" => Self::Output3,
+            "CONTROL" => Self::Control,
+            "CONTROLR" => Self::ControlR,
+            "INTERNAL" => Self::Internal,
+            "CLOCK" => Self::Clock,
+            "BIDIR" => Self::Bidir,
+            "OBSERVE_ONLY" => Self::ObserveOnly,
+            _ => return None,
+        };
+
+        Some(function)
+    }
+
+    /// Returns whether the cell captures the level of its pin.
+    pub fn is_input(self) -> bool {
+        matches!(
+            self,
+            Self::Input | Self::Clock | Self::Bidir | Self::ObserveOnly
+        )
+    }
+
+    /// Returns whether the cell drives its pin.
+    pub fn is_output(self) -> bool {
+        matches!(self, Self::Output2 | Self::Output3 | Self::Bidir)
+    }
+}
+
+/// A cell of the boundary register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundaryCell {
+    /// The number of the cell, where cell 0 is the closest to TDO.
+    pub number: usize,
+    /// The name of the cell type, like `BC_1`.
+    pub cell_type: String,
+    /// The port the cell is connected to, if any.
+    pub port: Option<String>,
+    /// The function of the cell.
+    pub function: CellFunction,
+    /// The value which is safe to load into the cell, if any.
+    pub safe: Option<bool>,
+    /// The control cell which disables the output of this cell, and its disable value.
+    pub control: Option<(usize, bool)>,
+}
+
+/// A pin of the device, and the boundary register cells connected to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    /// The name of the port the pin is connected to.
+    pub name: String,
+    /// The cell which captures the level of the pin.
+    pub input: Option<usize>,
+    /// The cell which drives the pin.
+    pub output: Option<usize>,
+    /// The control cell which enables the output, and its disable value.
+    pub control: Option<(usize, bool)>,
+}
+
+/// The expected value of the `IDCODE` register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdCodePattern {
+    /// The expected value of the bits which are not don't-care.
+    pub value: u32,
+    /// The bits which have to match.
+    pub mask: u32,
+}
+
+impl IdCodePattern {
+    /// Returns whether `idcode` matches the pattern.
+    pub fn matches(&self, idcode: u32) -> bool {
+        idcode & self.mask == self.value
+    }
+}
+
+/// The boundary scan description of a device, parsed from a BSDL file.
+#[derive(Debug, Clone)]
+pub struct Bsdl {
+    /// The name of the entity, usually the name of the device.
+    pub entity: String,
+    /// The length of the instruction register.
+    pub instruction_length: u32,
+    instructions: HashMap<String, u32>,
+    /// The expected value of the `IDCODE` register, if the device has one.
+    pub idcode: Option<IdCodePattern>,
+    /// The cells of the boundary register, ordered by their number.
+    pub cells: Vec<BoundaryCell>,
+    /// The pins which are connected to the boundary register, in the order of the port
+    /// declaration.
+    pub pins: Vec<Pin>,
+}
+
+impl Bsdl {
+    /// Parses the contents of a BSDL file.
+    pub fn parse(source: &str) -> Result<Self, BsdlError> {
+        let tokens = tokenize(source);
+
+        let entity = tokens
+            .windows(2)
+            .find_map(|window| match window {
+                [Token::Word(keyword), Token::Word(name)]
+                    if keyword.eq_ignore_ascii_case("entity") =>
+                {
+                    Some(name.clone())
+                }
+                _ => None,
+            })
+            .ok_or(BsdlError::MissingEntity)?;
+
+        let ports = parse_ports(&tokens)?;
+        let attributes = parse_attributes(&tokens);
+
+        let instruction_length = attributes
+            .get("INSTRUCTION_LENGTH")
+            .ok_or(BsdlError::MissingAttribute("INSTRUCTION_LENGTH"))?
+            .parse()
+            .map_err(|_| invalid("INSTRUCTION_LENGTH", "not a number"))?;
+
+        let instructions = parse_instructions(
+            attributes
+                .get("INSTRUCTION_OPCODE")
+                .ok_or(BsdlError::MissingAttribute("INSTRUCTION_OPCODE"))?,
+        )?;
+
+        let idcode = attributes
+            .get("IDCODE_REGISTER")
+            .map(|idcode| parse_idcode(idcode))
+            .transpose()?;
+
+        let boundary_length: usize = attributes
+            .get("BOUNDARY_LENGTH")
+            .ok_or(BsdlError::MissingAttribute("BOUNDARY_LENGTH"))?
+            .parse()
+            .map_err(|_| invalid("BOUNDARY_LENGTH", "not a number"))?;
+
+        let cells = parse_cells(
+            attributes
+                .get("BOUNDARY_REGISTER")
+                .ok_or(BsdlError::MissingAttribute("BOUNDARY_REGISTER"))?,
+            boundary_length,
+        )?;
+
+        let pins = collect_pins(&ports, &cells);
+
+        Ok(Self {
+            entity,
+            instruction_length,
+            instructions,
+            idcode,
+            cells,
+            pins,
+        })
+    }
+
+    /// Returns the opcode of the instruction with the given name.
+    pub fn instruction(&self, name: &str) -> Option<u32> {
+        self.instructions.get(&name.to_ascii_uppercase()).copied()
+    }
+
+    /// Returns the length of the boundary register.
+    pub fn boundary_length(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns the pin connected to the port with the given name.
+    pub fn pin(&self, name: &str) -> Option<&Pin> {
+        let name = normalize_port(name);
+        self.pins
+            .iter()
+            .find(|pin| pin.name.eq_ignore_ascii_case(&name))
+    }
+}
+
+fn invalid(attribute: &'static str, reason: impl Into<String>) -> BsdlError {
+    BsdlError::InvalidAttribute(attribute, reason.into())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// An identifier, keyword or number.
+    Word(String),
+    /// A string literal, without the quotes.
+    Str(String),
+    Punct(char),
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '-' if chars.peek() == Some(&'-') => {
+                // Comments last until the end of the line.
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                let mut string = String::new();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    string.push(c);
+                }
+                tokens.push(Token::Str(string));
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => tokens.push(Token::Punct(c)),
+        }
+    }
+
+    tokens
+}
+
+fn is_keyword(token: &Token, keyword: &str) -> bool {
+    matches!(token, Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+}
+
+/// Removes whitespace from a port name, so that `D (0)` and `D(0)` refer to the same port.
+fn normalize_port(name: &str) -> String {
+    name.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Parses the port clause of the entity, and returns the names of the ports, with bit vectors
+/// expanded to one port per bit.
+fn parse_ports(tokens: &[Token]) -> Result<Vec<String>, BsdlError> {
+    let Some(start) = tokens
+        .windows(2)
+        .position(|window| is_keyword(&window[0], "port") && window[1] == Token::Punct('('))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut ports = Vec::new();
+    let mut names = Vec::new();
+    let mut depth = 0;
+    let mut tokens = tokens[start + 2..].iter();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Punct('(') => depth += 1,
+            Token::Punct(')') if depth == 0 => break,
+            Token::Punct(')') => depth -= 1,
+            Token::Word(name) if depth == 0 => names.push(name.clone()),
+            Token::Punct(':') if depth == 0 => {
+                // The mode and type of the ports follow, and then an optional range.
+                let _mode = tokens.next();
+                let ty = tokens.next();
+
+                let range = if is_keyword(ty.ok_or(BsdlError::InvalidPort)?, "bit_vector") {
+                    let range: Vec<_> = tokens
+                        .by_ref()
+                        .take_while(|token| **token != Token::Punct(')'))
+                        .collect();
+
+                    match range.as_slice() {
+                        [Token::Punct('('), Token::Word(from), Token::Word(_), Token::Word(to)] => {
+                            let from: usize = from.parse().map_err(|_| BsdlError::InvalidPort)?;
+                            let to: usize = to.parse().map_err(|_| BsdlError::InvalidPort)?;
+                            Some(from.min(to)..=from.max(to))
+                        }
+                        _ => return Err(BsdlError::InvalidPort),
+                    }
+                } else {
+                    None
+                };
+
+                for name in names.drain(..) {
+                    match range.clone() {
+                        Some(range) => ports.extend(range.map(|i| format!("{name}({i})"))),
+                        None => ports.push(name),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(ports)
+}
+
+/// Collects the values of all attribute specifications, by the name of the attribute.
+///
+/// Concatenated strings are joined, other values are kept as written.
+fn parse_attributes(tokens: &[Token]) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        if !is_keyword(token, "attribute") {
+            continue;
+        }
+
+        let Some([Token::Word(name), of, ..]) = tokens.get(index + 1..) else {
+            continue;
+        };
+        // Attribute declarations don't have a value.
+        if !is_keyword(of, "of") {
+            continue;
+        }
+
+        let Some(value_start) = tokens[index..]
+            .iter()
+            .position(|token| is_keyword(token, "is"))
+        else {
+            continue;
+        };
+
+        let mut value = String::new();
+        for token in tokens[index + value_start + 1..]
+            .iter()
+            .take_while(|token| **token != Token::Punct(';'))
+        {
+            match token {
+                Token::Str(string) => value.push_str(string),
+                Token::Word(word) => value.push_str(word),
+                Token::Punct('&') => {}
+                Token::Punct(c) => value.push(*c),
+            }
+        }
+
+        attributes.entry(name.to_ascii_uppercase()).or_insert(value);
+    }
+
+    attributes
+}
+
+/// Parses a list of entries in the form `NAME (FIELD, FIELD, ...)`, separated by commas.
+fn parse_entries(
+    attribute: &'static str,
+    list: &str,
+) -> Result<Vec<(String, Vec<String>)>, BsdlError> {
+    let mut entries = Vec::new();
+    let mut rest = list.trim();
+
+    while !rest.is_empty() {
+        let (name, fields) = rest
+            .split_once('(')
+            .ok_or_else(|| invalid(attribute, format!("missing '(' in '{rest}'")))?;
+
+        // Fields can contain parentheses themselves, like the port `D(0)`.
+        let mut depth = 0;
+        let end = fields
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' if depth == 0 => return true,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                false
+            })
+            .map(|(end, _)| end)
+            .ok_or_else(|| invalid(attribute, format!("missing ')' in '{rest}'")))?;
+
+        entries.push((
+            name.trim().to_string(),
+            split_fields(&fields[..end])
+                .map(|field| field.trim().to_string())
+                .collect(),
+        ));
+
+        rest = fields[end + 1..]
+            .trim_start()
+            .trim_start_matches(',')
+            .trim();
+    }
+
+    Ok(entries)
+}
+
+/// Splits a comma separated list, ignoring commas in parentheses.
+fn split_fields(fields: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    fields.split(move |c| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => return true,
+            _ => {}
+        }
+        false
+    })
+}
+
+fn parse_instructions(opcodes: &str) -> Result<HashMap<String, u32>, BsdlError> {
+    let mut instructions = HashMap::new();
+
+    for (name, patterns) in parse_entries("INSTRUCTION_OPCODE", opcodes)? {
+        // If an instruction has multiple opcodes, any of them can be used.
+        let pattern = patterns
+            .first()
+            .ok_or_else(|| invalid("INSTRUCTION_OPCODE", format!("no opcode for {name}")))?;
+        let opcode = u32::from_str_radix(&pattern.replace(['X', 'x'], "0"), 2)
+            .map_err(|_| invalid("INSTRUCTION_OPCODE", format!("invalid opcode {pattern}")))?;
+
+        instructions.insert(name.to_ascii_uppercase(), opcode);
+    }
+
+    Ok(instructions)
+}
+
+fn parse_idcode(idcode: &str) -> Result<IdCodePattern, BsdlError> {
+    if idcode.len() != 32 {
+        return Err(invalid("IDCODE_REGISTER", "not 32 bits long"));
+    }
+
+    let mut pattern = IdCodePattern { value: 0, mask: 0 };
+    // The most significant bit is written first.
+    for (bit, c) in idcode.chars().rev().enumerate() {
+        match c {
+            '0' => pattern.mask |= 1 << bit,
+            '1' => {
+                pattern.mask |= 1 << bit;
+                pattern.value |= 1 << bit;
+            }
+            'X' | 'x' => {}
+            other => return Err(invalid("IDCODE_REGISTER", format!("invalid bit {other}"))),
+        }
+    }
+
+    Ok(pattern)
+}
+
+fn parse_bit(bit: &str) -> Option<bool> {
+    match bit {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+fn parse_cells(register: &str, boundary_length: usize) -> Result<Vec<BoundaryCell>, BsdlError> {
+    let mut cells: Vec<Option<BoundaryCell>> = vec![None; boundary_length];
+
+    for (number, fields) in parse_entries("BOUNDARY_REGISTER", register)? {
+        let number: usize = number
+            .parse()
+            .map_err(|_| invalid("BOUNDARY_REGISTER", format!("invalid cell number {number}")))?;
+
+        let [cell_type, port, function, safe, control @ ..] = fields.as_slice() else {
+            return Err(invalid(
+                "BOUNDARY_REGISTER",
+                format!("cell {number} has too few fields"),
+            ));
+        };
+
+        let function = CellFunction::parse(function).ok_or_else(|| {
+            invalid(
+                "BOUNDARY_REGISTER",
+                format!("cell {number} has the invalid function {function}"),
+            )
+        })?;
+
+        let control = match control {
+            [] => None,
+            [cell, disable, ..] => {
+                let cell = cell
+                    .parse()
+                    .ok()
+                    .filter(|&cell: &usize| cell < boundary_length)
+                    .ok_or_else(|| {
+                        invalid(
+                            "BOUNDARY_REGISTER",
+                            format!("cell {number} has the invalid control cell {cell}"),
+                        )
+                    })?;
+                let disable = parse_bit(disable).ok_or_else(|| {
+                    invalid(
+                        "BOUNDARY_REGISTER",
+                        format!("cell {number} has the invalid disable value {disable}"),
+                    )
+                })?;
+                Some((cell, disable))
+            }
+            [_] => {
+                return Err(invalid(
+                    "BOUNDARY_REGISTER",
+                    format!("cell {number} has no disable value"),
+                ))
+            }
+        };
+
+        let cell = cells.get_mut(number).ok_or_else(|| {
+            invalid(
+                "BOUNDARY_REGISTER",
+                format!("cell {number} is outside of the boundary register"),
+            )
+        })?;
+
+        *cell = Some(BoundaryCell {
+            number,
+            cell_type: cell_type.clone(),
+            port: (port != "*").then(|| normalize_port(port)),
+            function,
+            safe: parse_bit(safe),
+            control,
+        });
+    }
+
+    cells
+        .into_iter()
+        .enumerate()
+        .map(|(number, cell)| {
+            cell.ok_or_else(|| invalid("BOUNDARY_REGISTER", format!("cell {number} is missing")))
+        })
+        .collect()
+}
+
+fn collect_pins(ports: &[String], cells: &[BoundaryCell]) -> Vec<Pin> {
+    let mut pins: Vec<Pin> = Vec::new();
+
+    // Ports which aren't declared are added after the declared ones.
+    let cell_ports = cells.iter().filter_map(|cell| cell.port.as_ref());
+    for name in ports.iter().chain(cell_ports) {
+        if pins.iter().any(|pin| pin.name.eq_ignore_ascii_case(name)) {
+            continue;
+        }
+
+        let mut pin = Pin {
+            name: name.clone(),
+            input: None,
+            output: None,
+            control: None,
+        };
+
+        for cell in cells {
+            if !cell
+                .port
+                .as_ref()
+                .is_some_and(|port| port.eq_ignore_ascii_case(name))
+            {
+                continue;
+            }
+
+            if cell.function.is_input() && pin.input.is_none() {
+                pin.input = Some(cell.number);
+            }
+            if cell.function.is_output() && pin.output.is_none() {
+                pin.output = Some(cell.number);
+                pin.control = cell.control;
+            }
+        }
+
+        if pin.input.is_some() || pin.output.is_some() {
+            pins.push(pin);
+        }
+    }
+
+    pins
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// A BSDL file for a small device with a tristate output, an input and a bidirectional bus.
+    pub(crate) const BSDL: &str = r#"
+-- A small example device.
+entity EXAMPLE is
+    generic (PHYSICAL_PIN_MAP : string := "QFN");
+
+    port (
+        OUT0 : out bit;
+        IN0, IN1 : in bit;
+        D : inout bit_vector(0 to 1);
+        TCK, TMS, TDI : in bit;
+        TDO : out bit
+    );
+
+    use STD_1149_1_2001.all;
+
+    attribute COMPONENT_CONFORMANCE of EXAMPLE : entity is "STD_1149_1_2001";
+    attribute PIN_MAP of EXAMPLE : entity is PHYSICAL_PIN_MAP;
+    constant QFN : PIN_MAP_STRING :=
+        "OUT0 : 1, IN0 : 2, IN1 : 3, D : (4, 5)," &
+        "TCK : 6, TMS : 7, TDI : 8, TDO : 9";
+
+    attribute TAP_SCAN_CLOCK of TCK : signal is (10.0e6, BOTH);
+
+    attribute INSTRUCTION_LENGTH of EXAMPLE : entity is 4;
+    attribute INSTRUCTION_OPCODE of EXAMPLE : entity is
+        "BYPASS  (1111)," &
+        "EXTEST  (0000)," &
+        "SAMPLE  (0010, 1010)," &
+        "PRELOAD (0010)," &
+        "IDCODE  (1110)";
+    attribute INSTRUCTION_CAPTURE of EXAMPLE : entity is "0001";
+
+    attribute IDCODE_REGISTER of EXAMPLE : entity is
+        "XXXX" &              -- Version
+        "0100000100010001" &  -- Part number
+        "00000100011" &       -- Manufacturer
+        "1";
+
+    attribute BOUNDARY_LENGTH of EXAMPLE : entity is 8;
+    attribute BOUNDARY_REGISTER of EXAMPLE : entity is
+        -- num  cell   port   function  safe  ccell  disval  rslt
+        "0     (BC_1,  *,     control,  0),                      " &
+        "1     (BC_1,  OUT0,  output3,  X,    0,     0,      Z), " &
+        "2     (BC_4,  IN0,   input,    X),                      " &
+        "3     (BC_4,  IN1,   input,    X),                      " &
+        "4     (BC_7,  D(0),  bidir,    X,    6,     0,      Z), " &
+        "5     (BC_7,  D(1),  bidir,    X,    6,     0,      Z), " &
+        "6     (BC_1,  *,     control,  0),                      " &
+        "7     (BC_1,  *,     internal, 1)                       ";
+end EXAMPLE;
+"#;
+
+    #[test]
+    fn parse_example() {
+        let bsdl = Bsdl::parse(BSDL).unwrap();
+
+        assert_eq!(bsdl.entity, "EXAMPLE");
+        assert_eq!(bsdl.instruction_length, 4);
+        assert_eq!(bsdl.instruction("extest"), Some(0b0000));
+        assert_eq!(bsdl.instruction("SAMPLE"), Some(0b0010));
+        assert_eq!(bsdl.instruction("IDCODE"), Some(0b1110));
+        assert_eq!(bsdl.instruction("HIGHZ"), None);
+
+        let idcode = bsdl.idcode.unwrap();
+        assert!(idcode.matches(0x4411_1047));
+        assert!(idcode.matches(0x0411_1047));
+        assert!(!idcode.matches(0x0411_1045));
+
+        assert_eq!(bsdl.boundary_length(), 8);
+        assert_eq!(bsdl.cells[7].function, CellFunction::Internal);
+        assert_eq!(bsdl.cells[7].safe, Some(true));
+        assert_eq!(bsdl.cells[4].cell_type, "BC_7");
+    }
+
+    #[test]
+    fn pins_are_mapped_to_cells() {
+        let bsdl = Bsdl::parse(BSDL).unwrap();
+
+        let names: Vec<_> = bsdl.pins.iter().map(|pin| pin.name.as_str()).collect();
+        assert_eq!(names, ["OUT0", "IN0", "IN1", "D(0)", "D(1)"]);
+
+        assert_eq!(
+            bsdl.pin("out0"),
+            Some(&Pin {
+                name: "OUT0".to_string(),
+                input: None,
+                output: Some(1),
+                control: Some((0, false)),
+            })
+        );
+        assert_eq!(
+            bsdl.pin("D (1)"),
+            Some(&Pin {
+                name: "D(1)".to_string(),
+                input: Some(5),
+                output: Some(5),
+                control: Some((6, false)),
+            })
+        );
+        assert_eq!(bsdl.pin("TDO"), None);
+    }
+
+    #[test]
+    fn missing_cells_are_rejected() {
+        let bsdl = BSDL.replace("is 8;", "is 9;");
+
+        assert!(matches!(
+            Bsdl::parse(&bsdl),
+            Err(BsdlError::InvalidAttribute("BOUNDARY_REGISTER", _))
+        ));
+    }
+
+    #[test]
+    fn control_cells_outside_of_the_boundary_register_are_rejected() {
+        let bsdl = BSDL.replace(
+            "X,    6,     0,      Z), \" &\n        \"6",
+            "X,    8,     0,      Z), \" &\n        \"6",
+        );
+        assert_ne!(bsdl, BSDL);
+
+        assert!(matches!(
+            Bsdl::parse(&bsdl),
+            Err(BsdlError::InvalidAttribute("BOUNDARY_REGISTER", _))
+        ));
+    }
+}
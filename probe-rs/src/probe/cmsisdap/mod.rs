@@ -15,7 +15,7 @@ use crate::{
             general::info::{CapabilitiesCommand, PacketCountCommand, SWOTraceBufferSizeCommand},
             CmsisDapError, RequestError,
         },
-        BatchCommand, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector,
+        BatchCommand, DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, JTAGAccess,
         JtagChainItem, ProbeFactory, WireProtocol,
     },
     CoreStatus,
@@ -55,7 +55,9 @@ use std::{fmt::Write, time::Duration};
 
 use bitvec::prelude::*;
 
-use super::common::{extract_idcodes, extract_ir_lengths, ScanChainError};
+use super::common::{
+    extract_idcodes, extract_ir_lengths, JtagDriverState, JtagState, RawJtagIo, ScanChainError,
+};
 
 /// A factory for creating [`CmsisDap`] probes.
 #[derive(Debug)]
@@ -99,6 +101,11 @@ pub struct CmsisDap {
     scan_chain: Option<Vec<ScanChainElement>>,
 
     batch: Vec<BatchCommand>,
+
+    jtag_state: JtagDriverState,
+    /// JTAG bits which have not been sent to the probe yet, as `(tms, tdi, capture)`.
+    jtag_bits: Vec<(bool, bool, bool)>,
+    jtag_captured: BitVec<u8, Lsb0>,
 }
 
 impl std::fmt::Debug for CmsisDap {
@@ -152,6 +159,9 @@ impl CmsisDap {
             speed_khz: 1_000,
             scan_chain: None,
             batch: Vec::new(),
+            jtag_state: JtagDriverState::default(),
+            jtag_bits: Vec::new(),
+            jtag_captured: BitVec::new(),
         })
     }
 
@@ -216,6 +226,7 @@ impl CmsisDap {
         let sequence = JtagSequence::no_capture(false, &bitvec![u8, Lsb0; 0; 1])?;
         let sequences = vec![sequence];
         self.send_jtag_sequences(JtagSequenceRequest::new(sequences)?)?;
+        self.jtag_state.state = JtagState::Idle;
 
         Ok(())
     }
@@ -954,6 +965,125 @@ impl DebugProbe for CmsisDap {
     fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
         Some(self)
     }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.active_protocol() == Some(WireProtocol::Jtag) {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+impl CmsisDap {
+    /// Sends the buffered JTAG bits to the probe, and stores the captured TDO bits.
+    fn flush_jtag_bits(&mut self) -> Result<(), DebugProbeError> {
+        if self.jtag_bits.is_empty() {
+            return Ok(());
+        }
+        self.connect_if_needed()?;
+
+        let bits = std::mem::take(&mut self.jtag_bits);
+        let packet_size = self.packet_size as usize;
+        let captured = transfer_jtag_bits(&bits, packet_size, |request| {
+            self.send_jtag_sequences(request)
+        })?;
+        self.jtag_captured.extend_from_bitslice(&captured);
+
+        Ok(())
+    }
+}
+
+/// Sends JTAG bits, given as `(tms, tdi, capture)`, with DAP_JTAG_Sequence requests and
+/// responses which fit into packets of `packet_size` bytes, and returns the captured TDO bits.
+fn transfer_jtag_bits(
+    bits: &[(bool, bool, bool)],
+    packet_size: usize,
+    mut send: impl FnMut(JtagSequenceRequest) -> Result<Vec<u8>, CmsisDapError>,
+) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+    // Each sequence holds up to 64 bits with the same TMS value and capture setting.
+    let mut sequences = vec![];
+    for chunk in bits.chunk_by(|a, b| a.0 == b.0 && a.2 == b.2) {
+        for part in chunk.chunks(64) {
+            let tdi = part.iter().map(|(_, tdi, _)| *tdi).collect::<BitVec<u8>>();
+            let (tms, _, capture) = part[0];
+            let sequence = if capture {
+                JtagSequence::capture(tms, &tdi)?
+            } else {
+                JtagSequence::no_capture(tms, &tdi)?
+            };
+            sequences.push((part.len(), capture, sequence));
+        }
+    }
+
+    // Split the sequences into requests and responses which fit into a single packet.
+    let mut captured = BitVec::new();
+    let mut start = 0;
+    while start < sequences.len() {
+        let mut request_len = 2;
+        let mut response_len = 2;
+        let mut end = start;
+        while end < sequences.len() && end - start < u8::MAX as usize {
+            let (len, capture, _) = sequences[end];
+            let bytes = len.div_ceil(8);
+            let response_bytes = if capture { bytes } else { 0 };
+            if end > start
+                && (request_len + 1 + bytes > packet_size
+                    || response_len + response_bytes > packet_size)
+            {
+                break;
+            }
+            request_len += 1 + bytes;
+            response_len += response_bytes;
+            end += 1;
+        }
+
+        let batch = &sequences[start..end];
+        let request = JtagSequenceRequest::new(batch.iter().map(|(_, _, s)| *s).collect())?;
+        let tdo = send(request)?;
+
+        let mut offset = 0;
+        for (len, capture, _) in batch {
+            if *capture {
+                let bytes = len.div_ceil(8);
+                let data = tdo.get(offset..offset + bytes).ok_or_else(|| {
+                    DebugProbeError::Other("The probe returned too few TDO bits".to_string())
+                })?;
+                captured.extend_from_bitslice(&data.view_bits::<Lsb0>()[..*len]);
+                offset += bytes;
+            }
+        }
+
+        start = end;
+    }
+
+    Ok(captured)
+}
+
+impl RawJtagIo for CmsisDap {
+    fn state_mut(&mut self) -> &mut JtagDriverState {
+        &mut self.jtag_state
+    }
+
+    fn state(&self) -> &JtagDriverState {
+        &self.jtag_state
+    }
+
+    fn shift_bit(&mut self, tms: bool, tdi: bool, capture: bool) -> Result<(), DebugProbeError> {
+        self.jtag_state.state.update(tms);
+        self.jtag_bits.push((tms, tdi, capture));
+
+        if self.jtag_bits.len() >= 8 * self.packet_size as usize {
+            self.flush_jtag_bits()?;
+        }
+
+        Ok(())
+    }
+
+    fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+        self.flush_jtag_bits()?;
+        Ok(std::mem::take(&mut self.jtag_captured))
+    }
 }
 
 impl RawDapAccess for CmsisDap {
@@ -1100,7 +1230,12 @@ impl RawDapAccess for CmsisDap {
                     })
                     .as_deref(),
             )?;
-            chain.iter().map(|item| item.irlen as u8).collect()
+            let ir_lengths = chain.iter().map(|item| item.irlen as u8).collect();
+            self.jtag_state.scan_chain = chain;
+            if !self.jtag_state.scan_chain.is_empty() {
+                self.select_target(0)?;
+            }
+            ir_lengths
         };
         tracing::info!("Configuring JTAG with ir lengths: {:?}", ir_lengths);
         self.send_jtag_configure(JtagConfigureRequest::new(ir_lengths)?)?;
@@ -1163,7 +1298,11 @@ impl RawDapAccess for CmsisDap {
     }
 }
 
-impl DapProbe for CmsisDap {}
+impl DapProbe for CmsisDap {
+    fn use_overrun_detect(&self) -> bool {
+        false
+    }
+}
 
 impl SwoAccess for CmsisDap {
     fn enable_swo(&mut self, config: &SwoConfig) -> Result<(), ArmError> {
@@ -1291,3 +1430,144 @@ impl From<ScanChainError> for CmsisDapError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{commands::Request, *};
+
+    /// A sequence of a DAP_JTAG_Sequence request, as `(tck_cycles, tms, capture, tdi)`.
+    type DecodedSequence = (usize, bool, bool, BitVec<u8, Lsb0>);
+
+    /// Transfers `bits` with a probe which returns the TDI bits on TDO, checks that every
+    /// request and response fits into a packet, and returns the decoded requests and the
+    /// captured bits.
+    fn transfer(
+        bits: &[(bool, bool, bool)],
+        packet_size: usize,
+    ) -> (Vec<Vec<DecodedSequence>>, BitVec<u8, Lsb0>) {
+        let mut requests = vec![];
+        let captured = transfer_jtag_bits(bits, packet_size, |request| {
+            let mut buffer = vec![0; 4096];
+            let len = request.to_bytes(&mut buffer).unwrap();
+            // The command ID precedes the request.
+            let packet_len = 1 + len;
+            assert!(packet_len <= packet_size, "request of {packet_len} bytes");
+
+            let mut sequences = vec![];
+            let mut tdo = vec![];
+            let mut offset = 1;
+            for _ in 0..buffer[0] {
+                let info = buffer[offset];
+                let tck_cycles = match info & 0x3F {
+                    0 => 64,
+                    cycles => cycles as usize,
+                };
+                let bytes = tck_cycles.div_ceil(8);
+                let data = &buffer[offset + 1..offset + 1 + bytes];
+                let capture = info & 0x80 != 0;
+                if capture {
+                    tdo.extend_from_slice(data);
+                }
+                let tdi = data.view_bits::<Lsb0>()[..tck_cycles].to_bitvec();
+                sequences.push((tck_cycles, info & 0x40 != 0, capture, tdi));
+                offset += 1 + bytes;
+            }
+            assert_eq!(offset, len);
+            // The response starts with the command ID and the status.
+            assert!(
+                2 + tdo.len() <= packet_size,
+                "response of {} bytes",
+                tdo.len()
+            );
+
+            requests.push(sequences);
+            Ok(tdo)
+        })
+        .unwrap();
+
+        (requests, captured)
+    }
+
+    fn tdi(index: usize) -> bool {
+        index % 3 == 0 || index % 7 == 0
+    }
+
+    #[test]
+    fn bits_are_grouped_by_tms_and_capture() {
+        let bits: Vec<_> = (0..70)
+            .map(|i| (false, tdi(i), true))
+            .chain((70..73).map(|i| (true, tdi(i), false)))
+            .chain((73..78).map(|i| (false, tdi(i), false)))
+            .collect();
+
+        let (requests, _) = transfer(&bits, 64);
+
+        assert_eq!(requests.len(), 1);
+        let sequences: Vec<_> = requests[0]
+            .iter()
+            .map(|(len, tms, capture, _)| (*len, *tms, *capture))
+            .collect();
+        assert_eq!(
+            sequences,
+            [
+                (64, false, true),
+                (6, false, true),
+                (3, true, false),
+                (5, false, false)
+            ]
+        );
+        let sent: BitVec<u8, Lsb0> = requests[0]
+            .iter()
+            .flat_map(|(_, _, _, tdi)| tdi.iter().by_vals())
+            .collect();
+        let expected: BitVec<u8, Lsb0> = bits.iter().map(|(_, tdi, _)| *tdi).collect();
+        assert_eq!(sent, expected);
+    }
+
+    #[test]
+    fn requests_are_split_to_fit_into_packets() {
+        let bits: Vec<_> = (0..200).map(|i| (false, tdi(i), true)).collect();
+
+        // A sequence of 64 bits takes 9 bytes, so only one of them fits into a packet, but the
+        // last 8 bits still fit after it.
+        let (requests, captured) = transfer(&bits, 16);
+
+        let lengths: Vec<Vec<_>> = requests
+            .iter()
+            .map(|sequences| sequences.iter().map(|(len, ..)| *len).collect())
+            .collect();
+        assert_eq!(lengths, [vec![64], vec![64], vec![64, 8]]);
+        assert_eq!(captured.len(), 200);
+    }
+
+    #[test]
+    fn requests_have_at_most_255_sequences() {
+        // Every bit toggles TMS, so each one needs its own sequence.
+        let bits: Vec<_> = (0..600).map(|i| (i % 2 == 0, tdi(i), false)).collect();
+
+        let (requests, captured) = transfer(&bits, 4096);
+
+        let counts: Vec<_> = requests.iter().map(Vec::len).collect();
+        assert_eq!(counts, [255, 255, 90]);
+        assert!(captured.is_empty());
+    }
+
+    #[test]
+    fn captured_bits_are_sliced_from_tdo() {
+        let bits: Vec<_> = (0..3)
+            .map(|i| (false, tdi(i), true))
+            .chain((3..8).map(|i| (false, tdi(i), false)))
+            .chain((8..18).map(|i| (true, tdi(i), true)))
+            .chain((18..90).map(|i| (false, tdi(i), true)))
+            .collect();
+
+        let (_, captured) = transfer(&bits, 64);
+
+        let expected: BitVec<u8, Lsb0> = bits
+            .iter()
+            .filter(|(_, _, capture)| *capture)
+            .map(|(_, tdi, _)| *tdi)
+            .collect();
+        assert_eq!(captured, expected);
+    }
+}
@@ -1,5 +1,8 @@
 //! Crate-public structures and utilities to be shared between probes.
 
+#[cfg(test)]
+pub(crate) mod tap_model;
+
 use std::iter;
 
 use bitfield::bitfield;
@@ -8,7 +11,8 @@ use probe_rs_target::ScanChainElement;
 
 use crate::probe::{
     BatchExecutionError, ChainParams, CommandResult, DebugProbe, DebugProbeError,
-    DeferredResultSet, JTAGAccess, JtagChainItem, JtagCommand, JtagCommandQueue,
+    DeferredResultSet, JTAGAccess, JtagChainItem, JtagCommand, JtagCommandQueue, JtagRegister,
+    JtagStableState,
 };
 
 pub(crate) fn bits_to_byte(bits: impl IntoIterator<Item = bool>) -> u32 {
@@ -371,6 +375,17 @@ impl JtagState {
     }
 }
 
+impl From<JtagStableState> for JtagState {
+    fn from(state: JtagStableState) -> Self {
+        match state {
+            JtagStableState::Reset => Self::Reset,
+            JtagStableState::Idle => Self::Idle,
+            JtagStableState::DrPause => Self::Dr(RegisterState::Pause),
+            JtagStableState::IrPause => Self::Ir(RegisterState::Pause),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct JtagDriverState {
     pub state: JtagState,
@@ -752,6 +767,53 @@ impl<Probe: DebugProbe + RawJtagIo + 'static> JTAGAccess for Probe {
         Ok(result)
     }
 
+    fn run_test(&mut self, state: JtagStableState, cycles: u32) -> Result<(), DebugProbeError> {
+        jtag_move_to_state(self, state.into())?;
+
+        // TMS has to stay high to remain in Test-Logic-Reset, and low in all other stable states.
+        let tms = iter::repeat(state == JtagStableState::Reset).take(cycles as usize);
+        self.shift_bits(tms, iter::repeat(false), iter::repeat(false))?;
+        self.read_captured_bits()?;
+
+        Ok(())
+    }
+
+    fn shift_raw(
+        &mut self,
+        register: JtagRegister,
+        data: &[u8],
+        len: u32,
+        end_state: JtagStableState,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        let len = len as usize;
+        if data.len() * 8 < len {
+            return Err(DebugProbeError::Other(format!(
+                "Invalid data length. Bits: {}, expected: {}",
+                data.len() * 8,
+                len
+            )));
+        }
+
+        if len > 0 {
+            let shift = match register {
+                JtagRegister::Ir => JtagState::Ir(RegisterState::Shift),
+                JtagRegister::Dr => JtagState::Dr(RegisterState::Shift),
+            };
+            jtag_move_to_state(self, shift)?;
+
+            // The last bit is shifted when leaving the shift state.
+            let tms = iter::repeat(false).take(len - 1).chain(iter::once(true));
+            let tdi = data.view_bits::<Lsb0>()[..len].iter().map(|b| *b);
+            self.shift_bits(tms, tdi, iter::repeat(true))?;
+        }
+
+        jtag_move_to_state(self, end_state.into())?;
+
+        let mut response = self.read_captured_bits()?;
+        response.force_align();
+        Ok(response.into_vec())
+    }
+
     #[tracing::instrument(skip(self, writes))]
     fn write_register_batch(
         &mut self,
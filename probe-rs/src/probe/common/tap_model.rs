@@ -0,0 +1,177 @@
+//! A model of a JTAG TAP, which JTAG probe drivers can be tested against.
+
+use bitvec::prelude::*;
+use probe_rs_target::ScanChainElement;
+
+use super::{JtagDriverState, JtagState, RawJtagIo, RegisterState};
+use crate::probe::{DebugProbe, DebugProbeError, WireProtocol};
+
+/// The instruction which selects the `IDCODE` register.
+pub(crate) const IDCODE_INSTRUCTION: u32 = 0x01;
+
+/// A TAP with a 5 bit instruction register, and the `IDCODE` and `BYPASS` data registers.
+///
+/// All instructions other than [`IDCODE_INSTRUCTION`] select the `BYPASS` register.
+#[derive(Debug)]
+pub(crate) struct TapModel {
+    idcode: u32,
+    state: JtagState,
+    instruction: u32,
+
+    /// The shift register, with the bit which is shifted out next at bit 0.
+    shift: u64,
+    shift_len: usize,
+}
+
+impl TapModel {
+    pub fn new(idcode: u32) -> Self {
+        Self {
+            idcode,
+            state: JtagState::Reset,
+            instruction: IDCODE_INSTRUCTION,
+            shift: 0,
+            shift_len: 0,
+        }
+    }
+
+    /// Returns the value of TDO, which is shifted out of the register at the next clock.
+    pub fn tdo(&self) -> bool {
+        matches!(
+            self.state,
+            JtagState::Dr(RegisterState::Shift) | JtagState::Ir(RegisterState::Shift)
+        ) && self.shift & 1 == 1
+    }
+
+    /// Clocks the TAP once.
+    pub fn clock(&mut self, tms: bool, tdi: bool) {
+        if let JtagState::Dr(RegisterState::Shift) | JtagState::Ir(RegisterState::Shift) =
+            self.state
+        {
+            self.shift = (self.shift >> 1) | (u64::from(tdi) << (self.shift_len - 1));
+        }
+
+        self.state.update(tms);
+
+        match self.state {
+            JtagState::Reset => self.instruction = IDCODE_INSTRUCTION,
+            JtagState::Ir(RegisterState::Capture) => {
+                self.shift = 0b00001;
+                self.shift_len = 5;
+            }
+            JtagState::Ir(RegisterState::Update) => self.instruction = self.shift as u32,
+            JtagState::Dr(RegisterState::Capture) => {
+                (self.shift, self.shift_len) = match self.instruction {
+                    IDCODE_INSTRUCTION => (u64::from(self.idcode), 32),
+                    _ => (0, 1),
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A probe which is directly connected to a [`TapModel`].
+#[derive(Debug)]
+pub(crate) struct TapProbe {
+    pub tap: TapModel,
+    pub speed_khz: u32,
+    jtag_state: JtagDriverState,
+    captured: BitVec<u8, Lsb0>,
+}
+
+impl TapProbe {
+    pub fn new(idcode: u32) -> Self {
+        Self {
+            tap: TapModel::new(idcode),
+            speed_khz: 1000,
+            jtag_state: JtagDriverState::default(),
+            captured: BitVec::new(),
+        }
+    }
+}
+
+impl RawJtagIo for TapProbe {
+    fn state_mut(&mut self) -> &mut JtagDriverState {
+        &mut self.jtag_state
+    }
+
+    fn state(&self) -> &JtagDriverState {
+        &self.jtag_state
+    }
+
+    fn shift_bit(&mut self, tms: bool, tdi: bool, capture: bool) -> Result<(), DebugProbeError> {
+        if capture {
+            self.captured.push(self.tap.tdo());
+        }
+        self.tap.clock(tms, tdi);
+        self.jtag_state.state.update(tms);
+        Ok(())
+    }
+
+    fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+        Ok(std::mem::take(&mut self.captured))
+    }
+}
+
+impl DebugProbe for TapProbe {
+    fn get_name(&self) -> &str {
+        "TAP model"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.speed_khz = speed_khz;
+        Ok(speed_khz)
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        self.jtag_state.expected_scan_chain = Some(scan_chain);
+        Ok(())
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        Ok(self
+            .jtag_state
+            .expected_scan_chain
+            .as_deref()
+            .unwrap_or(&[]))
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        match protocol {
+            WireProtocol::Jtag => Ok(()),
+            _ => Err(DebugProbeError::UnsupportedProtocol(protocol)),
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        Some(WireProtocol::Jtag)
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+}
@@ -1,4 +1,6 @@
 #![allow(missing_docs)] // Don't require docs for test code
+mod simulator;
+
 use std::{
     cell::RefCell,
     collections::{BTreeSet, VecDeque},
@@ -13,17 +15,20 @@ use crate::{
         ap::memory_ap::{mock::MockMemoryAp, MemoryAp},
         armv8m::Dhcsr,
         communication_interface::{
-            ArmDebugState, Initialized, SwdSequence, Uninitialized, UninitializedArmProbe,
+            ArmDebugState, DapProbe, Initialized, SwdSequence, Uninitialized, UninitializedArmProbe,
         },
         memory::{adi_v5_memory_interface::ADIMemoryInterface, ArmMemoryInterface},
         sequences::ArmDebugSequence,
         ArmError, ArmProbeInterface, DapAccess, DpAddress, FullyQualifiedApAddress, PortType,
         RawDapAccess, SwoAccess,
     },
+    config::Target,
     probe::{DebugProbe, DebugProbeError, Probe, WireProtocol},
     Error, MemoryInterface, MemoryMappedRegister,
 };
 
+use simulator::SimulatedCore;
+
 /// This is a mock probe which can be used for mocking things in tests or for dry runs.
 #[allow(clippy::type_complexity)]
 pub struct FakeProbe {
@@ -46,6 +51,8 @@ enum MockedAp {
     MemoryAp(MockMemoryAp),
     /// Mock an ARM core behind a memory AP
     Core(MockCore),
+    /// Simulate an ARM core, together with the memory of its target
+    Simulated(Box<SimulatedCore>),
 }
 
 struct MockCore {
@@ -263,6 +270,19 @@ impl FakeProbe {
         }
     }
 
+    /// Fake probe with a simulated target
+    ///
+    /// The default core of `target` is simulated, which has to be a Cortex-M core. Its memory
+    /// follows the memory map of `target`, so it can be flashed with the flash algorithms of
+    /// `target`, and code in it can be run and debugged. Other cores of `target` access the same
+    /// simulated core, and access ports cannot be accessed directly.
+    pub fn with_simulated_target(target: &Target) -> Result<Self, DebugProbeError> {
+        Ok(FakeProbe {
+            memory_ap: MockedAp::Simulated(Box::new(SimulatedCore::new(target)?)),
+            ..FakeProbe::new()
+        })
+    }
+
     /// This sets the read handler for DAP register reads.
     /// Can be used to hook into the read.
     pub fn set_dap_register_read_handler(
@@ -295,6 +315,11 @@ impl FakeProbe {
         expected_ap: &FullyQualifiedApAddress,
         expected_address: u8,
     ) -> Result<u32, ArmError> {
+        // Only the core of a simulated target is simulated, not its access ports.
+        if let MockedAp::Simulated(_) = self.memory_ap {
+            return Err(ArmError::NotImplemented("read_raw_ap_register"));
+        }
+
         let operation = self.next_operation();
 
         match operation {
@@ -402,11 +427,21 @@ impl DebugProbe for FakeProbe {
     fn has_arm_interface(&self) -> bool {
         true
     }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        Some(self)
+    }
 }
 
 impl RawDapAccess for FakeProbe {
     /// Reads the DAP register on the specified port and address
     fn raw_read_register(&mut self, port: PortType, addr: u8) -> Result<u32, ArmError> {
+        if let (None, MockedAp::Simulated(core)) =
+            (&self.dap_register_read_handler, &mut self.memory_ap)
+        {
+            return core.read_dap_register(port, addr);
+        }
+
         let handler = self.dap_register_read_handler.as_ref().unwrap();
 
         handler(port, addr)
@@ -414,6 +449,12 @@ impl RawDapAccess for FakeProbe {
 
     /// Writes a value to the DAP register on the specified port and address
     fn raw_write_register(&mut self, port: PortType, addr: u8, value: u32) -> Result<(), ArmError> {
+        if let (None, MockedAp::Simulated(core)) =
+            (&self.dap_register_write_handler, &mut self.memory_ap)
+        {
+            return core.write_dap_register(port, addr, value);
+        }
+
         let handler = self.dap_register_write_handler.as_ref().unwrap();
 
         handler(port, addr, value)
@@ -424,6 +465,11 @@ impl RawDapAccess for FakeProbe {
     }
 
     fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        // The simulated debug port is always in SWD mode.
+        if let MockedAp::Simulated(_) = self.memory_ap {
+            return Ok(());
+        }
+
         todo!()
     }
 
@@ -433,6 +479,10 @@ impl RawDapAccess for FakeProbe {
         _pin_select: u32,
         _pin_wait: u32,
     ) -> Result<u32, DebugProbeError> {
+        if let MockedAp::Simulated(_) = self.memory_ap {
+            return Ok(0);
+        }
+
         todo!()
     }
 
@@ -445,6 +495,12 @@ impl RawDapAccess for FakeProbe {
     }
 }
 
+impl DapProbe for FakeProbe {
+    fn use_overrun_detect(&self) -> bool {
+        false
+    }
+}
+
 #[derive(Debug)]
 struct FakeArmInterface<S: ArmDebugState> {
     probe: Box<FakeProbe>,
@@ -523,6 +579,7 @@ impl ArmProbeInterface for FakeArmInterface<Initialized> {
                 Ok(Box::new(memory) as _)
             }
             MockedAp::Core(ref mut core) => Ok(Box::new(core) as _),
+            MockedAp::Simulated(ref mut core) => Ok(Box::new(core.as_mut()) as _),
         }
     }
 
@@ -0,0 +1,901 @@
+//! A simulated Cortex-M target, which allows flashing, running and debugging firmware without
+//! any hardware.
+//!
+//! The simulation covers what probe-rs needs from a target:
+//!
+//! - The memory follows the memory map of the target. Non-volatile memory starts out erased,
+//!   and can only be changed by calling a flash algorithm. All other memory starts out zeroed.
+//! - The debug registers DHCSR, DCRSR, DCRDR, DEMCR and DFSR, the flash patch breakpoint unit,
+//!   the DWT comparators and a system reset through AIRCR are emulated. The DWT comparators only
+//!   match data accesses, and halt the core after the instruction which made the access.
+//! - Calls to the flash algorithms of the target are emulated, so flashing does not depend on
+//!   the flash controller of the chip.
+//! - All other code is run by a simple executor for the Thumb instructions of ARMv6-M. When it
+//!   reaches an instruction it cannot execute, the core keeps running, but does not execute any
+//!   further instructions.
+//!
+//! Code only runs while the debugger polls the status of the core, so the simulation never
+//! runs ahead of the debugger.
+//!
+//! Debug sequences see the access port of the default core. Probes which access the debug and
+//! access port registers directly see a SW-DP with a single AHB-AP in front of the core, so
+//! sessions through them can be recorded.
+
+mod dap;
+mod thumb;
+
+use std::{collections::HashMap, ops::Range};
+
+use probe_rs_target::{
+    CoreType, FlashProperties, MemoryRegion, RawFlashAlgorithm, TransferEncoding,
+};
+
+use crate::{
+    architecture::arm::{
+        ap::memory_ap::{mock::MockMemoryAp, MemoryAp},
+        communication_interface::{Initialized, SwdSequence},
+        memory::ArmMemoryInterface,
+        ArmCommunicationInterface, ArmError, DapError,
+    },
+    config::{CoreExt, Target},
+    probe::DebugProbeError,
+    CoreStatus, MemoryInterface, WatchpointKind,
+};
+
+use thumb::{DataAccess, Stop, LR, PC, SP, XPSR};
+
+/// The number of instructions executed every time the status of a running core is read.
+const INSTRUCTIONS_PER_POLL: usize = 100_000;
+
+/// The number of flash patch breakpoint comparators.
+const BREAKPOINT_COUNT: usize = 4;
+
+/// The number of DWT comparators.
+const WATCHPOINT_COUNT: usize = 4;
+
+/// The number of registers which can be selected with DCRSR.
+const REGISTER_COUNT: usize = 128;
+
+const CPUID: u64 = 0xE000_ED00;
+const VTOR: u64 = 0xE000_ED08;
+const AIRCR: u64 = 0xE000_ED0C;
+const DFSR: u64 = 0xE000_ED30;
+const DHCSR: u64 = 0xE000_EDF0;
+const DCRSR: u64 = 0xE000_EDF4;
+const DCRDR: u64 = 0xE000_EDF8;
+const DEMCR: u64 = 0xE000_EDFC;
+const DWT_CTRL: u64 = 0xE000_1000;
+/// The DWT_COMPn, DWT_MASKn and DWT_FUNCTIONn registers of all comparators.
+const DWT_COMPARATORS: Range<u64> = 0xE000_1020..0xE000_1020 + 16 * WATCHPOINT_COUNT as u64;
+const FP_CTRL: u64 = 0xE000_2000;
+const FP_COMP: Range<u64> = 0xE000_2008..0xE000_2008 + 4 * BREAKPOINT_COUNT as u64;
+
+const DHCSR_DBGKEY: u32 = 0xA05F;
+const DHCSR_C_DEBUGEN: u32 = 1 << 0;
+const DHCSR_C_HALT: u32 = 1 << 1;
+const DHCSR_C_STEP: u32 = 1 << 2;
+const DHCSR_S_REGRDY: u32 = 1 << 16;
+const DHCSR_S_HALT: u32 = 1 << 17;
+const DHCSR_S_RESET_ST: u32 = 1 << 25;
+
+const DCRSR_REGWNR: u32 = 1 << 16;
+/// The DCRSR register selector of the main stack pointer, which is also the current one.
+const DCRSR_MSP: usize = 17;
+
+const DFSR_HALTED: u32 = 1 << 0;
+const DFSR_BKPT: u32 = 1 << 1;
+const DFSR_DWTTRAP: u32 = 1 << 2;
+const DFSR_VCATCH: u32 = 1 << 3;
+
+const AIRCR_VECTKEY: u32 = 0x05FA;
+const AIRCR_VECTKEYSTAT: u32 = 0xFA05 << 16;
+const AIRCR_VECTRESET: u32 = 1 << 0;
+const AIRCR_SYSRESETREQ: u32 = 1 << 2;
+
+const DEMCR_VC_CORERESET: u32 = 1 << 0;
+const DEMCR_TRCENA: u32 = 1 << 24;
+
+const DWT_FUNCTION_MATCHED: u32 = 1 << 24;
+
+const FP_CTRL_ENABLE: u32 = 1 << 0;
+const FP_CTRL_KEY: u32 = 1 << 1;
+
+/// The size of the pages the memory is allocated in.
+const PAGE_SIZE: u64 = 0x1000;
+
+/// A DWT comparator.
+#[derive(Clone, Copy, Default)]
+struct DwtComparator {
+    comp: u32,
+    mask: u32,
+    function: u32,
+    /// Set when the comparator matched, and cleared when DWT_FUNCTION is read.
+    matched: bool,
+}
+
+impl DwtComparator {
+    /// Returns whether the comparator generates a debug event for `access`.
+    fn matches(&self, core_type: CoreType, access: &DataAccess) -> bool {
+        let (kind, length) = if core_type == CoreType::Armv8m {
+            // Only comparators with the action "debug event" halt the core.
+            if (self.function >> 4) & 0b11 != 0b01 {
+                return false;
+            }
+            let kind = match self.function & 0xF {
+                0b0100 => WatchpointKind::Access,
+                0b0101 => WatchpointKind::Write,
+                0b0110 => WatchpointKind::Read,
+                _ => return false,
+            };
+            (kind, 1u64 << ((self.function >> 10) & 0b11))
+        } else {
+            let kind = match self.function & 0xF {
+                0b0101 => WatchpointKind::Read,
+                0b0110 => WatchpointKind::Write,
+                0b0111 => WatchpointKind::Access,
+                _ => return false,
+            };
+            (kind, 1u64 << (self.mask & 0x1F))
+        };
+
+        let kind_matches = match kind {
+            WatchpointKind::Read => !access.write,
+            WatchpointKind::Write => access.write,
+            WatchpointKind::Access => true,
+        };
+
+        let start = u64::from(self.comp) & !(length - 1);
+        let address = u64::from(access.address);
+
+        kind_matches && address < start + length && start < address + access.size as u64
+    }
+}
+
+/// The memory of a simulated target.
+///
+/// Memory is allocated in pages when it is first written, so the whole address space can be
+/// accessed.
+struct Memory {
+    /// The non-volatile memory regions, with their erased byte value.
+    nvm: Vec<(Range<u64>, u8)>,
+    pages: HashMap<u64, Box<[u8]>>,
+}
+
+impl Memory {
+    fn new(target: &Target) -> Self {
+        let nvm = target
+            .memory_map
+            .iter()
+            .filter_map(MemoryRegion::as_nvm_region)
+            .map(|region| {
+                let erased_byte_value = target
+                    .flash_algorithms
+                    .iter()
+                    .map(|algorithm| &algorithm.flash_properties)
+                    .find(|properties| properties.address_range.contains(&region.range.start))
+                    .map_or(0xFF, |properties| properties.erased_byte_value);
+
+                (region.range.clone(), erased_byte_value)
+            })
+            .collect();
+
+        Self {
+            nvm,
+            pages: HashMap::new(),
+        }
+    }
+
+    /// Returns the value of a byte which has never been written.
+    fn initial_value(&self, address: u64) -> u8 {
+        initial_value(&self.nvm, address)
+    }
+
+    /// Returns whether any byte in `range` is non-volatile memory.
+    fn is_nvm(&self, range: Range<u64>) -> bool {
+        self.nvm
+            .iter()
+            .any(|(nvm, _)| nvm.start < range.end && range.start < nvm.end)
+    }
+
+    fn page(&mut self, base: u64) -> &mut [u8] {
+        let nvm = &self.nvm;
+        self.pages.entry(base).or_insert_with(|| {
+            (base..base + PAGE_SIZE)
+                .map(|address| initial_value(nvm, address))
+                .collect()
+        })
+    }
+
+    fn read(&self, address: u64, data: &mut [u8]) {
+        for (address, byte) in (address..).zip(data.iter_mut()) {
+            let base = address - address % PAGE_SIZE;
+            *byte = match self.pages.get(&base) {
+                Some(page) => page[(address - base) as usize],
+                None => self.initial_value(address),
+            };
+        }
+    }
+
+    fn write(&mut self, address: u64, data: &[u8]) {
+        for (address, byte) in (address..).zip(data) {
+            let base = address - address % PAGE_SIZE;
+            self.page(base)[(address - base) as usize] = *byte;
+        }
+    }
+
+    /// Changes the memory at `address` to `data`, combining each byte with its old value
+    /// using `program`.
+    fn program(&mut self, address: u64, data: &[u8], program: impl Fn(u8, u8) -> u8) {
+        for (address, byte) in (address..).zip(data) {
+            let base = address - address % PAGE_SIZE;
+            let old = &mut self.page(base)[(address - base) as usize];
+            *old = program(*old, *byte);
+        }
+    }
+
+    fn fill(&mut self, range: Range<u64>, value: u8) {
+        let mut base = range.start - range.start % PAGE_SIZE;
+        while base < range.end {
+            let page = base..base + PAGE_SIZE;
+            // Pages which were never written only have to be allocated if their contents change.
+            if self.pages.contains_key(&base)
+                || page.clone().any(|a| self.initial_value(a) != value)
+            {
+                let start = range.start.max(page.start);
+                let end = range.end.min(page.end);
+                self.page(base)[(start - base) as usize..(end - base) as usize].fill(value);
+            }
+            base += PAGE_SIZE;
+        }
+    }
+
+    fn read_u32(&self, address: u64) -> u32 {
+        let mut bytes = [0; 4];
+        self.read(address, &mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+}
+
+/// Returns the value of a byte which has never been written, in memory with the `nvm` regions.
+fn initial_value(nvm: &[(Range<u64>, u8)], address: u64) -> u8 {
+    nvm.iter()
+        .find(|(range, _)| range.contains(&address))
+        .map_or(0, |(_, erased_byte_value)| *erased_byte_value)
+}
+
+/// A function of a flash algorithm.
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Init,
+    UnInit,
+    ProgramPage,
+    EraseSector,
+    EraseAll,
+    Verify,
+    Read,
+}
+
+impl Function {
+    /// Returns the functions of `algorithm`, with their offsets in its instructions.
+    fn entry_points(algorithm: &RawFlashAlgorithm) -> impl Iterator<Item = (Self, u64)> {
+        [
+            (Function::Init, algorithm.pc_init),
+            (Function::UnInit, algorithm.pc_uninit),
+            (Function::ProgramPage, Some(algorithm.pc_program_page)),
+            (Function::EraseSector, Some(algorithm.pc_erase_sector)),
+            (Function::EraseAll, algorithm.pc_erase_all),
+            (Function::Verify, algorithm.pc_verify),
+            (Function::Read, algorithm.pc_read),
+        ]
+        .into_iter()
+        .filter_map(|(function, offset)| Some((function, offset?)))
+    }
+}
+
+/// A simulated Cortex-M core, together with the memory of its target.
+pub(super) struct SimulatedCore {
+    core_type: CoreType,
+    /// The access port of the core. It only provides the address of the access port to debug
+    /// sequences, memory is always accessed through the simulation.
+    ap: MemoryAp,
+    memory: Memory,
+    algorithms: Vec<RawFlashAlgorithm>,
+    /// The address of the vector table after a reset.
+    boot_address: u64,
+
+    /// The core registers, indexed by their DCRSR register selector.
+    registers: [u32; REGISTER_COUNT],
+    halted: bool,
+    /// Set by a reset, and cleared when DHCSR is read.
+    reset_status: bool,
+    /// Set when the core is resumed, so a call of a flash algorithm can be detected.
+    resumed: bool,
+    /// The address of an instruction the executor does not support.
+    stalled_at: Option<u32>,
+
+    /// The control bits of DHCSR.
+    dhcsr: u32,
+    dcrdr: u32,
+    demcr: u32,
+    dfsr: u32,
+    vtor: u32,
+    breakpoints_enabled: bool,
+    breakpoints: [u32; BREAKPOINT_COUNT],
+    watchpoints: [DwtComparator; WATCHPOINT_COUNT],
+
+    dap: dap::DebugPort,
+}
+
+impl SimulatedCore {
+    /// Creates a simulation of the default core of `target`, which runs the (erased) contents
+    /// of its boot memory.
+    pub(super) fn new(target: &Target) -> Result<Self, DebugProbeError> {
+        let core_type = target.default_core().core_type;
+        if !matches!(
+            core_type,
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m
+        ) {
+            return Err(DebugProbeError::Other(format!(
+                "Only Cortex-M cores can be simulated, but {} has a {core_type:?} core",
+                target.name
+            )));
+        }
+
+        let nvm = || {
+            target
+                .memory_map
+                .iter()
+                .filter_map(MemoryRegion::as_nvm_region)
+        };
+        let boot_address = nvm()
+            .find(|region| region.is_boot_memory())
+            .or_else(|| nvm().next())
+            .map_or(0, |region| region.range.start);
+
+        // This can't fail, Cortex-M cores are always accessed through an access port.
+        let ap_address = target.default_core().memory_ap().unwrap();
+        let ap = MemoryAp::new(&mut MockMemoryAp::with_pattern_and_size(0), &ap_address)
+            .map_err(|error| DebugProbeError::Other(error.to_string()))?;
+
+        let mut core = Self {
+            core_type,
+            ap,
+            memory: Memory::new(target),
+            algorithms: target.flash_algorithms.clone(),
+            boot_address,
+
+            registers: [0; REGISTER_COUNT],
+            halted: false,
+            reset_status: false,
+            resumed: false,
+            stalled_at: None,
+
+            dhcsr: 0,
+            dcrdr: 0,
+            demcr: 0,
+            dfsr: 0,
+            vtor: 0,
+            breakpoints_enabled: false,
+            breakpoints: [0; BREAKPOINT_COUNT],
+            watchpoints: [DwtComparator::default(); WATCHPOINT_COUNT],
+
+            dap: dap::DebugPort::default(),
+        };
+        core.reset();
+        core.reset_status = false;
+
+        Ok(core)
+    }
+
+    fn cpuid(&self) -> u32 {
+        match self.core_type {
+            // Cortex-M0 r0p0
+            CoreType::Armv6m => 0x410C_C200,
+            // Cortex-M3 r2p1
+            CoreType::Armv7m => 0x412F_C231,
+            // Cortex-M33 r0p4
+            CoreType::Armv8m => 0x410F_D214,
+            // Cortex-M4 r0p1
+            _ => 0x410F_C241,
+        }
+    }
+
+    /// Resets the core, like a system reset does.
+    fn reset(&mut self) {
+        // The vector table has to be read before VTOR is reset, in case it is in RAM.
+        self.vtor = self.boot_address as u32;
+        let vector_table = u64::from(self.vtor);
+
+        self.registers = [0; REGISTER_COUNT];
+        self.registers[SP] = self.memory.read_u32(vector_table) & !0b11;
+        self.registers[PC] = self.memory.read_u32(vector_table + 4) & !1;
+        self.registers[LR] = 0xFFFF_FFFF;
+        self.registers[XPSR] = thumb::XPSR_THUMB;
+
+        self.reset_status = true;
+        self.resumed = true;
+        self.stalled_at = None;
+
+        self.halted = false;
+        if self.demcr & DEMCR_VC_CORERESET != 0 && self.dhcsr & DHCSR_C_DEBUGEN != 0 {
+            self.halt(DFSR_VCATCH);
+        }
+    }
+
+    fn halt(&mut self, reason: u32) {
+        self.halted = true;
+        self.dfsr |= reason;
+    }
+
+    fn read_register(&mut self, address: u64) -> Option<u32> {
+        let value = match address {
+            CPUID => self.cpuid(),
+            VTOR => self.vtor,
+            AIRCR => AIRCR_VECTKEYSTAT,
+            DFSR => self.dfsr,
+            DHCSR => {
+                if !self.halted {
+                    self.execute(INSTRUCTIONS_PER_POLL);
+                }
+
+                let mut dhcsr = self.dhcsr | DHCSR_S_REGRDY;
+                if self.halted {
+                    dhcsr |= DHCSR_S_HALT;
+                }
+                if std::mem::take(&mut self.reset_status) {
+                    dhcsr |= DHCSR_S_RESET_ST;
+                }
+                dhcsr
+            }
+            // DCRSR is write only.
+            DCRSR => 0,
+            DCRDR => self.dcrdr,
+            DEMCR => self.demcr,
+            DWT_CTRL => (WATCHPOINT_COUNT as u32) << 28,
+            FP_CTRL => {
+                // FPB version 2 on ARMv8-M, version 1 otherwise.
+                let revision = u32::from(self.core_type == CoreType::Armv8m) << 28;
+                let num_code = (BREAKPOINT_COUNT as u32) << 4;
+                revision | num_code | u32::from(self.breakpoints_enabled)
+            }
+            address if FP_COMP.contains(&address) => {
+                self.breakpoints[(address - FP_COMP.start) as usize / 4]
+            }
+            address if DWT_COMPARATORS.contains(&address) => {
+                let offset = address - DWT_COMPARATORS.start;
+                let comparator = &mut self.watchpoints[offset as usize / 16];
+                match offset % 16 {
+                    0x0 => comparator.comp,
+                    0x4 => comparator.mask,
+                    0x8 if std::mem::take(&mut comparator.matched) => {
+                        comparator.function | DWT_FUNCTION_MATCHED
+                    }
+                    0x8 => comparator.function,
+                    _ => 0,
+                }
+            }
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    fn write_register(&mut self, address: u64, value: u32) -> bool {
+        match address {
+            CPUID => {}
+            VTOR => self.vtor = value & !0x7F,
+            AIRCR => {
+                if value >> 16 == AIRCR_VECTKEY
+                    && value & (AIRCR_SYSRESETREQ | AIRCR_VECTRESET) != 0
+                {
+                    self.reset();
+                }
+            }
+            DFSR => self.dfsr &= !value,
+            DHCSR => self.write_dhcsr(value),
+            DCRSR => {
+                let index = match (value & 0x7F) as usize {
+                    DCRSR_MSP => SP,
+                    index => index,
+                };
+                if value & DCRSR_REGWNR == 0 {
+                    self.dcrdr = self.registers[index];
+                } else if index == PC {
+                    self.registers[index] = self.dcrdr & !1;
+                } else {
+                    self.registers[index] = self.dcrdr;
+                }
+            }
+            DCRDR => self.dcrdr = value,
+            DEMCR => self.demcr = value,
+            // The writable bits of DWT_CTRL only control tracing.
+            DWT_CTRL => {}
+            FP_CTRL => {
+                if value & FP_CTRL_KEY != 0 {
+                    self.breakpoints_enabled = value & FP_CTRL_ENABLE != 0;
+                }
+            }
+            address if FP_COMP.contains(&address) => {
+                self.breakpoints[(address - FP_COMP.start) as usize / 4] = value;
+            }
+            address if DWT_COMPARATORS.contains(&address) => {
+                let offset = address - DWT_COMPARATORS.start;
+                let comparator = &mut self.watchpoints[offset as usize / 16];
+                match offset % 16 {
+                    0x0 => comparator.comp = value,
+                    0x4 => comparator.mask = value & 0x1F,
+                    0x8 => comparator.function = value & !DWT_FUNCTION_MATCHED,
+                    _ => {}
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn write_dhcsr(&mut self, value: u32) {
+        if value >> 16 != DHCSR_DBGKEY {
+            return;
+        }
+        self.dhcsr = value & 0xFFFF;
+
+        let debug_enabled = self.dhcsr & DHCSR_C_DEBUGEN != 0;
+        if debug_enabled && self.dhcsr & DHCSR_C_HALT != 0 {
+            if !self.halted {
+                self.halt(DFSR_HALTED);
+            }
+        } else if self.halted {
+            self.halted = false;
+            self.resumed = true;
+
+            if debug_enabled && self.dhcsr & DHCSR_C_STEP != 0 {
+                self.execute(1);
+                if !self.halted {
+                    self.halt(DFSR_HALTED);
+                }
+            }
+        }
+    }
+
+    /// Executes up to `count` instructions, until the core halts.
+    fn execute(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.halted || self.stalled_at.is_some() {
+                return;
+            }
+
+            let pc = self.registers[PC];
+            if std::mem::take(&mut self.resumed) {
+                // The flash algorithms are always called by resuming the core at one of their
+                // functions.
+                if self.emulate_algorithm_call() {
+                    continue;
+                }
+            } else if self.breakpoint_at(pc) {
+                self.halt(DFSR_BKPT);
+                return;
+            }
+
+            let mut accesses = Vec::new();
+            match thumb::execute(&mut self.registers, &mut self.memory, &mut accesses) {
+                Ok(()) => {}
+                Err(Stop::Breakpoint) => self.halt(DFSR_BKPT),
+                Err(Stop::Unsupported(instruction)) => {
+                    tracing::debug!(
+                        "The simulated core stopped executing at {pc:#010x}, because instruction {instruction:#x} is not supported"
+                    );
+                    self.stalled_at = Some(pc);
+                }
+            }
+
+            if self.watchpoint_matched(&accesses) {
+                self.halt(DFSR_DWTTRAP);
+            }
+        }
+    }
+
+    /// Checks the DWT comparators against the data accesses of an instruction, and returns
+    /// whether any of them matched.
+    fn watchpoint_matched(&mut self, accesses: &[DataAccess]) -> bool {
+        // The DWT is disabled unless it is enabled in DEMCR.
+        if self.demcr & DEMCR_TRCENA == 0 {
+            return false;
+        }
+
+        let mut matched = false;
+        for comparator in &mut self.watchpoints {
+            if accesses
+                .iter()
+                .any(|access| comparator.matches(self.core_type, access))
+            {
+                comparator.matched = true;
+                matched = true;
+            }
+        }
+
+        matched
+    }
+
+    fn breakpoint_at(&self, pc: u32) -> bool {
+        if !self.breakpoints_enabled {
+            return false;
+        }
+
+        self.breakpoints
+            .iter()
+            .filter(|comparator| *comparator & 1 != 0)
+            .any(|&comparator| {
+                if self.core_type == CoreType::Armv8m {
+                    comparator & !1 == pc
+                } else {
+                    // Bits 31:30 select the halfword of the word address in bits 28:2.
+                    let halfword = match comparator >> 30 {
+                        0b01 => 0,
+                        0b10 => 2,
+                        _ => return comparator & 0x1FFF_FFFC == pc & !0b11,
+                    };
+                    comparator & 0x1FFF_FFFC | halfword == pc
+                }
+            })
+    }
+
+    /// Finds the flash algorithm function at the program counter.
+    fn algorithm_call(&self) -> Option<(usize, Function)> {
+        let pc = u64::from(self.registers[PC]);
+
+        self.algorithms
+            .iter()
+            .enumerate()
+            .find_map(|(index, algorithm)| {
+                Function::entry_points(algorithm).find_map(|(function, offset)| {
+                    let code_start = pc.checked_sub(offset & !1)?;
+
+                    // Comparing the start of the instructions is enough to identify an algorithm.
+                    let len = algorithm.instructions.len().min(64);
+                    let mut code = vec![0; len];
+                    self.memory.read(code_start, &mut code);
+
+                    (code == algorithm.instructions[..len]).then_some((index, function))
+                })
+            })
+    }
+
+    /// Emulates a call of a flash algorithm function, if the program counter is at one.
+    fn emulate_algorithm_call(&mut self) -> bool {
+        let Some((index, function)) = self.algorithm_call() else {
+            return false;
+        };
+        let algorithm = &self.algorithms[index];
+        let properties = &algorithm.flash_properties;
+        let erased_byte_value = properties.erased_byte_value;
+        let [address, size, buffer] = [0, 1, 2].map(|r| u64::from(self.registers[r]));
+
+        tracing::debug!(
+            "Emulating {function:?} of flash algorithm {} with r0={address:#010x}, r1={size:#x}, r2={buffer:#010x}",
+            algorithm.name
+        );
+
+        let in_flash = |len| {
+            properties.address_range.start <= address
+                && address + len <= properties.address_range.end
+        };
+
+        let result = match function {
+            Function::Init | Function::UnInit => 0,
+            Function::EraseSector => match sector(properties, address) {
+                Some(sector) => {
+                    self.memory.fill(sector, erased_byte_value);
+                    0
+                }
+                None => 1,
+            },
+            Function::EraseAll => {
+                self.memory
+                    .fill(properties.address_range.clone(), erased_byte_value);
+                0
+            }
+            Function::ProgramPage => {
+                if !in_flash(size) || algorithm.transfer_encoding == Some(TransferEncoding::Miniz) {
+                    1
+                } else {
+                    let mut data = vec![0; size as usize];
+                    self.memory.read(buffer, &mut data);
+                    // Programming can only change bits from their erased value.
+                    self.memory
+                        .program(address, &data, |old, new| match erased_byte_value {
+                            0xFF => old & new,
+                            0x00 => old | new,
+                            _ => new,
+                        });
+                    0
+                }
+            }
+            Function::Verify => {
+                let mut expected = vec![0; size as usize];
+                self.memory.read(buffer, &mut expected);
+                let mut actual = vec![0; size as usize];
+                self.memory.read(address, &mut actual);
+
+                // Returns the address of the first difference, or the end of the range.
+                let matching = expected.iter().zip(&actual).take_while(|(e, a)| e == a);
+                (address + matching.count() as u64) as u32
+            }
+            Function::Read => {
+                if in_flash(size) {
+                    let mut data = vec![0; size as usize];
+                    self.memory.read(address, &mut data);
+                    self.memory.write(buffer, &data);
+                    0
+                } else {
+                    1
+                }
+            }
+        };
+
+        self.registers[0] = result;
+        self.registers[PC] = self.registers[LR] & !1;
+
+        true
+    }
+
+    fn read_bytes(&mut self, address: u64, data: &mut [u8]) {
+        self.memory.read(address, data)
+    }
+
+    fn write_bytes(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        // Non-volatile memory can only be changed by the flash algorithms.
+        if self.memory.is_nvm(address..address + data.len() as u64) {
+            return Err(ArmError::Dap(DapError::FaultResponse));
+        }
+
+        self.memory.write(address, data);
+        Ok(())
+    }
+}
+
+/// Returns the sector of `properties` which contains `address`.
+fn sector(properties: &FlashProperties, address: u64) -> Option<Range<u64>> {
+    if !properties.address_range.contains(&address) {
+        return None;
+    }
+    let offset = address - properties.address_range.start;
+
+    let description = properties
+        .sectors
+        .iter()
+        .rev()
+        .find(|description| description.address <= offset)?;
+    let start =
+        properties.address_range.start + offset - (offset - description.address) % description.size;
+
+    Some(start..start + description.size)
+}
+
+impl MemoryInterface<ArmError> for &mut SimulatedCore {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        for (address, value) in (address..).step_by(8).zip(data.iter_mut()) {
+            let mut bytes = [0; 8];
+            self.read_bytes(address, &mut bytes);
+            *value = u64::from_le_bytes(bytes);
+        }
+
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (address, value) in (address..).step_by(4).zip(data.iter_mut()) {
+            *value = match self.read_register(address) {
+                Some(value) => value,
+                None => self.memory.read_u32(address),
+            };
+        }
+
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        for (address, value) in (address..).step_by(2).zip(data.iter_mut()) {
+            let mut bytes = [0; 2];
+            self.read_bytes(address, &mut bytes);
+            *value = u16::from_le_bytes(bytes);
+        }
+
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        self.read_bytes(address, data);
+
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        for (address, value) in (address..).step_by(8).zip(data) {
+            self.write_bytes(address, &value.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (address, value) in (address..).step_by(4).zip(data) {
+            if !self.write_register(address, *value) {
+                self.write_bytes(address, &value.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        for (address, value) in (address..).step_by(2).zip(data) {
+            self.write_bytes(address, &value.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        self.write_bytes(address, data)
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+}
+
+impl SwdSequence for &mut SimulatedCore {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_sequence",
+        })
+    }
+
+    fn swj_pins(
+        &mut self,
+        _pin_out: u32,
+        _pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "swj_pins",
+        })
+    }
+}
+
+impl ArmMemoryInterface for &mut SimulatedCore {
+    fn base_address(&mut self) -> Result<u64, ArmError> {
+        Err(ArmError::NotImplemented("base_address"))
+    }
+
+    fn ap(&mut self) -> &mut MemoryAp {
+        &mut self.ap
+    }
+
+    fn get_arm_communication_interface(
+        &mut self,
+    ) -> Result<&mut ArmCommunicationInterface<Initialized>, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_arm_communication_interface",
+        })
+    }
+
+    fn try_as_parts(
+        &mut self,
+    ) -> Result<(&mut ArmCommunicationInterface<Initialized>, &mut MemoryAp), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "try_as_parts",
+        })
+    }
+
+    fn update_core_status(&mut self, _state: CoreStatus) {}
+}
@@ -0,0 +1,211 @@
+//! The debug port of the simulated target, for probes which access the debug and access port
+//! registers directly.
+//!
+//! A SW-DP (version 1) is simulated, with a single AHB-AP at the address of the access port of
+//! the core. The AHB-AP accesses the memory of the simulated core, and has no ROM table.
+
+use super::SimulatedCore;
+use crate::{
+    architecture::arm::{ap::AccessPortType, ArmError, DapError, PortType},
+    MemoryInterface,
+};
+
+/// The DPIDR of the SW-DP of a Cortex-M0.
+const DPIDR: u32 = 0x0BB1_1477;
+/// The IDR of the AHB-AP of a Cortex-M0.
+const AHB_AP_IDR: u32 = 0x0477_0021;
+/// A BASE register which indicates that there are no debug components.
+const BASE_NOT_PRESENT: u32 = 0xFFFF_FFFF;
+
+const DP_ABORT_DPIDR: u8 = 0x0;
+const DP_CTRL_STAT: u8 = 0x4;
+const DP_SELECT: u8 = 0x8;
+const DP_RDBUFF: u8 = 0xC;
+
+const AP_CSW: u8 = 0x00;
+const AP_TAR: u8 = 0x04;
+const AP_DRW: u8 = 0x0C;
+const AP_BD0: u8 = 0x10;
+const AP_BD3: u8 = 0x1C;
+const AP_CFG: u8 = 0xF4;
+const AP_BASE: u8 = 0xF8;
+const AP_IDR: u8 = 0xFC;
+
+/// The request bits of CTRL/STAT, each of which is acknowledged by the next higher bit.
+const CTRL_STAT_REQUESTS: u32 = 1 << 30 | 1 << 28 | 1 << 26;
+const CTRL_STAT_STICKYERR: u32 = 1 << 5;
+
+const CSW_SIZE: u32 = 0b111;
+const CSW_ADDRINC_SINGLE: u32 = 0b01 << 4;
+const CSW_ADDRINC: u32 = 0b11 << 4;
+const CSW_DEVICEEN: u32 = 1 << 6;
+
+/// The registers of the debug port and the access port.
+#[derive(Default)]
+pub(super) struct DebugPort {
+    ctrl_stat: u32,
+    select: u32,
+    /// The result of the last access port read.
+    rdbuff: u32,
+    csw: u32,
+    tar: u32,
+}
+
+impl SimulatedCore {
+    /// Reads a register of the debug port, or of the access port selected by the debug port.
+    pub(in crate::probe::fake_probe) fn read_dap_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+    ) -> Result<u32, ArmError> {
+        match port {
+            PortType::DebugPort => Ok(match address {
+                DP_ABORT_DPIDR => DPIDR,
+                // Only bank 0 (CTRL/STAT) is implemented.
+                DP_CTRL_STAT if self.dap.select & 0xF == 0 => {
+                    let ctrl_stat = self.dap.ctrl_stat;
+                    ctrl_stat | (ctrl_stat & CTRL_STAT_REQUESTS) << 1
+                }
+                DP_RDBUFF => self.dap.rdbuff,
+                _ => 0,
+            }),
+            PortType::AccessPort => {
+                let value = self.read_ap_register(address)?;
+                self.dap.rdbuff = value;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Writes a register of the debug port, or of the access port selected by the debug port.
+    pub(in crate::probe::fake_probe) fn write_dap_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        match port {
+            PortType::DebugPort => {
+                match address {
+                    // Any write to ABORT clears the sticky error.
+                    DP_ABORT_DPIDR => self.dap.ctrl_stat &= !CTRL_STAT_STICKYERR,
+                    DP_CTRL_STAT if self.dap.select & 0xF == 0 => {
+                        self.dap.ctrl_stat =
+                            value & !CTRL_STAT_STICKYERR | self.dap.ctrl_stat & CTRL_STAT_STICKYERR;
+                    }
+                    DP_SELECT => self.dap.select = value,
+                    _ => {}
+                }
+
+                Ok(())
+            }
+            PortType::AccessPort => self.write_ap_register(address, value),
+        }
+    }
+
+    /// Returns the address of the selected access port register, or `None` if no access port
+    /// is selected.
+    fn ap_register(&self, address: u8) -> Option<u8> {
+        let apsel = (self.dap.select >> 24) as u8;
+        let apbanksel = (self.dap.select & 0xF0) as u8;
+
+        (self.ap.ap_address().ap_v1().ok() == Some(apsel)).then_some(apbanksel | address & 0xC)
+    }
+
+    fn read_ap_register(&mut self, address: u8) -> Result<u32, ArmError> {
+        let Some(register) = self.ap_register(address) else {
+            return Ok(0);
+        };
+
+        match register {
+            AP_CSW => Ok(self.dap.csw | CSW_DEVICEEN),
+            AP_TAR => Ok(self.dap.tar),
+            AP_DRW => {
+                let address = self.dap.tar;
+                let value = self.access_memory(address, None)?;
+                self.increment_tar();
+                Ok(value)
+            }
+            AP_BD0..=AP_BD3 => {
+                let address = self.dap.tar & !0xF | u32::from(register - AP_BD0);
+                let mut value = [0];
+                (&mut *self)
+                    .read_32(address.into(), &mut value)
+                    .inspect_err(|_| self.dap.ctrl_stat |= CTRL_STAT_STICKYERR)?;
+                Ok(value[0])
+            }
+            AP_CFG => Ok(0),
+            AP_BASE => Ok(BASE_NOT_PRESENT),
+            AP_IDR => Ok(AHB_AP_IDR),
+            _ => Ok(0),
+        }
+    }
+
+    fn write_ap_register(&mut self, address: u8, value: u32) -> Result<(), ArmError> {
+        let Some(register) = self.ap_register(address) else {
+            return Ok(());
+        };
+
+        match register {
+            AP_CSW => self.dap.csw = value & !CSW_DEVICEEN,
+            AP_TAR => self.dap.tar = value,
+            AP_DRW => {
+                let address = self.dap.tar;
+                self.access_memory(address, Some(value))?;
+                self.increment_tar();
+            }
+            AP_BD0..=AP_BD3 => {
+                let address = self.dap.tar & !0xF | u32::from(register - AP_BD0);
+                (&mut *self)
+                    .write_32(address.into(), &[value])
+                    .inspect_err(|_| self.dap.ctrl_stat |= CTRL_STAT_STICKYERR)?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Reads or writes the memory at `address`, with the size selected by CSW.
+    ///
+    /// Like on the bus, smaller values are transferred in the byte lanes of their address.
+    fn access_memory(&mut self, address: u32, write: Option<u32>) -> Result<u32, ArmError> {
+        let lane = (address & 0b11) * 8;
+        let address = u64::from(address);
+
+        let size = self.dap.csw & CSW_SIZE;
+        let mut core = &mut *self;
+        let result = match (size, write) {
+            (0, None) => {
+                let mut value = [0];
+                core.read_8(address, &mut value)
+                    .map(|()| u32::from(value[0]) << lane)
+            }
+            (1, None) => {
+                let mut value = [0];
+                core.read_16(address, &mut value)
+                    .map(|()| u32::from(value[0]) << lane)
+            }
+            (2, None) => {
+                let mut value = [0];
+                core.read_32(address, &mut value).map(|()| value[0])
+            }
+            (0, Some(value)) => core.write_8(address, &[(value >> lane) as u8]).map(|()| 0),
+            (1, Some(value)) => core
+                .write_16(address, &[(value >> lane) as u16])
+                .map(|()| 0),
+            (2, Some(value)) => core.write_32(address, &[value]).map(|()| 0),
+            _ => Err(ArmError::Dap(DapError::FaultResponse)),
+        };
+
+        result.inspect_err(|_| self.dap.ctrl_stat |= CTRL_STAT_STICKYERR)
+    }
+
+    /// Increments TAR after an access to DRW, if CSW enables it.
+    fn increment_tar(&mut self) {
+        if self.dap.csw & CSW_ADDRINC == CSW_ADDRINC_SINGLE {
+            let size = 1 << (self.dap.csw & CSW_SIZE);
+            self.dap.tar = self.dap.tar.wrapping_add(size);
+        }
+    }
+}
@@ -0,0 +1,625 @@
+//! An executor for the 16-bit Thumb instructions of ARMv6-M, and `BL`.
+//!
+//! `CBZ` and `CBNZ` are supported as well, because they are common in ARMv7-M code. Exceptions
+//! are not simulated, so instructions which would cause one are not supported.
+
+use super::Memory;
+
+pub(super) const SP: usize = 13;
+pub(super) const LR: usize = 14;
+pub(super) const PC: usize = 15;
+/// The index of xPSR in the register file, which is its DCRSR register selector.
+pub(super) const XPSR: usize = 16;
+/// The index of the register combining CONTROL, FAULTMASK, BASEPRI and PRIMASK.
+const SPECIAL: usize = 20;
+
+pub(super) const XPSR_THUMB: u32 = 1 << 24;
+const XPSR_N: u32 = 1 << 31;
+const XPSR_Z: u32 = 1 << 30;
+const XPSR_C: u32 = 1 << 29;
+const XPSR_V: u32 = 1 << 28;
+
+/// The reason an instruction was not executed.
+#[derive(Debug, PartialEq)]
+pub(super) enum Stop {
+    /// The instruction is a `BKPT`.
+    Breakpoint,
+    /// The instruction is not supported.
+    Unsupported(u32),
+}
+
+/// A data access of an executed instruction.
+#[derive(Debug, PartialEq)]
+pub(super) struct DataAccess {
+    pub address: u32,
+    pub size: usize,
+    pub write: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Shift {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+}
+
+/// Executes the instruction at the program counter, and adds its data accesses to `accesses`.
+///
+/// The program counter is left unchanged if the instruction is not executed.
+pub(super) fn execute(
+    registers: &mut [u32],
+    memory: &mut Memory,
+    accesses: &mut Vec<DataAccess>,
+) -> Result<(), Stop> {
+    Executor {
+        registers,
+        memory,
+        accesses,
+    }
+    .execute()
+}
+
+struct Executor<'a> {
+    registers: &'a mut [u32],
+    memory: &'a mut Memory,
+    accesses: &'a mut Vec<DataAccess>,
+}
+
+impl Executor<'_> {
+    fn execute(&mut self) -> Result<(), Stop> {
+        let pc = self.registers[PC];
+        let instruction = self.fetch(pc);
+
+        // 32-bit instructions start with 0b11101, 0b11110 or 0b11111.
+        if instruction >> 11 >= 0b11101 {
+            let second = self.fetch(pc.wrapping_add(2));
+            if instruction & 0xF800 != 0xF000 || second & 0xD000 != 0xD000 {
+                return Err(Stop::Unsupported(
+                    (u32::from(instruction) << 16) | u32::from(second),
+                ));
+            }
+
+            self.registers[LR] = pc.wrapping_add(4) | 1;
+            self.registers[PC] = pc
+                .wrapping_add(4)
+                .wrapping_add(bl_offset(instruction, second));
+            return Ok(());
+        }
+
+        self.registers[PC] = match self.execute_16(instruction, pc)? {
+            Some(target) => target,
+            None => pc.wrapping_add(2),
+        };
+
+        Ok(())
+    }
+
+    /// Executes a 16-bit instruction, and returns the target if it is a taken branch.
+    fn execute_16(&mut self, instruction: u16, pc: u32) -> Result<Option<u32>, Stop> {
+        let unsupported = Stop::Unsupported(u32::from(instruction));
+        let low = |shift: u16| usize::from((instruction >> shift) & 0b111);
+        let (rd, rn, rm) = (low(0), low(3), low(6));
+        // The register in bits 10:8.
+        let rt = low(8);
+        let imm5 = u32::from((instruction >> 6) & 0x1F);
+        let imm8 = u32::from(instruction & 0xFF);
+        // The aligned program counter, as used for PC relative addresses.
+        let base = pc.wrapping_add(4) & !0b11;
+
+        match instruction >> 11 {
+            0b00000 => self.shift_immediate(Shift::Lsl, rd, rn, imm5),
+            0b00001 => self.shift_immediate(Shift::Lsr, rd, rn, imm5),
+            0b00010 => self.shift_immediate(Shift::Asr, rd, rn, imm5),
+            0b00011 => {
+                let operand = if instruction & (1 << 10) != 0 {
+                    rm as u32
+                } else {
+                    self.registers[rm]
+                };
+                self.registers[rd] = if instruction & (1 << 9) == 0 {
+                    self.add(self.registers[rn], operand, false)
+                } else {
+                    self.add(self.registers[rn], !operand, true)
+                };
+            }
+            0b00100 => {
+                self.registers[rt] = imm8;
+                self.set_nz(imm8);
+            }
+            0b00101 => {
+                self.add(self.registers[rt], !imm8, true);
+            }
+            0b00110 => self.registers[rt] = self.add(self.registers[rt], imm8, false),
+            0b00111 => self.registers[rt] = self.add(self.registers[rt], !imm8, true),
+            0b01000 if instruction & (1 << 10) == 0 => self.data_processing(instruction),
+            0b01000 => return self.special_data_processing(instruction, pc),
+            0b01001 => self.registers[rt] = self.load(base.wrapping_add(imm8 * 4), 4),
+            0b01010 | 0b01011 => {
+                let address = self.registers[rn].wrapping_add(self.registers[rm]);
+                match (instruction >> 9) & 0b111 {
+                    0 => self.store(address, self.registers[rd], 4),
+                    1 => self.store(address, self.registers[rd], 2),
+                    2 => self.store(address, self.registers[rd], 1),
+                    3 => self.registers[rd] = self.load(address, 1) as i8 as u32,
+                    4 => self.registers[rd] = self.load(address, 4),
+                    5 => self.registers[rd] = self.load(address, 2),
+                    6 => self.registers[rd] = self.load(address, 1),
+                    _ => self.registers[rd] = self.load(address, 2) as i16 as u32,
+                }
+            }
+            0b01100 => self.store(
+                self.registers[rn].wrapping_add(imm5 * 4),
+                self.registers[rd],
+                4,
+            ),
+            0b01101 => self.registers[rd] = self.load(self.registers[rn].wrapping_add(imm5 * 4), 4),
+            0b01110 => self.store(self.registers[rn].wrapping_add(imm5), self.registers[rd], 1),
+            0b01111 => self.registers[rd] = self.load(self.registers[rn].wrapping_add(imm5), 1),
+            0b10000 => self.store(
+                self.registers[rn].wrapping_add(imm5 * 2),
+                self.registers[rd],
+                2,
+            ),
+            0b10001 => self.registers[rd] = self.load(self.registers[rn].wrapping_add(imm5 * 2), 2),
+            0b10010 => self.store(
+                self.registers[SP].wrapping_add(imm8 * 4),
+                self.registers[rt],
+                4,
+            ),
+            0b10011 => self.registers[rt] = self.load(self.registers[SP].wrapping_add(imm8 * 4), 4),
+            0b10100 => self.registers[rt] = base.wrapping_add(imm8 * 4),
+            0b10101 => self.registers[rt] = self.registers[SP].wrapping_add(imm8 * 4),
+            0b10110 | 0b10111 => return self.miscellaneous(instruction, pc),
+            0b11000 => {
+                let mut address = self.registers[rt];
+                for register in register_list(instruction) {
+                    self.store(address, self.registers[register], 4);
+                    address = address.wrapping_add(4);
+                }
+                self.registers[rt] = address;
+            }
+            0b11001 => {
+                let mut address = self.registers[rt];
+                for register in register_list(instruction) {
+                    self.registers[register] = self.load(address, 4);
+                    address = address.wrapping_add(4);
+                }
+                if instruction & (1 << rt) == 0 {
+                    self.registers[rt] = address;
+                }
+            }
+            0b11010 | 0b11011 => {
+                let condition = (instruction >> 8) & 0xF;
+                // Condition 0b1110 is UDF, and 0b1111 is SVC.
+                if condition >= 0b1110 {
+                    return Err(unsupported);
+                }
+                if self.condition(condition) {
+                    let offset = (i32::from(instruction as u8 as i8) * 2) as u32;
+                    return Ok(Some(pc.wrapping_add(4).wrapping_add(offset)));
+                }
+            }
+            0b11100 => {
+                // Sign extends the 11-bit immediate, multiplied by 2.
+                let offset = ((u32::from(instruction) << 21) as i32 >> 20) as u32;
+                return Ok(Some(pc.wrapping_add(4).wrapping_add(offset)));
+            }
+            _ => return Err(unsupported),
+        }
+
+        Ok(None)
+    }
+
+    fn shift_immediate(&mut self, shift: Shift, rd: usize, rm: usize, amount: u32) {
+        // An amount of 0 encodes a shift by 32 for right shifts.
+        let amount = match shift {
+            Shift::Lsr | Shift::Asr if amount == 0 => 32,
+            _ => amount,
+        };
+        let (result, carry) = shift_with_carry(shift, self.registers[rm], amount, self.carry());
+        self.registers[rd] = result;
+        self.set_nzc(result, carry);
+    }
+
+    fn data_processing(&mut self, instruction: u16) {
+        let rdn = usize::from(instruction & 0b111);
+        let rm = usize::from((instruction >> 3) & 0b111);
+        let (a, b) = (self.registers[rdn], self.registers[rm]);
+
+        let result = match (instruction >> 6) & 0xF {
+            0b0000 => a & b,
+            0b0001 => a ^ b,
+            0b0010 => return self.shift_register(Shift::Lsl, rdn, b),
+            0b0011 => return self.shift_register(Shift::Lsr, rdn, b),
+            0b0100 => return self.shift_register(Shift::Asr, rdn, b),
+            0b0101 => {
+                self.registers[rdn] = self.add(a, b, self.carry());
+                return;
+            }
+            0b0110 => {
+                self.registers[rdn] = self.add(a, !b, self.carry());
+                return;
+            }
+            0b0111 => return self.shift_register(Shift::Ror, rdn, b),
+            0b1000 => {
+                self.set_nz(a & b);
+                return;
+            }
+            0b1001 => {
+                self.registers[rdn] = self.add(!b, 0, true);
+                return;
+            }
+            0b1010 => {
+                self.add(a, !b, true);
+                return;
+            }
+            0b1011 => {
+                self.add(a, b, false);
+                return;
+            }
+            0b1100 => a | b,
+            0b1101 => a.wrapping_mul(b),
+            0b1110 => a & !b,
+            _ => !b,
+        };
+
+        self.registers[rdn] = result;
+        self.set_nz(result);
+    }
+
+    fn shift_register(&mut self, shift: Shift, rdn: usize, amount: u32) {
+        let (result, carry) =
+            shift_with_carry(shift, self.registers[rdn], amount & 0xFF, self.carry());
+        self.registers[rdn] = result;
+        self.set_nzc(result, carry);
+    }
+
+    /// Executes `ADD`, `CMP` and `MOV` with high registers, `BX` and `BLX`.
+    fn special_data_processing(&mut self, instruction: u16, pc: u32) -> Result<Option<u32>, Stop> {
+        let rdn = usize::from((instruction & 0b111) | ((instruction >> 4) & 0b1000));
+        let rm = usize::from((instruction >> 3) & 0xF);
+        let read = |registers: &[u32], register: usize| match register {
+            PC => pc.wrapping_add(4),
+            register => registers[register],
+        };
+        let (a, b) = (read(self.registers, rdn), read(self.registers, rm));
+
+        let result = match (instruction >> 8) & 0b11 {
+            0b00 => a.wrapping_add(b),
+            0b01 => {
+                self.add(a, !b, true);
+                return Ok(None);
+            }
+            0b10 => b,
+            _ => {
+                let target = self.interworking_target(b, instruction)?;
+                if instruction & (1 << 7) != 0 {
+                    self.registers[LR] = pc.wrapping_add(2) | 1;
+                }
+                return Ok(Some(target));
+            }
+        };
+
+        if rdn == PC {
+            Ok(Some(result & !1))
+        } else {
+            self.registers[rdn] = result;
+            Ok(None)
+        }
+    }
+
+    fn miscellaneous(&mut self, instruction: u16, pc: u32) -> Result<Option<u32>, Stop> {
+        let unsupported = Stop::Unsupported(u32::from(instruction));
+        let rd = usize::from(instruction & 0b111);
+        let rm = usize::from((instruction >> 3) & 0b111);
+
+        match (instruction >> 8) & 0xF {
+            0b0000 => {
+                let offset = u32::from(instruction & 0x7F) * 4;
+                self.registers[SP] = if instruction & (1 << 7) == 0 {
+                    self.registers[SP].wrapping_add(offset)
+                } else {
+                    self.registers[SP].wrapping_sub(offset)
+                };
+            }
+            0b0010 => {
+                let value = self.registers[rm];
+                self.registers[rd] = match (instruction >> 6) & 0b11 {
+                    0b00 => value as i16 as u32,
+                    0b01 => value as i8 as u32,
+                    0b10 => value & 0xFFFF,
+                    _ => value & 0xFF,
+                };
+            }
+            // CBZ and CBNZ
+            0b0001 | 0b0011 | 0b1001 | 0b1011 => {
+                let offset = u32::from(((instruction >> 3) & 0x40) | ((instruction >> 2) & 0x3E));
+                let non_zero = instruction & (1 << 11) != 0;
+                if (self.registers[rd] != 0) == non_zero {
+                    return Ok(Some(pc.wrapping_add(4).wrapping_add(offset)));
+                }
+            }
+            // PUSH
+            0b0100 | 0b0101 => {
+                let mut registers = register_list(instruction);
+                if instruction & (1 << 8) != 0 {
+                    registers.push(LR);
+                }
+
+                let start = self.registers[SP].wrapping_sub(4 * registers.len() as u32);
+                for (address, register) in (start..).step_by(4).zip(registers) {
+                    self.store(address, self.registers[register], 4);
+                }
+                self.registers[SP] = start;
+            }
+            // POP
+            0b1100 | 0b1101 => {
+                let mut address = self.registers[SP];
+                for register in register_list(instruction) {
+                    self.registers[register] = self.load(address, 4);
+                    address = address.wrapping_add(4);
+                }
+
+                let target = if instruction & (1 << 8) != 0 {
+                    let value = self.load(address, 4);
+                    address = address.wrapping_add(4);
+                    Some(self.interworking_target(value, instruction)?)
+                } else {
+                    None
+                };
+                self.registers[SP] = address;
+
+                return Ok(target);
+            }
+            // CPSIE i and CPSID i
+            0b0110 if instruction & 0xFFEF == 0xB662 => {
+                let disable = instruction & (1 << 4) != 0;
+                self.registers[SPECIAL] = (self.registers[SPECIAL] & !1) | u32::from(disable);
+            }
+            0b1010 => {
+                let value = self.registers[rm];
+                self.registers[rd] = match (instruction >> 6) & 0b11 {
+                    0b00 => value.swap_bytes(),
+                    0b01 => ((value & 0xFF00_FF00) >> 8) | ((value & 0x00FF_00FF) << 8),
+                    0b11 => (value as u16).swap_bytes() as i16 as u32,
+                    _ => return Err(unsupported),
+                };
+            }
+            0b1110 => return Err(Stop::Breakpoint),
+            // NOP, YIELD, WFE, WFI and SEV. Everything else is an IT instruction.
+            0b1111 if instruction & 0xF == 0 && instruction & 0xF0 <= 0x40 => {}
+            _ => return Err(unsupported),
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the target of a branch to `address`, which has to stay in Thumb state.
+    fn interworking_target(&self, address: u32, instruction: u16) -> Result<u32, Stop> {
+        // Switching to ARM state causes a fault, and exception returns are not simulated.
+        if address & 1 == 0 || address >= 0xF000_0000 {
+            return Err(Stop::Unsupported(u32::from(instruction)));
+        }
+
+        Ok(address & !1)
+    }
+
+    fn condition(&self, condition: u16) -> bool {
+        let xpsr = self.registers[XPSR];
+        let [n, z, c, v] = [XPSR_N, XPSR_Z, XPSR_C, XPSR_V].map(|flag| xpsr & flag != 0);
+
+        match condition {
+            0b0000 => z,
+            0b0001 => !z,
+            0b0010 => c,
+            0b0011 => !c,
+            0b0100 => n,
+            0b0101 => !n,
+            0b0110 => v,
+            0b0111 => !v,
+            0b1000 => c && !z,
+            0b1001 => !c || z,
+            0b1010 => n == v,
+            0b1011 => n != v,
+            0b1100 => !z && n == v,
+            0b1101 => z || n != v,
+            _ => true,
+        }
+    }
+
+    /// Adds `a`, `b` and `carry`, and sets all flags.
+    fn add(&mut self, a: u32, b: u32, carry: bool) -> u32 {
+        let sum = u64::from(a) + u64::from(b) + u64::from(carry);
+        let result = sum as u32;
+        let overflow = (a ^ result) & (b ^ result) & (1 << 31) != 0;
+
+        self.set_nzc(result, sum > u64::from(u32::MAX));
+        self.set_flag(XPSR_V, overflow);
+
+        result
+    }
+
+    fn carry(&self) -> bool {
+        self.registers[XPSR] & XPSR_C != 0
+    }
+
+    fn set_flag(&mut self, flag: u32, set: bool) {
+        if set {
+            self.registers[XPSR] |= flag;
+        } else {
+            self.registers[XPSR] &= !flag;
+        }
+    }
+
+    fn set_nz(&mut self, result: u32) {
+        self.set_flag(XPSR_N, result & (1 << 31) != 0);
+        self.set_flag(XPSR_Z, result == 0);
+    }
+
+    fn set_nzc(&mut self, result: u32, carry: bool) {
+        self.set_nz(result);
+        self.set_flag(XPSR_C, carry);
+    }
+
+    fn fetch(&self, address: u32) -> u16 {
+        let mut bytes = [0; 2];
+        self.memory.read(u64::from(address), &mut bytes);
+        u16::from_le_bytes(bytes)
+    }
+
+    fn load(&mut self, address: u32, size: usize) -> u32 {
+        self.accesses.push(DataAccess {
+            address,
+            size,
+            write: false,
+        });
+
+        let mut bytes = [0; 4];
+        self.memory.read(u64::from(address), &mut bytes[..size]);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn store(&mut self, address: u32, value: u32, size: usize) {
+        self.accesses.push(DataAccess {
+            address,
+            size,
+            write: true,
+        });
+
+        let address = u64::from(address);
+        // Writes to non-volatile memory only have an effect through the flash controller,
+        // which is not simulated.
+        if !self.memory.is_nvm(address..address + size as u64) {
+            self.memory.write(address, &value.to_le_bytes()[..size]);
+        }
+    }
+}
+
+/// Returns the low registers in the register list of `instruction`.
+fn register_list(instruction: u16) -> Vec<usize> {
+    (0..8).filter(|r| instruction & (1 << r) != 0).collect()
+}
+
+/// Returns the offset of a `BL` instruction.
+fn bl_offset(first: u16, second: u16) -> u32 {
+    let [first, second] = [u32::from(first), u32::from(second)];
+    let s = (first >> 10) & 1;
+    let i1 = !((second >> 13) ^ s) & 1;
+    let i2 = !((second >> 11) ^ s) & 1;
+    let offset =
+        (s << 24) | (i1 << 23) | (i2 << 22) | ((first & 0x3FF) << 12) | ((second & 0x7FF) << 1);
+
+    // Sign extends the 25-bit offset.
+    ((offset << 7) as i32 >> 7) as u32
+}
+
+/// Shifts `value` by `amount`, and returns the result together with the carry flag.
+fn shift_with_carry(shift: Shift, value: u32, amount: u32, carry: bool) -> (u32, bool) {
+    if amount == 0 {
+        return (value, carry);
+    }
+    let bit = |n: u32| (value >> n) & 1 != 0;
+
+    match shift {
+        Shift::Lsl if amount < 32 => (value << amount, bit(32 - amount)),
+        Shift::Lsl => (0, amount == 32 && bit(0)),
+        Shift::Lsr if amount < 32 => (value >> amount, bit(amount - 1)),
+        Shift::Lsr => (0, amount == 32 && bit(31)),
+        Shift::Asr if amount < 32 => (((value as i32) >> amount) as u32, bit(amount - 1)),
+        Shift::Asr => (((value as i32) >> 31) as u32, bit(31)),
+        Shift::Ror => {
+            let result = value.rotate_right(amount % 32);
+            (result, result >> 31 != 0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{execute, Memory, Stop, LR, PC, SP, XPSR, XPSR_THUMB};
+
+    fn memory(address: u64, code: &[u16]) -> Memory {
+        let mut memory = Memory {
+            nvm: vec![],
+            pages: Default::default(),
+        };
+        let bytes: Vec<u8> = code.iter().flat_map(|i| i.to_le_bytes()).collect();
+        memory.write(address, &bytes);
+        memory
+    }
+
+    /// Runs the code at `address` until it reaches a `BKPT`.
+    fn run(registers: &mut [u32; 32], memory: &mut Memory) {
+        for _ in 0..100_000 {
+            match execute(registers, memory, &mut Vec::new()) {
+                Ok(()) => {}
+                Err(Stop::Breakpoint) => return,
+                Err(error) => panic!("Unexpected stop {error:?} at {:#x}", registers[PC]),
+            }
+        }
+        panic!("The code did not reach a breakpoint");
+    }
+
+    #[test]
+    fn call_and_return() {
+        let code = [
+            0xb500, // push {lr}
+            0x2005, // movs r0, #5
+            0xf000, 0xf803, // bl double
+            0xbc02, // pop {r1}
+            0xbe00, // bkpt
+            0xbf00, // nop
+            // double:
+            0x1800, // adds r0, r0, r0
+            0x4770, // bx lr
+        ];
+        let mut memory = memory(0x100, &code);
+        let mut registers = [0; 32];
+        registers[PC] = 0x100;
+        registers[SP] = 0x2000_1000;
+        registers[LR] = 0x1235;
+        registers[XPSR] = XPSR_THUMB;
+
+        run(&mut registers, &mut memory);
+
+        assert_eq!(registers[0], 10);
+        assert_eq!(registers[1], 0x1235);
+        assert_eq!(registers[SP], 0x2000_1000);
+        assert_eq!(registers[PC], 0x10A);
+    }
+
+    #[test]
+    fn conditional_loop() {
+        let code = [
+            0x2000, // movs r0, #0
+            0x210a, // movs r1, #10
+            // loop:
+            0x1840, // adds r0, r0, r1
+            0x3901, // subs r1, #1
+            0xd1fc, // bne loop
+            0xbe00, // bkpt
+        ];
+        let mut memory = memory(0, &code);
+        let mut registers = [0; 32];
+
+        run(&mut registers, &mut memory);
+
+        assert_eq!(registers[0], 55);
+        assert_ne!(registers[XPSR] & super::XPSR_Z, 0);
+    }
+
+    #[test]
+    fn unsupported_instructions_are_not_executed() {
+        // A 32-bit `ADD.W r0, r0, #1`.
+        let mut memory = memory(0x200, &[0xf100, 0x0001]);
+        let mut registers = [0; 32];
+        registers[PC] = 0x200;
+
+        assert_eq!(
+            execute(&mut registers, &mut memory, &mut Vec::new()),
+            Err(Stop::Unsupported(0xf100_0001))
+        );
+        assert_eq!(registers[PC], 0x200);
+    }
+}
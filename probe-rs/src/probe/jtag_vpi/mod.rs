@@ -0,0 +1,484 @@
+//! Probe driver for OpenOCD's `jtag_vpi` protocol.
+//!
+//! The `jtag_vpi` VPI module connects the JTAG port of a simulated chip in a Verilog simulator,
+//! like Icarus Verilog or Verilator, to a TCP socket. Bits are sent in commands of up to 4096
+//! bits, which either shift a TMS sequence, or shift TDI and capture TDO while TMS is low.
+//!
+//! As the server can't be discovered, the probe is selected with the address of the server and
+//! the pseudo VID:PID `0000:0002`, as in `tcp://localhost:5555/0000:0002`. Without an address,
+//! as in `0000:0002`, the default address `localhost:5555` is used.
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use bitvec::prelude::*;
+use probe_rs_target::ScanChainElement;
+
+use crate::{
+    architecture::{
+        arm::{
+            communication_interface::{DapProbe, UninitializedArmProbe},
+            SwoAccess,
+        },
+        riscv::{communication_interface::RiscvInterfaceBuilder, dtm::jtag_dtm::JtagDtmBuilder},
+        xtensa::communication_interface::{
+            XtensaCommunicationInterface, XtensaDebugInterfaceState,
+        },
+    },
+    probe::{
+        common::{JtagDriverState, RawJtagIo},
+        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, JTAGAccess,
+        ProbeCreationError, ProbeError, ProbeFactory, WireProtocol,
+    },
+};
+
+/// The pseudo USB vendor ID used to select a `jtag_vpi` server.
+pub const JTAG_VPI_VID: u16 = 0x0000;
+
+/// The pseudo USB product ID used to select a `jtag_vpi` server.
+pub const JTAG_VPI_PID: u16 = 0x0002;
+
+/// The address OpenOCD connects to by default.
+const DEFAULT_ADDRESS: &str = "localhost:5555";
+
+/// The size of the TDI and TDO buffers of a command.
+const BUFFER_SIZE: usize = 512;
+
+/// The maximum number of bits in a command.
+const MAX_BITS: usize = BUFFER_SIZE * 8;
+
+/// The size of a command on the wire: the command, the TDI and TDO buffers, the number of used
+/// bytes and the number of bits.
+const COMMAND_SIZE: usize = 4 + 2 * BUFFER_SIZE + 4 + 4;
+
+/// A command of the `jtag_vpi` protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Command {
+    /// Shifts the bits as TMS. The server doesn't respond.
+    TmsSequence = 1,
+    /// Shifts the bits as TDI with TMS low. The server responds with the captured TDO.
+    ScanChain = 2,
+    /// Like `ScanChain`, but with TMS high for the last bit.
+    ScanChainFlipTms = 3,
+}
+
+impl Command {
+    fn encode(self, bits: &BitSlice<u8, Lsb0>) -> [u8; COMMAND_SIZE] {
+        let mut encoded = [0; COMMAND_SIZE];
+        let len = bits.len().div_ceil(8);
+
+        encoded[..4].copy_from_slice(&(self as u32).to_le_bytes());
+        encoded[4..][..BUFFER_SIZE].view_bits_mut::<Lsb0>()[..bits.len()].copy_from_bitslice(bits);
+        encoded[4 + 2 * BUFFER_SIZE..][..4].copy_from_slice(&(len as u32).to_le_bytes());
+        encoded[4 + 2 * BUFFER_SIZE + 4..].copy_from_slice(&(bits.len() as u32).to_le_bytes());
+
+        encoded
+    }
+}
+
+/// An error in the communication with a `jtag_vpi` server.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum JtagVpiError {
+    /// Failed to communicate with the server.
+    Io(#[from] std::io::Error),
+}
+
+impl ProbeError for JtagVpiError {}
+
+/// Factory for probes which connect to a `jtag_vpi` server.
+#[derive(Debug)]
+pub struct JtagVpiFactory;
+
+impl std::fmt::Display for JtagVpiFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("jtag_vpi")
+    }
+}
+
+impl ProbeFactory for JtagVpiFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        if selector.vendor_id != JTAG_VPI_VID || selector.product_id != JTAG_VPI_PID {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound,
+            ));
+        }
+
+        let address = selector.remote.as_deref().unwrap_or(DEFAULT_ADDRESS);
+
+        Ok(Box::new(JtagVpi::connect(address)?))
+    }
+
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        // Servers can't be discovered.
+        vec![]
+    }
+}
+
+/// A JTAG probe which is connected to a `jtag_vpi` server.
+#[derive(Debug)]
+pub struct JtagVpi {
+    stream: TcpStream,
+
+    /// The TMS, TDI and capture values of the bits which haven't been sent yet.
+    bits: Vec<(bool, bool, bool)>,
+    /// The TMS value of the last bit which has been sent.
+    last_tms: bool,
+    captured: BitVec<u8, Lsb0>,
+
+    speed_khz: u32,
+    jtag_state: JtagDriverState,
+}
+
+impl JtagVpi {
+    /// Connects to the `jtag_vpi` server at `address`.
+    pub fn connect(address: &str) -> Result<Self, JtagVpiError> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            stream,
+            bits: Vec::with_capacity(MAX_BITS),
+            last_tms: false,
+            captured: BitVec::new(),
+            speed_khz: 1000,
+            jtag_state: JtagDriverState::default(),
+        })
+    }
+
+    /// Sends the buffered bits to the server.
+    ///
+    /// Runs of bits with TMS low, including the bit with TMS high which ends them, are sent as
+    /// scans, so that TDI is shifted in and TDO is captured. Bits with TMS high which follow
+    /// another bit with TMS high can't be in a shift state, so they are sent as TMS sequences,
+    /// unless they have to be captured.
+    fn flush(&mut self) -> Result<(), JtagVpiError> {
+        let mut tms_sequence = BitVec::<u8, Lsb0>::new();
+        let mut scan = BitVec::<u8, Lsb0>::new();
+        let mut capture = Vec::new();
+
+        for (tms, tdi, capture_tdo) in std::mem::take(&mut self.bits) {
+            let tms_only = tms && self.last_tms && !capture_tdo;
+            self.last_tms = tms;
+
+            if tms_only {
+                tms_sequence.push(true);
+                if tms_sequence.len() == MAX_BITS {
+                    self.send(Command::TmsSequence, &tms_sequence)?;
+                    tms_sequence.clear();
+                }
+                continue;
+            }
+
+            if !tms_sequence.is_empty() {
+                self.send(Command::TmsSequence, &tms_sequence)?;
+                tms_sequence.clear();
+            }
+
+            scan.push(tdi);
+            capture.push(capture_tdo);
+
+            if tms {
+                self.scan(Command::ScanChainFlipTms, &scan, &capture)?;
+                scan.clear();
+                capture.clear();
+            } else if scan.len() == MAX_BITS {
+                self.scan(Command::ScanChain, &scan, &capture)?;
+                scan.clear();
+                capture.clear();
+            }
+        }
+
+        if !tms_sequence.is_empty() {
+            self.send(Command::TmsSequence, &tms_sequence)?;
+        }
+        if !scan.is_empty() {
+            self.scan(Command::ScanChain, &scan, &capture)?;
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, command: Command, bits: &BitSlice<u8, Lsb0>) -> Result<(), JtagVpiError> {
+        self.stream.write_all(&command.encode(bits))?;
+        Ok(())
+    }
+
+    /// Sends a scan command, and collects the TDO values of the bits which should be captured.
+    fn scan(
+        &mut self,
+        command: Command,
+        tdi: &BitSlice<u8, Lsb0>,
+        capture: &[bool],
+    ) -> Result<(), JtagVpiError> {
+        self.send(command, tdi)?;
+
+        let mut response = [0; COMMAND_SIZE];
+        self.stream.read_exact(&mut response)?;
+
+        let tdo = response[4 + BUFFER_SIZE..][..BUFFER_SIZE].view_bits::<Lsb0>();
+        for (tdo, _) in tdo.iter().zip(capture).filter(|(_, capture)| **capture) {
+            self.captured.push(*tdo);
+        }
+
+        Ok(())
+    }
+}
+
+impl RawJtagIo for JtagVpi {
+    fn shift_bit(&mut self, tms: bool, tdi: bool, capture: bool) -> Result<(), DebugProbeError> {
+        self.jtag_state.state.update(tms);
+
+        self.bits.push((tms, tdi, capture));
+        if self.bits.len() >= MAX_BITS {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+        self.flush()?;
+
+        Ok(std::mem::take(&mut self.captured))
+    }
+
+    fn state_mut(&mut self) -> &mut JtagDriverState {
+        &mut self.jtag_state
+    }
+
+    fn state(&self) -> &JtagDriverState {
+        &self.jtag_state
+    }
+}
+
+impl DebugProbe for JtagVpi {
+    fn get_name(&self) -> &str {
+        "jtag_vpi"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        // The speed is determined by the server, so there is nothing to configure.
+        self.speed_khz = speed_khz;
+        Ok(speed_khz)
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        tracing::info!("Setting scan chain to {:?}", scan_chain);
+        self.jtag_state.expected_scan_chain = Some(scan_chain);
+        Ok(())
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        if let Some(ref scan_chain) = self.jtag_state.expected_scan_chain {
+            Ok(scan_chain)
+        } else {
+            Ok(&[])
+        }
+    }
+
+    fn select_jtag_tap(&mut self, index: usize) -> Result<(), DebugProbeError> {
+        self.select_target(index)
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        tracing::debug!("Attaching to jtag_vpi server");
+
+        JTAGAccess::scan_chain(self)?;
+        self.select_target(0)
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        self.flush().map_err(DebugProbeError::from)?;
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset",
+        })
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        // The protocol has no reset signal.
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset_assert",
+        })
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "target_reset_deassert",
+        })
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        if matches!(protocol, WireProtocol::Jtag) {
+            Ok(())
+        } else {
+            Err(DebugProbeError::UnsupportedProtocol(protocol))
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        Some(WireProtocol::Jtag)
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        Ok(Box::new(JtagDtmBuilder::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_xtensa_interface<'probe>(
+        &'probe mut self,
+        state: &'probe mut XtensaDebugInterfaceState,
+    ) -> Result<XtensaCommunicationInterface<'probe>, DebugProbeError> {
+        Ok(XtensaCommunicationInterface::new(self, state))
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        // JTAG-DP is not implemented on top of raw JTAG access.
+        Err((
+            self,
+            DebugProbeError::InterfaceNotAvailable {
+                interface_name: "SWD/ARM",
+            },
+        ))
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        false
+    }
+
+    fn get_swo_interface(&self) -> Option<&dyn SwoAccess> {
+        None
+    }
+
+    fn get_swo_interface_mut(&mut self) -> Option<&mut dyn SwoAccess> {
+        None
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        None
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::probe::common::tap_model::{TapModel, IDCODE_INSTRUCTION};
+
+    const IDCODE: u32 = 0x2000_0a6d;
+
+    /// Serves the `jtag_vpi` protocol for a [`TapModel`].
+    fn serve(mut stream: TcpStream) {
+        let mut tap = TapModel::new(IDCODE);
+
+        let mut command = [0; COMMAND_SIZE];
+        while stream.read_exact(&mut command).is_ok() {
+            let kind = u32::from_le_bytes(command[..4].try_into().unwrap());
+            let len = u32::from_le_bytes(command[COMMAND_SIZE - 4..].try_into().unwrap()) as usize;
+            let out = command[4..][..BUFFER_SIZE].view_bits::<Lsb0>()[..len].to_bitvec();
+
+            if kind == Command::TmsSequence as u32 {
+                for tms in out {
+                    tap.clock(tms, false);
+                }
+                continue;
+            }
+
+            let flip_tms = kind == Command::ScanChainFlipTms as u32;
+            let tdo = command[4 + BUFFER_SIZE..][..BUFFER_SIZE].view_bits_mut::<Lsb0>();
+            for (i, tdi) in out.into_iter().enumerate() {
+                tdo.set(i, tap.tdo());
+                tap.clock(flip_tms && i == len - 1, tdi);
+            }
+            stream.write_all(&command).unwrap();
+        }
+    }
+
+    fn connect() -> JtagVpi {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream);
+        });
+
+        JtagVpi::connect(&address).unwrap()
+    }
+
+    #[test]
+    fn scan_chain_is_detected() {
+        let mut probe = connect();
+
+        JTAGAccess::scan_chain(&mut probe).unwrap();
+
+        let chain = &probe.state().scan_chain;
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].idcode.map(|idcode| idcode.0), Some(IDCODE));
+        assert_eq!(chain[0].irlen, 5);
+    }
+
+    #[test]
+    fn registers_can_be_read() {
+        let mut probe = connect();
+        probe.attach().unwrap();
+
+        let idcode = probe.read_register(IDCODE_INSTRUCTION, 32).unwrap();
+        assert_eq!(idcode, IDCODE.to_le_bytes());
+    }
+
+    #[test]
+    fn long_scans_are_split() {
+        let mut probe = connect();
+        probe.attach().unwrap();
+
+        // The bypass register delays the data by one bit.
+        let data: Vec<u8> = (0..1024u32).map(|i| (i * 13) as u8).collect();
+        let response = probe
+            .write_register(0x1f, &data, data.len() as u32 * 8)
+            .unwrap();
+
+        let expected = data.view_bits::<Lsb0>();
+        let response = response.view_bits::<Lsb0>();
+        assert!(!response[0]);
+        assert_eq!(response[1..], expected[..expected.len() - 1]);
+    }
+}
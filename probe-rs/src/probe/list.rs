@@ -4,7 +4,9 @@ use crate::probe::{
     DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe, ProbeCreationError, ProbeFactory,
 };
 
-use super::{blackmagic, cmsisdap, espusbjtag, ftdi, jlink, stlink, wlink};
+use super::{
+    blackmagic, cmsisdap, espusbjtag, ftdi, jlink, jtag_vpi, remote, remote_bitbang, stlink, wlink,
+};
 
 /// Struct to list all attached debug probes
 #[derive(Debug)]
@@ -75,6 +77,11 @@ impl Default for AllProbesLister {
 
 impl AllProbesLister {
     const DRIVERS: &'static [&'static dyn ProbeFactory] = &[
+        // These take precedence over probe servers, as they are selected by the address of their
+        // server together with a pseudo VID:PID.
+        &remote_bitbang::RemoteBitbangFactory,
+        &jtag_vpi::JtagVpiFactory,
+        &remote::RemoteProbeFactory,
         &blackmagic::BlackMagicProbeFactory,
         &cmsisdap::CmsisDapFactory,
         &ftdi::FtdiProbeFactory,
@@ -0,0 +1,400 @@
+//! Recording the operations on a debug probe, and replaying them without the hardware.
+//!
+//! A [`RecordingProbe`] wraps another probe, and writes every operation on the probe, together
+//! with its result, to a recording. A [`ReplayProbe`] returns the recorded results for the same
+//! sequence of operations. This turns a problem observed with real hardware into a test which
+//! runs without it:
+//!
+//! ```no_run
+//! use probe_rs::{
+//!     probe::{recording::ReplayProbe, Probe},
+//!     Permissions,
+//! };
+//!
+//! let probe = Probe::from_specific_probe(Box::new(ReplayProbe::open("nrf52840-attach.jsonl")?));
+//! let session = probe.attach("nRF52840_xxAA", Permissions::default())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The operations are recorded at the level of [`RawDapAccess`](crate::architecture::arm::RawDapAccess)
+//! and [`JTAGAccess`](super::JTAGAccess), so only probes which provide this access can be
+//! recorded. JTAG command batches are recorded as individual register accesses.
+//!
+//! A recording is a JSON Lines file. The first line describes the recorded probe, and each
+//! following line contains one operation with its result.
+
+mod recorder;
+mod replay;
+
+pub use recorder::RecordingProbe;
+pub use replay::{ReplayError, ReplayProbe};
+
+use probe_rs_target::ScanChainElement;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    architecture::arm::{ArmError, DapError, PortType},
+    probe::{DebugProbeError, JtagRegister, JtagStableState, WireProtocol},
+};
+
+/// The version of the recording format. Must be increased whenever a recorded type changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// The first line of a recording, which describes the recorded probe.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    name: String,
+    speed_khz: u32,
+    protocol: Option<WireProtocol>,
+    /// Whether the probe provides raw DAP access.
+    dap: bool,
+    use_overrun_detect: bool,
+    /// Whether the probe provides low-level JTAG access.
+    jtag: bool,
+    riscv: bool,
+    xtensa: bool,
+}
+
+/// An operation on the probe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Operation {
+    SetSpeed(u32),
+    SetScanChain(Vec<ScanChainElement>),
+    Attach,
+    SelectJtagTap(usize),
+    Detach,
+    TargetReset,
+    TargetResetAssert,
+    TargetResetDeassert,
+    SelectProtocol(WireProtocol),
+    TargetVoltage,
+    Read {
+        port: PortType,
+        address: u8,
+    },
+    ReadBlock {
+        port: PortType,
+        address: u8,
+        len: usize,
+    },
+    Write {
+        port: PortType,
+        address: u8,
+        value: u32,
+    },
+    WriteBlock {
+        port: PortType,
+        address: u8,
+        values: Vec<u32>,
+    },
+    Flush,
+    ConfigureJtag {
+        skip_scan: bool,
+    },
+    JtagSequence {
+        cycles: u8,
+        tms: bool,
+        tdi: u64,
+    },
+    SwjSequence {
+        bit_len: u8,
+        bits: u64,
+    },
+    SwjPins {
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    },
+    ScanChain,
+    TapReset,
+    WriteRegister {
+        address: u32,
+        data: Vec<u8>,
+        len: u32,
+    },
+    WriteDr {
+        data: Vec<u8>,
+        len: u32,
+    },
+    RunTest {
+        state: JtagStableState,
+        cycles: u32,
+    },
+    ShiftRaw {
+        register: JtagRegister,
+        data: Vec<u8>,
+        len: u32,
+        end_state: JtagStableState,
+    },
+}
+
+/// An operation together with its result.
+#[derive(Debug, Serialize, Deserialize)]
+struct Transaction {
+    operation: Operation,
+    result: Result<Value, RecordedError>,
+}
+
+/// The value returned by an operation.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Value {
+    None,
+    Word(u32),
+    Words(Vec<u32>),
+    Bytes(Vec<u8>),
+    Protocol(Option<WireProtocol>),
+    Voltage(Option<f32>),
+    ScanChain(Vec<ScanChainElement>),
+}
+
+/// A type returned by a recorded operation.
+trait Recorded: Sized {
+    fn to_value(&self) -> Value;
+
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+macro_rules! recorded {
+    ($type:ty, $variant:ident) => {
+        impl Recorded for $type {
+            fn to_value(&self) -> Value {
+                Value::$variant(self.clone())
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value {
+                    Value::$variant(value) => Some(value),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+recorded!(u32, Word);
+recorded!(Vec<u32>, Words);
+recorded!(Vec<u8>, Bytes);
+recorded!(Option<WireProtocol>, Protocol);
+recorded!(Option<f32>, Voltage);
+recorded!(Vec<ScanChainElement>, ScanChain);
+
+impl Recorded for () {
+    fn to_value(&self) -> Value {
+        Value::None
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        matches!(value, Value::None).then_some(())
+    }
+}
+
+/// An error returned by a recorded operation.
+///
+/// The errors which are handled by the callers of a probe keep their type, all others are
+/// recorded as text.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedError {
+    Dap(DapError),
+    Timeout,
+    Other(String),
+}
+
+impl RecordedError {
+    fn other(error: &dyn std::error::Error) -> Self {
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(error) = source {
+            message.push_str(": ");
+            message.push_str(&error.to_string());
+            source = error.source();
+        }
+
+        RecordedError::Other(message)
+    }
+}
+
+impl From<&DebugProbeError> for RecordedError {
+    fn from(error: &DebugProbeError) -> Self {
+        match error {
+            DebugProbeError::Timeout => RecordedError::Timeout,
+            error => RecordedError::other(error),
+        }
+    }
+}
+
+impl From<&ArmError> for RecordedError {
+    fn from(error: &ArmError) -> Self {
+        match error {
+            ArmError::Dap(error) => RecordedError::Dap(*error),
+            ArmError::Timeout => RecordedError::Timeout,
+            ArmError::Probe(error) => error.into(),
+            error => RecordedError::other(error),
+        }
+    }
+}
+
+impl From<RecordedError> for DebugProbeError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::Dap(error) => ReplayError::Recorded(error.to_string()).into(),
+            RecordedError::Timeout => DebugProbeError::Timeout,
+            RecordedError::Other(message) => ReplayError::Recorded(message).into(),
+        }
+    }
+}
+
+impl From<RecordedError> for ArmError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::Dap(error) => ArmError::Dap(error),
+            RecordedError::Timeout => ArmError::Timeout,
+            RecordedError::Other(message) => ArmError::Probe(ReplayError::Recorded(message).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use super::{RecordingProbe, ReplayError, ReplayProbe};
+    use crate::{
+        architecture::arm::{ArmError, DapError, PortType, RawDapAccess},
+        probe::{fake_probe::FakeProbe, DebugProbe, DebugProbeError},
+    };
+
+    /// A buffer which is still accessible after it has been moved into a [`RecordingProbe`].
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs a few DAP operations on `probe`, and returns their results.
+    fn operations(probe: &mut dyn RawDapAccess) -> Vec<Result<u32, String>> {
+        let mut results = vec![];
+
+        probe
+            .raw_write_register(PortType::DebugPort, 8, 0xf0)
+            .unwrap();
+        results.push(
+            probe
+                .raw_read_register(PortType::AccessPort, 0xc)
+                .map_err(|e| e.to_string()),
+        );
+        results.push(
+            probe
+                .raw_write_register(PortType::AccessPort, 4, 0xdead)
+                .map(|()| 0)
+                .map_err(|e| e.to_string()),
+        );
+        let mut block = [0; 2];
+        probe
+            .raw_read_block(PortType::AccessPort, 0xc, &mut block)
+            .unwrap();
+        results.extend(block.map(Ok));
+
+        results
+    }
+
+    fn record() -> (Vec<Result<u32, String>>, Vec<u8>) {
+        let mut probe = FakeProbe::new();
+        let reads = Arc::new(Mutex::new(0));
+        probe.set_dap_register_read_handler(Box::new(move |_, address| {
+            let mut reads = reads.lock().unwrap();
+            *reads += 1;
+            Ok(address as u32 + *reads)
+        }));
+        probe.set_dap_register_write_handler(Box::new(|_, _, value| {
+            if value == 0xdead {
+                Err(DapError::FaultResponse.into())
+            } else {
+                Ok(())
+            }
+        }));
+
+        let buffer = SharedBuffer::default();
+        let mut probe = RecordingProbe::new(probe.into_probe(), buffer.clone()).unwrap();
+        let results = operations(&mut probe);
+        drop(probe);
+
+        let recording = buffer.0.lock().unwrap().clone();
+        (results, recording)
+    }
+
+    fn replay_error(error: &ArmError) -> &ReplayError {
+        let ArmError::Probe(DebugProbeError::ProbeSpecific(error)) = error else {
+            panic!("Unexpected error {error:?}");
+        };
+
+        error.downcast_ref::<ReplayError>().unwrap()
+    }
+
+    #[test]
+    fn replay_returns_recorded_results() {
+        let (recorded, recording) = record();
+        assert_eq!(recorded[0], Ok(0xd));
+        assert!(recorded[1].is_err());
+
+        let mut probe = ReplayProbe::new(&recording[..]).unwrap();
+        assert_eq!(probe.get_name(), "Mock probe for testing");
+        assert_eq!(operations(&mut probe), recorded);
+        assert_eq!(probe.remaining(), 0);
+    }
+
+    #[test]
+    fn replay_keeps_dap_errors() {
+        let (_, recording) = record();
+
+        let mut probe = ReplayProbe::new(&recording[..]).unwrap();
+        probe
+            .raw_write_register(PortType::DebugPort, 8, 0xf0)
+            .unwrap();
+        probe.raw_read_register(PortType::AccessPort, 0xc).unwrap();
+
+        let error = probe
+            .raw_write_register(PortType::AccessPort, 4, 0xdead)
+            .unwrap_err();
+        assert!(matches!(error, ArmError::Dap(DapError::FaultResponse)));
+    }
+
+    #[test]
+    fn replay_detects_divergence() {
+        let (_, recording) = record();
+
+        let mut probe = ReplayProbe::new(&recording[..]).unwrap();
+        let error = probe
+            .raw_write_register(PortType::DebugPort, 8, 0xf1)
+            .unwrap_err();
+        assert!(matches!(replay_error(&error), ReplayError::Mismatch { .. }));
+    }
+
+    #[test]
+    fn replay_detects_end_of_recording() {
+        let (_, recording) = record();
+
+        let mut probe = ReplayProbe::new(&recording[..]).unwrap();
+        operations(&mut probe);
+
+        let error = probe.raw_flush().unwrap_err();
+        assert!(matches!(
+            replay_error(&error),
+            ReplayError::EndOfRecording(_)
+        ));
+    }
+}
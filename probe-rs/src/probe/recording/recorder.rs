@@ -0,0 +1,489 @@
+use std::{fmt, io::Write};
+
+use probe_rs_target::ScanChainElement;
+
+use super::{Header, Operation, Recorded, RecordedError, Transaction, FORMAT_VERSION};
+use crate::{
+    architecture::{
+        arm::{
+            communication_interface::{DapProbe, UninitializedArmProbe},
+            ArmCommunicationInterface, ArmError, PortType, RawDapAccess,
+        },
+        riscv::{communication_interface::RiscvInterfaceBuilder, dtm::jtag_dtm::JtagDtmBuilder},
+        xtensa::communication_interface::{
+            XtensaCommunicationInterface, XtensaDebugInterfaceState,
+        },
+    },
+    probe::{
+        DebugProbe, DebugProbeError, JTAGAccess, JtagRegister, JtagStableState, Probe, WireProtocol,
+    },
+    CoreStatus,
+};
+
+/// A debug probe which records all operations on another probe.
+///
+/// Each operation is written to the recording as soon as it has finished, so the recording is
+/// complete up to the point of a crash. If writing the recording fails, a warning is logged and
+/// the recording stops, but the probe can still be used.
+pub struct RecordingProbe {
+    inner: Box<dyn DebugProbe>,
+    writer: Option<Box<dyn Write + Send>>,
+    /// Whether the recorded probe provides raw DAP access.
+    dap: bool,
+    use_overrun_detect: bool,
+    /// Whether the recorded probe provides low-level JTAG access.
+    jtag: bool,
+    idle_cycles: u8,
+}
+
+impl fmt::Debug for RecordingProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingProbe")
+            .field("inner", &self.inner)
+            .field("recording", &self.writer.is_some())
+            .finish()
+    }
+}
+
+impl RecordingProbe {
+    /// Records all operations on `probe` to `writer`.
+    ///
+    /// The probe must not be attached yet.
+    pub fn new(probe: Probe, writer: impl Write + Send + 'static) -> Result<Self, std::io::Error> {
+        let mut inner = probe.inner;
+
+        let dap = inner.try_as_dap_probe().map(|dap| dap.use_overrun_detect());
+        let idle_cycles = inner
+            .try_as_jtag_probe()
+            .map(|jtag| jtag.idle_cycles())
+            .unwrap_or_default();
+        let header = Header {
+            version: FORMAT_VERSION,
+            name: inner.get_name().to_string(),
+            speed_khz: inner.speed_khz(),
+            protocol: inner.active_protocol(),
+            dap: dap.is_some(),
+            use_overrun_detect: dap.unwrap_or_default(),
+            jtag: inner.try_as_jtag_probe().is_some(),
+            riscv: inner.has_riscv_interface(),
+            xtensa: inner.has_xtensa_interface(),
+        };
+
+        let mut writer = Box::new(std::io::BufWriter::new(writer));
+        serde_json::to_writer(&mut writer, &header)?;
+        writeln!(writer)?;
+
+        Ok(Self {
+            inner,
+            writer: Some(writer),
+            dap: header.dap,
+            use_overrun_detect: header.use_overrun_detect,
+            jtag: header.jtag,
+            idle_cycles,
+        })
+    }
+
+    /// Executes `operation` with `f`, and records its result.
+    fn record<T, E>(
+        &mut self,
+        operation: Operation,
+        f: impl FnOnce(&mut dyn DebugProbe) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        T: Recorded,
+        for<'e> RecordedError: From<&'e E>,
+    {
+        let result = f(self.inner.as_mut());
+
+        let transaction = Transaction {
+            operation,
+            result: match &result {
+                Ok(value) => Ok(value.to_value()),
+                Err(error) => Err(error.into()),
+            },
+        };
+        self.write(&transaction);
+
+        result
+    }
+
+    fn write(&mut self, transaction: &Transaction) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+
+        let result = serde_json::to_writer(&mut *writer, transaction)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writeln!(writer))
+            .and_then(|()| writer.flush());
+        if let Err(error) = result {
+            tracing::warn!("Failed to write the probe recording, recording stopped: {error}");
+            self.writer = None;
+        }
+    }
+}
+
+fn dap(probe: &mut dyn DebugProbe) -> Result<&mut dyn DapProbe, DebugProbeError> {
+    probe
+        .try_as_dap_probe()
+        .ok_or(DebugProbeError::InterfaceNotAvailable {
+            interface_name: "ARM",
+        })
+}
+
+fn jtag(probe: &mut dyn DebugProbe) -> Result<&mut dyn JTAGAccess, DebugProbeError> {
+    probe
+        .try_as_jtag_probe()
+        .ok_or(DebugProbeError::InterfaceNotAvailable {
+            interface_name: "JTAG",
+        })
+}
+
+impl DebugProbe for RecordingProbe {
+    fn get_name(&self) -> &str {
+        self.inner.get_name()
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.inner.speed_khz()
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.record(Operation::SetSpeed(speed_khz), |probe| {
+            probe.set_speed(speed_khz)
+        })
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        self.record(Operation::SetScanChain(scan_chain.clone()), |probe| {
+            probe.set_scan_chain(scan_chain)
+        })
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        self.inner.scan_chain()
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        // The protocol is recorded, because the probe may select it while attaching.
+        self.record(Operation::Attach, |probe| {
+            probe.attach().map(|()| probe.active_protocol())
+        })?;
+
+        Ok(())
+    }
+
+    fn select_jtag_tap(&mut self, index: usize) -> Result<(), DebugProbeError> {
+        self.record(Operation::SelectJtagTap(index), |probe| {
+            probe.select_jtag_tap(index)
+        })
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        let result = self.inner.detach();
+        let transaction = Transaction {
+            operation: Operation::Detach,
+            result: match &result {
+                Ok(()) => Ok(().to_value()),
+                Err(error) => Err(RecordedError::other(error)),
+            },
+        };
+        self.write(&transaction);
+
+        result
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.record(Operation::TargetReset, |probe| probe.target_reset())
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.record(Operation::TargetResetAssert, |probe| {
+            probe.target_reset_assert()
+        })
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.record(Operation::TargetResetDeassert, |probe| {
+            probe.target_reset_deassert()
+        })
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.record(Operation::SelectProtocol(protocol), |probe| {
+            probe.select_protocol(protocol)
+        })
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.inner.active_protocol()
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.dap
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        if !self.dap {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "ARM",
+                },
+            ));
+        }
+
+        let use_overrun_detect = self.use_overrun_detect;
+        Ok(Box::new(ArmCommunicationInterface::new(
+            self,
+            use_overrun_detect,
+        )))
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        if !self.has_riscv_interface() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "RISC-V",
+            });
+        }
+
+        Ok(Box::new(JtagDtmBuilder::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.jtag && self.inner.has_riscv_interface()
+    }
+
+    fn try_get_xtensa_interface<'probe>(
+        &'probe mut self,
+        state: &'probe mut XtensaDebugInterfaceState,
+    ) -> Result<XtensaCommunicationInterface<'probe>, DebugProbeError> {
+        if !self.has_xtensa_interface() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "Xtensa",
+            });
+        }
+
+        Ok(XtensaCommunicationInterface::new(self, state))
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        self.jtag && self.inner.has_xtensa_interface()
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        if self.dap {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.jtag {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        self.record(Operation::TargetVoltage, |probe| probe.get_target_voltage())
+    }
+}
+
+impl RawDapAccess for RecordingProbe {
+    fn raw_read_register(&mut self, port: PortType, address: u8) -> Result<u32, ArmError> {
+        self.record(Operation::Read { port, address }, |probe| {
+            dap(probe)?.raw_read_register(port, address)
+        })
+    }
+
+    fn raw_read_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        let len = values.len();
+        let read = self.record(Operation::ReadBlock { port, address, len }, |probe| {
+            let mut read = vec![0; len];
+            dap(probe)?.raw_read_block(port, address, &mut read)?;
+            Ok::<_, ArmError>(read)
+        })?;
+        values.copy_from_slice(&read);
+
+        Ok(())
+    }
+
+    fn raw_write_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.record(
+            Operation::Write {
+                port,
+                address,
+                value,
+            },
+            |probe| dap(probe)?.raw_write_register(port, address, value),
+        )
+    }
+
+    fn raw_write_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        self.record(
+            Operation::WriteBlock {
+                port,
+                address,
+                values: values.to_vec(),
+            },
+            |probe| dap(probe)?.raw_write_block(port, address, values),
+        )
+    }
+
+    fn raw_flush(&mut self) -> Result<(), ArmError> {
+        self.record(Operation::Flush, |probe| dap(probe)?.raw_flush())
+    }
+
+    fn configure_jtag(&mut self, skip_scan: bool) -> Result<(), DebugProbeError> {
+        self.record(Operation::ConfigureJtag { skip_scan }, |probe| {
+            dap(probe)?.configure_jtag(skip_scan)
+        })
+    }
+
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), DebugProbeError> {
+        self.record(Operation::JtagSequence { cycles, tms, tdi }, |probe| {
+            dap(probe)?.jtag_sequence(cycles, tms, tdi)
+        })
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.record(Operation::SwjSequence { bit_len, bits }, |probe| {
+            dap(probe)?.swj_sequence(bit_len, bits)
+        })
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        self.record(
+            Operation::SwjPins {
+                pin_out,
+                pin_select,
+                pin_wait,
+            },
+            |probe| dap(probe)?.swj_pins(pin_out, pin_select, pin_wait),
+        )
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn core_status_notification(&mut self, state: CoreStatus) -> Result<(), DebugProbeError> {
+        // This only drives status LEDs of some probes, so it isn't recorded.
+        dap(self.inner.as_mut())?.core_status_notification(state)
+    }
+}
+
+impl DapProbe for RecordingProbe {
+    fn use_overrun_detect(&self) -> bool {
+        self.use_overrun_detect
+    }
+}
+
+impl JTAGAccess for RecordingProbe {
+    fn scan_chain(&mut self) -> Result<(), DebugProbeError> {
+        self.record(Operation::ScanChain, |probe| {
+            JTAGAccess::scan_chain(jtag(probe)?)?;
+            Ok::<_, DebugProbeError>(DebugProbe::scan_chain(probe)?.to_vec())
+        })?;
+
+        Ok(())
+    }
+
+    fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.record(Operation::TapReset, |probe| jtag(probe)?.tap_reset())
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        // The idle cycles are not recorded, because they don't change the data which is shifted.
+        self.idle_cycles = idle_cycles;
+        if let Some(jtag) = self.inner.try_as_jtag_probe() {
+            jtag.set_idle_cycles(idle_cycles);
+        }
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.record(
+            Operation::WriteRegister {
+                address,
+                data: data.to_vec(),
+                len,
+            },
+            |probe| jtag(probe)?.write_register(address, data, len),
+        )
+    }
+
+    fn write_dr(&mut self, data: &[u8], len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        self.record(
+            Operation::WriteDr {
+                data: data.to_vec(),
+                len,
+            },
+            |probe| jtag(probe)?.write_dr(data, len),
+        )
+    }
+
+    fn run_test(&mut self, state: JtagStableState, cycles: u32) -> Result<(), DebugProbeError> {
+        self.record(Operation::RunTest { state, cycles }, |probe| {
+            jtag(probe)?.run_test(state, cycles)
+        })
+    }
+
+    fn shift_raw(
+        &mut self,
+        register: JtagRegister,
+        data: &[u8],
+        len: u32,
+        end_state: JtagStableState,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.record(
+            Operation::ShiftRaw {
+                register,
+                data: data.to_vec(),
+                len,
+                end_state,
+            },
+            |probe| jtag(probe)?.shift_raw(register, data, len, end_state),
+        )
+    }
+}
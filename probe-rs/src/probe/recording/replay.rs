@@ -0,0 +1,445 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use probe_rs_target::ScanChainElement;
+
+use super::{Header, Operation, Recorded, Transaction, FORMAT_VERSION};
+use crate::{
+    architecture::{
+        arm::{
+            communication_interface::{DapProbe, UninitializedArmProbe},
+            ArmCommunicationInterface, ArmError, PortType, RawDapAccess,
+        },
+        riscv::{communication_interface::RiscvInterfaceBuilder, dtm::jtag_dtm::JtagDtmBuilder},
+        xtensa::communication_interface::{
+            XtensaCommunicationInterface, XtensaDebugInterfaceState,
+        },
+    },
+    probe::{
+        DebugProbe, DebugProbeError, JTAGAccess, JtagRegister, JtagStableState, ProbeError,
+        WireProtocol,
+    },
+    CoreStatus,
+};
+
+/// An error while replaying a recording.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum ReplayError {
+    /// Failed to read the recording.
+    Io(#[from] std::io::Error),
+
+    /// The recording is malformed.
+    Format(#[from] serde_json::Error),
+
+    /// The recording is empty.
+    Empty,
+
+    /// The recording has version {0} of the format, which is not supported.
+    UnsupportedVersion(u32),
+
+    /// The operation {actual} doesn't match the recorded operation {expected}.
+    Mismatch {
+        /// The recorded operation.
+        expected: String,
+        /// The operation which was executed instead.
+        actual: String,
+    },
+
+    /// The operation {0} was executed after the end of the recording.
+    EndOfRecording(String),
+
+    /// The recorded result of the operation {0} has the wrong type.
+    InvalidResult(String),
+
+    /// The recorded operation failed: {0}
+    Recorded(String),
+}
+
+impl ProbeError for ReplayError {}
+
+/// A debug probe which replays a recording of a [`RecordingProbe`](super::RecordingProbe).
+///
+/// The operations on the probe have to be executed in the same order as they were recorded, and
+/// return the recorded results. Any other operation fails with [`ReplayError::Mismatch`].
+#[derive(Debug)]
+pub struct ReplayProbe {
+    header: Header,
+    transactions: VecDeque<Transaction>,
+    scan_chain: Option<Vec<ScanChainElement>>,
+    idle_cycles: u8,
+}
+
+impl ReplayProbe {
+    /// Opens the recording in the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a recording from `reader`.
+    pub fn new(reader: impl BufRead) -> Result<Self, ReplayError> {
+        let mut lines = reader.lines();
+
+        let header = lines.next().ok_or(ReplayError::Empty)??;
+        let header = serde_json::from_str::<Header>(&header)?;
+        if header.version != FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedVersion(header.version));
+        }
+
+        let transactions = lines
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<Result<_, ReplayError>>()?;
+
+        Ok(Self {
+            header,
+            transactions,
+            scan_chain: None,
+            idle_cycles: 0,
+        })
+    }
+
+    /// Returns the number of recorded operations which have not been replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Returns the recorded result of `operation`.
+    fn replay<T, E>(&mut self, operation: Operation) -> Result<T, E>
+    where
+        T: Recorded,
+        E: From<super::RecordedError> + From<DebugProbeError>,
+    {
+        let Some(transaction) = self.transactions.pop_front() else {
+            return Err(DebugProbeError::from(ReplayError::EndOfRecording(format!(
+                "{operation:?}"
+            )))
+            .into());
+        };
+
+        if transaction.operation != operation {
+            return Err(DebugProbeError::from(ReplayError::Mismatch {
+                expected: format!("{:?}", transaction.operation),
+                actual: format!("{operation:?}"),
+            })
+            .into());
+        }
+
+        match transaction.result {
+            Ok(value) => T::from_value(value).ok_or_else(|| {
+                DebugProbeError::from(ReplayError::InvalidResult(format!("{operation:?}"))).into()
+            }),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl DebugProbe for ReplayProbe {
+    fn get_name(&self) -> &str {
+        &self.header.name
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.header.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        let speed_khz = self.replay::<_, DebugProbeError>(Operation::SetSpeed(speed_khz))?;
+        self.header.speed_khz = speed_khz;
+
+        Ok(speed_khz)
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        self.replay::<(), DebugProbeError>(Operation::SetScanChain(scan_chain.clone()))?;
+        self.scan_chain = Some(scan_chain);
+
+        Ok(())
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        match &self.scan_chain {
+            Some(chain) => Ok(chain),
+            None => Err(DebugProbeError::Other(
+                "No scan chain set for replay probe".to_string(),
+            )),
+        }
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        self.header.protocol = self.replay::<_, DebugProbeError>(Operation::Attach)?;
+
+        Ok(())
+    }
+
+    fn select_jtag_tap(&mut self, index: usize) -> Result<(), DebugProbeError> {
+        self.replay(Operation::SelectJtagTap(index))
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        Ok(self.replay::<(), DebugProbeError>(Operation::Detach)?)
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Operation::TargetReset)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Operation::TargetResetAssert)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Operation::TargetResetDeassert)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.replay::<(), DebugProbeError>(Operation::SelectProtocol(protocol))?;
+        self.header.protocol = Some(protocol);
+
+        Ok(())
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.header.protocol
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.header.dap
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        if !self.header.dap {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "ARM",
+                },
+            ));
+        }
+
+        let use_overrun_detect = self.header.use_overrun_detect;
+        Ok(Box::new(ArmCommunicationInterface::new(
+            self,
+            use_overrun_detect,
+        )))
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        if !self.has_riscv_interface() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "RISC-V",
+            });
+        }
+
+        Ok(Box::new(JtagDtmBuilder::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.header.jtag && self.header.riscv
+    }
+
+    fn try_get_xtensa_interface<'probe>(
+        &'probe mut self,
+        state: &'probe mut XtensaDebugInterfaceState,
+    ) -> Result<XtensaCommunicationInterface<'probe>, DebugProbeError> {
+        if !self.has_xtensa_interface() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "Xtensa",
+            });
+        }
+
+        Ok(XtensaCommunicationInterface::new(self, state))
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        self.header.jtag && self.header.xtensa
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        if self.header.dap {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.header.jtag {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        self.replay(Operation::TargetVoltage)
+    }
+}
+
+impl RawDapAccess for ReplayProbe {
+    fn raw_read_register(&mut self, port: PortType, address: u8) -> Result<u32, ArmError> {
+        self.replay(Operation::Read { port, address })
+    }
+
+    fn raw_read_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        let operation = Operation::ReadBlock {
+            port,
+            address,
+            len: values.len(),
+        };
+        let read = self.replay::<Vec<u32>, ArmError>(operation.clone())?;
+        if read.len() != values.len() {
+            return Err(ArmError::Probe(
+                ReplayError::InvalidResult(format!("{operation:?}")).into(),
+            ));
+        }
+        values.copy_from_slice(&read);
+
+        Ok(())
+    }
+
+    fn raw_write_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.replay(Operation::Write {
+            port,
+            address,
+            value,
+        })
+    }
+
+    fn raw_write_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        self.replay(Operation::WriteBlock {
+            port,
+            address,
+            values: values.to_vec(),
+        })
+    }
+
+    fn raw_flush(&mut self) -> Result<(), ArmError> {
+        self.replay(Operation::Flush)
+    }
+
+    fn configure_jtag(&mut self, skip_scan: bool) -> Result<(), DebugProbeError> {
+        self.replay(Operation::ConfigureJtag { skip_scan })
+    }
+
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), DebugProbeError> {
+        self.replay(Operation::JtagSequence { cycles, tms, tdi })
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.replay(Operation::SwjSequence { bit_len, bits })
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        self.replay(Operation::SwjPins {
+            pin_out,
+            pin_select,
+            pin_wait,
+        })
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn core_status_notification(&mut self, _state: CoreStatus) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+}
+
+impl DapProbe for ReplayProbe {
+    fn use_overrun_detect(&self) -> bool {
+        self.header.use_overrun_detect
+    }
+}
+
+impl JTAGAccess for ReplayProbe {
+    fn scan_chain(&mut self) -> Result<(), DebugProbeError> {
+        self.scan_chain = Some(self.replay::<_, DebugProbeError>(Operation::ScanChain)?);
+
+        Ok(())
+    }
+
+    fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.replay(Operation::TapReset)
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        self.idle_cycles = idle_cycles;
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.replay(Operation::WriteRegister {
+            address,
+            data: data.to_vec(),
+            len,
+        })
+    }
+
+    fn write_dr(&mut self, data: &[u8], len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        self.replay(Operation::WriteDr {
+            data: data.to_vec(),
+            len,
+        })
+    }
+
+    fn run_test(&mut self, state: JtagStableState, cycles: u32) -> Result<(), DebugProbeError> {
+        self.replay(Operation::RunTest { state, cycles })
+    }
+
+    fn shift_raw(
+        &mut self,
+        register: JtagRegister,
+        data: &[u8],
+        len: u32,
+        end_state: JtagStableState,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.replay(Operation::ShiftRaw {
+            register,
+            data: data.to_vec(),
+            len,
+            end_state,
+        })
+    }
+}
@@ -0,0 +1,628 @@
+use std::net::TcpStream;
+
+use probe_rs_target::ScanChainElement;
+
+use super::{
+    protocol::{
+        read_message, write_message, DapCommand, JtagCommand, JtagReply, ProbeDescription,
+        RemoteError, Request, Response, MAX_READ_VALUES, PROTOCOL_VERSION,
+    },
+    RemoteProbeError,
+};
+use crate::{
+    architecture::{
+        arm::{
+            communication_interface::{DapProbe, UninitializedArmProbe},
+            ArmCommunicationInterface, ArmError, PortType, RawDapAccess,
+        },
+        riscv::{communication_interface::RiscvInterfaceBuilder, dtm::jtag_dtm::JtagDtmBuilder},
+        xtensa::communication_interface::{
+            XtensaCommunicationInterface, XtensaDebugInterfaceState,
+        },
+    },
+    probe::{
+        BatchExecutionError, DebugProbe, DebugProbeError, DebugProbeSelector, DeferredResultSet,
+        JTAGAccess, JtagCommandQueue, JtagRegister, JtagStableState, WireProtocol,
+    },
+    CoreStatus,
+};
+
+/// The number of words written by batched DAP commands after which the batch is sent to the
+/// server.
+const MAX_BATCH_WORDS: usize = 16 * 1024;
+
+/// A debug probe which is attached to a probe server.
+#[derive(Debug)]
+pub struct RemoteProbe {
+    stream: TcpStream,
+    description: ProbeDescription,
+    scan_chain: Option<Vec<ScanChainElement>>,
+    idle_cycles: u8,
+    /// Set when the idle cycles have been changed, but not been sent to the server yet.
+    idle_cycles_changed: bool,
+    batch: Vec<DapCommand>,
+    batch_words: usize,
+}
+
+/// The error of a request to the server.
+enum CallError {
+    /// The communication with the server failed.
+    Transport(RemoteProbeError),
+    /// The server failed to execute the request.
+    Remote(RemoteError),
+}
+
+impl From<RemoteProbeError> for CallError {
+    fn from(error: RemoteProbeError) -> Self {
+        CallError::Transport(error)
+    }
+}
+
+impl From<CallError> for DebugProbeError {
+    fn from(error: CallError) -> Self {
+        match error {
+            CallError::Transport(error) => error.into(),
+            CallError::Remote(error) => error.into(),
+        }
+    }
+}
+
+impl From<CallError> for ArmError {
+    fn from(error: CallError) -> Self {
+        match error {
+            CallError::Transport(error) => ArmError::Probe(error.into()),
+            CallError::Remote(error) => error.into(),
+        }
+    }
+}
+
+impl RemoteProbe {
+    /// Connects to the probe server at `address` and opens the probe selected by `selector`.
+    pub fn open(address: &str, selector: &DebugProbeSelector) -> Result<Self, RemoteProbeError> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        write_message(
+            &mut stream,
+            &Request::Open {
+                version: PROTOCOL_VERSION,
+                selector: selector.to_string(),
+            },
+        )?;
+
+        let description = match read_message(&mut stream)? {
+            Response::Opened(description) => description,
+            Response::Error(RemoteError::Other(message)) => {
+                return Err(RemoteProbeError::Server(message))
+            }
+            _ => return Err(RemoteProbeError::UnexpectedResponse),
+        };
+
+        tracing::info!("Opened {} at {address}", description.name);
+
+        Ok(Self {
+            stream,
+            description,
+            scan_chain: None,
+            idle_cycles: 0,
+            idle_cycles_changed: false,
+            batch: vec![],
+            batch_words: 0,
+        })
+    }
+
+    fn request(&mut self, request: &Request) -> Result<Response, RemoteProbeError> {
+        write_message(&mut self.stream, request)?;
+        read_message(&mut self.stream)
+    }
+
+    /// Sends a request, after the batched DAP commands have been executed.
+    fn call(&mut self, request: Request) -> Result<Response, CallError> {
+        self.flush_batch()?;
+
+        match self.request(&request)? {
+            Response::Error(error) => Err(CallError::Remote(error)),
+            response => Ok(response),
+        }
+    }
+
+    fn call_ok(&mut self, request: Request) -> Result<(), DebugProbeError> {
+        match self.call(request)? {
+            Response::Ok => Ok(()),
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+
+    fn call_protocol(&mut self, request: Request) -> Result<(), DebugProbeError> {
+        match self.call(request)? {
+            Response::Protocol(protocol) => {
+                self.description.protocol = protocol;
+                Ok(())
+            }
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+
+    /// Executes the batched DAP commands, followed by `commands`, and returns the values read
+    /// by them.
+    fn execute_dap(&mut self, commands: Vec<DapCommand>) -> Result<Vec<u32>, CallError> {
+        let mut batch = std::mem::take(&mut self.batch);
+        self.batch_words = 0;
+        batch.extend(commands);
+        if batch.is_empty() {
+            return Ok(vec![]);
+        }
+
+        match self.request(&Request::Dap(batch))? {
+            Response::Dap {
+                values,
+                error: None,
+            } => Ok(values),
+            Response::Dap {
+                error: Some(error), ..
+            }
+            | Response::Error(error) => Err(CallError::Remote(error)),
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+
+    fn flush_batch(&mut self) -> Result<(), CallError> {
+        self.execute_dap(vec![]).map(|_| ())
+    }
+
+    /// Adds a command which doesn't return a value to the batch.
+    ///
+    /// Errors of batched commands are reported by the next request.
+    fn batch_add(&mut self, command: DapCommand, words: usize) -> Result<(), ArmError> {
+        self.batch.push(command);
+        self.batch_words += words;
+        if self.batch_words >= MAX_BATCH_WORDS {
+            self.flush_batch()?;
+        }
+
+        Ok(())
+    }
+
+    fn execute_jtag(&mut self, command: JtagCommand) -> Result<JtagReply, DebugProbeError> {
+        let (mut replies, error) = self.execute_jtag_batch(vec![command])?;
+        match error {
+            Some(error) => Err(error.into()),
+            None => replies
+                .pop()
+                .ok_or_else(|| RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+
+    /// Executes the JTAG commands, and returns the replies of the commands which were executed
+    /// before one failed.
+    fn execute_jtag_batch(
+        &mut self,
+        mut commands: Vec<JtagCommand>,
+    ) -> Result<(Vec<JtagReply>, Option<RemoteError>), DebugProbeError> {
+        let set_idle_cycles = self.idle_cycles_changed;
+        if set_idle_cycles {
+            commands.insert(0, JtagCommand::SetIdleCycles(self.idle_cycles));
+        }
+        let expected = commands.len();
+
+        match self.call(Request::Jtag(commands))? {
+            Response::Jtag { mut replies, error } => {
+                if set_idle_cycles && !replies.is_empty() {
+                    self.idle_cycles_changed = false;
+                    replies.remove(0);
+                }
+                if error.is_none() && replies.len() + usize::from(set_idle_cycles) != expected {
+                    return Err(RemoteProbeError::UnexpectedResponse.into());
+                }
+
+                Ok((replies, error))
+            }
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+
+    fn jtag_data(&mut self, command: JtagCommand) -> Result<Vec<u8>, DebugProbeError> {
+        match self.execute_jtag(command)? {
+            JtagReply::Data(data) => Ok(data),
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+}
+
+impl DebugProbe for RemoteProbe {
+    fn get_name(&self) -> &str {
+        &self.description.name
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.description.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        match self.call(Request::SetSpeed(speed_khz))? {
+            Response::Speed(speed_khz) => {
+                self.description.speed_khz = speed_khz;
+                Ok(speed_khz)
+            }
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        self.call_ok(Request::SetScanChain(scan_chain.clone()))?;
+        self.scan_chain = Some(scan_chain);
+
+        Ok(())
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        match &self.scan_chain {
+            Some(chain) => Ok(chain),
+            None => Err(DebugProbeError::Other(
+                "No scan chain set for remote probe".to_string(),
+            )),
+        }
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        self.call_protocol(Request::Attach)
+    }
+
+    fn select_jtag_tap(&mut self, index: usize) -> Result<(), DebugProbeError> {
+        self.call_ok(Request::SelectJtagTap(index))
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        Ok(self.call_ok(Request::Detach)?)
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.call_ok(Request::TargetReset)
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.call_ok(Request::TargetResetAssert)
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.call_ok(Request::TargetResetDeassert)
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        self.call_protocol(Request::SelectProtocol(protocol))
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        self.description.protocol
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        self.description.dap
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        if !self.description.dap {
+            return Err((
+                self,
+                DebugProbeError::InterfaceNotAvailable {
+                    interface_name: "ARM",
+                },
+            ));
+        }
+
+        let use_overrun_detect = self.description.use_overrun_detect;
+        Ok(Box::new(ArmCommunicationInterface::new(
+            self,
+            use_overrun_detect,
+        )))
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        if !self.has_riscv_interface() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "RISC-V",
+            });
+        }
+
+        Ok(Box::new(JtagDtmBuilder::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        self.description.jtag && self.description.riscv
+    }
+
+    fn try_get_xtensa_interface<'probe>(
+        &'probe mut self,
+        state: &'probe mut XtensaDebugInterfaceState,
+    ) -> Result<XtensaCommunicationInterface<'probe>, DebugProbeError> {
+        if !self.has_xtensa_interface() {
+            return Err(DebugProbeError::InterfaceNotAvailable {
+                interface_name: "Xtensa",
+            });
+        }
+
+        Ok(XtensaCommunicationInterface::new(self, state))
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        self.description.jtag && self.description.xtensa
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        if self.description.dap {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        if self.description.jtag {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        match self.call(Request::TargetVoltage)? {
+            Response::Voltage(voltage) => Ok(voltage),
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+}
+
+impl RawDapAccess for RemoteProbe {
+    fn raw_read_register(&mut self, port: PortType, address: u8) -> Result<u32, ArmError> {
+        let values = self.execute_dap(vec![DapCommand::Read { port, address }])?;
+
+        values
+            .last()
+            .copied()
+            .ok_or_else(|| ArmError::Probe(RemoteProbeError::UnexpectedResponse.into()))
+    }
+
+    fn raw_read_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &mut [u32],
+    ) -> Result<(), ArmError> {
+        // The server limits the number of values read by a single request.
+        for chunk in values.chunks_mut(MAX_READ_VALUES) {
+            let read = self.execute_dap(vec![DapCommand::ReadBlock {
+                port,
+                address,
+                len: chunk.len() as u32,
+            }])?;
+            if read.len() != chunk.len() {
+                return Err(ArmError::Probe(RemoteProbeError::UnexpectedResponse.into()));
+            }
+
+            chunk.copy_from_slice(&read);
+        }
+
+        Ok(())
+    }
+
+    fn raw_write_register(
+        &mut self,
+        port: PortType,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.batch_add(
+            DapCommand::Write {
+                port,
+                address,
+                value,
+            },
+            1,
+        )
+    }
+
+    fn raw_write_block(
+        &mut self,
+        port: PortType,
+        address: u8,
+        values: &[u32],
+    ) -> Result<(), ArmError> {
+        self.batch_add(
+            DapCommand::WriteBlock {
+                port,
+                address,
+                values: values.to_vec(),
+            },
+            values.len(),
+        )
+    }
+
+    fn raw_flush(&mut self) -> Result<(), ArmError> {
+        self.execute_dap(vec![DapCommand::Flush])?;
+
+        Ok(())
+    }
+
+    fn configure_jtag(&mut self, skip_scan: bool) -> Result<(), DebugProbeError> {
+        self.execute_dap(vec![DapCommand::ConfigureJtag { skip_scan }])?;
+
+        Ok(())
+    }
+
+    fn jtag_sequence(&mut self, cycles: u8, tms: bool, tdi: u64) -> Result<(), DebugProbeError> {
+        self.execute_dap(vec![DapCommand::JtagSequence { cycles, tms, tdi }])?;
+
+        Ok(())
+    }
+
+    fn swj_sequence(&mut self, bit_len: u8, bits: u64) -> Result<(), DebugProbeError> {
+        self.execute_dap(vec![DapCommand::SwjSequence { bit_len, bits }])?;
+
+        Ok(())
+    }
+
+    fn swj_pins(
+        &mut self,
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        let values = self.execute_dap(vec![DapCommand::SwjPins {
+            pin_out,
+            pin_select,
+            pin_wait,
+        }])?;
+
+        values
+            .last()
+            .copied()
+            .ok_or_else(|| RemoteProbeError::UnexpectedResponse.into())
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn core_status_notification(&mut self, state: CoreStatus) -> Result<(), DebugProbeError> {
+        // This is called whenever the core status is polled, so it is sent with the next request.
+        self.batch.push(DapCommand::CoreStatus {
+            running: state.is_running(),
+        });
+
+        Ok(())
+    }
+}
+
+impl DapProbe for RemoteProbe {
+    fn use_overrun_detect(&self) -> bool {
+        self.description.use_overrun_detect
+    }
+}
+
+impl JTAGAccess for RemoteProbe {
+    fn scan_chain(&mut self) -> Result<(), DebugProbeError> {
+        match self.execute_jtag(JtagCommand::ScanChain)? {
+            JtagReply::ScanChain(chain) => {
+                self.scan_chain = Some(chain);
+                Ok(())
+            }
+            _ => Err(RemoteProbeError::UnexpectedResponse.into()),
+        }
+    }
+
+    fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.execute_jtag(JtagCommand::TapReset).map(|_| ())
+    }
+
+    fn set_idle_cycles(&mut self, idle_cycles: u8) {
+        // This can't fail, so the idle cycles are sent with the next JTAG command.
+        if idle_cycles != self.idle_cycles {
+            self.idle_cycles = idle_cycles;
+            self.idle_cycles_changed = true;
+        }
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        self.idle_cycles
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.jtag_data(JtagCommand::WriteRegister {
+            address,
+            data: data.to_vec(),
+            len,
+        })
+    }
+
+    fn write_dr(&mut self, data: &[u8], len: u32) -> Result<Vec<u8>, DebugProbeError> {
+        self.jtag_data(JtagCommand::WriteDr {
+            data: data.to_vec(),
+            len,
+        })
+    }
+
+    fn run_test(&mut self, state: JtagStableState, cycles: u32) -> Result<(), DebugProbeError> {
+        self.execute_jtag(JtagCommand::RunTest { state, cycles })
+            .map(|_| ())
+    }
+
+    fn shift_raw(
+        &mut self,
+        register: JtagRegister,
+        data: &[u8],
+        len: u32,
+        end_state: JtagStableState,
+    ) -> Result<Vec<u8>, DebugProbeError> {
+        self.jtag_data(JtagCommand::ShiftRaw {
+            register,
+            data: data.to_vec(),
+            len,
+            end_state,
+        })
+    }
+
+    fn write_register_batch(
+        &mut self,
+        writes: &JtagCommandQueue,
+    ) -> Result<DeferredResultSet, BatchExecutionError> {
+        let commands = writes
+            .iter()
+            .map(|(_, command)| match command {
+                crate::probe::JtagCommand::WriteRegister(write) => JtagCommand::WriteRegister {
+                    address: write.address,
+                    data: write.data.clone(),
+                    len: write.len,
+                },
+                crate::probe::JtagCommand::ShiftDr(write) => JtagCommand::WriteDr {
+                    data: write.data.clone(),
+                    len: write.len,
+                },
+            })
+            .collect();
+
+        let mut results = DeferredResultSet::with_capacity(writes.len());
+        let (replies, error) = self
+            .execute_jtag_batch(commands)
+            .map_err(|error| BatchExecutionError::new(error.into(), DeferredResultSet::new()))?;
+
+        for ((index, command), reply) in writes.iter().zip(replies) {
+            let JtagReply::Data(response) = reply else {
+                let error = DebugProbeError::from(RemoteProbeError::UnexpectedResponse);
+                return Err(BatchExecutionError::new(error.into(), results));
+            };
+
+            let result = match command {
+                crate::probe::JtagCommand::WriteRegister(write) => {
+                    (write.transform)(write, response)
+                }
+                crate::probe::JtagCommand::ShiftDr(write) => (write.transform)(write, response),
+            };
+            match result {
+                Ok(result) => results.push(index, result),
+                Err(error) => return Err(BatchExecutionError::new(error, results)),
+            }
+        }
+
+        match error {
+            Some(error) => Err(BatchExecutionError::new(
+                DebugProbeError::from(error).into(),
+                results,
+            )),
+            None => Ok(results),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Debug probes which are attached to another machine.
+//!
+//! A probe server, started with [`serve`], makes the probes attached to its machine available
+//! over TCP. The probes are accessed at the level of [`DebugProbe`],
+//! [`RawDapAccess`](crate::architecture::arm::RawDapAccess) and [`JTAGAccess`](super::JTAGAccess),
+//! so the debugging and flashing logic runs on the client, like it does for a local probe.
+//! Writes to DAP registers are batched, so the latency of the network only affects reads.
+//!
+//! A remote probe is selected by prefixing the selector of the probe with the address of the
+//! server, as in `tcp://lab-pc:3500/1366:1015:000123456789`. The pseudo VID:PIDs `0000:0001`
+//! and `0000:0002` select a [`remote_bitbang`](super::remote_bitbang) or
+//! [`jtag_vpi`](super::jtag_vpi) server instead.
+//!
+//! Only probes which provide raw DAP access, like CMSIS-DAP probes and J-Links, or low-level
+//! JTAG access can be used remotely.
+//!
+//! The protocol is neither authenticated nor encrypted, so a probe server should only be
+//! reachable from trusted networks.
+
+mod client;
+mod protocol;
+mod server;
+
+pub use client::RemoteProbe;
+pub use server::{serve, serve_connection};
+
+use crate::probe::{
+    DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, ProbeCreationError,
+    ProbeError, ProbeFactory,
+};
+
+/// An error in the communication with a probe server.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum RemoteProbeError {
+    /// Failed to communicate with the probe server.
+    Io(#[from] std::io::Error),
+
+    /// Failed to encode or decode a message.
+    Encoding(#[from] bincode::Error),
+
+    /// The message is larger than the maximum message size.
+    MessageTooLarge,
+
+    /// The probe server sent an unexpected response.
+    UnexpectedResponse,
+
+    /// The probe server reported an error: {0}
+    Server(String),
+}
+
+impl ProbeError for RemoteProbeError {}
+
+/// A factory for probes which are attached to a probe server.
+///
+/// Remote probes can't be discovered, so they are never listed.
+#[derive(Debug)]
+pub struct RemoteProbeFactory;
+
+impl std::fmt::Display for RemoteProbeFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Remote probe")
+    }
+}
+
+impl ProbeFactory for RemoteProbeFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        let Some(address) = selector.remote.as_deref() else {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound,
+            ));
+        };
+
+        let local_selector = DebugProbeSelector {
+            remote: None,
+            ..selector.clone()
+        };
+
+        Ok(Box::new(RemoteProbe::open(address, &local_selector)?))
+    }
+
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+    };
+
+    use super::{
+        protocol::{
+            read_message, write_message, DapCommand, Request, Response, MAX_READ_VALUES,
+            PROTOCOL_VERSION,
+        },
+        serve_connection, RemoteProbe,
+    };
+    use crate::{
+        architecture::arm::{ArmError, DapError, PortType, RawDapAccess},
+        probe::{
+            fake_probe::FakeProbe,
+            list::{Lister, ProbeLister},
+            DebugProbeError, DebugProbeInfo, DebugProbeSelector, Probe,
+        },
+    };
+
+    type Writes = Arc<Mutex<Vec<(PortType, u8, u32)>>>;
+
+    /// Opens a [`FakeProbe`] which records all writes, and fails writes of `0xdead`.
+    #[derive(Debug)]
+    struct FakeLister {
+        writes: Writes,
+    }
+
+    impl ProbeLister for FakeLister {
+        fn open(&self, _selector: &DebugProbeSelector) -> Result<Probe, DebugProbeError> {
+            let mut probe = FakeProbe::new();
+            probe.set_dap_register_read_handler(Box::new(|_, address| Ok(address as u32)));
+
+            let writes = self.writes.clone();
+            probe.set_dap_register_write_handler(Box::new(move |port, address, value| {
+                if value == 0xdead {
+                    return Err(DapError::FaultResponse.into());
+                }
+                writes.lock().unwrap().push((port, address, value));
+                Ok(())
+            }));
+
+            Ok(probe.into_probe())
+        }
+
+        fn list_all(&self) -> Vec<DebugProbeInfo> {
+            vec![]
+        }
+    }
+
+    /// Starts a server for a single client, and returns its address.
+    fn start_server(writes: Writes) -> String {
+        let lister = FakeLister { writes };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve_connection(stream, &Lister::with_lister(Box::new(lister))).unwrap();
+        });
+
+        address
+    }
+
+    /// Starts a server for a single client, and connects to it.
+    fn connect() -> (RemoteProbe, Writes) {
+        let writes = Writes::default();
+        let address = start_server(writes.clone());
+
+        let selector = "1234:5678".parse::<DebugProbeSelector>().unwrap();
+        (RemoteProbe::open(&address, &selector).unwrap(), writes)
+    }
+
+    #[test]
+    fn open_through_selector() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let lister = FakeLister {
+                writes: Writes::default(),
+            };
+            serve_connection(stream, &Lister::with_lister(Box::new(lister))).unwrap();
+        });
+
+        let selector = format!("tcp://{address}/1234:5678")
+            .parse::<DebugProbeSelector>()
+            .unwrap();
+        let mut probe = Lister::new().open(selector).unwrap();
+
+        assert_eq!(probe.get_name(), "Mock probe for testing");
+        assert_eq!(probe.set_speed(1234).unwrap(), 1234);
+        assert_eq!(probe.speed_khz(), 1234);
+    }
+
+    #[test]
+    fn writes_are_batched() {
+        let (mut probe, writes) = connect();
+
+        probe.raw_write_register(PortType::DebugPort, 4, 1).unwrap();
+        probe
+            .raw_write_block(PortType::AccessPort, 0xc, &[2, 3])
+            .unwrap();
+        assert!(writes.lock().unwrap().is_empty());
+
+        assert_eq!(probe.raw_read_register(PortType::AccessPort, 8).unwrap(), 8);
+        assert_eq!(
+            *writes.lock().unwrap(),
+            [
+                (PortType::DebugPort, 4, 1),
+                (PortType::AccessPort, 0xc, 2),
+                (PortType::AccessPort, 0xc, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn batched_write_error_is_reported_by_next_request() {
+        let (mut probe, writes) = connect();
+
+        probe
+            .raw_write_register(PortType::DebugPort, 4, 0xdead)
+            .unwrap();
+        probe.raw_write_register(PortType::DebugPort, 4, 1).unwrap();
+
+        let error = probe.raw_read_register(PortType::DebugPort, 0).unwrap_err();
+        assert!(matches!(error, ArmError::Dap(DapError::FaultResponse)));
+        // The commands after the failed write are not executed.
+        assert!(writes.lock().unwrap().is_empty());
+
+        // The batch has been discarded.
+        assert_eq!(probe.raw_read_register(PortType::DebugPort, 8).unwrap(), 8);
+    }
+
+    #[test]
+    fn read_block() {
+        let (mut probe, _) = connect();
+
+        let mut values = [0; 3];
+        probe
+            .raw_read_block(PortType::AccessPort, 0xc, &mut values)
+            .unwrap();
+        assert_eq!(values, [0xc; 3]);
+    }
+
+    #[test]
+    fn large_read_block_is_split() {
+        let (mut probe, _) = connect();
+
+        let mut values = vec![0; MAX_READ_VALUES + 3];
+        probe
+            .raw_read_block(PortType::AccessPort, 0xc, &mut values)
+            .unwrap();
+        assert!(values.iter().all(|&value| value == 0xc));
+    }
+
+    #[test]
+    fn server_rejects_oversized_reads() {
+        let mut stream = TcpStream::connect(start_server(Writes::default())).unwrap();
+        write_message(
+            &mut stream,
+            &Request::Open {
+                version: PROTOCOL_VERSION,
+                selector: "1234:5678".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            read_message(&mut stream).unwrap(),
+            Response::Opened(_)
+        ));
+
+        let read_block = |len| DapCommand::ReadBlock {
+            port: PortType::AccessPort,
+            address: 0xc,
+            len,
+        };
+
+        // A single command which is too large.
+        write_message(&mut stream, &Request::Dap(vec![read_block(u32::MAX)])).unwrap();
+        let response = read_message(&mut stream).unwrap();
+        assert!(matches!(
+            response,
+            Response::Dap { values, error: Some(_) } if values.is_empty()
+        ));
+
+        // Commands which are too large together. The values read before are returned.
+        let half = MAX_READ_VALUES as u32 / 2 + 1;
+        write_message(
+            &mut stream,
+            &Request::Dap(vec![read_block(half), read_block(half)]),
+        )
+        .unwrap();
+        let response = read_message(&mut stream).unwrap();
+        assert!(matches!(
+            response,
+            Response::Dap { values, error: Some(_) } if values.len() == half as usize
+        ));
+    }
+}
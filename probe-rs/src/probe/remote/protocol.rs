@@ -0,0 +1,285 @@
+//! The messages exchanged between a probe server and its clients.
+//!
+//! Every message is encoded with `bincode` and prefixed with its length as a little endian `u32`.
+//! The client sends a [`Request`] and waits for the [`Response`] of the server before sending
+//! the next request.
+
+use std::io::{Read, Write};
+
+use probe_rs_target::ScanChainElement;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::RemoteProbeError;
+use crate::{
+    architecture::arm::{ArmError, DapError, PortType},
+    probe::{DebugProbeError, JtagRegister, JtagStableState, WireProtocol},
+};
+
+/// The version of the protocol. Must be increased whenever a message changes.
+pub(super) const PROTOCOL_VERSION: u32 = 2;
+
+/// The maximum size of a single message, which protects against allocating huge buffers when
+/// something other than a probe server or client is connected.
+const MAX_MESSAGE_SIZE: u32 = 64 * 1024 * 1024;
+
+/// The maximum number of values read by the commands of a single [`Request::Dap`], which keeps
+/// the response well below the maximum message size.
+pub(super) const MAX_READ_VALUES: usize = 1024 * 1024;
+
+/// A request from the client.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) enum Request {
+    /// Opens the probe selected by `selector`.
+    ///
+    /// This has to stay the first variant, so it can be decoded by servers with a different
+    /// protocol version.
+    Open {
+        version: u32,
+        selector: String,
+    },
+    SetSpeed(u32),
+    SetScanChain(Vec<ScanChainElement>),
+    Attach,
+    SelectJtagTap(usize),
+    Detach,
+    TargetReset,
+    TargetResetAssert,
+    TargetResetDeassert,
+    SelectProtocol(WireProtocol),
+    TargetVoltage,
+    /// Executes the commands in order, until one of them fails.
+    Dap(Vec<DapCommand>),
+    /// Executes the commands in order, until one of them fails.
+    Jtag(Vec<JtagCommand>),
+}
+
+/// A response of the server.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) enum Response {
+    Opened(ProbeDescription),
+    Ok,
+    Speed(u32),
+    Protocol(Option<WireProtocol>),
+    Voltage(Option<f32>),
+    /// The values read by the executed commands, and the error of the command which failed.
+    Dap {
+        values: Vec<u32>,
+        error: Option<RemoteError>,
+    },
+    /// The replies of the executed commands, and the error of the command which failed.
+    Jtag {
+        replies: Vec<JtagReply>,
+        error: Option<RemoteError>,
+    },
+    Error(RemoteError),
+}
+
+/// The properties of the probe opened by the server.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct ProbeDescription {
+    pub name: String,
+    pub speed_khz: u32,
+    pub protocol: Option<WireProtocol>,
+    /// Whether the probe provides raw DAP access.
+    pub dap: bool,
+    pub use_overrun_detect: bool,
+    /// Whether the probe provides low-level JTAG access.
+    pub jtag: bool,
+    pub riscv: bool,
+    pub xtensa: bool,
+}
+
+/// An operation of [`RawDapAccess`](crate::architecture::arm::RawDapAccess).
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) enum DapCommand {
+    Read {
+        port: PortType,
+        address: u8,
+    },
+    ReadBlock {
+        port: PortType,
+        address: u8,
+        len: u32,
+    },
+    Write {
+        port: PortType,
+        address: u8,
+        value: u32,
+    },
+    WriteBlock {
+        port: PortType,
+        address: u8,
+        values: Vec<u32>,
+    },
+    Flush,
+    ConfigureJtag {
+        skip_scan: bool,
+    },
+    JtagSequence {
+        cycles: u8,
+        tms: bool,
+        tdi: u64,
+    },
+    SwjSequence {
+        bit_len: u8,
+        bits: u64,
+    },
+    SwjPins {
+        pin_out: u32,
+        pin_select: u32,
+        pin_wait: u32,
+    },
+    CoreStatus {
+        running: bool,
+    },
+}
+
+/// An operation of [`JTAGAccess`](crate::probe::JTAGAccess).
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) enum JtagCommand {
+    ScanChain,
+    TapReset,
+    SetIdleCycles(u8),
+    WriteRegister {
+        address: u32,
+        data: Vec<u8>,
+        len: u32,
+    },
+    WriteDr {
+        data: Vec<u8>,
+        len: u32,
+    },
+    RunTest {
+        state: JtagStableState,
+        cycles: u32,
+    },
+    ShiftRaw {
+        register: JtagRegister,
+        data: Vec<u8>,
+        len: u32,
+        end_state: JtagStableState,
+    },
+}
+
+/// The result of a [`JtagCommand`].
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) enum JtagReply {
+    Done,
+    ScanChain(Vec<ScanChainElement>),
+    Data(Vec<u8>),
+}
+
+/// An error which occurred on the server.
+///
+/// The errors which are handled by the callers of a probe keep their type, all others are
+/// transferred as text.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) enum RemoteError {
+    Dap(DapError),
+    Timeout,
+    Other(String),
+}
+
+impl RemoteError {
+    pub(super) fn other(error: &dyn std::error::Error) -> Self {
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(error) = source {
+            message.push_str(": ");
+            message.push_str(&error.to_string());
+            source = error.source();
+        }
+
+        RemoteError::Other(message)
+    }
+}
+
+impl From<DebugProbeError> for RemoteError {
+    fn from(error: DebugProbeError) -> Self {
+        match error {
+            DebugProbeError::Timeout => RemoteError::Timeout,
+            error => RemoteError::other(&error),
+        }
+    }
+}
+
+impl From<ArmError> for RemoteError {
+    fn from(error: ArmError) -> Self {
+        match error {
+            ArmError::Dap(error) => RemoteError::Dap(error),
+            ArmError::Timeout => RemoteError::Timeout,
+            ArmError::Probe(error) => error.into(),
+            error => RemoteError::other(&error),
+        }
+    }
+}
+
+impl From<crate::Error> for RemoteError {
+    fn from(error: crate::Error) -> Self {
+        match error {
+            crate::Error::Probe(error) => error.into(),
+            crate::Error::Arm(error) => error.into(),
+            crate::Error::Timeout => RemoteError::Timeout,
+            error => RemoteError::other(&error),
+        }
+    }
+}
+
+impl From<RemoteError> for DebugProbeError {
+    fn from(error: RemoteError) -> Self {
+        match error {
+            RemoteError::Dap(error) => RemoteProbeError::Server(error.to_string()).into(),
+            RemoteError::Timeout => DebugProbeError::Timeout,
+            RemoteError::Other(message) => RemoteProbeError::Server(message).into(),
+        }
+    }
+}
+
+impl From<RemoteError> for ArmError {
+    fn from(error: RemoteError) -> Self {
+        match error {
+            RemoteError::Dap(error) => ArmError::Dap(error),
+            RemoteError::Timeout => ArmError::Timeout,
+            RemoteError::Other(message) => {
+                ArmError::Probe(RemoteProbeError::Server(message).into())
+            }
+        }
+    }
+}
+
+/// Writes a length-prefixed message.
+pub(super) fn write_message(
+    stream: &mut impl Write,
+    message: &impl Serialize,
+) -> Result<(), RemoteProbeError> {
+    let data = bincode::serialize(message)?;
+    let len = u32::try_from(data.len())
+        .ok()
+        .filter(|&len| len <= MAX_MESSAGE_SIZE)
+        .ok_or(RemoteProbeError::MessageTooLarge)?;
+
+    let mut buffer = Vec::with_capacity(data.len() + 4);
+    buffer.extend_from_slice(&len.to_le_bytes());
+    buffer.extend_from_slice(&data);
+    stream.write_all(&buffer)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// Reads a length-prefixed message.
+pub(super) fn read_message<T: DeserializeOwned>(
+    stream: &mut impl Read,
+) -> Result<T, RemoteProbeError> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_MESSAGE_SIZE {
+        return Err(RemoteProbeError::MessageTooLarge);
+    }
+
+    let mut data = vec![0; len as usize];
+    stream.read_exact(&mut data)?;
+
+    Ok(bincode::deserialize(&data)?)
+}
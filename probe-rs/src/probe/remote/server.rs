@@ -0,0 +1,372 @@
+use std::{
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+};
+
+use super::{
+    protocol::{
+        read_message, write_message, DapCommand, JtagCommand, JtagReply, ProbeDescription,
+        RemoteError, Request, Response, MAX_READ_VALUES, PROTOCOL_VERSION,
+    },
+    RemoteProbeError,
+};
+use crate::{
+    architecture::arm::{communication_interface::DapProbe, ArmError},
+    probe::{
+        list::Lister, CommandResult, DebugProbe, DebugProbeSelector, JTAGAccess, JtagCommandQueue,
+        JtagWriteCommand, Probe, ShiftDrCommand,
+    },
+    CoreStatus,
+};
+
+/// Accepts clients on `listener`, and serves each of them on its own thread.
+///
+/// `lister` is called on the thread of each client, to create the [`Lister`] which opens the
+/// probe selected by the client.
+pub fn serve<F>(listener: TcpListener, lister: F) -> !
+where
+    F: Fn() -> Lister + Send + Sync + 'static,
+{
+    let lister = Arc::new(lister);
+
+    loop {
+        let (stream, peer) = match listener.accept() {
+            Ok(client) => client,
+            Err(error) => {
+                tracing::warn!("Failed to accept a client: {error}");
+                continue;
+            }
+        };
+
+        let lister = lister.clone();
+        std::thread::spawn(move || {
+            tracing::info!("{peer} connected");
+            match serve_connection(stream, &lister()) {
+                Ok(()) => tracing::info!("{peer} disconnected"),
+                Err(error) => tracing::warn!("Connection to {peer} failed: {error}"),
+            }
+        });
+    }
+}
+
+/// Serves the client connected with `stream`, until it disconnects.
+///
+/// The probe opened by the client is detached when the client disconnects.
+pub fn serve_connection(mut stream: TcpStream, lister: &Lister) -> Result<(), RemoteProbeError> {
+    stream.set_nodelay(true)?;
+
+    let mut probe: Option<Box<dyn DebugProbe>> = None;
+    let result = loop {
+        let request = match read_message(&mut stream) {
+            Ok(request) => request,
+            Err(RemoteProbeError::Io(error)) if error.kind() == ErrorKind::UnexpectedEof => {
+                break Ok(())
+            }
+            Err(error) => break Err(error),
+        };
+
+        let response = match &mut probe {
+            Some(probe) => handle_request(probe.as_mut(), request),
+            None => match open_probe(lister, request) {
+                Ok((opened, description)) => {
+                    probe = Some(opened);
+                    Response::Opened(description)
+                }
+                Err(error) => Response::Error(error),
+            },
+        };
+
+        if let Err(error) = write_message(&mut stream, &response) {
+            break Err(error);
+        }
+    };
+
+    if let Some(mut probe) = probe {
+        if let Err(error) = probe.detach() {
+            tracing::warn!("Failed to detach {}: {error}", probe.get_name());
+        }
+    }
+
+    result
+}
+
+fn open_probe(
+    lister: &Lister,
+    request: Request,
+) -> Result<(Box<dyn DebugProbe>, ProbeDescription), RemoteError> {
+    let Request::Open { version, selector } = request else {
+        return Err(RemoteError::Other("No probe has been opened".to_string()));
+    };
+    if version != PROTOCOL_VERSION {
+        return Err(RemoteError::Other(format!(
+            "The probe server uses version {PROTOCOL_VERSION} of the protocol, but the client uses version {version}"
+        )));
+    }
+
+    let selector = selector
+        .parse::<DebugProbeSelector>()
+        .map_err(|error| RemoteError::other(&error))?;
+    let Probe {
+        inner: mut probe, ..
+    } = lister.open(selector)?;
+    tracing::info!("Opened {}", probe.get_name());
+
+    let dap = probe.try_as_dap_probe().map(|dap| dap.use_overrun_detect());
+    let description = ProbeDescription {
+        name: probe.get_name().to_string(),
+        speed_khz: probe.speed_khz(),
+        protocol: probe.active_protocol(),
+        dap: dap.is_some(),
+        use_overrun_detect: dap.unwrap_or_default(),
+        jtag: probe.try_as_jtag_probe().is_some(),
+        riscv: probe.has_riscv_interface(),
+        xtensa: probe.has_xtensa_interface(),
+    };
+
+    Ok((probe, description))
+}
+
+fn handle_request(probe: &mut dyn DebugProbe, request: Request) -> Response {
+    let result = match request {
+        Request::Open { .. } => Err(RemoteError::Other(
+            "A probe has already been opened".to_string(),
+        )),
+        Request::SetSpeed(speed_khz) => probe
+            .set_speed(speed_khz)
+            .map(Response::Speed)
+            .map_err(RemoteError::from),
+        Request::SetScanChain(scan_chain) => probe
+            .set_scan_chain(scan_chain)
+            .map(|()| Response::Ok)
+            .map_err(RemoteError::from),
+        Request::Attach => probe
+            .attach()
+            .map(|()| Response::Protocol(probe.active_protocol()))
+            .map_err(RemoteError::from),
+        Request::SelectJtagTap(index) => probe
+            .select_jtag_tap(index)
+            .map(|()| Response::Ok)
+            .map_err(RemoteError::from),
+        Request::Detach => probe
+            .detach()
+            .map(|()| Response::Ok)
+            .map_err(RemoteError::from),
+        Request::TargetReset => probe
+            .target_reset()
+            .map(|()| Response::Ok)
+            .map_err(RemoteError::from),
+        Request::TargetResetAssert => probe
+            .target_reset_assert()
+            .map(|()| Response::Ok)
+            .map_err(RemoteError::from),
+        Request::TargetResetDeassert => probe
+            .target_reset_deassert()
+            .map(|()| Response::Ok)
+            .map_err(RemoteError::from),
+        Request::SelectProtocol(protocol) => probe
+            .select_protocol(protocol)
+            .map(|()| Response::Protocol(probe.active_protocol()))
+            .map_err(RemoteError::from),
+        Request::TargetVoltage => probe
+            .get_target_voltage()
+            .map(Response::Voltage)
+            .map_err(RemoteError::from),
+        Request::Dap(commands) => match probe.try_as_dap_probe() {
+            Some(dap) => Ok(execute_dap(dap, commands)),
+            None => Err(RemoteError::Other(
+                "The probe does not provide DAP access".to_string(),
+            )),
+        },
+        Request::Jtag(commands) => match probe.try_as_jtag_probe() {
+            Some(jtag) => Ok(execute_jtag(jtag, commands)),
+            None => Err(RemoteError::Other(
+                "The probe does not provide JTAG access".to_string(),
+            )),
+        },
+    };
+
+    result.unwrap_or_else(Response::Error)
+}
+
+fn execute_dap(probe: &mut dyn DapProbe, commands: Vec<DapCommand>) -> Response {
+    let mut values = vec![];
+
+    for command in commands {
+        // The number of values is chosen by the client, so it is limited before allocating them.
+        let read_len = match &command {
+            DapCommand::Read { .. } | DapCommand::SwjPins { .. } => 1,
+            DapCommand::ReadBlock { len, .. } => *len as usize,
+            _ => 0,
+        };
+        if read_len > MAX_READ_VALUES - values.len() {
+            return Response::Dap {
+                values,
+                error: Some(RemoteError::Other(format!(
+                    "Reading {read_len} more values exceeds the limit of {MAX_READ_VALUES} values per request"
+                ))),
+            };
+        }
+
+        let result = match command {
+            DapCommand::Read { port, address } => probe
+                .raw_read_register(port, address)
+                .map(|value| values.push(value)),
+            DapCommand::ReadBlock { port, address, len } => {
+                let start = values.len();
+                values.resize(start + len as usize, 0);
+                probe.raw_read_block(port, address, &mut values[start..])
+            }
+            DapCommand::Write {
+                port,
+                address,
+                value,
+            } => probe.raw_write_register(port, address, value),
+            DapCommand::WriteBlock {
+                port,
+                address,
+                values,
+            } => probe.raw_write_block(port, address, &values),
+            DapCommand::Flush => probe.raw_flush(),
+            DapCommand::ConfigureJtag { skip_scan } => {
+                probe.configure_jtag(skip_scan).map_err(ArmError::from)
+            }
+            DapCommand::JtagSequence { cycles, tms, tdi } => probe
+                .jtag_sequence(cycles, tms, tdi)
+                .map_err(ArmError::from),
+            DapCommand::SwjSequence { bit_len, bits } => {
+                probe.swj_sequence(bit_len, bits).map_err(ArmError::from)
+            }
+            DapCommand::SwjPins {
+                pin_out,
+                pin_select,
+                pin_wait,
+            } => probe
+                .swj_pins(pin_out, pin_select, pin_wait)
+                .map(|value| values.push(value))
+                .map_err(ArmError::from),
+            DapCommand::CoreStatus { running } => probe
+                .core_status_notification(if running {
+                    CoreStatus::Running
+                } else {
+                    CoreStatus::Unknown
+                })
+                .map_err(ArmError::from),
+        };
+
+        if let Err(error) = result {
+            return Response::Dap {
+                values,
+                error: Some(error.into()),
+            };
+        }
+    }
+
+    Response::Dap {
+        values,
+        error: None,
+    }
+}
+
+fn execute_jtag(probe: &mut dyn JTAGAccess, commands: Vec<JtagCommand>) -> Response {
+    let mut replies = vec![];
+    let mut commands = commands.into_iter().peekable();
+
+    while let Some(command) = commands.next() {
+        let result = match command {
+            JtagCommand::ScanChain => JTAGAccess::scan_chain(probe)
+                .and_then(|()| {
+                    Ok(JtagReply::ScanChain(
+                        DebugProbe::scan_chain(probe)?.to_vec(),
+                    ))
+                })
+                .map_err(RemoteError::from),
+            JtagCommand::TapReset => probe
+                .tap_reset()
+                .map(|()| JtagReply::Done)
+                .map_err(RemoteError::from),
+            JtagCommand::SetIdleCycles(idle_cycles) => {
+                probe.set_idle_cycles(idle_cycles);
+                Ok(JtagReply::Done)
+            }
+            JtagCommand::RunTest { state, cycles } => probe
+                .run_test(state, cycles)
+                .map(|()| JtagReply::Done)
+                .map_err(RemoteError::from),
+            JtagCommand::ShiftRaw {
+                register,
+                data,
+                len,
+                end_state,
+            } => probe
+                .shift_raw(register, &data, len, end_state)
+                .map(JtagReply::Data)
+                .map_err(RemoteError::from),
+            command => {
+                // Consecutive register accesses are executed as one batch, which the probe can
+                // combine into fewer transfers.
+                let mut queue = JtagCommandQueue::new();
+                let mut indices = vec![queue.schedule(queued_command(command))];
+                while let Some(command) = commands.next_if(|command| {
+                    matches!(
+                        command,
+                        JtagCommand::WriteRegister { .. } | JtagCommand::WriteDr { .. }
+                    )
+                }) {
+                    indices.push(queue.schedule(queued_command(command)));
+                }
+
+                let (mut results, error) = match probe.write_register_batch(&queue) {
+                    Ok(results) => (results, None),
+                    Err(error) => (error.results, Some(error.error)),
+                };
+                for index in indices {
+                    match results.take(index) {
+                        Ok(CommandResult::VecU8(data)) => replies.push(JtagReply::Data(data)),
+                        _ => break,
+                    }
+                }
+
+                match error {
+                    Some(error) => Err(RemoteError::from(error)),
+                    None => continue,
+                }
+            }
+        };
+
+        match result {
+            Ok(reply) => replies.push(reply),
+            Err(error) => {
+                return Response::Jtag {
+                    replies,
+                    error: Some(error),
+                }
+            }
+        }
+    }
+
+    Response::Jtag {
+        replies,
+        error: None,
+    }
+}
+
+/// Converts a register access into a command which returns the captured data.
+fn queued_command(command: JtagCommand) -> crate::probe::JtagCommand {
+    match command {
+        JtagCommand::WriteDr { data, len } => ShiftDrCommand {
+            data,
+            len,
+            transform: |_, data| Ok(CommandResult::VecU8(data)),
+        }
+        .into(),
+        JtagCommand::WriteRegister { address, data, len } => JtagWriteCommand {
+            address,
+            data,
+            len,
+            transform: |_, data| Ok(CommandResult::VecU8(data)),
+        }
+        .into(),
+        _ => unreachable!("Only register accesses are batched"),
+    }
+}
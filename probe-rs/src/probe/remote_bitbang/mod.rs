@@ -0,0 +1,448 @@
+//! Probe driver for OpenOCD's `remote_bitbang` protocol.
+//!
+//! Simulators like Verilator or Spike can expose the JTAG port of a simulated chip over TCP using
+//! this protocol. Each JTAG clock is sent as ASCII characters which set the TCK, TMS and TDI
+//! lines, and TDO is sampled with an explicit read command.
+//!
+//! As the server can't be discovered, the probe is selected with the address of the server and
+//! the pseudo VID:PID `0000:0001`, as in `tcp://localhost:44853/0000:0001`.
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use bitvec::prelude::*;
+use probe_rs_target::ScanChainElement;
+
+use crate::{
+    architecture::{
+        arm::{
+            communication_interface::{DapProbe, UninitializedArmProbe},
+            SwoAccess,
+        },
+        riscv::{communication_interface::RiscvInterfaceBuilder, dtm::jtag_dtm::JtagDtmBuilder},
+        xtensa::communication_interface::{
+            XtensaCommunicationInterface, XtensaDebugInterfaceState,
+        },
+    },
+    probe::{
+        common::{JtagDriverState, RawJtagIo},
+        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeSelector, JTAGAccess,
+        ProbeCreationError, ProbeError, ProbeFactory, WireProtocol,
+    },
+};
+
+/// The pseudo USB vendor ID used to select a `remote_bitbang` server.
+pub const REMOTE_BITBANG_VID: u16 = 0x0000;
+
+/// The pseudo USB product ID used to select a `remote_bitbang` server.
+pub const REMOTE_BITBANG_PID: u16 = 0x0001;
+
+/// The number of buffered command bytes after which they are sent to the server.
+///
+/// This is kept small enough that the responses to the buffered reads always fit into the
+/// socket buffers, so the server never blocks while we are still sending.
+const MAX_BUFFERED_COMMANDS: usize = 4096;
+
+/// Command which samples TDO. The server responds with `'0'` or `'1'`.
+const READ_TDO: u8 = b'R';
+
+/// Command which releases the system reset (and TRST).
+const RESET_DEASSERT: u8 = b'r';
+
+/// Command which asserts the system reset.
+const RESET_ASSERT: u8 = b's';
+
+/// An error in the communication with a `remote_bitbang` server.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum RemoteBitbangError {
+    /// Failed to communicate with the server.
+    Io(#[from] std::io::Error),
+
+    /// The server sent the invalid TDO value {0:#04x}.
+    InvalidTdo(u8),
+}
+
+impl ProbeError for RemoteBitbangError {}
+
+/// Factory for probes which connect to a `remote_bitbang` server.
+#[derive(Debug)]
+pub struct RemoteBitbangFactory;
+
+impl std::fmt::Display for RemoteBitbangFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("remote_bitbang")
+    }
+}
+
+impl ProbeFactory for RemoteBitbangFactory {
+    fn open(&self, selector: &DebugProbeSelector) -> Result<Box<dyn DebugProbe>, DebugProbeError> {
+        if selector.vendor_id != REMOTE_BITBANG_VID || selector.product_id != REMOTE_BITBANG_PID {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound,
+            ));
+        }
+
+        let Some(address) = selector.remote.as_deref() else {
+            return Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::Other(
+                    "The address of the remote_bitbang server has to be given, as in `tcp://HOST:PORT/0000:0001`",
+                ),
+            ));
+        };
+
+        Ok(Box::new(RemoteBitbang::connect(address)?))
+    }
+
+    fn list_probes(&self) -> Vec<DebugProbeInfo> {
+        // Servers can't be discovered.
+        vec![]
+    }
+}
+
+/// A JTAG probe which is connected to a `remote_bitbang` server.
+#[derive(Debug)]
+pub struct RemoteBitbang {
+    stream: TcpStream,
+
+    /// Command bytes which haven't been sent yet.
+    commands: Vec<u8>,
+    /// The number of TDO reads in `commands`.
+    pending_reads: usize,
+    captured: BitVec<u8, Lsb0>,
+
+    speed_khz: u32,
+    jtag_state: JtagDriverState,
+}
+
+impl RemoteBitbang {
+    /// Connects to the `remote_bitbang` server at `address`.
+    pub fn connect(address: &str) -> Result<Self, RemoteBitbangError> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            stream,
+            commands: Vec::with_capacity(MAX_BUFFERED_COMMANDS),
+            pending_reads: 0,
+            captured: BitVec::new(),
+            speed_khz: 1000,
+            jtag_state: JtagDriverState::default(),
+        })
+    }
+
+    /// Sends the buffered commands, and collects the TDO values the server responds with.
+    fn flush(&mut self) -> Result<(), RemoteBitbangError> {
+        if self.commands.is_empty() {
+            return Ok(());
+        }
+
+        self.stream.write_all(&self.commands)?;
+        self.commands.clear();
+
+        let mut responses = vec![0; self.pending_reads];
+        self.stream.read_exact(&mut responses)?;
+        self.pending_reads = 0;
+
+        for response in responses {
+            let tdo = match response {
+                b'0' => false,
+                b'1' => true,
+                other => return Err(RemoteBitbangError::InvalidTdo(other)),
+            };
+            self.captured.push(tdo);
+        }
+
+        Ok(())
+    }
+
+    fn send_command(&mut self, command: u8) -> Result<(), RemoteBitbangError> {
+        self.commands.push(command);
+        self.flush()
+    }
+}
+
+/// Returns the command which sets TCK, TMS and TDI to the given values.
+fn write_command(tck: bool, tms: bool, tdi: bool) -> u8 {
+    b'0' + ((tck as u8) << 2 | (tms as u8) << 1 | tdi as u8)
+}
+
+impl RawJtagIo for RemoteBitbang {
+    fn shift_bit(&mut self, tms: bool, tdi: bool, capture: bool) -> Result<(), DebugProbeError> {
+        self.jtag_state.state.update(tms);
+
+        // TDO is sampled while TCK is low, and the TAP advances on the rising edge.
+        self.commands.push(write_command(false, tms, tdi));
+        if capture {
+            self.commands.push(READ_TDO);
+            self.pending_reads += 1;
+        }
+        self.commands.push(write_command(true, tms, tdi));
+
+        if self.commands.len() >= MAX_BUFFERED_COMMANDS {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn read_captured_bits(&mut self) -> Result<BitVec<u8, Lsb0>, DebugProbeError> {
+        self.flush()?;
+
+        Ok(std::mem::take(&mut self.captured))
+    }
+
+    fn state_mut(&mut self) -> &mut JtagDriverState {
+        &mut self.jtag_state
+    }
+
+    fn state(&self) -> &JtagDriverState {
+        &self.jtag_state
+    }
+}
+
+impl DebugProbe for RemoteBitbang {
+    fn get_name(&self) -> &str {
+        "remote_bitbang"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        // The speed is determined by the server, so there is nothing to configure.
+        self.speed_khz = speed_khz;
+        Ok(speed_khz)
+    }
+
+    fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
+        tracing::info!("Setting scan chain to {:?}", scan_chain);
+        self.jtag_state.expected_scan_chain = Some(scan_chain);
+        Ok(())
+    }
+
+    fn scan_chain(&self) -> Result<&[ScanChainElement], DebugProbeError> {
+        if let Some(ref scan_chain) = self.jtag_state.expected_scan_chain {
+            Ok(scan_chain)
+        } else {
+            Ok(&[])
+        }
+    }
+
+    fn select_jtag_tap(&mut self, index: usize) -> Result<(), DebugProbeError> {
+        self.select_target(index)
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        tracing::debug!("Attaching to remote_bitbang server");
+
+        JTAGAccess::scan_chain(self)?;
+        self.select_target(0)
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        self.flush().map_err(DebugProbeError::from)?;
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.target_reset_assert()?;
+        self.target_reset_deassert()
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.send_command(RESET_ASSERT)?;
+        Ok(())
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.send_command(RESET_DEASSERT)?;
+        Ok(())
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        if matches!(protocol, WireProtocol::Jtag) {
+            Ok(())
+        } else {
+            Err(DebugProbeError::UnsupportedProtocol(protocol))
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        Some(WireProtocol::Jtag)
+    }
+
+    fn try_get_riscv_interface_builder<'probe>(
+        &'probe mut self,
+    ) -> Result<Box<dyn RiscvInterfaceBuilder<'probe> + 'probe>, DebugProbeError> {
+        Ok(Box::new(JtagDtmBuilder::new(self)))
+    }
+
+    fn has_riscv_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_xtensa_interface<'probe>(
+        &'probe mut self,
+        state: &'probe mut XtensaDebugInterfaceState,
+    ) -> Result<XtensaCommunicationInterface<'probe>, DebugProbeError> {
+        Ok(XtensaCommunicationInterface::new(self, state))
+    }
+
+    fn has_xtensa_interface(&self) -> bool {
+        true
+    }
+
+    fn try_get_arm_interface<'probe>(
+        self: Box<Self>,
+    ) -> Result<Box<dyn UninitializedArmProbe + 'probe>, (Box<dyn DebugProbe>, DebugProbeError)>
+    {
+        // JTAG-DP is not implemented on top of raw JTAG access.
+        Err((
+            self,
+            DebugProbeError::InterfaceNotAvailable {
+                interface_name: "SWD/ARM",
+            },
+        ))
+    }
+
+    fn has_arm_interface(&self) -> bool {
+        false
+    }
+
+    fn get_swo_interface(&self) -> Option<&dyn SwoAccess> {
+        None
+    }
+
+    fn get_swo_interface_mut(&mut self) -> Option<&mut dyn SwoAccess> {
+        None
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
+    fn try_as_dap_probe(&mut self) -> Option<&mut dyn DapProbe> {
+        None
+    }
+
+    fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::probe::{
+        common::tap_model::{TapModel, IDCODE_INSTRUCTION},
+        list::Lister,
+    };
+
+    const IDCODE: u32 = 0x1000_0a6d;
+
+    /// Serves the `remote_bitbang` protocol for a [`TapModel`], and records the reset commands.
+    fn serve(mut stream: TcpStream, resets: Arc<Mutex<Vec<u8>>>) {
+        let mut tap = TapModel::new(IDCODE);
+        let mut tck = false;
+
+        let mut command = [0];
+        while stream.read_exact(&mut command).is_ok() {
+            match command[0] {
+                c @ b'0'..=b'7' => {
+                    let bits = c - b'0';
+                    if !tck && bits & 4 != 0 {
+                        tap.clock(bits & 2 != 0, bits & 1 != 0);
+                    }
+                    tck = bits & 4 != 0;
+                }
+                b'R' => {
+                    let tdo = if tap.tdo() { b'1' } else { b'0' };
+                    stream.write_all(&[tdo]).unwrap();
+                }
+                c @ b'r'..=b'u' => resets.lock().unwrap().push(c),
+                _ => panic!("Unexpected command {:#04x}", command[0]),
+            }
+        }
+    }
+
+    /// Starts a server for a single connection, and returns its address.
+    fn start_server() -> (String, Arc<Mutex<Vec<u8>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let resets = Arc::new(Mutex::new(Vec::new()));
+
+        let server_resets = resets.clone();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream, server_resets);
+        });
+
+        (address, resets)
+    }
+
+    fn connect() -> (RemoteBitbang, Arc<Mutex<Vec<u8>>>) {
+        let (address, resets) = start_server();
+
+        (RemoteBitbang::connect(&address).unwrap(), resets)
+    }
+
+    #[test]
+    fn open_through_selector() {
+        let (address, _) = start_server();
+
+        let selector = format!("tcp://{address}/0000:0001")
+            .parse::<DebugProbeSelector>()
+            .unwrap();
+        let probe = Lister::new().open(selector).unwrap();
+        assert_eq!(probe.get_name(), "remote_bitbang");
+
+        let selector = "0000:0001".parse::<DebugProbeSelector>().unwrap();
+        assert!(Lister::new().open(selector).is_err());
+    }
+
+    #[test]
+    fn scan_chain_is_detected() {
+        let (mut probe, _) = connect();
+
+        JTAGAccess::scan_chain(&mut probe).unwrap();
+
+        let chain = &probe.state().scan_chain;
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].idcode.map(|idcode| idcode.0), Some(IDCODE));
+        assert_eq!(chain[0].irlen, 5);
+    }
+
+    #[test]
+    fn registers_can_be_read() {
+        let (mut probe, _) = connect();
+        probe.attach().unwrap();
+
+        let idcode = probe.read_register(IDCODE_INSTRUCTION, 32).unwrap();
+        assert_eq!(idcode, IDCODE.to_le_bytes());
+    }
+
+    #[test]
+    fn reset_is_forwarded() {
+        let (mut probe, resets) = connect();
+
+        probe.target_reset().unwrap();
+        // Wait for the server to process the commands.
+        probe.shift_bit(false, false, true).unwrap();
+        probe.read_captured_bits().unwrap();
+
+        assert_eq!(*resets.lock().unwrap(), b"sr");
+    }
+}
@@ -0,0 +1,827 @@
+//! Playback of SVF and XSVF files.
+//!
+//! SVF (Serial Vector Format) and its binary variant XSVF describe a sequence of JTAG operations,
+//! and are commonly used to program CPLDs and FPGAs. The operations are played on the whole scan
+//! chain, so a file can target any device on the chain, as long as it accounts for the other
+//! devices with header and trailer patterns.
+//!
+//! ```no_run
+//! use probe_rs::probe::{list::Lister, svf::Program, WireProtocol};
+//!
+//! let program = Program::parse_svf(&std::fs::read_to_string("cpld.svf")?)?;
+//!
+//! let mut probe = Lister::new().list_all()[0].open()?;
+//! probe.select_protocol(WireProtocol::Jtag)?;
+//! probe.attach_to_unspecified()?;
+//!
+//! let jtag = probe.try_as_jtag_probe().expect("JTAG is not supported");
+//! program.play(jtag)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod xsvf;
+
+use std::time::{Duration, Instant};
+
+use bitvec::prelude::*;
+
+use crate::probe::{DebugProbeError, JTAGAccess, JtagRegister, JtagStableState};
+
+/// An error which occurred while parsing or playing an SVF or XSVF file.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum SvfError {
+    /// An error occurred while accessing the JTAG interface.
+    Probe(#[from] DebugProbeError),
+
+    /// Failed to parse {position}: {message}
+    Parse {
+        /// Where the error occurred.
+        position: Position,
+        /// A description of the error.
+        message: String,
+    },
+
+    /// The {command} command at {position} is not supported.
+    Unsupported {
+        /// Where the command is located.
+        position: Position,
+        /// The name of the command.
+        command: String,
+    },
+
+    /// The TDO data at {position} does not match. Expected {expected} with mask {mask}, but captured {captured}.
+    TdoMismatch {
+        /// Where the scan is located.
+        position: Position,
+        /// The expected TDO data, as a hex string.
+        expected: String,
+        /// The mask of the TDO bits which are checked, as a hex string.
+        mask: String,
+        /// The captured TDO data, as a hex string.
+        captured: String,
+    },
+}
+
+/// The position of a command in an SVF or XSVF file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, docsplay::Display)]
+pub enum Position {
+    /// line {0}
+    Line(usize),
+    /// offset {0:#x}
+    Offset(usize),
+}
+
+/// A sequence of JTAG operations, parsed from an SVF or XSVF file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    position: Position,
+    command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    /// Shifts data through a register, and checks the captured data.
+    Shift(Shift),
+
+    /// Clocks TCK in a stable state, and waits for at least `min_time`.
+    RunTest {
+        state: JtagStableState,
+        cycles: u32,
+        min_time: Duration,
+        end_state: JtagStableState,
+    },
+
+    /// Moves to a stable state.
+    State(JtagStableState),
+
+    /// Sets the TCK frequency in kHz.
+    Frequency(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Shift {
+    register: JtagRegister,
+    tdi: BitVec<u8, Lsb0>,
+    /// The expected TDO data, and the mask of the bits which are checked.
+    tdo: Option<(BitVec<u8, Lsb0>, BitVec<u8, Lsb0>)>,
+    end_state: JtagStableState,
+
+    /// How often the scan is repeated if the TDO data does not match.
+    retries: u8,
+    /// The time in µs to wait in Run-Test/Idle before a retry.
+    retry_wait: u32,
+}
+
+impl Program {
+    /// Parses an SVF file.
+    pub fn parse_svf(source: &str) -> Result<Self, SvfError> {
+        SvfParser::default().parse(source)
+    }
+
+    /// Parses an XSVF file.
+    pub fn parse_xsvf(data: &[u8]) -> Result<Self, SvfError> {
+        xsvf::parse(data)
+    }
+
+    /// Plays the program on the scan chain of `jtag`.
+    ///
+    /// The TAP controllers are reset before the first operation.
+    pub fn play(&self, jtag: &mut dyn JTAGAccess) -> Result<(), SvfError> {
+        jtag.tap_reset()?;
+
+        for step in &self.steps {
+            step.execute(jtag)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Step {
+    fn execute(&self, jtag: &mut dyn JTAGAccess) -> Result<(), SvfError> {
+        match &self.command {
+            Command::Shift(shift) => {
+                let len = shift.tdi.len();
+                let mut attempt = 0;
+
+                loop {
+                    let tdo = jtag.shift_raw(
+                        shift.register,
+                        shift.tdi.as_raw_slice(),
+                        len as u32,
+                        shift.end_state,
+                    )?;
+
+                    let Some((expected, mask)) = &shift.tdo else {
+                        break;
+                    };
+
+                    let mut captured = BitVec::<u8, Lsb0>::from_vec(tdo);
+                    captured.resize(len, false);
+
+                    let mismatch = (captured.clone() ^ expected) & mask;
+                    if mismatch.not_any() {
+                        break;
+                    }
+
+                    if attempt == shift.retries {
+                        return Err(SvfError::TdoMismatch {
+                            position: self.position,
+                            expected: to_hex(expected),
+                            mask: to_hex(mask),
+                            captured: to_hex(&captured),
+                        });
+                    }
+
+                    attempt += 1;
+                    tracing::debug!("TDO mismatch at {}, retry {attempt}", self.position);
+                    run_test(
+                        jtag,
+                        JtagStableState::Idle,
+                        shift.retry_wait,
+                        Duration::from_micros(shift.retry_wait.into()),
+                    )?;
+                }
+            }
+            Command::RunTest {
+                state,
+                cycles,
+                min_time,
+                end_state,
+            } => {
+                run_test(jtag, *state, *cycles, *min_time)?;
+                jtag.run_test(*end_state, 0)?;
+            }
+            Command::State(state) => {
+                // Clocking TMS high five times reaches Test-Logic-Reset from any state, even if
+                // the state of the TAP controllers is not known.
+                let cycles = if *state == JtagStableState::Reset {
+                    5
+                } else {
+                    0
+                };
+                jtag.run_test(*state, cycles)?;
+            }
+            Command::Frequency(speed_khz) => {
+                let actual = jtag.set_speed(*speed_khz)?;
+                tracing::debug!("Set TCK frequency to {actual} kHz");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn run_test(
+    jtag: &mut dyn JTAGAccess,
+    state: JtagStableState,
+    cycles: u32,
+    min_time: Duration,
+) -> Result<(), DebugProbeError> {
+    let start = Instant::now();
+    jtag.run_test(state, cycles)?;
+
+    if let Some(remaining) = min_time.checked_sub(start.elapsed()) {
+        std::thread::sleep(remaining);
+    }
+
+    Ok(())
+}
+
+/// Formats bits as a hex string, with the last bit first.
+fn to_hex(bits: &BitSlice<u8, Lsb0>) -> String {
+    if bits.is_empty() {
+        return "0".to_string();
+    }
+
+    bits.chunks(4)
+        .rev()
+        .map(|nibble| {
+            let value = nibble.load_le::<u8>();
+            char::from_digit(value.into(), 16).unwrap_or('?')
+        })
+        .collect()
+}
+
+/// The data of a scan, or of its header or trailer pattern.
+///
+/// The data is kept between commands, if the length does not change.
+#[derive(Debug, Default)]
+struct ScanData {
+    tdi: BitVec<u8, Lsb0>,
+    mask: BitVec<u8, Lsb0>,
+}
+
+#[derive(Debug)]
+struct SvfParser {
+    steps: Vec<Step>,
+
+    header_ir: ScanData,
+    trailer_ir: ScanData,
+    header_dr: ScanData,
+    trailer_dr: ScanData,
+    ir: ScanData,
+    dr: ScanData,
+
+    end_ir: JtagStableState,
+    end_dr: JtagStableState,
+    run_state: JtagStableState,
+    run_end_state: JtagStableState,
+}
+
+impl Default for SvfParser {
+    fn default() -> Self {
+        Self {
+            steps: vec![],
+            header_ir: ScanData::default(),
+            trailer_ir: ScanData::default(),
+            header_dr: ScanData::default(),
+            trailer_dr: ScanData::default(),
+            ir: ScanData::default(),
+            dr: ScanData::default(),
+            end_ir: JtagStableState::Idle,
+            end_dr: JtagStableState::Idle,
+            run_state: JtagStableState::Idle,
+            run_end_state: JtagStableState::Idle,
+        }
+    }
+}
+
+enum StatementError {
+    Invalid(String),
+    Unsupported(String),
+}
+
+impl From<String> for StatementError {
+    fn from(message: String) -> Self {
+        Self::Invalid(message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// The content of parentheses, without whitespace.
+    Group(String),
+}
+
+impl SvfParser {
+    fn parse(mut self, source: &str) -> Result<Program, SvfError> {
+        for (line, tokens) in statements(source)? {
+            let position = Position::Line(line);
+            match self.statement(&tokens) {
+                Ok(Some(command)) => self.steps.push(Step { position, command }),
+                Ok(None) => {}
+                Err(StatementError::Invalid(message)) => {
+                    return Err(SvfError::Parse { position, message })
+                }
+                Err(StatementError::Unsupported(command)) => {
+                    return Err(SvfError::Unsupported { position, command })
+                }
+            }
+        }
+
+        Ok(Program { steps: self.steps })
+    }
+
+    /// Parses a statement into a command, or returns `None` for statements which only change
+    /// the state of the parser.
+    fn statement(&mut self, tokens: &[Token]) -> Result<Option<Command>, StatementError> {
+        let Some((Token::Word(name), args)) = tokens.split_first() else {
+            return Err("Expected a command".to_string().into());
+        };
+        let name = name.to_ascii_uppercase();
+
+        let command = match name.as_str() {
+            "ENDIR" => {
+                self.end_ir = parse_stable_state(single_word(args)?)?;
+                None
+            }
+            "ENDDR" => {
+                self.end_dr = parse_stable_state(single_word(args)?)?;
+                None
+            }
+            "FREQUENCY" => match args {
+                [] => None,
+                [Token::Word(value), Token::Word(unit)] if unit.eq_ignore_ascii_case("HZ") => {
+                    let hz = parse_number(value)?;
+                    Some(Command::Frequency(((hz / 1000.0) as u32).max(1)))
+                }
+                _ => return Err("Expected a frequency in HZ".to_string().into()),
+            },
+            "HIR" => {
+                scan(&mut self.header_ir, args, false)?;
+                None
+            }
+            "TIR" => {
+                scan(&mut self.trailer_ir, args, false)?;
+                None
+            }
+            "HDR" => {
+                scan(&mut self.header_dr, args, false)?;
+                None
+            }
+            "TDR" => {
+                scan(&mut self.trailer_dr, args, false)?;
+                None
+            }
+            "SIR" => {
+                let tdo = scan(&mut self.ir, args, true)?;
+                Some(Command::Shift(compose(
+                    JtagRegister::Ir,
+                    &self.header_ir,
+                    &self.ir,
+                    &self.trailer_ir,
+                    tdo,
+                    self.end_ir,
+                )))
+            }
+            "SDR" => {
+                let tdo = scan(&mut self.dr, args, true)?;
+                Some(Command::Shift(compose(
+                    JtagRegister::Dr,
+                    &self.header_dr,
+                    &self.dr,
+                    &self.trailer_dr,
+                    tdo,
+                    self.end_dr,
+                )))
+            }
+            "RUNTEST" => Some(self.run_test(args)?),
+            "STATE" => {
+                let Some((Token::Word(last), path)) = args.split_last() else {
+                    return Err("Expected a state".to_string().into());
+                };
+                for state in path {
+                    match state {
+                        Token::Word(state) if is_state(state) => {}
+                        _ => return Err(format!("Invalid state {state:?}").into()),
+                    }
+                }
+                Some(Command::State(parse_stable_state(last)?))
+            }
+            "TRST" => {
+                tracing::debug!("Ignoring TRST, the test reset signal is not supported");
+                None
+            }
+            "PIO" | "PIOMAP" => return Err(StatementError::Unsupported(name)),
+            _ => return Err(format!("Unknown command {name}").into()),
+        };
+
+        Ok(command)
+    }
+
+    fn run_test(&mut self, mut args: &[Token]) -> Result<Command, String> {
+        let mut end_state = None;
+        if let [rest @ .., Token::Word(keyword), Token::Word(state)] = args {
+            if keyword.eq_ignore_ascii_case("ENDSTATE") {
+                end_state = Some(parse_stable_state(state)?);
+                args = rest;
+            }
+        }
+
+        if let [Token::Word(state), rest @ ..] = args {
+            if is_state(state) {
+                self.run_state = parse_stable_state(state)?;
+                self.run_end_state = self.run_state;
+                args = rest;
+            }
+        }
+        if let Some(end_state) = end_state {
+            self.run_end_state = end_state;
+        }
+
+        // The maximum time is only a limit for the player, and can be ignored.
+        if let [rest @ .., Token::Word(keyword), Token::Word(_), Token::Word(unit)] = args {
+            if keyword.eq_ignore_ascii_case("MAXIMUM") && unit.eq_ignore_ascii_case("SEC") {
+                args = rest;
+            }
+        }
+
+        let mut cycles = 0;
+        let mut min_time = 0.0;
+        match args {
+            [Token::Word(count), Token::Word(clock), rest @ ..]
+                if clock.eq_ignore_ascii_case("TCK") || clock.eq_ignore_ascii_case("SCK") =>
+            {
+                if clock.eq_ignore_ascii_case("TCK") {
+                    cycles = parse_number(count)? as u32;
+                }
+                match rest {
+                    [] => {}
+                    [Token::Word(time), Token::Word(unit)] if unit.eq_ignore_ascii_case("SEC") => {
+                        min_time = parse_number(time)?;
+                    }
+                    _ => return Err("Expected a time in SEC".to_string()),
+                }
+            }
+            [Token::Word(time), Token::Word(unit)] if unit.eq_ignore_ascii_case("SEC") => {
+                min_time = parse_number(time)?;
+            }
+            _ => return Err("Expected a number of clock cycles, or a time".to_string()),
+        }
+
+        Ok(Command::RunTest {
+            state: self.run_state,
+            cycles,
+            min_time: Duration::from_secs_f64(min_time),
+            end_state: self.run_end_state,
+        })
+    }
+}
+
+/// Splits an SVF file into statements, with the line each statement starts at.
+fn statements(source: &str) -> Result<Vec<(usize, Vec<Token>)>, SvfError> {
+    let mut statements = vec![];
+    let mut tokens = vec![];
+    let mut start = 1;
+    let mut group: Option<String> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('!').next().unwrap_or_default();
+        let line = line.split("//").next().unwrap_or_default();
+
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if let Some(content) = &mut group {
+                match c {
+                    ')' => {
+                        tokens.push(Token::Group(std::mem::take(content)));
+                        group = None;
+                    }
+                    c if c.is_whitespace() => {}
+                    c => content.push(c),
+                }
+                continue;
+            }
+
+            match c {
+                c if c.is_whitespace() => {}
+                '(' => group = Some(String::new()),
+                ';' => {
+                    if !tokens.is_empty() {
+                        statements.push((start, std::mem::take(&mut tokens)));
+                    }
+                }
+                c => {
+                    if tokens.is_empty() {
+                        start = line_number;
+                    }
+                    let mut word = String::from(c);
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || c == ';' || c == '(' {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token::Word(word));
+                }
+            }
+        }
+    }
+
+    if group.is_some() || !tokens.is_empty() {
+        return Err(SvfError::Parse {
+            position: Position::Line(start),
+            message: "The statement is not terminated".to_string(),
+        });
+    }
+
+    Ok(statements)
+}
+
+/// Parses the arguments of a scan command into `data`, and returns the expected TDO data.
+fn scan(
+    data: &mut ScanData,
+    args: &[Token],
+    requires_tdi: bool,
+) -> Result<Option<BitVec<u8, Lsb0>>, String> {
+    let Some((Token::Word(len), mut args)) = args.split_first() else {
+        return Err("Expected a length".to_string());
+    };
+    let len = parse_number(len)? as usize;
+
+    let length_changed = len != data.tdi.len();
+    if length_changed {
+        data.tdi = bitvec![u8, Lsb0; 1; len];
+        data.mask = bitvec![u8, Lsb0; 1; len];
+    }
+
+    let mut tdi_given = false;
+    let mut tdo = None;
+    while let [Token::Word(name), Token::Group(value), rest @ ..] = args {
+        let value = parse_hex(value, len)?;
+        match name.to_ascii_uppercase().as_str() {
+            "TDI" => {
+                data.tdi = value;
+                tdi_given = true;
+            }
+            "TDO" => tdo = Some(value),
+            "MASK" => data.mask = value,
+            // The scan mask only marks which TDI bits are relevant, all bits are shifted anyway.
+            "SMASK" => {}
+            _ => return Err(format!("Unknown scan parameter {name}")),
+        }
+        args = rest;
+    }
+
+    if !args.is_empty() {
+        return Err(format!("Unexpected {:?}", args[0]));
+    }
+    if requires_tdi && length_changed && len > 0 && !tdi_given {
+        return Err("TDI is required when the length of the scan changes".to_string());
+    }
+
+    Ok(tdo)
+}
+
+/// Builds a scan of the whole chain from the header, data and trailer patterns.
+fn compose(
+    register: JtagRegister,
+    header: &ScanData,
+    data: &ScanData,
+    trailer: &ScanData,
+    tdo: Option<BitVec<u8, Lsb0>>,
+    end_state: JtagStableState,
+) -> Shift {
+    let mut tdi = header.tdi.clone();
+    tdi.extend_from_bitslice(&data.tdi);
+    tdi.extend_from_bitslice(&trailer.tdi);
+
+    // Only the TDO data of the scan itself is checked, the header and trailer bits are masked.
+    let tdo = tdo.map(|expected| {
+        let mut padded = bitvec![u8, Lsb0; 0; header.tdi.len()];
+        padded.extend_from_bitslice(&expected);
+        padded.resize(tdi.len(), false);
+
+        let mut mask = bitvec![u8, Lsb0; 0; header.tdi.len()];
+        mask.extend_from_bitslice(&data.mask);
+        mask.resize(tdi.len(), false);
+
+        (padded, mask)
+    });
+
+    Shift {
+        register,
+        tdi,
+        tdo,
+        end_state,
+        retries: 0,
+        retry_wait: 0,
+    }
+}
+
+fn single_word(args: &[Token]) -> Result<&str, String> {
+    match args {
+        [Token::Word(word)] => Ok(word),
+        _ => Err("Expected a single argument".to_string()),
+    }
+}
+
+fn parse_number(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .ok_or_else(|| format!("Invalid number {value}"))
+}
+
+/// Parses a hex string, with the last bit first, into `len` bits.
+fn parse_hex(value: &str, len: usize) -> Result<BitVec<u8, Lsb0>, String> {
+    let mut bits = BitVec::with_capacity(value.len() * 4);
+    for c in value.chars().rev() {
+        let nibble = c
+            .to_digit(16)
+            .ok_or_else(|| format!("Invalid hex digit {c:?}"))?;
+        bits.extend_from_bitslice(&nibble.view_bits::<Lsb0>()[..4]);
+    }
+
+    if bits.len() > len {
+        if bits[len..].any() {
+            return Err(format!("The value {value} is longer than {len} bits"));
+        }
+        bits.truncate(len);
+    } else {
+        bits.resize(len, false);
+    }
+
+    Ok(bits)
+}
+
+fn is_state(name: &str) -> bool {
+    const STATES: &[&str] = &[
+        "RESET",
+        "IDLE",
+        "DRSELECT",
+        "DRCAPTURE",
+        "DRSHIFT",
+        "DREXIT1",
+        "DRPAUSE",
+        "DREXIT2",
+        "DRUPDATE",
+        "IRSELECT",
+        "IRCAPTURE",
+        "IRSHIFT",
+        "IREXIT1",
+        "IRPAUSE",
+        "IREXIT2",
+        "IRUPDATE",
+    ];
+
+    STATES.iter().any(|state| state.eq_ignore_ascii_case(name))
+}
+
+fn parse_stable_state(name: &str) -> Result<JtagStableState, String> {
+    match name.to_ascii_uppercase().as_str() {
+        "RESET" => Ok(JtagStableState::Reset),
+        "IDLE" => Ok(JtagStableState::Idle),
+        "DRPAUSE" => Ok(JtagStableState::DrPause),
+        "IRPAUSE" => Ok(JtagStableState::IrPause),
+        _ => Err(format!("{name} is not a stable state")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::probe::common::tap_model::TapProbe;
+
+    const IDCODE: u32 = 0x1000_0a6d;
+
+    fn shift(program: &Program, index: usize) -> &Shift {
+        match &program.steps[index].command {
+            Command::Shift(shift) => shift,
+            command => panic!("Expected a shift, found {command:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_hex_values() {
+        assert_eq!(parse_hex("a", 4).unwrap(), bits![u8, Lsb0; 0, 1, 0, 1]);
+        assert_eq!(parse_hex("0005", 3).unwrap(), bits![u8, Lsb0; 1, 0, 1]);
+        assert!(parse_hex("10", 4).is_err());
+        assert!(parse_hex("1g", 8).is_err());
+    }
+
+    #[test]
+    fn scans_are_sticky_and_padded() {
+        let program = Program::parse_svf(
+            "! Two devices in bypass after the target\n\
+             HIR 2 TDI (3);\n\
+             SIR 5 TDI (01)\n    TDO (01);\n\
+             SIR 5 MASK (1f); // The TDI data stays the same\n\
+             SIR 4 TDI (2);\n",
+        )
+        .unwrap();
+
+        assert_eq!(program.steps.len(), 3);
+        assert_eq!(program.steps[0].position, Position::Line(3));
+        assert_eq!(program.steps[1].position, Position::Line(5));
+
+        let first = shift(&program, 0);
+        assert_eq!(first.register, JtagRegister::Ir);
+        assert_eq!(first.tdi, bits![u8, Lsb0; 1, 1, 1, 0, 0, 0, 0]);
+        let (expected, mask) = first.tdo.as_ref().unwrap();
+        assert_eq!(expected, bits![u8, Lsb0; 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(mask, bits![u8, Lsb0; 0, 0, 1, 1, 1, 1, 1]);
+
+        assert_eq!(shift(&program, 1).tdi, first.tdi);
+        assert_eq!(shift(&program, 1).tdo, None);
+        assert_eq!(shift(&program, 2).tdi, bits![u8, Lsb0; 1, 1, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn tdi_is_required_when_the_length_changes() {
+        let error = Program::parse_svf("SDR 8 TDI (ff);\nSDR 16;").unwrap_err();
+        assert!(matches!(
+            error,
+            SvfError::Parse {
+                position: Position::Line(2),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn run_test_states_are_sticky() {
+        let program = Program::parse_svf(
+            "RUNTEST DRPAUSE 10 TCK 1.0E-3 SEC MAXIMUM 1 SEC;\n\
+             RUNTEST 5 TCK ENDSTATE IDLE;\n\
+             RUNTEST 2E-3 SEC;",
+        )
+        .unwrap();
+
+        let commands: Vec<_> = program.steps.iter().map(|s| s.command.clone()).collect();
+        assert_eq!(
+            commands,
+            [
+                Command::RunTest {
+                    state: JtagStableState::DrPause,
+                    cycles: 10,
+                    min_time: Duration::from_millis(1),
+                    end_state: JtagStableState::DrPause,
+                },
+                Command::RunTest {
+                    state: JtagStableState::DrPause,
+                    cycles: 5,
+                    min_time: Duration::ZERO,
+                    end_state: JtagStableState::Idle,
+                },
+                Command::RunTest {
+                    state: JtagStableState::DrPause,
+                    cycles: 0,
+                    min_time: Duration::from_millis(2),
+                    end_state: JtagStableState::Idle,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn pio_is_not_supported() {
+        let error = Program::parse_svf("PIO (HLZ);").unwrap_err();
+        assert!(matches!(error, SvfError::Unsupported { command, .. } if command == "PIO"));
+    }
+
+    #[test]
+    fn idcode_is_checked() {
+        let mut probe = TapProbe::new(IDCODE);
+
+        let program = Program::parse_svf(&format!(
+            "FREQUENCY 4E6 HZ;\n\
+             STATE RESET IDLE;\n\
+             SIR 5 TDI (01) TDO (01) MASK (03);\n\
+             SDR 32 TDI (00000000) TDO ({IDCODE:08x});\n\
+             RUNTEST 100 TCK;"
+        ))
+        .unwrap();
+        program.play(&mut probe).unwrap();
+        assert_eq!(probe.speed_khz, 4000);
+
+        let program = Program::parse_svf(
+            "SIR 5 TDI (01);\n\
+             SDR 32 TDI (00000000) TDO (12345678) MASK (0000ffff);",
+        )
+        .unwrap();
+        let error = program.play(&mut probe).unwrap_err();
+        assert!(matches!(
+            error,
+            SvfError::TdoMismatch {
+                position: Position::Line(2),
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "The TDO data at line 2 does not match. Expected 12345678 with mask 0000ffff, \
+             but captured 10000a6d."
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Decoding of XSVF files, as described in Xilinx application note XAPP503.
+
+use std::time::Duration;
+
+use bitvec::prelude::*;
+
+use super::{Command, Position, Program, Shift, Step, SvfError};
+use crate::probe::{JtagRegister, JtagStableState};
+
+const XCOMPLETE: u8 = 0x00;
+const XTDOMASK: u8 = 0x01;
+const XSIR: u8 = 0x02;
+const XSDR: u8 = 0x03;
+const XRUNTEST: u8 = 0x04;
+const XREPEAT: u8 = 0x07;
+const XSDRSIZE: u8 = 0x08;
+const XSDRTDO: u8 = 0x09;
+const XSETSDRMASKS: u8 = 0x0A;
+const XSDRINC: u8 = 0x0B;
+const XSDRB: u8 = 0x0C;
+const XSDRC: u8 = 0x0D;
+const XSDRE: u8 = 0x0E;
+const XSDRTDOB: u8 = 0x0F;
+const XSDRTDOC: u8 = 0x10;
+const XSDRTDOE: u8 = 0x11;
+const XSTATE: u8 = 0x12;
+const XENDIR: u8 = 0x13;
+const XENDDR: u8 = 0x14;
+const XSIR2: u8 = 0x15;
+const XCOMMENT: u8 = 0x16;
+const XWAIT: u8 = 0x17;
+const XTRST: u8 = 0x18;
+
+/// The number of times a failed `XSDR` scan is repeated, if the file does not set it.
+const DEFAULT_REPEAT: u8 = 32;
+
+struct Reader<'data> {
+    data: &'data [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, count: usize) -> Result<&[u8], SvfError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + count)
+            .ok_or(SvfError::Parse {
+                position: Position::Offset(self.data.len()),
+                message: "Unexpected end of file".to_string(),
+            })?;
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SvfError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SvfError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SvfError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a vector of `len` bits, which is stored with the most significant byte first.
+    fn vector(&mut self, len: usize) -> Result<BitVec<u8, Lsb0>, SvfError> {
+        let mut bytes = self.bytes(len.div_ceil(8))?.to_vec();
+        bytes.reverse();
+
+        let mut bits = BitVec::from_vec(bytes);
+        bits.truncate(len);
+        Ok(bits)
+    }
+}
+
+/// A data register scan which is split across several `XSDRB`, `XSDRC` and `XSDRE` commands.
+#[derive(Default)]
+struct SplitScan {
+    tdi: BitVec<u8, Lsb0>,
+    expected: BitVec<u8, Lsb0>,
+    mask: BitVec<u8, Lsb0>,
+    checked: bool,
+}
+
+pub(super) fn parse(data: &[u8]) -> Result<Program, SvfError> {
+    let mut reader = Reader { data, offset: 0 };
+    let mut steps = vec![];
+
+    let mut sdr_size = 0;
+    let mut tdo_mask = BitVec::new();
+    let mut tdo_expected: Option<BitVec<u8, Lsb0>> = None;
+    let mut repeat = DEFAULT_REPEAT;
+    let mut run_test = 0;
+    let mut end_ir = JtagStableState::Idle;
+    let mut end_dr = JtagStableState::Idle;
+    let mut split_scan: Option<SplitScan> = None;
+
+    loop {
+        let position = Position::Offset(reader.offset);
+        let invalid = |message: String| SvfError::Parse { position, message };
+
+        let opcode = reader.u8()?;
+        let mut push = |command| steps.push(Step { position, command });
+
+        match opcode {
+            XCOMPLETE => break,
+            XTDOMASK => tdo_mask = reader.vector(sdr_size)?,
+            XSIR | XSIR2 => {
+                let len = if opcode == XSIR {
+                    reader.u8()?.into()
+                } else {
+                    reader.u16()?.into()
+                };
+                let tdi = reader.vector(len)?;
+
+                push(Command::Shift(Shift {
+                    register: JtagRegister::Ir,
+                    tdi,
+                    tdo: None,
+                    end_state: end_ir,
+                    retries: 0,
+                    retry_wait: 0,
+                }));
+                if run_test > 0 {
+                    push(run_test_command(JtagStableState::Idle, run_test));
+                }
+            }
+            XSDR | XSDRTDO => {
+                let tdi = reader.vector(sdr_size)?;
+                if opcode == XSDRTDO {
+                    tdo_expected = Some(reader.vector(sdr_size)?);
+                }
+
+                // XSDR checks against the data of the last XSDRTDO command.
+                let tdo = tdo_expected
+                    .clone()
+                    .filter(|expected| expected.len() == sdr_size && tdo_mask.len() == sdr_size)
+                    .map(|expected| (expected, tdo_mask.clone()));
+
+                push(Command::Shift(Shift {
+                    register: JtagRegister::Dr,
+                    tdi,
+                    tdo,
+                    end_state: end_dr,
+                    retries: repeat,
+                    retry_wait: run_test,
+                }));
+                if run_test > 0 {
+                    push(run_test_command(JtagStableState::Idle, run_test));
+                }
+            }
+            XSDRB | XSDRC | XSDRE | XSDRTDOB | XSDRTDOC | XSDRTDOE => {
+                let checked = matches!(opcode, XSDRTDOB | XSDRTDOC | XSDRTDOE);
+                let tdi = reader.vector(sdr_size)?;
+                let expected = if checked {
+                    reader.vector(sdr_size)?
+                } else {
+                    bitvec![u8, Lsb0; 0; sdr_size]
+                };
+                let mask = if checked && tdo_mask.len() == sdr_size {
+                    tdo_mask.clone()
+                } else {
+                    bitvec![u8, Lsb0; 0; sdr_size]
+                };
+
+                let scan = match opcode {
+                    XSDRB | XSDRTDOB => split_scan.insert(SplitScan::default()),
+                    _ => split_scan
+                        .as_mut()
+                        .ok_or_else(|| invalid("The scan was not started".to_string()))?,
+                };
+                scan.tdi.extend_from_bitslice(&tdi);
+                scan.expected.extend_from_bitslice(&expected);
+                scan.mask.extend_from_bitslice(&mask);
+                scan.checked |= checked;
+
+                if matches!(opcode, XSDRE | XSDRTDOE) {
+                    let scan = split_scan.take().unwrap_or_default();
+                    push(Command::Shift(Shift {
+                        register: JtagRegister::Dr,
+                        tdi: scan.tdi,
+                        tdo: scan.checked.then_some((scan.expected, scan.mask)),
+                        end_state: end_dr,
+                        retries: 0,
+                        retry_wait: 0,
+                    }));
+                }
+            }
+            XRUNTEST => run_test = reader.u32()?,
+            XREPEAT => repeat = reader.u8()?,
+            XSDRSIZE => sdr_size = reader.u32()? as usize,
+            XSTATE => {
+                let state = parse_state(reader.u8()?).map_err(invalid)?;
+                push(Command::State(state));
+            }
+            XENDIR => {
+                end_ir = match reader.u8()? {
+                    0 => JtagStableState::Idle,
+                    1 => JtagStableState::IrPause,
+                    state => return Err(invalid(format!("Invalid ENDIR state {state}"))),
+                }
+            }
+            XENDDR => {
+                end_dr = match reader.u8()? {
+                    0 => JtagStableState::Idle,
+                    1 => JtagStableState::DrPause,
+                    state => return Err(invalid(format!("Invalid ENDDR state {state}"))),
+                }
+            }
+            XCOMMENT => {
+                let start = reader.offset;
+                while reader.u8()? != 0 {}
+                let comment = String::from_utf8_lossy(&data[start..reader.offset - 1]);
+                tracing::info!("{comment}");
+            }
+            XWAIT => {
+                let state = parse_state(reader.u8()?).map_err(invalid)?;
+                let end_state = parse_state(reader.u8()?).map_err(invalid)?;
+                let time = reader.u32()?;
+                push(Command::RunTest {
+                    state,
+                    cycles: 0,
+                    min_time: Duration::from_micros(time.into()),
+                    end_state,
+                });
+            }
+            XTRST => {
+                reader.u8()?;
+                tracing::debug!("Ignoring XTRST, the test reset signal is not supported");
+            }
+            XSETSDRMASKS | XSDRINC => {
+                return Err(SvfError::Unsupported {
+                    position,
+                    command: if opcode == XSETSDRMASKS {
+                        "XSETSDRMASKS"
+                    } else {
+                        "XSDRINC"
+                    }
+                    .to_string(),
+                })
+            }
+            _ => return Err(invalid(format!("Unknown command {opcode:#04x}"))),
+        }
+    }
+
+    Ok(Program { steps })
+}
+
+/// Creates a command which waits for `time` µs in `state`.
+///
+/// TCK is clocked once per µs as well, which matches the TCK frequency of 1 MHz the timing of
+/// XSVF files is usually generated for.
+fn run_test_command(state: JtagStableState, time: u32) -> Command {
+    Command::RunTest {
+        state,
+        cycles: time,
+        min_time: Duration::from_micros(time.into()),
+        end_state: state,
+    }
+}
+
+fn parse_state(state: u8) -> Result<JtagStableState, String> {
+    match state {
+        0x00 => Ok(JtagStableState::Reset),
+        0x01 => Ok(JtagStableState::Idle),
+        0x06 => Ok(JtagStableState::DrPause),
+        0x0D => Ok(JtagStableState::IrPause),
+        _ => Err(format!("The state {state:#04x} is not a stable state")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::probe::common::tap_model::TapProbe;
+
+    const IDCODE: u32 = 0x1000_0a6d;
+
+    /// Reads the IDCODE, checking it against `expected`.
+    fn read_idcode(expected: u32) -> Vec<u8> {
+        let mut data = vec![XSTATE, 0x00, XSTATE, 0x01, XREPEAT, 0x02, XENDDR, 0x01];
+        data.extend([XSIR, 5, 0x01]);
+        data.extend([XSDRSIZE, 0, 0, 0, 32]);
+        data.extend([XTDOMASK, 0xff, 0xff, 0xff, 0xff]);
+        data.extend([XSDRTDO, 0, 0, 0, 0]);
+        data.extend(expected.to_be_bytes());
+        data.extend([XCOMPLETE]);
+        data
+    }
+
+    #[test]
+    fn vectors_are_most_significant_byte_first() {
+        let mut reader = Reader {
+            data: &[0x01, 0x80],
+            offset: 0,
+        };
+
+        let bits = reader.vector(9).unwrap();
+        assert_eq!(bits.load_le::<u16>(), 0x180);
+        assert_eq!(bits.len(), 9);
+    }
+
+    #[test]
+    fn idcode_is_checked() {
+        let mut probe = TapProbe::new(IDCODE);
+
+        let program = Program::parse_xsvf(&read_idcode(IDCODE)).unwrap();
+        program.play(&mut probe).unwrap();
+
+        let program = Program::parse_xsvf(&read_idcode(0x1234_5678)).unwrap();
+        let error = program.play(&mut probe).unwrap_err();
+        assert!(matches!(
+            error,
+            SvfError::TdoMismatch {
+                position: Position::Offset(0x15),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn split_scans_are_joined() {
+        let mut data = vec![XSDRSIZE, 0, 0, 0, 8];
+        data.extend([XSDRB, 0x01, XSDRC, 0x02, XSDRE, 0x03, XCOMPLETE]);
+
+        let program = Program::parse_xsvf(&data).unwrap();
+        assert_eq!(program.steps.len(), 1);
+
+        let Command::Shift(shift) = &program.steps[0].command else {
+            panic!("Expected a shift");
+        };
+        assert_eq!(shift.tdi.as_raw_slice(), [0x01, 0x02, 0x03]);
+        assert_eq!(shift.tdo, None);
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let error = Program::parse_xsvf(&[XSDRSIZE, 0, 0]).unwrap_err();
+        assert!(matches!(error, SvfError::Parse { .. }));
+    }
+}
@@ -363,6 +363,10 @@ impl DebugProbe for WchLink {
         self
     }
 
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JTAGAccess> {
+        Some(self)
+    }
+
     fn has_riscv_interface(&self) -> bool {
         true
     }
@@ -0,0 +1,40 @@
+//! Readout protection of devices.
+//!
+//! Most microcontrollers can be protected against reading out their memory through the debug
+//! interface. The details differ between vendors, so the protection state is abstracted into a
+//! small set of [`ProtectionLevel`]s, which can be read and changed using
+//! [`Session::protection_status`](crate::Session::protection_status) and
+//! [`Session::set_protection`](crate::Session::set_protection).
+
+use serde::{Deserialize, Serialize};
+
+/// The readout protection level of a device.
+///
+/// The levels are ordered by their strength, so a transition to a lower level
+/// removes protection from the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ProtectionLevel {
+    /// The memory of the device can be accessed freely.
+    Unprotected,
+    /// Debug access to the memory of the device is blocked.
+    ///
+    /// Removing the protection erases the device.
+    Protected,
+    /// Debug access to the device is blocked permanently.
+    ///
+    /// This protection cannot be removed anymore.
+    PermanentlyProtected,
+}
+
+/// The readout protection state of a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtectionStatus {
+    /// The protection level which is currently in effect.
+    pub level: ProtectionLevel,
+    /// The protection level which takes effect after the next reset, if it differs from
+    /// `level`.
+    #[serde(default)]
+    pub pending: Option<ProtectionLevel>,
+    /// A vendor specific description of the protection, e.g. the raw register values.
+    pub details: String,
+}
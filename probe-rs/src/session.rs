@@ -3,9 +3,10 @@ use crate::{
         arm::{
             communication_interface::ArmProbeInterface,
             component::{get_arm_components, TraceSink},
+            etm::TraceProtocol,
             memory::CoresightComponent,
             sequences::{ArmDebugSequence, DefaultArmSequence},
-            ArmError, DpAddress, SwoReader,
+            ArmError, DpAddress, FullyQualifiedApAddress, SwoReader,
         },
         riscv::communication_interface::{
             RiscvCommunicationInterface, RiscvDebugInterfaceState, RiscvError,
@@ -20,7 +21,7 @@ use crate::{
         fake_probe::FakeProbe, list::Lister, AttachMethod, DebugProbeError, Probe,
         ProbeCreationError,
     },
-    Core, CoreType, Error,
+    Core, CoreType, Error, ProtectionLevel, ProtectionStatus,
 };
 use std::ops::DerefMut;
 use std::{fmt, sync::Arc, time::Duration};
@@ -49,6 +50,8 @@ pub struct Session {
     interfaces: ArchitectureInterface,
     cores: Vec<CombinedCoreState>,
     configured_trace_sink: Option<TraceSink>,
+    configured_instruction_trace: Option<TraceProtocol>,
+    permissions: Permissions,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -160,6 +163,7 @@ impl Session {
         };
 
         session.clear_all_hw_breakpoints()?;
+        session.clear_all_hw_watchpoints()?;
 
         Ok(session)
     }
@@ -171,13 +175,7 @@ impl Session {
         permissions: Permissions,
         cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
-        let default_core = target.default_core();
-
-        let default_memory_ap = default_core.memory_ap().ok_or_else(|| {
-            Error::Other(format!(
-                "Unable to connect to core {default_core:?}, no memory AP configured"
-            ))
-        })?;
+        let default_memory_ap = Self::default_memory_ap(&target)?;
 
         let default_dp = default_memory_ap.dp();
 
@@ -229,6 +227,20 @@ impl Session {
             Err(e) => return Err(Error::Arm(e)),
         }
 
+        // Targets with several debug ports on an SWD multidrop bus, like dual-die parts, have to
+        // be unlocked through each of them.
+        let mut unlocked_dps = vec![default_dp];
+        for core in &cores {
+            let memory_ap = core.arm_memory_ap();
+            if unlocked_dps.contains(&memory_ap.dp()) {
+                continue;
+            }
+            unlocked_dps.push(memory_ap.dp());
+
+            tracing::debug!("Unlocking debug port {:x?}", memory_ap.dp());
+            sequence_handle.debug_device_unlock(&mut *interface, &memory_ap, &permissions)?;
+        }
+
         // For each core, setup debugging
         for core in &cores {
             core.enable_arm_debug(&mut *interface)?;
@@ -261,6 +273,8 @@ impl Session {
                 interfaces: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                configured_instruction_trace: None,
+                permissions,
             };
 
             {
@@ -284,6 +298,8 @@ impl Session {
                 interfaces: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                configured_instruction_trace: None,
+                permissions,
             })
         }
     }
@@ -292,7 +308,7 @@ impl Session {
         mut probe: Probe,
         target: Target,
         _attach_method: AttachMethod,
-        _permissions: Permissions,
+        permissions: Permissions,
         cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
         // While we still don't support mixed architectures
@@ -367,6 +383,8 @@ impl Session {
             interfaces,
             cores,
             configured_trace_sink: None,
+            configured_instruction_trace: None,
+            permissions,
         };
 
         // Wait for the cores to be halted.
@@ -521,7 +539,7 @@ impl Session {
             }
 
             TraceSink::TraceMemory => {
-                let components = self.get_arm_components(DpAddress::Default)?;
+                let components = self.get_arm_components(self.default_dp()?)?;
                 let interface = self.get_arm_interface()?;
                 crate::architecture::arm::component::read_trace_memory(interface, &components)
             }
@@ -621,11 +639,7 @@ impl Session {
 
     /// Check if the connected device has a debug erase sequence defined
     pub fn has_sequence_erase_all(&self) -> bool {
-        match &self.target.debug_sequence {
-            DebugSequence::Arm(seq) => seq.debug_erase_sequence().is_some(),
-            // Currently, debug_erase_sequence is ARM (and ATSAM) specific
-            _ => false,
-        }
+        self.target.has_sequence_erase_all()
     }
 
     /// Erase all flash memory using the Device's Debug Erase Sequence if any
@@ -670,6 +684,102 @@ impl Session {
         Ok(())
     }
 
+    /// Reads the readout protection state of the device.
+    ///
+    /// # Errors
+    /// NotImplemented if the protection of the device can not be read.
+    pub fn protection_status(&mut self) -> Result<ProtectionStatus, Error> {
+        let ArchitectureInterface::Arm(ref mut interface) = self.interfaces else {
+            return Err(Error::NotImplemented(
+                "device protection for non-ARM targets",
+            ));
+        };
+
+        let DebugSequence::Arm(ref debug_sequence) = self.target.debug_sequence else {
+            unreachable!("This should never happen. Please file a bug if it does.");
+        };
+
+        let default_ap = Self::default_memory_ap(&self.target)?;
+
+        Ok(debug_sequence.protection_status(interface.deref_mut(), &default_ap)?)
+    }
+
+    /// Changes the readout protection of the device to `level`.
+    ///
+    /// Removing protection erases the device, so it requires the `erase_all` permission.
+    /// Protecting the device permanently requires the `permanent_protection` permission.
+    /// Depending on the device, the new level may only take effect after a reset.
+    ///
+    /// # Errors
+    /// NotImplemented if the protection of the device can not be changed to `level`.
+    pub fn set_protection(&mut self, level: ProtectionLevel) -> Result<(), Error> {
+        let current = self.protection_status()?.level;
+
+        let permission = if level < current {
+            self.permissions.erase_all()
+        } else if level == ProtectionLevel::PermanentlyProtected && current != level {
+            self.permissions.permanent_protection()
+        } else {
+            Ok(())
+        };
+        permission
+            .map_err(|MissingPermissions(permission)| Error::MissingPermissions(permission))?;
+
+        if level == current {
+            tracing::info!("The device already has protection level {:?}", level);
+            return Ok(());
+        }
+
+        let ArchitectureInterface::Arm(ref mut interface) = self.interfaces else {
+            return Err(Error::NotImplemented(
+                "device protection for non-ARM targets",
+            ));
+        };
+
+        let DebugSequence::Arm(ref debug_sequence) = self.target.debug_sequence else {
+            unreachable!("This should never happen. Please file a bug if it does.");
+        };
+
+        let default_ap = Self::default_memory_ap(&self.target)?;
+
+        tracing::info!("Changing the protection level from {current:?} to {level:?}");
+        let result = debug_sequence.set_protection(
+            interface.deref_mut(),
+            &default_ap,
+            level,
+            &self.permissions,
+        );
+
+        match result {
+            Ok(()) => Ok(()),
+            // Removing protection usually erases and resets the device.
+            Err(ArmError::ReAttachRequired) => {
+                Self::reattach_arm_interface(interface, debug_sequence)?;
+                for core_state in &self.cores {
+                    core_state.enable_arm_debug(interface.deref_mut())?;
+                }
+                Ok(())
+            }
+            Err(e) => Err(Error::Arm(e)),
+        }
+    }
+
+    fn default_memory_ap(target: &Target) -> Result<FullyQualifiedApAddress, Error> {
+        let default_core = target.default_core();
+
+        default_core.memory_ap().ok_or_else(|| {
+            Error::Other(format!(
+                "Unable to connect to core {default_core:?}, no memory AP configured"
+            ))
+        })
+    }
+
+    /// The debug port of the default core.
+    fn default_dp(&self) -> Result<DpAddress, ArmError> {
+        let memory_ap = self.target.default_core().memory_ap();
+        memory_ap.map(|ap| ap.dp()).ok_or(ArmError::NoArmTarget)
+    }
+
     /// Reads all the available ARM CoresightComponents of the currently attached target.
     ///
     /// This will recursively parse the Romtable of the attached target
@@ -705,7 +815,8 @@ impl Session {
             _ => unreachable!("Mismatch between architecture and sequence type!"),
         };
 
-        let components = self.get_arm_components(DpAddress::Default)?;
+        let dp = self.cores[core_index].arm_memory_ap().dp();
+        let components = self.get_arm_components(dp)?;
         let interface = self.get_arm_interface()?;
 
         // Configure SWO on the probe when the trace sink is configured for a serial output. Note
@@ -728,6 +839,60 @@ impl Session {
         Ok(())
     }
 
+    /// Configure the target for ETM instruction trace into the on-chip trace buffer (ETB or ETF).
+    ///
+    /// The trace buffer is used as a circular buffer, so that it always contains the most recently
+    /// executed instructions. The trace can be read with [`Session::read_instruction_trace`], and
+    /// decoded with [`crate::architecture::arm::etm::decode_instruction_trace`].
+    ///
+    /// # Returns
+    /// The trace protocol of the ETM, which is needed to decode the trace.
+    pub fn setup_instruction_trace(&mut self, core_index: usize) -> Result<TraceProtocol, Error> {
+        {
+            let mut core = self.core(core_index)?;
+            crate::architecture::arm::component::enable_tracing(&mut core)?;
+        }
+
+        let sequence_handle = match &self.target.debug_sequence {
+            DebugSequence::Arm(sequence) => sequence.clone(),
+            _ => unreachable!("Mismatch between architecture and sequence type!"),
+        };
+
+        let core_ap = self.cores[core_index].arm_memory_ap();
+        let core_position = self.cores[..core_index]
+            .iter()
+            .filter(|core| core.arm_memory_ap() == core_ap)
+            .count();
+        let components = self.get_arm_components(core_ap.dp())?;
+        let interface = self.get_arm_interface()?;
+
+        sequence_handle.trace_start(interface, &components, &TraceSink::TraceMemory)?;
+        let protocol = crate::architecture::arm::component::setup_instruction_trace(
+            interface,
+            &components,
+            &core_ap,
+            core_position,
+        )?;
+
+        self.configured_instruction_trace.replace(protocol);
+
+        Ok(protocol)
+    }
+
+    /// Stop the instruction trace capture, and read the raw ETM trace from the trace buffer.
+    ///
+    /// The trace has to be set up with [`Session::setup_instruction_trace`] before. The capture
+    /// stops when the trace is read, so it has to be set up again to continue tracing.
+    pub fn read_instruction_trace(&mut self) -> Result<Vec<u8>, Error> {
+        if self.configured_instruction_trace.is_none() {
+            return Err(ArmError::TracingUnconfigured.into());
+        }
+
+        let components = self.get_arm_components(self.default_dp()?)?;
+        let interface = self.get_arm_interface()?;
+        crate::architecture::arm::component::read_instruction_trace(interface, &components)
+    }
+
     /// Configure the target to stop emitting SWV trace data.
     #[tracing::instrument(skip(self))]
     pub fn disable_swv(&mut self, core_index: usize) -> Result<(), Error> {
@@ -736,7 +901,7 @@ impl Session {
 
     /// Begin tracing a memory address over SWV.
     pub fn add_swv_data_trace(&mut self, unit: usize, address: u32) -> Result<(), ArmError> {
-        let components = self.get_arm_components(DpAddress::Default)?;
+        let components = self.get_arm_components(self.default_dp()?)?;
         let interface = self.get_arm_interface()?;
         crate::architecture::arm::component::add_swv_data_trace(
             interface,
@@ -748,7 +913,7 @@ impl Session {
 
     /// Stop tracing from a given SWV unit
     pub fn remove_swv_data_trace(&mut self, unit: usize) -> Result<(), ArmError> {
-        let components = self.get_arm_components(DpAddress::Default)?;
+        let components = self.get_arm_components(self.default_dp()?)?;
         let interface = self.get_arm_interface()?;
         crate::architecture::arm::component::remove_swv_data_trace(interface, &components, unit)
     }
@@ -778,6 +943,17 @@ impl Session {
         })
     }
 
+    /// Clears all hardware watchpoints on all cores
+    pub fn clear_all_hw_watchpoints(&mut self) -> Result<(), Error> {
+        self.halted_access(|session| {
+            { 0..session.cores.len() }.try_for_each(|core| match session.core(core) {
+                Ok(mut core) => core.clear_all_hw_watchpoints(),
+                Err(Error::CoreDisabled(_)) => Ok(()),
+                Err(err) => Err(err),
+            })
+        })
+    }
+
     /// Resume all cores
     pub fn resume_all_cores(&mut self) -> Result<(), Error> {
         // Resume cores
@@ -814,6 +990,13 @@ impl Drop for Session {
             );
         }
 
+        if let Err(err) = self.clear_all_hw_watchpoints() {
+            tracing::warn!(
+                "Could not clear all hardware watchpoints: {:?}",
+                anyhow::anyhow!(err)
+            );
+        }
+
         // Call any necessary deconfiguration/shutdown hooks.
         if let Err(err) = { 0..self.cores.len() }.try_for_each(|core| match self.core(core) {
             Ok(mut core) => core.debug_core_stop(),
@@ -837,7 +1020,7 @@ fn get_target_from_selector(
 ) -> Result<(Probe, Target), Error> {
     let target = match target {
         TargetSelector::Unspecified(name) => crate::config::get_target_by_name(name)?,
-        TargetSelector::Specified(target) => target,
+        TargetSelector::Specified(target) => *target,
         TargetSelector::Auto => {
             // At this point we do not know what the target is, so we cannot use the chip specific reset sequence.
             // Thus, we try just using a normal reset for target detection if we want to do so under reset.
@@ -881,6 +1064,8 @@ fn get_target_from_selector(
 pub struct Permissions {
     /// When set to true, all memory of the chip may be erased or reset to factory default
     erase_all: bool,
+    /// When set to true, the chip may be protected permanently against debug access
+    permanent_protection: bool,
 }
 
 impl Permissions {
@@ -902,6 +1087,18 @@ impl Permissions {
         }
     }
 
+    /// Allow the session to protect the chip permanently against debug access.
+    ///
+    /// # Warning
+    /// This is irreversible. Depending on the device, it may never be debugged or reprogrammed again.
+    #[must_use]
+    pub fn allow_permanent_protection(self) -> Self {
+        Self {
+            permanent_protection: true,
+            ..self
+        }
+    }
+
     pub(crate) fn erase_all(&self) -> Result<(), MissingPermissions> {
         if self.erase_all {
             Ok(())
@@ -909,6 +1106,14 @@ impl Permissions {
             Err(MissingPermissions("erase_all".into()))
         }
     }
+
+    pub(crate) fn permanent_protection(&self) -> Result<(), MissingPermissions> {
+        if self.permanent_protection {
+            Ok(())
+        } else {
+            Err(MissingPermissions("permanent_protection".into()))
+        }
+    }
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
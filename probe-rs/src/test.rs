@@ -168,8 +168,8 @@ impl MemoryInterface for MockMemory {
         todo!()
     }
 
-    fn write_word_32(&mut self, _address: u64, _data: u32) -> Result<(), crate::Error> {
-        todo!()
+    fn write_word_32(&mut self, address: u64, data: u32) -> Result<(), crate::Error> {
+        self.write_8(address, &data.to_le_bytes())
     }
 
     fn write_word_16(&mut self, _address: u64, _data: u16) -> Result<(), crate::Error> {
@@ -192,12 +192,29 @@ impl MemoryInterface for MockMemory {
         todo!()
     }
 
-    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), crate::Error> {
-        todo!()
+    /// Overwrites existing data, writes to addresses without data panic.
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        let index = match self
+            .values
+            .binary_search_by_key(&address, |(addr, _data)| *addr)
+        {
+            Ok(index) => index,
+            Err(0) => self.missing_range(address, address + data.len() as u64),
+            Err(index) => index - 1,
+        };
+
+        let (start, stored_data) = &mut self.values[index];
+        let offset = (address - *start) as usize;
+        match stored_data.get_mut(offset..offset + data.len()) {
+            Some(stored_data) => stored_data.copy_from_slice(data),
+            None => self.missing_range(address, address + data.len() as u64),
+        }
+
+        Ok(())
     }
 
     fn flush(&mut self) -> Result<(), crate::Error> {
-        todo!()
+        Ok(())
     }
 }
 
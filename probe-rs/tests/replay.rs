@@ -0,0 +1,148 @@
+#![cfg(feature = "builtin-targets")]
+//! Replays a recorded session with a simulated target, which flashes the firmware of a
+//! coredump, restores its RAM and registers, and unwinds the stack.
+//!
+//! The recording is created by the ignored test [`record`]:
+//!
+//! ```text
+//! cargo test -p probe-rs --test replay -- --ignored record
+//! ```
+
+use std::{fs::File, io::BufWriter, path::PathBuf};
+
+use probe_rs::{
+    config::get_target_by_name,
+    debug::{DebugInfo, DebugRegisters},
+    exception_handler_for_core,
+    flashing::DownloadOptions,
+    integration::FakeProbe,
+    probe::{
+        recording::{RecordingProbe, ReplayProbe},
+        Probe,
+    },
+    CoreDump, MemoryInterface, Permissions, RegisterValue,
+};
+
+/// A Cortex-M4 with flash at 0 and RAM at 0x2000_0000, which uses the default debug sequence.
+const TARGET: &str = "EFM32PG1B200F256GM48";
+const TEST_NAME: &str = "nRF52833_xxAA_full_unwind";
+
+fn test_file(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(path)
+}
+
+fn recording() -> PathBuf {
+    test_file(&format!("recordings/{TEST_NAME}.jsonl"))
+}
+
+/// Runs the recorded session, and returns the names of the functions on the unwound stack.
+fn run_session(probe: Probe) -> Vec<String> {
+    let coredump = CoreDump::load(&test_file(&format!(
+        "debug-unwind-tests/{TEST_NAME}.coredump"
+    )))
+    .unwrap();
+    let (flash, ram): (Vec<_>, Vec<_>) = coredump
+        .data
+        .iter()
+        .partition(|(range, _)| range.start < 0x2000_0000);
+
+    let mut session = probe
+        .attach(TARGET, Permissions::default())
+        .expect("Failed to attach to the target");
+
+    // Unwinding only reads the stack, so only the vector table is flashed, which keeps the
+    // recording small.
+    let (vector_table, data) = flash[0];
+    assert_eq!(vector_table.start, 0);
+    let mut loader = session.target().flash_loader();
+    loader.add_data(vector_table.start, data).unwrap();
+    let mut options = DownloadOptions::new();
+    options.verify = true;
+    loader
+        .commit(&mut session, options)
+        .expect("Failed to flash the target");
+
+    let mut core = session.core(0).unwrap();
+    core.halt(std::time::Duration::from_millis(100)).unwrap();
+    for (range, data) in ram {
+        core.write_8(range.start, data).unwrap();
+    }
+
+    // Registers which the core does not have, like the FPU registers, are skipped.
+    let mut registers: Vec<_> = coredump.registers.iter().collect();
+    registers.sort_by_key(|(id, _)| **id);
+    for (id, value) in registers {
+        if !core
+            .registers()
+            .all_registers()
+            .any(|register| register.id() == *id)
+        {
+            continue;
+        }
+        let value = match *value {
+            RegisterValue::U32(value) => value,
+            _ => unreachable!("The coredump is from a 32-bit core"),
+        };
+        core.write_core_reg(*id, value).unwrap();
+    }
+
+    let debug_info =
+        DebugInfo::from_file(test_file(&format!("debug-unwind-tests/{TEST_NAME}.elf"))).unwrap();
+    let initial_registers = DebugRegisters::from_core(&mut core);
+    let exception_handler = exception_handler_for_core(core.core_type());
+    let instruction_set = core.instruction_set().ok();
+
+    let stack_frames = debug_info
+        .unwind(
+            &mut core,
+            initial_registers,
+            exception_handler.as_ref(),
+            instruction_set,
+        )
+        .unwrap();
+
+    stack_frames
+        .into_iter()
+        .map(|frame| frame.function_name)
+        .collect()
+}
+
+#[test]
+#[ignore = "creates the recording replayed by `recorded_session_can_be_replayed`"]
+fn record() {
+    let target = get_target_by_name(TARGET).unwrap();
+    let probe = FakeProbe::with_simulated_target(&target)
+        .unwrap()
+        .into_probe();
+
+    std::fs::create_dir_all(recording().parent().unwrap()).unwrap();
+    let writer = BufWriter::new(File::create(recording()).unwrap());
+    let probe = RecordingProbe::new(probe, writer).unwrap();
+
+    let functions = run_session(Probe::from_specific_probe(Box::new(probe)));
+    println!("{functions:#?}");
+}
+
+#[test]
+fn recorded_session_can_be_replayed() {
+    let probe = ReplayProbe::open(recording()).unwrap();
+
+    let functions = run_session(Probe::from_specific_probe(Box::new(probe)));
+
+    assert_eq!(
+        functions,
+        [
+            "test_deep_stack",
+            "test_deep_stack",
+            "test_deep_stack",
+            "test_deep_stack",
+            "test_deep_stack",
+            "test_deep_stack",
+            "setup_data_types",
+            "__cortex_m_rt_main",
+            "__cortex_m_rt_main",
+        ]
+    );
+}
@@ -0,0 +1,435 @@
+#![cfg(feature = "builtin-targets")]
+use std::{path::Path, time::Duration};
+
+use probe_rs::{
+    config::{get_target_by_name, ImageFixup, MemoryRegion, Target},
+    flashing::{
+        erase_all_preserving, BundleImage, DownloadOptions, FileDownloadError, FlashBundle,
+        FlashError, FlashProgress, FormatKind,
+    },
+    integration::FakeProbe,
+    probe::Probe,
+    rtt::Rtt,
+    CoreStatus, HaltReason, MemoryInterface, Permissions, Session, Watchpoint, WatchpointKind,
+};
+
+const TARGET: &str = "nRF51822_xxAC";
+
+/// A firmware for the simulated target, which writes "Hi" to the RTT channel set up by
+/// [`write_rtt_control_block`], and loops forever.
+const FIRMWARE: [u16; 14] = [
+    0x4804, // ldr r0, =RTT_BUFFER
+    0x4a05, // ldr r2, =RTT_WRITE_OFFSET
+    0x2148, // movs r1, #'H'
+    0x7001, // strb r1, [r0, #0]
+    0x2169, // movs r1, #'i'
+    0x7041, // strb r1, [r0, #1]
+    0x2102, // movs r1, #2
+    0x6011, // str r1, [r2]
+    0xe7fe, // b .
+    0xbf00, // nop
+    0x1200, 0x2000, // RTT_BUFFER
+    0x1024, 0x2000, // RTT_WRITE_OFFSET
+];
+
+const FIRMWARE_ADDRESS: u64 = 0x100;
+const RTT_CONTROL_BLOCK: u64 = 0x2000_1000;
+
+fn attach() -> Session {
+    attach_to(get_target_by_name(TARGET).unwrap())
+}
+
+fn attach_to(target: Target) -> Session {
+    let probe =
+        Probe::from_specific_probe(Box::new(FakeProbe::with_simulated_target(&target).unwrap()));
+
+    probe
+        .attach(target, Permissions::default())
+        .expect("Failed to attach to the simulated target")
+}
+
+/// Flashes `data` at `address`, and at the start of the flash a vector table which starts the
+/// code at [`FIRMWARE_ADDRESS`].
+fn flash(session: &mut Session, address: u64, data: &[u8]) {
+    let vector_table = [0x2000_8000u32, FIRMWARE_ADDRESS as u32 | 1];
+    let vector_table: Vec<u8> = vector_table.iter().flat_map(|w| w.to_le_bytes()).collect();
+
+    let mut loader = session.target().flash_loader();
+    loader.add_data(0, &vector_table).unwrap();
+    loader.add_data(address, data).unwrap();
+
+    let mut options = DownloadOptions::new();
+    options.verify = true;
+    loader
+        .commit(session, options)
+        .expect("Failed to flash the simulated target");
+}
+
+fn write_rtt_control_block(session: &mut Session) {
+    let mut control_block = b"SEGGER RTT\0\0\0\0\0\0".to_vec();
+    let words = [
+        // One up channel, no down channels.
+        1,
+        0,
+        // Name, buffer, size, write offset, read offset and flags of the up channel.
+        0x2000_1100,
+        0x2000_1200,
+        64,
+        0,
+        0,
+        0,
+    ];
+    control_block.extend(words.iter().flat_map(|w: &u32| w.to_le_bytes()));
+
+    let mut core = session.core(0).unwrap();
+    core.write_8(RTT_CONTROL_BLOCK, &control_block).unwrap();
+    core.write_8(0x2000_1100, b"Terminal\0").unwrap();
+}
+
+#[test]
+fn flashed_data_can_be_read_back() {
+    let mut session = attach();
+    let data: Vec<u8> = (0..0x1800u32).map(|i| (i * 7) as u8).collect();
+
+    flash(&mut session, 0x800, &data);
+
+    let mut core = session.core(0).unwrap();
+    let mut flash = vec![0; data.len()];
+    core.read_8(0x800, &mut flash).unwrap();
+    assert_eq!(flash, data);
+
+    // The flash can only be changed by the flash algorithm.
+    assert!(core.write_8(0x800, &[0]).is_err());
+}
+
+/// Flashes `data` at `address` with the given options, without adding a vector table.
+fn flash_with_options(session: &mut Session, address: u64, data: &[u8], options: DownloadOptions) {
+    let mut loader = session.target().flash_loader();
+    loader.add_data(address, data).unwrap();
+    loader
+        .commit(session, options)
+        .expect("Failed to flash the simulated target");
+}
+
+fn read_flash(session: &mut Session, range: std::ops::Range<u64>) -> Vec<u8> {
+    let mut data = vec![0; (range.end - range.start) as usize];
+    session
+        .core(0)
+        .unwrap()
+        .read_8(range.start, &mut data)
+        .unwrap();
+    data
+}
+
+#[test]
+fn preserved_data_survives_a_sector_erase() {
+    let mut session = attach();
+    let calibration: Vec<u8> = (0..16).collect();
+    flash(&mut session, 0xA00, &calibration);
+
+    // The page from 0x800 to 0xC00 is erased to program the new data.
+    let calibration_range = 0xA00..0xA10;
+    let mut options = DownloadOptions::new();
+    options.preserve = vec![calibration_range.clone()];
+    flash_with_options(&mut session, 0x800, &[0x55; 0x100], options);
+
+    assert_eq!(read_flash(&mut session, 0x800..0x900), [0x55; 0x100]);
+    assert_eq!(read_flash(&mut session, 0x900..0xA00), [0xFF; 0x100]);
+    assert_eq!(read_flash(&mut session, calibration_range), calibration);
+}
+
+#[test]
+fn preserved_data_survives_a_chip_erase() {
+    let mut session = attach();
+    let calibration: Vec<u8> = (0..16).collect();
+    let calibration_range = 0x2000..0x2010;
+    flash(&mut session, 0x2000, &calibration);
+    flash_with_options(&mut session, 0x3000, &[0xAA; 16], DownloadOptions::new());
+
+    let mut options = DownloadOptions::new();
+    options.do_chip_erase = true;
+    options.preserve = vec![calibration_range.clone()];
+    flash_with_options(&mut session, 0x800, &[0x55; 0x100], options);
+
+    assert_eq!(read_flash(&mut session, 0x800..0x900), [0x55; 0x100]);
+    assert_eq!(read_flash(&mut session, calibration_range), calibration);
+    assert_eq!(read_flash(&mut session, 0x3000..0x3010), [0xFF; 16]);
+}
+
+#[test]
+fn preserved_data_survives_erasing_everything() {
+    let mut session = attach();
+    let calibration: Vec<u8> = (0..16).collect();
+    let calibration_range = 0x2000..0x2010;
+    flash(&mut session, 0x2000, &calibration);
+
+    let preserve = [calibration_range.clone()];
+    erase_all_preserving(&mut session, FlashProgress::empty(), &preserve).unwrap();
+
+    assert_eq!(read_flash(&mut session, 0x0..0x10), [0xFF; 16]);
+    assert_eq!(read_flash(&mut session, calibration_range), calibration);
+}
+
+#[test]
+fn firmware_stops_at_breakpoints() {
+    let mut session = attach();
+    let firmware: Vec<u8> = FIRMWARE.iter().flat_map(|i| i.to_le_bytes()).collect();
+    flash(&mut session, FIRMWARE_ADDRESS, &firmware);
+
+    let mut core = session.core(0).unwrap();
+    let timeout = Duration::from_secs(1);
+
+    let info = core.reset_and_halt(timeout).unwrap();
+    assert_eq!(info.pc, FIRMWARE_ADDRESS);
+
+    core.set_hw_breakpoint(FIRMWARE_ADDRESS + 0xe).unwrap();
+    core.run().unwrap();
+    core.wait_for_core_halted(timeout).unwrap();
+    assert!(matches!(
+        core.status().unwrap(),
+        CoreStatus::Halted(HaltReason::Breakpoint(_))
+    ));
+    let r1: u32 = core.read_core_reg(1).unwrap();
+    assert_eq!(r1, 2);
+
+    core.step().unwrap();
+    let pc: u32 = core.read_core_reg(core.program_counter()).unwrap();
+    assert_eq!(u64::from(pc), FIRMWARE_ADDRESS + 0x10);
+}
+
+#[test]
+fn firmware_stops_at_watchpoints() {
+    let mut session = attach();
+    let firmware: Vec<u8> = FIRMWARE.iter().flat_map(|i| i.to_le_bytes()).collect();
+    flash(&mut session, FIRMWARE_ADDRESS, &firmware);
+
+    let mut core = session.core(0).unwrap();
+    let timeout = Duration::from_secs(1);
+    core.reset_and_halt(timeout).unwrap();
+
+    // The firmware writes the RTT write offset with `str r1, [r2]`.
+    core.set_hw_watchpoint(0x2000_1024, 4, WatchpointKind::Write)
+        .unwrap();
+    core.run().unwrap();
+    // The simulation runs while DHCSR is read, so this lets the core halt without decoding the
+    // halt reason.
+    let dhcsr = core.read_word_32(0xE000_EDF0).unwrap();
+    assert_ne!(dhcsr & (1 << 17), 0);
+
+    // Listing the watchpoints must not hide which one halted the core.
+    assert_eq!(
+        core.hw_watchpoints().unwrap()[3],
+        Some(Watchpoint {
+            address: 0x2000_1024,
+            length: 4,
+            kind: WatchpointKind::Write,
+        })
+    );
+    assert_eq!(
+        core.status().unwrap(),
+        CoreStatus::Halted(HaltReason::Watchpoint(Some(Watchpoint {
+            address: 0x2000_1024,
+            length: 4,
+            kind: WatchpointKind::Write,
+        })))
+    );
+    let pc: u32 = core.read_core_reg(core.program_counter()).unwrap();
+    assert_eq!(u64::from(pc), FIRMWARE_ADDRESS + 0x10);
+}
+
+#[test]
+fn rtt_output_of_firmware_can_be_read() {
+    let mut session = attach();
+    let firmware: Vec<u8> = FIRMWARE.iter().flat_map(|i| i.to_le_bytes()).collect();
+    flash(&mut session, FIRMWARE_ADDRESS, &firmware);
+
+    session
+        .core(0)
+        .unwrap()
+        .reset_and_halt(Duration::from_secs(1))
+        .unwrap();
+    write_rtt_control_block(&mut session);
+
+    let mut core = session.core(0).unwrap();
+    let mut rtt = Rtt::attach(&mut core).unwrap();
+    assert_eq!(rtt.ptr(), RTT_CONTROL_BLOCK);
+
+    core.run().unwrap();
+    // The firmware runs while its status is polled.
+    assert_eq!(core.status().unwrap(), CoreStatus::Running);
+
+    let mut output = [0; 8];
+    let len = rtt.up_channels()[0].read(&mut core, &mut output).unwrap();
+    assert_eq!(&output[..len], b"Hi");
+}
+
+#[test]
+fn watchpoint_units_are_reused_or_allocated_from_the_top() {
+    let mut session = attach();
+    let mut core = session.core(0).unwrap();
+    core.halt(Duration::from_secs(1)).unwrap();
+
+    assert_eq!(core.available_watchpoint_units().unwrap(), 4);
+
+    let watchpoint = |address, length, kind| {
+        Some(Watchpoint {
+            address,
+            length,
+            kind,
+        })
+    };
+
+    core.set_hw_watchpoint(0x2000_0000, 4, WatchpointKind::Write)
+        .unwrap();
+    core.set_hw_watchpoint(0x2000_0000, 4, WatchpointKind::Read)
+        .unwrap();
+    assert_eq!(
+        core.hw_watchpoints().unwrap(),
+        [
+            None,
+            None,
+            watchpoint(0x2000_0000, 4, WatchpointKind::Read),
+            watchpoint(0x2000_0000, 4, WatchpointKind::Write),
+        ]
+    );
+
+    // A watchpoint with the same address and kind replaces the existing one.
+    core.set_hw_watchpoint(0x2000_0000, 2, WatchpointKind::Write)
+        .unwrap();
+    assert_eq!(
+        core.hw_watchpoints().unwrap()[3],
+        watchpoint(0x2000_0000, 2, WatchpointKind::Write)
+    );
+
+    core.set_hw_watchpoint(0x2000_0010, 4, WatchpointKind::Access)
+        .unwrap();
+    core.set_hw_watchpoint(0x2000_0020, 4, WatchpointKind::Access)
+        .unwrap();
+    assert!(core
+        .set_hw_watchpoint(0x2000_0030, 4, WatchpointKind::Access)
+        .is_err());
+
+    // A cleared unit is the only free one, so it is used for the next watchpoint.
+    core.clear_hw_watchpoint(0x2000_0000, WatchpointKind::Read)
+        .unwrap();
+    core.set_hw_watchpoint(0x2000_0030, 4, WatchpointKind::Access)
+        .unwrap();
+    assert_eq!(
+        core.hw_watchpoints().unwrap()[2],
+        watchpoint(0x2000_0030, 4, WatchpointKind::Access)
+    );
+
+    assert!(core
+        .clear_hw_watchpoint(0x2000_0040, WatchpointKind::Access)
+        .is_err());
+    core.clear_all_hw_watchpoints().unwrap();
+    assert_eq!(core.hw_watchpoints().unwrap(), [None; 4]);
+}
+
+#[test]
+fn debug_sequences_needing_the_debug_port_fail_cleanly() {
+    // The nRF5340 sequence looks up the access ports of both cores, and then needs raw access
+    // to them, which the simulation does not provide.
+    let target = get_target_by_name("nRF5340_xxAA").unwrap();
+    let probe =
+        Probe::from_specific_probe(Box::new(FakeProbe::with_simulated_target(&target).unwrap()));
+
+    assert!(probe.attach(target, Permissions::default()).is_err());
+}
+
+#[test]
+fn verification_applies_the_image_fixups_unless_they_are_skipped() {
+    let mut target = get_target_by_name(TARGET).unwrap();
+    target.image_fixups = vec![ImageFixup::LpcVectorChecksum];
+    let probe =
+        Probe::from_specific_probe(Box::new(FakeProbe::with_simulated_target(&target).unwrap()));
+    let mut session = probe.attach(target, Permissions::default()).unwrap();
+
+    let vector_table: Vec<u8> = (0..8u32).flat_map(|i| (i + 1).to_le_bytes()).collect();
+    let mut options = DownloadOptions::new();
+    options.skip_image_fixups = true;
+    flash_with_options(&mut session, 0, &vector_table, options);
+
+    let mut loader = session.target().flash_loader();
+    loader.add_data(0, &vector_table).unwrap();
+
+    // The flashed image has no checksum, so it only matches without the fixup.
+    let mut options = DownloadOptions::new();
+    assert!(matches!(
+        loader.verify(&mut session, &options),
+        Err(FlashError::Verify)
+    ));
+    options.skip_image_fixups = true;
+    loader.verify(&mut session, &options).unwrap();
+}
+
+#[test]
+fn locking_after_flashing_reports_unsupported_protection() {
+    let mut session = attach();
+
+    // The flashing itself succeeds, but the simulated target cannot be protected.
+    let mut options = DownloadOptions::new();
+    options.lock = true;
+    let mut loader = session.target().flash_loader();
+    loader.add_data(0x800, &[0x55; 16]).unwrap();
+    assert!(matches!(
+        loader.commit(&mut session, options),
+        Err(FlashError::Protection(_))
+    ));
+    assert_eq!(read_flash(&mut session, 0x800..0x810), [0x55; 16]);
+}
+
+/// An image of a bundle, which contains the bytes of an arbitrary file of the repository.
+fn bundle_image(base_address: u64, core: Option<&str>) -> BundleImage {
+    BundleImage {
+        path: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/README.md"),
+        format: Some(FormatKind::Bin),
+        base_address: Some(base_address),
+        core: core.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn bundles_with_overlapping_images_are_rejected() {
+    let mut session = attach();
+
+    let bundle = FlashBundle {
+        images: vec![bundle_image(0, None), bundle_image(0x400, None)],
+    };
+
+    match bundle.build_loader(&mut session) {
+        Err(FileDownloadError::BundleOverlap { address, .. }) => assert_eq!(address, 0x400),
+        Err(error) => panic!("Unexpected error: {error}"),
+        Ok(_) => panic!("Overlapping images were accepted"),
+    }
+}
+
+#[test]
+fn bundle_images_must_be_accessible_by_their_core() {
+    // The RAM is only accessible by another core.
+    let mut target = get_target_by_name(TARGET).unwrap();
+    for region in target.memory_map.iter_mut() {
+        if let MemoryRegion::Ram(region) = region {
+            region.cores = vec!["other".to_string()];
+        }
+    }
+    let mut session = attach_to(target);
+
+    let bundle = FlashBundle {
+        images: vec![bundle_image(0, Some("main"))],
+    };
+    assert!(bundle.build_loader(&mut session).is_ok());
+
+    let bundle = FlashBundle {
+        images: vec![bundle_image(0x2000_0000, Some("main"))],
+    };
+    match bundle.build_loader(&mut session) {
+        Err(FileDownloadError::BundleCoreMismatch { core, address, .. }) => {
+            assert_eq!(core, "main");
+            assert_eq!(address, 0x2000_0000);
+        }
+        Err(error) => panic!("Unexpected error: {error}"),
+        Ok(_) => panic!("An image in memory of another core was accepted"),
+    }
+}
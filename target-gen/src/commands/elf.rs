@@ -116,6 +116,7 @@ pub fn cmd_elf(
                 rtt_scan_ranges: None,
                 jtag: None,
                 default_binary_format: None,
+                image_fixups: vec![],
             }],
             flash_algorithms: vec![algorithm],
             source: TargetDescriptionSource::BuiltIn,
@@ -171,6 +171,7 @@ where
             rtt_scan_ranges: None,
             jtag: None, // TODO, parse scan chain from sdf
             default_binary_format: None,
+            image_fixups: vec![],
         });
     }
 